@@ -0,0 +1,217 @@
+//! Bulk message operations
+//!
+//! Actions applied to every message in a multi-selection. Cache writes for a
+//! bulk action run in a single transaction; server updates are batched into
+//! one UID STORE/MOVE per action (see `MailController::apply_bulk`).
+
+use crate::application::history::UndoableAction;
+use crate::common::Result;
+use crate::data::message_cache::MessageCache;
+
+/// An action applied to a set of selected messages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BulkAction {
+    MarkRead,
+    MarkUnread,
+    Star,
+    Unstar,
+    Delete,
+    Move { folder_id: i64, folder: String },
+    AddTag(String),
+    RemoveTag(String),
+}
+
+impl BulkAction {
+    /// Past-tense summary for status bar and screen-reader announcements.
+    pub fn summary(&self, count: usize) -> String {
+        let noun = if count == 1 { "message" } else { "messages" };
+        match self {
+            BulkAction::MarkRead => format!("{} {} marked as read", count, noun),
+            BulkAction::MarkUnread => format!("{} {} marked as unread", count, noun),
+            BulkAction::Star => format!("{} {} starred", count, noun),
+            BulkAction::Unstar => format!("{} {} unstarred", count, noun),
+            BulkAction::Delete => format!("{} {} deleted", count, noun),
+            BulkAction::Move { folder, .. } => format!("{} {} moved to {}", count, noun, folder),
+            BulkAction::AddTag(_) => format!("Tag added to {} {}", count, noun),
+            BulkAction::RemoveTag(_) => format!("Tag removed from {} {}", count, noun),
+        }
+    }
+
+    /// Whether the affected messages leave the current folder view.
+    pub fn removes_from_view(&self) -> bool {
+        matches!(self, BulkAction::Delete | BulkAction::Move { .. })
+    }
+
+    /// Apply the action to all cached messages in one transaction.
+    pub fn apply_to_cache(&self, cache: &MessageCache, message_ids: &[i64]) -> Result<()> {
+        cache.in_transaction(|c| {
+            for &id in message_ids {
+                self.apply_to_message(c, id)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Apply the action to all cached messages in `folder` in one
+    /// transaction, returning undo history entries for the deletes, moves
+    /// and tag removals. A move's entries don't know the messages' new UIDs
+    /// yet; see `UndoableAction::with_moved_uids`.
+    pub fn apply_with_undo(&self, cache: &MessageCache, message_ids: &[i64], folder: &str) -> Result<Vec<UndoableAction>> {
+        cache.in_transaction(|c| {
+            let mut undo = Vec::new();
+            for &id in message_ids {
+                let Some(message) = c.get_message(id)? else { continue };
+                let entry = match self {
+                    BulkAction::Delete => Some(UndoableAction::Delete {
+                        message_id: id,
+                        uid: message.uid,
+                        folder: folder.to_string(),
+                        read: message.read,
+                        starred: message.starred,
+                    }),
+                    BulkAction::Move { folder_id, folder: to_folder } if *folder_id != message.folder_id => {
+                        Some(UndoableAction::Move {
+                            message_id: id,
+                            uid: message.uid,
+                            to_uid: None,
+                            message_id_header: message.message_id.clone(),
+                            from_folder_id: message.folder_id,
+                            from_folder: folder.to_string(),
+                            to_folder_id: *folder_id,
+                            to_folder: to_folder.clone(),
+                        })
+                    }
+                    // Only messages that had the tag get it back
+                    BulkAction::RemoveTag(tag_id) if c.get_tags_for_message(id)?.iter().any(|t| &t.id == tag_id) => {
+                        Some(UndoableAction::RemoveTag { message_id: id, tag_id: tag_id.clone() })
+                    }
+                    _ => None,
+                };
+                self.apply_to_message(c, id)?;
+                undo.extend(entry);
+            }
+            Ok(undo)
+        })
+    }
+
+    fn apply_to_message(&self, c: &MessageCache, id: i64) -> Result<()> {
+        match self {
+            BulkAction::MarkRead => c.set_message_read(id, true),
+            BulkAction::MarkUnread => c.set_message_read(id, false),
+            BulkAction::Star => c.set_message_starred(id, true),
+            BulkAction::Unstar => c.set_message_starred(id, false),
+            BulkAction::Delete => c.delete_message(id),
+            BulkAction::Move { folder_id, .. } => c.move_message(id, *folder_id),
+            BulkAction::AddTag(tag_id) => c.add_tag_to_message(id, tag_id),
+            BulkAction::RemoveTag(tag_id) => c.remove_tag_from_message(id, tag_id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::history::ActionHistory;
+    use crate::common::types::MessagePriority;
+    use crate::data::message_cache::{CachedFolder, CachedMessage, Tag};
+    use std::collections::HashMap;
+    use std::env;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn test_bulk_actions_update_cache() {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_bulk_{}", nanos));
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+
+        let folder = CachedFolder {
+            id: 0, account_id: "acc".to_string(), name: "INBOX".to_string(), path: "INBOX".to_string(),
+            folder_type: "Inbox".to_string(), unread_count: 0, total_count: 0,
+        };
+        let folder_id = cache.save_folder(&folder).unwrap();
+
+        let ids: Vec<i64> = (1..=3)
+            .map(|uid| {
+                let msg = CachedMessage {
+                    id: 0, uid, folder_id,
+                    message_id: format!("bulk-{}@example.com", uid), subject: format!("Bulk {}", uid),
+                    from_addr: "a@example.com".to_string(), to_addr: "b@example.com".to_string(),
+                    cc: None, date: "2024-01-01".to_string(), body_plain: None, body_html: None,
+                    read: false, starred: false, deleted: false, priority: MessagePriority::Normal,
+                };
+                cache.save_message(&msg).unwrap()
+            })
+            .collect();
+
+        BulkAction::MarkRead.apply_to_cache(&cache, &ids).unwrap();
+        BulkAction::Star.apply_to_cache(&cache, &ids[..2]).unwrap();
+        let messages = cache.get_messages_for_folder(folder_id, "acc").unwrap();
+        assert!(messages.iter().all(|m| m.read));
+        assert_eq!(messages.iter().filter(|m| m.starred).count(), 2);
+
+        BulkAction::Delete.apply_to_cache(&cache, &ids).unwrap();
+        assert!(cache.get_messages_for_folder(folder_id, "acc").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_bulk_move_and_tag_removal_can_be_undone() {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_bulk_undo_{}", nanos));
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+
+        let folder = |name: &str| CachedFolder {
+            id: 0, account_id: "acc".to_string(), name: name.to_string(), path: name.to_string(),
+            folder_type: "Custom".to_string(), unread_count: 0, total_count: 0,
+        };
+        let inbox_id = cache.save_folder(&folder("INBOX")).unwrap();
+        let archive_id = cache.save_folder(&folder("Archive")).unwrap();
+        let ids: Vec<i64> = (1..=2)
+            .map(|uid| {
+                cache.save_message(&CachedMessage {
+                    id: 0, uid, folder_id: inbox_id,
+                    message_id: format!("undo-{}@example.com", uid), subject: format!("Undo {}", uid),
+                    from_addr: "a@example.com".to_string(), to_addr: "b@example.com".to_string(),
+                    cc: None, date: "2024-01-01".to_string(), body_plain: None, body_html: None,
+                    read: false, starred: false, deleted: false, priority: MessagePriority::Normal,
+                }).unwrap()
+            })
+            .collect();
+        cache.create_tag(&Tag {
+            id: "work".to_string(), account_id: "acc".to_string(), name: "Work".to_string(),
+            color: "#0000ff".to_string(), created_at: "2024-01-01".to_string(), shortcut: None,
+        }).unwrap();
+        cache.add_tag_to_message(ids[0], "work").unwrap();
+
+        let mut history = ActionHistory::default();
+        // Only the tagged message gets an entry
+        let removed = BulkAction::RemoveTag("work".to_string()).apply_with_undo(&cache, &ids, "INBOX").unwrap();
+        assert_eq!(removed.len(), 1);
+        removed.into_iter().for_each(|entry| history.record(entry));
+
+        let action = BulkAction::Move { folder_id: archive_id, folder: "Archive".to_string() };
+        let moved = action.apply_with_undo(&cache, &ids, "INBOX").unwrap();
+        assert_eq!(moved.len(), 2);
+        let reported = HashMap::from([(1, 40), (2, 41)]);
+        moved.into_iter().for_each(|entry| history.record(entry.with_moved_uids(&reported)));
+        assert!(cache.get_messages_for_folder(inbox_id, "acc").unwrap().is_empty());
+
+        // Undo the move, newest first, then the tag removal
+        while let Some(entry) = history.pop() {
+            if let UndoableAction::Move { uid, to_uid, ref message_id_header, .. } = entry {
+                assert_eq!(to_uid, reported.get(&uid).copied());
+                assert_eq!(*message_id_header, format!("undo-{}@example.com", uid));
+            }
+            entry.revert_in_cache(&cache).unwrap();
+        }
+        assert_eq!(cache.get_messages_for_folder(inbox_id, "acc").unwrap().len(), 2);
+        assert_eq!(cache.get_tags_for_message(ids[0]).unwrap().len(), 1);
+        assert!(cache.get_tags_for_message(ids[1]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_bulk_summary() {
+        assert_eq!(BulkAction::MarkRead.summary(3), "3 messages marked as read");
+        assert_eq!(BulkAction::Delete.summary(1), "1 message deleted");
+        assert!(BulkAction::Move { folder_id: 1, folder: "Archive".into() }.removes_from_view());
+    }
+}
@@ -0,0 +1,809 @@
+//! Calendar invitations
+//!
+//! Reads the event of a `text/calendar` part (RFC 5545) sent with a
+//! meeting invitation, and writes the `METHOD:REPLY` calendar that
+//! accepts, tentatively accepts or declines it (RFC 5546). Times given
+//! with a `TZID` are converted using the `VTIMEZONE` definitions sent in
+//! the same calendar, so no time zone database is needed.
+
+use crate::data::message_cache::CachedCalendarEvent;
+use chrono::{DateTime, Datelike, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
+use mail_parser::MimeHeaders;
+
+/// Longest content line in octets before it is folded (RFC 5545 §3.1)
+const MAX_LINE_OCTETS: usize = 75;
+
+/// An attendee's answer to an invitation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InviteResponse {
+    Accepted,
+    Tentative,
+    Declined,
+}
+
+impl InviteResponse {
+    /// All answers in button order
+    pub const ALL: [InviteResponse; 3] = [InviteResponse::Accepted, InviteResponse::Tentative, InviteResponse::Declined];
+
+    /// `PARTSTAT` parameter value
+    pub fn partstat(&self) -> &'static str {
+        match self {
+            InviteResponse::Accepted => "ACCEPTED",
+            InviteResponse::Tentative => "TENTATIVE",
+            InviteResponse::Declined => "DECLINED",
+        }
+    }
+
+    /// Parse a `PARTSTAT`; `None` for `NEEDS-ACTION` and unknown values
+    pub fn from_partstat(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|r| r.partstat().eq_ignore_ascii_case(value.trim()))
+    }
+
+    /// Word used in reply subjects and announcements
+    pub fn label(&self) -> &'static str {
+        match self {
+            InviteResponse::Accepted => "Accepted",
+            InviteResponse::Tentative => "Tentative",
+            InviteResponse::Declined => "Declined",
+        }
+    }
+}
+
+/// The organizer or an attendee of an event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attendee {
+    pub email: String,
+    pub name: Option<String>,
+    /// Their answer so far, if any
+    pub response: Option<InviteResponse>,
+}
+
+impl Attendee {
+    /// "Name <email>", or the address alone
+    pub fn label(&self) -> String {
+        match &self.name {
+            Some(name) => format!("{} <{}>", name, self.email),
+            None => self.email.clone(),
+        }
+    }
+}
+
+/// When an event starts or ends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventTime {
+    At(DateTime<Utc>),
+    AllDay(NaiveDate),
+}
+
+impl EventTime {
+    /// Local time for display, e.g. "Fri Mar 14, 2025 15:00"
+    pub fn display(&self) -> String {
+        match self {
+            EventTime::At(time) => time.with_timezone(&Local).format("%a %b %-d, %Y %H:%M").to_string(),
+            EventTime::AllDay(day) => day.format("%a %b %-d, %Y (all day)").to_string(),
+        }
+    }
+
+    /// Sortable text stored in the cache: RFC 3339 in UTC, or `YYYY-MM-DD`
+    pub fn to_cache(self) -> String {
+        match self {
+            EventTime::At(time) => time.to_rfc3339(),
+            EventTime::AllDay(day) => day.format("%Y-%m-%d").to_string(),
+        }
+    }
+
+    /// `DTSTART`/`DTEND` property in UTC, or as a date
+    fn to_property(self, name: &str) -> String {
+        match self {
+            EventTime::At(time) => format!("{}:{}", name, time.format("%Y%m%dT%H%M%SZ")),
+            EventTime::AllDay(day) => format!("{};VALUE=DATE:{}", name, day.format("%Y%m%d")),
+        }
+    }
+}
+
+/// The event of an invitation, update, cancellation or reply
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalendarInvite {
+    /// Calendar `METHOD`: `REQUEST` for an invitation, `CANCEL`, `REPLY`, ...
+    pub method: String,
+    pub uid: String,
+    /// Revision number; each update from the organizer increases it
+    pub sequence: u32,
+    pub summary: String,
+    pub location: Option<String>,
+    pub start: EventTime,
+    pub end: Option<EventTime>,
+    pub organizer: Option<Attendee>,
+    pub attendees: Vec<Attendee>,
+}
+
+impl CalendarInvite {
+    /// Find and read the first `text/calendar` (or `application/ics`)
+    /// part of a raw message
+    pub fn of_message(raw: &str) -> Option<Self> {
+        let parsed = mail_parser::MessageParser::default().parse(raw.as_bytes())?;
+        parsed.parts.iter().find_map(|part| {
+            let content_type = part.content_type()?;
+            let kind = format!("{}/{}", content_type.ctype(), content_type.subtype().unwrap_or_default());
+            if !matches!(kind.to_ascii_lowercase().as_str(), "text/calendar" | "application/ics") {
+                return None;
+            }
+            Self::parse(&String::from_utf8_lossy(part.contents()))
+        })
+    }
+
+    /// Read the first `VEVENT` of an iCalendar object. `None` without an
+    /// event, or when the event has no `UID` or start.
+    pub fn parse(text: &str) -> Option<Self> {
+        let properties: Vec<Property> = unfold(text).iter().filter_map(|line| Property::parse(line)).collect();
+
+        let mut method = String::new();
+        let mut zones: Vec<ZoneDefinition> = Vec::new();
+        let mut event: Option<Vec<&Property>> = None;
+        let mut event_done = false;
+        let mut stack: Vec<String> = Vec::new();
+        for property in &properties {
+            let component = stack.last().map(String::as_str);
+            match property.name.as_str() {
+                "BEGIN" => {
+                    let name = property.value.to_ascii_uppercase();
+                    match name.as_str() {
+                        "VTIMEZONE" => zones.push(ZoneDefinition::default()),
+                        "STANDARD" | "DAYLIGHT" if component == Some("VTIMEZONE") => {
+                            if let Some(zone) = zones.last_mut() {
+                                zone.observances.push(Observance::default());
+                            }
+                        }
+                        "VEVENT" if event.is_none() => event = Some(Vec::new()),
+                        _ => {}
+                    }
+                    stack.push(name);
+                }
+                "END" => event_done |= stack.pop().as_deref() == Some("VEVENT"),
+                "METHOD" if component == Some("VCALENDAR") => method = property.value.trim().to_ascii_uppercase(),
+                "TZID" if component == Some("VTIMEZONE") => {
+                    if let Some(zone) = zones.last_mut() {
+                        zone.tzid = property.value.clone();
+                    }
+                }
+                _ if matches!(component, Some("STANDARD" | "DAYLIGHT")) => {
+                    if let Some(observance) = zones.last_mut().and_then(|z| z.observances.last_mut()) {
+                        observance.set(property);
+                    }
+                }
+                // Properties of alarms and other components inside the event are skipped
+                _ if component == Some("VEVENT") && !event_done => {
+                    if let Some(event) = event.as_mut() {
+                        event.push(property);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let event = event?;
+        let find = |name: &str| event.iter().find(|p| p.name == name).copied();
+        let start = parse_time(find("DTSTART")?, &zones)?;
+        let end = find("DTEND")
+            .and_then(|p| parse_time(p, &zones))
+            .or_else(|| find("DURATION").and_then(|p| add_duration(start, &p.value)));
+        Some(Self {
+            method: if method.is_empty() { "PUBLISH".to_string() } else { method },
+            uid: find("UID").map(|p| p.value.trim().to_string()).filter(|uid| !uid.is_empty())?,
+            sequence: find("SEQUENCE").and_then(|p| p.value.trim().parse().ok()).unwrap_or(0),
+            summary: find("SUMMARY").map(|p| unescape(&p.value)).unwrap_or_default(),
+            location: find("LOCATION").map(|p| unescape(&p.value)).filter(|l| !l.trim().is_empty()),
+            start,
+            end,
+            organizer: find("ORGANIZER").and_then(attendee),
+            attendees: event.iter().filter(|p| p.name == "ATTENDEE").filter_map(|p| attendee(p)).collect(),
+        })
+    }
+
+    /// Whether this asks for an answer
+    pub fn is_request(&self) -> bool {
+        self.method == "REQUEST"
+    }
+
+    /// The attendee among `addresses` (the account's own), if invited
+    pub fn attendee_for(&self, addresses: &[&str]) -> Option<&Attendee> {
+        self.attendees.iter().find(|a| addresses.iter().any(|address| a.email.eq_ignore_ascii_case(address)))
+    }
+
+    /// One-line description for the preview, e.g. "Invitation: Lunch,
+    /// Fri Mar 14, 2025 12:00 – 13:00 at Cafe, from bob@example.org
+    /// (3 attendees)"
+    pub fn description(&self) -> String {
+        let heading = match self.method.as_str() {
+            "REQUEST" => "Invitation".to_string(),
+            "CANCEL" => "Cancelled".to_string(),
+            "REPLY" => match self.attendees.first().and_then(|a| a.response.map(|r| (a, r))) {
+                Some((attendee, response)) => format!("{} from {}", response.label(), attendee.label()),
+                None => "Reply".to_string(),
+            },
+            _ => "Event".to_string(),
+        };
+        let mut text = format!("{}: {}, {}", heading, self.summary, self.start.display());
+        match (self.start, self.end) {
+            (EventTime::At(start), Some(EventTime::At(end))) if start.with_timezone(&Local).date_naive()
+                == end.with_timezone(&Local).date_naive() =>
+            {
+                text.push_str(&format!(" – {}", end.with_timezone(&Local).format("%H:%M")));
+            }
+            (_, Some(end)) if end != self.start => text.push_str(&format!(" – {}", end.display())),
+            _ => {}
+        }
+        if let Some(location) = &self.location {
+            text.push_str(&format!(" at {}", location));
+        }
+        if let Some(organizer) = &self.organizer {
+            text.push_str(&format!(", from {}", organizer.label()));
+        }
+        if !self.attendees.is_empty() {
+            text.push_str(&format!(" ({} attendees)", self.attendees.len()));
+        }
+        text
+    }
+
+    /// The calendar answering this invitation for `attendee`
+    pub fn reply(&self, attendee: &Attendee, response: InviteResponse, now: DateTime<Utc>) -> String {
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_string(),
+            "VERSION:2.0".to_string(),
+            format!("PRODID:-//Wixen Mail//{}//EN", env!("CARGO_PKG_VERSION")),
+            "METHOD:REPLY".to_string(),
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}", self.uid),
+            format!("SEQUENCE:{}", self.sequence),
+            format!("DTSTAMP:{}", now.format("%Y%m%dT%H%M%SZ")),
+            self.start.to_property("DTSTART"),
+        ];
+        if let Some(end) = self.end {
+            lines.push(end.to_property("DTEND"));
+        }
+        lines.push(format!("SUMMARY:{}", escape(&self.summary)));
+        if let Some(organizer) = &self.organizer {
+            lines.push(address_property("ORGANIZER", organizer, None));
+        }
+        lines.push(address_property("ATTENDEE", attendee, Some(response)));
+        lines.push("END:VEVENT".to_string());
+        lines.push("END:VCALENDAR".to_string());
+        lines.iter().map(|line| fold(line)).collect::<Vec<_>>().join("\r\n") + "\r\n"
+    }
+
+    /// The reply email from `attendee` to the organizer: a short note with
+    /// the reply calendar as a `text/calendar; method=REPLY` alternative.
+    /// `None` if the invitation has no organizer to answer.
+    pub fn reply_mime(&self, attendee: &Attendee, response: InviteResponse, now: DateTime<Utc>) -> Option<Vec<u8>> {
+        let organizer = self.organizer.as_ref()?;
+        let boundary = format!("reply-{}", uuid::Uuid::new_v4().simple());
+        let subject = format!("{}: {}", response.label(), self.summary.replace(['\r', '\n'], " "));
+        let encode = |text: &str| {
+            if text.is_ascii() {
+                text.to_string()
+            } else {
+                use base64::Engine;
+                format!("=?UTF-8?B?{}?=", base64::engine::general_purpose::STANDARD.encode(text))
+            }
+        };
+        let from = match &attendee.name {
+            Some(name) => format!("{} <{}>", encode(name), attendee.email),
+            None => attendee.email.clone(),
+        };
+        let verb = match response {
+            InviteResponse::Accepted => "accepted",
+            InviteResponse::Tentative => "tentatively accepted",
+            InviteResponse::Declined => "declined",
+        };
+        let text = format!("{} has {} this invitation: {}", attendee.label(), verb, self.description());
+        let headers = [
+            format!("From: {}", from),
+            format!("To: {}", organizer.email),
+            format!("Subject: {}", encode(&subject)),
+            format!("Date: {}", now.to_rfc2822()),
+            format!("Message-ID: <{}@wixen-mail>", uuid::Uuid::new_v4()),
+            "MIME-Version: 1.0".to_string(),
+            format!("Content-Type: multipart/alternative; boundary=\"{}\"", boundary),
+        ];
+        Some(
+            format!(
+                "{}\r\n\r\n--{b}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n{}\r\n\r\n\
+                 --{b}\r\nContent-Type: text/calendar; charset=utf-8; method=REPLY\r\nContent-Transfer-Encoding: 8bit\r\n\r\n{}\r\n\
+                 --{b}--\r\n",
+                headers.join("\r\n"),
+                text,
+                self.reply(attendee, response, now),
+                b = boundary
+            )
+            .into_bytes(),
+        )
+    }
+
+    /// The event as stored in the cache for `account_id`
+    pub fn to_cached(&self, account_id: &str, message_id: Option<i64>, response: Option<InviteResponse>) -> CachedCalendarEvent {
+        CachedCalendarEvent {
+            id: 0,
+            account_id: account_id.to_string(),
+            uid: self.uid.clone(),
+            message_id,
+            method: self.method.clone(),
+            sequence: self.sequence as i64,
+            summary: self.summary.clone(),
+            location: self.location.clone(),
+            starts_at: self.start.to_cache(),
+            ends_at: self.end.map(|e| e.to_cache()),
+            all_day: matches!(self.start, EventTime::AllDay(_)),
+            organizer: self.organizer.as_ref().map(|o| o.email.clone()),
+            response: response.map(|r| r.partstat().to_string()),
+            updated_at: Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+/// One content line: `NAME;PARAM=value:value`
+#[derive(Debug)]
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    fn parse(line: &str) -> Option<Self> {
+        // The value starts at the first colon outside a quoted parameter
+        let mut quoted = false;
+        let colon = line.char_indices().find_map(|(i, c)| {
+            match c {
+                '"' => quoted = !quoted,
+                ':' if !quoted => return Some(i),
+                _ => {}
+            }
+            None
+        })?;
+        let mut head = split_unquoted(&line[..colon], ';').into_iter();
+        let name = head.next()?.trim().to_ascii_uppercase();
+        if name.is_empty() {
+            return None;
+        }
+        let params = head
+            .filter_map(|param| {
+                let (key, value) = param.split_once('=')?;
+                Some((key.trim().to_ascii_uppercase(), value.trim().trim_matches('"').to_string()))
+            })
+            .collect();
+        Some(Self { name, params, value: line[colon + 1..].to_string() })
+    }
+
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+}
+
+fn split_unquoted(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        if c == '"' {
+            quoted = !quoted;
+        } else if c == separator && !quoted {
+            parts.push(&text[start..i]);
+            start = i + c.len_utf8();
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+/// Join continuation lines (those starting with a space or tab)
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ if line.trim().is_empty() => {}
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+/// Split lines longer than 75 octets, without breaking a character
+fn fold(line: &str) -> String {
+    let mut out = String::with_capacity(line.len() + line.len() / MAX_LINE_OCTETS * 3);
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > MAX_LINE_OCTETS {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out
+}
+
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// `NAME;CN=...;PARTSTAT=...:mailto:address`
+fn address_property(name: &str, person: &Attendee, response: Option<InviteResponse>) -> String {
+    let mut line = name.to_string();
+    if let Some(response) = response {
+        line.push_str(&format!(";PARTSTAT={}", response.partstat()));
+    }
+    if let Some(cn) = &person.name {
+        line.push_str(&format!(";CN=\"{}\"", cn.replace('"', "'")));
+    }
+    format!("{}:mailto:{}", line, person.email)
+}
+
+fn attendee(property: &Property) -> Option<Attendee> {
+    let value = property.value.trim();
+    let email = match value.get(..7) {
+        Some(scheme) if scheme.eq_ignore_ascii_case("mailto:") => &value[7..],
+        _ => value,
+    };
+    if !email.contains('@') {
+        return None;
+    }
+    Some(Attendee {
+        email: email.to_string(),
+        name: property.param("CN").map(str::to_string).filter(|n| !n.trim().is_empty()),
+        response: property.param("PARTSTAT").and_then(InviteResponse::from_partstat),
+    })
+}
+
+/// A `DTSTART`/`DTEND`: a date, a UTC time (`Z`), a time in a `TZID`
+/// defined in the calendar, or a floating time read as local time
+fn parse_time(property: &Property, zones: &[ZoneDefinition]) -> Option<EventTime> {
+    let value = property.value.trim();
+    if property.param("VALUE").is_some_and(|v| v.eq_ignore_ascii_case("DATE")) || value.len() == 8 {
+        return NaiveDate::parse_from_str(value, "%Y%m%d").ok().map(EventTime::AllDay);
+    }
+    if let Some(utc) = value.strip_suffix(['Z', 'z']) {
+        let naive = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some(EventTime::At(Utc.from_utc_datetime(&naive)));
+    }
+    let naive = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    let utc = match property.param("TZID") {
+        Some(tzid) => match zones.iter().find(|z| z.tzid.eq_ignore_ascii_case(tzid)) {
+            Some(zone) => zone.to_utc(naive),
+            None if is_utc_name(tzid) => Utc.from_utc_datetime(&naive),
+            None => local_to_utc(naive)?,
+        },
+        None => local_to_utc(naive)?,
+    };
+    Some(EventTime::At(utc))
+}
+
+fn is_utc_name(tzid: &str) -> bool {
+    let name = tzid.trim().trim_start_matches('/').to_ascii_uppercase();
+    matches!(name.as_str(), "UTC" | "GMT" | "Z" | "ETC/UTC" | "ETC/GMT" | "UTC+00:00" | "(UTC) COORDINATED UNIVERSAL TIME")
+}
+
+fn local_to_utc(naive: NaiveDateTime) -> Option<DateTime<Utc>> {
+    Local.from_local_datetime(&naive).earliest().map(|t| t.with_timezone(&Utc))
+}
+
+/// `start` plus an ISO 8601 duration such as `PT1H30M` or `P1D`
+fn add_duration(start: EventTime, value: &str) -> Option<EventTime> {
+    let value = value.trim();
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    for c in value.strip_prefix('P')?.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => {}
+            'W' | 'D' | 'H' | 'M' | 'S' => {
+                let n: i64 = number.parse().ok()?;
+                number.clear();
+                total += match c {
+                    'W' => Duration::weeks(n),
+                    'D' => Duration::days(n),
+                    'H' => Duration::hours(n),
+                    'M' => Duration::minutes(n),
+                    _ => Duration::seconds(n),
+                };
+            }
+            _ => return None,
+        }
+    }
+    let total = if negative { -total } else { total };
+    Some(match start {
+        EventTime::At(time) => EventTime::At(time + total),
+        EventTime::AllDay(day) => EventTime::AllDay(day + total),
+    })
+}
+
+/// A `VTIMEZONE`: the offsets a named zone switches between, and when
+#[derive(Debug, Default)]
+struct ZoneDefinition {
+    tzid: String,
+    observances: Vec<Observance>,
+}
+
+impl ZoneDefinition {
+    /// Convert a wall-clock time in this zone to UTC using the observance
+    /// (standard or daylight time) most recently in effect
+    fn to_utc(&self, local: NaiveDateTime) -> DateTime<Utc> {
+        let offset = self
+            .observances
+            .iter()
+            .filter_map(|o| Some((o.last_onset(local)?, o.offset_to?)))
+            .max_by_key(|(onset, _)| *onset)
+            .map(|(_, offset)| offset)
+            // Before the zone's first change: the offset it changed from
+            .or_else(|| self.observances.iter().filter(|o| o.start.is_some()).min_by_key(|o| o.start)?.offset_from)
+            .unwrap_or_else(|| FixedOffset::east_opt(0).expect("zero offset"));
+        Utc.from_utc_datetime(&(local - Duration::seconds(offset.local_minus_utc() as i64)))
+    }
+}
+
+/// A `STANDARD` or `DAYLIGHT` block of a `VTIMEZONE`
+#[derive(Debug, Default)]
+struct Observance {
+    /// First onset, in the local time before the change
+    start: Option<NaiveDateTime>,
+    offset_from: Option<FixedOffset>,
+    offset_to: Option<FixedOffset>,
+    rule: Option<YearlyRule>,
+}
+
+impl Observance {
+    fn set(&mut self, property: &Property) {
+        let value = property.value.trim();
+        match property.name.as_str() {
+            "DTSTART" => self.start = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok(),
+            "TZOFFSETFROM" => self.offset_from = parse_offset(value),
+            "TZOFFSETTO" => self.offset_to = parse_offset(value),
+            "RRULE" => self.rule = YearlyRule::parse(value),
+            _ => {}
+        }
+    }
+
+    /// The latest time this observance began at or before `local`
+    fn last_onset(&self, local: NaiveDateTime) -> Option<NaiveDateTime> {
+        let start = self.start?;
+        let Some(rule) = &self.rule else {
+            return (start <= local).then_some(start);
+        };
+        [local.year(), local.year() - 1].into_iter().find_map(|year| {
+            let onset = rule.date_in(year)?.and_time(start.time());
+            let in_force = onset >= start && onset <= local && rule.until.is_none_or(|until| onset <= until);
+            in_force.then_some(onset)
+        })
+    }
+}
+
+/// The yearly recurrence of a time zone change, e.g.
+/// `FREQ=YEARLY;BYMONTH=3;BYDAY=2SU` for the second Sunday of March
+#[derive(Debug)]
+struct YearlyRule {
+    month: u32,
+    weekday: Weekday,
+    /// 1 for the first such weekday of the month, -1 for the last, ...
+    ordinal: i32,
+    /// Days of the month the weekday must fall on (older zone definitions)
+    month_days: Vec<u32>,
+    until: Option<NaiveDateTime>,
+}
+
+impl YearlyRule {
+    fn parse(rule: &str) -> Option<Self> {
+        let part = |name: &str| {
+            rule.split(';').find_map(|p| p.split_once('=').filter(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v))
+        };
+        if !part("FREQ")?.eq_ignore_ascii_case("YEARLY") {
+            return None;
+        }
+        let by_day = part("BYDAY")?.split(',').next()?.trim();
+        let split = by_day.len().checked_sub(2)?;
+        let weekday = match by_day.get(split..)?.to_ascii_uppercase().as_str() {
+            "MO" => Weekday::Mon,
+            "TU" => Weekday::Tue,
+            "WE" => Weekday::Wed,
+            "TH" => Weekday::Thu,
+            "FR" => Weekday::Fri,
+            "SA" => Weekday::Sat,
+            "SU" => Weekday::Sun,
+            _ => return None,
+        };
+        let ordinal = match &by_day[..split] {
+            "" => 0,
+            n => n.trim_start_matches('+').parse().ok()?,
+        };
+        let until = part("UNTIL").and_then(|u| {
+            let u = u.trim().trim_end_matches(['Z', 'z']);
+            NaiveDateTime::parse_from_str(u, "%Y%m%dT%H%M%S")
+                .ok()
+                .or_else(|| NaiveDate::parse_from_str(u, "%Y%m%d").ok()?.and_hms_opt(23, 59, 59))
+        });
+        Some(Self {
+            month: part("BYMONTH")?.trim().parse().ok()?,
+            weekday,
+            ordinal: part("BYSETPOS").and_then(|p| p.trim().parse().ok()).unwrap_or(ordinal),
+            month_days: part("BYMONTHDAY")
+                .map(|days| days.split(',').filter_map(|d| d.trim().parse().ok()).collect())
+                .unwrap_or_default(),
+            until,
+        })
+    }
+
+    fn date_in(&self, year: i32) -> Option<NaiveDate> {
+        let first = NaiveDate::from_ymd_opt(year, self.month, 1)?;
+        let days: Vec<NaiveDate> = first
+            .iter_days()
+            .take_while(|d| d.month() == self.month)
+            .filter(|d| d.weekday() == self.weekday)
+            .filter(|d| self.month_days.is_empty() || self.month_days.contains(&d.day()))
+            .collect();
+        match self.ordinal {
+            0 => days.first().copied(),
+            n if n > 0 => days.get(n as usize - 1).copied(),
+            n => days.len().checked_sub(n.unsigned_abs() as usize).map(|i| days[i]),
+        }
+    }
+}
+
+/// `+0100`, `-0500` or `+053000`
+fn parse_offset(value: &str) -> Option<FixedOffset> {
+    let (sign, digits) = match value.as_bytes().first()? {
+        b'+' => (1, &value[1..]),
+        b'-' => (-1, &value[1..]),
+        _ => return None,
+    };
+    let number = |range: std::ops::Range<usize>| digits.get(range).and_then(|d| d.parse::<i32>().ok());
+    let seconds = number(0..2)? * 3600 + number(2..4)? * 60 + number(4..6).unwrap_or(0);
+    FixedOffset::east_opt(sign * seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INVITE: &str = "BEGIN:VCALENDAR\r\n\
+        PRODID:-//Example//Calendar//EN\r\n\
+        VERSION:2.0\r\n\
+        METHOD:REQUEST\r\n\
+        BEGIN:VTIMEZONE\r\n\
+        TZID:America/New_York\r\n\
+        BEGIN:DAYLIGHT\r\n\
+        TZOFFSETFROM:-0500\r\n\
+        TZOFFSETTO:-0400\r\n\
+        DTSTART:20070311T020000\r\n\
+        RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=2SU\r\n\
+        END:DAYLIGHT\r\n\
+        BEGIN:STANDARD\r\n\
+        TZOFFSETFROM:-0400\r\n\
+        TZOFFSETTO:-0500\r\n\
+        DTSTART:20071104T020000\r\n\
+        RRULE:FREQ=YEARLY;BYMONTH=11;BYDAY=1SU\r\n\
+        END:STANDARD\r\n\
+        END:VTIMEZONE\r\n\
+        BEGIN:VEVENT\r\n\
+        UID:lunch-42@example.org\r\n\
+        SEQUENCE:2\r\n\
+        SUMMARY:Lunch\\, then planning\r\n\
+        LOCATION:Cafe\\; upstairs\r\n\
+        DTSTART;TZID=America/New_York:20250314T120000\r\n\
+        DURATION:PT1H30M\r\n\
+        ORGANIZER;CN=\"Bob: Organizer\":mailto:bob@example.org\r\n\
+        ATTENDEE;CN=Alice;PARTSTAT=NEEDS-ACTION;RSVP=TRUE:MAILTO:alice@example.com\r\n\
+        ATTENDEE;PARTSTAT=ACCEPTED;CN=Carol:mailto:carol@example\r\n .net\r\n\
+        BEGIN:VALARM\r\n\
+        TRIGGER:-PT15M\r\n\
+        DESCRIPTION:Reminder\r\n\
+        END:VALARM\r\n\
+        END:VEVENT\r\n\
+        END:VCALENDAR\r\n";
+
+    fn utc(y: i32, m: u32, d: u32, h: u32, min: u32) -> EventTime {
+        EventTime::At(Utc.with_ymd_and_hms(y, m, d, h, min, 0).unwrap())
+    }
+
+    #[test]
+    fn test_parse_invite() {
+        let invite = CalendarInvite::parse(INVITE).unwrap();
+        assert!(invite.is_request());
+        assert_eq!((invite.uid.as_str(), invite.sequence), ("lunch-42@example.org", 2));
+        assert_eq!(invite.summary, "Lunch, then planning");
+        assert_eq!(invite.location.as_deref(), Some("Cafe; upstairs"));
+        // 14 March 2025 is after the second Sunday of March, so daylight time (UTC-4)
+        assert_eq!(invite.start, utc(2025, 3, 14, 16, 0));
+        assert_eq!(invite.end, Some(utc(2025, 3, 14, 17, 30)));
+
+        let organizer = invite.organizer.as_ref().unwrap();
+        assert_eq!((organizer.email.as_str(), organizer.name.as_deref()), ("bob@example.org", Some("Bob: Organizer")));
+        assert_eq!(invite.attendees.len(), 2);
+        assert_eq!(invite.attendees[0].response, None);
+        assert_eq!(invite.attendees[1].email, "carol@example.net");
+        assert_eq!(invite.attendees[1].response, Some(InviteResponse::Accepted));
+        assert_eq!(invite.attendee_for(&["ALICE@example.com"]).unwrap().name.as_deref(), Some("Alice"));
+        assert!(invite.description().starts_with("Invitation: Lunch, then planning, "));
+        assert!(invite.description().ends_with(" at Cafe; upstairs, from Bob: Organizer <bob@example.org> (2 attendees)"));
+    }
+
+    #[test]
+    fn test_time_zones() {
+        let at = |dtstart: &str| {
+            CalendarInvite::parse(&INVITE.replace("DTSTART;TZID=America/New_York:20250314T120000", dtstart))
+                .unwrap()
+                .start
+        };
+        // A week earlier is still standard time (UTC-5)
+        assert_eq!(at("DTSTART;TZID=America/New_York:20250307T120000"), utc(2025, 3, 7, 17, 0));
+        // Early January is covered by the previous November's change
+        assert_eq!(at("DTSTART;TZID=\"America/New_York\":20250102T090000"), utc(2025, 1, 2, 14, 0));
+        assert_eq!(at("DTSTART:20250314T120000Z"), utc(2025, 3, 14, 12, 0));
+        assert_eq!(at("DTSTART;TZID=Etc/UTC:20250314T120000"), utc(2025, 3, 14, 12, 0));
+        assert_eq!(
+            at("DTSTART;VALUE=DATE:20250314"),
+            EventTime::AllDay(NaiveDate::from_ymd_opt(2025, 3, 14).unwrap())
+        );
+
+        // Last Sunday of March, as used in Europe
+        let rule = YearlyRule::parse("FREQ=YEARLY;BYDAY=-1SU;BYMONTH=3").unwrap();
+        assert_eq!(rule.date_in(2025), NaiveDate::from_ymd_opt(2025, 3, 30));
+        assert_eq!(parse_offset("+0530"), FixedOffset::east_opt(5 * 3600 + 30 * 60));
+    }
+
+    #[test]
+    fn test_reply() {
+        let invite = CalendarInvite::parse(INVITE).unwrap();
+        let alice = invite.attendee_for(&["alice@example.com"]).unwrap().clone();
+        let now = Utc.with_ymd_and_hms(2025, 3, 10, 9, 0, 0).unwrap();
+        let reply = invite.reply(&alice, InviteResponse::Tentative, now);
+        assert!(reply.contains("\r\nMETHOD:REPLY\r\n"));
+        assert!(reply.contains("\r\nUID:lunch-42@example.org\r\nSEQUENCE:2\r\nDTSTAMP:20250310T090000Z\r\n"));
+        assert!(reply.contains("\r\nDTSTART:20250314T160000Z\r\nDTEND:20250314T173000Z\r\n"));
+        assert!(reply.contains("\r\nSUMMARY:Lunch\\, then planning\r\n"));
+        assert!(reply.contains("\r\nATTENDEE;PARTSTAT=TENTATIVE;CN=\"Alice\":mailto:alice@example.com\r\n"));
+        assert!(reply.lines().all(|line| line.len() <= MAX_LINE_OCTETS));
+
+        // What we write, we can read back
+        let parsed = CalendarInvite::parse(&reply).unwrap();
+        assert_eq!((parsed.start, parsed.end), (invite.start, invite.end));
+        assert_eq!(parsed.organizer.unwrap().name.as_deref(), Some("Bob: Organizer"));
+        assert_eq!(parsed.attendees[0].response, Some(InviteResponse::Tentative));
+
+        let mime = String::from_utf8(invite.reply_mime(&alice, InviteResponse::Accepted, now).unwrap()).unwrap();
+        assert!(mime.contains("To: bob@example.org\r\n"));
+        assert!(mime.contains("Subject: Accepted: Lunch, then planning\r\n"));
+        let sent = CalendarInvite::of_message(&mime).unwrap();
+        assert_eq!(sent.method, "REPLY");
+        assert_eq!(sent.attendees[0].response, Some(InviteResponse::Accepted));
+        assert!(sent.description().starts_with("Accepted from Alice <alice@example.com>: Lunch"));
+
+        let long = fold(&format!("SUMMARY:{}", "é".repeat(60)));
+        assert!(long.split("\r\n").all(|line| line.len() <= MAX_LINE_OCTETS));
+        assert_eq!(unfold(&long)[0], format!("SUMMARY:{}", "é".repeat(60)));
+    }
+}
@@ -2,7 +2,13 @@
 //!
 //! Handles creation and editing of email messages.
 
-use crate::common::{types::EmailAddress, Result};
+use crate::common::{
+    types::{validate_email, EmailAddress},
+    Result,
+};
+use crate::data::message_cache::{parse_message_date, CachedDraft, MessageCache};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashSet;
 
 /// Draft message
 #[derive(Debug, Clone)]
@@ -35,6 +41,311 @@ impl Default for Draft {
     }
 }
 
+/// Recipients for a reply-all as `(to, cc)` header values.
+///
+/// To gets the original Reply-To (or From when there is none) and Cc the
+/// original To and Cc recipients. Addresses are compared case-insensitively,
+/// de-duplicated, and the user's own addresses are left out.
+pub fn reply_all_recipients(
+    from: &str,
+    reply_to: Option<&str>,
+    to: &str,
+    cc: &str,
+    own_addresses: &[String],
+) -> (String, String) {
+    let mut seen: HashSet<String> = own_addresses.iter().map(|a| a.to_lowercase()).collect();
+    let mut take = |list: &str| -> Vec<String> {
+        EmailAddress::parse_list(list)
+            .into_iter()
+            .filter(|a| seen.insert(a.address.to_lowercase()))
+            .map(|a| a.to_string())
+            .collect()
+    };
+    let reply_to = reply_to.filter(|r| !r.trim().is_empty()).unwrap_or(from);
+    let mut primary = take(reply_to);
+    let mut copies = take(to);
+    copies.extend(take(cc));
+    // Replying to your own message goes back to its recipients
+    if primary.is_empty() && !copies.is_empty() {
+        primary.push(copies.remove(0));
+    }
+    (primary.join(", "), copies.join(", "))
+}
+
+/// The recipient still being typed: the text after the last `,` or `;`.
+pub fn partial_recipient(field: &str) -> &str {
+    field.rsplit([',', ';']).next().unwrap_or("").trim()
+}
+
+/// Replace the recipient being typed with `completion`, ending with a
+/// separator so the next address can follow.
+pub fn complete_recipient(field: &str, completion: &str) -> String {
+    match field.rfind([',', ';']) {
+        Some(i) => format!("{} {}, ", &field[..=i], completion),
+        None => format!("{}, ", completion),
+    }
+}
+
+/// How a recipient address looks before sending
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecipientStatus {
+    Valid,
+    /// Deliverable in principle, but more likely a typo: a domain without
+    /// a dot, an address literal, a quoted local part or non-ASCII text
+    Unusual,
+    /// Fails [`validate_email`]; sending is blocked until it's fixed
+    Invalid,
+}
+
+impl RecipientStatus {
+    pub fn of(address: &str) -> Self {
+        if !validate_email(address) {
+            return RecipientStatus::Invalid;
+        }
+        let (local, domain) = address.rsplit_once('@').unwrap_or_default();
+        if !domain.contains('.') || domain.starts_with('[') || local.starts_with('"') || !address.is_ascii() {
+            RecipientStatus::Unusual
+        } else {
+            RecipientStatus::Valid
+        }
+    }
+}
+
+/// Each recipient in a To, Cc or Bcc field with how its address looks
+pub fn check_recipients(field: &str) -> Vec<(EmailAddress, RecipientStatus)> {
+    EmailAddress::parse_list(field)
+        .into_iter()
+        .map(|a| {
+            let status = RecipientStatus::of(&a.address);
+            (a, status)
+        })
+        .collect()
+}
+
+/// The field with its `index`th recipient taken out
+pub fn remove_recipient(field: &str, index: usize) -> String {
+    let kept: Vec<String> = EmailAddress::parse_list(field)
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| *i != index)
+        .map(|(_, a)| match &a.name {
+            // Quote names that would otherwise split the list
+            Some(name) if name.contains([',', ';']) => format!("\"{}\" <{}>", name, a.address),
+            _ => a.to_string(),
+        })
+        .collect();
+    kept.join(", ")
+}
+
+/// Number of addresses across the To, Cc and Bcc fields
+pub fn recipient_count(to: &str, cc: &str, bcc: &str) -> usize {
+    [to, cc, bcc].iter().map(|field| EmailAddress::parse_list(field).len()).sum()
+}
+
+/// Whether the text the user wrote mentions an attachment. `keywords` is
+/// the active locale's `|`-separated list, e.g. `attached|attachment`, and
+/// matches at the start of a word, ignoring case. Quoted (`>`) lines and a
+/// forwarded message don't count, as they are someone else's words.
+pub fn mentions_attachment(body: &str, keywords: &str) -> bool {
+    let keywords: Vec<String> = keywords
+        .split('|')
+        .map(|k| k.trim().to_lowercase())
+        .filter(|k| !k.is_empty())
+        .collect();
+    body.lines()
+        .take_while(|line| !line.trim_start().starts_with("---------- Forwarded message"))
+        .filter(|line| !line.trim_start().starts_with('>'))
+        .any(|line| {
+            let line = line.to_lowercase();
+            keywords.iter().any(|keyword| {
+                line.match_indices(keyword.as_str())
+                    .any(|(i, _)| !line[..i].chars().next_back().is_some_and(char::is_alphanumeric))
+            })
+        })
+}
+
+/// Attribution line above a quoted reply, e.g.
+/// "On Mon, 1 Jan 2024 at 10:00, Alice wrote:".
+pub fn attribution(date: &str, sender: &str) -> String {
+    let name = EmailAddress::parse_list(sender)
+        .into_iter()
+        .next()
+        .map(|a| a.name.unwrap_or(a.address))
+        .unwrap_or_else(|| sender.trim().to_string());
+    match parse_message_date(date) {
+        Some(d) => format!("On {}, {} wrote:", d.format("%a, %-d %b %Y at %H:%M"), name),
+        None if !date.trim().is_empty() => format!("On {}, {} wrote:", date.trim(), name),
+        None => format!("{} wrote:", name),
+    }
+}
+
+/// Quote an original message under an attribution line: `> ` prefixed
+/// lines for plain text, a `<blockquote>` for HTML bodies.
+pub fn quote_original(body: &str, date: &str, sender: &str, html: bool) -> String {
+    let attribution = attribution(date, sender);
+    if html {
+        return format!(
+            "<p>{}</p>\n<blockquote type=\"cite\">\n{}\n</blockquote>",
+            html_escape(&attribution),
+            body
+        );
+    }
+    let quoted: Vec<String> = body
+        .trim_end()
+        .lines()
+        .map(|line| if line.is_empty() || line.starts_with('>') { format!(">{}", line) } else { format!("> {}", line) })
+        .collect();
+    format!("{}\n{}", attribution, quoted.join("\n"))
+}
+
+/// Initial reply body: blank lines for the cursor, then the signature,
+/// then the quoted original.
+pub fn reply_body(quote: &str, signature: Option<&str>) -> String {
+    match signature.map(str::trim_end).filter(|s| !s.is_empty()) {
+        Some(signature) => format!("\n\n-- \n{}\n\n{}", signature, quote),
+        None => format!("\n\n{}", quote),
+    }
+}
+
+/// Values for the `{{placeholder}}` fields of a message template
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TemplateValues {
+    /// First recipient's name, or their address when the name is unknown
+    pub recipient_name: String,
+    pub recipient_email: String,
+    pub sender_email: String,
+    /// Today's date, as shown to the user
+    pub date: String,
+}
+
+impl TemplateValues {
+    /// Value of a placeholder, or `None` for unknown names
+    fn get(&self, placeholder: &str) -> Option<&str> {
+        match placeholder {
+            "recipient_name" => Some(&self.recipient_name),
+            "recipient_first_name" => Some(self.recipient_name.split_whitespace().next().unwrap_or_default()),
+            "recipient_email" => Some(&self.recipient_email),
+            "sender_email" => Some(&self.sender_email),
+            "date" => Some(&self.date),
+            _ => None,
+        }
+    }
+}
+
+/// Replace `{{placeholder}}` fields in template text. Unknown placeholders
+/// are left as typed so mistakes show up in the message.
+pub fn fill_template(text: &str, values: &TemplateValues) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else { break };
+        let end = start + 2 + len + 2;
+        out.push_str(&rest[..start]);
+        match values.get(rest[start + 2..end - 2].trim()) {
+            Some(value) => out.push_str(value),
+            None => out.push_str(&rest[start..end]),
+        }
+        rest = &rest[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// To, Cc, Bcc, subject and body of a draft
+type DraftContent = (String, Option<String>, Option<String>, String, String);
+
+/// The draft behind an open composer. The message is saved as the user
+/// writes, marked open so a crash leaves it for recovery, and removed when
+/// the composer closes unless the user saved it on purpose.
+#[derive(Debug, Clone)]
+pub struct DraftSession {
+    draft_id: String,
+    interval: Option<Duration>,
+    last_saved_at: DateTime<Utc>,
+    last_saved: Option<DraftContent>,
+    /// Saved on purpose, or a draft before the composer opened it
+    keep: bool,
+}
+
+impl DraftSession {
+    /// A composer opening at `now` on draft `draft_id`, which `existing`
+    /// says was already a draft. It autosaves every `autosave_seconds`;
+    /// 0 turns autosave off.
+    pub fn new(draft_id: &str, existing: bool, autosave_seconds: u32, now: DateTime<Utc>) -> Self {
+        Self {
+            draft_id: draft_id.to_string(),
+            interval: (autosave_seconds > 0).then(|| Duration::seconds(autosave_seconds as i64)),
+            last_saved_at: now,
+            last_saved: None,
+            keep: existing,
+        }
+    }
+
+    pub fn draft_id(&self) -> &str {
+        &self.draft_id
+    }
+
+    /// Whether an autosave is due: autosave is on and a full interval has
+    /// passed since the composer opened or last saved
+    pub fn should_auto_save(&self, now: DateTime<Utc>) -> bool {
+        self.interval.is_some_and(|interval| now - self.last_saved_at >= interval)
+    }
+
+    /// Save `draft` if an autosave is due and it changed since the last
+    /// save. Returns whether it was saved.
+    pub fn autosave(&mut self, cache: &MessageCache, draft: &CachedDraft, now: DateTime<Utc>) -> Result<bool> {
+        if !self.should_auto_save(now) {
+            return Ok(false);
+        }
+        self.save_if_changed(cache, draft, now)
+    }
+
+    /// Save `draft` now if it changed since the last save, marked open
+    pub fn save_if_changed(&mut self, cache: &MessageCache, draft: &CachedDraft, now: DateTime<Utc>) -> Result<bool> {
+        let content = Some(Self::content(draft));
+        self.last_saved_at = now;
+        if content == self.last_saved {
+            return Ok(false);
+        }
+        cache.save_draft(&CachedDraft { id: self.draft_id.clone(), ..draft.clone() })?;
+        cache.set_draft_open(&self.draft_id, true)?;
+        self.last_saved = content;
+        Ok(true)
+    }
+
+    /// Put an earlier version back, saving what's being written first so
+    /// it becomes a version too
+    pub fn restore(&mut self, cache: &MessageCache, current: &CachedDraft, revision_id: i64, now: DateTime<Utc>) -> Result<CachedDraft> {
+        self.save_if_changed(cache, current, now)?;
+        let restored = cache.restore_draft_revision(revision_id)?;
+        self.last_saved = Some(Self::content(&restored));
+        Ok(restored)
+    }
+
+    /// The user saved the draft on purpose, so it stays when the composer closes
+    pub fn keep(&mut self) {
+        self.keep = true;
+    }
+
+    /// The composer closed. A sent message's draft goes; so does one the
+    /// user never saved on purpose. A kept draft is no longer open.
+    pub fn close(&mut self, cache: &MessageCache, sent: bool) -> Result<()> {
+        if sent || !self.keep {
+            cache.delete_draft(&self.draft_id)
+        } else {
+            cache.set_draft_open(&self.draft_id, false)
+        }
+    }
+
+    fn content(draft: &CachedDraft) -> DraftContent {
+        (draft.to_addr.clone(), draft.cc.clone(), draft.bcc.clone(), draft.subject.clone(), draft.body.clone())
+    }
+}
+
 /// Manages message composition
 #[derive(Default)]
 pub struct CompositionManager {
@@ -80,4 +391,182 @@ mod tests {
         assert_eq!(manager.get_drafts().len(), 1);
         assert_eq!(manager.get_drafts()[0].subject, "Test");
     }
+
+    #[test]
+    fn test_quote_original() {
+        let quote = quote_original(
+            "Hi Bob,\n\n> earlier\nThanks\n",
+            "Mon, 1 Jan 2024 10:00:00 +0000",
+            "Alice Smith <alice@example.com>",
+            false,
+        );
+        assert_eq!(
+            quote,
+            "On Mon, 1 Jan 2024 at 10:00, Alice Smith wrote:\n> Hi Bob,\n>\n>> earlier\n> Thanks"
+        );
+
+        let html = quote_original("<p>Hi</p>", "", "bob@example.com", true);
+        assert_eq!(html, "<p>bob@example.com wrote:</p>\n<blockquote type=\"cite\">\n<p>Hi</p>\n</blockquote>");
+
+        // Signature sits between the cursor and the quote
+        assert_eq!(reply_body("> q", Some("Bob\n")), "\n\n-- \nBob\n\n> q");
+        assert_eq!(reply_body("> q", None), "\n\n> q");
+    }
+
+    #[test]
+    fn test_reply_all_recipients() {
+        let own = vec!["me@example.com".to_string()];
+        let (to, cc) = reply_all_recipients(
+            "Alice <alice@example.com>",
+            None,
+            "Me <ME@example.com>, bob@example.com",
+            "carol@example.com, Bob <BOB@example.com>, alice@example.com",
+            &own,
+        );
+        assert_eq!(to, "Alice <alice@example.com>");
+        assert_eq!(cc, "bob@example.com, carol@example.com");
+
+        let (to, cc) = reply_all_recipients("alice@example.com", Some("list@example.com"), "me@example.com", "", &own);
+        assert_eq!(to, "list@example.com");
+        assert_eq!(cc, "");
+
+        // Reply-all on a message you sent
+        let (to, cc) = reply_all_recipients("me@example.com", None, "bob@example.com, carol@example.com", "", &own);
+        assert_eq!(to, "bob@example.com");
+        assert_eq!(cc, "carol@example.com");
+    }
+
+    #[test]
+    fn test_fill_template() {
+        let values = TemplateValues {
+            recipient_name: "Ana Lima".to_string(),
+            recipient_email: "ana@example.com".to_string(),
+            sender_email: "me@example.com".to_string(),
+            date: "14 March 2025".to_string(),
+        };
+        assert_eq!(
+            fill_template("Hi {{recipient_first_name}}, re {{ recipient_email }} on {{date}}", &values),
+            "Hi Ana, re ana@example.com on 14 March 2025"
+        );
+        assert_eq!(fill_template("{{recipient_name}} / {{sender_email}}", &values), "Ana Lima / me@example.com");
+        assert_eq!(fill_template("{{unknown}} and {{unclosed", &values), "{{unknown}} and {{unclosed");
+        assert_eq!(fill_template("Dear {{recipient_first_name}}", &TemplateValues::default()), "Dear ");
+    }
+
+    #[test]
+    fn test_complete_recipient() {
+        assert_eq!(partial_recipient("  an"), "an");
+        assert_eq!(partial_recipient("bob@example.com; An"), "An");
+        assert_eq!(partial_recipient("bob@example.com, "), "");
+        assert_eq!(complete_recipient("an", "Ana <ana@example.com>"), "Ana <ana@example.com>, ");
+        assert_eq!(
+            complete_recipient("bob@example.com,an", "a@example.com, b@example.com"),
+            "bob@example.com, a@example.com, b@example.com, "
+        );
+    }
+
+    fn session_cache(name: &str) -> MessageCache {
+        let dir = std::env::temp_dir().join(format!("wixen_mail_test_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        MessageCache::new(dir, None).unwrap()
+    }
+
+    fn typed(body: &str) -> CachedDraft {
+        CachedDraft {
+            id: String::new(),
+            account_id: "acc".to_string(),
+            to_addr: "bob@example.com".to_string(),
+            cc: None,
+            bcc: None,
+            subject: "Plans".to_string(),
+            body: body.to_string(),
+            created_at: Utc::now().to_rfc3339(),
+            updated_at: Utc::now().to_rfc3339(),
+            server_uid: None,
+        }
+    }
+
+    #[test]
+    fn test_draft_session_autosave_interval() {
+        let cache = session_cache("draft_session_interval");
+        let opened = Utc::now();
+        let mut session = DraftSession::new("d1", false, 30, opened);
+        assert!(!session.should_auto_save(opened + Duration::seconds(29)));
+        assert!(!session.autosave(&cache, &typed("Hi"), opened + Duration::seconds(29)).unwrap());
+        assert!(session.autosave(&cache, &typed("Hi"), opened + Duration::seconds(30)).unwrap());
+        // Unchanged text isn't saved again
+        assert!(!session.autosave(&cache, &typed("Hi"), opened + Duration::seconds(60)).unwrap());
+        assert!(session.autosave(&cache, &typed("Hi Bob"), opened + Duration::seconds(90)).unwrap());
+        assert_eq!(cache.load_draft("d1").unwrap().unwrap().body, "Hi Bob");
+
+        let off = DraftSession::new("d2", false, 0, opened);
+        assert!(!off.should_auto_save(opened + Duration::days(1)));
+    }
+
+    #[test]
+    fn test_draft_session_discard_leaves_no_draft() {
+        let cache = session_cache("draft_session_discard");
+        let opened = Utc::now();
+        let mut session = DraftSession::new("d1", false, 60, opened);
+        session.autosave(&cache, &typed("Half a thought"), opened + Duration::seconds(60)).unwrap();
+        session.autosave(&cache, &typed("Half a thought, more"), opened + Duration::seconds(120)).unwrap();
+        assert!(cache.load_draft("d1").unwrap().is_some());
+
+        session.close(&cache, false).unwrap();
+        assert!(cache.load_draft("d1").unwrap().is_none());
+        assert!(cache.list_draft_revisions("d1").unwrap().is_empty());
+        assert!(cache.take_interrupted_drafts().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_draft_session_survives_closing_the_app() {
+        let cache = session_cache("draft_session_crash");
+        let opened = Utc::now();
+        let mut session = DraftSession::new("d1", false, 60, opened);
+        session.autosave(&cache, &typed("Dear Bob"), opened + Duration::seconds(60)).unwrap();
+        // The app stops with the composer still open: close is never called
+        drop(session);
+
+        let interrupted = cache.take_interrupted_drafts().unwrap();
+        assert_eq!(interrupted.len(), 1);
+        assert_eq!(interrupted[0].body, "Dear Bob");
+
+        // Recovered, then closed without saving: it was a draft already, so it stays
+        let mut session = DraftSession::new("d1", true, 60, opened);
+        session.close(&cache, false).unwrap();
+        assert!(cache.load_draft("d1").unwrap().is_some());
+
+        // Saved on purpose, then sent
+        let mut session = DraftSession::new("d1", true, 60, opened);
+        session.keep();
+        session.close(&cache, true).unwrap();
+        assert!(cache.load_draft("d1").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_send_warnings() {
+        assert_eq!(recipient_count("a@example.com, \"B, Bee\" <b@example.com>", "c@example.com", ""), 3);
+
+        let keywords = "attached|attachment|enclosed";
+        assert!(mentions_attachment("Hi,\n\nThe report is Attached.", keywords));
+        assert!(mentions_attachment("See the attachments", keywords));
+        assert!(!mentions_attachment("We are unattached to the outcome", keywords));
+        assert!(!mentions_attachment("Sounds good\n\n> I've attached the plan", keywords));
+        assert!(!mentions_attachment("FYI\n\n---------- Forwarded message ----------\nattached: plan.pdf", keywords));
+        assert!(mentions_attachment("Ci-joint le fichier", "ci-joint|pièce jointe"));
+        assert!(!mentions_attachment("attached", ""));
+    }
+
+    #[test]
+    fn test_check_recipients() {
+        let checked = check_recipients("Ana <ana@example.com>, bob@localhost; carol at example.com");
+        let statuses: Vec<RecipientStatus> = checked.iter().map(|(_, s)| *s).collect();
+        assert_eq!(statuses, [RecipientStatus::Valid, RecipientStatus::Unusual, RecipientStatus::Invalid]);
+        assert_eq!(checked[0].0.name.as_deref(), Some("Ana"));
+        assert_eq!(RecipientStatus::of("\"x y\"@example.com"), RecipientStatus::Unusual);
+
+        let field = "\"Doe, Jane\" <jane@example.com>, bob@example.com; Carol <carol@example.com>";
+        assert_eq!(remove_recipient(field, 1), "\"Doe, Jane\" <jane@example.com>, Carol <carol@example.com>");
+        assert_eq!(remove_recipient("bob@example.com", 0), "");
+    }
 }
@@ -1,9 +1,11 @@
 //! Contact management
 //!
-//! Manages contacts, address book, and contact groups (distribution lists).
+//! Manages contacts, address book, and contact groups (distribution lists),
+//! and builds recipient autocomplete suggestions.
 
 use crate::common::{types::EmailAddress, Result};
-use crate::data::message_cache::ContactEntry;
+use crate::data::message_cache::{ContactEntry, MessageCache};
+use std::collections::HashSet;
 
 /// Contact information
 #[derive(Debug, Clone)]
@@ -50,6 +52,111 @@ pub struct ContactGroupInfo {
     pub member_count: usize,
 }
 
+/// One entry in the recipient autocomplete dropdown
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddressSuggestion {
+    /// A saved contact
+    Contact { name: String, email: String },
+    /// A contact group; choosing it inserts every member
+    Group { name: String, emails: Vec<String> },
+    /// Someone from message history, with how often they appear
+    Recent { name: String, email: String, count: u32 },
+}
+
+impl AddressSuggestion {
+    /// Icon distinguishing contacts, groups and history entries
+    pub fn icon(&self) -> &'static str {
+        match self {
+            AddressSuggestion::Contact { .. } => "👤",
+            AddressSuggestion::Group { .. } => "👥",
+            AddressSuggestion::Recent { .. } => "🕘",
+        }
+    }
+
+    /// Kind of suggestion, spelled out for screen readers
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AddressSuggestion::Contact { .. } => "Contact",
+            AddressSuggestion::Group { .. } => "Group",
+            AddressSuggestion::Recent { .. } => "Recent",
+        }
+    }
+
+    /// Dropdown text, without the icon
+    pub fn label(&self) -> String {
+        match self {
+            AddressSuggestion::Contact { name, email } => mailbox(name, email),
+            AddressSuggestion::Group { name, emails } => {
+                let noun = if emails.len() == 1 { "member" } else { "members" };
+                format!("{} ({} {})", name, emails.len(), noun)
+            }
+            AddressSuggestion::Recent { name, email, count } => {
+                let noun = if *count == 1 { "message" } else { "messages" };
+                format!("{} ({} {})", mailbox(name, email), count, noun)
+            }
+        }
+    }
+
+    /// Text inserted into the recipient field; a group expands to its members
+    pub fn insert_text(&self) -> String {
+        match self {
+            AddressSuggestion::Contact { name, email } | AddressSuggestion::Recent { name, email, .. } => {
+                mailbox(name, email)
+            }
+            AddressSuggestion::Group { emails, .. } => emails.join(", "),
+        }
+    }
+}
+
+/// `Name <email>`, or the bare address when there is no usable name.
+/// Names containing separators are dropped so the field still splits cleanly.
+fn mailbox(name: &str, email: &str) -> String {
+    let name = name.trim();
+    if name.is_empty() || name.contains([',', ';', '"', '<', '>']) {
+        email.to_string()
+    } else {
+        format!("{} <{}>", name, email)
+    }
+}
+
+/// Recipient autocomplete for `query`: matching contacts first, then
+/// non-empty groups, then frequent correspondents not already listed.
+pub fn address_suggestions(
+    cache: &MessageCache,
+    account_id: &str,
+    query: &str,
+    limit: usize,
+) -> Result<Vec<AddressSuggestion>> {
+    let query = query.trim();
+    let mut suggestions = Vec::new();
+    let mut seen = HashSet::new();
+
+    for c in cache.search_contacts_for_account(account_id, query, limit)? {
+        if seen.insert(c.email.to_lowercase()) {
+            suggestions.push(AddressSuggestion::Contact { name: c.name, email: c.email });
+        }
+    }
+
+    let needle = query.to_lowercase();
+    for group in cache.load_contact_groups(account_id)? {
+        if group.name.to_lowercase().contains(&needle) {
+            let emails = cache.resolve_group_emails(&group.id)?;
+            if !emails.is_empty() {
+                suggestions.push(AddressSuggestion::Group { name: group.name, emails });
+            }
+        }
+    }
+
+    for r in cache.rank_frequent_recipients(account_id, query, limit)? {
+        if seen.insert(r.email.to_lowercase()) {
+            suggestions.push(AddressSuggestion::Recent { name: r.name, email: r.email, count: r.count });
+        }
+    }
+
+    suggestions.truncate(limit);
+    Ok(suggestions)
+}
+
 /// Contact manager
 #[derive(Default)]
 pub struct ContactManager {
@@ -168,6 +275,8 @@ impl ContactManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::common::types::MessagePriority;
+    use crate::data::message_cache::{CachedMessage, ContactGroup};
 
     #[test]
     fn test_contact_creation() {
@@ -232,4 +341,40 @@ mod tests {
         // Contacts still exist
         assert_eq!(manager.get_contacts().len(), 2);
     }
+
+    #[test]
+    fn test_address_suggestions_merge_sources() {
+        let temp_dir = std::env::temp_dir().join(format!("wixen_mail_test_suggest_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+        let vcard = "BEGIN:VCARD\nVERSION:3.0\nFN:Ana Lopez\nEMAIL:ana@example.com\nEND:VCARD\n\
+                     BEGIN:VCARD\nVERSION:3.0\nFN:Ben Ng\nEMAIL:ben@example.com\nEND:VCARD\n";
+        cache.import_contacts_from_vcard("acc", vcard).unwrap();
+        let group = ContactGroup {
+            id: "g1".to_string(), account_id: "acc".to_string(), name: "Analysts".to_string(),
+            description: None, created_at: String::new(), member_ids: Vec::new(),
+        };
+        cache.create_contact_group(&group).unwrap();
+        for contact in cache.get_contacts_for_account("acc").unwrap() {
+            cache.add_contact_to_group("g1", &contact.id).unwrap();
+        }
+        let folder_id = cache.ensure_folder("acc", "INBOX").unwrap().id;
+        for (uid, from) in ["Anand <anand@example.com>", "anand@example.com", "Ana Lopez <ana@example.com>"].iter().enumerate() {
+            cache.save_message(&CachedMessage {
+                id: 0, uid: uid as u32 + 1, folder_id, message_id: format!("s{}", uid),
+                subject: "Hi".to_string(), from_addr: from.to_string(), to_addr: String::new(), cc: None,
+                date: "2024-01-01".to_string(), body_plain: None, body_html: None,
+                read: true, starred: false, deleted: false, priority: MessagePriority::Normal,
+            }).unwrap();
+        }
+
+        let suggestions = address_suggestions(&cache, "acc", "ana", 10).unwrap();
+        let kinds: Vec<&str> = suggestions.iter().map(|s| s.kind()).collect();
+        assert_eq!(kinds, vec!["Contact", "Group", "Recent"]);
+        assert_eq!(suggestions[0].insert_text(), "Ana Lopez <ana@example.com>");
+        assert_eq!(suggestions[1].insert_text(), "ana@example.com, ben@example.com");
+        assert_eq!(suggestions[1].label(), "Analysts (2 members)");
+        assert_eq!(suggestions[2].label(), "Anand <anand@example.com> (2 messages)");
+        assert_eq!(address_suggestions(&cache, "acc", "ana", 1).unwrap().len(), 1);
+    }
 }
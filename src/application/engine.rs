@@ -0,0 +1,245 @@
+//! Headless mail engine
+//!
+//! Accounts, folders, messages, sending and search as plain async calls
+//! returning [`Result`], with no UI toolkit involved. Other front ends (a
+//! terminal UI, a web service, tests) drive the mail client through it.
+//! The engine owns the message cache, shared through a [`CachePool`] so
+//! calls from several tasks can read it at once, and one [`MailController`]
+//! per connected account.
+
+use crate::application::mail_controller::{MailController, MessagePreview, SendEmailRequest};
+use crate::application::search::SearchQuery;
+use crate::common::{Error, Result};
+use crate::data::account::Account;
+use crate::data::message_cache::{CachePool, CachedMessage, MessageCache};
+use crate::service::protocols::certificate_pin::{self, CertificateChange};
+use crate::service::protocols::NetworkTimeouts;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// The mail client without a window
+pub struct MailEngine {
+    cache: CachePool,
+    sessions: tokio::sync::Mutex<HashMap<String, Arc<MailController>>>,
+    timeouts: NetworkTimeouts,
+    /// Pinned IMAP certificates found changed, by account id
+    certificate_changes: Mutex<HashMap<String, CertificateChange>>,
+}
+
+impl MailEngine {
+    /// An engine over `cache`, with no accounts connected
+    pub fn new(cache: MessageCache) -> Self {
+        Self::with_pool(CachePool::new(cache))
+    }
+
+    /// An engine over a cache already shared with others, such as the
+    /// window's
+    pub fn with_pool(cache: CachePool) -> Self {
+        Self {
+            cache,
+            sessions: Default::default(),
+            timeouts: NetworkTimeouts::default(),
+            certificate_changes: Default::default(),
+        }
+    }
+
+    /// Use `timeouts` for every server
+    pub fn with_timeouts(mut self, timeouts: NetworkTimeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// The shared cache, for reads and writes the engine doesn't cover
+    pub fn cache(&self) -> &CachePool {
+        &self.cache
+    }
+
+    // ── Accounts ───────────────────────────────────────────────────────────
+
+    /// All saved accounts
+    pub fn accounts(&self) -> Result<Vec<Account>> {
+        self.cache.read(|c| c.load_accounts())
+    }
+
+    /// The saved account with this id
+    pub fn account(&self, account_id: &str) -> Result<Account> {
+        self.accounts()?
+            .into_iter()
+            .find(|a| a.id == account_id)
+            .ok_or_else(|| Error::NotFound(format!("No account with id {}", account_id)))
+    }
+
+    /// Add or update an account, once its settings are valid
+    pub fn save_account(&self, account: &Account) -> Result<()> {
+        account.validate().map_err(Error::Config)?;
+        self.cache.write(|c| c.save_account(account))
+    }
+
+    /// Disconnect and remove an account with its cached mail
+    pub async fn delete_account(&self, account_id: &str) -> Result<()> {
+        self.disconnect(account_id).await;
+        self.cache.write(|c| {
+            c.in_transaction(|c| {
+                c.clear_account_cache(account_id)?;
+                c.delete_account(account_id)
+            })?;
+            c.compact()
+        })
+    }
+
+    // ── Connections ────────────────────────────────────────────────────────
+
+    /// Sign in to the account's IMAP server, replacing any earlier session.
+    ///
+    /// With certificate pinning on, the first successful connection pins
+    /// the server's certificate. A connection refused because the pinned
+    /// certificate changed is recorded for [`MailEngine::take_certificate_change`].
+    pub async fn connect(&self, account_id: &str) -> Result<()> {
+        let account = self.account(account_id)?;
+        let port = account
+            .imap_port
+            .parse()
+            .map_err(|_| Error::Config(format!("{} is not a valid IMAP port", account.imap_port)))?;
+        let pinned = account.pin_certificates.then(|| account.imap_pinned_fingerprint.clone()).flatten();
+        let controller = MailController::new()
+            .with_timeouts(self.timeouts)
+            .with_pinned_certificate(pinned.clone());
+        let connected = controller
+            .connect_imap(account.imap_server.clone(), port, account.username.clone(), account.password.clone(), account.imap_use_tls)
+            .await;
+        if let Err(e) = connected {
+            if let (Error::Security(_), Some(pinned)) = (&e, &pinned) {
+                let change =
+                    certificate_pin::changed_certificate(&account.id, &account.imap_server, port, self.timeouts, pinned).await;
+                if let (Some(change), Ok(mut changes)) = (change, self.certificate_changes.lock()) {
+                    changes.insert(account.id.clone(), change);
+                }
+            }
+            return Err(e);
+        }
+        if account.pin_certificates && account.imap_use_tls && pinned.is_none() {
+            self.pin_imap_certificate(account.clone(), port).await;
+        }
+        self.sessions.lock().await.insert(account.id, Arc::new(controller));
+        Ok(())
+    }
+
+    /// The changed IMAP certificate that last refused a connection to the
+    /// account, if any, for the user to check before trusting it
+    pub fn take_certificate_change(&self, account_id: &str) -> Option<CertificateChange> {
+        self.certificate_changes.lock().ok()?.remove(account_id)
+    }
+
+    /// Trust on first use: remember the certificate the IMAP server just
+    /// presented. A failure only means the next connection pins instead.
+    async fn pin_imap_certificate(&self, mut account: Account, port: u16) {
+        let fingerprint = match certificate_pin::server_fingerprint(&account.imap_server, port, self.timeouts).await {
+            Ok(fingerprint) => fingerprint,
+            Err(e) => {
+                tracing::warn!("Could not pin certificate of {}: {}", account.imap_server, e);
+                return;
+            }
+        };
+        tracing::info!("Pinned certificate of {}: {}", account.imap_server, fingerprint);
+        account.imap_pinned_fingerprint = Some(fingerprint);
+        if let Err(e) = self.cache.write(|c| c.save_account(&account)) {
+            tracing::warn!("Failed to save pinned certificate: {}", e);
+        }
+    }
+
+    /// Drop the account's session, if it has one
+    pub async fn disconnect(&self, account_id: &str) {
+        self.sessions.lock().await.remove(account_id);
+    }
+
+    /// Whether the account has a session
+    pub async fn is_connected(&self, account_id: &str) -> bool {
+        self.sessions.lock().await.contains_key(account_id)
+    }
+
+    async fn session(&self, account_id: &str) -> Result<Arc<MailController>> {
+        self.sessions
+            .lock()
+            .await
+            .get(account_id)
+            .cloned()
+            .ok_or_else(|| Error::Protocol(format!("Account {} is not connected", account_id)))
+    }
+
+    // ── Folders and messages ───────────────────────────────────────────────
+
+    /// Folder paths on the server
+    pub async fn folders(&self, account_id: &str) -> Result<Vec<String>> {
+        self.session(account_id).await?.fetch_folders().await
+    }
+
+    /// Messages in `folder` on the server. Their headers are cached, so
+    /// [`MailEngine::search`] finds them, and the folder's sync time is
+    /// updated.
+    pub async fn messages(&self, account_id: &str, folder: &str) -> Result<Vec<MessagePreview>> {
+        let previews = self.session(account_id).await?.fetch_messages(folder).await?;
+        self.cache.write(|cache| {
+            let folder_id = cache.ensure_folder(account_id, folder)?.id;
+            let headers: Vec<CachedMessage> = previews.iter().map(|p| p.cached_header(folder_id)).collect();
+            cache.in_transaction(|c| {
+                c.save_message_headers(&headers)?;
+                for p in previews.iter().filter(|p| p.list_id.is_some()) {
+                    c.set_list_id(folder_id, p.uid, p.list_id.as_deref())?;
+                }
+                for p in previews.iter().filter(|p| !p.attachments.is_empty()) {
+                    c.save_attachments(folder_id, p.uid, &p.cached_attachments())?;
+                }
+                c.mark_folder_synced(folder_id)?;
+                c.recompute_folder_counts(folder_id)
+            })
+        })?;
+        Ok(previews)
+    }
+
+    /// Text of one message
+    pub async fn message_body(&self, account_id: &str, folder: &str, uid: u32) -> Result<String> {
+        self.session(account_id).await?.fetch_message_body(folder, uid).await
+    }
+
+    // ── Sending ────────────────────────────────────────────────────────────
+
+    /// A request through the account's SMTP server, for the caller to
+    /// address and fill in before [`MailEngine::send`]
+    pub fn send_request(&self, account_id: &str) -> Result<SendEmailRequest> {
+        Ok(SendEmailRequest::for_account(&self.account(account_id)?, self.timeouts))
+    }
+
+    /// Send mail, returning the MIME that was sent
+    pub async fn send(&self, request: &SendEmailRequest) -> Result<Vec<u8>> {
+        if request.to.is_empty() && request.cc.is_empty() && request.bcc.is_empty() {
+            return Err(Error::Config("The message has no recipients".into()));
+        }
+        MailController::new().send_email(request).await
+    }
+
+    // ── Search ─────────────────────────────────────────────────────────────
+
+    /// Cached messages of the account matching `query` in their subject,
+    /// sender or body, in `query.folder` or else in every folder
+    pub fn search(&self, account_id: &str, query: &SearchQuery) -> Result<Vec<CachedMessage>> {
+        self.cache.read(|cache| {
+            let folders = cache
+                .get_folders_for_account(account_id)?
+                .into_iter()
+                .filter(|f| query.folder.as_ref().is_none_or(|path| *path == f.path));
+            let mut found = Vec::new();
+            for folder in folders {
+                found.extend(
+                    cache
+                        .get_messages_for_folder(folder.id, account_id)?
+                        .into_iter()
+                        .filter(|m| {
+                            let body = m.body_plain.as_deref().or(m.body_html.as_deref());
+                            query.matches_with_body(&m.subject, &m.from_addr, body, m.priority)
+                        }),
+                );
+            }
+            Ok(found)
+        })
+    }
+}
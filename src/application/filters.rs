@@ -16,6 +16,10 @@ pub enum FilterAction {
     Star,
     Unstar,
     Delete,
+    /// Forward the message, attachments and all, to this address
+    Forward(String),
+    /// Answer the sender, once, with the template of this name
+    AutoReply(String),
 }
 
 /// Message filter rule
@@ -35,6 +39,44 @@ pub struct FilterRule {
     pub enabled: bool,
 }
 
+impl FilterRule {
+    /// The rule a blocked sender becomes: their mail goes to Trash. The
+    /// address must match whole, so blocking `ana@example.com` leaves
+    /// `hana@example.com` alone.
+    pub fn blocked_sender(address: &str) -> Self {
+        Self {
+            id: format!("blocked:{}", address),
+            name: format!("Blocked sender {}", address),
+            field: "from".to_string(),
+            match_type: "regex".to_string(),
+            pattern: sender_pattern(address),
+            case_sensitive: false,
+            action: FilterAction::Delete,
+            enabled: true,
+        }
+    }
+
+    /// The rule a sender marked as spam becomes: their mail goes to the
+    /// Junk folder
+    pub fn junk_sender(address: &str, junk_folder: &str) -> Self {
+        Self {
+            id: format!("junk:{}", address),
+            name: format!("Junk sender {}", address),
+            field: "from".to_string(),
+            match_type: "regex".to_string(),
+            pattern: sender_pattern(address),
+            case_sensitive: false,
+            action: FilterAction::MoveToFolder(junk_folder.to_string()),
+            enabled: true,
+        }
+    }
+}
+
+/// Matches `address` as a whole address in a From header
+fn sender_pattern(address: &str) -> String {
+    format!(r"(?i)(^|[<\s,]){}($|[>\s,])", regex::escape(address.trim()))
+}
+
 /// Filter engine for automatic message processing
 #[derive(Default)]
 pub struct FilterEngine {
@@ -53,6 +95,21 @@ impl FilterEngine {
         Ok(())
     }
 
+    /// Run rules for blocked senders before all others, so their mail is
+    /// removed without other rules acting on it
+    pub fn block_senders(&mut self, addresses: &[String]) {
+        let blocked: Vec<FilterRule> = addresses.iter().map(|a| FilterRule::blocked_sender(a)).collect();
+        self.rules.splice(0..0, blocked);
+    }
+
+    /// Run rules sending junk senders' mail to `junk_folder` after any
+    /// for blocked senders and before all others
+    pub fn junk_senders(&mut self, addresses: &[String], junk_folder: &str) {
+        let junk: Vec<FilterRule> = addresses.iter().map(|a| FilterRule::junk_sender(a, junk_folder)).collect();
+        let at = self.rules.iter().take_while(|r| r.id.starts_with("blocked:")).count();
+        self.rules.splice(at..at, junk);
+    }
+
     /// Get all rules
     pub fn get_rules(&self) -> &[FilterRule] {
         &self.rules
@@ -60,11 +117,12 @@ impl FilterEngine {
 
     /// Evaluate all enabled rules against a message and return matched actions
     pub fn evaluate_message(&self, message: &CachedMessage) -> Vec<FilterAction> {
-        self.rules
-            .iter()
-            .filter(|rule| rule.enabled && Self::matches(rule, message))
-            .map(|rule| rule.action.clone())
-            .collect()
+        self.matching_rules(message).map(|rule| rule.action.clone()).collect()
+    }
+
+    /// Enabled rules that match a message, in order
+    pub fn matching_rules<'a>(&'a self, message: &'a CachedMessage) -> impl Iterator<Item = &'a FilterRule> {
+        self.rules.iter().filter(|rule| rule.enabled && Self::matches(rule, message))
     }
 
     /// Convert persisted rules into runtime rules for execution
@@ -150,6 +208,12 @@ impl FilterEngine {
             "star" => FilterAction::Star,
             "unstar" => FilterAction::Unstar,
             "delete" => FilterAction::Delete,
+            "forward" => FilterAction::Forward(
+                Self::validated_action_value(rule.action_value.as_ref()).filter(|a| a.contains('@'))?,
+            ),
+            "auto_reply" => {
+                FilterAction::AutoReply(Self::validated_action_value(rule.action_value.as_ref())?)
+            }
             _ => return None,
         };
 
@@ -178,6 +242,7 @@ impl FilterEngine {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::common::types::MessagePriority;
 
     #[test]
     fn test_filter_engine_creation() {
@@ -216,6 +281,7 @@ mod tests {
             read: false,
             starred: false,
             deleted: false,
+            priority: MessagePriority::Normal,
         };
 
         let actions = engine.evaluate_message(&message);
@@ -254,10 +320,84 @@ mod tests {
             read: false,
             starred: false,
             deleted: false,
+            priority: MessagePriority::Normal,
         };
 
         let actions = engine.evaluate_message(&message);
         assert_eq!(actions.len(), 1);
         assert!(matches!(actions[0], FilterAction::Star));
     }
+
+    #[test]
+    fn test_forward_and_auto_reply_rules() {
+        let persisted = |action_type: &str, action_value: &str| MessageFilterRule {
+            id: action_type.to_string(),
+            account_id: "me@example.org".to_string(),
+            name: action_type.to_string(),
+            field: "from".to_string(),
+            match_type: "contains".to_string(),
+            pattern: "example.com".to_string(),
+            case_sensitive: false,
+            action_type: action_type.to_string(),
+            action_value: Some(action_value.to_string()),
+            enabled: true,
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+        };
+        let mut engine = FilterEngine::new().unwrap();
+        engine.load_from_persisted(&[
+            persisted("forward", " archive@example.net "),
+            persisted("auto_reply", "Away"),
+            persisted("forward", "not an address"),
+            persisted("auto_reply", " "),
+        ]);
+        assert_eq!(engine.get_rules().len(), 2);
+        assert!(matches!(&engine.get_rules()[0].action, FilterAction::Forward(a) if a == "archive@example.net"));
+        assert!(matches!(&engine.get_rules()[1].action, FilterAction::AutoReply(t) if t == "Away"));
+    }
+
+    #[test]
+    fn test_blocked_senders_run_first() {
+        let mut engine = FilterEngine::new().unwrap();
+        engine
+            .add_rule(FilterRule {
+                id: "star".to_string(),
+                name: "Star everything".to_string(),
+                field: "subject".to_string(),
+                match_type: "is_not_empty".to_string(),
+                pattern: String::new(),
+                case_sensitive: false,
+                action: FilterAction::Star,
+                enabled: true,
+            })
+            .unwrap();
+        engine.block_senders(&["spam@example.com".to_string()]);
+
+        let from = |from_addr: &str| CachedMessage {
+            id: 1,
+            uid: 1,
+            folder_id: 1,
+            message_id: "msg-1".to_string(),
+            subject: "Offer".to_string(),
+            from_addr: from_addr.to_string(),
+            to_addr: "user@example.com".to_string(),
+            cc: None,
+            date: "2026-01-01".to_string(),
+            body_plain: None,
+            body_html: None,
+            read: false,
+            starred: false,
+            deleted: false,
+            priority: MessagePriority::Normal,
+        };
+        let actions = engine.evaluate_message(&from("Deals <SPAM@example.com>"));
+        assert!(matches!(actions.as_slice(), [FilterAction::Delete, FilterAction::Star]));
+        assert_eq!(engine.evaluate_message(&from("spam@example.com")).len(), 2);
+        assert!(matches!(engine.evaluate_message(&from("nospam@example.com")).as_slice(), [FilterAction::Star]));
+
+        engine.junk_senders(&["deals@example.com".to_string()], "Junk");
+        let ids: Vec<&str> = engine.get_rules().iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, ["blocked:spam@example.com", "junk:deals@example.com", "star"]);
+        let actions = engine.evaluate_message(&from("Deals <deals@example.com>"));
+        assert!(matches!(actions.as_slice(), [FilterAction::MoveToFolder(f), FilterAction::Star] if f == "Junk"));
+    }
 }
@@ -0,0 +1,121 @@
+//! Follow-up flags
+//!
+//! A follow-up flag marks a message that still needs action, optionally by
+//! a due date. It is kept apart from the star, which only marks favourites.
+
+use crate::common::{Error, Result};
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+
+/// Follow-up state of one message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FollowUp {
+    pub due: Option<DateTime<Utc>>,
+}
+
+impl FollowUp {
+    /// From a stored RFC 3339 due time; unreadable times count as no due date
+    pub fn from_stored(due: Option<&str>) -> Self {
+        let due = due
+            .and_then(|d| DateTime::parse_from_rfc3339(d).ok())
+            .map(|d| d.with_timezone(&Utc));
+        Self { due }
+    }
+
+    /// Whether the due time has passed
+    pub fn is_overdue(&self, now: DateTime<Utc>) -> bool {
+        self.due.is_some_and(|due| due < now)
+    }
+
+    /// Short status for the message list, e.g. "Follow up", "Due Fri 14 Mar"
+    /// or "Overdue"
+    pub fn label(&self, now: DateTime<Utc>) -> String {
+        match self.due {
+            _ if self.is_overdue(now) => "Overdue".to_string(),
+            Some(due) => format!("Due {}", due.with_timezone(&Local).format("%a %e %b")),
+            None => "Follow up".to_string(),
+        }
+    }
+}
+
+/// Preset due dates offered when flagging a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FollowUpDue {
+    NoDate,
+    Today,
+    Tomorrow,
+    NextWeek,
+}
+
+impl FollowUpDue {
+    /// All presets in menu order
+    pub const ALL: [FollowUpDue; 4] = [FollowUpDue::NoDate, FollowUpDue::Today, FollowUpDue::Tomorrow, FollowUpDue::NextWeek];
+
+    /// Label for the follow-up menu
+    pub fn label(&self) -> &'static str {
+        match self {
+            FollowUpDue::NoDate => "No due date",
+            FollowUpDue::Today => "Today",
+            FollowUpDue::Tomorrow => "Tomorrow",
+            FollowUpDue::NextWeek => "In a week",
+        }
+    }
+
+    /// Due time for a message flagged at `now`: the end of the chosen day
+    pub fn due_at<Tz: TimeZone>(&self, now: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let days = match self {
+            FollowUpDue::NoDate => return None,
+            FollowUpDue::Today => 0,
+            FollowUpDue::Tomorrow => 1,
+            FollowUpDue::NextWeek => 7,
+        };
+        Some(end_of_day(&now, now.date_naive() + Duration::days(days)))
+    }
+}
+
+fn end_of_day<Tz: TimeZone>(now: &DateTime<Tz>, date: NaiveDate) -> DateTime<Tz> {
+    let local = date.and_time(NaiveTime::from_hms_opt(23, 59, 59).expect("valid time"));
+    now.timezone().from_local_datetime(&local).latest().unwrap_or_else(|| now.clone())
+}
+
+/// Parse a typed due date: `YYYY-MM-DD HH:MM`, or `YYYY-MM-DD` for the end
+/// of that day. The time must not have passed.
+pub fn parse_due_date(text: &str, now: DateTime<Local>) -> Result<DateTime<Local>> {
+    let text = text.trim();
+    let due = match NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M") {
+        Ok(naive) => Local.from_local_datetime(&naive).earliest(),
+        Err(_) => NaiveDate::parse_from_str(text, "%Y-%m-%d").ok().map(|d| end_of_day(&now, d)),
+    };
+    let due = due.ok_or_else(|| Error::Parse(format!("\"{}\" is not a date like 2025-03-14 or 2025-03-14 17:30", text)))?;
+    if due <= now {
+        return Err(Error::Config("Choose a due date that hasn't passed".to_string()));
+    }
+    Ok(due)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Timelike;
+
+    #[test]
+    fn test_follow_up_due_dates() {
+        let now = Utc.with_ymd_and_hms(2025, 3, 12, 15, 30, 0).unwrap();
+        assert_eq!(FollowUpDue::NoDate.due_at(now), None);
+        assert_eq!(FollowUpDue::Today.due_at(now), Some(Utc.with_ymd_and_hms(2025, 3, 12, 23, 59, 59).unwrap()));
+        assert_eq!(FollowUpDue::NextWeek.due_at(now), Some(Utc.with_ymd_and_hms(2025, 3, 19, 23, 59, 59).unwrap()));
+
+        let flag = FollowUp::from_stored(Some("2025-03-12T23:59:59Z"));
+        assert!(!flag.is_overdue(now));
+        assert!(flag.label(now).starts_with("Due "));
+        assert!(flag.is_overdue(now + Duration::days(1)));
+        assert_eq!(flag.label(now + Duration::days(1)), "Overdue");
+        assert_eq!(FollowUp::from_stored(None).label(now), "Follow up");
+        assert_eq!(FollowUp::from_stored(Some("garbage")).due, None);
+
+        let local_now = Local.with_ymd_and_hms(2025, 3, 12, 15, 30, 0).unwrap();
+        assert_eq!(parse_due_date("2025-03-14", local_now).unwrap().hour(), 23);
+        assert_eq!(parse_due_date("2025-03-12 17:00", local_now).unwrap().hour(), 17);
+        assert!(parse_due_date("2025-03-12 09:00", local_now).is_err());
+        assert!(parse_due_date("soon", local_now).is_err());
+    }
+}
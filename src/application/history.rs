@@ -0,0 +1,219 @@
+//! Undo history for destructive message actions
+//!
+//! Records reversible operations (delete, move, tag removal) so the UI can
+//! replay their inverse against the local cache and the server.
+
+use crate::common::Result;
+use crate::data::message_cache::MessageCache;
+use std::collections::{HashMap, VecDeque};
+
+/// Default number of actions kept in the undo history.
+pub const DEFAULT_UNDO_DEPTH: usize = 50;
+
+/// A destructive action together with the state needed to reverse it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UndoableAction {
+    /// Message was deleted; undo restores it with its original flags.
+    Delete {
+        message_id: i64,
+        uid: u32,
+        folder: String,
+        read: bool,
+        starred: bool,
+    },
+    /// Message was moved; undo moves it back to the original folder.
+    ///
+    /// `uid` is the message's UID in `from_folder`. The server numbers it
+    /// afresh in `to_folder`: `to_uid` when it reported that UID (UIDPLUS),
+    /// otherwise undo finds it there by `message_id_header`.
+    Move {
+        message_id: i64,
+        uid: u32,
+        to_uid: Option<u32>,
+        message_id_header: String,
+        from_folder_id: i64,
+        from_folder: String,
+        to_folder_id: i64,
+        to_folder: String,
+    },
+    /// Tag was removed from a message; undo re-applies it.
+    RemoveTag { message_id: i64, tag_id: String },
+}
+
+impl UndoableAction {
+    /// Short human-readable label of the original action, e.g. "Move to Archive".
+    pub fn description(&self) -> String {
+        match self {
+            UndoableAction::Delete { .. } => "Delete message".to_string(),
+            UndoableAction::Move { to_folder, .. } => format!("Move to {}", to_folder),
+            UndoableAction::RemoveTag { .. } => "Remove tag".to_string(),
+        }
+    }
+
+    /// Fill in the destination UID of a move from the UIDs the server
+    /// reported, keyed by source UID (see `MailController::apply_bulk`).
+    pub fn with_moved_uids(mut self, moved: &HashMap<u32, u32>) -> Self {
+        if let UndoableAction::Move { uid, to_uid, .. } = &mut self {
+            if let Some(new_uid) = moved.get(uid) {
+                *to_uid = Some(*new_uid);
+            }
+        }
+        self
+    }
+
+    /// Apply the inverse of this action to the local message cache.
+    pub fn revert_in_cache(&self, cache: &MessageCache) -> Result<()> {
+        match self {
+            UndoableAction::Delete { message_id, read, starred, .. } => {
+                cache.restore_message(*message_id, *read, *starred)
+            }
+            UndoableAction::Move { message_id, from_folder_id, .. } => {
+                cache.move_message(*message_id, *from_folder_id)
+            }
+            UndoableAction::RemoveTag { message_id, tag_id } => {
+                cache.add_tag_to_message(*message_id, tag_id)
+            }
+        }
+    }
+}
+
+/// Bounded stack of undoable actions (most recent last).
+#[derive(Debug, Clone)]
+pub struct ActionHistory {
+    entries: VecDeque<UndoableAction>,
+    max_depth: usize,
+}
+
+impl ActionHistory {
+    /// Create a history that keeps at most `max_depth` actions.
+    pub fn new(max_depth: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            max_depth: max_depth.max(1),
+        }
+    }
+
+    /// Record an action, discarding the oldest entry when the depth is exceeded.
+    pub fn record(&mut self, action: UndoableAction) {
+        if self.entries.len() == self.max_depth {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(action);
+    }
+
+    /// Remove and return the most recent action.
+    pub fn pop(&mut self) -> Option<UndoableAction> {
+        self.entries.pop_back()
+    }
+
+    /// Peek at the most recent action without removing it.
+    pub fn peek(&self) -> Option<&UndoableAction> {
+        self.entries.back()
+    }
+
+    /// Number of recorded actions.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether there is nothing to undo.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Drop all recorded actions (e.g. after switching accounts).
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+impl Default for ActionHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_UNDO_DEPTH)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::types::MessagePriority;
+    use crate::data::message_cache::{CachedFolder, CachedMessage, Tag};
+    use std::env;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn tag_removal(n: i64) -> UndoableAction {
+        UndoableAction::RemoveTag { message_id: n, tag_id: format!("tag-{}", n) }
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let mut history = ActionHistory::new(2);
+        history.record(tag_removal(1));
+        history.record(tag_removal(2));
+        history.record(tag_removal(3));
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.pop(), Some(tag_removal(3)));
+        assert_eq!(history.pop(), Some(tag_removal(2)));
+        assert!(history.pop().is_none());
+    }
+
+    #[test]
+    fn test_revert_actions_in_cache() {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_undo_{}", nanos));
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+
+        let inbox = CachedFolder {
+            id: 0, account_id: "acc".to_string(), name: "INBOX".to_string(), path: "INBOX".to_string(),
+            folder_type: "Inbox".to_string(), unread_count: 0, total_count: 0,
+        };
+        let archive = CachedFolder {
+            id: 0, account_id: "acc".to_string(), name: "Archive".to_string(), path: "Archive".to_string(),
+            folder_type: "Archive".to_string(), unread_count: 0, total_count: 0,
+        };
+        let inbox_id = cache.save_folder(&inbox).unwrap();
+        let archive_id = cache.save_folder(&archive).unwrap();
+
+        let msg = CachedMessage {
+            id: 0, uid: 7, folder_id: inbox_id,
+            message_id: "undo@example.com".to_string(), subject: "Undo me".to_string(),
+            from_addr: "a@example.com".to_string(), to_addr: "b@example.com".to_string(),
+            cc: None, date: "2024-01-01".to_string(), body_plain: None, body_html: None,
+            read: true, starred: true, deleted: false, priority: MessagePriority::Normal,
+        };
+        let id = cache.save_message(&msg).unwrap();
+
+        // Delete → restore
+        cache.delete_message(id).unwrap();
+        assert!(cache.get_messages_for_folder(inbox_id, "acc").unwrap().is_empty());
+        let delete = UndoableAction::Delete {
+            message_id: id, uid: 7, folder: "INBOX".to_string(), read: true, starred: true,
+        };
+        delete.revert_in_cache(&cache).unwrap();
+        let restored = cache.get_message(id).unwrap().unwrap();
+        assert!(!restored.deleted);
+        assert!(restored.read && restored.starred);
+
+        // Move → move back
+        cache.move_message(id, archive_id).unwrap();
+        let moved = UndoableAction::Move {
+            message_id: id, uid: 7, to_uid: None, message_id_header: "undo@example.com".to_string(),
+            from_folder_id: inbox_id, from_folder: "INBOX".to_string(),
+            to_folder_id: archive_id, to_folder: "Archive".to_string(),
+        };
+        let moved = moved.with_moved_uids(&HashMap::from([(7, 12)]));
+        assert!(matches!(moved, UndoableAction::Move { to_uid: Some(12), .. }));
+        moved.revert_in_cache(&cache).unwrap();
+        assert_eq!(cache.get_message(id).unwrap().unwrap().folder_id, inbox_id);
+
+        // Remove tag → add tag
+        let tag = Tag {
+            id: "t1".to_string(), account_id: "acc".to_string(), name: "Work".to_string(),
+            color: "#ff0000".to_string(), created_at: "2024-01-01".to_string(), shortcut: None,
+        };
+        cache.create_tag(&tag).unwrap();
+        let removal = UndoableAction::RemoveTag { message_id: id, tag_id: "t1".to_string() };
+        removal.revert_in_cache(&cache).unwrap();
+        assert_eq!(cache.get_tags_for_message(id).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,102 @@
+//! Sending identities
+//!
+//! An identity is another From address for the same mailbox (an alias).
+//! This module checks that an alias belongs to its account and picks the
+//! identity a reply is sent from.
+
+use crate::common::{types::EmailAddress, Error, Result};
+use crate::data::account::is_bare_address;
+use crate::data::message_cache::Identity;
+
+/// Domain of a bare address, lower-cased
+fn domain(address: &str) -> Option<String> {
+    let (local, domain) = address.trim().rsplit_once('@')?;
+    (!local.is_empty() && domain.contains('.') && !domain.contains(char::is_whitespace))
+        .then(|| domain.to_ascii_lowercase())
+}
+
+/// Check an identity before it is saved. The From address must be in the
+/// same domain as the account's own address, since the mail server will
+/// only send as addresses of that mailbox; a Reply-To or bounce address
+/// may be anywhere.
+pub fn validate_identity(account_email: &str, identity: &Identity) -> Result<()> {
+    let Some(from_domain) = domain(&identity.from_email) else {
+        return Err(Error::Config(format!("\"{}\" is not an email address", identity.from_email)));
+    };
+    if domain(account_email).as_deref() != Some(from_domain.as_str()) {
+        return Err(Error::Config(format!(
+            "{} is not an address of the account {}; aliases must use the account's domain",
+            identity.from_email, account_email
+        )));
+    }
+    if let Some(reply_to) = identity.reply_to.as_deref().filter(|r| !r.trim().is_empty()) {
+        if domain(reply_to).is_none() {
+            return Err(Error::Config(format!("Reply-To \"{}\" is not an email address", reply_to)));
+        }
+    }
+    // The bounce address may be anywhere, such as a bounce-processing
+    // service, but SMTP takes it bare
+    if let Some(envelope_from) = identity.envelope_from.as_deref().filter(|e| !e.trim().is_empty()) {
+        if !is_bare_address(envelope_from) {
+            return Err(Error::Config(format!("Bounce address \"{}\" is not a plain email address", envelope_from)));
+        }
+    }
+    Ok(())
+}
+
+/// The identity to reply from for a message sent to `to` and `cc`: the
+/// first whose address is among the recipients. `None` means the account's
+/// own address.
+pub fn identity_for_reply<'a>(identities: &'a [Identity], to: &str, cc: &str) -> Option<&'a Identity> {
+    let recipients: Vec<EmailAddress> = EmailAddress::parse_list(to)
+        .into_iter()
+        .chain(EmailAddress::parse_list(cc))
+        .collect();
+    identities
+        .iter()
+        .find(|i| recipients.iter().any(|r| r.address.eq_ignore_ascii_case(i.from_email.trim())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity(from_email: &str, reply_to: Option<&str>) -> Identity {
+        Identity {
+            id: from_email.to_string(),
+            account_id: "acct".to_string(),
+            from_name: String::new(),
+            from_email: from_email.to_string(),
+            reply_to: reply_to.map(str::to_string),
+            envelope_from: None,
+            signature_id: None,
+            created_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_validate_identity() {
+        let account = "me@example.com";
+        assert!(validate_identity(account, &identity("sales@Example.com", None)).is_ok());
+        assert!(validate_identity(account, &identity("me+news@example.com", Some("desk@other.org"))).is_ok());
+        assert!(matches!(validate_identity(account, &identity("me@other.org", None)), Err(Error::Config(_))));
+        assert!(validate_identity(account, &identity("not an address", None)).is_err());
+        assert!(validate_identity(account, &identity("sales@example.com", Some("nobody"))).is_err());
+
+        let mut bulk = identity("news@example.com", None);
+        bulk.envelope_from = Some("bounces+news@mailer.example.net".to_string());
+        assert!(validate_identity(account, &bulk).is_ok());
+        bulk.envelope_from = Some("News <bounces@example.net>".to_string());
+        assert!(matches!(validate_identity(account, &bulk), Err(Error::Config(_))));
+    }
+
+    #[test]
+    fn test_identity_for_reply() {
+        let identities = vec![identity("sales@example.com", None), identity("support@example.com", None)];
+        let chosen = identity_for_reply(&identities, "Team <SUPPORT@example.com>", "");
+        assert_eq!(chosen.map(|i| i.from_email.as_str()), Some("support@example.com"));
+        let chosen = identity_for_reply(&identities, "me@example.com", "sales@example.com");
+        assert_eq!(chosen.map(|i| i.from_email.as_str()), Some("sales@example.com"));
+        assert!(identity_for_reply(&identities, "me@example.com", "").is_none());
+    }
+}
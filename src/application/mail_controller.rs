@@ -2,14 +2,152 @@
 //!
 //! Bridges the UI with IMAP/SMTP protocols and manages mail operations.
 
+use crate::application::bulk::BulkAction;
+use crate::application::history::UndoableAction;
+use crate::common::mime::{decode_body, decode_header};
+use crate::common::types::{FolderType, MessagePriority};
 use crate::common::{Error, Result};
+use crate::data::account::{Account, DeleteMode};
+use crate::data::message_cache::{CachedAttachment, CachedMessage, ServerFolder};
+use crate::data::storage::DRAFT_ID_HEADER;
+use crate::service::protocols::body_structure::BodyPart;
 use crate::service::protocols::imap::{
-    ImapClient, ImapConfig, ImapIdleEvent, ImapIdleHandle, ImapIdleOptions, ImapSession,
+    ImapClient, ImapConfig, ImapFolder, ImapIdleEvent, ImapIdleHandle, ImapIdleOptions, ImapQuota, ImapSession,
 };
 use crate::service::protocols::pop3::{Pop3Client, Pop3Config, Pop3Session};
-use crate::service::protocols::smtp::{Email, SmtpClient, SmtpConfig};
+use crate::service::protocols::rate_limit::{self, ServerLimiter};
+use crate::service::protocols::smtp::{Email, ReadReceipt, SmtpClient, SmtpConfig};
+use crate::service::protocols::{network, NetworkTimeouts};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use tokio::sync::{Mutex, MutexGuard};
+use tokio::sync::{Mutex, MutexGuard, OwnedSemaphorePermit};
+
+/// Folder names tried when the server doesn't advertise a `\Sent` folder.
+const SENT_FOLDER_NAMES: &[&str] = &["Sent", "Sent Items", "Sent Messages", "[Gmail]/Sent Mail"];
+/// Folder names tried when the server doesn't advertise a `\Drafts` folder.
+const DRAFTS_FOLDER_NAMES: &[&str] = &["Drafts", "Draft", "[Gmail]/Drafts"];
+/// Folder names tried when the server doesn't advertise a `\Trash` folder.
+const TRASH_FOLDER_NAMES: &[&str] = &["Trash", "Deleted Items", "Deleted Messages", "[Gmail]/Trash", "[Gmail]/Bin"];
+/// Folder names tried when the server doesn't advertise a `\Junk` folder.
+const JUNK_FOLDER_NAMES: &[&str] = &["Junk", "Spam", "Junk E-mail", "Junk Email", "Bulk Mail", "[Gmail]/Spam"];
+/// Folder names tried when the server doesn't advertise an `\Archive` or
+/// `\All` folder. Gmail archives by removing the Inbox label, which a MOVE
+/// to All Mail does.
+const ARCHIVE_FOLDER_NAMES: &[&str] = &["Archive", "Archives", "[Gmail]/All Mail", "All Mail"];
+/// Folder snoozed messages wait in; created on first use.
+pub const SNOOZED_FOLDER: &str = "Snoozed";
+/// IMAP keyword for follow-up flags. `\Flagged` is already the star, so
+/// follow-up uses its own keyword.
+pub const FOLLOW_UP_KEYWORD: &str = "$FollowUp";
+/// IMAP keywords (RFC 5788) that tell the server's spam filter a message
+/// was marked as spam, or as not spam
+const JUNK_KEYWORD: &str = "$Junk";
+const NOT_JUNK_KEYWORD: &str = "$NotJunk";
+/// Messages larger than this (bytes) open with only their text until the
+/// whole message is asked for
+pub const LARGE_MESSAGE_BYTES: u64 = 256 * 1024;
+
+/// A folder with a special role, found by its SPECIAL-USE (RFC 6154) or
+/// Gmail XLIST flag, or failing that by a common name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecialFolder {
+    Sent,
+    Drafts,
+    Trash,
+    Junk,
+    Archive,
+}
+
+impl SpecialFolder {
+    pub const ALL: [SpecialFolder; 5] =
+        [SpecialFolder::Sent, SpecialFolder::Drafts, SpecialFolder::Trash, SpecialFolder::Junk, SpecialFolder::Archive];
+
+    /// Name used when the server can't be asked (offline)
+    pub fn default_name(&self) -> &'static str {
+        match self {
+            SpecialFolder::Sent => "Sent",
+            SpecialFolder::Drafts => "Drafts",
+            SpecialFolder::Trash => "Trash",
+            SpecialFolder::Junk => "Junk",
+            SpecialFolder::Archive => "Archive",
+        }
+    }
+
+    /// Role stored in the cache's `folder_type`
+    pub fn folder_type(&self) -> FolderType {
+        match self {
+            SpecialFolder::Sent => FolderType::Sent,
+            SpecialFolder::Drafts => FolderType::Drafts,
+            SpecialFolder::Trash => FolderType::Trash,
+            SpecialFolder::Junk => FolderType::Spam,
+            SpecialFolder::Archive => FolderType::Archive,
+        }
+    }
+
+    /// Flags marking the folder, most specific first: the SPECIAL-USE flag,
+    /// then Gmail's XLIST names for it
+    fn flags(&self) -> &'static [&'static str] {
+        match self {
+            SpecialFolder::Sent => &["\\Sent"],
+            SpecialFolder::Drafts => &["\\Drafts"],
+            SpecialFolder::Trash => &["\\Trash"],
+            SpecialFolder::Junk => &["\\Junk", "\\Spam"],
+            // Gmail archives by removing the Inbox label, which a MOVE to
+            // All Mail does
+            SpecialFolder::Archive => &["\\Archive", "\\All", "\\AllMail"],
+        }
+    }
+
+    fn fallback_names(&self) -> &'static [&'static str] {
+        match self {
+            SpecialFolder::Sent => SENT_FOLDER_NAMES,
+            SpecialFolder::Drafts => DRAFTS_FOLDER_NAMES,
+            SpecialFolder::Trash => TRASH_FOLDER_NAMES,
+            SpecialFolder::Junk => JUNK_FOLDER_NAMES,
+            SpecialFolder::Archive => ARCHIVE_FOLDER_NAMES,
+        }
+    }
+
+    /// Whether the server flagged `folder` with this role
+    fn flagged(&self, folder: &ImapFolder) -> bool {
+        folder.flags.iter().any(|flag| self.flags().iter().any(|f| flag.eq_ignore_ascii_case(f)))
+    }
+
+    /// The listed folder with this role: the first flagged with it, in
+    /// order of flag preference, or else the first with a common name
+    pub fn find(&self, folders: &[ImapFolder]) -> Option<String> {
+        self.flags()
+            .iter()
+            .find_map(|flag| folders.iter().find(|f| f.flags.iter().any(|g| g.eq_ignore_ascii_case(flag))))
+            .or_else(|| {
+                let unflagged = |f: &&ImapFolder| !SpecialFolder::ALL.iter().any(|role| role.flagged(f));
+                self.fallback_names()
+                    .iter()
+                    .find_map(|name| folders.iter().filter(unflagged).find(|f| f.name.eq_ignore_ascii_case(name)))
+            })
+            .map(|f| f.name.clone())
+    }
+}
+
+/// Role of each listed folder for the cache: the Inbox, the folder
+/// [`SpecialFolder::find`] picks for each role, or custom
+pub fn folder_types(folders: &[ImapFolder]) -> Vec<(String, FolderType)> {
+    let roles: Vec<(String, FolderType)> = SpecialFolder::ALL
+        .iter()
+        .filter_map(|role| Some((role.find(folders)?, role.folder_type())))
+        .collect();
+    folders
+        .iter()
+        .map(|f| {
+            let folder_type = if f.name.eq_ignore_ascii_case("INBOX") {
+                FolderType::Inbox
+            } else {
+                roles.iter().find(|(path, _)| *path == f.name).map(|(_, t)| *t).unwrap_or(FolderType::Custom)
+            };
+            (f.name.clone(), folder_type)
+        })
+        .collect()
+}
 
 /// Parameters for sending an email via SMTP.
 #[derive(Debug, Clone)]
@@ -19,9 +157,88 @@ pub struct SendEmailRequest {
     pub username: String,
     pub password: String,
     pub use_tls: bool,
+    /// Send as this address (an identity) instead of `username`
+    pub from: Option<String>,
+    pub from_name: Option<String>,
+    pub reply_to: Option<String>,
+    /// Bounce address given to the server as `MAIL FROM`; the `from`
+    /// address when unset
+    pub envelope_from: Option<String>,
     pub to: Vec<String>,
+    pub cc: Vec<String>,
+    pub bcc: Vec<String>,
     pub subject: String,
     pub body: String,
+    /// Ask recipients for a read receipt, sent back to `username`
+    pub request_receipt: bool,
+    pub priority: MessagePriority,
+    pub timeouts: NetworkTimeouts,
+    /// Refuse the SMTP server unless its certificate has this fingerprint
+    pub pinned_fingerprint: Option<String>,
+    /// Sign and/or encrypt with OpenPGP
+    #[cfg(feature = "openpgp")]
+    pub pgp: Option<crate::service::openpgp::OutgoingPgp>,
+}
+
+impl SendEmailRequest {
+    /// A request through `account`'s SMTP server, with no recipients or
+    /// content yet
+    pub fn for_account(account: &Account, timeouts: NetworkTimeouts) -> Self {
+        Self {
+            server: account.smtp_server.clone(),
+            port: account.smtp_port.parse().unwrap_or(465),
+            username: account.username.clone(),
+            password: account.password.clone(),
+            use_tls: account.smtp_use_tls,
+            from: None,
+            from_name: None,
+            reply_to: None,
+            envelope_from: account.envelope_from.clone(),
+            to: Vec::new(),
+            cc: Vec::new(),
+            bcc: Vec::new(),
+            subject: String::new(),
+            body: String::new(),
+            request_receipt: false,
+            priority: MessagePriority::Normal,
+            timeouts,
+            pinned_fingerprint: account.pin_certificates.then(|| account.smtp_pinned_fingerprint.clone()).flatten(),
+            #[cfg(feature = "openpgp")]
+            pgp: None,
+        }
+    }
+
+    fn smtp_config(&self) -> SmtpConfig {
+        SmtpConfig {
+            server: self.server.clone(),
+            port: self.port,
+            use_tls: self.use_tls,
+            username: self.username.clone(),
+            timeouts: self.timeouts,
+            pinned_fingerprint: self.pinned_fingerprint.clone(),
+        }
+    }
+
+    /// The message to send, from `username` unless another From is set
+    fn email(&self) -> Email {
+        let from = self.from.clone().unwrap_or_else(|| self.username.clone());
+        Email {
+            from: from.clone(),
+            from_name: self.from_name.clone(),
+            to: self.to.clone(),
+            cc: self.cc.clone(),
+            bcc: self.bcc.clone(),
+            subject: self.subject.clone(),
+            body_text: self.body.clone(),
+            body_html: None,
+            reply_to: self.reply_to.clone(),
+            envelope_from: self.envelope_from.clone(),
+            disposition_notification_to: self.request_receipt.then_some(from),
+            priority: self.priority,
+            #[cfg(feature = "openpgp")]
+            pgp: self.pgp.clone(),
+        }
+    }
 }
 
 /// Mail controller for managing mail operations
@@ -29,6 +246,9 @@ pub struct MailController {
     imap_session: Arc<Mutex<Option<ImapSession>>>,
     pop3_session: Arc<Mutex<Option<Pop3Session>>>,
     idle_handle: Arc<Mutex<Option<ImapIdleHandle>>>,
+    timeouts: NetworkTimeouts,
+    imap_pinned_fingerprint: Option<String>,
+    imap_limiter: Arc<Mutex<Option<Arc<ServerLimiter>>>>,
 }
 
 impl MailController {
@@ -38,9 +258,24 @@ impl MailController {
             imap_session: Arc::new(Mutex::new(None)),
             pop3_session: Arc::new(Mutex::new(None)),
             idle_handle: Arc::new(Mutex::new(None)),
+            timeouts: NetworkTimeouts::default(),
+            imap_pinned_fingerprint: None,
+            imap_limiter: Arc::new(Mutex::new(None)),
         }
     }
 
+    /// Use `timeouts` for connecting and for fetches during mail checks
+    pub fn with_timeouts(mut self, timeouts: NetworkTimeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Refuse the IMAP server unless its certificate has this fingerprint
+    pub fn with_pinned_certificate(mut self, fingerprint: Option<String>) -> Self {
+        self.imap_pinned_fingerprint = fingerprint;
+        self
+    }
+
     /// Lock and return the IMAP session guard, or error if not connected.
     async fn require_imap(&self) -> Result<MutexGuard<'_, Option<ImapSession>>> {
         let guard = self.imap_session.lock().await;
@@ -50,6 +285,23 @@ impl MailController {
         Ok(guard)
     }
 
+    /// Wait for one of the IMAP account's fetch slots, held until dropped
+    async fn fetch_slot(&self) -> Option<OwnedSemaphorePermit> {
+        let limiter = self.imap_limiter.lock().await.clone();
+        match limiter {
+            Some(limiter) => Some(limiter.fetch().await),
+            None => None,
+        }
+    }
+
+    /// Pause the IMAP account if the server asked to slow down
+    async fn imap_throttled<T>(&self, result: Result<T>) -> Result<T> {
+        match self.imap_limiter.lock().await.as_ref() {
+            Some(limiter) => limiter.finish(result),
+            None => result,
+        }
+    }
+
     /// Lock and return the POP3 session guard, or error if not connected.
     async fn require_pop3(&self) -> Result<MutexGuard<'_, Option<Pop3Session>>> {
         let guard = self.pop3_session.lock().await;
@@ -73,10 +325,15 @@ impl MailController {
             port,
             use_tls,
             username,
+            timeouts: self.timeouts,
+            pinned_fingerprint: self.imap_pinned_fingerprint.clone(),
         };
 
+        let limiter = rate_limit::limiter(&config.server, &config.username);
         let client = ImapClient::new(config)?;
-        let session = client.connect(&password).await?;
+        limiter.connection().await?;
+        let session = limiter.finish(client.connect(&password).await)?;
+        *self.imap_limiter.lock().await = Some(limiter);
 
         let mut imap_session = self.imap_session.lock().await;
         *imap_session = Some(session);
@@ -89,63 +346,301 @@ impl MailController {
     pub async fn fetch_folders(&self) -> Result<Vec<String>> {
         let mut guard = self.require_imap().await?;
         let session = guard.as_mut().unwrap();
-        let folders = session.list_folders().await?;
+        let folders = network::with_timeout(self.timeouts.read, "listing folders", session.list_folders()).await?;
         Ok(folders.into_iter().map(|f| f.name).collect())
     }
 
+    /// Every folder on the server with its role and whether it is on the
+    /// subscription list, from `LIST` and `LSUB`
+    pub async fn fetch_server_folders(&self) -> Result<Vec<ServerFolder>> {
+        let mut guard = self.require_imap().await?;
+        let session = guard.as_mut().unwrap();
+        let folders = network::with_timeout(self.timeouts.read, "listing folders", session.list_folders()).await?;
+        let subscribed: HashSet<String> =
+            network::with_timeout(self.timeouts.read, "listing subscribed folders", session.list_subscribed())
+                .await?
+                .into_iter()
+                .map(|f| f.name)
+                .collect();
+        Ok(folder_types(&folders)
+            .into_iter()
+            .map(|(path, folder_type)| ServerFolder { subscribed: subscribed.contains(&path), path, folder_type })
+            .collect())
+    }
+
+    /// Subscribe to a folder, or unsubscribe from it
+    pub async fn set_subscribed(&self, folder: &str, subscribed: bool) -> Result<()> {
+        let mut guard = self.require_imap().await?;
+        let session = guard.as_mut().unwrap();
+        if subscribed {
+            network::with_timeout(self.timeouts.read, "subscribing to a folder", session.subscribe(folder)).await
+        } else {
+            network::with_timeout(self.timeouts.read, "unsubscribing from a folder", session.unsubscribe(folder)).await
+        }
+    }
+
+    /// Create a folder called `name` inside `parent`, or at the top level.
+    /// Returns the new folder's path.
+    pub async fn create_folder(&self, parent: Option<&str>, name: &str) -> Result<String> {
+        let mut guard = self.require_imap().await?;
+        let session = guard.as_mut().unwrap();
+        let folders = network::with_timeout(self.timeouts.read, "listing folders", session.list_folders()).await?;
+        let path = child_folder_path(parent, name, &hierarchy_delimiter(&folders, parent))?;
+        if folders.iter().any(|f| f.name == path) {
+            return Err(Error::Config(format!("A folder called {} already exists", path)));
+        }
+        network::with_timeout(self.timeouts.read, "creating a folder", session.create_folder(&path)).await?;
+        Ok(path)
+    }
+
+    /// Rename a folder, keeping it in the same parent. Its subfolders move
+    /// with it. Returns the new path.
+    pub async fn rename_folder(&self, path: &str, new_name: &str) -> Result<String> {
+        let mut guard = self.require_imap().await?;
+        let session = guard.as_mut().unwrap();
+        let folders = network::with_timeout(self.timeouts.read, "listing folders", session.list_folders()).await?;
+        if is_special_use(&folders, path) {
+            return Err(Error::Config(format!("{} is a special folder and can't be renamed", path)));
+        }
+        let delimiter = hierarchy_delimiter(&folders, Some(path));
+        let parent = folder_parent(path, &delimiter);
+        let new_path = child_folder_path(parent, new_name, &delimiter)?;
+        if new_path != path && folders.iter().any(|f| f.name == new_path) {
+            return Err(Error::Config(format!("A folder called {} already exists", new_path)));
+        }
+        network::with_timeout(self.timeouts.read, "renaming a folder", session.rename_folder(path, &new_path)).await?;
+        Ok(new_path)
+    }
+
+    /// Delete a folder and the messages in it. The Inbox, special-use
+    /// folders and folders with subfolders are refused; servers differ in
+    /// what happens to subfolders, so they must go first.
+    pub async fn delete_folder(&self, path: &str) -> Result<()> {
+        let mut guard = self.require_imap().await?;
+        let session = guard.as_mut().unwrap();
+        let folders = network::with_timeout(self.timeouts.read, "listing folders", session.list_folders()).await?;
+        if is_special_use(&folders, path) {
+            return Err(Error::Config(format!("{} is a special folder and can't be deleted", path)));
+        }
+        let delimiter = hierarchy_delimiter(&folders, Some(path));
+        if folders.iter().any(|f| folder_parent(&f.name, &delimiter) == Some(path)) {
+            return Err(Error::Config(format!("Delete or move the folders inside {} first", path)));
+        }
+        network::with_timeout(self.timeouts.read, "deleting a folder", session.delete_folder(path)).await
+    }
+
+    /// The hierarchy separator around `path` (such as `/` or `.`, empty
+    /// when the server has no subfolders), and whether `path` is a special
+    /// folder that can't be renamed or deleted
+    pub async fn folder_details(&self, path: &str) -> Result<(String, bool)> {
+        let mut guard = self.require_imap().await?;
+        let session = guard.as_mut().unwrap();
+        let folders = network::with_timeout(self.timeouts.read, "listing folders", session.list_folders()).await?;
+        Ok((hierarchy_delimiter(&folders, Some(path)), is_special_use(&folders, path)))
+    }
+
+    /// Mailbox usage and limits for the account, from the quota root of
+    /// the Inbox. `None` if the server doesn't support quotas.
+    pub async fn fetch_quota(&self) -> Result<Option<ImapQuota>> {
+        let mut guard = self.require_imap().await?;
+        let session = guard.as_mut().unwrap();
+        network::with_timeout(self.timeouts.read, "checking mailbox usage", session.get_quota_root("INBOX")).await
+    }
+
     /// Fetch messages from a folder
     pub async fn fetch_messages(&self, folder: &str) -> Result<Vec<MessagePreview>> {
+        let _slot = self.fetch_slot().await;
         let mut guard = self.require_imap().await?;
         let session = guard.as_mut().unwrap();
-        let messages = session.fetch_messages(folder, None).await?;
+        let what = format!("fetching messages in {}", folder);
+        let fetched = network::with_timeout(self.timeouts.read, &what, session.fetch_messages(folder, None)).await;
+        let messages = self.imap_throttled(fetched).await?;
 
         Ok(messages
             .into_iter()
             .map(|m| MessagePreview {
                 uid: m.uid,
-                subject: m.subject,
-                from: m.from,
+                subject: decode_header(&m.subject),
+                from: decode_header(&m.from),
                 date: m.date,
+                message_id: m.message_id,
                 read: m.flags.contains(&"\\Seen".to_string()),
                 starred: m.flags.contains(&"\\Flagged".to_string()),
+                follow_up: m.flags.iter().any(|f| f.eq_ignore_ascii_case(FOLLOW_UP_KEYWORD)),
+                list_id: m.list_id,
+                attachments: m
+                    .body_structure
+                    .map(|structure| structure.attachments().into_iter().cloned().collect())
+                    .unwrap_or_default(),
             })
             .collect())
     }
 
     /// Fetch message body
     pub async fn fetch_message_body(&self, folder: &str, uid: u32) -> Result<String> {
+        let _slot = self.fetch_slot().await;
         let mut guard = self.require_imap().await?;
         let session = guard.as_mut().unwrap();
-        session.fetch_message_body(folder, uid).await
+        let fetched =
+            network::with_timeout(self.timeouts.read, "downloading the message", session.fetch_message_body(folder, uid)).await;
+        self.imap_throttled(fetched).await
     }
 
-    /// Send an email via SMTP
-    pub async fn send_email(&self, req: &SendEmailRequest) -> Result<()> {
-        let config = SmtpConfig {
-            server: req.server.clone(),
-            port: req.port,
-            use_tls: req.use_tls,
-            username: req.username.clone(),
+    /// Fetch a message to read. A message over [`LARGE_MESSAGE_BYTES`]
+    /// comes back as just its text part, found from its `BODYSTRUCTURE`,
+    /// so big attachments and quoted threads don't hold up reading it;
+    /// `fetch_message_body` then gets the whole message.
+    pub async fn fetch_message_preview(&self, folder: &str, uid: u32) -> Result<FetchedBody> {
+        let _slot = self.fetch_slot().await;
+        let mut guard = self.require_imap().await?;
+        let session = guard.as_mut().unwrap();
+        let fetched =
+            network::with_timeout(self.timeouts.read, "reading the message", session.fetch_body_structure(folder, uid)).await;
+        let structure = self.imap_throttled(fetched).await?;
+        let full_size = structure.size();
+        let fetched = match structure.text_part().filter(|part| full_size > LARGE_MESSAGE_BYTES && part.size < full_size) {
+            Some(part) => network::with_timeout(
+                self.timeouts.read,
+                "downloading the message text",
+                session.fetch_body_part(folder, uid, &part.section),
+            )
+            .await
+            .map(|text| FetchedBody::TextOnly { text: decode_body(&text, &part.encoding, part.charset.as_deref()), full_size }),
+            None => network::with_timeout(self.timeouts.read, "downloading the message", session.fetch_message_body(folder, uid))
+                .await
+                .map(FetchedBody::Full),
         };
+        self.imap_throttled(fetched).await
+    }
 
-        let client = SmtpClient::new(config)?;
+    /// Fetch the full raw message (headers and MIME) for "View Source"
+    pub async fn fetch_raw_message(&self, folder: &str, uid: u32) -> Result<Vec<u8>> {
+        let _slot = self.fetch_slot().await;
+        let mut guard = self.require_imap().await?;
+        let session = guard.as_mut().unwrap();
+        let fetched = session.fetch_raw_message(folder, uid).await;
+        self.imap_throttled(fetched).await
+    }
 
-        let email = Email {
-            from: req.username.clone(),
-            from_name: None,
-            to: req.to.clone(),
-            cc: vec![],
-            bcc: vec![],
-            subject: req.subject.clone(),
-            body_text: req.body.clone(),
-            body_html: None,
+    /// Send an email via SMTP, returning the MIME that was sent
+    pub async fn send_email(&self, req: &SendEmailRequest) -> Result<Vec<u8>> {
+        let client = SmtpClient::new(req.smtp_config())?;
+        let raw = client.send_email(req.email(), &req.password).await?;
+        tracing::info!("Email sent successfully");
+        Ok(raw)
+    }
+
+    /// Send several messages through one account's SMTP server, returning
+    /// the MIME sent or the error for each, in order. With
+    /// `reuse_connection` they share one signed-in connection (the server
+    /// settings of the first request); otherwise each connects on its own,
+    /// as `send_email` does. An error for the whole batch means the
+    /// server couldn't be reached.
+    pub async fn send_batch(&self, requests: &[SendEmailRequest], reuse_connection: bool) -> Result<Vec<Result<Vec<u8>>>> {
+        let Some(first) = requests.first() else {
+            return Ok(Vec::new());
         };
+        if !reuse_connection {
+            let mut results = Vec::with_capacity(requests.len());
+            for req in requests {
+                results.push(self.send_email(req).await);
+            }
+            return Ok(results);
+        }
+        let emails: Vec<Email> = requests.iter().map(SendEmailRequest::email).collect();
+        SmtpClient::new(first.smtp_config())?.send_batch(&emails, &first.password).await
+    }
 
-        client.send_email(email, &req.password).await?;
-        tracing::info!("Email sent successfully");
+    /// Send a read receipt from `req.username` using the SMTP server in
+    /// `req`; the request's recipients and content are not used.
+    pub async fn send_read_receipt(&self, req: &SendEmailRequest, receipt: &ReadReceipt, automatic: bool) -> Result<()> {
+        let client = SmtpClient::new(req.smtp_config())?;
+        let raw = receipt.to_mime(&req.username, automatic);
+        client.send_raw(&req.username, std::slice::from_ref(&receipt.to), &raw, &req.password).await?;
+        tracing::info!("Read receipt sent");
         Ok(())
     }
 
+    /// Send an answer to a calendar invitation (see
+    /// [`CalendarInvite::reply_mime`](crate::application::calendar::CalendarInvite::reply_mime))
+    /// to `organizer` using the SMTP server in `req`
+    pub async fn send_calendar_reply(&self, req: &SendEmailRequest, organizer: &str, raw: &[u8]) -> Result<()> {
+        self.send_prepared(req, organizer, raw).await?;
+        tracing::info!("Calendar reply sent");
+        Ok(())
+    }
+
+    /// Send a message already rendered to MIME, such as a rule's automatic
+    /// reply or forward, to `to` using the SMTP server in `req`; the
+    /// request's recipients and content are not used. Bounces go to the
+    /// request's envelope sender, if it has one.
+    pub async fn send_prepared(&self, req: &SendEmailRequest, to: &str, raw: &[u8]) -> Result<()> {
+        let client = SmtpClient::new(req.smtp_config())?;
+        let envelope_from = req.envelope_from.as_deref().unwrap_or(&req.username);
+        client.send_raw(envelope_from, &[to.to_string()], raw, &req.password).await
+    }
+
+    /// Append a sent message to the account's Sent folder.
+    ///
+    /// The folder is found by its `\Sent` special-use flag, falling back to
+    /// common names. Returns the folder the message was filed in.
+    pub async fn append_to_sent(&self, raw: &[u8]) -> Result<String> {
+        let mut guard = self.require_imap().await?;
+        let session = guard.as_mut().unwrap();
+        let folders = session.list_folders().await?;
+        let sent = SpecialFolder::Sent.find(&folders)
+            .ok_or_else(|| Error::Protocol("No Sent folder found".to_string()))?;
+        session.append(&sent, raw, &["\\Seen"]).await?;
+        tracing::info!("Saved sent message to {}", sent);
+        Ok(sent)
+    }
+
+    /// Upload a draft to the server's Drafts folder, replacing its previous
+    /// copy (APPEND the new one, then STORE `\Deleted` on the old UID).
+    ///
+    /// Returns the new copy's UID, if the server reports or finds it.
+    pub async fn save_draft_to_server(
+        &self,
+        draft_id: &str,
+        raw: &[u8],
+        replace_uid: Option<u32>,
+    ) -> Result<Option<u32>> {
+        let mut guard = self.require_imap().await?;
+        let session = guard.as_mut().unwrap();
+        let folders = session.list_folders().await?;
+        let drafts = SpecialFolder::Drafts.find(&folders)
+            .ok_or_else(|| Error::Protocol("No Drafts folder found".to_string()))?;
+        let uid = match session.append(&drafts, raw, &["\\Draft", "\\Seen"]).await? {
+            Some(uid) => Some(uid),
+            // Without UIDPLUS, find the copy by its draft id
+            None => session
+                .search_header(&drafts, DRAFT_ID_HEADER, draft_id)
+                .await?
+                .into_iter()
+                .filter(|uid| Some(*uid) != replace_uid)
+                .max(),
+        };
+        if let Some(old) = replace_uid.filter(|old| Some(*old) != uid) {
+            session.store_flags(&drafts, &[old], "\\Deleted", true).await?;
+        }
+        Ok(uid)
+    }
+
+    /// Fetch the raw messages in the server's Drafts folder as (UID, message).
+    pub async fn fetch_server_drafts(&self) -> Result<Vec<(u32, String)>> {
+        let mut guard = self.require_imap().await?;
+        let session = guard.as_mut().unwrap();
+        let folders = session.list_folders().await?;
+        let drafts = SpecialFolder::Drafts.find(&folders)
+            .ok_or_else(|| Error::Protocol("No Drafts folder found".to_string()))?;
+        session.select_folder(&drafts).await?;
+        let mut result = Vec::new();
+        for uid in session.fetch_uids("1:*").await? {
+            result.push((uid, session.fetch_message_body(&drafts, uid).await?));
+        }
+        Ok(result)
+    }
+
     /// Mark message as read
     pub async fn mark_as_read(&self, folder: &str, uid: u32) -> Result<()> {
         let mut guard = self.require_imap().await?;
@@ -164,6 +659,163 @@ impl MailController {
         Ok(())
     }
 
+    /// Path of the account's Trash or Archive folder.
+    pub async fn special_folder(&self, role: SpecialFolder) -> Result<String> {
+        let mut guard = self.require_imap().await?;
+        let session = guard.as_mut().unwrap();
+        let folders = session.list_folders().await?;
+        role.find(&folders)
+            .ok_or_else(|| Error::Protocol(format!("No {} folder found", role.default_name())))
+    }
+
+    /// Delete messages according to the account's delete mode.
+    ///
+    /// Messages are moved to Trash, which is created first if the account
+    /// has none. They are flagged `\Deleted` and expunged only when the mode
+    /// is `Expunge` or they are already in Trash. Returns where they were
+    /// moved to, if anywhere.
+    pub async fn trash_messages(&self, folder: &str, uids: &[u32], mode: DeleteMode) -> Result<Option<MovedMessages>> {
+        let mut guard = self.require_imap().await?;
+        let session = guard.as_mut().unwrap();
+        let trash = match mode {
+            DeleteMode::Expunge => None,
+            DeleteMode::MoveToTrash => {
+                let folders = network::with_timeout(self.timeouts.read, "listing folders", session.list_folders()).await?;
+                match SpecialFolder::Trash.find(&folders) {
+                    Some(trash) => Some(trash),
+                    None => {
+                        let trash = SpecialFolder::Trash.default_name();
+                        network::with_timeout(self.timeouts.read, "creating the Trash folder", session.create_folder(trash)).await?;
+                        Some(trash.to_string())
+                    }
+                }
+            }
+        };
+        match trash {
+            Some(trash) if trash != folder => {
+                let moved = session.move_messages(folder, uids, &trash).await?;
+                tracing::info!("Moved {} messages to {}", uids.len(), trash);
+                Ok(Some(MovedMessages { folder: trash, uids: moved }))
+            }
+            _ => {
+                session.store_flags(folder, uids, "\\Deleted", true).await?;
+                session.expunge(folder, Some(uids)).await?;
+                tracing::info!("Expunged {} messages from {}", uids.len(), folder);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Move messages to the account's Archive (or All Mail) folder.
+    /// Returns where they were moved to.
+    pub async fn archive_messages(&self, folder: &str, uids: &[u32]) -> Result<MovedMessages> {
+        let mut guard = self.require_imap().await?;
+        let session = guard.as_mut().unwrap();
+        let folders = session.list_folders().await?;
+        let archive = SpecialFolder::Archive.find(&folders)
+            .ok_or_else(|| Error::Protocol("No Archive folder found".to_string()))?;
+        if archive == folder {
+            return Err(Error::Protocol(format!("Messages are already in {}", archive)));
+        }
+        let moved = session.move_messages(folder, uids, &archive).await?;
+        tracing::info!("Archived {} messages to {}", uids.len(), archive);
+        Ok(MovedMessages { folder: archive, uids: moved })
+    }
+
+    /// Set or clear the follow-up keyword on messages
+    pub async fn set_follow_up(&self, folder: &str, uids: &[u32], flagged: bool) -> Result<()> {
+        let mut guard = self.require_imap().await?;
+        let session = guard.as_mut().unwrap();
+        session.store_flags(folder, uids, FOLLOW_UP_KEYWORD, flagged).await
+    }
+
+    /// Move messages into the Snoozed folder, creating it if needed.
+    pub async fn snooze_messages(&self, folder: &str, uids: &[u32]) -> Result<()> {
+        if folder == SNOOZED_FOLDER {
+            return Err(Error::Protocol("Messages are already snoozed".to_string()));
+        }
+        let mut guard = self.require_imap().await?;
+        let session = guard.as_mut().unwrap();
+        let folders = session.list_folders().await?;
+        if !folders.iter().any(|f| f.name == SNOOZED_FOLDER) {
+            session.create_folder(SNOOZED_FOLDER).await?;
+        }
+        session.move_messages(folder, uids, SNOOZED_FOLDER).await?;
+        tracing::info!("Snoozed {} messages from {}", uids.len(), folder);
+        Ok(())
+    }
+
+    /// Move messages to the Junk folder, creating one if the server has
+    /// none. With `report`, they are tagged `$Junk` first, which servers
+    /// that learn from their users pick up. Returns where they were moved to.
+    pub async fn mark_as_spam(&self, folder: &str, uids: &[u32], report: bool) -> Result<MovedMessages> {
+        let mut guard = self.require_imap().await?;
+        let session = guard.as_mut().unwrap();
+        let folders = network::with_timeout(self.timeouts.read, "listing folders", session.list_folders()).await?;
+        let junk = match SpecialFolder::Junk.find(&folders) {
+            Some(junk) => junk,
+            None => {
+                let junk = SpecialFolder::Junk.default_name();
+                network::with_timeout(self.timeouts.read, "creating the Junk folder", session.create_folder(junk)).await?;
+                junk.to_string()
+            }
+        };
+        if junk == folder {
+            return Err(Error::Protocol(format!("Messages are already in {}", junk)));
+        }
+        if report {
+            session.store_flags(folder, uids, NOT_JUNK_KEYWORD, false).await?;
+            session.store_flags(folder, uids, JUNK_KEYWORD, true).await?;
+        }
+        let moved = network::with_timeout(self.timeouts.read, "moving messages", session.move_messages(folder, uids, &junk)).await?;
+        tracing::info!("Moved {} messages to {} as spam", uids.len(), junk);
+        Ok(MovedMessages { folder: junk, uids: moved })
+    }
+
+    /// Move messages marked as not spam back to the Inbox, tagging them
+    /// `$NotJunk` with `report`. Returns where they were moved to.
+    pub async fn mark_not_spam(&self, folder: &str, uids: &[u32], report: bool) -> Result<MovedMessages> {
+        let inbox = "INBOX";
+        if folder.eq_ignore_ascii_case(inbox) {
+            return Err(Error::Protocol(format!("Messages are already in {}", inbox)));
+        }
+        let mut guard = self.require_imap().await?;
+        let session = guard.as_mut().unwrap();
+        if report {
+            session.store_flags(folder, uids, JUNK_KEYWORD, false).await?;
+            session.store_flags(folder, uids, NOT_JUNK_KEYWORD, true).await?;
+        }
+        let moved = network::with_timeout(self.timeouts.read, "moving messages", session.move_messages(folder, uids, inbox)).await?;
+        tracing::info!("Moved {} messages from {} to {} as not spam", uids.len(), folder, inbox);
+        Ok(MovedMessages { folder: inbox.to_string(), uids: moved })
+    }
+
+    /// Return woken messages from `snoozed_folder` to `destination`, unread.
+    pub async fn wake_messages(&self, snoozed_folder: &str, uids: &[u32], destination: &str) -> Result<()> {
+        let mut guard = self.require_imap().await?;
+        let session = guard.as_mut().unwrap();
+        session.store_flags(snoozed_folder, uids, "\\Seen", false).await?;
+        session.move_messages(snoozed_folder, uids, destination).await?;
+        Ok(())
+    }
+
+    /// Permanently delete everything in Trash. Returns the Trash folder.
+    pub async fn empty_trash(&self) -> Result<String> {
+        let mut guard = self.require_imap().await?;
+        let session = guard.as_mut().unwrap();
+        let folders = session.list_folders().await?;
+        let trash = SpecialFolder::Trash.find(&folders)
+            .ok_or_else(|| Error::Protocol("No Trash folder found".to_string()))?;
+        session.select_folder(&trash).await?;
+        let uids = session.fetch_uids("1:*").await?;
+        if !uids.is_empty() {
+            session.store_flags(&trash, &uids, "\\Deleted", true).await?;
+            session.expunge(&trash, None).await?;
+        }
+        tracing::info!("Emptied {}", trash);
+        Ok(trash)
+    }
+
     /// Delete a message
     pub async fn delete_message(&self, folder: &str, uid: u32) -> Result<()> {
         let mut guard = self.require_imap().await?;
@@ -173,6 +825,76 @@ impl MailController {
         Ok(())
     }
 
+    /// Move a message to another folder
+    pub async fn move_message(&self, folder: &str, uid: u32, destination: &str) -> Result<()> {
+        let mut guard = self.require_imap().await?;
+        let session = guard.as_mut().unwrap();
+        session.move_message(folder, uid, destination).await?;
+        tracing::info!("Moved message {} to {}", uid, destination);
+        Ok(())
+    }
+
+    /// Apply a bulk action to many messages with one batched command.
+    ///
+    /// For a move, returns the UIDs the messages got in the destination,
+    /// keyed by their old UIDs (empty without UIDPLUS). Tags are local-only,
+    /// so tag actions have nothing to send to the server.
+    pub async fn apply_bulk(&self, folder: &str, uids: &[u32], action: &BulkAction) -> Result<HashMap<u32, u32>> {
+        if uids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let mut guard = self.require_imap().await?;
+        let session = guard.as_mut().unwrap();
+        let (flag, add) = match action {
+            BulkAction::MarkRead => ("\\Seen", true),
+            BulkAction::MarkUnread => ("\\Seen", false),
+            BulkAction::Star => ("\\Flagged", true),
+            BulkAction::Unstar => ("\\Flagged", false),
+            BulkAction::Delete => ("\\Deleted", true),
+            BulkAction::Move { folder: destination, .. } => {
+                return session.move_messages(folder, uids, destination).await;
+            }
+            BulkAction::AddTag(_) | BulkAction::RemoveTag(_) => return Ok(HashMap::new()),
+        };
+        session.store_flags(folder, uids, flag, add).await?;
+        Ok(HashMap::new())
+    }
+
+    /// Replay the inverse of an undoable action on the server.
+    ///
+    /// Tags are local-only, so tag removals have nothing to revert remotely.
+    pub async fn revert_action(&self, action: &UndoableAction) -> Result<()> {
+        match action {
+            UndoableAction::Delete { uid, folder, .. } => {
+                let mut guard = self.require_imap().await?;
+                let session = guard.as_mut().unwrap();
+                session.undelete_message(folder, *uid).await
+            }
+            UndoableAction::Move { to_uid, message_id_header, from_folder, to_folder, .. } => {
+                let mut guard = self.require_imap().await?;
+                let session = guard.as_mut().unwrap();
+                // The move gave the message a new UID in `to_folder`. Without
+                // UIDPLUS it wasn't reported, so look the message up there.
+                let uid = match to_uid {
+                    Some(uid) => *uid,
+                    None if message_id_header.is_empty() => {
+                        return Err(Error::NotFound(format!("Can't find the moved message in {}", to_folder)));
+                    }
+                    None => session
+                        .search_header(to_folder, "Message-ID", message_id_header)
+                        .await?
+                        .into_iter()
+                        .max()
+                        .ok_or_else(|| Error::NotFound(format!("{} is no longer in {}", message_id_header, to_folder)))?,
+                };
+                session.move_messages(to_folder, &[uid], from_folder).await?;
+                tracing::info!("Moved message {} back from {} to {}", uid, to_folder, from_folder);
+                Ok(())
+            }
+            UndoableAction::RemoveTag { .. } => Ok(()),
+        }
+    }
+
     /// Check if connected
     pub async fn is_connected(&self) -> bool {
         let imap_session = self.imap_session.lock().await;
@@ -193,6 +915,7 @@ impl MailController {
             port,
             use_tls,
             username,
+            timeouts: self.timeouts,
         };
         let client = Pop3Client::new(config)?;
         let session = client.connect(&password).await?;
@@ -264,12 +987,89 @@ impl MailController {
     }
 }
 
+/// Whether `path` is the Inbox, a folder with a special-use role (Sent,
+/// Drafts, Trash, Junk, Archive) or the Snoozed folder, which the app
+/// relies on and won't rename or delete
+pub fn is_special_use(folders: &[ImapFolder], path: &str) -> bool {
+    if path.eq_ignore_ascii_case("INBOX") || path == SNOOZED_FOLDER {
+        return true;
+    }
+    let flagged = folders
+        .iter()
+        .filter(|f| f.name == path)
+        .any(|f| SpecialFolder::ALL.iter().any(|role| role.flagged(f)));
+    flagged || SpecialFolder::ALL.iter().any(|role| role.find(folders).as_deref() == Some(path))
+}
+
+/// Hierarchy separator the server reported for `path`, or for its other
+/// folders when `path` isn't listed (or is `None`). Defaults to `/`.
+fn hierarchy_delimiter(folders: &[ImapFolder], path: Option<&str>) -> String {
+    path.and_then(|path| folders.iter().find(|f| f.name == path))
+        .or_else(|| folders.first())
+        .map(|f| f.delimiter.clone())
+        .unwrap_or_else(|| "/".to_string())
+}
+
+/// Path of the folder holding `path`, if it isn't at the top level
+fn folder_parent<'a>(path: &'a str, delimiter: &str) -> Option<&'a str> {
+    if delimiter.is_empty() {
+        return None;
+    }
+    path.rsplit_once(delimiter).map(|(parent, _)| parent)
+}
+
+/// Path of a folder called `name` inside `parent`, joined with the server's
+/// hierarchy separator
+pub fn child_folder_path(parent: Option<&str>, name: &str, delimiter: &str) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(Error::Config("Enter a folder name".into()));
+    }
+    if !delimiter.is_empty() && name.contains(delimiter) {
+        return Err(Error::Config(format!("Folder names can't contain \"{}\"", delimiter)));
+    }
+    if name.contains(['*', '%']) {
+        return Err(Error::Config("Folder names can't contain * or %".into()));
+    }
+    match parent {
+        None => Ok(name.to_string()),
+        Some(_) if delimiter.is_empty() => Err(Error::Config("This server doesn't support subfolders".into())),
+        Some(parent) => Ok(format!("{}{}{}", parent, delimiter, name)),
+    }
+}
+
 impl Default for MailController {
     fn default() -> Self {
         Self::new()
     }
 }
 
+/// Messages moved on the server: the folder they went to and their UIDs
+/// there, keyed by their old UIDs. The UIDs come from the server's
+/// `COPYUID` and are empty without UIDPLUS.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MovedMessages {
+    pub folder: String,
+    pub uids: HashMap<u32, u32>,
+}
+
+impl MovedMessages {
+    /// Messages moved to `folder` whose new UIDs aren't known, as when
+    /// only the local cache changes
+    pub fn without_uids(folder: String) -> Self {
+        Self { folder, uids: HashMap::new() }
+    }
+}
+
+/// A message as `fetch_message_preview` downloaded it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FetchedBody {
+    /// The whole raw message
+    Full(String),
+    /// Only the text of a large message, `full_size` bytes in all
+    TextOnly { text: String, full_size: u64 },
+}
+
 /// Message preview for UI display
 #[derive(Debug, Clone)]
 pub struct MessagePreview {
@@ -277,8 +1077,56 @@ pub struct MessagePreview {
     pub subject: String,
     pub from: String,
     pub date: String,
+    /// `Message-ID` without angle brackets, empty if the message has none
+    pub message_id: String,
     pub read: bool,
     pub starred: bool,
+    /// Flagged for follow-up on the server
+    pub follow_up: bool,
+    /// Mailing list the message came from (`List-Id`)
+    pub list_id: Option<String>,
+    /// Attached files, from the message's `BODYSTRUCTURE`; none are
+    /// downloaded
+    pub attachments: Vec<BodyPart>,
+}
+
+impl MessagePreview {
+    /// The attachments as cache rows
+    pub fn cached_attachments(&self) -> Vec<CachedAttachment> {
+        self.attachments
+            .iter()
+            .map(|part| CachedAttachment {
+                id: 0,
+                message_id: 0,
+                filename: part.display_name(),
+                mime_type: part.mime_type.clone(),
+                size: part.size as i64,
+                content_id: part.content_id.clone(),
+                section: Some(part.section.clone()),
+            })
+            .collect()
+    }
+
+    /// The cache row for this message in `folder_id`, headers only
+    pub fn cached_header(&self, folder_id: i64) -> CachedMessage {
+        CachedMessage {
+            id: 0,
+            uid: self.uid,
+            folder_id,
+            message_id: self.message_id.clone(),
+            subject: self.subject.clone(),
+            from_addr: self.from.clone(),
+            to_addr: String::new(),
+            cc: None,
+            date: self.date.clone(),
+            body_plain: None,
+            body_html: None,
+            read: self.read,
+            starred: self.starred,
+            deleted: false,
+            priority: MessagePriority::Normal,
+        }
+    }
 }
 
 /// POP3 message preview for UI display
@@ -357,6 +1205,358 @@ mod tests {
         assert!(body.contains("Subject: POP3 Test Message"));
     }
 
+    #[tokio::test]
+    async fn test_append_to_sent_uses_special_use_folder() {
+        let controller = MailController::new();
+        assert!(controller.append_to_sent(b"Subject: hi\r\n\r\nbody").await.is_err());
+
+        controller
+            .connect_imap(
+                "imap.example.com".to_string(),
+                993,
+                "test@example.com".to_string(),
+                "password".to_string(),
+                true,
+            )
+            .await
+            .unwrap();
+        let folder = controller.append_to_sent(b"Subject: hi\r\n\r\nbody").await.unwrap();
+        assert_eq!(folder, "Sent");
+    }
+
+    #[tokio::test]
+    async fn test_small_messages_are_fetched_whole() {
+        let controller = MailController::new();
+        assert!(controller.fetch_message_preview("INBOX", 1).await.is_err());
+
+        controller
+            .connect_imap(
+                "imap.example.com".to_string(),
+                993,
+                "test@example.com".to_string(),
+                "password".to_string(),
+                true,
+            )
+            .await
+            .unwrap();
+        let whole = controller.fetch_message_body("INBOX", 1).await.unwrap();
+        assert_eq!(controller.fetch_message_preview("INBOX", 1).await.unwrap(), FetchedBody::Full(whole));
+    }
+
+    #[tokio::test]
+    async fn test_attachments_listed_without_downloading() {
+        let controller = MailController::new();
+        controller
+            .connect_imap(
+                "imap.example.com".to_string(),
+                993,
+                "test@example.com".to_string(),
+                "password".to_string(),
+                true,
+            )
+            .await
+            .unwrap();
+        let previews = controller.fetch_messages("INBOX").await.unwrap();
+        assert!(previews.iter().filter(|p| p.uid != 3).all(|p| p.attachments.is_empty()));
+        let update = previews.iter().find(|p| p.uid == 3).unwrap();
+        let cached = update.cached_attachments();
+        assert_eq!(cached.len(), 1);
+        assert_eq!(cached[0].filename, "release-notes.pdf");
+        assert_eq!(cached[0].mime_type, "application/pdf");
+        assert_eq!(cached[0].size, 48200);
+        assert_eq!(cached[0].section.as_deref(), Some("2"));
+    }
+
+    #[tokio::test]
+    async fn test_mark_as_spam() {
+        let controller = MailController::new();
+        assert!(controller.mark_as_spam("INBOX", &[1], true).await.is_err());
+
+        controller
+            .connect_imap(
+                "imap.example.com".to_string(),
+                993,
+                "test@example.com".to_string(),
+                "password".to_string(),
+                true,
+            )
+            .await
+            .unwrap();
+        // No Junk folder on the server yet, so one is made
+        assert_eq!(controller.mark_as_spam("INBOX", &[1, 2], true).await.unwrap().folder, "Junk");
+        assert_eq!(controller.mark_not_spam("Junk", &[1], true).await.unwrap().folder, "INBOX");
+        assert!(controller.mark_not_spam("INBOX", &[1], false).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_folder_subscriptions() {
+        let controller = MailController::new();
+        assert!(controller.fetch_server_folders().await.is_err());
+
+        controller
+            .connect_imap(
+                "imap.example.com".to_string(),
+                993,
+                "test@example.com".to_string(),
+                "password".to_string(),
+                true,
+            )
+            .await
+            .unwrap();
+        assert!(controller.fetch_server_folders().await.unwrap().iter().all(|f| f.subscribed));
+
+        controller.set_subscribed("Archive", false).await.unwrap();
+        let folders = controller.fetch_server_folders().await.unwrap();
+        let archive = folders.iter().find(|f| f.path == "Archive").unwrap();
+        assert!(!archive.subscribed);
+        assert_eq!(archive.folder_type, FolderType::Archive);
+        let inbox = folders.iter().find(|f| f.path == "INBOX").unwrap();
+        assert!(inbox.subscribed);
+        assert_eq!(inbox.folder_type, FolderType::Inbox);
+    }
+
+    #[tokio::test]
+    async fn test_draft_sync_requires_drafts_folder_access() {
+        let controller = MailController::new();
+        assert!(controller.save_draft_to_server("d1", b"Subject: hi\r\n\r\n", None).await.is_err());
+
+        controller
+            .connect_imap(
+                "imap.example.com".to_string(),
+                993,
+                "test@example.com".to_string(),
+                "password".to_string(),
+                true,
+            )
+            .await
+            .unwrap();
+        controller.save_draft_to_server("d1", b"Subject: hi\r\n\r\n", Some(3)).await.unwrap();
+        let drafts = controller.fetch_server_drafts().await.unwrap();
+        assert!(!drafts.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_trash_and_archive_use_special_folders() {
+        let controller = MailController::new();
+        assert!(controller.trash_messages("INBOX", &[1], DeleteMode::MoveToTrash).await.is_err());
+
+        controller
+            .connect_imap(
+                "imap.example.com".to_string(),
+                993,
+                "test@example.com".to_string(),
+                "password".to_string(),
+                true,
+            )
+            .await
+            .unwrap();
+        let moved = controller.trash_messages("INBOX", &[1], DeleteMode::MoveToTrash).await.unwrap();
+        assert_eq!(moved.map(|m| m.folder).as_deref(), Some("Trash"));
+        // Deleting from Trash itself, or in expunge mode, removes for good
+        assert_eq!(controller.trash_messages("Trash", &[1], DeleteMode::MoveToTrash).await.unwrap(), None);
+        assert_eq!(controller.trash_messages("INBOX", &[1], DeleteMode::Expunge).await.unwrap(), None);
+        assert_eq!(controller.archive_messages("INBOX", &[2]).await.unwrap().folder, "Archive");
+        assert!(controller.archive_messages("Archive", &[2]).await.is_err());
+        assert_eq!(controller.empty_trash().await.unwrap(), "Trash");
+    }
+
+    #[tokio::test]
+    async fn test_folder_management() {
+        let controller = MailController::new();
+        assert!(controller.create_folder(None, "Projects").await.is_err());
+
+        controller
+            .connect_imap(
+                "imap.example.com".to_string(),
+                993,
+                "test@example.com".to_string(),
+                "password".to_string(),
+                true,
+            )
+            .await
+            .unwrap();
+        assert_eq!(controller.create_folder(None, " Projects ").await.unwrap(), "Projects");
+        assert_eq!(controller.create_folder(Some("Projects"), "2024").await.unwrap(), "Projects/2024");
+        assert!(controller.create_folder(None, "Trash").await.is_err());
+        assert!(controller.create_folder(None, "a/b").await.is_err());
+        assert_eq!(controller.rename_folder("Projects/2024", "2025").await.unwrap(), "Projects/2025");
+        assert_eq!(controller.rename_folder("Projects", "Work").await.unwrap(), "Work");
+        controller.delete_folder("Work").await.unwrap();
+        assert_eq!(controller.folder_details("Sent").await.unwrap(), ("/".to_string(), true));
+        assert_eq!(controller.folder_details("Work").await.unwrap(), ("/".to_string(), false));
+        for special in ["INBOX", "Sent", "Drafts", "Trash", "Archive", SNOOZED_FOLDER] {
+            assert!(controller.delete_folder(special).await.is_err(), "{}", special);
+            assert!(controller.rename_folder(special, "Other").await.is_err(), "{}", special);
+        }
+    }
+
+    #[test]
+    fn test_child_folder_paths() {
+        assert_eq!(child_folder_path(None, "Lists", ".").unwrap(), "Lists");
+        assert_eq!(child_folder_path(Some("INBOX"), "Lists", ".").unwrap(), "INBOX.Lists");
+        assert!(child_folder_path(Some("INBOX"), "a.b", ".").is_err());
+        assert!(child_folder_path(None, "  ", "/").is_err());
+        assert!(child_folder_path(None, "All*", "/").is_err());
+        assert!(child_folder_path(Some("INBOX"), "Lists", "").is_err());
+        assert_eq!(child_folder_path(None, "a/b", "").unwrap(), "a/b");
+        assert_eq!(folder_parent("INBOX.Lists.rust", "."), Some("INBOX.Lists"));
+        assert_eq!(folder_parent("Lists", "/"), None);
+        assert_eq!(folder_parent("Lists/rust", ""), None);
+    }
+
+    #[test]
+    fn test_special_folder_fallback_names() {
+        let folder = |name: &str, flags: &[&str]| ImapFolder {
+            name: name.to_string(),
+            delimiter: "/".to_string(),
+            flags: flags.iter().map(|f| f.to_string()).collect(),
+        };
+        let gmail = vec![
+            folder("INBOX", &[]),
+            folder("[Gmail]/All Mail", &["\\All"]),
+            folder("[Gmail]/Bin", &[]),
+        ];
+        assert_eq!(SpecialFolder::Archive.find(&gmail).as_deref(), Some("[Gmail]/All Mail"));
+        assert_eq!(SpecialFolder::Trash.find(&gmail).as_deref(), Some("[Gmail]/Bin"));
+        assert_eq!(SpecialFolder::Trash.find(&[folder("INBOX", &[])]), None);
+    }
+
+    #[test]
+    fn test_special_folder_roles() {
+        let folder = |name: &str, flags: &[&str]| ImapFolder {
+            name: name.to_string(),
+            delimiter: "/".to_string(),
+            flags: flags.iter().map(|f| f.to_string()).collect(),
+        };
+        // Gmail's XLIST flags
+        let xlist = vec![
+            folder("Inbox", &["\\Inbox"]),
+            folder("[Gmail]/Sent Mail", &["\\Sent"]),
+            folder("[Gmail]/Spam", &["\\Spam"]),
+            folder("[Gmail]/All Mail", &["\\AllMail"]),
+            folder("Spam", &[]),
+        ];
+        assert_eq!(SpecialFolder::Junk.find(&xlist).as_deref(), Some("[Gmail]/Spam"));
+        assert_eq!(SpecialFolder::Archive.find(&xlist).as_deref(), Some("[Gmail]/All Mail"));
+        assert_eq!(SpecialFolder::Sent.find(&xlist).as_deref(), Some("[Gmail]/Sent Mail"));
+
+        // \Archive wins over \All, and a name never picks a folder
+        // flagged with another role
+        let dovecot = vec![
+            folder("INBOX", &[]),
+            folder("All", &["\\All"]),
+            folder("Archives", &["\\Archive"]),
+            folder("Trash", &["\\Junk"]),
+            folder("Deleted Messages", &[]),
+        ];
+        assert_eq!(SpecialFolder::Archive.find(&dovecot).as_deref(), Some("Archives"));
+        assert_eq!(SpecialFolder::Trash.find(&dovecot).as_deref(), Some("Deleted Messages"));
+
+        let types = folder_types(&dovecot);
+        assert!(types.contains(&("INBOX".to_string(), FolderType::Inbox)));
+        assert!(types.contains(&("Archives".to_string(), FolderType::Archive)));
+        assert!(types.contains(&("All".to_string(), FolderType::Custom)));
+        assert!(types.contains(&("Trash".to_string(), FolderType::Spam)));
+        assert!(types.contains(&("Deleted Messages".to_string(), FolderType::Trash)));
+
+        assert!(is_special_use(&dovecot, "All"));
+        assert!(is_special_use(&dovecot, "Deleted Messages"));
+    }
+
+    #[tokio::test]
+    async fn test_apply_bulk_requires_connection() {
+        let controller = MailController::new();
+        assert!(controller.apply_bulk("INBOX", &[1, 2], &BulkAction::MarkRead).await.is_err());
+
+        controller
+            .connect_imap(
+                "imap.example.com".to_string(),
+                993,
+                "test@example.com".to_string(),
+                "password".to_string(),
+                true,
+            )
+            .await
+            .unwrap();
+        controller.apply_bulk("INBOX", &[1, 2, 3], &BulkAction::Star).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_undo_move_uses_destination_uid() {
+        let controller = MailController::new();
+        controller
+            .connect_imap(
+                "imap.example.com".to_string(),
+                993,
+                "test@example.com".to_string(),
+                "password".to_string(),
+                true,
+            )
+            .await
+            .unwrap();
+        let action = BulkAction::Move { folder_id: 2, folder: "Archive".to_string() };
+        let moved = controller.apply_bulk("INBOX", &[2], &action).await.unwrap();
+        let to_uid = moved[&2];
+        assert_ne!(to_uid, 2, "the server numbers moved messages afresh");
+
+        let undo = UndoableAction::Move {
+            message_id: 1, uid: 2, to_uid: Some(to_uid),
+            message_id_header: "moved@example.com".to_string(),
+            from_folder_id: 1, from_folder: "INBOX".to_string(),
+            to_folder_id: 2, to_folder: "Archive".to_string(),
+        };
+        controller.revert_action(&undo).await.unwrap();
+
+        // Without a reported UID the message is looked up by its Message-ID,
+        // never moved by its old UID
+        let unreported = UndoableAction::Move {
+            message_id: 1, uid: 2, to_uid: None,
+            message_id_header: "moved@example.com".to_string(),
+            from_folder_id: 1, from_folder: "INBOX".to_string(),
+            to_folder_id: 2, to_folder: "Archive".to_string(),
+        };
+        assert!(matches!(controller.revert_action(&unreported).await, Err(Error::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_undo_move_finds_synced_message_by_message_id() {
+        use crate::data::message_cache::MessageCache;
+        use std::time::{SystemTime, UNIX_EPOCH};
+
+        let controller = MailController::new();
+        controller
+            .connect_imap(
+                "imap.example.com".to_string(),
+                993,
+                "test@example.com".to_string(),
+                "password".to_string(),
+                true,
+            )
+            .await
+            .unwrap();
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let temp_dir = std::env::temp_dir().join(format!("wixen_mail_test_undo_sync_{}", nanos));
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+        let inbox = cache.ensure_folder("acc", "INBOX").unwrap();
+        let archive = cache.ensure_folder("acc", "Archive").unwrap();
+
+        // Headers cached the way a folder sync caches them keep their Message-ID
+        let previews = controller.fetch_messages("INBOX").await.unwrap();
+        let headers: Vec<CachedMessage> = previews.iter().map(|p| p.cached_header(inbox.id)).collect();
+        cache.save_message_headers(&headers).unwrap();
+        let cached = cache.get_messages_for_folder(inbox.id, "acc").unwrap();
+        let message = cached.iter().find(|m| m.uid == 2).unwrap();
+        assert!(!message.message_id.is_empty());
+
+        let action = BulkAction::Move { folder_id: archive.id, folder: "Archive".to_string() };
+        let undo = action.apply_with_undo(&cache, &[message.id], "INBOX").unwrap();
+        assert!(matches!(&undo[..], [UndoableAction::Move { to_uid: None, .. }]));
+        controller.apply_bulk("INBOX", &[2], &action).await.unwrap();
+
+        // The server didn't report the new UID, so undo searches Archive
+        controller.revert_action(&undo[0]).await.unwrap();
+    }
+
     #[tokio::test]
     async fn test_send_email_uses_smtp() {
         let controller = MailController::new();
@@ -366,9 +1566,21 @@ mod tests {
             username: "test@example.com".to_string(),
             password: "password".to_string(),
             use_tls: true,
+            from: None,
+            from_name: None,
+            reply_to: None,
+            envelope_from: None,
             to: vec!["to@example.com".to_string()],
+            cc: vec![],
+            bcc: vec![],
             subject: "Hello".to_string(),
             body: "Body".to_string(),
+            request_receipt: false,
+            priority: MessagePriority::Normal,
+            timeouts: NetworkTimeouts::default(),
+            pinned_fingerprint: None,
+            #[cfg(feature = "openpgp")]
+            pgp: None,
         };
         let result = controller.send_email(&req).await;
         assert!(result.is_err()); // expected in tests due placeholder/non-routable SMTP server
@@ -3,11 +3,12 @@
 //! Manages email messages, threads, and message operations.
 
 use crate::common::{
-    types::{Attachment, EmailAddress, Id, MessageBody},
+    types::{Attachment, EmailAddress, Id, MessageBody, MessagePriority},
     Result,
 };
 use crate::data::message_cache::CachedMessage;
 use chrono::{DateTime, Utc};
+use mail_parser::MimeHeaders;
 
 /// Message flags
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -149,6 +150,400 @@ impl From<CachedMessage> for Message {
     }
 }
 
+/// Result of one authentication method (RFC 8601 §2.7)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthOutcome {
+    Pass,
+    /// `fail`, `softfail`, `permerror` or `policy`
+    Fail,
+    /// `none`, `neutral`, `temperror` or an unknown result
+    Neutral,
+}
+
+impl AuthOutcome {
+    fn parse(result: &str) -> Self {
+        match result {
+            "pass" => AuthOutcome::Pass,
+            "fail" | "softfail" | "permerror" | "policy" => AuthOutcome::Fail,
+            _ => AuthOutcome::Neutral,
+        }
+    }
+
+    fn label(outcome: Option<Self>) -> &'static str {
+        match outcome {
+            Some(AuthOutcome::Pass) => "pass",
+            Some(AuthOutcome::Fail) => "fail",
+            Some(AuthOutcome::Neutral) => "neutral",
+            None => "none",
+        }
+    }
+}
+
+/// Parsed `Authentication-Results` header (RFC 8601)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthenticationResults {
+    /// Server that performed the checks
+    pub authserv_id: String,
+    pub spf: Option<AuthOutcome>,
+    /// Passes if any DKIM signature passed
+    pub dkim: Option<AuthOutcome>,
+    pub dmarc: Option<AuthOutcome>,
+}
+
+impl AuthenticationResults {
+    /// Parse a header value; `None` if it has no authserv-id.
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = strip_comments(value);
+        let mut sections = value.split(';').map(str::trim);
+        // The authserv-id may be followed by a version number
+        let authserv_id = sections.next()?.split_whitespace().next()?.to_string();
+        let mut results = Self { authserv_id, spf: None, dkim: None, dmarc: None };
+        for section in sections {
+            let Some((method, result)) = section.split_whitespace().next().and_then(|t| t.split_once('=')) else {
+                continue;
+            };
+            let outcome = AuthOutcome::parse(&result.to_ascii_lowercase());
+            let slot = match method.to_ascii_lowercase().as_str() {
+                "spf" => &mut results.spf,
+                "dkim" => &mut results.dkim,
+                "dmarc" => &mut results.dmarc,
+                _ => continue,
+            };
+            if *slot != Some(AuthOutcome::Pass) {
+                *slot = Some(outcome);
+            }
+        }
+        Some(results)
+    }
+
+    fn any_failed(&self) -> bool {
+        [self.spf, self.dkim, self.dmarc].contains(&Some(AuthOutcome::Fail))
+    }
+
+    /// DMARC passed, or with no DMARC result both SPF and DKIM passed
+    fn verified(&self) -> bool {
+        match self.dmarc {
+            Some(dmarc) => dmarc == AuthOutcome::Pass,
+            None => self.spf == Some(AuthOutcome::Pass) && self.dkim == Some(AuthOutcome::Pass),
+        }
+    }
+
+    /// "SPF pass, DKIM fail, DMARC none"
+    pub fn summary(&self) -> String {
+        format!(
+            "SPF {}, DKIM {}, DMARC {}",
+            AuthOutcome::label(self.spf),
+            AuthOutcome::label(self.dkim),
+            AuthOutcome::label(self.dmarc)
+        )
+    }
+}
+
+/// Sender authentication for a message, taken from its topmost
+/// `Authentication-Results` header: the one added by the receiving
+/// server. Lower headers could have been written by the sender.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SenderAuthentication {
+    /// `None` when the message carries no authentication results
+    pub results: Option<AuthenticationResults>,
+}
+
+impl SenderAuthentication {
+    /// Check a raw message. `None` if it has no header block to check.
+    pub fn of_message(raw: &str) -> Option<Self> {
+        let headers = unfolded_headers(raw);
+        headers.iter().find(|(name, _)| name.eq_ignore_ascii_case("from"))?;
+        let results = headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("authentication-results"))
+            .and_then(|(_, value)| AuthenticationResults::parse(value));
+        Some(Self { results })
+    }
+
+    /// Whether the sender's domain is confirmed
+    pub fn verified(&self) -> bool {
+        self.results.as_ref().is_some_and(|r| r.verified())
+    }
+
+    /// Warning to show when the sender isn't verified
+    pub fn warning(&self) -> Option<String> {
+        let Some(results) = &self.results else {
+            return Some("This message has no authentication results, so the sender can't be verified.".to_string());
+        };
+        if results.any_failed() {
+            Some(format!("Sender authentication failed ({}). This message may be forged.", results.summary()))
+        } else if !results.verified() {
+            Some(format!("The sender could not be verified ({}).", results.summary()))
+        } else {
+            None
+        }
+    }
+}
+
+/// Priority a raw message is marked with. `None` if it has no header
+/// block (e.g. only a body was downloaded).
+pub fn message_priority(raw: &str) -> Option<MessagePriority> {
+    let headers = unfolded_headers(raw);
+    let value = |wanted: &str| {
+        headers.iter().find(|(name, _)| name.eq_ignore_ascii_case(wanted)).map(|(_, v)| v.as_str())
+    };
+    value("from")?;
+    Some(MessagePriority::from_headers(value("x-priority"), value("importance")))
+}
+
+/// A recipient a delivery status notification reports as failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailedRecipient {
+    pub address: String,
+    /// Enhanced status code, e.g. `5.1.1`
+    pub status: Option<String>,
+    /// The remote server's reply, e.g. `550 5.1.1 User unknown`
+    pub diagnostic: Option<String>,
+}
+
+impl FailedRecipient {
+    /// "bob@example.com (550 5.1.1 User unknown)"
+    fn label(&self) -> String {
+        match self.diagnostic.as_ref().or(self.status.as_ref()) {
+            Some(reason) => format!("{} ({})", self.address, reason),
+            None => self.address.clone(),
+        }
+    }
+}
+
+/// A bounce: a `multipart/report; report-type=delivery-status` message
+/// saying a sent message couldn't be delivered (RFC 3464, RFC 6522)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeliveryFailure {
+    pub recipients: Vec<FailedRecipient>,
+    /// Message-ID of the undelivered message, without angle brackets
+    pub original_message_id: Option<String>,
+    pub original_subject: Option<String>,
+    /// Text of the returned copy, when the whole message came back
+    pub original_text: Option<String>,
+}
+
+impl DeliveryFailure {
+    /// Read a raw message. `None` unless it is a delivery report with at
+    /// least one failed recipient; delay and success reports are ignored.
+    pub fn of_message(raw: &str) -> Option<Self> {
+        let parsed = mail_parser::MessageParser::default().parse(raw.as_bytes())?;
+        let content_type = parsed.content_type()?;
+        let is_report = content_type.ctype().eq_ignore_ascii_case("multipart")
+            && content_type.subtype().is_some_and(|s| s.eq_ignore_ascii_case("report"))
+            && content_type.attribute("report-type").is_some_and(|t| t.eq_ignore_ascii_case("delivery-status"));
+        if !is_report {
+            return None;
+        }
+
+        let mut failure = Self { recipients: Vec::new(), original_message_id: None, original_subject: None, original_text: None };
+        for part in &parsed.parts {
+            let Some(part_type) = part.content_type() else { continue };
+            let kind = format!("{}/{}", part_type.ctype(), part_type.subtype().unwrap_or_default()).to_ascii_lowercase();
+            match kind.as_str() {
+                "message/delivery-status" => {
+                    failure.recipients.extend(failed_recipients(&String::from_utf8_lossy(part.contents())));
+                }
+                "message/rfc822" => {
+                    if let Some(original) = part.message() {
+                        failure.original_message_id = original.message_id().map(str::to_string);
+                        failure.original_subject = original.subject().map(str::to_string);
+                        failure.original_text = original
+                            .body_text(0)
+                            .map(|text| text.into_owned())
+                            .filter(|text| !text.trim().is_empty());
+                    }
+                }
+                // Only the headers of the original came back
+                "text/rfc822-headers" => {
+                    let headers = String::from_utf8_lossy(part.contents()).into_owned();
+                    let field = |name: &str| {
+                        unfolded_headers(&headers)
+                            .into_iter()
+                            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                            .map(|(_, value)| value)
+                    };
+                    failure.original_message_id = field("message-id")
+                        .map(|id| id.trim_matches(|c: char| c == '<' || c == '>' || c.is_whitespace()).to_string());
+                    failure.original_subject = field("subject");
+                }
+                _ => {}
+            }
+        }
+        (!failure.recipients.is_empty()).then_some(failure)
+    }
+
+    /// "Delivery failed to bob@example.com (550 5.1.1 User unknown) for message "Lunch""
+    pub fn summary(&self) -> String {
+        let recipients: Vec<String> = self.recipients.iter().map(FailedRecipient::label).collect();
+        let message = match (&self.original_subject, &self.original_message_id) {
+            (Some(subject), _) => format!("\"{}\"", subject),
+            (None, Some(id)) => format!("<{}>", id),
+            (None, None) => "(unknown)".to_string(),
+        };
+        format!("Delivery failed to {} for message {}", recipients.join(", "), message)
+    }
+}
+
+/// How to leave a mailing list, from its `List-Unsubscribe` header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Unsubscribe {
+    /// POST to this https URL, with no further steps (RFC 8058)
+    OneClick(String),
+    /// Send this message
+    Mail { to: String, subject: String, body: String },
+    /// Open this page in the browser
+    Web(String),
+}
+
+/// A message from a mailing list, going by its `List-Id` and
+/// `List-Unsubscribe` headers (RFC 2919, RFC 2369)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MailingList {
+    /// The list identifier, e.g. `announce.example.com`
+    pub id: Option<String>,
+    /// The best way offered to unsubscribe: one-click, then mail, then web
+    pub unsubscribe: Option<Unsubscribe>,
+}
+
+impl MailingList {
+    /// Read a raw message. `None` unless it carries list headers.
+    pub fn of_message(raw: &str) -> Option<Self> {
+        let headers = unfolded_headers(raw);
+        let field = |wanted: &str| {
+            headers.iter().find(|(name, _)| name.eq_ignore_ascii_case(wanted)).map(|(_, v)| v.as_str())
+        };
+        let id = field("list-id").and_then(list_id);
+        let one_click = field("list-unsubscribe-post")
+            .is_some_and(|v| v.trim().eq_ignore_ascii_case("List-Unsubscribe=One-Click"));
+        let unsubscribe = field("list-unsubscribe").and_then(|value| {
+            let urls: Vec<url::Url> = bracketed(value).filter_map(|u| url::Url::parse(u).ok()).collect();
+            let https = urls.iter().find(|u| u.scheme() == "https");
+            let web = https.or_else(|| urls.iter().find(|u| u.scheme() == "http"));
+            https
+                .filter(|_| one_click)
+                .map(|u| Unsubscribe::OneClick(u.to_string()))
+                .or_else(|| urls.iter().find(|u| u.scheme() == "mailto").and_then(mailto))
+                .or_else(|| web.map(|u| Unsubscribe::Web(u.to_string())))
+        });
+        (id.is_some() || unsubscribe.is_some()).then_some(Self { id, unsubscribe })
+    }
+}
+
+/// `announce.example.com` from `Announcements <announce.example.com>`.
+/// Lists without the angle brackets get the whole value.
+fn list_id(value: &str) -> Option<String> {
+    let value = strip_comments(value);
+    let id = bracketed(&value).next().unwrap_or(value.trim()).trim();
+    (!id.is_empty()).then(|| id.to_ascii_lowercase())
+}
+
+/// The `<...>` parts of a header value, in order
+fn bracketed(value: &str) -> impl Iterator<Item = &str> {
+    value.split('<').skip(1).filter_map(|part| part.split_once('>')).map(|(inside, _)| inside.trim())
+}
+
+/// The message a `mailto:` unsubscribe link asks for
+fn mailto(url: &url::Url) -> Option<Unsubscribe> {
+    let mut to = percent_decode(url.path());
+    let (mut subject, mut body) = (String::new(), String::new());
+    for (key, value) in url.query_pairs() {
+        match key.to_ascii_lowercase().as_str() {
+            "subject" => subject = value.into_owned(),
+            "body" => body = value.into_owned(),
+            "to" if to.is_empty() => to = value.into_owned(),
+            _ => {}
+        }
+    }
+    if to.is_empty() {
+        return None;
+    }
+    if subject.is_empty() {
+        subject = "unsubscribe".to_string();
+    }
+    Some(Unsubscribe::Mail { to, subject, body })
+}
+
+fn percent_decode(value: &str) -> String {
+    url::form_urlencoded::parse(format!("v={}", value.replace('+', "%2B")).as_bytes())
+        .next()
+        .map(|(_, v)| v.into_owned())
+        .unwrap_or_default()
+}
+
+/// Failed recipients in the body of a `message/delivery-status` part: a
+/// block of per-message fields, then a block per recipient. Only recipient
+/// blocks have an `Action` field.
+fn failed_recipients(status: &str) -> Vec<FailedRecipient> {
+    let status = status.replace("\r\n", "\n");
+    status
+        .split("\n\n")
+        .filter_map(|block| {
+            let fields = unfolded_headers(block.trim_start_matches('\n'));
+            let field = |name: &str| {
+                fields.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str())
+            };
+            if !field("action")?.trim().eq_ignore_ascii_case("failed") {
+                return None;
+            }
+            // Typed values look like `rfc822; bob@example.com`
+            let untyped = |value: &str| {
+                let value = value.split_once(';').map_or(value, |(_, v)| v);
+                value.trim().trim_matches(|c| c == '<' || c == '>').to_string()
+            };
+            Some(FailedRecipient {
+                address: untyped(field("final-recipient").or_else(|| field("original-recipient"))?),
+                status: field("status").map(|s| s.trim().to_string()),
+                diagnostic: field("diagnostic-code").map(untyped).filter(|d| !d.is_empty()),
+            })
+        })
+        .collect()
+}
+
+/// Header fields of a raw message in order, continuation lines unfolded.
+/// Stops at the first line that isn't a header.
+fn unfolded_headers(raw: &str) -> Vec<(&str, String)> {
+    let mut headers: Vec<(&str, String)> = Vec::new();
+    for line in raw.lines() {
+        if line.starts_with([' ', '\t']) {
+            match headers.last_mut() {
+                Some((_, value)) => {
+                    value.push(' ');
+                    value.push_str(line.trim());
+                    continue;
+                }
+                None => break,
+            }
+        }
+        match line.split_once(':') {
+            Some((name, value)) if !name.is_empty() && !name.contains(char::is_whitespace) => {
+                headers.push((name, value.trim().to_string()));
+            }
+            _ => break,
+        }
+    }
+    headers
+}
+
+/// Remove `(comments)` from a structured header value
+fn strip_comments(value: &str) -> String {
+    let mut depth = 0usize;
+    value
+        .chars()
+        .filter(|&c| {
+            match c {
+                '(' => depth += 1,
+                ')' if depth > 0 => {
+                    depth -= 1;
+                    return false;
+                }
+                _ => {}
+            }
+            depth == 0
+        })
+        .collect()
+}
+
 /// Manages email messages
 #[derive(Default)]
 pub struct MessageManager {
@@ -277,4 +672,119 @@ mod tests {
         let msg = manager.get_message(&id).unwrap();
         assert!(msg.flags.read);
     }
+
+    #[test]
+    fn test_authentication_results() {
+        let results = AuthenticationResults::parse(
+            "mx.example.com 1; spf=pass (sender SPF authorized) smtp.mailfrom=example.org; \
+             dkim=fail header.d=other.net; dkim=pass header.d=example.org; dmarc=pass (p=REJECT) header.from=example.org",
+        )
+        .unwrap();
+        assert_eq!(results.authserv_id, "mx.example.com");
+        assert_eq!(results.summary(), "SPF pass, DKIM pass, DMARC pass");
+
+        let raw = |header: &str| format!("{}From: a@example.org\r\nSubject: Hi\r\n\r\nBody", header);
+        let verified = SenderAuthentication::of_message(&raw(
+            "Authentication-Results: mx.example.com;\r\n spf=pass; dkim=pass; dmarc=pass\r\n",
+        ))
+        .unwrap();
+        assert!(verified.verified());
+        assert_eq!(verified.warning(), None);
+
+        let spoofed = SenderAuthentication::of_message(&raw(
+            "Authentication-Results: mx.example.com; spf=softfail; dkim=none; dmarc=fail\r\n\
+             Authentication-Results: forged.example; spf=pass; dkim=pass; dmarc=pass\r\n",
+        ))
+        .unwrap();
+        assert!(!spoofed.verified());
+        assert!(spoofed.warning().unwrap().contains("SPF fail, DKIM neutral, DMARC fail"));
+
+        let missing = SenderAuthentication::of_message(&raw("")).unwrap();
+        assert_eq!(missing.results, None);
+        assert!(missing.warning().unwrap().contains("no authentication results"));
+
+        assert_eq!(SenderAuthentication::of_message("<p>Just a body</p>"), None);
+    }
+
+    #[test]
+    fn test_message_priority() {
+        let raw = "From: boss@example.com\r\nX-Priority: 1 (Highest)\r\nSubject: Now\r\n\r\nBody";
+        assert_eq!(message_priority(raw), Some(MessagePriority::High));
+        let raw = "From: news@example.com\r\nImportance: Low\r\n\r\nBody";
+        assert_eq!(message_priority(raw), Some(MessagePriority::Low));
+        assert_eq!(message_priority("From: a@example.com\r\n\r\nBody"), Some(MessagePriority::Normal));
+        assert_eq!(message_priority("<p>Just a body</p>"), None);
+    }
+
+    #[test]
+    fn test_delivery_failure() {
+        let report = |original: &str| {
+            format!(
+                "From: MAILER-DAEMON@mx.example.com\r\n\
+                 Subject: Undelivered Mail Returned to Sender\r\n\
+                 Content-Type: multipart/report; report-type=delivery-status; boundary=\"b1\"\r\n\
+                 \r\n--b1\r\nContent-Type: text/plain\r\n\r\nYour message could not be delivered.\r\n\
+                 --b1\r\nContent-Type: message/delivery-status\r\n\r\n\
+                 Reporting-MTA: dns; mx.example.com\r\n\r\n\
+                 Final-Recipient: rfc822; bob@example.net\r\nAction: failed\r\nStatus: 5.1.1\r\n\
+                 Diagnostic-Code: smtp; 550 5.1.1 User unknown\r\n\r\n\
+                 Final-Recipient: rfc822; carol@example.net\r\nAction: delayed\r\nStatus: 4.4.1\r\n\
+                 \r\n--b1\r\n{}\r\n--b1--\r\n",
+                original
+            )
+        };
+
+        let returned = DeliveryFailure::of_message(&report(
+            "Content-Type: message/rfc822\r\n\r\nFrom: me@example.com\r\nTo: bob@example.net\r\n\
+             Subject: Lunch\r\nMessage-ID: <abc123@example.com>\r\n\r\nNoon on Friday?\r\n",
+        ))
+        .unwrap();
+        assert_eq!(returned.recipients.len(), 1);
+        assert_eq!(returned.recipients[0].status.as_deref(), Some("5.1.1"));
+        assert_eq!(returned.original_message_id.as_deref(), Some("abc123@example.com"));
+        assert!(returned.original_text.as_deref().is_some_and(|t| t.contains("Noon on Friday?")));
+        assert_eq!(
+            returned.summary(),
+            "Delivery failed to bob@example.net (550 5.1.1 User unknown) for message \"Lunch\""
+        );
+
+        let headers_only = DeliveryFailure::of_message(&report(
+            "Content-Type: text/rfc822-headers\r\n\r\nSubject: Lunch\r\nMessage-ID:\r\n <abc123@example.com>\r\n",
+        ))
+        .unwrap();
+        assert_eq!(headers_only.original_message_id.as_deref(), Some("abc123@example.com"));
+        assert_eq!(headers_only.original_subject.as_deref(), Some("Lunch"));
+        assert_eq!(headers_only.original_text, None);
+
+        assert_eq!(DeliveryFailure::of_message("From: a@example.org\r\nSubject: Hi\r\n\r\nBody"), None);
+    }
+
+    #[test]
+    fn test_mailing_list() {
+        let raw = "From: news@example.com\r\nList-Id: Announcements (monthly)\r\n <Announce.Example.com>\r\n\
+                   List-Unsubscribe: <mailto:leave@example.com?subject=remove%20me>,\r\n <https://example.com/u/42>\r\n\
+                   List-Unsubscribe-Post: List-Unsubscribe=One-Click\r\n\r\nBody";
+        let list = MailingList::of_message(raw).unwrap();
+        assert_eq!(list.id.as_deref(), Some("announce.example.com"));
+        assert_eq!(list.unsubscribe, Some(Unsubscribe::OneClick("https://example.com/u/42".into())));
+
+        // Without List-Unsubscribe-Post the link is only a web page, so mail is preferred
+        let raw = "From: news@example.com\r\n\
+                   List-Unsubscribe: <https://example.com/u/42>, <mailto:leave@example.com?subject=remove%20me>\r\n\r\nBody";
+        let list = MailingList::of_message(raw).unwrap();
+        assert_eq!(list.id, None);
+        assert_eq!(
+            list.unsubscribe,
+            Some(Unsubscribe::Mail { to: "leave@example.com".into(), subject: "remove me".into(), body: String::new() })
+        );
+
+        let raw = "From: news@example.com\r\nList-Unsubscribe: <http://example.com/u/42>\r\n\
+                   List-Unsubscribe-Post: List-Unsubscribe=One-Click\r\n\r\nBody";
+        let list = MailingList::of_message(raw).unwrap();
+        assert_eq!(list.unsubscribe, Some(Unsubscribe::Web("http://example.com/u/42".into())));
+
+        let raw = "From: news@example.com\r\nList-Id: <dev.example.org>\r\n\r\nBody";
+        assert_eq!(MailingList::of_message(raw).unwrap().unsubscribe, None);
+        assert_eq!(MailingList::of_message("From: a@example.org\r\nSubject: Hi\r\n\r\nBody"), None);
+    }
 }
@@ -3,17 +3,41 @@
 //! This layer contains the core business logic and management components.
 
 pub mod accounts;
+pub mod bulk;
+pub mod calendar;
 pub mod composition;
 pub mod contacts;
+pub mod engine;
 pub mod filters;
+pub mod follow_up;
+pub mod history;
+pub mod identities;
 pub mod mail_controller;
 pub mod messages;
+pub mod notifications;
+pub mod quota;
+pub mod readiness;
+pub mod responder;
 pub mod search;
+pub mod shutdown;
+pub mod snooze;
+pub mod spam;
 
 pub use accounts::AccountManager;
-pub use composition::CompositionManager;
-pub use contacts::ContactManager;
+pub use bulk::BulkAction;
+pub use calendar::{CalendarInvite, InviteResponse};
+pub use composition::{CompositionManager, DraftSession};
+pub use contacts::{AddressSuggestion, ContactManager};
+pub use engine::MailEngine;
 pub use filters::FilterEngine;
+pub use follow_up::FollowUp;
+pub use history::{ActionHistory, UndoableAction};
 pub use mail_controller::{MailController, SendEmailRequest};
-pub use messages::MessageManager;
+pub use messages::{DeliveryFailure, MailingList, MessageManager, SenderAuthentication, Unsubscribe};
+pub use notifications::NewMailNotifier;
+pub use quota::MailboxUsage;
+pub use readiness::{CheckStatus, ReadinessCheck};
 pub use search::SearchEngine;
+pub use shutdown::Shutdown;
+pub use snooze::SnoozePreset;
+pub use spam::SpamClassifier;
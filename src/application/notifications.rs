@@ -0,0 +1,165 @@
+//! New-mail notifications
+//!
+//! Mail checks report how many messages arrived; [`NewMailNotifier`]
+//! decides whether that is worth a desktop notification (the global and
+//! per-account switches, quiet hours, mailing lists) and coalesces bursts,
+//! so mail arriving over several checks in quick succession raises one
+//! notification.
+
+use crate::data::account::Account;
+use crate::data::config::AppConfig;
+use chrono::{DateTime, Duration, Local, Utc};
+
+/// Shortest time between two notifications; mail arriving sooner is held
+/// and counted into the next one
+pub const COALESCE_WINDOW: Duration = Duration::seconds(30);
+
+/// Sender and subject of a newly arrived message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MailPreview {
+    pub from: String,
+    pub subject: String,
+}
+
+/// A desktop notification to show
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub title: String,
+    pub body: String,
+    /// Play the new-mail sound with it
+    pub sound: bool,
+}
+
+/// Collects new mail and hands out at most one notification per
+/// [`COALESCE_WINDOW`]
+#[derive(Debug, Default)]
+pub struct NewMailNotifier {
+    count: usize,
+    latest: Option<MailPreview>,
+    sound: bool,
+    last_shown: Option<DateTime<Utc>>,
+}
+
+impl NewMailNotifier {
+    /// Note `count` new messages for `account`, `from_lists` more from
+    /// mailing lists, unless notifications are off for it or it is within
+    /// the quiet hours at `now`. List mail only counts when
+    /// [`AppConfig::notify_mailing_lists`] is set. Returns whether they
+    /// will be notified.
+    pub fn add(
+        &mut self,
+        config: &AppConfig,
+        account: Option<&Account>,
+        count: usize,
+        from_lists: usize,
+        latest: Option<MailPreview>,
+        now: DateTime<Utc>,
+    ) -> bool {
+        let count = if config.notify_mailing_lists { count + from_lists } else { count };
+        let wanted = count > 0
+            && config.enable_notifications
+            && account.is_none_or(|a| a.notify_new_mail)
+            && !config.quiet_hours.contains(now.with_timezone(&Local).time());
+        if wanted {
+            self.count += count;
+            self.latest = latest.or(self.latest.take());
+            self.sound |= config.notification_sound;
+        }
+        wanted
+    }
+
+    /// The notification for the mail noted so far, once the window since
+    /// the last one has passed
+    pub fn take_due(&mut self, now: DateTime<Utc>) -> Option<Notification> {
+        if self.count == 0 || self.last_shown.is_some_and(|last| now - last < COALESCE_WINDOW) {
+            return None;
+        }
+        let (title, body) = match (self.count, self.latest.take()) {
+            (1, Some(p)) => (format!("New mail from {}", p.from), p.subject),
+            (n, Some(p)) => (format!("{} new messages", n), format!("Latest from {}: {}", p.from, p.subject)),
+            (n, None) => (format!("{} new messages", n), String::new()),
+        };
+        let notification = Notification { title, body, sound: self.sound };
+        self.count = 0;
+        self.sound = false;
+        self.last_shown = Some(now);
+        Some(notification)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveTime, TimeZone};
+
+    fn preview(n: u32) -> Option<MailPreview> {
+        Some(MailPreview { from: "ana@example.com".into(), subject: format!("Report {}", n) })
+    }
+
+    #[test]
+    fn test_bursts_coalesce() {
+        let config = AppConfig::default();
+        let start = Utc.with_ymd_and_hms(2025, 3, 12, 9, 0, 0).unwrap();
+        let mut notifier = NewMailNotifier::default();
+        assert_eq!(notifier.take_due(start), None);
+
+        assert!(notifier.add(&config, None, 1, 0, preview(1), start));
+        let first = notifier.take_due(start).unwrap();
+        assert_eq!(first.title, "New mail from ana@example.com");
+        assert_eq!(first.body, "Report 1");
+        assert!(first.sound);
+
+        // Ten checks finding one message each within the window: one notification
+        for n in 2..12 {
+            notifier.add(&config, None, 1, 0, preview(n), start + Duration::seconds(n as i64));
+            assert_eq!(notifier.take_due(start + Duration::seconds(n as i64)), None);
+        }
+        let burst = notifier.take_due(start + COALESCE_WINDOW).unwrap();
+        assert_eq!(burst.title, "10 new messages");
+        assert_eq!(burst.body, "Latest from ana@example.com: Report 11");
+        assert_eq!(notifier.take_due(start + COALESCE_WINDOW * 3), None);
+    }
+
+    #[test]
+    fn test_switches_and_quiet_hours() {
+        let mut config = AppConfig::default();
+        let noon = Local.with_ymd_and_hms(2025, 3, 12, 12, 0, 0).unwrap().with_timezone(&Utc);
+        let mut notifier = NewMailNotifier::default();
+        let mut account = Account { notify_new_mail: false, ..Account::default() };
+
+        assert!(!notifier.add(&config, Some(&account), 3, 0, preview(1), noon));
+        account.notify_new_mail = true;
+        config.quiet_hours.enabled = true;
+        config.quiet_hours.start = NaiveTime::from_hms_opt(11, 0, 0).unwrap();
+        config.quiet_hours.end = NaiveTime::from_hms_opt(13, 0, 0).unwrap();
+        assert!(!notifier.add(&config, Some(&account), 3, 0, preview(1), noon));
+        config.quiet_hours.enabled = false;
+        config.enable_notifications = false;
+        assert!(!notifier.add(&config, Some(&account), 3, 0, preview(1), noon));
+        assert_eq!(notifier.take_due(noon), None);
+
+        config.enable_notifications = true;
+        config.notification_sound = false;
+        assert!(notifier.add(&config, Some(&account), 2, 0, None, noon));
+        assert_eq!(
+            notifier.take_due(noon),
+            Some(Notification { title: "2 new messages".into(), body: String::new(), sound: false })
+        );
+    }
+
+    #[test]
+    fn test_mailing_lists_are_quiet_by_default() {
+        let mut config = AppConfig::default();
+        let start = Utc.with_ymd_and_hms(2025, 3, 12, 9, 0, 0).unwrap();
+        let mut notifier = NewMailNotifier::default();
+
+        assert!(!notifier.add(&config, None, 0, 4, None, start));
+        assert!(notifier.add(&config, None, 1, 4, preview(1), start));
+        assert_eq!(notifier.take_due(start).unwrap().title, "New mail from ana@example.com");
+
+        config.notify_mailing_lists = true;
+        let later = start + COALESCE_WINDOW;
+        assert!(notifier.add(&config, None, 0, 4, None, later));
+        assert_eq!(notifier.take_due(later).unwrap().title, "4 new messages");
+    }
+}
@@ -0,0 +1,119 @@
+//! Mailbox quota
+//!
+//! Turns the usage and limits a server reports into the usage meter shown
+//! in the status bar, warning before the mailbox fills up and new mail
+//! starts bouncing.
+
+use crate::data::message_cache::CachedQuota;
+
+/// Cells in the text usage bar
+const BAR_CELLS: u32 = 10;
+
+/// Usage of whichever quota resource (storage or message count) is fullest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MailboxUsage {
+    /// Percent used, rounded down; over 100 when the server lets it overflow
+    pub percent: u32,
+    /// "820 MB of 1 GB" or "9,000 of 10,000 messages"
+    pub detail: String,
+}
+
+impl MailboxUsage {
+    /// `None` when the quota has no resource with a limit
+    pub fn of(quota: &CachedQuota) -> Option<Self> {
+        let storage = match (quota.storage_used, quota.storage_limit) {
+            (Some(used), Some(limit)) if limit > 0 => {
+                Some((used * 100 / limit, format!("{} of {}", format_size(used), format_size(limit))))
+            }
+            _ => None,
+        };
+        let messages = match (quota.messages_used, quota.messages_limit) {
+            (Some(used), Some(limit)) if limit > 0 => Some((
+                used * 100 / limit,
+                format!("{} of {} messages", format_count(used), format_count(limit)),
+            )),
+            _ => None,
+        };
+        let (percent, detail) = [storage, messages].into_iter().flatten().max_by_key(|(percent, _)| *percent)?;
+        Some(Self { percent: percent.max(0) as u32, detail })
+    }
+
+    /// At or above the warning threshold
+    pub fn near_limit(&self, warning_percent: u32) -> bool {
+        self.percent >= warning_percent
+    }
+
+    /// Status bar text: "Mailbox ▰▰▰▰▰▰▰▰▱▱ 82% full (820 MB of 1 GB)"
+    pub fn label(&self) -> String {
+        let filled = (self.percent.min(100) * BAR_CELLS / 100) as usize;
+        format!(
+            "Mailbox {}{} {}% full ({})",
+            "▰".repeat(filled),
+            "▱".repeat(BAR_CELLS as usize - filled),
+            self.percent,
+            self.detail
+        )
+    }
+}
+
+/// Byte count in the largest whole unit, one decimal below 10
+pub fn format_size(bytes: i64) -> String {
+    const UNITS: [&str; 5] = ["bytes", "KB", "MB", "GB", "TB"];
+    let mut size = bytes.max(0) as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 || size >= 10.0 || size.fract() == 0.0 {
+        format!("{:.0} {}", size.floor(), UNITS[unit])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Count with thousands separators
+fn format_count(count: i64) -> String {
+    let digits = count.to_string();
+    let mut out = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quota(storage: Option<(i64, i64)>, messages: Option<(i64, i64)>) -> CachedQuota {
+        CachedQuota {
+            storage_used: storage.map(|s| s.0),
+            storage_limit: storage.map(|s| s.1),
+            messages_used: messages.map(|m| m.0),
+            messages_limit: messages.map(|m| m.1),
+            checked_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_mailbox_usage() {
+        const MB: i64 = 1024 * 1024;
+        let usage = MailboxUsage::of(&quota(Some((820 * MB, 1024 * MB)), Some((100, 10_000)))).unwrap();
+        assert_eq!(usage.percent, 80);
+        assert_eq!(usage.label(), "Mailbox ▰▰▰▰▰▰▰▰▱▱ 80% full (820 MB of 1 GB)");
+        assert!(!usage.near_limit(90));
+
+        // The fuller resource is the one shown
+        let by_count = MailboxUsage::of(&quota(Some((MB, 1024 * MB)), Some((9_500, 10_000)))).unwrap();
+        assert_eq!(by_count.detail, "9,500 of 10,000 messages");
+        assert!(by_count.near_limit(90));
+
+        assert_eq!(MailboxUsage::of(&quota(Some((5 * MB / 2, 0)), None)), None);
+        assert_eq!(format_size(5 * MB / 2), "2.5 MB");
+        assert_eq!(format_size(512), "512 bytes");
+    }
+}
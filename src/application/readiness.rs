@@ -0,0 +1,311 @@
+//! Beta readiness diagnostics
+//!
+//! Help > Beta Readiness Check reports whether the app is set up to send
+//! and receive mail. `build_beta_readiness_report` looks only at what the
+//! app already knows; the other checks reach the mail servers, the
+//! keychain, the cache database and the disk, so they are run off the UI
+//! thread and reported as they finish.
+
+use crate::application::quota::format_size;
+use crate::common::Result;
+use crate::data::account::{requires_oauth, Account};
+use crate::service::oauth::OAuthTokenSet;
+use crate::service::protocols::network;
+use chrono::{DateTime, Utc};
+use std::path::Path;
+use std::time::Duration;
+
+/// Queued messages above which the outbox counts as backed up
+const OUTBOX_BACKLOG: usize = 10;
+/// Free space below which the cache may fail to grow
+const LOW_DISK_BYTES: u64 = 1024 * 1024 * 1024;
+/// Free space below which writes are likely to fail
+const CRITICAL_DISK_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Outcome of one check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl CheckStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            CheckStatus::Pass => "PASS",
+            CheckStatus::Warn => "WARN",
+            CheckStatus::Fail => "FAIL",
+        }
+    }
+}
+
+/// One line of the report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadinessCheck {
+    /// What was checked, e.g. "Work: incoming server"
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+impl ReadinessCheck {
+    pub fn pass(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self { name: name.into(), status: CheckStatus::Pass, detail: detail.into() }
+    }
+
+    pub fn warn(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self { name: name.into(), status: CheckStatus::Warn, detail: detail.into() }
+    }
+
+    pub fn fail(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self { name: name.into(), status: CheckStatus::Fail, detail: detail.into() }
+    }
+}
+
+impl std::fmt::Display for ReadinessCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {}: {}", self.status.label(), self.name, self.detail)
+    }
+}
+
+/// What the app knows without going to the network
+pub struct ReadinessSnapshot<'a> {
+    pub accounts: &'a [Account],
+    pub active_account_id: Option<&'a str>,
+    pub cache_available: bool,
+    pub offline: bool,
+    /// Messages waiting in the outbox, across accounts
+    pub queued_outbox: usize,
+}
+
+/// Checks that only look at in-memory state
+pub fn build_beta_readiness_report(snapshot: &ReadinessSnapshot) -> Vec<ReadinessCheck> {
+    let mut report = Vec::new();
+    let enabled = snapshot.accounts.iter().filter(|a| a.enabled).count();
+    report.push(match (snapshot.accounts.len(), enabled) {
+        (0, _) => ReadinessCheck::fail("Accounts", "No accounts are set up. Add one in Tools → Manage Accounts."),
+        (total, 0) => ReadinessCheck::warn("Accounts", format!("All {} accounts are disabled", total)),
+        (total, enabled) => ReadinessCheck::pass("Accounts", format!("{} of {} accounts enabled", enabled, total)),
+    });
+
+    let active = snapshot.active_account_id.and_then(|id| snapshot.accounts.iter().find(|a| a.id == id));
+    report.push(match active {
+        None => ReadinessCheck::fail("Active account", "No account is selected"),
+        Some(a) if !a.enabled => ReadinessCheck::warn("Active account", format!("{} is disabled", a.display_name())),
+        Some(a) => ReadinessCheck::pass("Active account", a.display_name()),
+    });
+
+    report.push(if snapshot.cache_available {
+        ReadinessCheck::pass("Message cache", "Available")
+    } else {
+        ReadinessCheck::fail("Message cache", "Unavailable; mail can't be kept for offline reading")
+    });
+
+    report.push(if snapshot.offline {
+        ReadinessCheck::warn("Connection", "Offline mode is on; mail is not checked or sent")
+    } else {
+        ReadinessCheck::pass("Connection", "Online")
+    });
+
+    report.push(match snapshot.queued_outbox {
+        0 => ReadinessCheck::pass("Outbox", "Empty"),
+        n if n > OUTBOX_BACKLOG || snapshot.offline => {
+            ReadinessCheck::warn("Outbox", format!("{} messages waiting to be sent", n))
+        }
+        n => ReadinessCheck::pass("Outbox", format!("{} messages waiting to be sent", n)),
+    });
+
+    let oauth = snapshot.accounts.iter().filter(|a| uses_oauth(a)).count();
+    report.push(ReadinessCheck::pass(
+        "OAuth",
+        match oauth {
+            0 => "No account signs in with OAuth".to_string(),
+            n => format!("{} accounts sign in with OAuth", n),
+        },
+    ));
+    report
+}
+
+/// Whether the account signs in through its provider's OAuth page
+pub fn uses_oauth(account: &Account) -> bool {
+    account.use_oauth || requires_oauth(&account.email)
+}
+
+/// Try to reach `host:port` within `timeout`. `name` labels the check,
+/// e.g. "Work: incoming server".
+pub async fn probe_server(name: &str, host: &str, port: &str, timeout: Duration) -> ReadinessCheck {
+    let Ok(port) = port.trim().parse::<u16>() else {
+        return ReadinessCheck::fail(name, format!("\"{}\" is not a valid port", port));
+    };
+    if host.trim().is_empty() {
+        return ReadinessCheck::fail(name, "No server is set");
+    }
+    match network::probe(host.trim(), port, timeout).await {
+        Ok(took) => ReadinessCheck::pass(name, format!("{}:{} answered in {} ms", host, port, took.as_millis())),
+        Err(e) => ReadinessCheck::fail(name, e.to_string()),
+    }
+}
+
+/// Whether the stored OAuth tokens can still sign in at `now`
+pub fn oauth_token_check(account: &Account, tokens: Option<&OAuthTokenSet>, now: DateTime<Utc>) -> ReadinessCheck {
+    let name = format!("{}: OAuth sign-in", account.display_name());
+    let Some(tokens) = tokens else {
+        return ReadinessCheck::fail(name, "Not signed in. Sign in again from Tools → Manage Accounts.");
+    };
+    let renewable = tokens.refresh_token.as_deref().is_some_and(|t| !t.is_empty());
+    let Some(expires) = tokens.expires_at.as_deref() else {
+        return ReadinessCheck::pass(name, "Signed in; the token does not expire");
+    };
+    match DateTime::parse_from_rfc3339(expires) {
+        Ok(at) if at > now => {
+            ReadinessCheck::pass(name, format!("Signed in until {}", at.with_timezone(&Utc).format("%Y-%m-%d %H:%M UTC")))
+        }
+        Ok(_) if renewable => ReadinessCheck::warn(name, "The access token has expired; it is renewed on next use"),
+        Ok(_) => ReadinessCheck::fail(name, "The sign-in has expired. Sign in again from Tools → Manage Accounts."),
+        Err(_) => ReadinessCheck::warn(name, format!("Unreadable expiry time \"{}\"", expires)),
+    }
+}
+
+/// Compare the cache's schema version with the one this build expects and
+/// report any damage SQLite found
+pub fn schema_check(version: Result<i32>, expected: i32, problems: Result<Vec<String>>) -> ReadinessCheck {
+    const NAME: &str = "Cache database";
+    let version = match version {
+        Ok(version) => version,
+        Err(e) => return ReadinessCheck::fail(NAME, e.to_string()),
+    };
+    match problems {
+        Err(e) => return ReadinessCheck::fail(NAME, e.to_string()),
+        Ok(problems) if !problems.is_empty() => {
+            return ReadinessCheck::fail(NAME, format!("Damaged: {}", problems.join("; ")));
+        }
+        Ok(_) => {}
+    }
+    match version.cmp(&expected) {
+        std::cmp::Ordering::Equal => ReadinessCheck::pass(NAME, format!("Schema version {}", version)),
+        std::cmp::Ordering::Less => {
+            ReadinessCheck::fail(NAME, format!("Schema version {} was not upgraded to {}", version, expected))
+        }
+        std::cmp::Ordering::Greater => ReadinessCheck::warn(
+            NAME,
+            format!("Schema version {} is from a newer Wixen Mail than this one ({})", version, expected),
+        ),
+    }
+}
+
+/// Free space on the disk holding `dir`
+pub fn disk_space_check(dir: &Path) -> ReadinessCheck {
+    const NAME: &str = "Disk space";
+    match fs2::available_space(dir) {
+        Ok(bytes) => disk_space_status(bytes),
+        Err(e) => ReadinessCheck::warn(NAME, format!("Could not read free space for {}: {}", dir.display(), e)),
+    }
+}
+
+fn disk_space_status(available: u64) -> ReadinessCheck {
+    const NAME: &str = "Disk space";
+    let free = format!("{} free for the cache", format_size(available.min(i64::MAX as u64) as i64));
+    if available < CRITICAL_DISK_BYTES {
+        ReadinessCheck::fail(NAME, free)
+    } else if available < LOW_DISK_BYTES {
+        ReadinessCheck::warn(NAME, free)
+    } else {
+        ReadinessCheck::pass(NAME, free)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::Error;
+
+    fn account(id: &str, email: &str, enabled: bool) -> Account {
+        let mut a = Account::new(id.to_string(), email.to_string());
+        a.id = id.to_string();
+        a.enabled = enabled;
+        a
+    }
+
+    #[test]
+    fn test_build_beta_readiness_report() {
+        let none = build_beta_readiness_report(&ReadinessSnapshot {
+            accounts: &[],
+            active_account_id: None,
+            cache_available: false,
+            offline: true,
+            queued_outbox: 2,
+        });
+        let statuses: Vec<_> = none.iter().map(|c| c.status).collect();
+        use CheckStatus::*;
+        assert_eq!(statuses, [Fail, Fail, Fail, Warn, Warn, Pass]);
+
+        let accounts = [account("work", "me@example.com", true), account("home", "me@gmail.com", false)];
+        let report = build_beta_readiness_report(&ReadinessSnapshot {
+            accounts: &accounts,
+            active_account_id: Some("home"),
+            cache_available: true,
+            offline: false,
+            queued_outbox: 3,
+        });
+        assert_eq!(report[0].to_string(), "[PASS] Accounts: 1 of 2 accounts enabled");
+        assert_eq!(report[1].status, CheckStatus::Warn);
+        assert!(report.iter().skip(2).take(3).all(|c| c.status == CheckStatus::Pass));
+        assert_eq!(report[5].detail, "1 accounts sign in with OAuth");
+    }
+
+    #[tokio::test]
+    async fn test_probe_server() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port().to_string();
+        let check = probe_server("Work: incoming server", "127.0.0.1", &port, Duration::from_secs(5)).await;
+        assert_eq!(check.status, CheckStatus::Pass);
+
+        let check = probe_server("Work: incoming server", "127.0.0.1", "imap", Duration::from_secs(5)).await;
+        assert_eq!(check.detail, "\"imap\" is not a valid port");
+        let check = probe_server("Work: outgoing server", " ", "587", Duration::from_secs(5)).await;
+        assert_eq!(check.status, CheckStatus::Fail);
+    }
+
+    #[test]
+    fn test_oauth_token_check() {
+        let a = account("home", "me@gmail.com", true);
+        let now = DateTime::parse_from_rfc3339("2026-03-01T12:00:00Z").unwrap().with_timezone(&Utc);
+        let tokens = |expires: Option<&str>, refresh: Option<&str>| OAuthTokenSet {
+            access_token: "access".to_string(),
+            refresh_token: refresh.map(str::to_string),
+            token_type: "Bearer".to_string(),
+            scope: None,
+            expires_at: expires.map(str::to_string),
+        };
+
+        assert_eq!(oauth_token_check(&a, None, now).status, CheckStatus::Fail);
+        let valid = tokens(Some("2026-03-01T13:00:00Z"), None);
+        assert_eq!(oauth_token_check(&a, Some(&valid), now).detail, "Signed in until 2026-03-01 13:00 UTC");
+        let renewable = tokens(Some("2026-03-01T11:00:00Z"), Some("refresh"));
+        assert_eq!(oauth_token_check(&a, Some(&renewable), now).status, CheckStatus::Warn);
+        let expired = tokens(Some("2026-03-01T11:00:00Z"), Some(""));
+        assert_eq!(oauth_token_check(&a, Some(&expired), now).status, CheckStatus::Fail);
+        assert_eq!(oauth_token_check(&a, Some(&tokens(None, None)), now).status, CheckStatus::Pass);
+    }
+
+    #[test]
+    fn test_schema_check() {
+        assert_eq!(schema_check(Ok(3), 3, Ok(vec![])).to_string(), "[PASS] Cache database: Schema version 3");
+        assert_eq!(schema_check(Ok(2), 3, Ok(vec![])).status, CheckStatus::Fail);
+        assert_eq!(schema_check(Ok(4), 3, Ok(vec![])).status, CheckStatus::Warn);
+        let damaged = schema_check(Ok(3), 3, Ok(vec!["row 5 missing from index".to_string()]));
+        assert_eq!(damaged.detail, "Damaged: row 5 missing from index");
+        let unreadable = schema_check(Err(Error::Database("locked".to_string())), 3, Ok(vec![]));
+        assert_eq!(unreadable.status, CheckStatus::Fail);
+    }
+
+    #[test]
+    fn test_disk_space_status() {
+        assert_eq!(disk_space_status(50 * 1024 * 1024).status, CheckStatus::Fail);
+        assert_eq!(disk_space_status(500 * 1024 * 1024).detail, "500 MB free for the cache");
+        assert_eq!(disk_space_status(500 * 1024 * 1024).status, CheckStatus::Warn);
+        assert_eq!(disk_space_status(20 * 1024 * 1024 * 1024).status, CheckStatus::Pass);
+        assert_eq!(disk_space_check(&std::env::temp_dir()).name, "Disk space");
+    }
+}
@@ -0,0 +1,233 @@
+//! Automatic replies and forwards
+//!
+//! Mail sent on the user's behalf without them seeing it first, such as
+//! filter rule forwards and auto-replies. Following RFC 3834, nothing is
+//! sent in answer to automatic mail, mailing lists or bounce addresses, and
+//! everything sent is marked `Auto-Submitted` so other responders leave it
+//! alone.
+
+use crate::common::types::EmailAddress;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+
+/// Local parts of addresses nobody reads replies to
+const UNATTENDED_MAILBOXES: [&str; 8] =
+    ["noreply", "no-reply", "no_reply", "donotreply", "do-not-reply", "mailer-daemon", "postmaster", "bounces"];
+
+/// What automatic replies and forwards need to know about a received message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncomingMail {
+    /// Where replies go: the envelope sender (`Return-Path`) or, failing
+    /// that, `From`. `None` for bounces, which have an empty envelope sender.
+    pub sender: Option<EmailAddress>,
+    pub subject: String,
+    /// Without angle brackets
+    pub message_id: Option<String>,
+    /// Message-IDs of the thread so far, oldest first
+    pub references: Vec<String>,
+    /// Sent by a program or through a mailing list
+    pub automated: bool,
+    /// Forwarded automatically by another program
+    pub auto_forwarded: bool,
+}
+
+impl IncomingMail {
+    /// Read the headers of a raw message
+    pub fn from_raw(raw: &[u8]) -> Option<Self> {
+        let parsed = mail_parser::MessageParser::default().parse(raw)?;
+        let header = |name: &str| parsed.header_raw(name).map(|v| v.trim().to_ascii_lowercase());
+        let auto_submitted = header("Auto-Submitted").filter(|v| !v.starts_with("no"));
+        let automated = auto_submitted.is_some()
+            || header("Precedence").is_some_and(|p| matches!(p.as_str(), "bulk" | "list" | "junk" | "auto_reply"))
+            || header("X-Auto-Response-Suppress")
+                .is_some_and(|v| ["all", "autoreply", "oof"].iter().any(|s| v.contains(s)))
+            || ["List-Id", "List-Unsubscribe", "List-Post", "X-Autoreply", "X-Autorespond"]
+                .iter()
+                .any(|name| parsed.header_raw(*name).is_some());
+        let from = parsed.from().and_then(|a| a.first()).and_then(|a| {
+            Some(EmailAddress::new(a.address()?.to_string(), a.name().map(str::to_string)))
+        });
+        let sender = match parsed.header_raw("Return-Path") {
+            Some(path) => EmailAddress::parse_list(path).into_iter().find(|a| a.address.contains('@')),
+            None => from,
+        };
+        let mut references: Vec<String> = parsed
+            .header_raw("References")
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(|id| id.trim_matches(['<', '>']).to_string())
+            .filter(|id| !id.is_empty())
+            .collect();
+        let message_id = parsed.message_id().map(str::to_string);
+        if let Some(id) = &message_id {
+            references.push(id.clone());
+        }
+        Some(Self {
+            sender,
+            subject: parsed.subject().unwrap_or_default().to_string(),
+            message_id,
+            references,
+            automated,
+            auto_forwarded: auto_submitted.is_some_and(|v| v.starts_with("auto-forwarded")),
+        })
+    }
+
+    /// The address an automatic reply may go to, or `None` when the message
+    /// mustn't be answered: it is automatic or list mail, it came from a
+    /// mailbox nobody reads, or from one of the user's `own` addresses.
+    pub fn reply_address(&self, own: &[&str]) -> Option<&EmailAddress> {
+        let sender = self.sender.as_ref().filter(|_| !self.automated)?;
+        let local = sender.address.split('@').next().unwrap_or_default().to_ascii_lowercase();
+        let unattended = UNATTENDED_MAILBOXES.contains(&local.as_str()) || local.starts_with("bounce");
+        let own_address = own.iter().any(|a| a.eq_ignore_ascii_case(&sender.address));
+        (!unattended && !own_address).then_some(sender)
+    }
+
+    /// "Re: " and the original subject, without doubling an existing prefix
+    pub fn reply_subject(&self) -> String {
+        let subject = self.subject.trim();
+        if subject.to_ascii_lowercase().starts_with("re:") {
+            subject.to_string()
+        } else {
+            format!("Re: {}", subject)
+        }
+    }
+
+    /// MIME for an automatic reply from `from` to `to`
+    pub fn reply_mime(&self, from: &EmailAddress, to: &str, subject: &str, body: &str, html: bool, now: DateTime<Utc>) -> Vec<u8> {
+        let mut headers = vec![
+            format!("From: {}", mailbox(from)),
+            format!("To: {}", to),
+            format!("Subject: {}", encode_word(subject)),
+            format!("Date: {}", now.to_rfc2822()),
+            format!("Message-ID: <{}@wixen-mail>", uuid::Uuid::new_v4()),
+            "Auto-Submitted: auto-replied".to_string(),
+            "X-Auto-Response-Suppress: All".to_string(),
+        ];
+        if let Some(id) = &self.message_id {
+            headers.push(format!("In-Reply-To: <{}>", id));
+        }
+        if !self.references.is_empty() {
+            let ids: Vec<String> = self.references.iter().map(|id| format!("<{}>", id)).collect();
+            headers.push(format!("References: {}", ids.join(" ")));
+        }
+        headers.push("MIME-Version: 1.0".to_string());
+        headers.push(format!("Content-Type: text/{}; charset=utf-8", if html { "html" } else { "plain" }));
+        headers.push("Content-Transfer-Encoding: 8bit".to_string());
+        format!("{}\r\n\r\n{}\r\n", headers.join("\r\n"), crlf(body)).into_bytes()
+    }
+
+    /// MIME forwarding `raw`, this message, from `from` to `to`. The
+    /// original is attached whole, so its attachments and headers survive.
+    pub fn forward_mime(&self, raw: &[u8], from: &EmailAddress, to: &str, now: DateTime<Utc>) -> Vec<u8> {
+        let boundary = format!("fwd-{}", uuid::Uuid::new_v4().simple());
+        let sender = self.sender.as_ref().map(|s| s.address.as_str()).unwrap_or("an unknown sender");
+        let headers = [
+            format!("From: {}", mailbox(from)),
+            format!("To: {}", to),
+            format!("Subject: {}", encode_word(&format!("Fwd: {}", self.subject.trim()))),
+            format!("Date: {}", now.to_rfc2822()),
+            format!("Message-ID: <{}@wixen-mail>", uuid::Uuid::new_v4()),
+            "Auto-Submitted: auto-forwarded".to_string(),
+            "MIME-Version: 1.0".to_string(),
+            format!("Content-Type: multipart/mixed; boundary=\"{}\"", boundary),
+        ];
+        let mut mime = format!(
+            "{}\r\n\r\n--{b}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n\
+             Forwarded automatically by a mail rule. The original message from {} is attached.\r\n\r\n\
+             --{b}\r\nContent-Type: message/rfc822\r\nContent-Disposition: inline\r\n\r\n",
+            headers.join("\r\n"),
+            sender,
+            b = boundary
+        )
+        .into_bytes();
+        mime.extend_from_slice(raw);
+        if !raw.ends_with(b"\n") {
+            mime.extend_from_slice(b"\r\n");
+        }
+        mime.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+        mime
+    }
+}
+
+fn encode_word(text: &str) -> String {
+    let text = text.replace(['\r', '\n'], " ");
+    if text.is_ascii() {
+        text
+    } else {
+        format!("=?UTF-8?B?{}?=", base64::engine::general_purpose::STANDARD.encode(text))
+    }
+}
+
+fn mailbox(address: &EmailAddress) -> String {
+    match &address.name {
+        Some(name) => format!("{} <{}>", encode_word(name), address.address),
+        None => address.address.clone(),
+    }
+}
+
+fn crlf(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\n', "\r\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const PERSONAL: &str = "Return-Path: <ana@example.com>\r\nFrom: Ana <ana@example.com>\r\nTo: me@example.org\r\n\
+        Subject: Lunch\r\nMessage-ID: <m2@example.com>\r\nReferences: <m1@example.org>\r\n\r\nTuesday?\r\n";
+
+    #[test]
+    fn test_loop_prevention() {
+        let own = ["me@example.org"];
+        let personal = IncomingMail::from_raw(PERSONAL.as_bytes()).unwrap();
+        assert_eq!(personal.reply_address(&own).map(|a| a.address.as_str()), Some("ana@example.com"));
+        assert_eq!(personal.references, vec!["m1@example.org", "m2@example.com"]);
+
+        for header in [
+            "Auto-Submitted: auto-replied",
+            "Precedence: bulk",
+            "List-Id: <team.lists.example.com>",
+            "X-Auto-Response-Suppress: OOF, AutoReply",
+        ] {
+            let raw = PERSONAL.replacen("Subject:", &format!("{}\r\nSubject:", header), 1);
+            let mail = IncomingMail::from_raw(raw.as_bytes()).unwrap();
+            assert!(mail.reply_address(&own).is_none(), "{}", header);
+        }
+        let manual = PERSONAL.replacen("Subject:", "Auto-Submitted: no\r\nSubject:", 1);
+        assert!(IncomingMail::from_raw(manual.as_bytes()).unwrap().reply_address(&own).is_some());
+
+        let bounce = PERSONAL.replace("<ana@example.com>\r\nFrom", "<>\r\nFrom");
+        assert!(IncomingMail::from_raw(bounce.as_bytes()).unwrap().sender.is_none());
+        let robot = PERSONAL.replace("ana@example.com", "No-Reply@shop.example.com");
+        assert!(IncomingMail::from_raw(robot.as_bytes()).unwrap().reply_address(&own).is_none());
+        let mine = PERSONAL.replace("ana@example.com", "ME@example.org");
+        assert!(IncomingMail::from_raw(mine.as_bytes()).unwrap().reply_address(&own).is_none());
+
+        let forwarded = PERSONAL.replacen("Subject:", "Auto-Submitted: auto-forwarded\r\nSubject:", 1);
+        assert!(IncomingMail::from_raw(forwarded.as_bytes()).unwrap().auto_forwarded);
+    }
+
+    #[test]
+    fn test_reply_and_forward_mime() {
+        let now = Utc.with_ymd_and_hms(2025, 3, 12, 9, 0, 0).unwrap();
+        let mail = IncomingMail::from_raw(PERSONAL.as_bytes()).unwrap();
+        let me = EmailAddress::new("me@example.org".to_string(), Some("Zoë".to_string()));
+        assert_eq!(mail.reply_subject(), "Re: Lunch");
+
+        let reply = String::from_utf8(mail.reply_mime(&me, "ana@example.com", "Re: Lunch", "Away.\nBack Monday.", false, now)).unwrap();
+        assert!(reply.contains("From: =?UTF-8?B?"));
+        assert!(reply.contains("\r\nAuto-Submitted: auto-replied\r\n"));
+        assert!(reply.contains("\r\nIn-Reply-To: <m2@example.com>\r\nReferences: <m1@example.org> <m2@example.com>\r\n"));
+        assert!(reply.ends_with("\r\n\r\nAway.\r\nBack Monday.\r\n"));
+
+        let forward = mail.forward_mime(PERSONAL.as_bytes(), &me, "archive@example.net", now);
+        let parsed = mail_parser::MessageParser::default().parse(&forward).unwrap();
+        assert_eq!(parsed.subject(), Some("Fwd: Lunch"));
+        assert_eq!(parsed.header_raw("Auto-Submitted").map(str::trim), Some("auto-forwarded"));
+        let attached = parsed.attachment(0).and_then(|a| a.message()).unwrap();
+        assert_eq!(attached.message_id(), Some("m2@example.com"));
+        assert_eq!(attached.body_text(0).as_deref().map(str::trim), Some("Tuesday?"));
+    }
+}
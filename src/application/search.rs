@@ -1,8 +1,11 @@
 //! Search engine
 //!
-//! Provides full-text search and filtering capabilities.
+//! Provides full-text search and filtering capabilities, and where the
+//! search text was found so results can highlight it.
 
+use crate::common::types::MessagePriority;
 use crate::common::Result;
+use std::ops::Range;
 use std::sync::{Arc, RwLock};
 
 /// Search query
@@ -10,6 +13,98 @@ use std::sync::{Arc, RwLock};
 pub struct SearchQuery {
     pub text: String,
     pub folder: Option<String>,
+    /// Only match messages marked high priority
+    pub high_priority_only: bool,
+}
+
+impl SearchQuery {
+    /// Whether a message with these fields matches. With "high priority
+    /// only" set, an empty search text matches every high-priority message.
+    pub fn matches(&self, subject: &str, from: &str, priority: MessagePriority) -> bool {
+        if self.high_priority_only && !priority.is_high() {
+            return false;
+        }
+        let needle = self.text.trim().to_lowercase();
+        if needle.is_empty() {
+            return self.high_priority_only;
+        }
+        subject.to_lowercase().contains(&needle) || from.to_lowercase().contains(&needle)
+    }
+
+    /// Like `matches`, also looking in the message body when there is one
+    pub fn matches_with_body(&self, subject: &str, from: &str, body: Option<&str>, priority: MessagePriority) -> bool {
+        self.matches(subject, from, priority)
+            || (!self.text.trim().is_empty()
+                && (!self.high_priority_only || priority.is_high())
+                && body.is_some_and(|b| !self.find_in(b).is_empty()))
+    }
+
+    /// Byte ranges of each occurrence of the search text in `text`, ignoring
+    /// case, without overlaps
+    pub fn find_in(&self, text: &str) -> Vec<Range<usize>> {
+        let needle: Vec<char> = self.text.trim().chars().flat_map(char::to_lowercase).collect();
+        let mut found = Vec::new();
+        if needle.is_empty() {
+            return found;
+        }
+        let mut next = 0;
+        for (start, _) in text.char_indices() {
+            if start < next {
+                continue;
+            }
+            let mut wanted = needle.iter();
+            let mut end = None;
+            for (i, c) in text[start..].char_indices() {
+                if !c.to_lowercase().all(|l| wanted.next() == Some(&l)) {
+                    break;
+                }
+                if wanted.len() == 0 {
+                    end = Some(start + i + c.len_utf8());
+                    break;
+                }
+            }
+            if let Some(end) = end {
+                found.push(start..end);
+                next = end;
+            }
+        }
+        found
+    }
+
+    /// Where the search text appears in a message's subject, sender and body
+    pub fn highlights(&self, subject: &str, from: &str, body: &str) -> SearchHighlights {
+        SearchHighlights { subject: self.find_in(subject), from: self.find_in(from), body: self.find_in(body) }
+    }
+}
+
+/// Occurrences of the search text in one message, as byte ranges
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchHighlights {
+    pub subject: Vec<Range<usize>>,
+    pub from: Vec<Range<usize>>,
+    pub body: Vec<Range<usize>>,
+}
+
+impl SearchHighlights {
+    /// Number of occurrences in all three
+    pub fn total(&self) -> usize {
+        self.subject.len() + self.from.len() + self.body.len()
+    }
+
+    /// "Matches: 3 (1 in the subject, 2 in the body)", read before the
+    /// message so it is clear why it was found
+    pub fn summary(&self) -> String {
+        let parts: Vec<String> = [(&self.subject, "subject"), (&self.from, "sender"), (&self.body, "body")]
+            .iter()
+            .filter(|(ranges, _)| !ranges.is_empty())
+            .map(|(ranges, place)| format!("{} in the {}", ranges.len(), place))
+            .collect();
+        if parts.is_empty() {
+            "Matches: 0".to_string()
+        } else {
+            format!("Matches: {} ({})", self.total(), parts.join(", "))
+        }
+    }
 }
 
 /// Search engine for messages
@@ -21,6 +116,7 @@ pub struct SearchEngine {
 struct SearchItem {
     folder: Option<String>,
     text: String,
+    priority: MessagePriority,
 }
 
 impl SearchEngine {
@@ -33,18 +129,23 @@ impl SearchEngine {
 
     /// Add content to local search index
     pub fn index_text(&self, folder: Option<String>, text: String) -> Result<()> {
+        self.index_message(folder, text, MessagePriority::Normal)
+    }
+
+    /// Add a message's text to the index along with its priority
+    pub fn index_message(&self, folder: Option<String>, text: String, priority: MessagePriority) -> Result<()> {
         let mut items = self
             .indexed_items
             .write()
             .map_err(|_| crate::common::Error::Other("Search index lock poisoned".to_string()))?;
-        items.push(SearchItem { folder, text });
+        items.push(SearchItem { folder, text, priority });
         Ok(())
     }
 
     /// Search for messages
     pub fn search(&self, query: &SearchQuery) -> Result<Vec<String>> {
         let needle = query.text.trim().to_lowercase();
-        if needle.is_empty() {
+        if needle.is_empty() && !query.high_priority_only {
             return Ok(Vec::new());
         }
         let items = self
@@ -59,6 +160,9 @@ impl SearchEngine {
                         return false;
                     }
                 }
+                if query.high_priority_only && !item.priority.is_high() {
+                    return false;
+                }
                 item.text.to_lowercase().contains(&needle)
             })
             .map(|item| item.text.clone())
@@ -98,9 +202,50 @@ mod tests {
         let q = SearchQuery {
             text: "invoice".to_string(),
             folder: Some("INBOX".to_string()),
+            high_priority_only: false,
         };
         let results = engine.search(&q).unwrap();
         assert_eq!(results.len(), 1);
         assert!(results[0].contains("Invoice"));
     }
+
+    #[test]
+    fn test_high_priority_only() {
+        let engine = SearchEngine::new().unwrap();
+        engine.index_text(None, "Invoice reminder".to_string()).unwrap();
+        engine.index_message(None, "Invoice overdue".to_string(), MessagePriority::High).unwrap();
+
+        let mut q = SearchQuery { text: "invoice".to_string(), folder: None, high_priority_only: true };
+        assert_eq!(engine.search(&q).unwrap(), ["Invoice overdue"]);
+        q.text.clear();
+        assert_eq!(engine.search(&q).unwrap().len(), 1);
+
+        assert!(q.matches("Server down", "ops@example.com", MessagePriority::High));
+        assert!(!q.matches("Lunch", "bob@example.com", MessagePriority::Normal));
+        q.high_priority_only = false;
+        assert!(!q.matches("Lunch", "bob@example.com", MessagePriority::Normal));
+        q.text = "BOB".to_string();
+        assert!(q.matches("Lunch", "bob@example.com", MessagePriority::Low));
+    }
+
+    #[test]
+    fn test_find_in_and_highlights() {
+        let q = SearchQuery { text: " Invoice ".to_string(), folder: None, high_priority_only: false };
+        let body = "Your INVOICE is attached. Invoices are due monthly.";
+        assert_eq!(q.find_in(body), [5..12, 26..33]);
+        assert!(q.find_in("Lunch").is_empty());
+        // Offsets are bytes of the original text
+        let q = SearchQuery { text: "straße".to_string(), folder: None, high_priority_only: false };
+        assert_eq!(q.find_in("Die STRASSE, die Straße").first(), Some(&(17..24)));
+        let q = SearchQuery { text: "aa".to_string(), folder: None, high_priority_only: false };
+        assert_eq!(q.find_in("aaa").len(), 1);
+
+        let q = SearchQuery { text: "invoice".to_string(), folder: None, high_priority_only: false };
+        assert!(q.matches_with_body("Lunch", "bob@example.com", Some(body), MessagePriority::Normal));
+        assert!(!q.matches_with_body("Lunch", "bob@example.com", None, MessagePriority::Normal));
+        let hits = q.highlights("Invoice 42", "billing@example.com", body);
+        assert_eq!(hits.total(), 3);
+        assert_eq!(hits.summary(), "Matches: 3 (1 in the subject, 2 in the body)");
+        assert_eq!(SearchHighlights::default().summary(), "Matches: 0");
+    }
 }
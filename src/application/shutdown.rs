@@ -0,0 +1,122 @@
+//! Stopping the app without losing work
+//!
+//! Background loops watch a [`Shutdown`] to know when to stop, and work that
+//! must not be cut short (an SMTP send, an outbox flush) holds a
+//! [`ShutdownGuard`] while it runs. On quit the app calls
+//! [`Shutdown::stop`], which waits, up to a limit, for the guards to drop.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// How long quitting waits for sends in progress
+pub const SHUTDOWN_WAIT: Duration = Duration::from_secs(10);
+
+/// Shared stop signal and count of work in progress
+#[derive(Debug, Clone, Default)]
+pub struct Shutdown {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    stopping: AtomicBool,
+    in_flight: AtomicUsize,
+    changed: Notify,
+}
+
+/// Work in progress; quitting waits until it is dropped
+#[derive(Debug)]
+pub struct ShutdownGuard {
+    inner: Arc<Inner>,
+}
+
+impl Drop for ShutdownGuard {
+    fn drop(&mut self) {
+        self.inner.in_flight.fetch_sub(1, Ordering::SeqCst);
+        self.inner.changed.notify_waiters();
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether [`Shutdown::stop`] has been called
+    pub fn is_stopping(&self) -> bool {
+        self.inner.stopping.load(Ordering::SeqCst)
+    }
+
+    /// Number of guards alive
+    pub fn in_flight(&self) -> usize {
+        self.inner.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Mark work as in progress until the guard drops. `None` once the app
+    /// is stopping, since new work would only be cut off.
+    pub fn guard(&self) -> Option<ShutdownGuard> {
+        self.inner.in_flight.fetch_add(1, Ordering::SeqCst);
+        let guard = ShutdownGuard { inner: self.inner.clone() };
+        (!self.is_stopping()).then_some(guard)
+    }
+
+    /// Resolves once the app starts stopping, for background loops to
+    /// select on
+    pub async fn stopped(&self) {
+        self.wait_until(|| self.is_stopping()).await
+    }
+
+    /// Tell background work to stop and wait up to `limit` for guarded work
+    /// to finish. Returns whether it all did.
+    pub async fn stop(&self, limit: Duration) -> bool {
+        self.inner.stopping.store(true, Ordering::SeqCst);
+        self.inner.changed.notify_waiters();
+        tokio::time::timeout(limit, self.wait_until(|| self.in_flight() == 0)).await.is_ok()
+    }
+
+    async fn wait_until(&self, done: impl Fn() -> bool) {
+        loop {
+            // Created before the check, so a change in between still wakes it
+            let changed = self.inner.changed.notified();
+            if done() {
+                return;
+            }
+            changed.await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_stop_waits_for_guarded_work() {
+        let shutdown = Shutdown::new();
+        let guard = shutdown.guard().unwrap();
+        let watcher = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move { shutdown.stopped().await }
+        });
+        let send = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            drop(guard);
+        });
+
+        assert!(shutdown.stop(Duration::from_secs(5)).await);
+        assert_eq!(shutdown.in_flight(), 0);
+        watcher.await.unwrap();
+        send.await.unwrap();
+        assert!(shutdown.guard().is_none(), "no new work once stopping");
+    }
+
+    #[tokio::test]
+    async fn test_stop_gives_up_after_limit() {
+        let shutdown = Shutdown::new();
+        let _stuck = shutdown.guard().unwrap();
+        assert!(!shutdown.stop(Duration::from_millis(20)).await);
+        assert_eq!(shutdown.in_flight(), 1);
+    }
+}
@@ -0,0 +1,103 @@
+//! Snooze times
+//!
+//! Works out when a snoozed message should reappear, from the preset
+//! choices in the "Snooze until" menu or a typed date and time.
+
+use crate::common::{Error, Result};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+
+/// Hour presets wake at, local time
+const MORNING_HOUR: u32 = 8;
+
+/// Preset snooze choices
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnoozePreset {
+    /// Three hours from now
+    LaterToday,
+    /// 8:00 tomorrow
+    Tomorrow,
+    /// 8:00 next Monday
+    NextWeek,
+}
+
+impl SnoozePreset {
+    /// All presets in menu order
+    pub const ALL: [SnoozePreset; 3] = [SnoozePreset::LaterToday, SnoozePreset::Tomorrow, SnoozePreset::NextWeek];
+
+    /// Label for the snooze menu
+    pub fn label(&self) -> &'static str {
+        match self {
+            SnoozePreset::LaterToday => "Later today (in 3 hours)",
+            SnoozePreset::Tomorrow => "Tomorrow morning",
+            SnoozePreset::NextWeek => "Next week (Monday morning)",
+        }
+    }
+
+    /// When a message snoozed at `now` wakes
+    pub fn wake_at<Tz: TimeZone>(&self, now: DateTime<Tz>) -> DateTime<Tz> {
+        match self {
+            SnoozePreset::LaterToday => now + Duration::hours(3),
+            SnoozePreset::Tomorrow => at_morning(&now, now.date_naive() + Duration::days(1)),
+            SnoozePreset::NextWeek => {
+                let days = 7 - now.weekday().num_days_from_monday() as i64;
+                at_morning(&now, now.date_naive() + Duration::days(days))
+            }
+        }
+    }
+}
+
+fn at_morning<Tz: TimeZone>(now: &DateTime<Tz>, date: NaiveDate) -> DateTime<Tz> {
+    let local = date.and_time(NaiveTime::from_hms_opt(MORNING_HOUR, 0, 0).expect("valid hour"));
+    now.timezone()
+        .from_local_datetime(&local)
+        .earliest()
+        .unwrap_or_else(|| now.clone() + Duration::days(1))
+}
+
+/// Parse a typed wake time: `YYYY-MM-DD HH:MM`, or `YYYY-MM-DD` for 8:00
+/// that day. The time must be in the future.
+pub fn parse_wake_time(text: &str, now: DateTime<Local>) -> Result<DateTime<Local>> {
+    let text = text.trim();
+    let naive = NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M")
+        .or_else(|_| {
+            NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .map(|d| d.and_time(NaiveTime::from_hms_opt(MORNING_HOUR, 0, 0).expect("valid hour")))
+        })
+        .map_err(|_| Error::Parse(format!("\"{}\" is not a date like 2025-03-14 or 2025-03-14 17:30", text)))?;
+    let wake_at = Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .ok_or_else(|| Error::Parse(format!("{} doesn't exist in the local time zone", text)))?;
+    if wake_at <= now {
+        return Err(Error::Config("Choose a time in the future".to_string()));
+    }
+    Ok(wake_at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn test_snooze_presets() {
+        // Wednesday afternoon
+        let now = Utc.with_ymd_and_hms(2025, 3, 12, 15, 30, 0).unwrap();
+        assert_eq!(SnoozePreset::LaterToday.wake_at(now), Utc.with_ymd_and_hms(2025, 3, 12, 18, 30, 0).unwrap());
+        assert_eq!(SnoozePreset::Tomorrow.wake_at(now), Utc.with_ymd_and_hms(2025, 3, 13, 8, 0, 0).unwrap());
+        assert_eq!(SnoozePreset::NextWeek.wake_at(now), Utc.with_ymd_and_hms(2025, 3, 17, 8, 0, 0).unwrap());
+        // On a Monday, next week is the following Monday
+        let monday = Utc.with_ymd_and_hms(2025, 3, 17, 9, 0, 0).unwrap();
+        assert_eq!(SnoozePreset::NextWeek.wake_at(monday), Utc.with_ymd_and_hms(2025, 3, 24, 8, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_parse_wake_time() {
+        let now = Local.with_ymd_and_hms(2025, 3, 12, 15, 30, 0).unwrap();
+        let at = parse_wake_time(" 2025-03-14 17:45 ", now).unwrap();
+        assert_eq!((at.day(), at.format("%H:%M").to_string()), (14, "17:45".to_string()));
+        assert_eq!(parse_wake_time("2025-03-13", now).unwrap().format("%H:%M").to_string(), "08:00");
+        assert!(parse_wake_time("2025-03-01 09:00", now).is_err());
+        assert!(parse_wake_time("next tuesday", now).is_err());
+    }
+}
@@ -0,0 +1,179 @@
+//! Local spam classifier
+//!
+//! A naive Bayes classifier trained only from the user's Mark as Spam and
+//! Mark as Not Spam choices. Nothing leaves the computer: token counts live
+//! in the message cache, per account.
+
+use crate::common::Result;
+use crate::data::message_cache::{CachedMessage, MessageCache};
+use std::collections::{BTreeSet, HashMap};
+
+/// Messages of each kind the classifier must have seen before it scores
+const MIN_TRAINED: u32 = 5;
+/// Tokens that decide a score: the ones furthest from neutral
+const DECIDING_TOKENS: usize = 20;
+/// Longest token kept; longer runs are encoded data, not words
+const MAX_TOKEN_LEN: usize = 30;
+
+/// Words of a message the classifier learns from: its subject, sender
+/// and body, lower-cased, each once. Subject words and the sender's
+/// domain are kept apart from body words, as they say more.
+pub fn tokens(message: &CachedMessage) -> Vec<String> {
+    let mut found = BTreeSet::new();
+    let words = |text: &str| {
+        text.split(|c: char| !c.is_alphanumeric() && c != '\'' && c != '$')
+            .map(|w| w.trim_matches('\'').to_lowercase())
+            .filter(|w| (3..=MAX_TOKEN_LEN).contains(&w.chars().count()) && !w.chars().all(|c| c.is_ascii_digit()))
+            .collect::<Vec<_>>()
+    };
+    found.extend(words(&message.subject).into_iter().map(|w| format!("subject:{}", w)));
+    if let Some((_, domain)) = message.from_addr.rsplit_once('@') {
+        let domain = domain.trim_end_matches(|c: char| c == '>' || c.is_whitespace()).to_lowercase();
+        if !domain.is_empty() {
+            found.insert(format!("from:{}", domain));
+        }
+    }
+    let body = message.body_plain.as_deref().or(message.body_html.as_deref()).unwrap_or_default();
+    found.extend(words(body));
+    found.into_iter().collect()
+}
+
+/// Chance, from 0 to 1, that a message with `tokens` is spam, given how
+/// often each appeared in spam and in other mail (`counts`) out of
+/// `spam_messages` and `ham_messages` trained. `None` until enough of both
+/// kinds have been trained to tell them apart.
+pub fn spam_probability(
+    tokens: &[String],
+    counts: &HashMap<String, (u32, u32)>,
+    spam_messages: u32,
+    ham_messages: u32,
+) -> Option<f64> {
+    if spam_messages < MIN_TRAINED || ham_messages < MIN_TRAINED {
+        return None;
+    }
+    // Log-likelihood ratio of each known token, smoothed so a token seen
+    // only once on one side doesn't decide the message alone
+    let mut evidence: Vec<f64> = tokens
+        .iter()
+        .filter_map(|t| counts.get(t))
+        .map(|&(spam, ham)| {
+            let in_spam = (spam as f64 + 1.0) / (spam_messages as f64 + 2.0);
+            let in_ham = (ham as f64 + 1.0) / (ham_messages as f64 + 2.0);
+            (in_spam / in_ham).ln()
+        })
+        .collect();
+    evidence.sort_by(|a, b| b.abs().total_cmp(&a.abs()));
+    let log_odds: f64 = evidence.iter().take(DECIDING_TOKENS).sum();
+    Some(1.0 / (1.0 + (-log_odds).exp()))
+}
+
+/// The spam classifier for one account
+pub struct SpamClassifier<'a> {
+    cache: &'a MessageCache,
+    account_id: &'a str,
+}
+
+impl<'a> SpamClassifier<'a> {
+    pub fn new(cache: &'a MessageCache, account_id: &'a str) -> Self {
+        Self { cache, account_id }
+    }
+
+    /// Learn from a message marked as spam or not spam. Marking a message
+    /// the other way later retrains on it. Returns false if it was already
+    /// trained this way.
+    pub fn train(&self, message: &CachedMessage, spam: bool) -> Result<bool> {
+        self.cache.train_spam(self.account_id, &message_key(message), &tokens(message), spam)
+    }
+
+    /// Chance, from 0 to 1, that a message is spam; `None` until trained
+    /// on enough of both kinds
+    pub fn score(&self, message: &CachedMessage) -> Result<Option<f64>> {
+        let (spam_messages, ham_messages) = self.cache.spam_training_counts(self.account_id)?;
+        if spam_messages < MIN_TRAINED || ham_messages < MIN_TRAINED {
+            return Ok(None);
+        }
+        let tokens = tokens(message);
+        let counts = self.cache.spam_token_counts(self.account_id, &tokens)?;
+        Ok(spam_probability(&tokens, &counts, spam_messages, ham_messages))
+    }
+}
+
+/// What a trained message is remembered by: its Message-ID, or its sender,
+/// date and subject when it has none
+fn message_key(message: &CachedMessage) -> String {
+    if message.message_id.trim().is_empty() {
+        format!("{}|{}|{}", message.from_addr, message.date, message.subject)
+    } else {
+        message.message_id.trim().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::types::MessagePriority;
+    use std::env;
+
+    fn message(id: &str, from: &str, subject: &str, body: &str) -> CachedMessage {
+        CachedMessage {
+            id: 0,
+            uid: 0,
+            folder_id: 0,
+            message_id: id.to_string(),
+            subject: subject.to_string(),
+            from_addr: from.to_string(),
+            to_addr: "me@example.com".to_string(),
+            cc: None,
+            date: "2026-01-01".to_string(),
+            body_plain: Some(body.to_string()),
+            body_html: None,
+            read: false,
+            starred: false,
+            deleted: false,
+            priority: MessagePriority::Normal,
+        }
+    }
+
+    #[test]
+    fn test_tokens() {
+        let body = "Claim your $1000 prize, claim it now! 12345";
+        let tokens = tokens(&message("<1@x>", "Deals <win@Prizes.Example>", "You WON", body));
+        assert!(tokens.contains(&"subject:won".to_string()));
+        assert!(tokens.contains(&"from:prizes.example".to_string()));
+        assert!(tokens.contains(&"claim".to_string()));
+        assert!(tokens.contains(&"$1000".to_string()));
+        assert!(!tokens.contains(&"12345".to_string()));
+        assert!(!tokens.contains(&"it".to_string()));
+        assert_eq!(tokens.iter().filter(|t| *t == "claim").count(), 1);
+    }
+
+    #[test]
+    fn test_classifier_learns_and_unlearns() {
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_classifier_{}", std::process::id()));
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+        let classifier = SpamClassifier::new(&cache, "acc");
+        let spam = |i: u32| {
+            message(&format!("<s{}@x>", i), "win@prizes.example", "Claim your prize", "Cheap pills, claim your free prize now")
+        };
+        let ham = |i: u32| {
+            message(&format!("<h{}@x>", i), "ana@example.com", "Project meeting", "Agenda for the project meeting on Monday")
+        };
+
+        assert_eq!(classifier.score(&spam(0)).unwrap(), None);
+        for i in 1..=MIN_TRAINED {
+            assert!(classifier.train(&spam(i), true).unwrap());
+            assert!(classifier.train(&ham(i), false).unwrap());
+        }
+        let new_spam = message("<new1@x>", "deals@prizes.example", "Free prize inside", "Claim cheap pills now");
+        let new_ham = message("<new2@x>", "ana@example.com", "Meeting notes", "Notes from the project meeting");
+        assert!(classifier.score(&new_spam).unwrap().unwrap() > 0.9);
+        assert!(classifier.score(&new_ham).unwrap().unwrap() < 0.1);
+
+        // Correcting every spam message as not spam leaves nothing to
+        // tell the kinds apart by
+        for i in 1..=MIN_TRAINED {
+            assert!(classifier.train(&spam(i), false).unwrap());
+        }
+        assert_eq!(classifier.score(&new_spam).unwrap(), None);
+    }
+}
@@ -5,6 +5,7 @@
 
 use wixen_mail::{
     common::logging::{init_logging, LoggerConfig},
+    common::profile::ProfilePaths,
     presentation::WxMailApp,
 };
 
@@ -16,8 +17,16 @@ fn main() {
 
     tracing::info!("Starting Wixen Mail with wxdragon UI");
 
+    // Settings and cache of the profile named by --profile or the environment
+    let profile = ProfilePaths::resolve_from_env_and_args(std::env::args().skip(1))
+        .unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(2)
+        })
+        .activate();
+
     // Create and run wxdragon app
-    let app = WxMailApp::new().expect("Failed to create wxdragon app");
+    let app = WxMailApp::new(profile).expect("Failed to create wxdragon app");
     if let Err(e) = app.run() {
         eprintln!("UI error: {}", e);
         std::process::exit(1);
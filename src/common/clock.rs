@@ -0,0 +1,88 @@
+//! Source of the current time
+//!
+//! Code that stamps or compares times asks a [`Clock`] instead of calling
+//! `Utc::now()` itself, so tests can hold time still or move it forward
+//! with a [`MockClock`].
+
+use chrono::{DateTime, Duration, Local, NaiveDate, Utc};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// A clock shared between the parts of the app that need the time
+pub type SharedClock = Arc<dyn Clock>;
+
+/// Tells the time
+pub trait Clock: Send + Sync + fmt::Debug {
+    /// The current instant
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Today's date in the local time zone
+    fn today(&self) -> NaiveDate {
+        self.now().with_timezone(&Local).date_naive()
+    }
+}
+
+/// The system clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl SystemClock {
+    /// The system clock, ready to share
+    pub fn shared() -> SharedClock {
+        Arc::new(SystemClock)
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock that only moves when told to
+#[derive(Debug)]
+pub struct MockClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl MockClock {
+    /// A clock stopped at `now`
+    pub fn new(now: DateTime<Utc>) -> Arc<Self> {
+        Arc::new(Self { now: Mutex::new(now) })
+    }
+
+    /// Set the time
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner()) = now;
+    }
+
+    /// Move the time forward by `by`
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner()) += by;
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_mock_clock() {
+        let start = Utc.with_ymd_and_hms(2025, 3, 12, 9, 0, 0).unwrap();
+        let clock = MockClock::new(start);
+        let shared: SharedClock = clock.clone();
+        assert_eq!(shared.now(), start);
+
+        clock.advance(Duration::minutes(90));
+        assert_eq!(shared.now(), start + Duration::minutes(90));
+        clock.set(start);
+        assert_eq!(shared.now(), start);
+    }
+}
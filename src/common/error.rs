@@ -11,12 +11,26 @@ pub enum Error {
     Database(String),
     /// Network error
     Network(String),
+    /// Server didn't answer in time
+    Timeout(String),
+    /// Server name couldn't be resolved
+    HostNotFound(String),
+    /// Server is reachable but nothing accepts connections on the port
+    ConnectionRefused(String),
+    /// TLS handshake failed or the server's certificate isn't trusted
+    Certificate(String),
+    /// The server asked to slow down, or a configured rate limit was reached
+    RateLimited(String),
     /// Authentication error
     Authentication(String),
     /// Protocol error (IMAP/SMTP/POP3)
     Protocol(String),
     /// Security error (encryption/decryption/key management)
     Security(String),
+    /// A message, folder, account or other record that doesn't exist
+    NotFound(String),
+    /// Text that couldn't be read: a date, address, token or message
+    Parse(String),
     /// IO error
     Io(std::io::Error),
     /// Generic error
@@ -29,6 +43,44 @@ impl Error {
     pub fn auth(msg: String) -> Self {
         Error::Authentication(msg)
     }
+
+    /// Whether trying again later may succeed without the user changing
+    /// anything
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Error::Timeout(_) | Error::ConnectionRefused(_) | Error::Network(_))
+    }
+
+    /// What the user can do about a connection failure, if anything
+    pub fn troubleshooting(&self) -> Option<&'static str> {
+        match self {
+            Error::HostNotFound(_) => {
+                Some("Check the server name in the account settings and that you are connected to the internet.")
+            }
+            Error::ConnectionRefused(_) => {
+                Some("Check the port number and security setting; the server may also be down for maintenance.")
+            }
+            Error::Timeout(_) => Some(
+                "The server is slow or unreachable. Check your connection, or raise the timeouts in Settings.",
+            ),
+            Error::Certificate(_) => Some(
+                "The server's identity could not be verified. Check the server name and security setting, \
+                 and the system date and time.",
+            ),
+            Error::Authentication(_) => Some(
+                "Check the username and password. Some providers require an app password when two-step \
+                 verification is on.",
+            ),
+            Error::RateLimited(_) => Some(
+                "The server limits how often an account may connect or send. Wait a few minutes, or lower the \
+                 rate limits in Settings.",
+            ),
+            Error::Network(_) => Some("Check your internet connection and try again."),
+            Error::Database(_) => {
+                Some("The local mail cache couldn't be read or written. Check there is free disk space and try again.")
+            }
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for Error {
@@ -37,9 +89,16 @@ impl fmt::Display for Error {
             Error::Config(msg) => write!(f, "Configuration error: {}", msg),
             Error::Database(msg) => write!(f, "Database error: {}", msg),
             Error::Network(msg) => write!(f, "Network error: {}", msg),
+            Error::Timeout(msg) => write!(f, "Timed out: {}", msg),
+            Error::HostNotFound(msg) => write!(f, "Server not found: {}", msg),
+            Error::ConnectionRefused(msg) => write!(f, "Connection refused: {}", msg),
+            Error::Certificate(msg) => write!(f, "Certificate error: {}", msg),
+            Error::RateLimited(msg) => write!(f, "Rate limited: {}", msg),
             Error::Authentication(msg) => write!(f, "Authentication error: {}", msg),
             Error::Protocol(msg) => write!(f, "Protocol error: {}", msg),
             Error::Security(msg) => write!(f, "Security error: {}", msg),
+            Error::NotFound(msg) => write!(f, "Not found: {}", msg),
+            Error::Parse(msg) => write!(f, "Parse error: {}", msg),
             Error::Io(err) => write!(f, "IO error: {}", err),
             Error::Other(msg) => write!(f, "Error: {}", msg),
         }
@@ -84,4 +143,30 @@ mod tests {
         let err = Error::Security("Decryption failed".to_string());
         assert!(err.to_string().contains("Security error"));
     }
+
+    #[test]
+    fn test_connection_errors() {
+        let err = Error::HostNotFound("Could not find a server called imap.example.con".to_string());
+        assert!(err.to_string().contains("Server not found"));
+        assert!(err.troubleshooting().unwrap().contains("server name"));
+        assert!(!err.is_transient());
+        assert!(Error::Timeout("slow".to_string()).is_transient());
+        assert!(Error::auth("rejected".to_string()).troubleshooting().unwrap().contains("password"));
+        assert!(Error::Config("bad".to_string()).troubleshooting().is_none());
+        // Retrying at once would only prolong the throttling
+        let err = Error::RateLimited("smtp.example.com: 421 Too many connections".to_string());
+        assert!(err.to_string().starts_with("Rate limited"));
+        assert!(err.troubleshooting().unwrap().contains("Wait"));
+        assert!(!err.is_transient());
+    }
+
+    #[test]
+    fn test_not_found_and_parse_errors() {
+        let err = Error::NotFound("Message 7 is not cached".to_string());
+        assert_eq!(err.to_string(), "Not found: Message 7 is not cached");
+        let err = Error::Parse("\"soon\" is not a date".to_string());
+        assert_eq!(err.to_string(), "Parse error: \"soon\" is not a date");
+        assert!(err.troubleshooting().is_none() && !err.is_transient());
+        assert!(Error::Database("disk I/O error".to_string()).troubleshooting().unwrap().contains("disk space"));
+    }
 }
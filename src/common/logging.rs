@@ -1,11 +1,25 @@
 //! Logging framework with privacy-aware features
 //!
 //! Provides structured logging with file rotation and privacy protection.
+//! Recent events are also kept in memory for the log viewer, and the level
+//! can be changed while the app runs.
 
+use chrono::{DateTime, Local};
+use std::collections::VecDeque;
+use std::fmt::Write as _;
 use std::path::PathBuf;
-use tracing::Level;
+use std::sync::{Arc, Mutex, OnceLock};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
 use tracing_appender::non_blocking::WorkerGuard;
-use tracing_subscriber::{fmt, layer::SubscriberExt, EnvFilter, Layer, Registry};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::{fmt, reload, EnvFilter, Layer, Registry};
+
+/// Number of recent events kept for the log viewer
+pub const LOG_BUFFER_CAPACITY: usize = 2000;
+
+static RECENT: OnceLock<Arc<LogBuffer>> = OnceLock::new();
+static LEVEL: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
 
 /// Privacy-aware string wrapper that masks sensitive data in logs
 #[derive(Debug)]
@@ -51,6 +65,17 @@ impl LogLevel {
         }
     }
 
+    /// Lower-case name, as stored in settings
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        }
+    }
+
     /// Parse from string
     pub fn parse(s: &str) -> Option<Self> {
         match s.to_lowercase().as_str() {
@@ -106,31 +131,29 @@ pub fn init_logging(config: LoggerConfig) -> Result<WorkerGuard, Box<dyn std::er
     let file_appender = tracing_appender::rolling::daily(&config.log_dir, &config.log_file_prefix);
     let (non_blocking, guard) = tracing_appender::non_blocking(file_appender);
 
-    // Create filter
-    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| {
-        EnvFilter::new(format!(
-            "wixen_mail={}",
-            config.level.to_tracing_level().as_str()
-        ))
-    });
+    // Create filter; RUST_LOG, when set, wins over the configured level
+    let from_env = EnvFilter::try_from_default_env().ok();
+    let use_env = from_env.is_some();
+    let filter = from_env.unwrap_or_else(|| level_filter(config.level));
+    let (filter, handle) = reload::Layer::new(filter);
 
     // Create layers
-    let file_layer = fmt::layer()
-        .with_writer(non_blocking)
-        .with_ansi(false)
-        .with_filter(filter.clone());
-
-    let subscriber = Registry::default().with(file_layer);
-
-    // Add console layer if enabled
-    if config.console_logging {
-        let console_layer = fmt::layer()
-            .with_writer(std::io::stdout)
-            .with_filter(filter);
-        let subscriber = subscriber.with(console_layer);
-        tracing::subscriber::set_global_default(subscriber)?;
-    } else {
-        tracing::subscriber::set_global_default(subscriber)?;
+    let file_layer = fmt::layer().with_writer(non_blocking).with_ansi(false);
+    let console_layer = config
+        .console_logging
+        .then(|| fmt::layer().with_writer(std::io::stdout));
+    let buffer = RECENT
+        .get_or_init(|| Arc::new(LogBuffer::new(LOG_BUFFER_CAPACITY)))
+        .clone();
+
+    let subscriber = Registry::default()
+        .with(filter)
+        .with(file_layer)
+        .with(console_layer)
+        .with(LogBufferLayer::new(buffer));
+    tracing::subscriber::set_global_default(subscriber)?;
+    if !use_env {
+        let _ = LEVEL.set(handle);
     }
 
     tracing::info!("Logging initialized at level: {:?}", config.level);
@@ -138,6 +161,148 @@ pub fn init_logging(config: LoggerConfig) -> Result<WorkerGuard, Box<dyn std::er
     Ok(guard)
 }
 
+fn level_filter(level: LogLevel) -> EnvFilter {
+    EnvFilter::new(format!("wixen_mail={}", level.as_str()))
+}
+
+/// Change the log level while running. Does nothing before `init_logging`,
+/// or when `RUST_LOG` chose the filter.
+pub fn set_log_level(level: LogLevel) {
+    if let Some(handle) = LEVEL.get() {
+        match handle.reload(level_filter(level)) {
+            Ok(()) => tracing::info!("Log level set to {}", level.as_str()),
+            Err(e) => tracing::warn!("Could not change the log level: {}", e),
+        }
+    }
+}
+
+/// Events logged recently, oldest first
+pub fn recent_logs() -> Vec<LogEntry> {
+    RECENT
+        .get()
+        .map(|buffer| buffer.entries())
+        .unwrap_or_default()
+}
+
+/// One logged event
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub time: DateTime<Local>,
+    pub level: Level,
+    /// Module that logged it, e.g. `wixen_mail::service::protocols::imap`
+    pub target: String,
+    /// Message followed by any other fields as `name=value`
+    pub message: String,
+}
+
+impl LogEntry {
+    /// Whether the entry is at `min_level` or more severe and contains
+    /// `query`, ignoring case. An empty query matches everything.
+    pub fn matches(&self, min_level: LogLevel, query: &str) -> bool {
+        if self.level > min_level.to_tracing_level() {
+            return false;
+        }
+        let query = query.trim().to_lowercase();
+        query.is_empty()
+            || self.message.to_lowercase().contains(&query)
+            || self.target.to_lowercase().contains(&query)
+    }
+}
+
+impl std::fmt::Display for LogEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {:>5} {}: {}",
+            self.time.format("%Y-%m-%d %H:%M:%S"),
+            self.level,
+            self.target,
+            self.message
+        )
+    }
+}
+
+/// Ring buffer of recent events; the oldest is dropped when full
+pub struct LogBuffer {
+    entries: Mutex<VecDeque<LogEntry>>,
+    capacity: usize,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+        }
+    }
+
+    pub fn push(&self, entry: LogEntry) {
+        if let Ok(mut entries) = self.entries.lock() {
+            if entries.len() >= self.capacity {
+                entries.pop_front();
+            }
+            entries.push_back(entry);
+        }
+    }
+
+    /// Copy of the buffered entries, oldest first
+    pub fn entries(&self) -> Vec<LogEntry> {
+        self.entries
+            .lock()
+            .map(|entries| entries.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Layer that copies each event into a `LogBuffer`
+pub struct LogBufferLayer {
+    buffer: Arc<LogBuffer>,
+}
+
+impl LogBufferLayer {
+    pub fn new(buffer: Arc<LogBuffer>) -> Self {
+        Self { buffer }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for LogBufferLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut fields = FieldText::default();
+        event.record(&mut fields);
+        let metadata = event.metadata();
+        self.buffer.push(LogEntry {
+            time: Local::now(),
+            level: *metadata.level(),
+            target: metadata.target().to_string(),
+            message: fields.0,
+        });
+    }
+}
+
+/// Formats an event's fields: the message first, then `name=value` pairs
+#[derive(Default)]
+struct FieldText(String);
+
+impl FieldText {
+    fn add(&mut self, field: &Field, value: std::fmt::Arguments<'_>) {
+        if field.name() == "message" {
+            self.0.insert_str(0, &value.to_string());
+        } else {
+            let _ = write!(self.0, " {}={}", field.name(), value);
+        }
+    }
+}
+
+impl Visit for FieldText {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.add(field, format_args!("{}", value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.add(field, format_args!("{:?}", value));
+    }
+}
+
 /// Mask email address for privacy
 pub fn mask_email(email: &str) -> String {
     if let Some(at_pos) = email.find('@') {
@@ -183,6 +348,46 @@ mod tests {
         assert_eq!(LogLevel::parse("invalid"), None);
     }
 
+    #[test]
+    fn test_log_level_names_round_trip() {
+        for level in [
+            LogLevel::Error,
+            LogLevel::Warn,
+            LogLevel::Info,
+            LogLevel::Debug,
+            LogLevel::Trace,
+        ] {
+            assert_eq!(LogLevel::parse(level.as_str()), Some(level));
+        }
+    }
+
+    #[test]
+    fn test_log_buffer_keeps_recent_events() {
+        let buffer = Arc::new(LogBuffer::new(2));
+        let subscriber = Registry::default().with(LogBufferLayer::new(buffer.clone()));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("first");
+            tracing::warn!(folder = "INBOX", "Sync failed: {}", "timeout");
+            tracing::debug!("third");
+        });
+
+        let entries = buffer.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].level, Level::WARN);
+        assert_eq!(entries[0].message, "Sync failed: timeout folder=INBOX");
+        assert_eq!(entries[1].message, "third");
+        assert!(entries[0].to_string().ends_with(
+            "WARN wixen_mail::common::logging::tests: Sync failed: timeout folder=INBOX"
+        ));
+
+        assert!(entries[0].matches(LogLevel::Warn, "inbox"));
+        assert!(entries[0].matches(LogLevel::Info, ""));
+        assert!(!entries[0].matches(LogLevel::Error, ""));
+        assert!(!entries[0].matches(LogLevel::Trace, "smtp"));
+        assert!(entries[1].matches(LogLevel::Trace, "logging"));
+        assert!(!entries[1].matches(LogLevel::Info, ""));
+    }
+
     #[test]
     fn test_mask_email() {
         assert_eq!(mask_email("user@example.com"), "us***@example.com");
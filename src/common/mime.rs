@@ -0,0 +1,311 @@
+//! MIME text decoding
+//!
+//! Headers with non-ASCII text arrive as RFC 2047 encoded words, such as
+//! `=?UTF-8?B?Q2Fmw6k=?=`. Parsing a whole message decodes them, but the
+//! headers fetched for the message list come in raw and are decoded here
+//! before they are shown, cached or searched.
+//!
+//! Bodies arrive in a transfer encoding (quoted-printable or base64) and
+//! often in a charset other than UTF-8. `mail-parser` gives up on a part
+//! with any broken encoding and leaves it raw; the decoders here do what
+//! they can with it instead, so a stray `=` doesn't leave `=3D` all over
+//! the message.
+
+use base64::Engine;
+use mail_parser::{Message, MimeHeaders};
+
+/// Decode the RFC 2047 encoded words in a header value, B and Q, in any
+/// charset. Whitespace between adjacent encoded words is dropped, and
+/// adjacent words in the same charset are decoded together, so a
+/// character split across two words comes out whole. Folded lines are
+/// unfolded. Text that isn't a well-formed encoded word is left as it is.
+pub fn decode_header(value: &str) -> String {
+    let value = unfold(value);
+    let mut out = String::new();
+    // Bytes of adjacent encoded words in one charset, decoded together
+    let mut pending: Option<(String, Vec<u8>)> = None;
+    // Whitespace since the last encoded word, kept only if text follows
+    let mut gap = String::new();
+    let mut rest = value.as_str();
+
+    while !rest.is_empty() {
+        if let Some((charset, bytes, len)) = encoded_word(rest) {
+            gap.clear();
+            match &mut pending {
+                Some((current, buffer)) if current.eq_ignore_ascii_case(&charset) => buffer.extend(bytes),
+                _ => {
+                    if let Some((current, buffer)) = pending.take() {
+                        out.push_str(&to_text(&current, &buffer));
+                    }
+                    pending = Some((charset, bytes));
+                }
+            }
+            rest = &rest[len..];
+            continue;
+        }
+        let c = rest.chars().next().unwrap_or_default();
+        if c.is_whitespace() && pending.is_some() {
+            gap.push(c);
+        } else {
+            if let Some((charset, buffer)) = pending.take() {
+                out.push_str(&to_text(&charset, &buffer));
+            }
+            out.push_str(&gap);
+            gap.clear();
+            out.push(c);
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    if let Some((charset, buffer)) = pending {
+        out.push_str(&to_text(&charset, &buffer));
+    }
+    out.push_str(&gap);
+    out
+}
+
+/// Join folded header lines: a line break followed by whitespace
+fn unfold(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut lines = value.split('\n').peekable();
+    while let Some(line) = lines.next() {
+        out.push_str(line.strip_suffix('\r').unwrap_or(line));
+        if lines.peek().is_some_and(|next| !next.starts_with([' ', '\t'])) {
+            out.push(' ');
+        }
+    }
+    out
+}
+
+/// The encoded word at the start of `text`: its charset, decoded bytes
+/// and length in `text`. `None` if `text` doesn't start with one.
+fn encoded_word(text: &str) -> Option<(String, Vec<u8>, usize)> {
+    let inner = text.strip_prefix("=?")?;
+    let (charset, inner) = inner.split_once('?')?;
+    let (encoding, inner) = inner.split_once('?')?;
+    let end = inner.find("?=")?;
+    let encoded = &inner[..end];
+    let len = text.len() - inner[end + 2..].len();
+    if charset.is_empty() || encoded.contains(char::is_whitespace) || charset.contains(char::is_whitespace) {
+        return None;
+    }
+    let bytes = match encoding {
+        "B" | "b" => {
+            let trimmed = encoded.trim_end_matches('=');
+            base64::engine::general_purpose::STANDARD_NO_PAD.decode(trimmed).ok()?
+        }
+        "Q" | "q" => decode_q(encoded)?,
+        _ => return None,
+    };
+    // RFC 2231 allows a language after the charset: `UTF-8*en`
+    let charset = charset.split('*').next().unwrap_or(charset).to_string();
+    Some((charset, bytes, len))
+}
+
+/// Decode Q encoding: `_` is a space and `=XX` a byte in hex
+fn decode_q(encoded: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut input = encoded.bytes();
+    while let Some(b) = input.next() {
+        match b {
+            b'_' => bytes.push(b' '),
+            b'=' => {
+                let hex = [input.next()?, input.next()?];
+                bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+            }
+            _ => bytes.push(b),
+        }
+    }
+    Some(bytes)
+}
+
+/// Text of a body part in `transfer_encoding` (`quoted-printable`,
+/// `base64`, or anything else for none) and `charset`. Broken encoding is
+/// decoded as far as it can be. With no charset, text that isn't UTF-8 is
+/// read as Windows-1252, the usual unlabelled charset.
+pub fn decode_body(content: &[u8], transfer_encoding: &str, charset: Option<&str>) -> String {
+    let bytes = match transfer_encoding.trim().to_ascii_lowercase().as_str() {
+        "quoted-printable" => decode_quoted_printable(content),
+        "base64" => decode_base64(content),
+        _ => content.to_vec(),
+    };
+    match charset.map(str::trim).filter(|c| !c.is_empty()) {
+        Some(charset) => to_text(charset, &bytes),
+        None => match String::from_utf8(bytes) {
+            Ok(text) => text,
+            Err(e) => to_text("windows-1252", e.as_bytes()),
+        },
+    }
+}
+
+/// The plain-text and HTML bodies of a parsed message, decoded to UTF-8.
+/// Parts `mail-parser` couldn't decode are decoded with [`decode_body`].
+pub fn message_texts(message: &Message) -> (Option<String>, Option<String>) {
+    let plain = message.text_part(0).filter(|p| !p.is_text_html()).and_then(|p| p.text_contents());
+    let html = message.html_part(0).filter(|p| p.is_text_html()).and_then(|p| p.text_contents());
+    let plain = plain.map(str::to_string).or_else(|| undecoded_text(message, "plain"));
+    let html = html.map(str::to_string).or_else(|| undecoded_text(message, "html"));
+    (plain, html)
+}
+
+/// The first `text/<subtype>` part with broken encoding that isn't an
+/// attachment, decoded as far as it can be. `mail-parser` leaves such
+/// parts out of the message's text and HTML bodies.
+fn undecoded_text(message: &Message, subtype: &str) -> Option<String> {
+    message
+        .parts
+        .iter()
+        .filter(|part| part.is_encoding_problem)
+        .filter(|part| !part.content_disposition().is_some_and(|d| d.is_attachment()))
+        .filter(|part| match part.content_type() {
+            Some(ct) => ct.ctype().eq_ignore_ascii_case("text") && ct.subtype().is_some_and(|s| s.eq_ignore_ascii_case(subtype)),
+            None => subtype == "plain",
+        })
+        .find_map(|part| {
+            let content = message.raw_message.get(part.offset_body..part.offset_end)?;
+            let charset = part.content_type().and_then(|ct| ct.attribute("charset"));
+            Some(decode_body(content, part.content_transfer_encoding().unwrap_or_default(), charset))
+        })
+}
+
+/// Decode quoted-printable: `=XX` is a byte in hex and `=` at the end of
+/// a line joins it to the next. An `=` followed by anything else is kept.
+fn decode_quoted_printable(content: &[u8]) -> Vec<u8> {
+    let hex = |b: u8| (b as char).to_digit(16).map(|d| d as u8);
+    let mut bytes = Vec::with_capacity(content.len());
+    let mut i = 0;
+    while i < content.len() {
+        if content[i] != b'=' {
+            bytes.push(content[i]);
+            i += 1;
+            continue;
+        }
+        let rest = &content[i + 1..];
+        // Soft line break, allowing the trailing spaces some mailers leave
+        let spaces = rest.iter().take_while(|b| **b == b' ' || **b == b'\t').count();
+        match &rest[spaces..] {
+            [b'\r', b'\n', ..] => i += 1 + spaces + 2,
+            [b'\n', ..] => i += 1 + spaces + 1,
+            [] => i += 1 + spaces,
+            [high, low, ..] if hex(*high).is_some() && hex(*low).is_some() => {
+                bytes.push(hex(*high).unwrap_or_default() << 4 | hex(*low).unwrap_or_default());
+                i += 3;
+            }
+            _ => {
+                bytes.push(b'=');
+                i += 1;
+            }
+        }
+    }
+    bytes
+}
+
+/// Decode base64, skipping line breaks and anything else outside the
+/// alphabet. Padding in the middle, from joined encodings, starts a new
+/// run; a dangling last character is dropped.
+fn decode_base64(content: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(content.len() * 3 / 4);
+    for run in content.split(|b| *b == b'=') {
+        let mut run: Vec<u8> = run.iter().copied().filter(|b| b.is_ascii_alphanumeric() || *b == b'+' || *b == b'/').collect();
+        if run.len() % 4 == 1 {
+            run.pop();
+        }
+        if let Ok(decoded) = base64::engine::general_purpose::STANDARD_NO_PAD.decode(&run) {
+            bytes.extend(decoded);
+        }
+    }
+    bytes
+}
+
+/// Text of `bytes` in `charset`. Unknown charsets are read as UTF-8,
+/// which is right far more often than not.
+fn to_text(charset: &str, bytes: &[u8]) -> String {
+    let utf8 = ["utf-8", "utf8", "us-ascii", "ascii"];
+    if utf8.iter().any(|c| charset.eq_ignore_ascii_case(c)) {
+        return String::from_utf8_lossy(bytes).into_owned();
+    }
+    match mail_parser::decoders::charsets::map::charset_decoder(charset.as_bytes()) {
+        Some(decode) => decode(bytes),
+        None => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_header_encodings_and_charsets() {
+        assert_eq!(decode_header("=?UTF-8?B?Q2Fmw6kgbWVudQ==?="), "Café menu");
+        assert_eq!(decode_header("=?utf-8?q?Caf=C3=A9_menu?="), "Café menu");
+        assert_eq!(decode_header("=?ISO-8859-1?Q?Andr=E9?= <andre@example.fr>"), "André <andre@example.fr>");
+        assert_eq!(decode_header("=?windows-1252?Q?=93Quoted=94?="), "\u{201c}Quoted\u{201d}");
+        assert_eq!(decode_header("=?ISO-2022-JP?B?GyRCJEYkOSRIGyhC?="), "てすと");
+        assert_eq!(decode_header("=?UTF-8*en?Q?Hello?="), "Hello");
+        // Mixed Latin-1 and UTF-8 in one header
+        assert_eq!(
+            decode_header("Re: =?ISO-8859-1?Q?R=E9union?= et =?UTF-8?B?w6l0w6k=?= 2026"),
+            "Re: Réunion et été 2026"
+        );
+    }
+
+    #[test]
+    fn test_decode_header_adjacent_words() {
+        // Whitespace, even a folded line, between encoded words is dropped
+        assert_eq!(decode_header("=?UTF-8?Q?Hello,?= =?UTF-8?Q?_world?="), "Hello, world");
+        assert_eq!(decode_header("=?UTF-8?B?U3DDpHRl?=\r\n =?UTF-8?B?c29tbWVy?="), "Spätesommer");
+        // A character split across two words
+        assert_eq!(decode_header("=?UTF-8?Q?Gr=C3?= =?UTF-8?Q?=BC=C3=9Fe?="), "Grüße");
+        // Different charsets side by side
+        assert_eq!(decode_header("=?ISO-8859-1?Q?=C0_?= =?UTF-8?Q?bient=C3=B4t?="), "À bientôt");
+        // Whitespace next to plain text is kept
+        assert_eq!(decode_header("Fw: =?UTF-8?Q?na=C3=AFve?= idea"), "Fw: naïve idea");
+    }
+
+    #[test]
+    fn test_decode_body() {
+        assert_eq!(decode_body(b"Caf=E9 a=3Db, soft=\r\nbreak", "quoted-printable", Some("ISO-8859-1")), "Café a=b, softbreak");
+        assert_eq!(decode_body(b"Gr=c3=bc=C3=9Fe=  \nund", "Quoted-Printable", Some("utf-8")), "Grüßeund");
+        assert_eq!(decode_body(b"Q2Fmw6kg\r\nbWVudQ==\r\n", "base64", Some("UTF-8")), "Café menu");
+        assert_eq!(decode_body(b"\x93Hi\x94", "8bit", Some("windows-1252")), "\u{201c}Hi\u{201d}");
+        assert_eq!(decode_body("Déjà".as_bytes(), "8bit", None), "Déjà");
+        // Unlabelled text that isn't UTF-8
+        assert_eq!(decode_body(b"D\xe9j\xe0", "8bit", None), "Déjà");
+    }
+
+    #[test]
+    fn test_decode_body_broken_encoding() {
+        // A bad escape is kept, everything around it is still decoded
+        assert_eq!(decode_body(b"a=3Db =ZZ 100%=\r\n off=", "quoted-printable", None), "a=b =ZZ 100% off");
+        assert_eq!(decode_body(b"Q2Fmw6kg\r\nbWVudQ=!=", "base64", Some("utf-8")), "Café menu");
+        // Two encodings joined together
+        assert_eq!(decode_body(b"SGk=SGk=", "base64", None), "HiHi");
+    }
+
+    #[test]
+    fn test_message_texts_recovers_broken_parts() {
+        let raw = "Content-Type: multipart/alternative; boundary=b\r\n\r\n\
+                   --b\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: base64\r\n\r\n\
+                   Q2Fmw6kg\r\nbWVudQ=!=\r\n\
+                   --b\r\nContent-Type: text/html; charset=windows-1252\r\nContent-Transfer-Encoding: quoted-printable\r\n\r\n\
+                   <p>Caf=E9 =3D =G1</p>\r\n--b--\r\n";
+        let message = mail_parser::MessageParser::default().parse(raw.as_bytes()).unwrap();
+        let (plain, html) = message_texts(&message);
+        assert_eq!(plain.as_deref(), Some("Café menu"));
+        assert_eq!(html.as_deref(), Some("<p>Café = =G1</p>"));
+
+        let raw = "Content-Type: text/plain; charset=iso-8859-1\r\nContent-Transfer-Encoding: quoted-printable\r\n\r\nNo=EBl";
+        let message = mail_parser::MessageParser::default().parse(raw.as_bytes()).unwrap();
+        assert_eq!(message_texts(&message), (Some("Noël".to_string()), None));
+    }
+
+    #[test]
+    fn test_decode_header_leaves_other_text() {
+        assert_eq!(decode_header("Plain subject"), "Plain subject");
+        assert_eq!(decode_header("Price =? maybe ?="), "Price =? maybe ?=");
+        assert_eq!(decode_header("=?UTF-8?X?abc?="), "=?UTF-8?X?abc?=");
+        assert_eq!(decode_header("=?UTF-8?Q?bad=Z?="), "=?UTF-8?Q?bad=Z?=");
+        assert_eq!(decode_header("Ünïcode already"), "Ünïcode already");
+        assert_eq!(decode_header("Long\r\n subject"), "Long subject");
+        assert_eq!(decode_header(""), "");
+    }
+}
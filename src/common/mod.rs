@@ -1,7 +1,11 @@
 //! Common types and utilities used across all layers
 
+pub mod clock;
 pub mod error;
 pub mod logging;
+pub mod mime;
+pub mod profile;
 pub mod types;
 
+pub use clock::{Clock, SharedClock};
 pub use error::{Error, Result};
@@ -0,0 +1,174 @@
+//! Profiles
+//!
+//! A profile is a separate set of accounts, settings and cached mail, such
+//! as "work" and "personal". The default profile lives directly in the
+//! `wixen-mail` settings and cache folders, and a named one in
+//! `wixen-mail/profiles/<name>` inside each. A profile is chosen at startup
+//! with `--profile <name>` or the `WIXEN_MAIL_PROFILE` environment variable.
+//!
+//! When a folder can't be created, the next place is tried and a warning
+//! logged. Settings fall back to the system temp folder, where they may be
+//! cleaned up. The cache falls back to a `cache` folder inside the profile's
+//! settings folder, then to the temp folder; a lost cache is only
+//! downloaded again.
+
+use crate::common::{Error, Result};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// Environment variable naming the profile when `--profile` isn't given
+pub const PROFILE_ENV: &str = "WIXEN_MAIL_PROFILE";
+
+const APP_DIR: &str = "wixen-mail";
+
+static ACTIVE: OnceLock<ProfilePaths> = OnceLock::new();
+
+/// Where a profile keeps its settings and cached mail
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfilePaths {
+    /// `None` for the default profile
+    pub name: Option<String>,
+    pub config_dir: PathBuf,
+    pub cache_dir: PathBuf,
+}
+
+impl ProfilePaths {
+    /// Folders for the profile called `name`, creating them if needed
+    pub fn resolve(name: Option<&str>) -> Result<Self> {
+        let config_base = if cfg!(windows) { dirs::data_local_dir().or_else(dirs::config_dir) } else { dirs::config_dir() };
+        Self::resolve_in(name, config_base, dirs::cache_dir())
+    }
+
+    fn resolve_in(name: Option<&str>, config_base: Option<PathBuf>, cache_base: Option<PathBuf>) -> Result<Self> {
+        if let Some(name) = name {
+            validate_name(name)?;
+        }
+        let within = |base: PathBuf| match name {
+            Some(name) => base.join(APP_DIR).join("profiles").join(name),
+            None => base.join(APP_DIR),
+        };
+        let config_dir = first_usable("settings", config_base.map(within).into_iter().chain([within(std::env::temp_dir())]))?;
+        let cache_dir = first_usable(
+            "cache",
+            cache_base.map(within).into_iter().chain([config_dir.join("cache"), within(std::env::temp_dir())]),
+        )?;
+        Ok(Self { name: name.map(str::to_string), config_dir, cache_dir })
+    }
+
+    /// Folders for the profile named in `args` (the command line without the
+    /// program name) or else the `WIXEN_MAIL_PROFILE` environment variable.
+    /// The choice, or why it failed, is logged.
+    pub fn resolve_from_env_and_args(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let resolved = profile_from_args(args, std::env::var(PROFILE_ENV).ok())
+            .and_then(|name| Self::resolve(name.as_deref()));
+        match &resolved {
+            Ok(paths) => tracing::info!("Using profile {}", paths.name.as_deref().unwrap_or("default")),
+            Err(e) => tracing::error!("Profile error: {}", e),
+        }
+        resolved
+    }
+
+    /// Use these folders for the rest of the run. Only the first call has
+    /// any effect; the profile in use is returned.
+    pub fn activate(self) -> &'static ProfilePaths {
+        ACTIVE.get_or_init(|| self)
+    }
+
+    /// The profile chosen at startup, if one was activated
+    pub fn active() -> Option<&'static ProfilePaths> {
+        ACTIVE.get()
+    }
+
+    /// The active profile, or else the default one
+    pub fn current() -> Result<Self> {
+        match Self::active() {
+            Some(paths) => Ok(paths.clone()),
+            None => Self::resolve(None),
+        }
+    }
+}
+
+/// The profile named on the command line (`--profile work` or
+/// `--profile=work`), else in `env`. "default" names the default profile.
+pub fn profile_from_args(args: impl IntoIterator<Item = String>, env: Option<String>) -> Result<Option<String>> {
+    let mut args = args.into_iter();
+    let mut name = None;
+    while let Some(arg) = args.next() {
+        if arg == "--profile" {
+            name = Some(args.next().ok_or_else(|| Error::Config("--profile needs a profile name".to_string()))?);
+        } else if let Some(value) = arg.strip_prefix("--profile=") {
+            name = Some(value.to_string());
+        }
+    }
+    let name = name.or(env).map(|n| n.trim().to_string()).filter(|n| !n.is_empty() && n != "default");
+    if let Some(name) = &name {
+        validate_name(name)?;
+    }
+    Ok(name)
+}
+
+/// Profile names become folder names, so only letters, digits, `-` and `_`
+fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::Config(format!(
+            "\"{}\" is not a valid profile name; use letters, digits, - and _",
+            name
+        )))
+    }
+}
+
+fn first_usable(what: &str, candidates: impl IntoIterator<Item = PathBuf>) -> Result<PathBuf> {
+    for dir in candidates {
+        match fs::create_dir_all(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(e) => tracing::warn!("Can't use {} folder {}: {}", what, dir.display(), e),
+        }
+    }
+    Err(Error::Config(format!("Could not create a {} folder", what)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_profile_from_args() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(profile_from_args(args(&["wixen-mail"]), None).unwrap(), None);
+        assert_eq!(profile_from_args(args(&["wixen-mail", "--profile", "work"]), None).unwrap().as_deref(), Some("work"));
+        assert_eq!(profile_from_args(args(&["--profile=home"]), Some("work".into())).unwrap().as_deref(), Some("home"));
+        assert_eq!(profile_from_args(args(&[]), Some("work".into())).unwrap().as_deref(), Some("work"));
+        assert_eq!(profile_from_args(args(&["--profile", "default"]), None).unwrap(), None);
+        assert!(profile_from_args(args(&["--profile"]), None).is_err());
+        assert!(profile_from_args(args(&["--profile", "../work"]), None).is_err());
+
+        // The command line wins over the environment, so a bad name fails either way
+        assert!(ProfilePaths::resolve_from_env_and_args(args(&["--profile", "../work"])).is_err());
+    }
+
+    #[test]
+    fn test_profile_folders() {
+        let root = env::temp_dir().join(format!("wixen_mail_test_profile_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let (config, cache) = (root.join("config"), root.join("cache"));
+
+        let default = ProfilePaths::resolve_in(None, Some(config.clone()), Some(cache.clone())).unwrap();
+        assert_eq!(default.config_dir, config.join("wixen-mail"));
+        assert_eq!(default.cache_dir, cache.join("wixen-mail"));
+        let work = ProfilePaths::resolve_in(Some("work"), Some(config.clone()), Some(cache.clone())).unwrap();
+        assert_eq!(work.config_dir, config.join("wixen-mail/profiles/work"));
+        assert!(work.cache_dir.is_dir());
+
+        // A cache folder that can't be created falls back inside the settings
+        fs::write(root.join("file"), "").unwrap();
+        let fallback = ProfilePaths::resolve_in(Some("work"), Some(config.clone()), Some(root.join("file"))).unwrap();
+        assert_eq!(fallback.cache_dir, work.config_dir.join("cache"));
+    }
+}
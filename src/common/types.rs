@@ -1,5 +1,6 @@
 //! Common types used throughout the application
 
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
 use std::fmt;
 
 /// Unique identifier for various entities
@@ -17,6 +18,46 @@ impl EmailAddress {
     pub fn new(address: String, name: Option<String>) -> Self {
         Self { address, name }
     }
+
+    /// Parse a header-style address list such as
+    /// `"Doe, Jane" <jane@example.com>, bob@example.com`.
+    ///
+    /// Commas inside quotes or angle brackets don't split entries.
+    pub fn parse_list(list: &str) -> Vec<Self> {
+        let mut entries = Vec::new();
+        let (mut current, mut quoted, mut angle) = (String::new(), false, false);
+        for c in list.chars() {
+            match c {
+                '"' => quoted = !quoted,
+                '<' if !quoted => angle = true,
+                '>' if !quoted => angle = false,
+                ',' | ';' if !quoted && !angle => {
+                    entries.push(std::mem::take(&mut current));
+                    continue;
+                }
+                _ => {}
+            }
+            current.push(c);
+        }
+        entries.push(current);
+
+        entries
+            .iter()
+            .filter_map(|entry| {
+                let entry = entry.trim();
+                match (entry.rfind('<'), entry.rfind('>')) {
+                    (Some(start), Some(end)) if start < end => {
+                        let address = entry[start + 1..end].trim().to_string();
+                        let name = entry[..start].trim().trim_matches('"').trim();
+                        (!address.is_empty())
+                            .then(|| Self::new(address, (!name.is_empty()).then(|| name.to_string())))
+                    }
+                    _ if !entry.is_empty() => Some(Self::new(entry.to_string(), None)),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
 }
 
 impl fmt::Display for EmailAddress {
@@ -28,6 +69,42 @@ impl fmt::Display for EmailAddress {
     }
 }
 
+/// Basic RFC 5321 check of a bare address (no display name or angle
+/// brackets). Accepts anything a server could plausibly deliver, including
+/// quoted local parts, address literals and non-ASCII (RFC 6531) text; it
+/// rejects only addresses that can't be right, such as a missing `@`,
+/// spaces, or empty or overlong parts.
+pub fn validate_email(address: &str) -> bool {
+    let Some((local, domain)) = address.rsplit_once('@') else {
+        return false;
+    };
+    if address.len() > 254 || local.is_empty() || local.len() > 64 || domain.is_empty() || domain.len() > 255 {
+        return false;
+    }
+    let local_ok = if local.len() >= 2 && local.starts_with('"') && local.ends_with('"') {
+        !local[1..local.len() - 1].chars().any(|c| c.is_control() || c == '"')
+    } else {
+        const SPECIALS: &str = "()<>[]:;@\\,\"";
+        !local.starts_with('.')
+            && !local.ends_with('.')
+            && !local.contains("..")
+            && !local.chars().any(|c| c.is_whitespace() || c.is_control() || SPECIALS.contains(c))
+    };
+    let domain_ok = if let Some(literal) = domain.strip_prefix('[').and_then(|d| d.strip_suffix(']')) {
+        literal.parse::<std::net::Ipv4Addr>().is_ok()
+            || literal.strip_prefix("IPv6:").is_some_and(|ip| ip.parse::<std::net::Ipv6Addr>().is_ok())
+    } else {
+        domain.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c == '-' || c.is_alphanumeric())
+        })
+    };
+    local_ok && domain_ok
+}
+
 /// Mail protocol types
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Protocol {
@@ -47,6 +124,34 @@ pub enum FolderType {
     Custom,
 }
 
+impl FolderType {
+    /// Value stored in the cache's `folders.folder_type` column
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FolderType::Inbox => "Inbox",
+            FolderType::Sent => "Sent",
+            FolderType::Drafts => "Drafts",
+            FolderType::Trash => "Trash",
+            FolderType::Spam => "Spam",
+            FolderType::Archive => "Archive",
+            FolderType::Custom => "Custom",
+        }
+    }
+
+    /// Stored value; anything unrecognised is a custom folder
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "Inbox" => FolderType::Inbox,
+            "Sent" => FolderType::Sent,
+            "Drafts" => FolderType::Drafts,
+            "Trash" => FolderType::Trash,
+            "Spam" => FolderType::Spam,
+            "Archive" => FolderType::Archive,
+            _ => FolderType::Custom,
+        }
+    }
+}
+
 /// Server configuration for email protocols
 #[derive(Debug, Clone)]
 pub struct ServerConfig {
@@ -170,6 +275,163 @@ impl Folder {
     }
 }
 
+/// Message importance, from the `X-Priority` and `Importance` headers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MessagePriority {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+impl MessagePriority {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MessagePriority::High => "high",
+            MessagePriority::Normal => "normal",
+            MessagePriority::Low => "low",
+        }
+    }
+
+    /// Stored value; anything unrecognised is normal priority
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "high" => MessagePriority::High,
+            "low" => MessagePriority::Low,
+            _ => MessagePriority::Normal,
+        }
+    }
+
+    /// Priority from header values. `X-Priority` is a number from 1
+    /// (highest) to 5 (lowest), often followed by a comment such as
+    /// "1 (Highest)"; `Importance` is "high", "normal" or "low". When both
+    /// are present `X-Priority` wins, as most clients set it first.
+    pub fn from_headers(x_priority: Option<&str>, importance: Option<&str>) -> Self {
+        let from_number = x_priority
+            .and_then(|v| v.trim().chars().next()?.to_digit(10))
+            .map(|n| match n {
+                1 | 2 => MessagePriority::High,
+                4 | 5 => MessagePriority::Low,
+                _ => MessagePriority::Normal,
+            });
+        let from_importance = importance.map(|v| match v.trim().to_ascii_lowercase().as_str() {
+            "high" => MessagePriority::High,
+            "low" => MessagePriority::Low,
+            _ => MessagePriority::Normal,
+        });
+        from_number.or(from_importance).unwrap_or_default()
+    }
+
+    /// `X-Priority` and `Importance` values for an outgoing message, or
+    /// `None` for normal priority, which is sent without them
+    pub fn header_values(&self) -> Option<(&'static str, &'static str)> {
+        match self {
+            MessagePriority::High => Some(("1 (Highest)", "high")),
+            MessagePriority::Normal => None,
+            MessagePriority::Low => Some(("5 (Lowest)", "low")),
+        }
+    }
+
+    pub fn is_high(&self) -> bool {
+        *self == MessagePriority::High
+    }
+}
+
+/// Parse a stored message date: RFC 2822 (as sent in `Date:` headers),
+/// RFC 3339, `YYYY-MM-DD HH:MM[:SS]` or `YYYY-MM-DD`. Dates without a zone
+/// are taken as UTC.
+pub fn parse_message_date(date: &str) -> Option<DateTime<Utc>> {
+    let date = date.trim();
+    DateTime::parse_from_rfc2822(date)
+        .or_else(|_| DateTime::parse_from_rfc3339(date))
+        .map(|d| d.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"]
+                .iter()
+                .find_map(|format| NaiveDateTime::parse_from_str(date, format).ok())
+                .or_else(|| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok().and_then(|d| d.and_hms_opt(0, 0, 0)))
+                .map(|d| d.and_utc())
+        })
+}
+
+/// How far back a date is, for the message list's date column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelativeDate {
+    /// Earlier today (or later today, for a sender with a fast clock)
+    Today(NaiveTime),
+    Yesterday(NaiveTime),
+    /// Another day this year
+    ThisYear(NaiveDate),
+    /// A day in another year
+    Older(NaiveDate),
+}
+
+impl RelativeDate {
+    /// Place `date` relative to `now`, in `now`'s time zone
+    pub fn of<Tz: TimeZone>(date: DateTime<Utc>, now: &DateTime<Tz>) -> Self {
+        let local = date.with_timezone(&now.timezone());
+        let (day, today) = (local.date_naive(), now.date_naive());
+        if day == today {
+            RelativeDate::Today(local.time())
+        } else if day + Duration::days(1) == today {
+            RelativeDate::Yesterday(local.time())
+        } else if day.year() == today.year() {
+            RelativeDate::ThisYear(day)
+        } else {
+            RelativeDate::Older(day)
+        }
+    }
+}
+
+/// Typical silent reading speed for prose
+const WORDS_PER_MINUTE: usize = 230;
+
+/// How long a message is, for the preview header and announcements
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ReadingStats {
+    pub words: usize,
+    /// Characters other than whitespace
+    pub characters: usize,
+    /// Estimated reading time, rounded up; zero only for empty text
+    pub minutes: usize,
+}
+
+impl ReadingStats {
+    /// Count `text`. Chinese and Japanese are written without spaces, so
+    /// each of their characters counts as a word.
+    pub fn of(text: &str) -> Self {
+        let mut words = 0;
+        let mut in_word = false;
+        let mut characters = 0;
+        for c in text.chars() {
+            if c.is_whitespace() {
+                in_word = false;
+                continue;
+            }
+            characters += 1;
+            if is_cjk(c) {
+                words += 1;
+                in_word = false;
+            } else if !in_word {
+                words += 1;
+                in_word = true;
+            }
+        }
+        Self { words, characters, minutes: words.div_ceil(WORDS_PER_MINUTE) }
+    }
+}
+
+/// Han ideographs and kana, the scripts written without word spaces
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}'   // Hiragana, Katakana
+        | '\u{3400}'..='\u{4DBF}' // CJK Extension A
+        | '\u{4E00}'..='\u{9FFF}' // CJK Unified Ideographs
+        | '\u{F900}'..='\u{FAFF}' // CJK Compatibility Ideographs
+        | '\u{20000}'..='\u{2FA1F}')
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -189,6 +451,25 @@ mod tests {
         assert_eq!(addr.to_string(), "test@example.com");
     }
 
+    #[test]
+    fn test_email_address_parse_list() {
+        let list = EmailAddress::parse_list("\"Doe, Jane\" <jane@example.com>, bob@example.com; ,Carol <carol@example.com>");
+        let rendered: Vec<String> = list.iter().map(|a| a.to_string()).collect();
+        assert_eq!(rendered, ["Doe, Jane <jane@example.com>", "bob@example.com", "Carol <carol@example.com>"]);
+        assert!(EmailAddress::parse_list("  ").is_empty());
+    }
+
+    #[test]
+    fn test_validate_email() {
+        for good in ["jane@example.com", "first.last+tag@mail.example.co.uk", "\"odd name\"@example.com", "root@localhost", "admin@[192.0.2.1]", "josé@exämple.de"] {
+            assert!(validate_email(good), "{} should pass", good);
+        }
+        for bad in ["", "jane", "@example.com", "jane@", "jane doe@example.com", "jane..doe@example.com", ".jane@example.com", "jane@example..com", "jane@-example.com", "jane@exa_mple.com", "a,b@example.com", "jane@[999.1.1.1]"] {
+            assert!(!validate_email(bad), "{} should fail", bad);
+        }
+        assert!(!validate_email(&format!("{}@example.com", "a".repeat(65))));
+    }
+
     #[test]
     fn test_server_config() {
         let config = ServerConfig::new("imap.example.com".to_string(), 993, true);
@@ -256,5 +537,75 @@ mod tests {
         assert_eq!(folder.unread_count, 0);
         assert_eq!(folder.total_count, 0);
         assert!(folder.parent_id.is_none());
+
+        for t in [FolderType::Sent, FolderType::Spam, FolderType::Archive, FolderType::Custom] {
+            assert_eq!(FolderType::parse(t.as_str()), t);
+        }
+        assert_eq!(FolderType::parse("Projects"), FolderType::Custom);
+    }
+
+    #[test]
+    fn test_message_priority_headers() {
+        assert_eq!(MessagePriority::from_headers(Some("1 (Highest)"), None), MessagePriority::High);
+        assert_eq!(MessagePriority::from_headers(Some("2"), None), MessagePriority::High);
+        assert_eq!(MessagePriority::from_headers(Some(" 5 (Lowest)"), Some("high")), MessagePriority::Low);
+        assert_eq!(MessagePriority::from_headers(None, Some("High")), MessagePriority::High);
+        assert_eq!(MessagePriority::from_headers(Some("3 (Normal)"), None), MessagePriority::Normal);
+        assert_eq!(MessagePriority::from_headers(Some("urgent"), Some("low")), MessagePriority::Low);
+        assert_eq!(MessagePriority::from_headers(None, None), MessagePriority::Normal);
+
+        for priority in [MessagePriority::High, MessagePriority::Normal, MessagePriority::Low] {
+            assert_eq!(MessagePriority::parse(priority.as_str()), priority);
+            let headers = priority.header_values();
+            assert_eq!(MessagePriority::from_headers(headers.map(|h| h.0), headers.map(|h| h.1)), priority);
+        }
+    }
+
+    #[test]
+    fn test_parse_message_date_formats() {
+        let rfc2822 = parse_message_date("Mon, 3 Mar 2025 14:32:00 +0100").unwrap();
+        assert_eq!(rfc2822, Utc.with_ymd_and_hms(2025, 3, 3, 13, 32, 0).unwrap());
+        assert_eq!(parse_message_date("2025-03-03T13:32:00Z"), Some(rfc2822));
+        assert_eq!(parse_message_date("2025-03-03 13:32"), Some(rfc2822));
+        assert!(parse_message_date("2025-03-03").is_some());
+        assert!(parse_message_date("yesterday").is_none());
+
+        // Mixed formats order by time, not by text
+        let mut dates = ["Tue, 4 Mar 2025 09:00:00 +0000", "2025-03-03 13:32", "Sun, 2 Mar 2025 23:00:00 -0500"];
+        dates.sort_by_key(|d| parse_message_date(d));
+        assert_eq!(dates, ["Sun, 2 Mar 2025 23:00:00 -0500", "2025-03-03 13:32", "Tue, 4 Mar 2025 09:00:00 +0000"]);
+    }
+
+    #[test]
+    fn test_relative_date() {
+        let now = Utc.with_ymd_and_hms(2025, 3, 5, 10, 0, 0).unwrap();
+        let at = |y, m, d, h| Utc.with_ymd_and_hms(y, m, d, h, 15, 0).unwrap();
+        let time = |h| NaiveTime::from_hms_opt(h, 15, 0).unwrap();
+        assert_eq!(RelativeDate::of(at(2025, 3, 5, 8), &now), RelativeDate::Today(time(8)));
+        assert_eq!(RelativeDate::of(at(2025, 3, 4, 23), &now), RelativeDate::Yesterday(time(23)));
+        let march_3 = NaiveDate::from_ymd_opt(2025, 3, 3).unwrap();
+        assert_eq!(RelativeDate::of(at(2025, 3, 3, 12), &now), RelativeDate::ThisYear(march_3));
+        let new_year = NaiveDate::from_ymd_opt(2024, 12, 31).unwrap();
+        assert_eq!(RelativeDate::of(at(2024, 12, 31, 12), &now), RelativeDate::Older(new_year));
+
+        // The day boundary is the viewer's, not UTC's
+        let tokyo = now.with_timezone(&chrono::FixedOffset::east_opt(9 * 3600).unwrap());
+        assert!(matches!(RelativeDate::of(at(2025, 3, 4, 20), &tokyo), RelativeDate::Today(_)));
+    }
+
+    #[test]
+    fn test_reading_stats() {
+        assert_eq!(ReadingStats::of(""), ReadingStats::default());
+        assert_eq!(ReadingStats::of(" \n\t "), ReadingStats::default());
+        assert_eq!(ReadingStats::of("Lunch on Tuesday?\n\nAna"), ReadingStats { words: 4, characters: 18, minutes: 1 });
+
+        // Each ideograph or kana is a word; Korean has spaces between words
+        assert_eq!(ReadingStats::of("明日の会議"), ReadingStats { words: 5, characters: 5, minutes: 1 });
+        assert_eq!(ReadingStats::of("Meeting 明日 at 3pm").words, 5);
+        assert_eq!(ReadingStats::of("내일 회의").words, 2);
+
+        let long = "word ".repeat(231);
+        assert_eq!(ReadingStats::of(&long).minutes, 2);
+        assert_eq!(ReadingStats::of(&"word ".repeat(230)).minutes, 1);
     }
 }
@@ -29,7 +29,7 @@ pub struct Account {
 
     /// Authentication
     pub username: String,
-    #[serde(skip_serializing)] // Don't serialize password to logs
+    #[serde(skip_serializing, default)] // Don't serialize password to logs
     pub password: String,
 
     /// Whether this account uses OAuth2 for authentication.
@@ -61,12 +61,111 @@ pub struct Account {
     /// Account color for visual distinction (hex code)
     #[serde(default = "default_account_color")]
     pub color: String,
+
+    /// Append a copy of sent mail to the Sent folder. Off for providers
+    /// that already file SMTP submissions there, to avoid duplicates.
+    #[serde(default = "default_true")]
+    pub save_sent_copy: bool,
+
+    /// Mirror drafts to the server's Drafts folder so other devices see them.
+    #[serde(default)]
+    pub sync_drafts: bool,
+
+    /// What deleting a message does on the server
+    #[serde(default)]
+    pub delete_mode: DeleteMode,
+
+    /// Remember each server's TLS certificate the first time a connection
+    /// succeeds, and refuse to connect if it later changes
+    #[serde(default)]
+    pub pin_certificates: bool,
+
+    /// SHA-256 fingerprints of the pinned server certificates
+    #[serde(default)]
+    pub imap_pinned_fingerprint: Option<String>,
+    #[serde(default)]
+    pub smtp_pinned_fingerprint: Option<String>,
+
+    /// Raise a desktop notification when new mail arrives
+    #[serde(default = "default_true")]
+    pub notify_new_mail: bool,
+
+    /// Bounce address: the SMTP `MAIL FROM` (envelope sender) for mail
+    /// from this account, which the receiving server records as
+    /// `Return-Path`. It is separate from the `From` header readers see, so
+    /// a mailing list or bulk sender can route bounces to a processing
+    /// address. `None` uses the From address.
+    #[serde(default)]
+    pub envelope_from: Option<String>,
+}
+
+/// How deleted messages are removed on the server
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeleteMode {
+    /// MOVE to the Trash folder, where messages can be restored
+    #[default]
+    MoveToTrash,
+    /// STORE `\Deleted` and EXPUNGE, removing messages for good
+    Expunge,
+}
+
+impl DeleteMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeleteMode::MoveToTrash => "move_to_trash",
+            DeleteMode::Expunge => "expunge",
+        }
+    }
+
+    /// Parse a stored value, defaulting to `MoveToTrash`.
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "expunge" => DeleteMode::Expunge,
+            _ => DeleteMode::MoveToTrash,
+        }
+    }
 }
 
 fn default_account_color() -> String {
     "#4A90E2".to_string() // Default blue
 }
 
+fn default_true() -> bool {
+    true
+}
+
+/// Helper: returns true if the provider stores messages sent over SMTP in
+/// the Sent folder by itself (Gmail).
+pub fn provider_saves_sent(email: &str) -> bool {
+    email
+        .split('@')
+        .nth(1)
+        .map(|d| matches!(d.to_lowercase().as_str(), "gmail.com" | "googlemail.com"))
+        .unwrap_or(false)
+}
+
+/// Helper: returns true if the provider models folders as labels (Gmail),
+/// where expunging from a folder only removes the label instead of deleting.
+pub fn provider_uses_labels(email: &str) -> bool {
+    provider_saves_sent(email)
+}
+
+/// Helper: returns true for a bare `local@domain` address, the only form
+/// SMTP accepts as `MAIL FROM` (no display name or angle brackets).
+pub fn is_bare_address(address: &str) -> bool {
+    match address.trim().rsplit_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !address.trim().contains(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"' | ',' | ';'))
+        }
+        None => false,
+    }
+}
+
 /// Helper: returns true if the email domain requires OAuth.
 pub fn requires_oauth(email: &str) -> bool {
     email
@@ -91,6 +190,7 @@ impl Account {
     /// Create a new account with default settings
     pub fn new(name: String, email: String) -> Self {
         let oauth = requires_oauth(&email);
+        let save_sent_copy = !provider_saves_sent(&email);
         Self {
             id: uuid::Uuid::new_v4().to_string(),
             name,
@@ -112,6 +212,24 @@ impl Account {
             provider: None,
             last_sync: None,
             color: default_account_color(),
+            save_sent_copy,
+            sync_drafts: false,
+            delete_mode: DeleteMode::MoveToTrash,
+            pin_certificates: false,
+            imap_pinned_fingerprint: None,
+            smtp_pinned_fingerprint: None,
+            notify_new_mail: true,
+            envelope_from: None,
+        }
+    }
+
+    /// Delete mode to use with the server. Label-based providers always move
+    /// to Trash, since expunging there would only archive the message.
+    pub fn effective_delete_mode(&self) -> DeleteMode {
+        if provider_uses_labels(&self.email) {
+            DeleteMode::MoveToTrash
+        } else {
+            self.delete_mode
         }
     }
 
@@ -165,6 +283,12 @@ impl Account {
             return Err("Password is required".to_string());
         }
 
+        if let Some(envelope_from) = self.envelope_from.as_deref().filter(|a| !a.trim().is_empty()) {
+            if !is_bare_address(envelope_from) {
+                return Err(format!("Bounce address \"{}\" is not a plain email address", envelope_from));
+            }
+        }
+
         Ok(())
     }
 
@@ -209,6 +333,14 @@ impl Account {
             provider,
             last_sync: None,
             color: "#4A90E2".to_string(),
+            save_sent_copy: !provider_saves_sent(&email),
+            sync_drafts: false,
+            delete_mode: DeleteMode::MoveToTrash,
+            pin_certificates: false,
+            imap_pinned_fingerprint: None,
+            smtp_pinned_fingerprint: None,
+            notify_new_mail: true,
+            envelope_from: None,
         }
     }
 }
@@ -375,6 +507,34 @@ mod tests {
 
         account.password = "password".to_string();
         assert!(account.validate().is_ok());
+
+        account.envelope_from = Some("bounces@lists.example.net".to_string());
+        assert!(account.validate().is_ok());
+        account.envelope_from = Some("Bounces <bounces@lists.example.net>".to_string());
+        assert!(account.validate().is_err());
+        account.envelope_from = Some("bounces".to_string());
+        assert!(account.validate().is_err());
+        account.envelope_from = Some(" ".to_string());
+        assert!(account.validate().is_ok());
+    }
+
+    #[test]
+    fn test_save_sent_copy_default_by_provider() {
+        assert!(Account::new("Work".to_string(), "me@example.com".to_string()).save_sent_copy);
+        assert!(!Account::new("Gmail".to_string(), "me@gmail.com".to_string()).save_sent_copy);
+    }
+
+    #[test]
+    fn test_delete_mode_per_provider() {
+        let mut account = Account::new("Work".to_string(), "me@example.com".to_string());
+        assert_eq!(account.effective_delete_mode(), DeleteMode::MoveToTrash);
+        account.delete_mode = DeleteMode::Expunge;
+        assert_eq!(account.effective_delete_mode(), DeleteMode::Expunge);
+        assert_eq!(DeleteMode::parse(DeleteMode::Expunge.as_str()), DeleteMode::Expunge);
+
+        let mut gmail = Account::new("Gmail".to_string(), "me@gmail.com".to_string());
+        gmail.delete_mode = DeleteMode::Expunge;
+        assert_eq!(gmail.effective_delete_mode(), DeleteMode::MoveToTrash);
     }
 
     #[test]
@@ -2,19 +2,196 @@
 //!
 //! Handles application settings, account configurations, and persistence.
 
+use crate::common::logging::LogLevel;
+use crate::common::profile::ProfilePaths;
 use crate::common::{types::Id, Error, Result};
+use crate::data::message_cache::{ContentEncryption, PrunePolicy};
+use crate::service::protocols::{NetworkTimeouts, RateLimits};
+use chrono::{NaiveDate, NaiveTime};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 
+/// Colour theme selection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Theme {
+    /// Follow the operating system colours
+    System,
+    Light,
+    Dark,
+    /// Maximum-contrast palette for low-vision users
+    HighContrast,
+}
+
+impl Theme {
+    /// All themes in settings-dialog order
+    pub const ALL: [Theme; 4] = [Theme::System, Theme::Light, Theme::Dark, Theme::HighContrast];
+
+    /// Parse the `AppConfig::theme` key; unknown values follow the system.
+    pub fn from_key(key: &str) -> Self {
+        match key {
+            "light" => Theme::Light,
+            "dark" => Theme::Dark,
+            "high_contrast" => Theme::HighContrast,
+            _ => Theme::System,
+        }
+    }
+
+    /// Key stored in `AppConfig::theme`
+    pub fn as_key(&self) -> &'static str {
+        match self {
+            Theme::System => "default",
+            Theme::Light => "light",
+            Theme::Dark => "dark",
+            Theme::HighContrast => "high_contrast",
+        }
+    }
+
+    /// Human-readable name for the settings dialog
+    pub fn label(&self) -> &'static str {
+        match self {
+            Theme::System => "System",
+            Theme::Light => "Light",
+            Theme::Dark => "Dark",
+            Theme::HighContrast => "High Contrast",
+        }
+    }
+}
+
+/// What to do when an incoming message asks for a read receipt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReceiptPolicy {
+    /// Ask each time a message requesting a receipt is opened
+    Ask,
+    Always,
+    Never,
+}
+
+impl ReceiptPolicy {
+    /// All policies in settings-dialog order
+    pub const ALL: [ReceiptPolicy; 3] = [ReceiptPolicy::Ask, ReceiptPolicy::Always, ReceiptPolicy::Never];
+
+    /// Parse the `AppConfig::read_receipt_policy` key; unknown values ask.
+    pub fn from_key(key: &str) -> Self {
+        match key {
+            "always" => ReceiptPolicy::Always,
+            "never" => ReceiptPolicy::Never,
+            _ => ReceiptPolicy::Ask,
+        }
+    }
+
+    /// Key stored in `AppConfig::read_receipt_policy`
+    pub fn as_key(&self) -> &'static str {
+        match self {
+            ReceiptPolicy::Ask => "ask",
+            ReceiptPolicy::Always => "always",
+            ReceiptPolicy::Never => "never",
+        }
+    }
+
+    /// Human-readable name for the settings dialog
+    pub fn label(&self) -> &'static str {
+        match self {
+            ReceiptPolicy::Ask => "Ask me",
+            ReceiptPolicy::Always => "Always send",
+            ReceiptPolicy::Never => "Never send",
+        }
+    }
+}
+
+/// Automatic replies to everyone who writes while the user is away
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VacationResponder {
+    pub enabled: bool,
+    /// First day replies are sent; `None` starts straight away
+    pub start: Option<NaiveDate>,
+    /// Last day replies are sent; `None` keeps replying until turned off
+    pub end: Option<NaiveDate>,
+    /// Reply subject; empty uses "Re: " and the original subject
+    pub subject: String,
+    pub message: String,
+    /// Days before someone already answered is answered again
+    pub resend_after_days: u32,
+}
+
+impl Default for VacationResponder {
+    fn default() -> Self {
+        Self { enabled: false, start: None, end: None, subject: String::new(), message: String::new(), resend_after_days: 7 }
+    }
+}
+
+impl VacationResponder {
+    /// Whether replies go out on `today`
+    pub fn is_active(&self, today: NaiveDate) -> bool {
+        self.enabled && !matches!(self.start, Some(start) if start > today) && !matches!(self.end, Some(end) if end < today)
+    }
+
+    /// Check the settings before they are switched on
+    pub fn validate(&self) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.message.trim().is_empty() {
+            return Err(Error::Config("Write the message people receive while you're away".to_string()));
+        }
+        if let (Some(start), Some(end)) = (self.start, self.end) {
+            if end < start {
+                return Err(Error::Config("The last day is before the first day".to_string()));
+            }
+        }
+        if !(1..=365).contains(&self.resend_after_days) {
+            return Err(Error::Config("Reply to the same person again after 1 to 365 days".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Hours when new mail doesn't pop up a notification or play a sound
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuietHours {
+    pub enabled: bool,
+    pub start: NaiveTime,
+    /// End of the quiet hours; earlier than `start` when they run past midnight
+    pub end: NaiveTime,
+}
+
+impl Default for QuietHours {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            start: NaiveTime::from_hms_opt(22, 0, 0).unwrap_or_default(),
+            end: NaiveTime::from_hms_opt(7, 0, 0).unwrap_or_default(),
+        }
+    }
+}
+
+impl QuietHours {
+    /// Whether `time` falls in the quiet hours
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
 /// Application-wide configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
     /// Application version
     pub version: String,
-    /// Default folder for downloads
+    /// Default folder for downloads; attachments are opened from here
     pub download_folder: PathBuf,
+    /// Ask before opening an attachment in another program
+    #[serde(default = "default_true")]
+    pub confirm_open_attachments: bool,
     /// Check for updates on startup
     pub check_updates: bool,
     /// Theme name
@@ -23,44 +200,313 @@ pub struct AppConfig {
     pub font_size: u32,
     /// Enable notifications
     pub enable_notifications: bool,
+    /// Play a sound with new-mail notifications
+    #[serde(default = "default_true")]
+    pub notification_sound: bool,
+    /// Do-not-disturb hours for new-mail notifications
+    #[serde(default)]
+    pub quiet_hours: QuietHours,
+    /// Notify for mail from mailing lists too (messages with `List-Id`)
+    #[serde(default)]
+    pub notify_mailing_lists: bool,
     /// Log level
     pub log_level: String,
     /// Show preview dialog before sending emails
     #[serde(default = "default_true")]
     pub preview_before_send: bool,
+    /// Ask before a Reply All goes to more than this many recipients;
+    /// 0 never asks
+    #[serde(default = "default_reply_all_warning")]
+    pub reply_all_warning_recipients: u32,
+    /// Seconds between saves of the message being written; 0 turns
+    /// autosave off
+    #[serde(default = "default_autosave_seconds")]
+    pub autosave_seconds: u32,
+    /// Open the composer in a window of its own, left open beside the
+    /// main window, instead of over it
+    #[serde(default)]
+    pub compose_in_window: bool,
     /// Spell-check language code (e.g. "en", "es", "fr", "de")
     #[serde(default = "default_language")]
     pub language: String,
+    /// Interface language code (e.g. "de"); `None` follows the system locale
+    #[serde(default)]
+    pub ui_locale: Option<String>,
     /// Default sort order for message list
     #[serde(default = "default_sort_order")]
     pub default_sort_order: String,
+    /// List only folders on the server's subscription list (`LSUB`)
+    /// instead of every folder (`LIST`)
+    #[serde(default)]
+    pub subscribed_folders_only: bool,
+    /// Tell the server when messages are marked as spam or not spam, with
+    /// the `$Junk` and `$NotJunk` keywords its spam filter can learn from
+    #[serde(default = "default_true")]
+    pub report_junk: bool,
+    /// Score new mail with the local spam classifier and move likely spam
+    /// to Junk
+    #[serde(default)]
+    pub spam_filter: bool,
+    /// Spam score (percent) at which the classifier moves new mail to Junk
+    #[serde(default = "default_spam_threshold")]
+    pub spam_threshold_percent: u32,
+    /// Minutes between automatic mail checks (0 disables automatic checks)
+    #[serde(default = "default_check_interval")]
+    pub check_interval_minutes: u32,
+    /// Open the message list in threaded view
+    #[serde(default)]
+    pub thread_view_default: bool,
+    /// Load remote images referenced by HTML messages; when off, the preview
+    /// drops their web addresses and marks them as blocked
+    #[serde(default)]
+    pub load_remote_images: bool,
+    /// Ask for confirmation before deleting messages
+    #[serde(default = "default_true")]
+    pub confirm_before_delete: bool,
+    /// Drop cached message bodies older than this many days (0 keeps them)
+    #[serde(default)]
+    pub cache_keep_body_days: u32,
+    /// Drop cached message bodies larger than this many KB (0 = no limit)
+    #[serde(default)]
+    pub cache_max_body_kb: u32,
+    /// Seconds an opened message stays selected before it is marked read
+    /// (`None` = only mark messages read manually)
+    #[serde(default = "default_mark_read_delay")]
+    pub mark_read_delay_seconds: Option<u32>,
+    /// Response to read receipt requests (see `ReceiptPolicy`)
+    #[serde(default = "default_receipt_policy")]
+    pub read_receipt_policy: String,
+    /// Keep each message's raw source in the cache once it has been viewed
+    #[serde(default)]
+    pub cache_message_source: bool,
+    /// Encrypt cached message bodies and sources with the master password
+    #[serde(default)]
+    pub encrypt_message_bodies: bool,
+    /// Encrypt cached subjects too (only with `encrypt_message_bodies`)
+    #[serde(default)]
+    pub encrypt_message_subjects: bool,
+    /// Show messages as plain text even when they have an HTML version
+    #[serde(default)]
+    pub plain_text_default: bool,
+    /// View mode remembered per sender (lower-cased address → plain text),
+    /// taking precedence over `plain_text_default`
+    #[serde(default)]
+    pub sender_plain_text: HashMap<String, bool>,
+    /// Warn when a mailbox is this full (percent of its quota); 0 hides
+    /// the mailbox usage meter
+    #[serde(default = "default_quota_warning_percent")]
+    pub quota_warning_percent: u32,
+    /// Seconds to wait when connecting to a mail server
+    #[serde(default = "default_connect_timeout")]
+    pub connect_timeout_seconds: u32,
+    /// Seconds to wait for a mail server to answer once connected
+    #[serde(default = "default_read_timeout")]
+    pub read_timeout_seconds: u32,
+    /// Send queued messages over one SMTP connection instead of one each;
+    /// off for servers that limit messages per connection
+    #[serde(default = "default_true")]
+    pub reuse_smtp_connection: bool,
+    /// Messages each account may send a minute (0 = no limit)
+    #[serde(default = "default_sends_per_minute")]
+    pub sends_per_minute: u32,
+    /// New connections each account may open to a server a minute (0 = no
+    /// limit)
+    #[serde(default = "default_connections_per_minute")]
+    pub connections_per_minute: u32,
+    /// Messages each account may download at once
+    #[serde(default = "default_fetch_concurrency")]
+    pub fetch_concurrency: u32,
+    /// Automatic replies while away, for every account
+    #[serde(default)]
+    pub vacation: VacationResponder,
+    /// Keyboard shortcuts changed from their defaults, by command (the
+    /// menu item's string key, e.g. "menu.reply" → "Ctrl+Shift+Y"); an
+    /// empty shortcut removes the command's default one
+    #[serde(default)]
+    pub shortcuts: HashMap<String, String>,
 }
 
+/// Lower-cased address part of `Name <address>` (or of a bare address).
+fn sender_key(sender: &str) -> String {
+    let address = match (sender.rfind('<'), sender.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &sender[start + 1..end],
+        _ => sender,
+    };
+    address.trim().to_lowercase()
+}
+
+/// Base font size; zoom percentages are relative to this.
+pub const DEFAULT_FONT_SIZE: u32 = 12;
+/// Smallest allowed font size
+pub const MIN_FONT_SIZE: u32 = 8;
+/// Largest allowed font size
+pub const MAX_FONT_SIZE: u32 = 72;
+
 fn default_true() -> bool { true }
 fn default_language() -> String { "en".to_string() }
 fn default_sort_order() -> String { "date_newest".to_string() }
+fn default_check_interval() -> u32 { 15 }
+fn default_quota_warning_percent() -> u32 { 90 }
+fn default_reply_all_warning() -> u32 { 10 }
+fn default_autosave_seconds() -> u32 { 60 }
+fn default_spam_threshold() -> u32 { 90 }
+fn default_connect_timeout() -> u32 { 30 }
+fn default_read_timeout() -> u32 { 60 }
+fn default_sends_per_minute() -> u32 { RateLimits::default().sends_per_minute }
+fn default_connections_per_minute() -> u32 { RateLimits::default().connections_per_minute }
+fn default_fetch_concurrency() -> u32 { RateLimits::default().fetch_concurrency }
+fn default_mark_read_delay() -> Option<u32> { Some(0) }
+fn default_receipt_policy() -> String { "ask".to_string() }
 
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
             version: env!("CARGO_PKG_VERSION").to_string(),
             download_folder: dirs::download_dir().unwrap_or_else(|| PathBuf::from(".")),
+            confirm_open_attachments: true,
             check_updates: true,
             theme: "default".to_string(),
-            font_size: 12,
+            font_size: DEFAULT_FONT_SIZE,
             enable_notifications: true,
+            notification_sound: true,
+            quiet_hours: QuietHours::default(),
+            notify_mailing_lists: false,
             log_level: "info".to_string(),
             preview_before_send: true,
+            reply_all_warning_recipients: default_reply_all_warning(),
+            autosave_seconds: default_autosave_seconds(),
+            compose_in_window: false,
             language: "en".to_string(),
+            ui_locale: None,
             default_sort_order: "date_newest".to_string(),
+            subscribed_folders_only: false,
+            report_junk: true,
+            spam_filter: false,
+            spam_threshold_percent: default_spam_threshold(),
+            check_interval_minutes: 15,
+            thread_view_default: false,
+            load_remote_images: false,
+            confirm_before_delete: true,
+            cache_keep_body_days: 0,
+            cache_max_body_kb: 0,
+            mark_read_delay_seconds: Some(0),
+            read_receipt_policy: default_receipt_policy(),
+            cache_message_source: false,
+            encrypt_message_bodies: false,
+            encrypt_message_subjects: false,
+            plain_text_default: false,
+            sender_plain_text: HashMap::new(),
+            quota_warning_percent: default_quota_warning_percent(),
+            connect_timeout_seconds: default_connect_timeout(),
+            read_timeout_seconds: default_read_timeout(),
+            reuse_smtp_connection: true,
+            sends_per_minute: default_sends_per_minute(),
+            connections_per_minute: default_connections_per_minute(),
+            fetch_concurrency: default_fetch_concurrency(),
+            vacation: VacationResponder::default(),
+            shortcuts: HashMap::new(),
         }
     }
 }
 
 impl AppConfig {
+    /// Selected colour theme
+    pub fn theme_preference(&self) -> Theme {
+        Theme::from_key(&self.theme)
+    }
+
+    /// Selected log level; unknown values log at info
+    pub fn logging_level(&self) -> LogLevel {
+        LogLevel::parse(&self.log_level).unwrap_or(LogLevel::Info)
+    }
+
+    /// Step the font size by `steps` zoom increments (2pt each) and return it.
+    ///
+    /// A step of 0 resets to the default size.
+    pub fn zoom_font(&mut self, steps: i32) -> u32 {
+        self.font_size = if steps == 0 {
+            DEFAULT_FONT_SIZE
+        } else {
+            (self.font_size as i32 + steps * 2).clamp(MIN_FONT_SIZE as i32, MAX_FONT_SIZE as i32) as u32
+        };
+        self.font_size
+    }
+
+    /// Current zoom level as a percentage of the default font size
+    pub fn zoom_percent(&self) -> u32 {
+        self.font_size * 100 / DEFAULT_FONT_SIZE
+    }
+
+    /// Cache pruning rules from the storage settings, or `None` when no
+    /// limits are configured.
+    pub fn prune_policy(&self) -> Option<PrunePolicy> {
+        let policy = PrunePolicy {
+            keep_bodies_days: (self.cache_keep_body_days > 0).then_some(self.cache_keep_body_days),
+            max_body_kb: (self.cache_max_body_kb > 0).then_some(self.cache_max_body_kb),
+            vacuum: true,
+        };
+        (policy.keep_bodies_days.is_some() || policy.max_body_kb.is_some()).then_some(policy)
+    }
+
+    /// Which cached message text to encrypt once a master password is set
+    pub fn content_encryption(&self) -> ContentEncryption {
+        ContentEncryption::from_flags(self.encrypt_message_bodies, self.encrypt_message_subjects)
+    }
+
+    /// How long to wait before marking an opened message read, or `None`
+    /// when messages are only marked read manually.
+    pub fn mark_read_delay(&self) -> Option<std::time::Duration> {
+        self.mark_read_delay_seconds.map(|s| std::time::Duration::from_secs(s as u64))
+    }
+
+    /// Connection timeouts for the mail protocols
+    pub fn network_timeouts(&self) -> NetworkTimeouts {
+        NetworkTimeouts::from_secs(self.connect_timeout_seconds as u64, self.read_timeout_seconds as u64)
+    }
+
+    /// How fast each account may use its mail servers
+    pub fn rate_limits(&self) -> RateLimits {
+        RateLimits {
+            sends_per_minute: self.sends_per_minute,
+            connections_per_minute: self.connections_per_minute,
+            fetch_concurrency: self.fetch_concurrency.max(1),
+        }
+    }
+
+    /// Spam score, from 0 to 1, at which new mail counts as likely spam,
+    /// or `None` when the spam filter is off
+    pub fn spam_threshold(&self) -> Option<f64> {
+        self.spam_filter.then(|| self.spam_threshold_percent.min(100) as f64 / 100.0)
+    }
+
+    /// Response to read receipt requests
+    pub fn receipt_policy(&self) -> ReceiptPolicy {
+        ReceiptPolicy::from_key(&self.read_receipt_policy)
+    }
+
+    /// Whether messages from `sender` open as plain text.
+    ///
+    /// `sender` may be a bare address or `Name <address>`.
+    pub fn prefers_plain_text(&self, sender: &str) -> bool {
+        self.sender_plain_text
+            .get(&sender_key(sender))
+            .copied()
+            .unwrap_or(self.plain_text_default)
+    }
+
+    /// Remember how to show messages from `sender`; `None` forgets the
+    /// choice so the global default applies again.
+    pub fn remember_plain_text(&mut self, sender: &str, plain: Option<bool>) {
+        let key = sender_key(sender);
+        match plain {
+            Some(plain) => { self.sender_plain_text.insert(key, plain); }
+            None => { self.sender_plain_text.remove(&key); }
+        }
+    }
+
     /// Validate configuration values
     pub fn validate(&self) -> Result<()> {
-        if self.font_size < 8 || self.font_size > 72 {
+        if self.font_size < MIN_FONT_SIZE || self.font_size > MAX_FONT_SIZE {
             return Err(Error::Config(
                 "Font size must be between 8 and 72".to_string(),
             ));
@@ -74,6 +520,12 @@ impl AppConfig {
             )));
         }
 
+        if self.check_interval_minutes > 1440 {
+            return Err(Error::Config(
+                "Check interval must be between 0 and 1440 minutes".to_string(),
+            ));
+        }
+
         Ok(())
     }
 }
@@ -120,6 +572,57 @@ impl AccountConfig {
     }
 }
 
+/// Smallest saved window size that is restored
+const MIN_WINDOW_SIZE: (i32, i32) = (400, 300);
+
+/// Where the user left off, saved in `session.json` as it changes and on
+/// quit, and restored at the next start
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionState {
+    /// Account shown
+    #[serde(default)]
+    pub account_id: Option<String>,
+    /// Folder path shown in the message list
+    #[serde(default)]
+    pub folder: Option<String>,
+    /// Sort order key, as in [`AppConfig::default_sort_order`]
+    #[serde(default)]
+    pub sort_order: Option<String>,
+    /// Messages grouped into conversations
+    #[serde(default)]
+    pub thread_view: Option<bool>,
+    /// Message list grouping key: `none`, `sender`, `domain` or `list`
+    #[serde(default)]
+    pub group_by: Option<String>,
+    /// Main window size when last closed
+    #[serde(default)]
+    pub window_width: Option<i32>,
+    #[serde(default)]
+    pub window_height: Option<i32>,
+}
+
+impl SessionState {
+    /// The saved folder if it is still one of `folders`, else the Inbox
+    pub fn folder_in(&self, folders: &[String]) -> String {
+        self.folder
+            .clone()
+            .filter(|f| folders.contains(f))
+            .unwrap_or_else(|| "INBOX".to_string())
+    }
+
+    /// The saved account if it still exists
+    pub fn account_in<'a>(&self, account_ids: impl IntoIterator<Item = &'a str>) -> Option<String> {
+        let saved = self.account_id.as_deref()?;
+        account_ids.into_iter().find(|id| *id == saved).map(str::to_string)
+    }
+
+    /// The saved window size, unless it is too small to use
+    pub fn window_size(&self) -> Option<(i32, i32)> {
+        let size = (self.window_width?, self.window_height?);
+        (size.0 >= MIN_WINDOW_SIZE.0 && size.1 >= MIN_WINDOW_SIZE.1).then_some(size)
+    }
+}
+
 /// Legacy configuration (for backwards compatibility)
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -166,33 +669,27 @@ pub struct ConfigManager {
 impl ConfigManager {
     /// Create a new configuration manager
     pub fn new() -> Result<Self> {
-        let config_dir = Self::get_config_dir()?;
-        Ok(Self {
+        Ok(Self::with_dir(Self::get_config_dir()?))
+    }
+
+    /// Folder holding extra interface translations (`<language>.json`)
+    pub fn locale_dir(&self) -> PathBuf {
+        self.config_dir.join("locales")
+    }
+
+    /// Create a configuration manager for the settings in `config_dir`
+    pub fn with_dir(config_dir: PathBuf) -> Self {
+        Self {
             config: Config::new(),
             app_config: AppConfig::default(),
             account_configs: HashMap::new(),
             config_dir,
-        })
+        }
     }
 
-    /// Get the configuration directory
+    /// Get the configuration directory of the active profile
     fn get_config_dir() -> Result<PathBuf> {
-        let base_dir = if cfg!(windows) {
-            dirs::data_local_dir().or_else(dirs::config_dir)
-        } else {
-            dirs::config_dir()
-        };
-
-        let config_dir = base_dir
-            .ok_or_else(|| Error::Config("Could not determine config directory".to_string()))?
-            .join("wixen-mail");
-
-        if !config_dir.exists() {
-            fs::create_dir_all(&config_dir)
-                .map_err(|e| Error::Config(format!("Failed to create config directory: {}", e)))?;
-        }
-
-        Ok(config_dir)
+        Ok(ProfilePaths::current()?.config_dir)
     }
 
     /// Get app config file path
@@ -205,6 +702,11 @@ impl ConfigManager {
         self.config_dir.join(format!("account_{}.json", account_id))
     }
 
+    /// Get session state file path
+    fn session_path(&self) -> PathBuf {
+        self.config_dir.join("session.json")
+    }
+
     /// Load configuration from file
     pub fn load(&mut self) -> Result<()> {
         // Load app config
@@ -275,6 +777,32 @@ impl ConfigManager {
         Ok(())
     }
 
+    /// Replace the application configuration and write it to disk.
+    pub fn save_app_settings(&mut self, config: &AppConfig) -> Result<()> {
+        config.validate()?;
+        self.app_config = config.clone();
+        self.save_app_config()
+    }
+
+    /// The session saved on the last quit, or an empty one if there is none
+    pub fn load_session(&self) -> Result<SessionState> {
+        let path = self.session_path();
+        if !path.exists() {
+            return Ok(SessionState::default());
+        }
+        let content = fs::read_to_string(&path)
+            .map_err(|e| Error::Config(format!("Failed to read session: {}", e)))?;
+        serde_json::from_str(&content).map_err(|e| Error::Config(format!("Failed to parse session: {}", e)))
+    }
+
+    /// Write the session for the next start
+    pub fn save_session(&self, session: &SessionState) -> Result<()> {
+        let content = serde_json::to_string_pretty(session)
+            .map_err(|e| Error::Config(format!("Failed to serialize session: {}", e)))?;
+        fs::write(self.session_path(), content)
+            .map_err(|e| Error::Config(format!("Failed to write session: {}", e)))
+    }
+
     /// Get application configuration
     pub fn app_config(&self) -> &AppConfig {
         &self.app_config
@@ -323,12 +851,7 @@ impl ConfigManager {
 
 impl Default for ConfigManager {
     fn default() -> Self {
-        Self::new().unwrap_or_else(|_| Self {
-            config: Config::new(),
-            app_config: AppConfig::default(),
-            account_configs: HashMap::new(),
-            config_dir: PathBuf::from("."),
-        })
+        Self::new().unwrap_or_else(|_| Self::with_dir(PathBuf::from(".")))
     }
 }
 
@@ -369,6 +892,26 @@ mod tests {
         assert!(config.enable_notifications);
     }
 
+    #[test]
+    fn test_zoom_font_clamps_and_resets() {
+        let mut config = AppConfig::default();
+        assert_eq!(config.zoom_font(2), 16);
+        assert_eq!(config.zoom_percent(), 133);
+        assert_eq!(config.zoom_font(100), MAX_FONT_SIZE);
+        assert_eq!(config.zoom_font(-100), MIN_FONT_SIZE);
+        assert_eq!(config.zoom_font(0), DEFAULT_FONT_SIZE);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_theme_key_round_trip() {
+        for theme in Theme::ALL {
+            assert_eq!(Theme::from_key(theme.as_key()), theme);
+        }
+        assert_eq!(AppConfig::default().theme_preference(), Theme::System);
+        assert_eq!(Theme::from_key("solarized"), Theme::System);
+    }
+
     #[test]
     fn test_app_config_validation() {
         let mut config = AppConfig::default();
@@ -380,6 +923,113 @@ mod tests {
         config.font_size = 12;
         config.log_level = "invalid".to_string();
         assert!(config.validate().is_err());
+        assert_eq!(config.logging_level(), LogLevel::Info);
+
+        config.log_level = "debug".to_string();
+        assert_eq!(config.logging_level(), LogLevel::Debug);
+    }
+
+    #[test]
+    fn test_app_config_missing_fields_use_defaults() {
+        let legacy = r#"{
+            "version": "0.1.0", "download_folder": ".", "check_updates": true,
+            "theme": "dark", "font_size": 14, "enable_notifications": true,
+            "log_level": "info"
+        }"#;
+        let config: AppConfig = serde_json::from_str(legacy).unwrap();
+        assert_eq!(config.theme, "dark");
+        assert_eq!(config.check_interval_minutes, 15);
+        assert!(config.confirm_before_delete);
+        assert!(!config.load_remote_images);
+        assert_eq!(config.prune_policy(), None);
+        assert_eq!(config.mark_read_delay(), Some(std::time::Duration::ZERO));
+        assert_eq!(config.receipt_policy(), ReceiptPolicy::Ask);
+        assert_eq!(config.network_timeouts(), NetworkTimeouts::default());
+        assert_eq!(config.ui_locale, None);
+        assert_eq!(config.reply_all_warning_recipients, 10);
+        assert_eq!(config.autosave_seconds, 60);
+        assert!(!config.compose_in_window);
+        assert!(!config.subscribed_folders_only);
+        assert!(config.report_junk);
+        assert!(!config.spam_filter);
+        assert_eq!(config.spam_threshold_percent, 90);
+        assert_eq!(config.spam_threshold(), None);
+        assert!(config.reuse_smtp_connection);
+        assert_eq!(config.rate_limits(), RateLimits::default());
+        assert_eq!(config.content_encryption(), ContentEncryption::Off);
+        assert!(config.confirm_open_attachments);
+        assert!(config.shortcuts.is_empty());
+
+        let mut config = config;
+        config.cache_max_body_kb = 512;
+        let policy = config.prune_policy().unwrap();
+        assert_eq!(policy.max_body_kb, Some(512));
+        assert_eq!(policy.keep_bodies_days, None);
+        config.spam_filter = true;
+        assert_eq!(config.spam_threshold(), Some(0.9));
+        config.check_interval_minutes = 5000;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_vacation_responder_dates() {
+        let day = |d: u32| NaiveDate::from_ymd_opt(2025, 7, d).unwrap();
+        let mut vacation = AppConfig::default().vacation;
+        assert!(!vacation.is_active(day(1)));
+        assert!(vacation.validate().is_ok());
+
+        vacation.enabled = true;
+        assert!(vacation.validate().is_err(), "a message is required");
+        vacation.message = "Back on the 14th.".to_string();
+        assert!(vacation.is_active(day(1)));
+
+        vacation.start = Some(day(4));
+        vacation.end = Some(day(13));
+        assert!(!vacation.is_active(day(3)));
+        assert!(vacation.is_active(day(4)) && vacation.is_active(day(13)));
+        assert!(!vacation.is_active(day(14)));
+        assert!(vacation.validate().is_ok());
+
+        vacation.end = Some(day(2));
+        assert!(vacation.validate().is_err());
+        let json = serde_json::to_string(&vacation).unwrap();
+        assert!(json.contains("\"start\":\"2025-07-04\""));
+        assert_eq!(serde_json::from_str::<VacationResponder>(&json).unwrap(), vacation);
+    }
+
+    #[test]
+    fn test_quiet_hours() {
+        let at = |h: u32, m: u32| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        let mut quiet = AppConfig::default().quiet_hours;
+        assert!(!quiet.contains(at(23, 0)), "off by default");
+
+        // 22:00 to 07:00 runs past midnight
+        quiet.enabled = true;
+        assert!(quiet.contains(at(22, 0)) && quiet.contains(at(2, 30)));
+        assert!(!quiet.contains(at(7, 0)) && !quiet.contains(at(12, 0)));
+
+        quiet.start = at(12, 0);
+        quiet.end = at(13, 30);
+        assert!(quiet.contains(at(12, 45)));
+        assert!(!quiet.contains(at(13, 30)) && !quiet.contains(at(23, 0)));
+        let json = serde_json::to_string(&quiet).unwrap();
+        assert_eq!(serde_json::from_str::<QuietHours>(&json).unwrap(), quiet);
+    }
+
+    #[test]
+    fn test_plain_text_preference() {
+        let mut config = AppConfig::default();
+        assert!(!config.prefers_plain_text("alice@example.com"));
+
+        config.remember_plain_text("Alice <Alice@Example.com>", Some(true));
+        assert!(config.prefers_plain_text("alice@example.com"));
+        assert!(!config.prefers_plain_text("bob@example.com"));
+
+        config.plain_text_default = true;
+        config.remember_plain_text("bob@example.com", Some(false));
+        assert!(!config.prefers_plain_text("Bob <bob@example.com>"));
+        config.remember_plain_text("bob@example.com", None);
+        assert!(config.prefers_plain_text("bob@example.com"));
     }
 
     #[test]
@@ -413,4 +1063,51 @@ mod tests {
         assert!(retrieved.is_some());
         assert_eq!(retrieved.unwrap().name, "Test");
     }
+
+    #[test]
+    fn test_session_round_trip() {
+        let dir = std::env::temp_dir().join(format!("wixen_mail_test_session_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let manager = ConfigManager::with_dir(dir.clone());
+        assert_eq!(manager.load_session().unwrap(), SessionState::default());
+
+        let session = SessionState {
+            account_id: Some("acc-1".into()),
+            folder: Some("Archive".into()),
+            sort_order: Some("sender_az".into()),
+            thread_view: Some(true),
+            group_by: Some("domain".into()),
+            window_width: Some(1024),
+            window_height: Some(700),
+        };
+        manager.save_session(&session).unwrap();
+        assert_eq!(ConfigManager::with_dir(dir.clone()).load_session().unwrap(), session);
+
+        fs::write(dir.join("session.json"), "{not json").unwrap();
+        assert!(matches!(manager.load_session(), Err(Error::Config(_))));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_session_stale_references() {
+        let mut session: SessionState = serde_json::from_str(r#"{"folder": "Old Project"}"#).unwrap();
+        assert_eq!(session.sort_order, None);
+        let folders = vec!["INBOX".to_string(), "Archive".to_string()];
+        assert_eq!(session.folder_in(&folders), "INBOX", "deleted folder falls back to the Inbox");
+        session.folder = Some("Archive".into());
+        assert_eq!(session.folder_in(&folders), "Archive");
+
+        assert_eq!(session.account_in(["acc-1"]), None);
+        session.account_id = Some("acc-2".into());
+        assert_eq!(session.account_in(["acc-1"]), None, "removed account is ignored");
+        assert_eq!(session.account_in(["acc-1", "acc-2"]), Some("acc-2".to_string()));
+
+        session.window_width = Some(120);
+        session.window_height = Some(90);
+        assert_eq!(session.window_size(), None);
+        session.window_width = Some(900);
+        session.window_height = Some(600);
+        assert_eq!(session.window_size(), Some((900, 600)));
+    }
 }
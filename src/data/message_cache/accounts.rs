@@ -7,19 +7,20 @@ use rusqlite::params;
 impl MessageCache {
     /// Save an account to the database
     pub fn save_account(&self, account: &crate::data::account::Account) -> Result<()> {
-        use chrono::Utc;
-
         let encoded_password = self.encrypt_value(&account.password)?;
 
-        let now = Utc::now().to_rfc3339();
+        let now = self.now().to_rfc3339();
 
         self.conn.execute(
             "INSERT OR REPLACE INTO accounts
              (id, name, email, imap_server, imap_port, imap_use_tls,
               smtp_server, smtp_port, smtp_use_tls, username, password,
               enabled, check_interval_minutes, provider, last_sync, color,
-              created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+              created_at, updated_at, save_sent_copy, sync_drafts, delete_mode,
+              pin_certificates, imap_pinned_fingerprint, smtp_pinned_fingerprint, notify_new_mail,
+              envelope_from)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21,
+                     ?22, ?23, ?24, ?25, ?26)",
             params![
                 &account.id,
                 &account.name,
@@ -36,13 +37,21 @@ impl MessageCache {
                 &account.check_interval_minutes,
                 &account.provider,
                 &account.last_sync.as_ref().map(|t| {
-                    chrono::DateTime::<Utc>::from(*t).to_rfc3339()
+                    chrono::DateTime::<chrono::Utc>::from(*t).to_rfc3339()
                 }),
                 &account.color,
                 &now,
-                &now
+                &now,
+                &account.save_sent_copy,
+                &account.sync_drafts,
+                &account.delete_mode.as_str(),
+                &account.pin_certificates,
+                &account.imap_pinned_fingerprint,
+                &account.smtp_pinned_fingerprint,
+                &account.notify_new_mail,
+                &account.envelope_from
             ],
-        ).map_err(|e| Error::Other(format!("Failed to save account: {}", e)))?;
+        ).map_err(|e| Error::Database(format!("Failed to save account: {}", e)))?;
 
         Ok(())
     }
@@ -54,11 +63,13 @@ impl MessageCache {
             .prepare(
                 "SELECT id, name, email, imap_server, imap_port, imap_use_tls,
                     smtp_server, smtp_port, smtp_use_tls, username, password,
-                    enabled, check_interval_minutes, provider, last_sync, color
+                    enabled, check_interval_minutes, provider, last_sync, color, save_sent_copy, sync_drafts,
+                    delete_mode, pin_certificates, imap_pinned_fingerprint, smtp_pinned_fingerprint, notify_new_mail,
+                    envelope_from
              FROM accounts
              ORDER BY created_at",
             )
-            .map_err(|e| Error::Other(format!("Failed to prepare statement: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to prepare statement: {}", e)))?;
 
         let accounts = stmt
             .query_map([], |row| {
@@ -88,6 +99,14 @@ impl MessageCache {
                         provider: row.get(13)?,
                         last_sync: last_sync_time,
                         color: row.get(15)?,
+                        save_sent_copy: row.get(16)?,
+                        sync_drafts: row.get(17)?,
+                        delete_mode: crate::data::account::DeleteMode::parse(&row.get::<_, String>(18)?),
+                        pin_certificates: row.get(19)?,
+                        imap_pinned_fingerprint: row.get(20)?,
+                        smtp_pinned_fingerprint: row.get(21)?,
+                        notify_new_mail: row.get(22)?,
+                        envelope_from: row.get(23)?,
                         use_oauth: false,
                         oauth_access_token: String::new(),
                         oauth_refresh_token: String::new(),
@@ -95,12 +114,12 @@ impl MessageCache {
                     },
                 ))
             })
-            .map_err(|e| Error::Other(format!("Failed to query accounts: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to query accounts: {}", e)))?;
 
         let mut result = Vec::new();
         for row in accounts {
             let (encoded_password, mut account) =
-                row.map_err(|e| Error::Other(format!("Failed to parse account: {}", e)))?;
+                row.map_err(|e| Error::Database(format!("Failed to parse account: {}", e)))?;
             account.password = self.decrypt_value(&encoded_password).unwrap_or_default();
             result.push(account);
         }
@@ -112,20 +131,19 @@ impl MessageCache {
     pub fn delete_account(&self, account_id: &str) -> Result<()> {
         self.conn
             .execute("DELETE FROM accounts WHERE id = ?1", params![account_id])
-            .map_err(|e| Error::Other(format!("Failed to delete account: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to delete account: {}", e)))?;
         Ok(())
     }
 
     /// Update an account's last sync timestamp
     pub fn update_account_last_sync(&self, account_id: &str) -> Result<()> {
-        use chrono::Utc;
-        let now = Utc::now().to_rfc3339();
+        let now = self.now().to_rfc3339();
         self.conn
             .execute(
                 "UPDATE accounts SET last_sync = ?1, updated_at = ?2 WHERE id = ?3",
                 params![&now, &now, account_id],
             )
-            .map_err(|e| Error::Other(format!("Failed to update last sync: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to update last sync: {}", e)))?;
         Ok(())
     }
 }
@@ -135,6 +153,90 @@ mod tests {
     use super::*;
     use std::env;
 
+    #[test]
+    fn test_rekey_reencrypts_account_passwords() {
+        use crate::service::security::SecurityService;
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_rekey_{}", nanos));
+        let mut cache = MessageCache::new(temp_dir, None).unwrap();
+
+        let mut account = crate::data::account::Account::new("Work".into(), "work@example.com".into());
+        account.password = "secret123".to_string();
+        cache.save_account(&account).unwrap();
+
+        let first = SecurityService::from_master_password("first", b"0123456789abcdef").unwrap();
+        cache.rekey(first).unwrap();
+        let stored: String = cache
+            .conn
+            .query_row("SELECT password FROM accounts WHERE id = ?1", params![account.id], |r| r.get(0))
+            .unwrap();
+        assert!(stored.starts_with("WXM2:"));
+        assert_eq!(cache.load_accounts().unwrap()[0].password, "secret123");
+
+        let second = SecurityService::from_master_password("second", b"fedcba9876543210").unwrap();
+        cache.rekey(second.clone()).unwrap();
+        assert_eq!(cache.load_accounts().unwrap()[0].password, "secret123");
+
+        // A key that cannot read the stored secrets leaves them untouched.
+        cache.set_security(Some(SecurityService::from_master_password("wrong", b"0000000000000000").unwrap()));
+        assert!(cache.rekey(second.clone()).is_err());
+        cache.set_security(Some(second));
+        assert_eq!(cache.load_accounts().unwrap()[0].password, "secret123");
+    }
+
+    #[test]
+    fn test_reencrypt_plaintext_secrets() {
+        use crate::service::security::SecurityService;
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_upgrade_{}", nanos));
+        let mut cache = MessageCache::new(temp_dir, None).unwrap();
+
+        for name in ["One", "Two"] {
+            let mut account = crate::data::account::Account::new(name.into(), format!("{}@example.com", name));
+            account.password = format!("{}-secret", name);
+            cache.save_account(&account).unwrap();
+        }
+        assert_eq!(cache.count_plaintext_secrets().unwrap(), 2);
+        assert!(cache.reencrypt_plaintext_secrets().is_err());
+
+        let key = SecurityService::from_master_password("pw", b"0123456789abcdef").unwrap();
+        cache.set_security(Some(key));
+        assert_eq!(cache.reencrypt_plaintext_secrets().unwrap(), 2);
+        assert_eq!(cache.count_plaintext_secrets().unwrap(), 0);
+        assert_eq!(cache.reencrypt_plaintext_secrets().unwrap(), 0);
+
+        let mut passwords: Vec<String> = cache.load_accounts().unwrap().into_iter().map(|a| a.password).collect();
+        passwords.sort();
+        assert_eq!(passwords, vec!["One-secret", "Two-secret"]);
+    }
+
+    #[test]
+    fn test_locked_cache_refuses_to_overwrite_passwords() {
+        use crate::service::security::SecurityService;
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_locked_{}", nanos));
+        let key = SecurityService::from_master_password("pw", b"0123456789abcdef").unwrap();
+        let mut account = crate::data::account::Account::new("Work".into(), "work@example.com".into());
+        account.password = "secret123".to_string();
+        MessageCache::new(temp_dir.clone(), Some(key.clone())).unwrap().save_account(&account).unwrap();
+
+        let mut cache = MessageCache::new(temp_dir, None).unwrap().with_secrets_locked();
+        account.password = String::new();
+        assert!(matches!(cache.save_account(&account), Err(Error::Security(_))));
+
+        cache.set_security(Some(key));
+        assert_eq!(cache.load_accounts().unwrap()[0].password, "secret123");
+    }
+
     #[test]
     fn test_account_persistence() {
         let temp_dir = env::temp_dir().join("wixen_mail_test_accounts");
@@ -150,7 +252,11 @@ mod tests {
             username: "work@example.com".to_string(), password: "secret123".to_string(),
             enabled: true, check_interval_minutes: 5,
             provider: Some("Gmail".to_string()), last_sync: None,
-            color: "#FF0000".to_string(),
+            color: "#FF0000".to_string(), save_sent_copy: true, sync_drafts: true,
+            delete_mode: crate::data::account::DeleteMode::Expunge,
+            pin_certificates: true, imap_pinned_fingerprint: None,
+            smtp_pinned_fingerprint: Some("AB:CD".to_string()), notify_new_mail: true,
+            envelope_from: Some("bounces@example.net".to_string()),
             use_oauth: false, oauth_access_token: String::new(),
             oauth_refresh_token: String::new(), oauth_token_expires_at: None,
         };
@@ -172,7 +278,10 @@ mod tests {
             username: "personal@example.com".to_string(), password: "password456".to_string(),
             enabled: false, check_interval_minutes: 10,
             provider: Some("Gmail".to_string()), last_sync: None,
-            color: "#00FF00".to_string(),
+            color: "#00FF00".to_string(), save_sent_copy: false, sync_drafts: false,
+            delete_mode: crate::data::account::DeleteMode::MoveToTrash,
+            pin_certificates: false, imap_pinned_fingerprint: None, smtp_pinned_fingerprint: None,
+            notify_new_mail: false, envelope_from: None,
             use_oauth: false, oauth_access_token: String::new(),
             oauth_refresh_token: String::new(), oauth_token_expires_at: None,
         };
@@ -180,6 +289,15 @@ mod tests {
         cache.save_account(&account2).unwrap();
         let all_accounts = cache.load_accounts().unwrap();
         assert_eq!(all_accounts.len(), 2);
+        assert!(all_accounts.iter().any(|a| a.id == "acc-2" && !a.save_sent_copy && !a.notify_new_mail));
+        assert!(all_accounts.iter().any(|a| a.id == "acc-1" && a.notify_new_mail));
+        assert!(all_accounts.iter().any(|a| a.id == "acc-1" && a.envelope_from.as_deref() == Some("bounces@example.net")));
+        assert!(all_accounts.iter().any(|a| a.id == "acc-2" && a.envelope_from.is_none()));
+        assert!(all_accounts.iter().any(|a| a.id == "acc-1" && a.sync_drafts));
+        assert!(all_accounts.iter().any(|a| a.id == "acc-1" && a.delete_mode == crate::data::account::DeleteMode::Expunge));
+        assert!(all_accounts.iter().any(|a| {
+            a.id == "acc-1" && a.pin_certificates && a.smtp_pinned_fingerprint.as_deref() == Some("AB:CD")
+        }));
 
         cache.update_account_last_sync("acc-1").unwrap();
 
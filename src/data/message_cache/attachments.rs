@@ -0,0 +1,150 @@
+//! Attachment list persistence
+//!
+//! What a message has attached, from its `BODYSTRUCTURE`, so the list shows
+//! attachments before any message is downloaded.
+
+use super::{CachedAttachment, MessageCache};
+use crate::common::{Error, Result};
+use rusqlite::{params, Row};
+use std::collections::HashMap;
+
+fn attachment_from_row(row: &Row<'_>) -> rusqlite::Result<CachedAttachment> {
+    Ok(CachedAttachment {
+        id: row.get(0)?,
+        message_id: row.get(1)?,
+        filename: row.get(2)?,
+        mime_type: row.get(3)?,
+        size: row.get(4)?,
+        content_id: row.get(5)?,
+        section: row.get(6)?,
+    })
+}
+
+impl MessageCache {
+    /// Replace the attachment list of the message with `uid` in the folder;
+    /// the ids in `attachments` are not used
+    pub fn save_attachments(&self, folder_id: i64, uid: u32, attachments: &[CachedAttachment]) -> Result<()> {
+        self.in_transaction(|c| {
+            c.conn
+                .execute(
+                    "DELETE FROM attachments
+                     WHERE message_id = (SELECT id FROM messages WHERE folder_id = ?1 AND uid = ?2)",
+                    params![folder_id, uid],
+                )
+                .map_err(|e| Error::Database(format!("Failed to clear attachments: {}", e)))?;
+            let mut insert = c
+                .conn
+                .prepare_cached(
+                    "INSERT INTO attachments (message_id, filename, mime_type, size, content_id, section)
+                     SELECT id, ?3, ?4, ?5, ?6, ?7 FROM messages WHERE folder_id = ?1 AND uid = ?2",
+                )
+                .map_err(|e| Error::Database(format!("Failed to prepare attachment insert: {}", e)))?;
+            for a in attachments {
+                insert
+                    .execute(params![folder_id, uid, a.filename, a.mime_type, a.size, a.content_id, a.section])
+                    .map_err(|e| Error::Database(format!("Failed to save attachment: {}", e)))?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Attachments of a message, in the order they were listed
+    pub fn get_attachments(&self, message_id: i64) -> Result<Vec<CachedAttachment>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached(
+                "SELECT id, message_id, filename, mime_type, size, content_id, section
+                 FROM attachments WHERE message_id = ?1 ORDER BY id",
+            )
+            .map_err(|e| Error::Database(format!("Failed to prepare attachment query: {}", e)))?;
+        let rows = stmt
+            .query_map(params![message_id], attachment_from_row)
+            .map_err(|e| Error::Database(format!("Failed to get attachments: {}", e)))?;
+        rows.collect::<std::result::Result<_, _>>()
+            .map_err(|e| Error::Database(format!("Failed to read attachment: {}", e)))
+    }
+
+    /// Attachments of each message in the folder that has any, by UID
+    pub fn get_attachments_for_folder(&self, folder_id: i64) -> Result<HashMap<u32, Vec<CachedAttachment>>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached(
+                "SELECT a.id, a.message_id, a.filename, a.mime_type, a.size, a.content_id, a.section, m.uid
+                 FROM attachments a INNER JOIN messages m ON a.message_id = m.id
+                 WHERE m.folder_id = ?1 ORDER BY a.id",
+            )
+            .map_err(|e| Error::Database(format!("Failed to prepare attachment query: {}", e)))?;
+        let rows = stmt
+            .query_map(params![folder_id], |row| Ok((row.get::<_, u32>(7)?, attachment_from_row(row)?)))
+            .map_err(|e| Error::Database(format!("Failed to get attachments: {}", e)))?;
+        let mut by_uid: HashMap<u32, Vec<CachedAttachment>> = HashMap::new();
+        for row in rows {
+            let (uid, attachment) = row.map_err(|e| Error::Database(format!("Failed to read attachment: {}", e)))?;
+            by_uid.entry(uid).or_default().push(attachment);
+        }
+        Ok(by_uid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::types::MessagePriority;
+    use crate::data::message_cache::CachedMessage;
+    use std::env;
+
+    #[test]
+    fn test_attachment_lists() {
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_attachments_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+        let inbox = cache.ensure_folder("acc", "INBOX").unwrap().id;
+        let header = |uid: u32| CachedMessage {
+            id: 0, uid, folder_id: inbox, message_id: format!("a{}@example.com", uid),
+            subject: format!("Message {}", uid), from_addr: "a@example.com".to_string(),
+            to_addr: String::new(), cc: None, date: "2024-01-01".to_string(),
+            body_plain: None, body_html: None, read: false, starred: false, deleted: false, priority: MessagePriority::Normal,
+        };
+        cache.save_message_headers(&[header(1), header(2)]).unwrap();
+        let pdf = CachedAttachment {
+            id: 0,
+            message_id: 0,
+            filename: "report.pdf".to_string(),
+            mime_type: "application/pdf".to_string(),
+            size: 2_000_000,
+            content_id: None,
+            section: Some("2".to_string()),
+        };
+        let logo = CachedAttachment {
+            id: 0,
+            message_id: 0,
+            filename: "logo.png".to_string(),
+            mime_type: "image/png".to_string(),
+            size: 5000,
+            content_id: Some("logo@example.com".to_string()),
+            section: Some("3".to_string()),
+        };
+
+        let names = |attachments: &[CachedAttachment]| attachments.iter().map(|a| a.filename.clone()).collect::<Vec<_>>();
+
+        cache.save_attachments(inbox, 1, &[pdf.clone(), logo.clone()]).unwrap();
+        // Saving again replaces the list rather than adding to it
+        cache.save_attachments(inbox, 1, &[pdf.clone(), logo]).unwrap();
+        // An unknown message has nothing to attach to
+        cache.save_attachments(inbox, 9, std::slice::from_ref(&pdf)).unwrap();
+
+        let by_uid = cache.get_attachments_for_folder(inbox).unwrap();
+        assert_eq!(by_uid.len(), 1);
+        assert_eq!(names(&by_uid[&1]), ["report.pdf", "logo.png"]);
+        assert_eq!(by_uid[&1][1].content_id.as_deref(), Some("logo@example.com"));
+        assert_eq!(by_uid[&1][0].section.as_deref(), Some("2"));
+        assert_eq!(by_uid[&1][0].size, 2_000_000);
+        let id = cache.get_messages_for_folder(inbox, "acc").unwrap().iter().find(|m| m.uid == 1).unwrap().id;
+        assert_eq!(names(&cache.get_attachments(id).unwrap()), ["report.pdf", "logo.png"]);
+
+        // Headers saved again keep the message, and so its attachments
+        cache.save_message_headers(&[header(1)]).unwrap();
+        cache.save_attachments(inbox, 1, &[pdf]).unwrap();
+        assert_eq!(names(&cache.get_attachments(id).unwrap()), ["report.pdf"]);
+    }
+}
@@ -0,0 +1,358 @@
+//! Settings backup
+//!
+//! Accounts, tags, signatures, filter rules and contacts in one JSON
+//! bundle, for moving to a new computer or sending to support. Passwords
+//! are left out, or encrypted with a passphrase chosen for the backup so
+//! the bundle is safe to store anywhere.
+
+use super::{ContactEntry, ContactGroup, MessageCache, MessageFilterRule, Signature, Tag};
+use crate::common::{Error, Result};
+use crate::data::account::Account;
+use crate::data::config::AppConfig;
+use crate::service::security::SecurityService;
+use base64::Engine;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+/// Version of the bundle written by this build; newer bundles are refused
+pub const BACKUP_VERSION: u32 = 1;
+/// Bytes of random salt for the passphrase key
+const SALT_LEN: usize = 16;
+
+/// Everything a backup holds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsBackup {
+    pub version: u32,
+    /// RFC 3339 time the backup was made
+    pub created_at: String,
+    /// Application settings, when they were included
+    #[serde(default)]
+    pub settings: Option<AppConfig>,
+    pub accounts: Vec<BackupAccount>,
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+    #[serde(default)]
+    pub signatures: Vec<Signature>,
+    #[serde(default)]
+    pub filter_rules: Vec<MessageFilterRule>,
+    #[serde(default)]
+    pub contacts: Vec<ContactEntry>,
+    #[serde(default)]
+    pub contact_groups: Vec<ContactGroup>,
+    /// Base64 salt of the passphrase that encrypts account secrets;
+    /// `None` when secrets were left out
+    #[serde(default)]
+    pub secrets_salt: Option<String>,
+}
+
+/// An account in a backup. Its password and OAuth tokens are never in
+/// `account` itself.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupAccount {
+    #[serde(flatten)]
+    pub account: Account,
+    /// Password and OAuth tokens, encrypted with the backup passphrase
+    #[serde(default)]
+    pub secrets: Option<String>,
+}
+
+/// Account secrets, kept together so they are encrypted as one value
+#[derive(Serialize, Deserialize)]
+struct AccountSecrets {
+    password: String,
+    oauth_access_token: String,
+    oauth_refresh_token: String,
+}
+
+/// What happens to account passwords and tokens in a backup
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackupSecrets<'a> {
+    /// Left out; the passwords are asked for again after restoring. Safe
+    /// to share, for example with support.
+    Exclude,
+    /// Encrypted with this passphrase
+    Encrypt(&'a str),
+}
+
+/// What restoring does with an item that already exists: an account with
+/// the same id, a contact with the same address, or a tag, signature,
+/// rule or group with the same name in the account
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImportConflict {
+    /// Keep the existing item
+    #[default]
+    Skip,
+    /// Replace it with the one from the backup
+    Overwrite,
+}
+
+/// What a restore did
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub added: usize,
+    pub replaced: usize,
+    pub skipped: usize,
+    /// Names of restored accounts that need their password entered again
+    pub without_password: Vec<String>,
+}
+
+impl ImportSummary {
+    fn count(&mut self, existed: bool, conflict: ImportConflict) -> bool {
+        match (existed, conflict) {
+            (false, _) => self.added += 1,
+            (true, ImportConflict::Overwrite) => self.replaced += 1,
+            (true, ImportConflict::Skip) => {
+                self.skipped += 1;
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl SettingsBackup {
+    /// The backup as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).map_err(|e| Error::Parse(format!("Failed to write backup: {}", e)))
+    }
+
+    /// Read a backup, refusing one from a newer version of the app
+    pub fn from_json(json: &str) -> Result<Self> {
+        let backup: Self =
+            serde_json::from_str(json).map_err(|e| Error::Parse(format!("Not a Wixen Mail settings backup: {}", e)))?;
+        if backup.version > BACKUP_VERSION {
+            return Err(Error::Config("This backup was made by a newer version of Wixen Mail".to_string()));
+        }
+        Ok(backup)
+    }
+
+    /// Whether account passwords are in the backup, needing its passphrase
+    pub fn has_secrets(&self) -> bool {
+        self.secrets_salt.is_some()
+    }
+
+    /// The key for the secrets, from the passphrase and the backup's salt
+    fn secrets_key(&self, passphrase: &str) -> Result<Option<SecurityService>> {
+        let Some(salt) = &self.secrets_salt else { return Ok(None) };
+        let salt = base64::engine::general_purpose::STANDARD
+            .decode(salt)
+            .map_err(|e| Error::Parse(format!("Backup salt is damaged: {}", e)))?;
+        SecurityService::from_master_password(passphrase, &salt).map(Some)
+    }
+}
+
+impl MessageCache {
+    /// Back up every account with its tags, signatures, filter rules,
+    /// contacts and contact groups, and `settings` when given
+    pub fn export_settings(&self, settings: Option<&AppConfig>, secrets: BackupSecrets) -> Result<SettingsBackup> {
+        let key = match secrets {
+            BackupSecrets::Exclude => None,
+            BackupSecrets::Encrypt(passphrase) => {
+                let mut salt = [0u8; SALT_LEN];
+                rand::thread_rng().fill_bytes(&mut salt);
+                Some((SecurityService::from_master_password(passphrase, &salt)?, salt))
+            }
+        };
+        let mut backup = SettingsBackup {
+            version: BACKUP_VERSION,
+            created_at: self.now().to_rfc3339(),
+            settings: settings.cloned(),
+            accounts: Vec::new(),
+            tags: Vec::new(),
+            signatures: Vec::new(),
+            filter_rules: Vec::new(),
+            contacts: Vec::new(),
+            contact_groups: Vec::new(),
+            secrets_salt: key.as_ref().map(|(_, salt)| base64::engine::general_purpose::STANDARD.encode(salt)),
+        };
+        for mut account in self.load_accounts()? {
+            let account_secrets = AccountSecrets {
+                password: std::mem::take(&mut account.password),
+                oauth_access_token: std::mem::take(&mut account.oauth_access_token),
+                oauth_refresh_token: std::mem::take(&mut account.oauth_refresh_token),
+            };
+            let secrets = match &key {
+                Some((key, _)) => {
+                    let json = serde_json::to_vec(&account_secrets)
+                        .map_err(|e| Error::Security(format!("Failed to prepare account secrets: {}", e)))?;
+                    let encrypted = key.encrypt(&json)?;
+                    Some(String::from_utf8_lossy(&encrypted).into_owned())
+                }
+                None => None,
+            };
+            backup.tags.extend(self.get_tags_for_account(&account.id)?);
+            backup.signatures.extend(self.get_signatures_for_account(&account.id)?);
+            backup.filter_rules.extend(self.get_filter_rules_for_account(&account.id)?);
+            backup.contacts.extend(self.get_contacts_for_account(&account.id)?);
+            backup.contact_groups.extend(self.load_contact_groups(&account.id)?);
+            backup.accounts.push(BackupAccount { account, secrets });
+        }
+        Ok(backup)
+    }
+
+    /// Restore a backup. `passphrase` unlocks its account passwords;
+    /// without it accounts are restored with no password. A wrong
+    /// passphrase restores nothing. Settings are left to the caller.
+    pub fn import_settings(
+        &self,
+        backup: &SettingsBackup,
+        passphrase: Option<&str>,
+        on_conflict: ImportConflict,
+    ) -> Result<ImportSummary> {
+        let key = match passphrase {
+            Some(passphrase) => backup.secrets_key(passphrase)?,
+            None => None,
+        };
+        // Decrypt every account first, so a wrong passphrase changes nothing
+        let mut accounts = Vec::new();
+        for entry in &backup.accounts {
+            let mut account = entry.account.clone();
+            if let (Some(key), Some(secrets)) = (&key, &entry.secrets) {
+                let json = key
+                    .decrypt(secrets.as_bytes())
+                    .map_err(|_| Error::Security("The passphrase does not match this backup".to_string()))?;
+                let secrets: AccountSecrets = serde_json::from_slice(&json)
+                    .map_err(|e| Error::Security(format!("Account secrets in the backup are damaged: {}", e)))?;
+                account.password = secrets.password;
+                account.oauth_access_token = secrets.oauth_access_token;
+                account.oauth_refresh_token = secrets.oauth_refresh_token;
+            }
+            accounts.push(account);
+        }
+
+        self.in_transaction(|c| {
+            let mut summary = ImportSummary::default();
+            let existing_accounts = c.load_accounts()?;
+            for account in &accounts {
+                let existed = existing_accounts.iter().any(|a| a.id == account.id);
+                if summary.count(existed, on_conflict) {
+                    c.save_account(account)?;
+                    if account.password.is_empty() && !account.use_oauth {
+                        summary.without_password.push(account.display_name());
+                    }
+                }
+            }
+            for tag in &backup.tags {
+                let existing = c.get_tags_for_account(&tag.account_id)?.into_iter().find(|t| t.name == tag.name);
+                if summary.count(existing.is_some(), on_conflict) {
+                    match existing {
+                        Some(existing) => c.update_tag(&Tag { id: existing.id, ..tag.clone() })?,
+                        None => c.create_tag(tag)?,
+                    }
+                }
+            }
+            for signature in &backup.signatures {
+                let existing =
+                    c.get_signatures_for_account(&signature.account_id)?.into_iter().find(|s| s.name == signature.name);
+                if summary.count(existing.is_some(), on_conflict) {
+                    match existing {
+                        Some(existing) => c.update_signature(&Signature { id: existing.id, ..signature.clone() })?,
+                        None => c.create_signature(signature)?,
+                    }
+                }
+            }
+            for rule in &backup.filter_rules {
+                let existing = c.get_filter_rules_for_account(&rule.account_id)?.into_iter().find(|r| r.name == rule.name);
+                if summary.count(existing.is_some(), on_conflict) {
+                    match existing {
+                        Some(existing) => c.update_filter_rule(&MessageFilterRule { id: existing.id, ..rule.clone() })?,
+                        None => c.create_filter_rule(rule)?,
+                    }
+                }
+            }
+            for contact in &backup.contacts {
+                let existed = c
+                    .get_contacts_for_account(&contact.account_id)?
+                    .iter()
+                    .any(|e| e.email.eq_ignore_ascii_case(&contact.email));
+                if summary.count(existed, on_conflict) {
+                    c.save_contact(contact)?;
+                }
+            }
+            for group in &backup.contact_groups {
+                let existing = c.load_contact_groups(&group.account_id)?.into_iter().find(|g| g.name == group.name);
+                if !summary.count(existing.is_some(), on_conflict) {
+                    continue;
+                }
+                let id = match existing {
+                    Some(existing) => {
+                        c.update_contact_group(&ContactGroup { id: existing.id.clone(), ..group.clone() })?;
+                        existing.id
+                    }
+                    None => {
+                        c.create_contact_group(group)?;
+                        group.id.clone()
+                    }
+                };
+                // Members by address, as a contact kept from before has its own id
+                let contacts = c.get_contacts_for_account(&group.account_id)?;
+                for member in &group.member_ids {
+                    let email = backup.contacts.iter().find(|b| &b.id == member).map(|b| b.email.as_str());
+                    if let Some(contact) = contacts.iter().find(|c| Some(c.email.as_str()) == email || &c.id == member) {
+                        c.add_contact_to_group(&id, &contact.id)?;
+                    }
+                }
+            }
+            Ok(summary)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn tag(id: &str, account_id: &str, name: &str, color: &str) -> Tag {
+        Tag {
+            id: id.to_string(),
+            account_id: account_id.to_string(),
+            name: name.to_string(),
+            color: color.to_string(),
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            shortcut: None,
+        }
+    }
+
+    #[test]
+    fn test_settings_backup_round_trip() {
+        let temp = |name: &str| env::temp_dir().join(format!("wixen_mail_test_backup_{}_{}", name, std::process::id()));
+        let source = MessageCache::new(temp("source"), None).unwrap();
+        let mut account = Account::new("Work".to_string(), "me@example.com".to_string());
+        account.password = "hunter2".to_string();
+        source.save_account(&account).unwrap();
+        source.create_tag(&tag("t1", &account.id, "Urgent", "#ff0000")).unwrap();
+
+        let shared = source.export_settings(None, BackupSecrets::Exclude).unwrap();
+        let json = shared.to_json().unwrap();
+        assert!(!json.contains("hunter2"));
+        assert!(!shared.has_secrets());
+
+        let private = source.export_settings(Some(&AppConfig::default()), BackupSecrets::Encrypt("passphrase")).unwrap();
+        let private = SettingsBackup::from_json(&private.to_json().unwrap()).unwrap();
+        assert!(private.has_secrets());
+        assert!(private.settings.is_some());
+
+        // Restoring on a new computer, where the tag already exists
+        let target = MessageCache::new(temp("target"), None).unwrap();
+        target.save_account(&account).unwrap();
+        target.create_tag(&tag("t9", &account.id, "Urgent", "#0000ff")).unwrap();
+        assert!(target.import_settings(&private, Some("wrong"), ImportConflict::Overwrite).is_err());
+        assert_eq!(target.get_tags_for_account(&account.id).unwrap()[0].color, "#0000ff");
+
+        let skipped = target.import_settings(&private, Some("passphrase"), ImportConflict::Skip).unwrap();
+        assert_eq!((skipped.added, skipped.skipped), (0, 2));
+        let replaced = target.import_settings(&private, Some("passphrase"), ImportConflict::Overwrite).unwrap();
+        assert_eq!((replaced.replaced, replaced.without_password.len()), (2, 0));
+        assert_eq!(target.load_accounts().unwrap()[0].password, "hunter2");
+        let tags = target.get_tags_for_account(&account.id).unwrap();
+        assert_eq!((tags.len(), tags[0].id.as_str(), tags[0].color.as_str()), (1, "t9", "#ff0000"));
+
+        let fresh = MessageCache::new(temp("fresh"), None).unwrap();
+        let summary = fresh.import_settings(&SettingsBackup::from_json(&json).unwrap(), None, ImportConflict::Skip).unwrap();
+        assert_eq!(summary.added, 2);
+        assert_eq!(summary.without_password, vec![account.display_name()]);
+
+        assert!(SettingsBackup::from_json(&json.replace("\"version\": 1", "\"version\": 99")).is_err());
+    }
+}
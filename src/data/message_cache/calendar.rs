@@ -0,0 +1,135 @@
+//! Calendar event persistence operations
+
+use super::{CachedCalendarEvent, MessageCache};
+use crate::common::{Error, Result};
+use rusqlite::{params, OptionalExtension, Row};
+
+const EVENT_COLUMNS: &str = "id, account_id, uid, message_id, method, sequence, summary, location,
+     starts_at, ends_at, all_day, organizer, response, updated_at";
+
+fn event_from_row(row: &Row<'_>) -> rusqlite::Result<CachedCalendarEvent> {
+    Ok(CachedCalendarEvent {
+        id: row.get(0)?, account_id: row.get(1)?, uid: row.get(2)?, message_id: row.get(3)?,
+        method: row.get(4)?, sequence: row.get(5)?, summary: row.get(6)?, location: row.get(7)?,
+        starts_at: row.get(8)?, ends_at: row.get(9)?, all_day: row.get(10)?, organizer: row.get(11)?,
+        response: row.get(12)?, updated_at: row.get(13)?,
+    })
+}
+
+impl MessageCache {
+    /// Save an event, replacing the stored one with the same account and
+    /// `UID` unless that has a higher sequence (a message re-opened after
+    /// the event was updated). The stored response is kept. Returns the
+    /// event's id.
+    pub fn save_calendar_event(&self, event: &CachedCalendarEvent) -> Result<i64> {
+        self.conn
+            .execute(
+                "INSERT INTO calendar_events (account_id, uid, message_id, method, sequence, summary, location,
+                     starts_at, ends_at, all_day, organizer, response, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+                 ON CONFLICT(account_id, uid) DO UPDATE SET
+                     message_id = excluded.message_id, method = excluded.method, sequence = excluded.sequence,
+                     summary = excluded.summary, location = excluded.location, starts_at = excluded.starts_at,
+                     ends_at = excluded.ends_at, all_day = excluded.all_day, organizer = excluded.organizer,
+                     updated_at = excluded.updated_at
+                 WHERE excluded.sequence >= calendar_events.sequence",
+                params![
+                    &event.account_id, &event.uid, &event.message_id, &event.method, &event.sequence,
+                    &event.summary, &event.location, &event.starts_at, &event.ends_at, &event.all_day,
+                    &event.organizer, &event.response, &event.updated_at,
+                ],
+            )
+            .map_err(|e| Error::Database(format!("Failed to save calendar event: {}", e)))?;
+        self.conn
+            .query_row(
+                "SELECT id FROM calendar_events WHERE account_id = ?1 AND uid = ?2",
+                params![&event.account_id, &event.uid],
+                |row| row.get(0),
+            )
+            .map_err(|e| Error::Database(format!("Failed to save calendar event: {}", e)))
+    }
+
+    /// Events received by an account, earliest first
+    pub fn get_calendar_events(&self, account_id: &str) -> Result<Vec<CachedCalendarEvent>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM calendar_events WHERE account_id = ?1 ORDER BY starts_at",
+            EVENT_COLUMNS
+        )).map_err(|e| Error::Database(format!("Failed to prepare statement: {}", e)))?;
+
+        let events = stmt
+            .query_map(params![account_id], event_from_row)
+            .map_err(|e| Error::Database(format!("Failed to query calendar events: {}", e)))?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::Database(format!("Failed to collect calendar events: {}", e)))?;
+        Ok(events)
+    }
+
+    /// Get an account's event by its `UID`
+    pub fn get_calendar_event(&self, account_id: &str, uid: &str) -> Result<Option<CachedCalendarEvent>> {
+        self.conn
+            .query_row(
+                &format!("SELECT {} FROM calendar_events WHERE account_id = ?1 AND uid = ?2", EVENT_COLUMNS),
+                params![account_id, uid],
+                event_from_row,
+            )
+            .optional()
+            .map_err(|e| Error::Database(format!("Failed to get calendar event: {}", e)))
+    }
+
+    /// Record the account's answer to an event (`ACCEPTED`, `TENTATIVE`, `DECLINED`)
+    pub fn set_calendar_response(&self, account_id: &str, uid: &str, response: &str) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE calendar_events SET response = ?1 WHERE account_id = ?2 AND uid = ?3",
+                params![response, account_id, uid],
+            )
+            .map_err(|e| Error::Database(format!("Failed to update calendar response: {}", e)))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn test_calendar_event_operations() {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_calendar_{}", nanos));
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+
+        let event = CachedCalendarEvent {
+            id: 0, account_id: "test@example.com".to_string(), uid: "lunch-1@example.org".to_string(),
+            message_id: Some(7), method: "REQUEST".to_string(), sequence: 1, summary: "Lunch".to_string(),
+            location: Some("Cafe".to_string()), starts_at: "2025-03-14T12:00:00+00:00".to_string(),
+            ends_at: Some("2025-03-14T13:00:00+00:00".to_string()), all_day: false,
+            organizer: Some("bob@example.org".to_string()), response: None,
+            updated_at: chrono::Utc::now().to_rfc3339(),
+        };
+        let id = cache.save_calendar_event(&event).unwrap();
+        cache.save_calendar_event(&CachedCalendarEvent {
+            uid: "offsite@example.org".to_string(), starts_at: "2025-03-10".to_string(), all_day: true,
+            ..event.clone()
+        }).unwrap();
+        cache.set_calendar_response("test@example.com", "lunch-1@example.org", "ACCEPTED").unwrap();
+
+        // A newer revision updates the event but keeps the answer
+        let moved = CachedCalendarEvent {
+            sequence: 2, starts_at: "2025-03-14T13:00:00+00:00".to_string(), ..event.clone()
+        };
+        assert_eq!(cache.save_calendar_event(&moved).unwrap(), id);
+        // An older one is ignored
+        cache.save_calendar_event(&event).unwrap();
+        let loaded = cache.get_calendar_event("test@example.com", "lunch-1@example.org").unwrap().unwrap();
+        assert_eq!((loaded.sequence, loaded.starts_at.as_str()), (2, "2025-03-14T13:00:00+00:00"));
+        assert_eq!(loaded.response.as_deref(), Some("ACCEPTED"));
+
+        let uids: Vec<String> = cache
+            .get_calendar_events("test@example.com").unwrap()
+            .into_iter().map(|e| e.uid).collect();
+        assert_eq!(uids, ["offsite@example.org", "lunch-1@example.org"]);
+        assert!(cache.get_calendar_events("other@example.com").unwrap().is_empty());
+    }
+}
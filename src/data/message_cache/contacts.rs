@@ -1,13 +1,14 @@
 //! Contact, contact group, and vCard persistence operations
 
-use super::{ContactEntry, ContactGroup, MessageCache};
+use super::{ContactEntry, ContactGroup, FrequentRecipient, MessageCache};
 use crate::common::{Error, Result};
-use rusqlite::params;
+use rusqlite::{params, OptionalExtension};
+use std::collections::HashMap;
 
 impl MessageCache {
     /// Save or update a contact
     pub fn save_contact(&self, contact: &ContactEntry) -> Result<()> {
-        let now = chrono::Utc::now().to_rfc3339();
+        let now = self.now().to_rfc3339();
         self.conn.execute(
             "INSERT INTO contacts
              (id, account_id, name, email, provider_contact_id, phone, company, job_title, website, address, birthday,
@@ -51,7 +52,7 @@ impl MessageCache {
                 &contact.emails_json, &contact.phones_json, &contact.addresses_json,
                 &contact.custom_fields_json,
             ],
-        ).map_err(|e| Error::Other(format!("Failed to save contact: {}", e)))?;
+        ).map_err(|e| Error::Database(format!("Failed to save contact: {}", e)))?;
         Ok(())
     }
 
@@ -64,7 +65,7 @@ impl MessageCache {
              FROM contacts
              WHERE account_id = ?1
              ORDER BY favorite DESC, name ASC"
-        ).map_err(|e| Error::Other(format!("Failed to prepare statement: {}", e)))?;
+        ).map_err(|e| Error::Database(format!("Failed to prepare statement: {}", e)))?;
 
         let contacts = stmt
             .query_map(params![account_id], |row| {
@@ -83,9 +84,9 @@ impl MessageCache {
                     custom_fields_json: row.get(25)?,
                 })
             })
-            .map_err(|e| Error::Other(format!("Failed to query contacts: {}", e)))?
+            .map_err(|e| Error::Database(format!("Failed to query contacts: {}", e)))?
             .collect::<std::result::Result<Vec<_>, _>>()
-            .map_err(|e| Error::Other(format!("Failed to collect contacts: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to collect contacts: {}", e)))?;
         Ok(contacts)
     }
 
@@ -117,7 +118,7 @@ impl MessageCache {
                )
              ORDER BY favorite DESC, name ASC
              LIMIT ?3"
-        ).map_err(|e| Error::Other(format!("Failed to prepare search statement: {}", e)))?;
+        ).map_err(|e| Error::Database(format!("Failed to prepare search statement: {}", e)))?;
 
         let contacts = stmt
             .query_map(params![account_id, pattern, limit as i64], |row| {
@@ -136,12 +137,80 @@ impl MessageCache {
                     custom_fields_json: row.get(25)?,
                 })
             })
-            .map_err(|e| Error::Other(format!("Failed to search contacts: {}", e)))?
+            .map_err(|e| Error::Database(format!("Failed to search contacts: {}", e)))?
             .collect::<std::result::Result<Vec<_>, _>>()
-            .map_err(|e| Error::Other(format!("Failed to collect contacts: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to collect contacts: {}", e)))?;
         Ok(contacts)
     }
 
+    /// Addresses from the account's mail matching `query`, most frequent
+    /// first, counting each appearance as sender, recipient or Cc. The
+    /// account's own address is left out.
+    pub fn rank_frequent_recipients(
+        &self,
+        account_id: &str,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<FrequentRecipient>> {
+        let query = query.trim().to_lowercase();
+        let escaped = query.replace('!', "!!").replace('%', "!%").replace('_', "!_");
+        let pattern = format!("%{}%", escaped);
+        let own: Option<String> = self.conn
+            .query_row("SELECT email FROM accounts WHERE id = ?1", params![account_id], |row| row.get(0))
+            .optional()
+            .map_err(|e| Error::Database(format!("Failed to look up account address: {}", e)))?;
+        let own = own.map(|e| e.to_lowercase());
+
+        let mut stmt = self.conn.prepare(
+            "SELECT m.from_addr, m.to_addr, m.cc
+             FROM messages m
+             INNER JOIN folders f ON m.folder_id = f.id
+             WHERE f.account_id = ?1 AND m.deleted = 0
+               AND (LOWER(m.from_addr) LIKE ?2 ESCAPE '!' OR LOWER(m.to_addr) LIKE ?2 ESCAPE '!'
+                    OR LOWER(COALESCE(m.cc, '')) LIKE ?2 ESCAPE '!')",
+        ).map_err(|e| Error::Database(format!("Failed to prepare recipient ranking query: {}", e)))?;
+
+        let rows = stmt
+            .query_map(params![account_id, pattern], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                ))
+            })
+            .map_err(|e| Error::Database(format!("Failed to query recipients: {}", e)))?;
+
+        let mut ranked: HashMap<String, FrequentRecipient> = HashMap::new();
+        for row in rows {
+            let (from_addr, to_addr, cc) =
+                row.map_err(|e| Error::Database(format!("Failed to parse recipient row: {}", e)))?;
+            let lines = [Some(from_addr), Some(to_addr), cc];
+            for token in lines.iter().flatten().flat_map(|line| line.split(',')) {
+                let Some((name, email)) = Self::parse_name_email(token) else { continue };
+                let key = email.to_lowercase();
+                if own.as_deref() == Some(key.as_str())
+                    || !(key.contains(&query) || name.to_lowercase().contains(&query))
+                {
+                    continue;
+                }
+                let entry = ranked.entry(key).or_insert_with(|| FrequentRecipient {
+                    name: String::new(),
+                    email,
+                    count: 0,
+                });
+                entry.count += 1;
+                if entry.name.is_empty() {
+                    entry.name = name;
+                }
+            }
+        }
+
+        let mut ranked: Vec<FrequentRecipient> = ranked.into_values().collect();
+        ranked.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.email.cmp(&b.email)));
+        ranked.truncate(limit);
+        Ok(ranked)
+    }
+
     /// Auto-import contacts from cached messages (senders/recipients).
     pub fn auto_import_contacts_from_messages(
         &self,
@@ -154,7 +223,7 @@ impl MessageCache {
              FROM messages m
              INNER JOIN folders f ON m.folder_id = f.id
              WHERE f.account_id = ?1 AND m.deleted = 0",
-        ).map_err(|e| Error::Other(format!("Failed to prepare auto-import query: {}", e)))?;
+        ).map_err(|e| Error::Database(format!("Failed to prepare auto-import query: {}", e)))?;
 
         let rows = stmt
             .query_map(params![account_id], |row| {
@@ -164,11 +233,11 @@ impl MessageCache {
                     row.get::<_, Option<String>>(2)?,
                 ))
             })
-            .map_err(|e| Error::Other(format!("Failed to query import rows: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to query import rows: {}", e)))?;
 
         for row in rows {
             let (from_addr, to_addr, cc) =
-                row.map_err(|e| Error::Other(format!("Failed to parse import row: {}", e)))?;
+                row.map_err(|e| Error::Database(format!("Failed to parse import row: {}", e)))?;
             let mut candidates = vec![from_addr, to_addr];
             if let Some(cc_line) = cc {
                 candidates.push(cc_line);
@@ -190,11 +259,11 @@ impl MessageCache {
                             job_title: None, website: None, address: None, birthday: None,
                             avatar_url: None, avatar_data_base64: None,
                             source_provider: source_provider.map(|p| p.to_string()),
-                            last_synced_at: Some(chrono::Utc::now().to_rfc3339()),
+                            last_synced_at: Some(self.now().to_rfc3339()),
                             vcard_raw: None,
                             notes: Some("Imported automatically from message history".to_string()),
                             favorite: false,
-                            created_at: chrono::Utc::now().to_rfc3339(),
+                            created_at: self.now().to_rfc3339(),
                             nickname: None, department: None, relationship: None,
                             emails_json: None, phones_json: None, addresses_json: None,
                             custom_fields_json: None,
@@ -330,7 +399,7 @@ impl MessageCache {
     pub fn delete_contact(&self, contact_id: &str) -> Result<()> {
         self.conn
             .execute("DELETE FROM contacts WHERE id = ?1", params![contact_id])
-            .map_err(|e| Error::Other(format!("Failed to delete contact: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to delete contact: {}", e)))?;
         Ok(())
     }
 
@@ -342,7 +411,7 @@ impl MessageCache {
             "INSERT INTO contact_groups (id, account_id, name, description, created_at)
              VALUES (?1, ?2, ?3, ?4, ?5)",
             params![&group.id, &group.account_id, &group.name, &group.description, &group.created_at],
-        ).map_err(|e| Error::Other(format!("Failed to create contact group: {}", e)))?;
+        ).map_err(|e| Error::Database(format!("Failed to create contact group: {}", e)))?;
         Ok(())
     }
 
@@ -350,7 +419,7 @@ impl MessageCache {
     pub fn load_contact_groups(&self, account_id: &str) -> Result<Vec<ContactGroup>> {
         let mut stmt = self.conn.prepare(
             "SELECT id, account_id, name, description, created_at FROM contact_groups WHERE account_id = ?1 ORDER BY name"
-        ).map_err(|e| Error::Other(format!("Failed to prepare contact groups query: {}", e)))?;
+        ).map_err(|e| Error::Database(format!("Failed to prepare contact groups query: {}", e)))?;
 
         let groups = stmt.query_map(params![account_id], |row| {
             Ok(ContactGroup {
@@ -358,9 +427,9 @@ impl MessageCache {
                 description: row.get(3)?, created_at: row.get(4)?,
                 member_ids: Vec::new(),
             })
-        }).map_err(|e| Error::Other(format!("Failed to query contact groups: {}", e)))?
+        }).map_err(|e| Error::Database(format!("Failed to query contact groups: {}", e)))?
           .collect::<std::result::Result<Vec<_>, _>>()
-          .map_err(|e| Error::Other(format!("Failed to collect contact groups: {}", e)))?;
+          .map_err(|e| Error::Database(format!("Failed to collect contact groups: {}", e)))?;
 
         let mut result = groups;
         for group in &mut result {
@@ -374,26 +443,26 @@ impl MessageCache {
         self.conn.execute(
             "UPDATE contact_groups SET name = ?2, description = ?3 WHERE id = ?1",
             params![&group.id, &group.name, &group.description],
-        ).map_err(|e| Error::Other(format!("Failed to update contact group: {}", e)))?;
+        ).map_err(|e| Error::Database(format!("Failed to update contact group: {}", e)))?;
         Ok(())
     }
 
     /// Delete a contact group and its memberships
     pub fn delete_contact_group(&self, group_id: &str) -> Result<()> {
         self.conn.execute("DELETE FROM contact_group_members WHERE group_id = ?1", params![group_id])
-            .map_err(|e| Error::Other(format!("Failed to delete group members: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to delete group members: {}", e)))?;
         self.conn.execute("DELETE FROM contact_groups WHERE id = ?1", params![group_id])
-            .map_err(|e| Error::Other(format!("Failed to delete contact group: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to delete contact group: {}", e)))?;
         Ok(())
     }
 
     /// Add a contact to a group
     pub fn add_contact_to_group(&self, group_id: &str, contact_id: &str) -> Result<()> {
-        let now = chrono::Utc::now().to_rfc3339();
+        let now = self.now().to_rfc3339();
         self.conn.execute(
             "INSERT OR IGNORE INTO contact_group_members (group_id, contact_id, added_at) VALUES (?1, ?2, ?3)",
             params![group_id, contact_id, now],
-        ).map_err(|e| Error::Other(format!("Failed to add member to group: {}", e)))?;
+        ).map_err(|e| Error::Database(format!("Failed to add member to group: {}", e)))?;
         Ok(())
     }
 
@@ -402,19 +471,19 @@ impl MessageCache {
         self.conn.execute(
             "DELETE FROM contact_group_members WHERE group_id = ?1 AND contact_id = ?2",
             params![group_id, contact_id],
-        ).map_err(|e| Error::Other(format!("Failed to remove member from group: {}", e)))?;
+        ).map_err(|e| Error::Database(format!("Failed to remove member from group: {}", e)))?;
         Ok(())
     }
 
     fn load_group_member_ids(&self, group_id: &str) -> Result<Vec<String>> {
         let mut stmt = self.conn.prepare(
             "SELECT contact_id FROM contact_group_members WHERE group_id = ?1"
-        ).map_err(|e| Error::Other(format!("Failed to prepare group members query: {}", e)))?;
+        ).map_err(|e| Error::Database(format!("Failed to prepare group members query: {}", e)))?;
 
         let ids = stmt.query_map(params![group_id], |row| row.get(0))
-            .map_err(|e| Error::Other(format!("Failed to query group members: {}", e)))?
+            .map_err(|e| Error::Database(format!("Failed to query group members: {}", e)))?
             .collect::<std::result::Result<Vec<String>, _>>()
-            .map_err(|e| Error::Other(format!("Failed to collect group members: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to collect group members: {}", e)))?;
         Ok(ids)
     }
 
@@ -425,12 +494,12 @@ impl MessageCache {
              INNER JOIN contact_group_members m ON c.id = m.contact_id
              WHERE m.group_id = ?1
              ORDER BY c.name"
-        ).map_err(|e| Error::Other(format!("Failed to resolve group emails: {}", e)))?;
+        ).map_err(|e| Error::Database(format!("Failed to resolve group emails: {}", e)))?;
 
         let emails = stmt.query_map(params![group_id], |row| row.get(0))
-            .map_err(|e| Error::Other(format!("Failed to query group emails: {}", e)))?
+            .map_err(|e| Error::Database(format!("Failed to query group emails: {}", e)))?
             .collect::<std::result::Result<Vec<String>, _>>()
-            .map_err(|e| Error::Other(format!("Failed to collect group emails: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to collect group emails: {}", e)))?;
         Ok(emails)
     }
 
@@ -673,6 +742,7 @@ impl MessageCache {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::common::types::MessagePriority;
     use crate::data::message_cache::{CachedFolder, CachedMessage};
     use std::env;
     use std::time::{SystemTime, UNIX_EPOCH};
@@ -770,7 +840,7 @@ END:VCARD";
             cc: Some("Katherine Johnson <katherine@example.com>".to_string()),
             date: chrono::Utc::now().to_rfc3339(),
             body_plain: Some("Hello".to_string()), body_html: None,
-            read: false, starred: false, deleted: false,
+            read: false, starred: false, deleted: false, priority: MessagePriority::Normal,
         };
         cache.save_message(&message).unwrap();
 
@@ -782,4 +852,38 @@ END:VCARD";
         assert!(contacts.iter().any(|c| c.email == "ada@example.com"));
         assert!(contacts.iter().any(|c| c.email == "katherine@example.com"));
     }
+
+    #[test]
+    fn test_rank_frequent_recipients() {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_frequent_{}", nanos));
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+        let folder_id = cache.ensure_folder("acc", "INBOX").unwrap().id;
+        let lines = [
+            ("Grace Hopper <grace@example.com>", "me@example.com"),
+            ("grace@example.com", "me@example.com, alan@example.com"),
+            ("me@example.com", "Alan Turing <alan@example.com>, Grace <GRACE@example.com>"),
+        ];
+        for (uid, (from, to)) in lines.iter().enumerate() {
+            cache.save_message(&CachedMessage {
+                id: 0, uid: uid as u32 + 1, folder_id,
+                message_id: format!("rank-{}", uid), subject: "Hi".to_string(),
+                from_addr: from.to_string(), to_addr: to.to_string(), cc: None,
+                date: chrono::Utc::now().to_rfc3339(),
+                body_plain: None, body_html: None, read: true, starred: false, deleted: false, priority: MessagePriority::Normal,
+            }).unwrap();
+        }
+        let mut account = crate::data::account::Account::new("Me".to_string(), "me@example.com".to_string());
+        account.id = "acc".to_string();
+        cache.save_account(&account).unwrap();
+
+        let ranked = cache.rank_frequent_recipients("acc", "", 10).unwrap();
+        let summary: Vec<(&str, &str, u32)> = ranked.iter().map(|r| (r.name.as_str(), r.email.as_str(), r.count)).collect();
+        assert_eq!(summary, vec![("Grace Hopper", "grace@example.com", 3), ("Alan Turing", "alan@example.com", 2)]);
+
+        let by_name = cache.rank_frequent_recipients("acc", "turing", 10).unwrap();
+        assert_eq!(by_name.len(), 1);
+        assert_eq!(by_name[0].email, "alan@example.com");
+        assert_eq!(cache.rank_frequent_recipients("acc", "grace", 0).unwrap().len(), 0);
+    }
 }
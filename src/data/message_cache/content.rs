@@ -0,0 +1,350 @@
+//! Encrypting cached message text
+//!
+//! With a master password, message bodies, kept sources and optionally
+//! subjects are stored encrypted like account passwords. Values are sealed
+//! with `encrypt_value` and recognised on read by their `WXM2:` prefix, so
+//! rows saved before the option was turned on still read, and the cache can
+//! hold both forms while [`MessageCache::apply_content_encryption`] converts it.
+//!
+//! The cache has no full-text index: search and filter rules match messages
+//! in memory after they are read, so they keep working. Only SQL matching on
+//! encrypted text is lost; finding a conversation decrypts every encrypted
+//! subject of the account instead of narrowing them with `LIKE`.
+
+use super::{CachedMessage, MessageCache};
+use crate::common::{Error, Result};
+use rusqlite::params;
+
+/// Prefix of a value sealed by `encrypt_value`
+pub(super) const SEALED_PREFIX: &str = "WXM2:";
+
+/// Rows converted per batch, so a large cache is never held in memory at once
+const BATCH_SIZE: i64 = 200;
+
+/// Which message text is encrypted when it is saved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContentEncryption {
+    /// Stored as received
+    #[default]
+    Off,
+    /// Plain and HTML bodies and the kept message source
+    Bodies,
+    /// Bodies and source, plus subjects
+    BodiesAndSubjects,
+}
+
+impl ContentEncryption {
+    /// The mode for the two settings; subjects are only encrypted with bodies
+    pub fn from_flags(bodies: bool, subjects: bool) -> Self {
+        match (bodies, subjects) {
+            (false, _) => Self::Off,
+            (true, false) => Self::Bodies,
+            (true, true) => Self::BodiesAndSubjects,
+        }
+    }
+
+    fn bodies(self) -> bool {
+        self != Self::Off
+    }
+
+    fn subjects(self) -> bool {
+        self == Self::BodiesAndSubjects
+    }
+}
+
+fn locked() -> Error {
+    Error::Security("Cached messages are encrypted; enter the master password to read them".to_string())
+}
+
+/// A message row's encryptable columns
+struct StoredText {
+    id: i64,
+    subject: String,
+    body_plain: Option<String>,
+    body_html: Option<String>,
+    raw_source: Option<String>,
+}
+
+impl MessageCache {
+    /// Encrypt message text saved from now on. Needs an encryption key:
+    /// saving fails rather than storing plain text while none is set.
+    /// Existing rows are converted by [`MessageCache::apply_content_encryption`].
+    pub fn set_content_encryption(&mut self, mode: ContentEncryption) {
+        self.content_encryption = mode;
+    }
+
+    /// Which message text is encrypted when saved
+    pub fn content_encryption(&self) -> ContentEncryption {
+        self.content_encryption
+    }
+
+    fn seal(&self, text: &str) -> Result<String> {
+        if self.security.is_none() {
+            return Err(locked());
+        }
+        self.encrypt_value(text)
+    }
+
+    /// Stored form of a body or message source
+    pub(super) fn seal_body(&self, text: Option<&str>) -> Result<Option<String>> {
+        match text {
+            Some(text) if self.content_encryption.bodies() => self.seal(text).map(Some),
+            other => Ok(other.map(str::to_string)),
+        }
+    }
+
+    /// Stored form of a subject
+    pub(super) fn seal_subject(&self, subject: &str) -> Result<String> {
+        if self.content_encryption.subjects() {
+            self.seal(subject)
+        } else {
+            Ok(subject.to_string())
+        }
+    }
+
+    /// Readable form of stored text; text saved unencrypted is returned as is
+    pub(super) fn open_text(&self, stored: String) -> Result<String> {
+        if !stored.starts_with(SEALED_PREFIX) {
+            return Ok(stored);
+        }
+        if self.security.is_none() {
+            return Err(locked());
+        }
+        self.decrypt_value(&stored)
+    }
+
+    /// `msg` as read from the database, with its subject and bodies decrypted
+    pub(super) fn open_message(&self, mut msg: CachedMessage) -> Result<CachedMessage> {
+        msg.subject = self.open_text(msg.subject)?;
+        msg.body_plain = msg.body_plain.map(|b| self.open_text(b)).transpose()?;
+        msg.body_html = msg.body_html.map(|b| self.open_text(b)).transpose()?;
+        Ok(msg)
+    }
+
+    /// [`MessageCache::open_message`] for every message in `msgs`
+    pub(super) fn open_messages(&self, msgs: Vec<CachedMessage>) -> Result<Vec<CachedMessage>> {
+        msgs.into_iter().map(|m| self.open_message(m)).collect()
+    }
+
+    /// Encrypt or decrypt cached message text to match
+    /// [`MessageCache::content_encryption`], in one transaction. Returns the
+    /// number of messages rewritten.
+    pub fn apply_content_encryption(&self) -> Result<usize> {
+        let mode = self.content_encryption;
+        self.in_transaction(|c| {
+            c.rewrite_text(false, mode, |stored, encrypt| match (stored.starts_with(SEALED_PREFIX), encrypt) {
+                (false, true) => c.seal(stored).map(Some),
+                (true, false) => c.open_text(stored.to_string()).map(Some),
+                _ => Ok(None),
+            })
+        })
+    }
+
+    /// Re-encrypt encrypted message text with `encrypt`, for
+    /// [`MessageCache::rekey`]. Returns the number of messages rewritten.
+    pub(super) fn reseal_content(&self, encrypt: &dyn Fn(&str) -> Result<String>) -> Result<usize> {
+        self.rewrite_text(true, ContentEncryption::Off, |stored, _| {
+            if stored.starts_with(SEALED_PREFIX) {
+                encrypt(&self.decrypt_value(stored)?).map(Some)
+            } else {
+                Ok(None)
+            }
+        })
+    }
+
+    /// Pass each stored subject, body and source to `convert` along with
+    /// whether `mode` encrypts it, and save the values it replaces. With
+    /// `sealed_only`, only rows holding encrypted text are read.
+    fn rewrite_text(
+        &self,
+        sealed_only: bool,
+        mode: ContentEncryption,
+        convert: impl Fn(&str, bool) -> Result<Option<String>>,
+    ) -> Result<usize> {
+        let sql = format!(
+            "SELECT id, subject, body_plain, body_html, raw_source FROM messages
+             WHERE id > ?1 {} ORDER BY id LIMIT ?2",
+            if sealed_only {
+                "AND (subject LIKE 'WXM2:%' OR body_plain LIKE 'WXM2:%'
+                      OR body_html LIKE 'WXM2:%' OR raw_source LIKE 'WXM2:%')"
+            } else {
+                ""
+            }
+        );
+        let convert_body = |body: &Option<String>| -> Result<Option<Option<String>>> {
+            match body {
+                Some(text) => Ok(convert(text, mode.bodies())?.map(Some)),
+                None => Ok(None),
+            }
+        };
+        let (mut after, mut rewritten) = (0, 0);
+        loop {
+            let rows: Vec<StoredText> = self
+                .conn
+                .prepare_cached(&sql)
+                .and_then(|mut stmt| {
+                    stmt.query_map(params![after, BATCH_SIZE], |row| {
+                        Ok(StoredText {
+                            id: row.get(0)?,
+                            subject: row.get(1)?,
+                            body_plain: row.get(2)?,
+                            body_html: row.get(3)?,
+                            raw_source: row.get(4)?,
+                        })
+                    })?
+                    .collect()
+                })
+                .map_err(|e| Error::Database(format!("Failed to read message text: {}", e)))?;
+            let Some(last) = rows.last() else { break };
+            after = last.id;
+            for row in rows {
+                let subject = convert(&row.subject, mode.subjects())?;
+                let plain = convert_body(&row.body_plain)?;
+                let html = convert_body(&row.body_html)?;
+                let raw = convert_body(&row.raw_source)?;
+                if subject.is_none() && plain.is_none() && html.is_none() && raw.is_none() {
+                    continue;
+                }
+                self.conn
+                    .execute(
+                        "UPDATE messages SET subject = ?1, body_plain = ?2, body_html = ?3, raw_source = ?4
+                         WHERE id = ?5",
+                        params![
+                            subject.unwrap_or(row.subject),
+                            plain.unwrap_or(row.body_plain),
+                            html.unwrap_or(row.body_html),
+                            raw.unwrap_or(row.raw_source),
+                            row.id
+                        ],
+                    )
+                    .map_err(|e| Error::Database(format!("Failed to rewrite message text: {}", e)))?;
+                rewritten += 1;
+            }
+        }
+        Ok(rewritten)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::types::MessagePriority;
+    use crate::data::message_cache::CachedFolder;
+    use crate::service::security::SecurityService;
+    use std::path::PathBuf;
+
+    fn test_cache(name: &str) -> (MessageCache, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("wixen_mail_test_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let key = SecurityService::from_master_password("pw", b"0123456789abcdef").unwrap();
+        (MessageCache::new(dir.clone(), Some(key)).unwrap(), dir)
+    }
+
+    fn save(cache: &MessageCache, uid: u32) -> i64 {
+        let folder_id = cache
+            .save_folder(&CachedFolder {
+                id: 0, account_id: "acc1".to_string(), name: "INBOX".to_string(), path: "INBOX".to_string(),
+                folder_type: "Inbox".to_string(), unread_count: 0, total_count: 0,
+            })
+            .unwrap();
+        cache
+            .save_message(&CachedMessage {
+                id: 0, uid, folder_id, message_id: format!("m{}@example.com", uid),
+                subject: "Payroll".to_string(), from_addr: "hr@example.com".to_string(),
+                to_addr: "me@example.com".to_string(), cc: None, date: "2025-01-01T00:00:00Z".to_string(),
+                body_plain: Some("Salary details".to_string()), body_html: Some("<p>Salary</p>".to_string()),
+                read: false, starred: false, deleted: false, priority: MessagePriority::Normal,
+            })
+            .unwrap()
+    }
+
+    fn stored(cache: &MessageCache, id: i64) -> (String, String, String) {
+        cache
+            .conn
+            .query_row(
+                "SELECT subject, body_plain, COALESCE(raw_source, '') FROM messages WHERE id = ?1",
+                params![id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn test_from_flags() {
+        assert_eq!(ContentEncryption::from_flags(false, true), ContentEncryption::Off);
+        assert_eq!(ContentEncryption::from_flags(true, false), ContentEncryption::Bodies);
+        assert_eq!(ContentEncryption::from_flags(true, true), ContentEncryption::BodiesAndSubjects);
+    }
+
+    #[test]
+    fn test_bodies_are_encrypted_at_rest() {
+        let (mut cache, dir) = test_cache("content_bodies");
+        cache.set_content_encryption(ContentEncryption::Bodies);
+        let id = save(&cache, 1);
+        cache.save_raw_source(id, "Subject: Payroll\r\n\r\nSalary details").unwrap();
+
+        let (subject, plain, raw) = stored(&cache, id);
+        assert_eq!(subject, "Payroll");
+        assert!(plain.starts_with(SEALED_PREFIX) && !plain.contains("Salary"));
+        assert!(raw.starts_with(SEALED_PREFIX));
+
+        let msg = cache.get_message(id).unwrap().unwrap();
+        assert_eq!(msg.body_plain.as_deref(), Some("Salary details"));
+        assert_eq!(msg.body_html.as_deref(), Some("<p>Salary</p>"));
+        assert_eq!(cache.get_message_body(msg.folder_id, 1).unwrap().as_deref(), Some("<p>Salary</p>"));
+        assert_eq!(cache.get_raw_source(id).unwrap().as_deref(), Some("Subject: Payroll\r\n\r\nSalary details"));
+
+        // Without the key the text stays unreadable
+        cache.set_security(None);
+        assert!(matches!(cache.get_message(id), Err(Error::Security(_))));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_encrypted_subjects_still_thread() {
+        let (mut cache, dir) = test_cache("content_subjects");
+        cache.set_content_encryption(ContentEncryption::BodiesAndSubjects);
+        let id = save(&cache, 1);
+        assert!(stored(&cache, id).0.starts_with(SEALED_PREFIX));
+        let thread = cache.get_thread_messages("acc1", "payroll").unwrap();
+        assert_eq!(thread.len(), 1);
+        assert_eq!(thread[0].subject, "Payroll");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_apply_content_encryption_converts_existing_rows() {
+        let (mut cache, dir) = test_cache("content_apply");
+        let id = save(&cache, 1);
+        cache.save_raw_source(id, "raw").unwrap();
+
+        cache.set_content_encryption(ContentEncryption::BodiesAndSubjects);
+        assert_eq!(cache.apply_content_encryption().unwrap(), 1);
+        let (subject, plain, raw) = stored(&cache, id);
+        assert!([&subject, &plain, &raw].iter().all(|v| v.starts_with(SEALED_PREFIX)));
+        // Nothing left to convert
+        assert_eq!(cache.apply_content_encryption().unwrap(), 0);
+
+        cache.set_content_encryption(ContentEncryption::Bodies);
+        assert_eq!(cache.apply_content_encryption().unwrap(), 1);
+        assert_eq!(stored(&cache, id).0, "Payroll");
+
+        cache.set_content_encryption(ContentEncryption::Off);
+        cache.apply_content_encryption().unwrap();
+        assert_eq!(stored(&cache, id), ("Payroll".to_string(), "Salary details".to_string(), "raw".to_string()));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rekey_reencrypts_message_text() {
+        let (mut cache, dir) = test_cache("content_rekey");
+        cache.set_content_encryption(ContentEncryption::Bodies);
+        let id = save(&cache, 1);
+        let before = stored(&cache, id).1;
+
+        cache.rekey(SecurityService::from_master_password("new", b"fedcba9876543210").unwrap()).unwrap();
+        assert_ne!(stored(&cache, id).1, before);
+        assert_eq!(cache.get_message(id).unwrap().unwrap().body_plain.as_deref(), Some("Salary details"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,18 +1,32 @@
 //! Draft persistence operations
+//!
+//! Saving over a draft keeps the version it replaces in `draft_revisions`,
+//! up to [`MAX_DRAFT_REVISIONS`] per draft, so an accidental overwrite can
+//! be undone. A draft being edited is marked open in the composer; a mark
+//! still set at startup means the app stopped without closing it.
 
-use super::{CachedDraft, MessageCache};
+use super::{parse_message_date, CachedDraft, DraftRevision, MessageCache};
 use crate::common::{Error, Result};
 use rusqlite::{params, OptionalExtension};
 
+/// Earlier versions kept per draft
+pub const MAX_DRAFT_REVISIONS: usize = 20;
+
 impl MessageCache {
     /// Save a draft to cache
     pub fn save_draft(&self, draft: &CachedDraft) -> Result<()> {
-        let now = chrono::Utc::now().to_rfc3339();
+        self.store_draft(draft, &self.now().to_rfc3339())
+    }
 
+    /// Insert or replace a draft row with an explicit `updated_at`,
+    /// keeping the version it replaces.
+    fn store_draft(&self, draft: &CachedDraft, updated_at: &str) -> Result<()> {
+        self.keep_revision(draft)?;
         self.conn.execute(
-            "INSERT OR REPLACE INTO drafts (id, account_id, to_addr, cc, bcc, subject, body, created_at, updated_at)
+            "INSERT OR REPLACE INTO drafts (id, account_id, to_addr, cc, bcc, subject, body, created_at, updated_at, server_uid, open_in_composer)
              VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7,
-                     COALESCE((SELECT created_at FROM drafts WHERE id = ?1), ?8), ?9)",
+                     COALESCE((SELECT created_at FROM drafts WHERE id = ?1), ?8), ?9, ?10,
+                     COALESCE((SELECT open_in_composer FROM drafts WHERE id = ?1), 0))",
             params![
                 draft.id,
                 draft.account_id,
@@ -22,24 +36,199 @@ impl MessageCache {
                 draft.subject,
                 draft.body,
                 draft.created_at.clone(),
-                now,
+                updated_at,
+                draft.server_uid,
             ],
-        ).map_err(|e| Error::Other(format!("Failed to save draft: {}", e)))?;
+        ).map_err(|e| Error::Database(format!("Failed to save draft: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Copy the saved version of `draft` to its revisions if `draft`
+    /// changes it, dropping the oldest beyond [`MAX_DRAFT_REVISIONS`].
+    fn keep_revision(&self, draft: &CachedDraft) -> Result<()> {
+        let Some(saved) = self.load_draft(&draft.id)? else {
+            return Ok(());
+        };
+        let content = |d: &CachedDraft| (d.to_addr.clone(), d.cc.clone(), d.bcc.clone(), d.subject.clone(), d.body.clone());
+        if content(&saved) == content(draft) {
+            return Ok(());
+        }
+        self.conn
+            .execute(
+                "INSERT INTO draft_revisions (draft_id, to_addr, cc, bcc, subject, body, saved_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![saved.id, saved.to_addr, saved.cc, saved.bcc, saved.subject, saved.body, saved.updated_at],
+            )
+            .map_err(|e| Error::Database(format!("Failed to save draft revision: {}", e)))?;
+        self.conn
+            .execute(
+                "DELETE FROM draft_revisions WHERE draft_id = ?1 AND id NOT IN
+                 (SELECT id FROM draft_revisions WHERE draft_id = ?1 ORDER BY id DESC LIMIT ?2)",
+                params![saved.id, MAX_DRAFT_REVISIONS as i64],
+            )
+            .map_err(|e| Error::Database(format!("Failed to trim draft revisions: {}", e)))?;
+        Ok(())
+    }
+
+    /// Earlier versions of a draft, newest first
+    pub fn list_draft_revisions(&self, draft_id: &str) -> Result<Vec<DraftRevision>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, draft_id, to_addr, cc, bcc, subject, body, saved_at
+             FROM draft_revisions
+             WHERE draft_id = ?1
+             ORDER BY id DESC",
+            )
+            .map_err(|e| Error::Database(format!("Failed to prepare statement: {}", e)))?;
+        let revisions = stmt
+            .query_map(params![draft_id], |row| {
+                Ok(DraftRevision {
+                    id: row.get(0)?,
+                    draft_id: row.get(1)?,
+                    to_addr: row.get(2)?,
+                    cc: row.get(3)?,
+                    bcc: row.get(4)?,
+                    subject: row.get(5)?,
+                    body: row.get(6)?,
+                    saved_at: row.get(7)?,
+                })
+            })
+            .map_err(|e| Error::Database(format!("Failed to query draft revisions: {}", e)))?;
+        revisions
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::Database(format!("Failed to read draft revision: {}", e)))
+    }
+
+    /// Put an earlier version back as the draft. The version it replaces
+    /// becomes a revision itself, so restoring can be undone.
+    pub fn restore_draft_revision(&self, revision_id: i64) -> Result<CachedDraft> {
+        let revision = self
+            .conn
+            .query_row(
+                "SELECT draft_id, to_addr, cc, bcc, subject, body FROM draft_revisions WHERE id = ?1",
+                params![revision_id],
+                |row| Ok((row.get::<_, String>(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?)),
+            )
+            .optional()
+            .map_err(|e| Error::Database(format!("Failed to load draft revision: {}", e)))?
+            .ok_or_else(|| Error::NotFound(format!("Draft revision {}", revision_id)))?;
+        let (draft_id, to_addr, cc, bcc, subject, body) = revision;
+        let draft = self
+            .load_draft(&draft_id)?
+            .ok_or_else(|| Error::NotFound(format!("Draft {}", draft_id)))?;
+        let restored = CachedDraft { to_addr, cc, bcc, subject, body, ..draft };
+        self.save_draft(&restored)?;
+        Ok(self.load_draft(&draft_id)?.unwrap_or(restored))
+    }
+
+    /// Mark a draft as being edited in the composer, or no longer
+    pub fn set_draft_open(&self, draft_id: &str, open: bool) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE drafts SET open_in_composer = ?1 WHERE id = ?2",
+                params![open, draft_id],
+            )
+            .map_err(|e| Error::Database(format!("Failed to update draft: {}", e)))?;
+        Ok(())
+    }
+
+    /// Drafts still marked open in the composer, which means the app
+    /// stopped without closing them. Call once at startup: the marks are
+    /// cleared, leaving them as ordinary drafts.
+    pub fn take_interrupted_drafts(&self) -> Result<Vec<CachedDraft>> {
+        let ids: Vec<String> = {
+            let mut stmt = self
+                .conn
+                .prepare("SELECT id FROM drafts WHERE open_in_composer = 1 ORDER BY updated_at DESC")
+                .map_err(|e| Error::Database(format!("Failed to prepare statement: {}", e)))?;
+            let rows = stmt
+                .query_map([], |row| row.get(0))
+                .map_err(|e| Error::Database(format!("Failed to query open drafts: {}", e)))?;
+            rows.collect::<std::result::Result<_, _>>()
+                .map_err(|e| Error::Database(format!("Failed to read open draft: {}", e)))?
+        };
+        let mut drafts = Vec::new();
+        for id in ids {
+            self.set_draft_open(&id, false)?;
+            drafts.extend(self.load_draft(&id)?);
+        }
+        Ok(drafts)
+    }
+
+    /// Record the server Drafts-folder UID of an uploaded draft
+    pub fn set_draft_server_uid(&self, draft_id: &str, server_uid: Option<u32>) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE drafts SET server_uid = ?1 WHERE id = ?2",
+                params![server_uid, draft_id],
+            )
+            .map_err(|e| Error::Database(format!("Failed to update draft server UID: {}", e)))?;
 
         Ok(())
     }
 
+    /// Reconcile local drafts with the copies in the server's Drafts folder.
+    ///
+    /// Drafts are matched by id (or server UID) and the newer `updated_at`
+    /// wins. Server drafts missing locally are added; local drafts whose
+    /// server copy has disappeared were sent or discarded on another device
+    /// and are removed. Returns the local drafts that still need uploading,
+    /// with `server_uid` set to the server copy they replace.
+    pub fn reconcile_drafts(&self, account_id: &str, server: &[CachedDraft]) -> Result<Vec<CachedDraft>> {
+        let newer = |a: &str, b: &str| match (parse_message_date(a), parse_message_date(b)) {
+            (Some(a), Some(b)) => a > b,
+            _ => a > b,
+        };
+        let mut upload = Vec::new();
+        let mut matched = std::collections::HashSet::new();
+        let local = self.load_drafts(account_id)?;
+
+        for remote in server {
+            let existing = local.iter().find(|l| l.id == remote.id).or_else(|| {
+                local.iter().find(|l| l.server_uid.is_some() && l.server_uid == remote.server_uid)
+            });
+            match existing {
+                None => self.store_draft(remote, &remote.updated_at)?,
+                Some(l) if newer(&remote.updated_at, &l.updated_at) => {
+                    let pulled = CachedDraft { id: l.id.clone(), ..remote.clone() };
+                    self.store_draft(&pulled, &remote.updated_at)?;
+                    matched.insert(l.id.clone());
+                }
+                Some(l) => {
+                    matched.insert(l.id.clone());
+                    if newer(&l.updated_at, &remote.updated_at) || l.server_uid != remote.server_uid {
+                        upload.push(CachedDraft { server_uid: remote.server_uid, ..l.clone() });
+                    }
+                }
+            }
+        }
+
+        for l in &local {
+            if matched.contains(&l.id) {
+                continue;
+            }
+            if l.server_uid.is_some() {
+                self.delete_draft(&l.id)?;
+            } else {
+                upload.push(l.clone());
+            }
+        }
+        Ok(upload)
+    }
+
     /// Load all drafts for an account
     pub fn load_drafts(&self, account_id: &str) -> Result<Vec<CachedDraft>> {
         let mut stmt = self
             .conn
             .prepare(
-                "SELECT id, account_id, to_addr, cc, bcc, subject, body, created_at, updated_at
+                "SELECT id, account_id, to_addr, cc, bcc, subject, body, created_at, updated_at, server_uid
              FROM drafts
              WHERE account_id = ?1
              ORDER BY updated_at DESC",
             )
-            .map_err(|e| Error::Other(format!("Failed to prepare statement: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to prepare statement: {}", e)))?;
 
         let drafts = stmt
             .query_map(params![account_id], |row| {
@@ -53,13 +242,14 @@ impl MessageCache {
                     body: row.get(6)?,
                     created_at: row.get(7)?,
                     updated_at: row.get(8)?,
+                    server_uid: row.get(9)?,
                 })
             })
-            .map_err(|e| Error::Other(format!("Failed to query drafts: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to query drafts: {}", e)))?;
 
         let mut result = Vec::new();
         for draft in drafts {
-            result.push(draft.map_err(|e| Error::Other(format!("Failed to read draft: {}", e)))?);
+            result.push(draft.map_err(|e| Error::Database(format!("Failed to read draft: {}", e)))?);
         }
 
         Ok(result)
@@ -70,7 +260,7 @@ impl MessageCache {
         let result = self
             .conn
             .query_row(
-                "SELECT id, account_id, to_addr, cc, bcc, subject, body, created_at, updated_at
+                "SELECT id, account_id, to_addr, cc, bcc, subject, body, created_at, updated_at, server_uid
              FROM drafts
              WHERE id = ?1",
                 params![draft_id],
@@ -85,32 +275,42 @@ impl MessageCache {
                         body: row.get(6)?,
                         created_at: row.get(7)?,
                         updated_at: row.get(8)?,
+                        server_uid: row.get(9)?,
                     })
                 },
             )
             .optional()
-            .map_err(|e| Error::Other(format!("Failed to load draft: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to load draft: {}", e)))?;
 
         Ok(result)
     }
 
-    /// Delete a draft
+    /// Delete a draft and its earlier versions
     pub fn delete_draft(&self, draft_id: &str) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM draft_revisions WHERE draft_id = ?1", params![draft_id])
+            .map_err(|e| Error::Database(format!("Failed to delete draft revisions: {}", e)))?;
         self.conn
             .execute("DELETE FROM drafts WHERE id = ?1", params![draft_id])
-            .map_err(|e| Error::Other(format!("Failed to delete draft: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to delete draft: {}", e)))?;
 
         Ok(())
     }
 
     /// Clear all drafts for an account
     pub fn clear_drafts(&self, account_id: &str) -> Result<()> {
+        self.conn
+            .execute(
+                "DELETE FROM draft_revisions WHERE draft_id IN (SELECT id FROM drafts WHERE account_id = ?1)",
+                params![account_id],
+            )
+            .map_err(|e| Error::Database(format!("Failed to clear draft revisions: {}", e)))?;
         self.conn
             .execute(
                 "DELETE FROM drafts WHERE account_id = ?1",
                 params![account_id],
             )
-            .map_err(|e| Error::Other(format!("Failed to clear drafts: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to clear drafts: {}", e)))?;
 
         Ok(())
     }
@@ -136,6 +336,7 @@ mod tests {
             body: "Draft body content".to_string(),
             created_at: chrono::Utc::now().to_rfc3339(),
             updated_at: chrono::Utc::now().to_rfc3339(),
+            server_uid: None,
         };
 
         cache.save_draft(&draft).unwrap();
@@ -167,6 +368,7 @@ mod tests {
             body: "Original body".to_string(),
             created_at: chrono::Utc::now().to_rfc3339(),
             updated_at: chrono::Utc::now().to_rfc3339(),
+            server_uid: None,
         };
 
         cache.save_draft(&draft).unwrap();
@@ -181,4 +383,114 @@ mod tests {
         assert_eq!(loaded_draft.subject, "Updated Subject");
         assert_eq!(loaded_draft.body, "Updated body");
     }
+
+    #[test]
+    fn test_reconcile_drafts() {
+        let temp_dir = env::temp_dir().join("wixen_mail_test_draft_reconcile");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+        let draft = |id: &str, subject: &str, updated_at: &str, server_uid: Option<u32>| CachedDraft {
+            id: id.to_string(),
+            account_id: "acc".to_string(),
+            to_addr: "bob@example.com".to_string(),
+            cc: None,
+            bcc: None,
+            subject: subject.to_string(),
+            body: String::new(),
+            created_at: "2024-01-01T00:00:00+00:00".to_string(),
+            updated_at: updated_at.to_string(),
+            server_uid,
+        };
+        let old = "2024-01-01T10:00:00+00:00";
+        let new = "2024-01-02T10:00:00+00:00";
+        cache.store_draft(&draft("edited-here", "Local edit", new, Some(1)), new).unwrap();
+        cache.store_draft(&draft("edited-there", "Stale", old, Some(2)), old).unwrap();
+        cache.store_draft(&draft("gone", "Sent elsewhere", old, Some(3)), old).unwrap();
+        cache.store_draft(&draft("local-only", "Never uploaded", old, None), old).unwrap();
+
+        let server = vec![
+            draft("edited-here", "Server copy", old, Some(1)),
+            draft("edited-there", "Remote edit", new, Some(2)),
+            draft("from-phone", "Phone draft", old, Some(4)),
+        ];
+        let upload = cache.reconcile_drafts("acc", &server).unwrap();
+
+        let mut ids: Vec<_> = upload.iter().map(|d| d.id.as_str()).collect();
+        ids.sort();
+        assert_eq!(ids, ["edited-here", "local-only"]);
+        assert_eq!(upload.iter().find(|d| d.id == "edited-here").unwrap().server_uid, Some(1));
+        assert_eq!(cache.load_draft("edited-there").unwrap().unwrap().subject, "Remote edit");
+        assert_eq!(cache.load_draft("edited-here").unwrap().unwrap().subject, "Local edit");
+        assert!(cache.load_draft("gone").unwrap().is_none());
+        assert_eq!(cache.load_draft("from-phone").unwrap().unwrap().server_uid, Some(4));
+    }
+
+    #[test]
+    fn test_draft_revisions() {
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_draft_revisions_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+        let mut draft = CachedDraft {
+            id: "draft-789".to_string(),
+            account_id: "acc".to_string(),
+            to_addr: "bob@example.com".to_string(),
+            cc: None,
+            bcc: None,
+            subject: "Plan".to_string(),
+            body: "Version 1".to_string(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            updated_at: chrono::Utc::now().to_rfc3339(),
+            server_uid: None,
+        };
+        cache.save_draft(&draft).unwrap();
+        cache.save_draft(&draft).unwrap();
+        assert!(cache.list_draft_revisions("draft-789").unwrap().is_empty());
+
+        for n in 2..=MAX_DRAFT_REVISIONS + 3 {
+            draft.body = format!("Version {}", n);
+            cache.save_draft(&draft).unwrap();
+        }
+        let revisions = cache.list_draft_revisions("draft-789").unwrap();
+        assert_eq!(revisions.len(), MAX_DRAFT_REVISIONS);
+        assert_eq!(revisions[0].body, format!("Version {}", MAX_DRAFT_REVISIONS + 2));
+
+        let restored = cache.restore_draft_revision(revisions[0].id).unwrap();
+        assert_eq!(restored.body, format!("Version {}", MAX_DRAFT_REVISIONS + 2));
+        let revisions = cache.list_draft_revisions("draft-789").unwrap();
+        assert_eq!(revisions[0].body, format!("Version {}", MAX_DRAFT_REVISIONS + 3));
+        assert!(matches!(cache.restore_draft_revision(-1), Err(Error::NotFound(_))));
+
+        cache.delete_draft("draft-789").unwrap();
+        assert!(cache.list_draft_revisions("draft-789").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_interrupted_drafts() {
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_interrupted_drafts_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+        let mut draft = CachedDraft {
+            id: "in-progress".to_string(),
+            account_id: "acc".to_string(),
+            to_addr: String::new(),
+            cc: None,
+            bcc: None,
+            subject: "Half written".to_string(),
+            body: "Dear".to_string(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            updated_at: chrono::Utc::now().to_rfc3339(),
+            server_uid: None,
+        };
+        cache.save_draft(&draft).unwrap();
+        cache.set_draft_open("in-progress", true).unwrap();
+        // Saving again while open keeps the mark
+        draft.body = "Dear Bob".to_string();
+        cache.save_draft(&draft).unwrap();
+
+        let interrupted = cache.take_interrupted_drafts().unwrap();
+        assert_eq!(interrupted.len(), 1);
+        assert_eq!(interrupted[0].body, "Dear Bob");
+        assert!(cache.take_interrupted_drafts().unwrap().is_empty());
+        assert!(cache.load_draft("in-progress").unwrap().is_some());
+    }
 }
@@ -7,7 +7,7 @@ use rusqlite::params;
 impl MessageCache {
     /// Create a new message filter rule
     pub fn create_filter_rule(&self, rule: &MessageFilterRule) -> Result<()> {
-        let now = chrono::Utc::now().to_rfc3339();
+        let now = self.now().to_rfc3339();
         self.conn.execute(
             "INSERT INTO message_filter_rules
              (id, account_id, name, field, match_type, pattern, case_sensitive, action_type, action_value, enabled, created_at, updated_at)
@@ -18,7 +18,7 @@ impl MessageCache {
                 &rule.action_type, &rule.action_value, &rule.enabled,
                 &rule.created_at, &now,
             ],
-        ).map_err(|e| Error::Other(format!("Failed to create filter rule: {}", e)))?;
+        ).map_err(|e| Error::Database(format!("Failed to create filter rule: {}", e)))?;
         Ok(())
     }
 
@@ -29,7 +29,7 @@ impl MessageCache {
              FROM message_filter_rules
              WHERE account_id = ?1
              ORDER BY name"
-        ).map_err(|e| Error::Other(format!("Failed to prepare statement: {}", e)))?;
+        ).map_err(|e| Error::Database(format!("Failed to prepare statement: {}", e)))?;
 
         let rules = stmt
             .query_map(params![account_id], |row| {
@@ -40,15 +40,15 @@ impl MessageCache {
                     action_value: row.get(8)?, enabled: row.get(9)?, created_at: row.get(10)?,
                 })
             })
-            .map_err(|e| Error::Other(format!("Failed to query filter rules: {}", e)))?
+            .map_err(|e| Error::Database(format!("Failed to query filter rules: {}", e)))?
             .collect::<std::result::Result<Vec<_>, _>>()
-            .map_err(|e| Error::Other(format!("Failed to collect filter rules: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to collect filter rules: {}", e)))?;
         Ok(rules)
     }
 
     /// Update an existing message filter rule
     pub fn update_filter_rule(&self, rule: &MessageFilterRule) -> Result<()> {
-        let now = chrono::Utc::now().to_rfc3339();
+        let now = self.now().to_rfc3339();
         self.conn.execute(
             "UPDATE message_filter_rules
              SET name = ?1, field = ?2, match_type = ?3, pattern = ?4, case_sensitive = ?5, action_type = ?6, action_value = ?7, enabled = ?8, updated_at = ?9
@@ -58,7 +58,7 @@ impl MessageCache {
                 &rule.case_sensitive, &rule.action_type, &rule.action_value,
                 &rule.enabled, &now, &rule.id,
             ],
-        ).map_err(|e| Error::Other(format!("Failed to update filter rule: {}", e)))?;
+        ).map_err(|e| Error::Database(format!("Failed to update filter rule: {}", e)))?;
         Ok(())
     }
 
@@ -66,7 +66,35 @@ impl MessageCache {
     pub fn delete_filter_rule(&self, rule_id: &str) -> Result<()> {
         self.conn
             .execute("DELETE FROM message_filter_rules WHERE id = ?1", params![rule_id])
-            .map_err(|e| Error::Other(format!("Failed to delete filter rule: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to delete filter rule: {}", e)))?;
+        self.conn
+            .execute("DELETE FROM rule_sends WHERE rule_id = ?1", params![rule_id])
+            .map_err(|e| Error::Database(format!("Failed to delete filter rule history: {}", e)))?;
+        Ok(())
+    }
+
+    /// Record that a rule is sending mail about `target`: the Message-ID
+    /// of a forwarded message, or the address an auto-reply goes to.
+    /// Returns false if it already has, so nothing is sent twice.
+    pub fn claim_rule_send(&self, account_id: &str, rule_id: &str, target: &str) -> Result<bool> {
+        let now = self.now().to_rfc3339();
+        let inserted = self.conn
+            .execute(
+                "INSERT OR IGNORE INTO rule_sends (account_id, rule_id, target, sent_at) VALUES (?1, ?2, ?3, ?4)",
+                params![account_id, rule_id, target.to_lowercase(), now],
+            )
+            .map_err(|e| Error::Database(format!("Failed to record rule send: {}", e)))?;
+        Ok(inserted == 1)
+    }
+
+    /// Forget a claim whose mail could not be sent, so the next check retries
+    pub fn release_rule_send(&self, account_id: &str, rule_id: &str, target: &str) -> Result<()> {
+        self.conn
+            .execute(
+                "DELETE FROM rule_sends WHERE account_id = ?1 AND rule_id = ?2 AND target = ?3",
+                params![account_id, rule_id, target.to_lowercase()],
+            )
+            .map_err(|e| Error::Database(format!("Failed to release rule send: {}", e)))?;
         Ok(())
     }
 }
@@ -110,4 +138,20 @@ mod tests {
         let empty = cache.get_filter_rules_for_account("test@example.com").unwrap();
         assert!(empty.is_empty());
     }
+
+    #[test]
+    fn test_rule_sends_are_claimed_once() {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_rule_sends_{}", nanos));
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+
+        assert!(cache.claim_rule_send("me@example.org", "away", "Ana@Example.com").unwrap());
+        assert!(!cache.claim_rule_send("me@example.org", "away", "ana@example.com").unwrap());
+        assert!(cache.claim_rule_send("me@example.org", "other-rule", "ana@example.com").unwrap());
+
+        cache.release_rule_send("me@example.org", "away", "ana@example.com").unwrap();
+        assert!(cache.claim_rule_send("me@example.org", "away", "ana@example.com").unwrap());
+        cache.delete_filter_rule("away").unwrap();
+        assert!(cache.claim_rule_send("me@example.org", "away", "ana@example.com").unwrap());
+    }
 }
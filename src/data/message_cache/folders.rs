@@ -1,6 +1,7 @@
 //! Folder persistence operations
 
-use super::{CachedFolder, MessageCache};
+use super::{CachedFolder, FolderSubscription, MessageCache, ServerFolder};
+use crate::common::types::FolderType;
 use crate::common::{Error, Result};
 use rusqlite::{params, OptionalExtension};
 
@@ -18,7 +19,7 @@ impl MessageCache {
                 folder.unread_count,
                 folder.total_count,
             ],
-        ).map_err(|e| Error::Other(format!("Failed to save folder: {}", e)))?;
+        ).map_err(|e| Error::Database(format!("Failed to save folder: {}", e)))?;
 
         Ok(self.conn.last_insert_rowid())
     }
@@ -31,7 +32,7 @@ impl MessageCache {
                 "SELECT id, account_id, name, path, folder_type, unread_count, total_count
              FROM folders WHERE account_id = ?1 AND path = ?2",
             )
-            .map_err(|e| Error::Other(format!("Failed to prepare statement: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to prepare statement: {}", e)))?;
 
         let folder = stmt
             .query_row(params![account_id, path], |row| {
@@ -46,7 +47,7 @@ impl MessageCache {
                 })
             })
             .optional()
-            .map_err(|e| Error::Other(format!("Failed to get folder: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to get folder: {}", e)))?;
 
         Ok(folder)
     }
@@ -59,7 +60,7 @@ impl MessageCache {
                 "SELECT id, account_id, name, path, folder_type, unread_count, total_count
              FROM folders WHERE account_id = ?1 ORDER BY name",
             )
-            .map_err(|e| Error::Other(format!("Failed to prepare statement: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to prepare statement: {}", e)))?;
 
         let folders = stmt
             .query_map(params![account_id], |row| {
@@ -73,17 +74,322 @@ impl MessageCache {
                     total_count: row.get(6)?,
                 })
             })
-            .map_err(|e| Error::Other(format!("Failed to query folders: {}", e)))?
+            .map_err(|e| Error::Database(format!("Failed to query folders: {}", e)))?
             .collect::<std::result::Result<Vec<_>, _>>()
-            .map_err(|e| Error::Other(format!("Failed to collect folders: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to collect folders: {}", e)))?;
 
         Ok(folders)
     }
+
+    /// Get a folder, creating an empty cache entry for it if needed
+    pub fn ensure_folder(&self, account_id: &str, path: &str) -> Result<CachedFolder> {
+        if let Some(folder) = self.get_folder(account_id, path)? {
+            return Ok(folder);
+        }
+        let folder_type = if path.eq_ignore_ascii_case("INBOX") { "Inbox" } else { "Custom" };
+        self.conn
+            .execute(
+                "INSERT OR IGNORE INTO folders (account_id, name, path, folder_type) VALUES (?1, ?2, ?2, ?3)",
+                params![account_id, path, folder_type],
+            )
+            .map_err(|e| Error::Database(format!("Failed to save folder: {}", e)))?;
+        self.get_folder(account_id, path)?
+            .ok_or_else(|| Error::Database(format!("Folder {} missing after insert", path)))
+    }
+
+    /// Recount a folder's unread and total messages from the cached rows.
+    ///
+    /// Deleted messages are not counted.
+    pub fn recompute_folder_counts(&self, folder_id: i64) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE folders SET
+                    unread_count = (SELECT COUNT(*) FROM messages WHERE folder_id = ?1 AND deleted = 0 AND read = 0),
+                    total_count = (SELECT COUNT(*) FROM messages WHERE folder_id = ?1 AND deleted = 0)
+                 WHERE id = ?1",
+                params![folder_id],
+            )
+            .map_err(|e| Error::Database(format!("Failed to update folder counts: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Number of enabled accounts and their combined Inbox unread and total
+    /// counts, for the All Inboxes entry in the folder tree.
+    pub fn get_all_inboxes_counts(&self) -> Result<(usize, i64, i64)> {
+        self.conn
+            .query_row(
+                "SELECT (SELECT COUNT(*) FROM accounts WHERE enabled = 1),
+                        COALESCE(SUM(f.unread_count), 0), COALESCE(SUM(f.total_count), 0)
+                 FROM folders f
+                 INNER JOIN accounts a ON f.account_id = a.id
+                 WHERE UPPER(f.path) = 'INBOX' AND a.enabled = 1",
+                [],
+                |row| Ok((row.get::<_, i64>(0)? as usize, row.get(1)?, row.get(2)?)),
+            )
+            .map_err(|e| Error::Database(format!("Failed to count inbox messages: {}", e)))
+    }
+
+    /// Recount the folder holding a cached message
+    pub(crate) fn recompute_counts_for_message(&self, message_id: i64) -> Result<()> {
+        let folder_id: Option<i64> = self
+            .conn
+            .query_row("SELECT folder_id FROM messages WHERE id = ?1", params![message_id], |row| row.get(0))
+            .optional()
+            .map_err(|e| Error::Database(format!("Failed to look up message folder: {}", e)))?;
+        match folder_id {
+            Some(folder_id) => self.recompute_folder_counts(folder_id),
+            None => Ok(()),
+        }
+    }
+
+    /// Mark a folder as available offline (or not)
+    pub fn set_folder_offline_sync(&self, folder_id: i64, enabled: bool) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE folders SET offline_sync = ?1 WHERE id = ?2",
+                params![enabled, folder_id],
+            )
+            .map_err(|e| Error::Database(format!("Failed to update offline sync: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Paths of the account's subscribed folders marked as available
+    /// offline. Unsubscribed folders are left for the user to open.
+    pub fn get_offline_sync_folders(&self, account_id: &str) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path FROM folders WHERE account_id = ?1 AND offline_sync = 1 AND subscribed = 1 ORDER BY name")
+            .map_err(|e| Error::Database(format!("Failed to prepare statement: {}", e)))?;
+
+        let paths = stmt
+            .query_map(params![account_id], |row| row.get(0))
+            .map_err(|e| Error::Database(format!("Failed to query offline folders: {}", e)))?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::Database(format!("Failed to collect offline folders: {}", e)))?;
+
+        Ok(paths)
+    }
+
+    /// Record the account's folders as the server lists them, with whether
+    /// each is on the subscription list and its role. Folders the server no
+    /// longer lists stay cached.
+    pub fn record_server_folders(&self, account_id: &str, folders: &[ServerFolder]) -> Result<()> {
+        self.in_transaction(|c| {
+            for folder in folders {
+                c.ensure_folder(account_id, &folder.path)?;
+                c.set_folder_subscribed(account_id, &folder.path, folder.subscribed)?;
+                c.conn
+                    .execute(
+                        "UPDATE folders SET folder_type = ?1 WHERE account_id = ?2 AND path = ?3",
+                        params![folder.folder_type.as_str(), account_id, folder.path],
+                    )
+                    .map_err(|e| Error::Database(format!("Failed to update folder type: {}", e)))?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Path of the account's cached folder with a special role, such as
+    /// its Trash, as last listed by the server
+    pub fn get_special_folder(&self, account_id: &str, folder_type: FolderType) -> Result<Option<String>> {
+        self.conn
+            .query_row(
+                "SELECT path FROM folders WHERE account_id = ?1 AND folder_type = ?2 ORDER BY id LIMIT 1",
+                params![account_id, folder_type.as_str()],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| Error::Database(format!("Failed to look up special folder: {}", e)))
+    }
+
+    /// Subscription and show/hide choice of each of the account's folders
+    pub fn get_folder_subscriptions(&self, account_id: &str) -> Result<Vec<FolderSubscription>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path, subscribed, shown FROM folders WHERE account_id = ?1 ORDER BY name")
+            .map_err(|e| Error::Database(format!("Failed to prepare statement: {}", e)))?;
+
+        let subscriptions = stmt
+            .query_map(params![account_id], |row| {
+                Ok(FolderSubscription { path: row.get(0)?, subscribed: row.get(1)?, shown: row.get(2)? })
+            })
+            .map_err(|e| Error::Database(format!("Failed to query folder subscriptions: {}", e)))?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::Database(format!("Failed to collect folder subscriptions: {}", e)))?;
+
+        Ok(subscriptions)
+    }
+
+    /// Mark a folder as on the server's subscription list (or not)
+    pub fn set_folder_subscribed(&self, account_id: &str, path: &str, subscribed: bool) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE folders SET subscribed = ?1 WHERE account_id = ?2 AND path = ?3",
+                params![subscribed, account_id, path],
+            )
+            .map_err(|e| Error::Database(format!("Failed to update folder subscription: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Show or hide a folder in the folder list whatever its subscription;
+    /// `None` goes back to following it
+    pub fn set_folder_shown(&self, account_id: &str, path: &str, shown: Option<bool>) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE folders SET shown = ?1 WHERE account_id = ?2 AND path = ?3",
+                params![shown, account_id, path],
+            )
+            .map_err(|e| Error::Database(format!("Failed to update folder visibility: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Remember how a folder's messages are sorted, as a
+    /// `MailSortOption` config key. Renaming the folder keeps it.
+    pub fn set_folder_sort_order(&self, account_id: &str, path: &str, order: &str) -> Result<()> {
+        self.ensure_folder(account_id, path)?;
+        self.conn
+            .execute(
+                "UPDATE folders SET sort_order = ?1 WHERE account_id = ?2 AND path = ?3",
+                params![order, account_id, path],
+            )
+            .map_err(|e| Error::Database(format!("Failed to save folder sort order: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// A folder's remembered sort order; `None` if it was never sorted
+    pub fn get_folder_sort_order(&self, account_id: &str, path: &str) -> Result<Option<String>> {
+        self.conn
+            .query_row(
+                "SELECT sort_order FROM folders WHERE account_id = ?1 AND path = ?2",
+                params![account_id, path],
+                |row| row.get(0),
+            )
+            .optional()
+            .map(Option::flatten)
+            .map_err(|e| Error::Database(format!("Failed to get folder sort order: {}", e)))
+    }
+
+    /// Record that a folder was just fetched from the server
+    pub fn mark_folder_synced(&self, folder_id: i64) -> Result<()> {
+        self.conn
+            .execute("UPDATE folders SET last_sync = ?1 WHERE id = ?2", params![self.now().to_rfc3339(), folder_id])
+            .map_err(|e| Error::Database(format!("Failed to update folder sync time: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// When a folder was last fetched from the server (RFC 3339); `None`
+    /// if it never was
+    pub fn get_folder_last_sync(&self, account_id: &str, path: &str) -> Result<Option<String>> {
+        self.conn
+            .query_row(
+                "SELECT last_sync FROM folders WHERE account_id = ?1 AND path = ?2",
+                params![account_id, path],
+                |row| row.get(0),
+            )
+            .optional()
+            .map(Option::flatten)
+            .map_err(|e| Error::Database(format!("Failed to get folder sync time: {}", e)))
+    }
+
+    /// Whether background sync may poll a folder: it is subscribed, or not
+    /// cached yet
+    pub fn is_folder_subscribed(&self, account_id: &str, path: &str) -> Result<bool> {
+        let subscribed: Option<bool> = self
+            .conn
+            .query_row(
+                "SELECT subscribed FROM folders WHERE account_id = ?1 AND path = ?2",
+                params![account_id, path],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| Error::Database(format!("Failed to look up folder subscription: {}", e)))?;
+
+        Ok(subscribed.unwrap_or(true))
+    }
+
+    /// The account's folders that belong in the folder list (see
+    /// [`FolderSubscription::is_visible`])
+    pub fn get_visible_folders(&self, account_id: &str, subscribed_only: bool) -> Result<Vec<CachedFolder>> {
+        let visible: std::collections::HashSet<String> = self
+            .get_folder_subscriptions(account_id)?
+            .into_iter()
+            .filter(|f| f.is_visible(subscribed_only))
+            .map(|f| f.path)
+            .collect();
+        let mut folders = self.get_folders_for_account(account_id)?;
+        folders.retain(|f| visible.contains(&f.path));
+
+        Ok(folders)
+    }
+
+    /// Follow a folder renamed on the server: it and its subfolders (paths
+    /// below it, split by `delimiter`) take the new path, keeping their
+    /// cached messages.
+    pub fn rename_cached_folder(&self, account_id: &str, path: &str, new_path: &str, delimiter: &str) -> Result<usize> {
+        let leaf = match delimiter {
+            "" => new_path,
+            _ => new_path.rsplit(delimiter).next().unwrap_or(new_path),
+        };
+        self.conn
+            .execute(
+                "UPDATE folders SET
+                    name = CASE WHEN name = path THEN ?3 || substr(path, length(?2) + 1)
+                                WHEN path = ?2 THEN ?4 ELSE name END,
+                    path = ?3 || substr(path, length(?2) + 1)
+                 WHERE account_id = ?1
+                   AND (path = ?2 OR (?5 <> '' AND substr(path, 1, length(?2) + length(?5)) = ?2 || ?5))",
+                params![account_id, path, new_path, leaf, delimiter],
+            )
+            .map_err(|e| Error::Database(format!("Failed to rename folder: {}", e)))
+    }
+
+    /// Forget a folder deleted on the server, with its cached messages.
+    /// Returns the number of messages removed.
+    pub fn delete_cached_folder(&self, account_id: &str, path: &str) -> Result<usize> {
+        let Some(folder) = self.get_folder(account_id, path)? else { return Ok(0) };
+        self.in_transaction(|c| {
+            let removed = c
+                .conn
+                .execute("DELETE FROM messages WHERE folder_id = ?1", params![folder.id])
+                .map_err(|e| Error::Database(format!("Failed to delete folder messages: {}", e)))?;
+            c.conn
+                .execute("DELETE FROM folders WHERE id = ?1", params![folder.id])
+                .map_err(|e| Error::Database(format!("Failed to delete folder: {}", e)))?;
+            Ok(removed)
+        })
+    }
+
+    /// Drop downloaded bodies for a folder and clear its offline flag.
+    /// Headers stay so the folder can still be listed. Run
+    /// [`MessageCache::compact`] afterwards to give the space back.
+    ///
+    /// Returns the number of bodies removed.
+    pub fn purge_offline_data(&self, folder_id: i64) -> Result<usize> {
+        let purged = self.in_transaction(|c| {
+            c.set_folder_offline_sync(folder_id, false)?;
+            c.conn
+                .execute(
+                    "UPDATE messages SET body_plain = NULL, body_html = NULL
+                     WHERE folder_id = ?1 AND (body_plain IS NOT NULL OR body_html IS NOT NULL)",
+                    params![folder_id],
+                )
+                .map_err(|e| Error::Database(format!("Failed to purge offline data: {}", e)))
+        })?;
+
+        Ok(purged)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::common::types::MessagePriority;
     use std::env;
 
     #[test]
@@ -108,4 +414,191 @@ mod tests {
         assert!(retrieved.is_some());
         assert_eq!(retrieved.unwrap().name, "INBOX");
     }
+
+    #[test]
+    fn test_offline_sync_flag_and_purge() {
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_offline_{}", std::process::id()));
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+
+        let inbox = cache.ensure_folder("acc", "INBOX").unwrap();
+        assert_eq!(inbox.folder_type, "Inbox");
+        assert_eq!(cache.ensure_folder("acc", "INBOX").unwrap().id, inbox.id);
+        assert!(cache.get_offline_sync_folders("acc").unwrap().is_empty());
+
+        cache.set_folder_offline_sync(inbox.id, true).unwrap();
+        assert_eq!(cache.get_offline_sync_folders("acc").unwrap(), vec!["INBOX".to_string()]);
+
+        cache.save_message(&crate::data::message_cache::CachedMessage {
+            id: 0, uid: 1, folder_id: inbox.id,
+            message_id: "m1@example.com".to_string(), subject: "Hi".to_string(),
+            from_addr: "a@example.com".to_string(), to_addr: String::new(),
+            cc: None, date: "2024-01-01".to_string(),
+            body_plain: Some("body".to_string()), body_html: None,
+            read: false, starred: false, deleted: false, priority: MessagePriority::Normal,
+        }).unwrap();
+
+        assert_eq!(cache.purge_offline_data(inbox.id).unwrap(), 1);
+        assert!(cache.get_offline_sync_folders("acc").unwrap().is_empty());
+        assert_eq!(cache.get_message_body(inbox.id, 1).unwrap(), None);
+        assert_eq!(cache.get_messages_for_folder(inbox.id, "acc").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_folder_last_sync() {
+        use crate::common::clock::MockClock;
+        use chrono::{TimeZone, Utc};
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_folder_sync_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        let clock = MockClock::new(Utc.with_ymd_and_hms(2025, 3, 12, 9, 0, 0).unwrap());
+        let cache = MessageCache::new(temp_dir, None).unwrap().with_clock(clock);
+        let inbox = cache.ensure_folder("acc", "INBOX").unwrap();
+        cache.ensure_folder("acc", "Sent").unwrap();
+        assert_eq!(cache.get_folder_last_sync("acc", "INBOX").unwrap(), None);
+        assert_eq!(cache.get_folder_last_sync("acc", "Missing").unwrap(), None);
+
+        cache.mark_folder_synced(inbox.id).unwrap();
+        assert_eq!(cache.get_folder_last_sync("acc", "INBOX").unwrap().as_deref(), Some("2025-03-12T09:00:00+00:00"));
+        assert_eq!(cache.get_folder_last_sync("acc", "Sent").unwrap(), None);
+    }
+
+    #[test]
+    fn test_folder_subscriptions_and_visibility() {
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_subscriptions_{}", std::process::id()));
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+        let folders = [("INBOX", true), ("Archive", true), ("Lists/rust", false), ("Old", false)];
+        let folders: Vec<ServerFolder> = folders
+            .iter()
+            .map(|(p, s)| ServerFolder { path: p.to_string(), subscribed: *s, folder_type: FolderType::Custom })
+            .collect();
+        cache.record_server_folders("acc", &folders).unwrap();
+        let visible = |subscribed_only: bool| -> Vec<String> {
+            cache.get_visible_folders("acc", subscribed_only).unwrap().into_iter().map(|f| f.path).collect()
+        };
+        assert_eq!(visible(false).len(), 4);
+        assert_eq!(visible(true), vec!["Archive".to_string(), "INBOX".to_string()]);
+        assert!(!cache.is_folder_subscribed("acc", "Old").unwrap());
+        assert!(cache.is_folder_subscribed("acc", "Not cached").unwrap());
+
+        // Local overrides win over the subscription either way
+        cache.set_folder_shown("acc", "Old", Some(true)).unwrap();
+        cache.set_folder_shown("acc", "Archive", Some(false)).unwrap();
+        assert_eq!(visible(true), vec!["INBOX".to_string(), "Old".to_string()]);
+        cache.set_folder_shown("acc", "INBOX", Some(false)).unwrap();
+        assert!(visible(false).contains(&"INBOX".to_string()));
+
+        // Unsubscribed folders are not synced offline
+        let old = cache.get_folder("acc", "Old").unwrap().unwrap();
+        cache.set_folder_offline_sync(old.id, true).unwrap();
+        assert!(cache.get_offline_sync_folders("acc").unwrap().is_empty());
+        cache.set_folder_subscribed("acc", "Old", true).unwrap();
+        assert_eq!(cache.get_offline_sync_folders("acc").unwrap(), vec!["Old".to_string()]);
+
+        let mut archive = cache.get_folder_subscriptions("acc").unwrap().remove(0);
+        assert_eq!((archive.path.as_str(), archive.shown), ("Archive", Some(false)));
+        archive.choose_visible(true, true);
+        assert_eq!(archive.shown, None);
+        archive.choose_visible(false, false);
+        assert_eq!(archive.shown, Some(false));
+    }
+
+    #[test]
+    fn test_special_folder_roles() {
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_folder_roles_{}", std::process::id()));
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+        assert_eq!(cache.get_special_folder("acc", FolderType::Trash).unwrap(), None);
+
+        let listed = |path: &str, folder_type| ServerFolder { path: path.to_string(), subscribed: true, folder_type };
+        cache.record_server_folders("acc", &[
+            listed("INBOX", FolderType::Inbox),
+            listed("Deleted Items", FolderType::Trash),
+            listed("[Gmail]/Spam", FolderType::Spam),
+        ]).unwrap();
+        assert_eq!(cache.get_special_folder("acc", FolderType::Trash).unwrap().as_deref(), Some("Deleted Items"));
+        assert_eq!(cache.get_special_folder("acc", FolderType::Spam).unwrap().as_deref(), Some("[Gmail]/Spam"));
+        assert_eq!(cache.get_special_folder("other", FolderType::Trash).unwrap(), None);
+        assert_eq!(cache.get_folder("acc", "Deleted Items").unwrap().unwrap().folder_type, "Trash");
+
+        // A later listing without the role clears it
+        cache.record_server_folders("acc", &[listed("Deleted Items", FolderType::Custom)]).unwrap();
+        assert_eq!(cache.get_special_folder("acc", FolderType::Trash).unwrap(), None);
+    }
+
+    #[test]
+    fn test_rename_and_delete_cached_folders() {
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_folder_tree_{}", std::process::id()));
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+        for path in ["Lists", "Lists.rust", "Lists.rust.announce", "Listsold", "INBOX"] {
+            cache.ensure_folder("acc", path).unwrap();
+        }
+        let rust = cache.get_folder("acc", "Lists.rust").unwrap().unwrap();
+        cache.save_message(&crate::data::message_cache::CachedMessage {
+            id: 0, uid: 1, folder_id: rust.id,
+            message_id: "m1@example.com".to_string(), subject: "Hi".to_string(),
+            from_addr: "a@example.com".to_string(), to_addr: String::new(),
+            cc: None, date: "2024-01-01".to_string(),
+            body_plain: None, body_html: None,
+            read: false, starred: false, deleted: false, priority: MessagePriority::Normal,
+        }).unwrap();
+        let paths = || -> Vec<String> {
+            cache.get_folders_for_account("acc").unwrap().into_iter().map(|f| f.path).collect()
+        };
+
+        assert_eq!(cache.rename_cached_folder("acc", "Lists", "Mailing", ".").unwrap(), 3);
+        assert_eq!(paths(), vec!["INBOX", "Listsold", "Mailing", "Mailing.rust", "Mailing.rust.announce"]);
+        let renamed = cache.get_folder("acc", "Mailing.rust").unwrap().unwrap();
+        assert_eq!((renamed.id, renamed.name.as_str()), (rust.id, "Mailing.rust"));
+        assert_eq!(cache.get_messages_for_folder(rust.id, "acc").unwrap().len(), 1);
+
+        // Sort orders belong to the folder, so they follow a rename
+        assert_eq!(cache.get_folder_sort_order("acc", "Mailing.rust").unwrap(), None);
+        cache.set_folder_sort_order("acc", "Mailing.rust", "date_oldest").unwrap();
+        cache.set_folder_sort_order("acc", "Archive", "sender_az").unwrap();
+        assert_eq!(cache.get_folder_sort_order("other", "Mailing.rust").unwrap(), None);
+        cache.rename_cached_folder("acc", "Mailing.rust", "Rust", ".").unwrap();
+        assert_eq!(cache.get_folder_sort_order("acc", "Rust").unwrap().as_deref(), Some("date_oldest"));
+        assert_eq!(cache.get_folder_sort_order("acc", "Archive").unwrap().as_deref(), Some("sender_az"));
+        cache.rename_cached_folder("acc", "Rust", "Mailing.rust", ".").unwrap();
+        cache.delete_cached_folder("acc", "Archive").unwrap();
+
+        assert_eq!(cache.delete_cached_folder("acc", "Mailing.rust.announce").unwrap(), 0);
+        assert_eq!(cache.delete_cached_folder("acc", "Mailing.rust").unwrap(), 1);
+        assert_eq!(paths(), vec!["INBOX", "Listsold", "Mailing"]);
+        assert!(cache.get_messages_for_folder(rust.id, "acc").unwrap().is_empty());
+        assert_eq!(cache.delete_cached_folder("acc", "Mailing.rust").unwrap(), 0);
+    }
+
+    #[test]
+    fn test_folder_counts_follow_flag_changes() {
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_counts_{}", std::process::id()));
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+        let inbox = cache.ensure_folder("acc", "INBOX").unwrap();
+        let archive = cache.ensure_folder("acc", "Archive").unwrap();
+
+        let ids: Vec<i64> = (1..=3).map(|uid| {
+            cache.save_message(&crate::data::message_cache::CachedMessage {
+                id: 0, uid, folder_id: inbox.id,
+                message_id: format!("m{}@example.com", uid), subject: "Hi".to_string(),
+                from_addr: "a@example.com".to_string(), to_addr: String::new(),
+                cc: None, date: "2024-01-01".to_string(),
+                body_plain: None, body_html: None,
+                read: false, starred: false, deleted: false, priority: MessagePriority::Normal,
+            }).unwrap()
+        }).collect();
+        cache.recompute_folder_counts(inbox.id).unwrap();
+        let counts = |path: &str| {
+            let f = cache.get_folder("acc", path).unwrap().unwrap();
+            (f.unread_count, f.total_count)
+        };
+        assert_eq!(counts("INBOX"), (3, 3));
+
+        cache.set_message_read(ids[0], true).unwrap();
+        assert_eq!(counts("INBOX"), (2, 3));
+        cache.delete_message(ids[1]).unwrap();
+        assert_eq!(counts("INBOX"), (1, 2));
+        cache.move_message(ids[2], archive.id).unwrap();
+        assert_eq!(counts("INBOX"), (0, 1));
+        assert_eq!(counts("Archive"), (1, 1));
+        cache.restore_message(ids[1], false, false).unwrap();
+        assert_eq!(counts("INBOX"), (1, 2));
+    }
 }
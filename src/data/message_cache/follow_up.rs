@@ -0,0 +1,178 @@
+//! Follow-up flag persistence operations
+//!
+//! Follow-up is separate from the star: a message can be a favourite, need
+//! a reply, or both.
+
+use super::{CachedMessage, FollowUpMessage, MessageCache};
+use crate::common::types::MessagePriority;
+use crate::common::{Error, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::params;
+use std::collections::HashMap;
+
+/// Due times are stored in one fixed format so they sort as text.
+fn due_key(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+impl MessageCache {
+    /// Flag a message for follow-up, optionally due by `due`
+    pub fn set_follow_up(&self, message_id: i64, due: Option<DateTime<Utc>>) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE messages SET flagged = 1, flag_due = ?1 WHERE id = ?2",
+                params![due.map(due_key), message_id],
+            )
+            .map_err(|e| Error::Database(format!("Failed to flag message for follow-up: {}", e)))?;
+        Ok(())
+    }
+
+    /// Remove a message's follow-up flag and due date
+    pub fn clear_follow_up(&self, message_id: i64) -> Result<()> {
+        self.conn
+            .execute("UPDATE messages SET flagged = 0, flag_due = NULL WHERE id = ?1", params![message_id])
+            .map_err(|e| Error::Database(format!("Failed to clear follow-up flag: {}", e)))?;
+        Ok(())
+    }
+
+    /// Apply the follow-up flag as seen on the server. The due date is
+    /// local only, so it is kept while the message stays flagged.
+    pub fn sync_follow_up(&self, folder_id: i64, uid: u32, flagged: bool) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE messages SET flagged = ?1, flag_due = CASE WHEN ?1 THEN flag_due END
+                 WHERE folder_id = ?2 AND uid = ?3",
+                params![flagged, folder_id, uid],
+            )
+            .map_err(|e| Error::Database(format!("Failed to sync follow-up flag: {}", e)))?;
+        Ok(())
+    }
+
+    /// Follow-up state of the messages in a folder: message id to due time
+    pub fn get_follow_ups_for_folder(&self, folder_id: i64) -> Result<HashMap<i64, Option<String>>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, flag_due FROM messages WHERE folder_id = ?1 AND flagged = 1 AND deleted = 0")
+            .map_err(|e| Error::Database(format!("Failed to prepare statement: {}", e)))?;
+        let flags = stmt
+            .query_map(params![folder_id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| Error::Database(format!("Failed to query follow-up flags: {}", e)))?
+            .collect::<std::result::Result<HashMap<_, _>, _>>()
+            .map_err(|e| Error::Database(format!("Failed to collect follow-up flags: {}", e)))?;
+        Ok(flags)
+    }
+
+    /// Every message flagged for follow-up in an account, soonest due first
+    /// and those without a due date last.
+    pub fn get_follow_up_messages(&self, account_id: &str) -> Result<Vec<FollowUpMessage>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT m.id, m.uid, m.folder_id, m.message_id, m.subject, m.from_addr, m.to_addr, m.cc, m.date,
+                    m.body_plain, m.body_html, m.read, m.starred, m.deleted, m.priority, f.path, m.flag_due
+             FROM messages m
+             INNER JOIN folders f ON m.folder_id = f.id
+             WHERE f.account_id = ?1 AND m.flagged = 1 AND m.deleted = 0
+             ORDER BY m.flag_due IS NULL, m.flag_due, m.date DESC"
+        ).map_err(|e| Error::Database(format!("Failed to prepare statement: {}", e)))?;
+
+        let messages = stmt
+            .query_map(params![account_id], |row| {
+                Ok(FollowUpMessage {
+                    message: CachedMessage {
+                        id: row.get(0)?,
+                        uid: row.get(1)?,
+                        folder_id: row.get(2)?,
+                        message_id: row.get(3)?,
+                        subject: row.get(4)?,
+                        from_addr: row.get(5)?,
+                        to_addr: row.get(6)?,
+                        cc: row.get(7)?,
+                        date: row.get(8)?,
+                        body_plain: row.get(9)?,
+                        body_html: row.get(10)?,
+                        read: row.get(11)?,
+                        starred: row.get(12)?,
+                        deleted: row.get(13)?,
+                        priority: MessagePriority::parse(&row.get::<_, String>(14)?),
+                    },
+                    folder_path: row.get(15)?,
+                    due: row.get(16)?,
+                })
+            })
+            .map_err(|e| Error::Database(format!("Failed to query follow-up messages: {}", e)))?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::Database(format!("Failed to collect follow-up messages: {}", e)))?;
+
+        messages
+            .into_iter()
+            .map(|f| Ok(FollowUpMessage { message: self.open_message(f.message)?, ..f }))
+            .collect()
+    }
+
+    /// Flagged and overdue message counts for an account
+    pub fn get_follow_up_counts(&self, account_id: &str, now: DateTime<Utc>) -> Result<(i64, i64)> {
+        self.conn
+            .query_row(
+                "SELECT COUNT(*), COALESCE(SUM(m.flag_due IS NOT NULL AND m.flag_due < ?2), 0)
+                 FROM messages m
+                 INNER JOIN folders f ON m.folder_id = f.id
+                 WHERE f.account_id = ?1 AND m.flagged = 1 AND m.deleted = 0",
+                params![account_id, due_key(now)],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(|e| Error::Database(format!("Failed to count follow-up messages: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use std::env;
+
+    #[test]
+    fn test_follow_up_flags() {
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_follow_up_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+        let inbox = cache.ensure_folder("acc", "INBOX").unwrap().id;
+        let sent = cache.ensure_folder("acc", "Sent").unwrap().id;
+        let save = |folder_id: i64, uid: u32| {
+            cache.save_message(&CachedMessage {
+                id: 0, uid, folder_id, message_id: format!("f{}@example.com", uid),
+                subject: format!("Message {}", uid), from_addr: "a@example.com".to_string(),
+                to_addr: String::new(), cc: None, date: "2024-01-01".to_string(),
+                body_plain: None, body_html: None, read: true, starred: true, deleted: false, priority: MessagePriority::Normal,
+            }).unwrap()
+        };
+        let (undated, overdue, upcoming, plain) = (save(inbox, 1), save(sent, 2), save(inbox, 3), save(inbox, 4));
+        let now = Utc::now();
+
+        cache.set_follow_up(undated, None).unwrap();
+        cache.set_follow_up(overdue, Some(now - Duration::days(1))).unwrap();
+        cache.set_follow_up(upcoming, Some(now + Duration::days(1))).unwrap();
+
+        let flagged = cache.get_follow_up_messages("acc").unwrap();
+        let order: Vec<i64> = flagged.iter().map(|f| f.message.id).collect();
+        assert_eq!(order, vec![overdue, upcoming, undated]);
+        assert_eq!(flagged[0].folder_path, "Sent");
+        assert!(flagged[2].due.is_none());
+        assert_eq!(cache.get_follow_up_counts("acc", now).unwrap(), (3, 1));
+
+        let in_inbox = cache.get_follow_ups_for_folder(inbox).unwrap();
+        assert_eq!(in_inbox.len(), 2);
+        assert!(!in_inbox.contains_key(&plain));
+
+        // Starring is untouched by follow-up changes
+        cache.clear_follow_up(upcoming).unwrap();
+        assert!(cache.get_message(upcoming).unwrap().unwrap().starred);
+        assert_eq!(cache.get_follow_up_counts("acc", now).unwrap(), (2, 1));
+
+        // The server's flag wins, but keeps a local due date
+        cache.sync_follow_up(sent, 2, true).unwrap();
+        assert!(cache.get_follow_up_messages("acc").unwrap()[0].due.is_some());
+        cache.sync_follow_up(sent, 2, false).unwrap();
+        cache.sync_follow_up(inbox, 4, true).unwrap();
+        let ids: Vec<i64> = cache.get_follow_up_messages("acc").unwrap().iter().map(|f| f.message.id).collect();
+        assert_eq!(ids, vec![undated, plain]);
+    }
+}
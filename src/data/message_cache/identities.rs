@@ -0,0 +1,133 @@
+//! Sending identity (From alias) persistence operations
+
+use super::{Identity, MessageCache};
+use crate::common::{Error, Result};
+use rusqlite::{params, OptionalExtension, Row};
+
+fn identity_from_row(row: &Row<'_>) -> rusqlite::Result<Identity> {
+    Ok(Identity {
+        id: row.get(0)?, account_id: row.get(1)?, from_name: row.get(2)?,
+        from_email: row.get(3)?, reply_to: row.get(4)?, signature_id: row.get(5)?,
+        created_at: row.get(6)?, envelope_from: row.get(7)?,
+    })
+}
+
+impl MessageCache {
+    /// Create a new identity
+    pub fn create_identity(&self, identity: &Identity) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO identities
+             (id, account_id, from_name, from_email, reply_to, signature_id, created_at, envelope_from)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                &identity.id, &identity.account_id, &identity.from_name, &identity.from_email,
+                &identity.reply_to, &identity.signature_id, &identity.created_at, &identity.envelope_from,
+            ],
+        ).map_err(|e| Error::Database(format!("Failed to create identity: {}", e)))?;
+        Ok(())
+    }
+
+    /// Get all identities for an account, by address
+    pub fn get_identities_for_account(&self, account_id: &str) -> Result<Vec<Identity>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, account_id, from_name, from_email, reply_to, signature_id, created_at, envelope_from
+             FROM identities WHERE account_id = ?1 ORDER BY from_email COLLATE NOCASE",
+        ).map_err(|e| Error::Database(format!("Failed to prepare statement: {}", e)))?;
+
+        let identities = stmt
+            .query_map(params![account_id], identity_from_row)
+            .map_err(|e| Error::Database(format!("Failed to query identities: {}", e)))?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::Database(format!("Failed to collect identities: {}", e)))?;
+        Ok(identities)
+    }
+
+    /// Get a specific identity by ID
+    pub fn get_identity(&self, identity_id: &str) -> Result<Option<Identity>> {
+        self.conn
+            .query_row(
+                "SELECT id, account_id, from_name, from_email, reply_to, signature_id, created_at, envelope_from
+                 FROM identities WHERE id = ?1",
+                params![identity_id],
+                identity_from_row,
+            )
+            .optional()
+            .map_err(|e| Error::Database(format!("Failed to get identity: {}", e)))
+    }
+
+    /// Update an identity
+    pub fn update_identity(&self, identity: &Identity) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE identities SET from_name = ?1, from_email = ?2, reply_to = ?3, signature_id = ?4,
+                 envelope_from = ?5 WHERE id = ?6",
+                params![
+                    &identity.from_name, &identity.from_email, &identity.reply_to,
+                    &identity.signature_id, &identity.envelope_from, &identity.id
+                ],
+            )
+            .map_err(|e| Error::Database(format!("Failed to update identity: {}", e)))?;
+        Ok(())
+    }
+
+    /// Delete an identity
+    pub fn delete_identity(&self, identity_id: &str) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM identities WHERE id = ?1", params![identity_id])
+            .map_err(|e| Error::Database(format!("Failed to delete identity: {}", e)))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::message_cache::Signature;
+    use std::env;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn test_identity_operations() {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_identities_{}", nanos));
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+
+        cache.create_signature(&Signature {
+            id: "sig-support".to_string(), account_id: "acct".to_string(),
+            name: "Support".to_string(), content_plain: "The support team".to_string(),
+            content_html: None, is_default: false, created_at: chrono::Utc::now().to_rfc3339(),
+        }).unwrap();
+        let identity = Identity {
+            id: "id-support".to_string(), account_id: "acct".to_string(),
+            from_name: "Example Support".to_string(), from_email: "support@example.com".to_string(),
+            reply_to: Some("help@example.com".to_string()), signature_id: Some("sig-support".to_string()),
+            envelope_from: None, created_at: chrono::Utc::now().to_rfc3339(),
+        };
+        cache.create_identity(&identity).unwrap();
+        cache.create_identity(&Identity {
+            id: "id-billing".to_string(), from_email: "billing@example.com".to_string(), ..identity.clone()
+        }).unwrap();
+        // One identity per address
+        assert!(cache.create_identity(&Identity { id: "id-dup".to_string(), ..identity.clone() }).is_err());
+
+        let emails: Vec<String> = cache
+            .get_identities_for_account("acct").unwrap()
+            .into_iter().map(|i| i.from_email).collect();
+        assert_eq!(emails, ["billing@example.com", "support@example.com"]);
+
+        let mut updated = identity.clone();
+        updated.reply_to = None;
+        updated.envelope_from = Some("bounces@example.com".to_string());
+        cache.update_identity(&updated).unwrap();
+        let stored = cache.get_identity("id-support").unwrap().unwrap();
+        assert_eq!(stored.reply_to, None);
+        assert_eq!(stored.envelope_from.as_deref(), Some("bounces@example.com"));
+
+        // Deleting the signature leaves the identity without one
+        cache.delete_signature("sig-support").unwrap();
+        assert_eq!(cache.get_identity("id-support").unwrap().unwrap().signature_id, None);
+
+        cache.delete_identity("id-support").unwrap();
+        assert!(cache.get_identity("id-support").unwrap().is_none());
+    }
+}
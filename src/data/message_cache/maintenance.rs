@@ -0,0 +1,257 @@
+//! Cache size reporting, pruning and health checks
+
+use super::{parse_message_date, MessageCache, PrunePolicy};
+use crate::common::{Error, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::params;
+use std::path::Path;
+
+impl MessageCache {
+    /// Size of the cache database in bytes, counting writes still waiting
+    /// in the write-ahead log
+    pub fn cache_size_bytes(&self) -> Result<u64> {
+        let pragma = |name: &str| -> Result<u64> {
+            self.conn
+                .query_row(&format!("PRAGMA {}", name), [], |row| row.get::<_, i64>(0))
+                .map(|v| v.max(0) as u64)
+                .map_err(|e| Error::Database(format!("Failed to read {}: {}", name, e)))
+        };
+        let mut wal = self.path.clone().into_os_string();
+        wal.push("-wal");
+        let wal_bytes = std::fs::metadata(wal).map(|m| m.len()).unwrap_or(0);
+        Ok(pragma("page_count")? * pragma("page_size")? + wal_bytes)
+    }
+
+    /// Location of the cache database
+    pub fn database_path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Schema version recorded in the database; 0 before versions were kept
+    pub fn schema_version(&self) -> Result<i32> {
+        super::migrations::current_version(&self.conn)
+    }
+
+    /// Problems SQLite finds in the database file; empty when it is sound
+    pub fn integrity_problems(&self) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("PRAGMA quick_check")
+            .map_err(|e| Error::Database(format!("Failed to prepare integrity check: {}", e)))?;
+        let rows = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(|e| Error::Database(format!("Failed to check integrity: {}", e)))?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::Database(format!("Failed to collect integrity results: {}", e)))?;
+        Ok(rows.into_iter().filter(|row| row != "ok").collect())
+    }
+
+    /// Drop cached bodies for an account according to `policy`.
+    ///
+    /// Headers are kept so folders still list; bodies are downloaded again
+    /// when opened. Folders kept for offline use are left alone. Returns the
+    /// number of bodies removed; the caller reclaims the space afterwards
+    /// with [`MessageCache::compact`] or [`MessageCache::optimize`].
+    pub fn prune(&self, account_id: &str, policy: &PrunePolicy) -> Result<usize> {
+        self.in_transaction(|c| {
+            let mut removed = 0;
+            if let Some(kb) = policy.max_body_kb {
+                removed += c.conn
+                    .execute(
+                        "UPDATE messages SET body_plain = NULL, body_html = NULL, raw_source = NULL
+                         WHERE folder_id IN (SELECT id FROM folders WHERE account_id = ?1 AND NOT COALESCE(offline_sync, 0))
+                           AND COALESCE(LENGTH(CAST(body_plain AS BLOB)), 0)
+                             + COALESCE(LENGTH(CAST(body_html AS BLOB)), 0) > ?2",
+                        params![account_id, kb as i64 * 1024],
+                    )
+                    .map_err(|e| Error::Database(format!("Failed to prune large bodies: {}", e)))?;
+            }
+            if let Some(days) = policy.keep_bodies_days {
+                let cutoff = c.now() - chrono::Duration::days(days as i64);
+                for id in c.bodies_older_than(account_id, cutoff)? {
+                    removed += c.conn
+                        .execute(
+                            "UPDATE messages SET body_plain = NULL, body_html = NULL, raw_source = NULL WHERE id = ?1",
+                            params![id],
+                        )
+                        .map_err(|e| Error::Database(format!("Failed to prune old bodies: {}", e)))?;
+                }
+            }
+            Ok(removed)
+        })
+    }
+
+    /// Reclaim free pages and refresh query planner statistics.
+    ///
+    /// SQLite can't compact inside a transaction, so call this once the
+    /// writes that freed the space have committed.
+    pub fn compact(&self) -> Result<()> {
+        self.conn
+            .execute_batch("VACUUM")
+            .map_err(|e| Error::Database(format!("Failed to compact cache: {}", e)))?;
+        self.optimize()
+    }
+
+    /// Refresh query planner statistics; a cheaper step than [`MessageCache::compact`].
+    pub fn optimize(&self) -> Result<()> {
+        self.conn
+            .execute_batch("PRAGMA optimize")
+            .map_err(|e| Error::Database(format!("Failed to optimize cache: {}", e)))
+    }
+
+    /// Ids of the account's messages with a cached body dated before `cutoff`,
+    /// outside offline folders. Messages whose date cannot be parsed are kept.
+    fn bodies_older_than(&self, account_id: &str, cutoff: DateTime<Utc>) -> Result<Vec<i64>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT m.id, m.date FROM messages m
+                 INNER JOIN folders f ON m.folder_id = f.id
+                 WHERE f.account_id = ?1 AND NOT COALESCE(f.offline_sync, 0)
+                   AND (m.body_plain IS NOT NULL OR m.body_html IS NOT NULL)",
+            )
+            .map_err(|e| Error::Database(format!("Failed to prepare statement: {}", e)))?;
+
+        let rows = stmt
+            .query_map(params![account_id], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))
+            .map_err(|e| Error::Database(format!("Failed to query message dates: {}", e)))?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::Database(format!("Failed to collect message dates: {}", e)))?;
+
+        Ok(rows
+            .into_iter()
+            .filter(|(_, date)| parse_message_date(date).is_some_and(|d| d < cutoff))
+            .map(|(id, _)| id)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::types::MessagePriority;
+    use crate::data::message_cache::{CachedFolder, CachedMessage, SCHEMA_VERSION};
+    use std::env;
+
+    #[test]
+    fn test_prune_policy() {
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_prune_{}", std::process::id()));
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+        let folder_id = cache.save_folder(&CachedFolder {
+            id: 0, account_id: "acc".to_string(),
+            name: "INBOX".to_string(), path: "INBOX".to_string(),
+            folder_type: "Inbox".to_string(), unread_count: 0, total_count: 0,
+        }).unwrap();
+        let recent = Utc::now().to_rfc2822();
+        let bodies = [
+            (1, "2001-01-01", "old".to_string()),
+            (2, recent.as_str(), "x".repeat(4096)),
+            (3, recent.as_str(), "small".to_string()),
+            (4, "sometime", "undated".to_string()),
+        ];
+        for (uid, date, body) in &bodies {
+            cache.save_message(&CachedMessage {
+                id: 0, uid: *uid, folder_id,
+                message_id: format!("m{}@example.com", uid), subject: "s".to_string(),
+                from_addr: "a@example.com".to_string(), to_addr: String::new(),
+                cc: None, date: date.to_string(),
+                body_plain: Some(body.clone()), body_html: None,
+                read: false, starred: false, deleted: false, priority: MessagePriority::Normal,
+            }).unwrap();
+        }
+        assert!(cache.cache_size_bytes().unwrap() > 0);
+
+        let policy = PrunePolicy { keep_bodies_days: Some(30), max_body_kb: Some(2), vacuum: true };
+        assert_eq!(cache.prune("other", &policy).unwrap(), 0);
+        assert_eq!(cache.prune("acc", &policy).unwrap(), 2);
+
+        let kept: Vec<u32> = [1, 2, 3, 4]
+            .into_iter()
+            .filter(|uid| cache.get_message_body(folder_id, *uid).unwrap().is_some())
+            .collect();
+        assert_eq!(kept, vec![3, 4]);
+        assert_eq!(cache.prune("acc", &policy).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_prune_skips_offline_folders() {
+        let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos();
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_prune_offline_{}", nanos));
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+        let inbox = cache.ensure_folder("acc", "INBOX").unwrap();
+        let offline = cache.ensure_folder("acc", "Projects").unwrap();
+        cache.set_folder_offline_sync(offline.id, true).unwrap();
+        for folder_id in [inbox.id, offline.id] {
+            cache.save_message(&CachedMessage {
+                id: 0, uid: 1, folder_id,
+                message_id: format!("old-{}@example.com", folder_id), subject: "s".to_string(),
+                from_addr: "a@example.com".to_string(), to_addr: String::new(),
+                cc: None, date: "2001-01-01".to_string(),
+                body_plain: Some("x".repeat(4096)), body_html: None,
+                read: false, starred: false, deleted: false, priority: MessagePriority::Normal,
+            }).unwrap();
+        }
+
+        // Old and large bodies both go, but only outside the offline folder
+        for policy in [
+            PrunePolicy { keep_bodies_days: Some(30), max_body_kb: None, vacuum: false },
+            PrunePolicy { keep_bodies_days: None, max_body_kb: Some(2), vacuum: false },
+        ] {
+            cache.prune("acc", &policy).unwrap();
+            assert!(cache.get_message_body(offline.id, 1).unwrap().is_some());
+        }
+        assert!(cache.get_message_body(inbox.id, 1).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_cache_size_counts_write_ahead_log() {
+        let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos();
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_cache_size_{}", nanos));
+        let cache = MessageCache::new(temp_dir.clone(), None).unwrap();
+        let folder_id = cache.ensure_folder("acc", "INBOX").unwrap().id;
+        cache.save_message(&CachedMessage {
+            id: 0, uid: 1, folder_id,
+            message_id: "big@example.com".to_string(), subject: "s".to_string(),
+            from_addr: "a@example.com".to_string(), to_addr: String::new(),
+            cc: None, date: "2024-01-01".to_string(),
+            body_plain: Some("x".repeat(64 * 1024)), body_html: None,
+            read: false, starred: false, deleted: false, priority: MessagePriority::Normal,
+        }).unwrap();
+
+        let wal = std::fs::metadata(temp_dir.join("message_cache.db-wal")).unwrap().len();
+        let pages: i64 = cache.conn.query_row("PRAGMA page_count", [], |row| row.get(0)).unwrap();
+        let page_size: i64 = cache.conn.query_row("PRAGMA page_size", [], |row| row.get(0)).unwrap();
+        assert!(wal > 0);
+        assert_eq!(cache.cache_size_bytes().unwrap(), (pages * page_size) as u64 + wal);
+    }
+
+    #[test]
+    fn test_compact_after_clearing_account_in_transaction() {
+        let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos();
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_clear_account_{}", nanos));
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+        cache.ensure_folder("acc", "INBOX").unwrap();
+
+        cache.in_transaction(|c| c.clear_account_cache("acc")).unwrap();
+        assert!(cache.in_transaction(|c| c.compact()).is_err());
+        cache.compact().unwrap();
+        assert!(cache.get_folder("acc", "INBOX").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_schema_version_and_integrity() {
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_schema_{}", std::process::id()));
+        let cache = MessageCache::new(temp_dir.clone(), None).unwrap();
+        assert_eq!(cache.schema_version().unwrap(), SCHEMA_VERSION);
+        assert!(cache.integrity_problems().unwrap().is_empty());
+        assert_eq!(cache.database_path(), temp_dir.join("message_cache.db"));
+
+        // A database from a newer version keeps its number
+        cache.conn
+            .execute("INSERT INTO schema_version (version, description, applied_at) VALUES (99, 'Later', '')", [])
+            .unwrap();
+        drop(cache);
+        let reopened = MessageCache::new(temp_dir, None).unwrap();
+        assert_eq!(reopened.schema_version().unwrap(), 99);
+    }
+}
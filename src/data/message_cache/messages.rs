@@ -1,36 +1,129 @@
 //! Message persistence operations
 
-use super::{CachedMessage, MessageCache};
+use super::{parse_message_date, CachedMessage, InboxMessage, MessageCache, ReceiptStatus};
+use crate::common::types::MessagePriority;
 use crate::common::{Error, Result};
 use rusqlite::{params, OptionalExtension};
+use std::collections::HashMap;
+
+/// Reply/forward prefixes stripped when grouping messages into threads.
+const THREAD_PREFIXES: &[&str] = &["re", "fw", "fwd", "aw", "sv", "wg"];
+
+/// Conversation key for a subject: lowercased with reply/forward prefixes
+/// (`Re:`, `Fwd:`, `Re[2]:` ...) removed.
+pub fn thread_key(subject: &str) -> String {
+    let mut rest = subject.trim();
+    while let Some(colon) = rest.find(':') {
+        let prefix = rest[..colon].trim();
+        let word = prefix.split('[').next().unwrap_or(prefix).trim().to_lowercase();
+        if !THREAD_PREFIXES.contains(&word.as_str()) {
+            break;
+        }
+        rest = rest[colon + 1..].trim_start();
+    }
+    rest.to_lowercase()
+}
 
 impl MessageCache {
     /// Save a message to cache
     pub fn save_message(&self, msg: &CachedMessage) -> Result<i64> {
-        self.conn.execute(
+        let mut stmt = self.conn.prepare_cached(
             "INSERT OR REPLACE INTO messages
-             (uid, folder_id, message_id, subject, from_addr, to_addr, cc, date, body_plain, body_html, read, starred, deleted)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+             (uid, folder_id, message_id, subject, from_addr, to_addr, cc, date, body_plain, body_html, read, starred, deleted, priority)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+        ).map_err(|e| Error::Database(format!("Failed to prepare message insert: {}", e)))?;
+        stmt.execute(
             params![
                 msg.uid,
                 msg.folder_id,
                 msg.message_id,
-                msg.subject,
+                self.seal_subject(&msg.subject)?,
                 msg.from_addr,
                 msg.to_addr,
                 msg.cc,
                 msg.date,
-                msg.body_plain,
-                msg.body_html,
+                self.seal_body(msg.body_plain.as_deref())?,
+                self.seal_body(msg.body_html.as_deref())?,
                 msg.read,
                 msg.starred,
                 msg.deleted,
+                msg.priority.as_str(),
             ],
-        ).map_err(|e| Error::Other(format!("Failed to save message: {}", e)))?;
+        ).map_err(|e| Error::Database(format!("Failed to save message: {}", e)))?;
 
         Ok(self.conn.last_insert_rowid())
     }
 
+    /// Save many messages in one transaction, returning their ids in order.
+    /// Either every message is saved or, on error, none are.
+    pub fn save_messages(&self, msgs: &[CachedMessage]) -> Result<Vec<i64>> {
+        self.in_transaction(|c| msgs.iter().map(|msg| c.save_message(msg)).collect())
+    }
+
+    /// Insert or refresh a message's headers and flags, keeping any cached
+    /// body. A header sync that didn't fetch the recipients or Message-ID
+    /// leaves the cached ones alone.
+    pub fn save_message_header(&self, msg: &CachedMessage) -> Result<()> {
+        let mut stmt = self.conn.prepare_cached(
+            "INSERT INTO messages
+             (uid, folder_id, message_id, subject, from_addr, to_addr, cc, date, read, starred, priority)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+             ON CONFLICT(folder_id, uid) DO UPDATE SET
+                message_id = COALESCE(NULLIF(excluded.message_id, ''), messages.message_id),
+                subject = excluded.subject, from_addr = excluded.from_addr,
+                to_addr = COALESCE(NULLIF(excluded.to_addr, ''), messages.to_addr),
+                cc = COALESCE(NULLIF(excluded.cc, ''), messages.cc), date = excluded.date,
+                read = excluded.read, starred = excluded.starred",
+        ).map_err(|e| Error::Database(format!("Failed to prepare message header insert: {}", e)))?;
+        stmt.execute(
+            params![
+                msg.uid,
+                msg.folder_id,
+                msg.message_id,
+                self.seal_subject(&msg.subject)?,
+                msg.from_addr,
+                msg.to_addr,
+                msg.cc,
+                msg.date,
+                msg.read,
+                msg.starred,
+                msg.priority.as_str(),
+            ],
+        ).map_err(|e| Error::Database(format!("Failed to save message header: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// [`MessageCache::save_message_header`] for many messages in one
+    /// transaction; on error none are saved
+    pub fn save_message_headers(&self, msgs: &[CachedMessage]) -> Result<()> {
+        self.in_transaction(|c| msgs.iter().try_for_each(|msg| c.save_message_header(msg)))
+    }
+
+    /// Whether a message with this Message-ID is already cached in the folder
+    pub fn has_message_id(&self, folder_id: i64, message_id: &str) -> Result<bool> {
+        self.conn
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM messages WHERE folder_id = ?1 AND message_id = ?2)",
+                params![folder_id, message_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| Error::Database(format!("Failed to look up message: {}", e)))
+    }
+
+    /// Next unused UID in a folder, for messages that did not come from the server
+    pub fn next_local_uid(&self, folder_id: i64) -> Result<u32> {
+        let max: i64 = self
+            .conn
+            .query_row(
+                "SELECT COALESCE(MAX(uid), 0) FROM messages WHERE folder_id = ?1",
+                params![folder_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| Error::Database(format!("Failed to read max UID: {}", e)))?;
+        Ok(max as u32 + 1)
+    }
+
     /// Get messages for a folder scoped to an account
     pub fn get_messages_for_folder(
         &self,
@@ -39,12 +132,12 @@ impl MessageCache {
     ) -> Result<Vec<CachedMessage>> {
         let mut stmt = self.conn.prepare(
             "SELECT m.id, m.uid, m.folder_id, m.message_id, m.subject, m.from_addr, m.to_addr, m.cc, m.date,
-                    m.body_plain, m.body_html, m.read, m.starred, m.deleted
+                    m.body_plain, m.body_html, m.read, m.starred, m.deleted, m.priority
              FROM messages m
              INNER JOIN folders f ON m.folder_id = f.id
              WHERE m.folder_id = ?1 AND f.account_id = ?2 AND m.deleted = 0
              ORDER BY m.date DESC"
-        ).map_err(|e| Error::Other(format!("Failed to prepare statement: {}", e)))?;
+        ).map_err(|e| Error::Database(format!("Failed to prepare statement: {}", e)))?;
 
         let messages = stmt
             .query_map(params![folder_id, account_id], |row| {
@@ -63,12 +156,112 @@ impl MessageCache {
                     read: row.get(11)?,
                     starred: row.get(12)?,
                     deleted: row.get(13)?,
+                    priority: MessagePriority::parse(&row.get::<_, String>(14)?),
+                })
+            })
+            .map_err(|e| Error::Database(format!("Failed to query messages: {}", e)))?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::Database(format!("Failed to collect messages: {}", e)))?;
+
+        self.open_messages(messages)
+    }
+
+    /// Cached Inbox messages of every enabled account, newest first, each
+    /// tagged with its account for the All Inboxes view.
+    pub fn get_messages_for_all_accounts_inbox(&self) -> Result<Vec<InboxMessage>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT m.id, m.uid, m.folder_id, m.message_id, m.subject, m.from_addr, m.to_addr, m.cc, m.date,
+                    m.body_plain, m.body_html, m.read, m.starred, m.deleted, m.priority, a.id, a.color
+             FROM messages m
+             INNER JOIN folders f ON m.folder_id = f.id
+             INNER JOIN accounts a ON f.account_id = a.id
+             WHERE UPPER(f.path) = 'INBOX' AND a.enabled = 1 AND m.deleted = 0
+             ORDER BY m.date DESC"
+        ).map_err(|e| Error::Database(format!("Failed to prepare statement: {}", e)))?;
+
+        let messages = stmt
+            .query_map([], |row| {
+                Ok(InboxMessage {
+                    account_id: row.get(15)?,
+                    account_color: row.get(16)?,
+                    message: CachedMessage {
+                        id: row.get(0)?,
+                        uid: row.get(1)?,
+                        folder_id: row.get(2)?,
+                        message_id: row.get(3)?,
+                        subject: row.get(4)?,
+                        from_addr: row.get(5)?,
+                        to_addr: row.get(6)?,
+                        cc: row.get(7)?,
+                        date: row.get(8)?,
+                        body_plain: row.get(9)?,
+                        body_html: row.get(10)?,
+                        read: row.get(11)?,
+                        starred: row.get(12)?,
+                        deleted: row.get(13)?,
+                        priority: MessagePriority::parse(&row.get::<_, String>(14)?),
+                    },
+                })
+            })
+            .map_err(|e| Error::Database(format!("Failed to query messages: {}", e)))?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::Database(format!("Failed to collect messages: {}", e)))?;
+
+        messages
+            .into_iter()
+            .map(|m| Ok(InboxMessage { message: self.open_message(m.message)?, ..m }))
+            .collect()
+    }
+
+    /// All messages in an account's conversation, oldest first, across
+    /// folders (so replies in Sent are included).
+    ///
+    /// `thread_id` is the normalized subject from [`thread_key`].
+    pub fn get_thread_messages(&self, account_id: &str, thread_id: &str) -> Result<Vec<CachedMessage>> {
+        let pattern = format!(
+            "%{}%",
+            thread_id.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+        );
+        let mut stmt = self.conn.prepare(
+            "SELECT m.id, m.uid, m.folder_id, m.message_id, m.subject, m.from_addr, m.to_addr, m.cc, m.date,
+                    m.body_plain, m.body_html, m.read, m.starred, m.deleted, m.priority
+             FROM messages m
+             INNER JOIN folders f ON m.folder_id = f.id
+             WHERE f.account_id = ?1 AND m.deleted = 0
+               AND (m.subject LIKE ?2 ESCAPE '\\' OR m.subject LIKE 'WXM2:%')"
+        ).map_err(|e| Error::Database(format!("Failed to prepare statement: {}", e)))?;
+
+        let messages = stmt
+            .query_map(params![account_id, pattern], |row| {
+                Ok(CachedMessage {
+                    id: row.get(0)?,
+                    uid: row.get(1)?,
+                    folder_id: row.get(2)?,
+                    message_id: row.get(3)?,
+                    subject: row.get(4)?,
+                    from_addr: row.get(5)?,
+                    to_addr: row.get(6)?,
+                    cc: row.get(7)?,
+                    date: row.get(8)?,
+                    body_plain: row.get(9)?,
+                    body_html: row.get(10)?,
+                    read: row.get(11)?,
+                    starred: row.get(12)?,
+                    deleted: row.get(13)?,
+                    priority: MessagePriority::parse(&row.get::<_, String>(14)?),
                 })
             })
-            .map_err(|e| Error::Other(format!("Failed to query messages: {}", e)))?
+            .map_err(|e| Error::Database(format!("Failed to query messages: {}", e)))?
             .collect::<std::result::Result<Vec<_>, _>>()
-            .map_err(|e| Error::Other(format!("Failed to collect messages: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to collect messages: {}", e)))?;
 
+        // Encrypted subjects can't be matched in SQL, so they are all read
+        let mut messages = self.open_messages(messages)?;
+        messages.retain(|m| thread_key(&m.subject) == thread_id);
+        // The same message can be cached in two folders (e.g. imported twice)
+        let mut seen = std::collections::HashSet::new();
+        messages.retain(|m| m.message_id.is_empty() || seen.insert(m.message_id.clone()));
+        messages.sort_by_key(|m| parse_message_date(&m.date));
         Ok(messages)
     }
 
@@ -78,10 +271,10 @@ impl MessageCache {
             .conn
             .prepare(
                 "SELECT id, uid, folder_id, message_id, subject, from_addr, to_addr, cc, date,
-                    body_plain, body_html, read, starred, deleted
+                    body_plain, body_html, read, starred, deleted, priority
              FROM messages WHERE id = ?1",
             )
-            .map_err(|e| Error::Other(format!("Failed to prepare statement: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to prepare statement: {}", e)))?;
 
         let message = stmt
             .query_row(params![message_id], |row| {
@@ -100,12 +293,90 @@ impl MessageCache {
                     read: row.get(11)?,
                     starred: row.get(12)?,
                     deleted: row.get(13)?,
+                    priority: MessagePriority::parse(&row.get::<_, String>(14)?),
                 })
             })
             .optional()
-            .map_err(|e| Error::Other(format!("Failed to get message: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to get message: {}", e)))?;
+
+        message.map(|m| self.open_message(m)).transpose()
+    }
+
+    /// Find a message in any of an account's folders by its `Message-ID`
+    /// header, stored with or without angle brackets.
+    pub fn find_message_by_message_id(&self, account_id: &str, message_id: &str) -> Result<Option<CachedMessage>> {
+        let message_id = message_id.trim_matches(|c| c == '<' || c == '>');
+        self.conn
+            .query_row(
+                "SELECT m.id, m.uid, m.folder_id, m.message_id, m.subject, m.from_addr, m.to_addr, m.cc, m.date,
+                        m.body_plain, m.body_html, m.read, m.starred, m.deleted, m.priority
+                 FROM messages m
+                 INNER JOIN folders f ON m.folder_id = f.id
+                 WHERE f.account_id = ?1 AND m.deleted = 0 AND m.message_id IN (?2, '<' || ?2 || '>')
+                 ORDER BY m.id LIMIT 1",
+                params![account_id, message_id],
+                |row| {
+                    Ok(CachedMessage {
+                        id: row.get(0)?,
+                        uid: row.get(1)?,
+                        folder_id: row.get(2)?,
+                        message_id: row.get(3)?,
+                        subject: row.get(4)?,
+                        from_addr: row.get(5)?,
+                        to_addr: row.get(6)?,
+                        cc: row.get(7)?,
+                        date: row.get(8)?,
+                        body_plain: row.get(9)?,
+                        body_html: row.get(10)?,
+                        read: row.get(11)?,
+                        starred: row.get(12)?,
+                        deleted: row.get(13)?,
+                        priority: MessagePriority::parse(&row.get::<_, String>(14)?),
+                    })
+                },
+            )
+            .optional()
+            .map_err(|e| Error::Database(format!("Failed to look up message: {}", e)))?
+            .map(|m| self.open_message(m))
+            .transpose()
+    }
+
+    /// Cached body for a message keyed by folder and UID, preferring HTML.
+    ///
+    /// Returns `None` when the message or its body has not been downloaded yet.
+    pub fn get_message_body(&self, folder_id: i64, uid: u32) -> Result<Option<String>> {
+        let body: Option<(Option<String>, Option<String>)> = self
+            .conn
+            .query_row(
+                "SELECT body_html, body_plain FROM messages WHERE folder_id = ?1 AND uid = ?2",
+                params![folder_id, uid],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(|e| Error::Database(format!("Failed to get message body: {}", e)))?;
 
-        Ok(message)
+        body.and_then(|(html, plain)| html.or(plain)).map(|b| self.open_text(b)).transpose()
+    }
+
+    /// Store a downloaded body on the cached message row for folder and UID.
+    ///
+    /// Returns `false` when no such message is cached.
+    pub fn save_message_body(
+        &self,
+        folder_id: i64,
+        uid: u32,
+        body_plain: Option<&str>,
+        body_html: Option<&str>,
+    ) -> Result<bool> {
+        let updated = self
+            .conn
+            .execute(
+                "UPDATE messages SET body_plain = ?1, body_html = ?2 WHERE folder_id = ?3 AND uid = ?4",
+                params![self.seal_body(body_plain)?, self.seal_body(body_html)?, folder_id, uid],
+            )
+            .map_err(|e| Error::Database(format!("Failed to save message body: {}", e)))?;
+
+        Ok(updated > 0)
     }
 
     /// Update message flags
@@ -115,7 +386,109 @@ impl MessageCache {
                 "UPDATE messages SET read = ?1, starred = ?2 WHERE id = ?3",
                 params![read, starred, message_id],
             )
-            .map_err(|e| Error::Other(format!("Failed to update flags: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to update flags: {}", e)))?;
+
+        self.recompute_counts_for_message(message_id)
+    }
+
+    /// Set only the read flag on a message
+    pub fn set_message_read(&self, message_id: i64, read: bool) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE messages SET read = ?1 WHERE id = ?2",
+                params![read, message_id],
+            )
+            .map_err(|e| Error::Database(format!("Failed to update read flag: {}", e)))?;
+
+        self.recompute_counts_for_message(message_id)
+    }
+
+    /// Record whether a read receipt was requested, sent or declined
+    pub fn set_receipt_status(&self, message_id: i64, status: ReceiptStatus) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE messages SET receipt_status = ?1 WHERE id = ?2",
+                params![status.as_str(), message_id],
+            )
+            .map_err(|e| Error::Database(format!("Failed to update receipt status: {}", e)))?;
+        Ok(())
+    }
+
+    /// Record the priority found once a message's headers are downloaded
+    pub fn set_message_priority(&self, message_id: i64, priority: MessagePriority) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE messages SET priority = ?1 WHERE id = ?2",
+                params![priority.as_str(), message_id],
+            )
+            .map_err(|e| Error::Database(format!("Failed to update priority: {}", e)))?;
+        Ok(())
+    }
+
+    /// Record the mailing list (`List-Id`) a message came from, found once
+    /// its headers are downloaded
+    pub fn set_list_id(&self, folder_id: i64, uid: u32, list_id: Option<&str>) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE messages SET list_id = ?1 WHERE folder_id = ?2 AND uid = ?3",
+                params![list_id, folder_id, uid],
+            )
+            .map_err(|e| Error::Database(format!("Failed to update list id: {}", e)))?;
+        Ok(())
+    }
+
+    /// Mailing list of each message in the folder that came from one, by UID
+    pub fn get_list_ids(&self, folder_id: i64) -> Result<HashMap<u32, String>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached("SELECT uid, list_id FROM messages WHERE folder_id = ?1 AND list_id IS NOT NULL")
+            .map_err(|e| Error::Database(format!("Failed to prepare list id query: {}", e)))?;
+        let rows = stmt
+            .query_map(params![folder_id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| Error::Database(format!("Failed to get list ids: {}", e)))?;
+        rows.collect::<std::result::Result<_, _>>()
+            .map_err(|e| Error::Database(format!("Failed to read list id: {}", e)))
+    }
+
+    /// Read receipt state of a message, `None` if no receipt was requested
+    pub fn get_receipt_status(&self, message_id: i64) -> Result<Option<ReceiptStatus>> {
+        let status: Option<Option<String>> = self
+            .conn
+            .query_row("SELECT receipt_status FROM messages WHERE id = ?1", params![message_id], |row| row.get(0))
+            .optional()
+            .map_err(|e| Error::Database(format!("Failed to read receipt status: {}", e)))?;
+        Ok(status.flatten().as_deref().and_then(ReceiptStatus::parse))
+    }
+
+    /// Keep the full raw message (headers and MIME) for the source viewer
+    pub fn save_raw_source(&self, message_id: i64, raw: &str) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE messages SET raw_source = ?1 WHERE id = ?2",
+                params![self.seal_body(Some(raw))?, message_id],
+            )
+            .map_err(|e| Error::Database(format!("Failed to save message source: {}", e)))?;
+        Ok(())
+    }
+
+    /// Cached raw message, `None` if it was never kept
+    pub fn get_raw_source(&self, message_id: i64) -> Result<Option<String>> {
+        let raw: Option<Option<String>> = self
+            .conn
+            .query_row("SELECT raw_source FROM messages WHERE id = ?1", params![message_id], |row| row.get(0))
+            .optional()
+            .map_err(|e| Error::Database(format!("Failed to read message source: {}", e)))?;
+        raw.flatten().map(|r| self.open_text(r)).transpose()
+    }
+
+    /// Set only the starred flag on a message
+    pub fn set_message_starred(&self, message_id: i64, starred: bool) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE messages SET starred = ?1 WHERE id = ?2",
+                params![starred, message_id],
+            )
+            .map_err(|e| Error::Database(format!("Failed to update starred flag: {}", e)))?;
 
         Ok(())
     }
@@ -127,19 +500,107 @@ impl MessageCache {
                 "UPDATE messages SET deleted = 1 WHERE id = ?1",
                 params![message_id],
             )
-            .map_err(|e| Error::Other(format!("Failed to delete message: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to delete message: {}", e)))?;
 
-        Ok(())
+        self.recompute_counts_for_message(message_id)
     }
 
-    /// Clear cache for an account
+    /// Restore a previously deleted message with its original flags
+    pub fn restore_message(&self, message_id: i64, read: bool, starred: bool) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE messages SET deleted = 0, read = ?1, starred = ?2 WHERE id = ?3",
+                params![read, starred, message_id],
+            )
+            .map_err(|e| Error::Database(format!("Failed to restore message: {}", e)))?;
+
+        self.recompute_counts_for_message(message_id)
+    }
+
+    /// Move a message to another cached folder
+    pub fn move_message(&self, message_id: i64, folder_id: i64) -> Result<()> {
+        let from_folder = self.get_message(message_id)?.map(|m| m.folder_id);
+        self.conn
+            .execute(
+                "UPDATE messages SET folder_id = ?1 WHERE id = ?2",
+                params![folder_id, message_id],
+            )
+            .map_err(|e| Error::Database(format!("Failed to move message: {}", e)))?;
+
+        if let Some(from_folder) = from_folder {
+            self.recompute_folder_counts(from_folder)?;
+        }
+        self.recompute_folder_counts(folder_id)
+    }
+
+    /// Move a message to Trash, remembering where it came from so it can be
+    /// restored later
+    pub fn trash_message(&self, message_id: i64, trash_folder_id: i64) -> Result<()> {
+        let from_folder = self.get_message(message_id)?.map(|m| m.folder_id);
+        self.conn
+            .execute(
+                "UPDATE messages SET original_folder_id = folder_id, folder_id = ?1 WHERE id = ?2",
+                params![trash_folder_id, message_id],
+            )
+            .map_err(|e| Error::Database(format!("Failed to move message to trash: {}", e)))?;
+
+        if let Some(from_folder) = from_folder {
+            self.recompute_folder_counts(from_folder)?;
+        }
+        self.recompute_folder_counts(trash_folder_id)
+    }
+
+    /// Move a trashed message back to the folder it was deleted from.
+    ///
+    /// Returns the path of that folder, or `None` when the message has no
+    /// recorded origin.
+    pub fn restore_from_trash(&self, message_id: i64) -> Result<Option<String>> {
+        let origin: Option<(i64, String)> = self
+            .conn
+            .query_row(
+                "SELECT f.id, f.path FROM messages m JOIN folders f ON f.id = m.original_folder_id
+                 WHERE m.id = ?1",
+                params![message_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(|e| Error::Database(format!("Failed to look up original folder: {}", e)))?;
+        let Some((folder_id, path)) = origin else {
+            return Ok(None);
+        };
+        self.move_message(message_id, folder_id)?;
+        self.conn
+            .execute(
+                "UPDATE messages SET original_folder_id = NULL WHERE id = ?1",
+                params![message_id],
+            )
+            .map_err(|e| Error::Database(format!("Failed to restore message: {}", e)))?;
+
+        Ok(Some(path))
+    }
+
+    /// Permanently remove every cached message in a folder (Empty Trash).
+    ///
+    /// Returns the number of messages removed.
+    pub fn empty_folder(&self, folder_id: i64) -> Result<usize> {
+        let removed = self
+            .conn
+            .execute("DELETE FROM messages WHERE folder_id = ?1", params![folder_id])
+            .map_err(|e| Error::Database(format!("Failed to empty folder: {}", e)))?;
+        self.recompute_folder_counts(folder_id)?;
+
+        Ok(removed)
+    }
+
+    /// Clear cache for an account. Safe inside a transaction; run
+    /// [`MessageCache::compact`] after it commits to give the space back.
     pub fn clear_account_cache(&self, account_id: &str) -> Result<()> {
         self.conn
             .execute(
                 "DELETE FROM folders WHERE account_id = ?1",
                 params![account_id],
             )
-            .map_err(|e| Error::Other(format!("Failed to clear cache: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to clear cache: {}", e)))?;
 
         Ok(())
     }
@@ -182,6 +643,7 @@ mod tests {
             read: false,
             starred: false,
             deleted: false,
+            priority: MessagePriority::Normal,
         };
 
         let msg_id = cache.save_message(&message).unwrap();
@@ -194,6 +656,58 @@ mod tests {
         assert_eq!(messages[0].subject, "Test Subject");
     }
 
+    #[test]
+    fn test_message_body_cache() {
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_body_{}", std::process::id()));
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+        let folder_id = cache.save_folder(&CachedFolder {
+            id: 0, account_id: "acc".to_string(),
+            name: "INBOX".to_string(), path: "INBOX".to_string(),
+            folder_type: "Inbox".to_string(), unread_count: 0, total_count: 0,
+        }).unwrap();
+        cache.save_message(&CachedMessage {
+            id: 0, uid: 7, folder_id,
+            message_id: "m7@example.com".to_string(), subject: "Hi".to_string(),
+            from_addr: "a@example.com".to_string(), to_addr: "b@example.com".to_string(),
+            cc: None, date: "2024-01-01".to_string(),
+            body_plain: None, body_html: None,
+            read: false, starred: false, deleted: false, priority: MessagePriority::Normal,
+        }).unwrap();
+
+        assert_eq!(cache.get_message_body(folder_id, 7).unwrap(), None);
+        assert!(cache.save_message_body(folder_id, 7, Some("plain"), None).unwrap());
+        assert_eq!(cache.get_message_body(folder_id, 7).unwrap().as_deref(), Some("plain"));
+        assert!(cache.save_message_body(folder_id, 7, Some("plain"), Some("<p>html</p>")).unwrap());
+        assert_eq!(cache.get_message_body(folder_id, 7).unwrap().as_deref(), Some("<p>html</p>"));
+        assert!(!cache.save_message_body(folder_id, 8, Some("x"), None).unwrap());
+
+        // Bounces name the original by Message-ID, usually in angle brackets
+        assert_eq!(cache.find_message_by_message_id("acc", "<m7@example.com>").unwrap().map(|m| m.uid), Some(7));
+        assert!(cache.find_message_by_message_id("other", "m7@example.com").unwrap().is_none());
+
+        // Refreshing headers during a sync keeps the downloaded body
+        let mut header = cache.get_messages_for_folder(folder_id, "acc").unwrap().remove(0);
+        header.subject = "Hi again".to_string();
+        header.body_html = None;
+        cache.save_message_header(&header).unwrap();
+        let refreshed = cache.get_messages_for_folder(folder_id, "acc").unwrap().remove(0);
+        assert_eq!(refreshed.subject, "Hi again");
+        assert_eq!(refreshed.body_html.as_deref(), Some("<p>html</p>"));
+
+        // A sync that only fetched the envelope's sender keeps the cached
+        // recipients and Message-ID
+        let mut bare = refreshed.clone();
+        bare.message_id = String::new();
+        bare.to_addr = String::new();
+        bare.cc = Some(String::new());
+        cache.save_message_header(&CachedMessage { cc: Some("c@example.com".to_string()), ..refreshed }).unwrap();
+        cache.save_message_header(&bare).unwrap();
+        let kept = cache.get_messages_for_folder(folder_id, "acc").unwrap().remove(0);
+        assert_eq!(kept.message_id, "m7@example.com");
+        assert_eq!(kept.to_addr, "b@example.com");
+        assert_eq!(kept.cc.as_deref(), Some("c@example.com"));
+    }
+
     #[test]
     fn test_account_data_isolation() {
         use std::time::{SystemTime, UNIX_EPOCH};
@@ -224,7 +738,7 @@ mod tests {
             from_addr: "a1@example.com".to_string(), to_addr: "user@example.com".to_string(),
             cc: None, date: "2024-01-01".to_string(),
             body_plain: Some("Body 1".to_string()), body_html: None,
-            read: false, starred: false, deleted: false,
+            read: false, starred: false, deleted: false, priority: MessagePriority::Normal,
         };
         let msg2 = CachedMessage {
             id: 0, uid: 2, folder_id: folder2_id,
@@ -232,7 +746,7 @@ mod tests {
             from_addr: "a2@example.com".to_string(), to_addr: "user@example.com".to_string(),
             cc: None, date: "2024-01-01".to_string(),
             body_plain: Some("Body 2".to_string()), body_html: None,
-            read: false, starred: false, deleted: false,
+            read: false, starred: false, deleted: false, priority: MessagePriority::Normal,
         };
 
         cache.save_message(&msg1).unwrap();
@@ -245,4 +759,225 @@ mod tests {
         let messages_cross = cache.get_messages_for_folder(folder1_id, "acc-2").unwrap();
         assert!(messages_cross.is_empty());
     }
+
+    #[test]
+    fn test_thread_messages() {
+        assert_eq!(thread_key("Re: RE[2]: Fwd:  Lunch plans"), "lunch plans");
+        assert_eq!(thread_key("Agenda: Monday"), "agenda: monday");
+
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_threads_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+        let mut folder_ids = Vec::new();
+        for name in ["INBOX", "Sent"] {
+            folder_ids.push(cache.save_folder(&CachedFolder {
+                id: 0, account_id: "acc".to_string(), name: name.to_string(), path: name.to_string(),
+                folder_type: name.to_string(), unread_count: 0, total_count: 0,
+            }).unwrap());
+        }
+        let messages = [
+            (folder_ids[0], 1, "Lunch plans", "Mon, 1 Jan 2024 09:00:00 +0000"),
+            (folder_ids[1], 1, "Re: Lunch plans", "Mon, 1 Jan 2024 11:00:00 +0000"),
+            (folder_ids[0], 2, "RE: lunch plans", "Mon, 1 Jan 2024 10:00:00 +0000"),
+            (folder_ids[0], 3, "Lunch plans 100%", "Mon, 1 Jan 2024 12:00:00 +0000"),
+        ];
+        for (folder_id, uid, subject, date) in messages {
+            cache.save_message(&CachedMessage {
+                id: 0, uid, folder_id, message_id: format!("{}-{}@example.com", folder_id, uid),
+                subject: subject.to_string(), from_addr: "a@example.com".to_string(),
+                to_addr: "b@example.com".to_string(), cc: None, date: date.to_string(),
+                body_plain: None, body_html: None, read: true, starred: false, deleted: false, priority: MessagePriority::Normal,
+            }).unwrap();
+        }
+
+        let thread = cache.get_thread_messages("acc", &thread_key("Fwd: Lunch Plans")).unwrap();
+        let subjects: Vec<&str> = thread.iter().map(|m| m.subject.as_str()).collect();
+        assert_eq!(subjects, ["Lunch plans", "RE: lunch plans", "Re: Lunch plans"]);
+        assert_eq!(cache.get_thread_messages("acc", "lunch plans 100%").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_trash_restore_and_empty() {
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_trash_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+        let inbox = cache.ensure_folder("acc", "INBOX").unwrap().id;
+        let trash = cache.ensure_folder("acc", "Trash").unwrap().id;
+        let ids: Vec<i64> = (1..=2).map(|uid| {
+            cache.save_message(&CachedMessage {
+                id: 0, uid, folder_id: inbox, message_id: format!("t{}@example.com", uid),
+                subject: "Old news".to_string(), from_addr: "a@example.com".to_string(),
+                to_addr: String::new(), cc: None, date: "2024-01-01".to_string(),
+                body_plain: None, body_html: None, read: true, starred: false, deleted: false, priority: MessagePriority::Normal,
+            }).unwrap()
+        }).collect();
+
+        cache.trash_message(ids[0], trash).unwrap();
+        cache.trash_message(ids[1], trash).unwrap();
+        assert_eq!(cache.get_messages_for_folder(trash, "acc").unwrap().len(), 2);
+        assert!(cache.get_messages_for_folder(inbox, "acc").unwrap().is_empty());
+
+        assert_eq!(cache.restore_from_trash(ids[0]).unwrap().as_deref(), Some("INBOX"));
+        assert_eq!(cache.get_message(ids[0]).unwrap().unwrap().folder_id, inbox);
+        assert_eq!(cache.restore_from_trash(ids[0]).unwrap(), None);
+
+        assert_eq!(cache.empty_folder(trash).unwrap(), 1);
+        assert!(cache.get_message(ids[1]).unwrap().is_none());
+        assert_eq!(cache.get_folder("acc", "Trash").unwrap().unwrap().total_count, 0);
+    }
+
+    #[test]
+    fn test_all_accounts_inbox() {
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_all_inboxes_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+        for (id, color, enabled, day) in [("work", "#FF0000", true, 5), ("home", "#00AA00", true, 3), ("old", "#0000FF", false, 9)] {
+            let mut account = crate::data::account::Account::new(id.to_string(), format!("{}@example.com", id));
+            account.id = id.to_string();
+            account.color = color.to_string();
+            account.enabled = enabled;
+            cache.save_account(&account).unwrap();
+            let inbox = cache.ensure_folder(id, "INBOX").unwrap().id;
+            let sent = cache.ensure_folder(id, "Sent").unwrap().id;
+            for (uid, folder, date) in [(1, inbox, format!("2024-01-0{}", day)), (2, sent, "2024-02-01".to_string())] {
+                cache.save_message(&CachedMessage {
+                    id: 0, uid, folder_id: folder, message_id: format!("{}{}@example.com", id, uid),
+                    subject: format!("From {}", id), from_addr: "a@example.com".to_string(),
+                    to_addr: String::new(), cc: None, date,
+                    body_plain: None, body_html: None, read: false, starred: false, deleted: false, priority: MessagePriority::Normal,
+                }).unwrap();
+            }
+            cache.recompute_folder_counts(inbox).unwrap();
+        }
+
+        let merged = cache.get_messages_for_all_accounts_inbox().unwrap();
+        let tags: Vec<(&str, &str)> = merged.iter().map(|m| (m.account_id.as_str(), m.account_color.as_str())).collect();
+        assert_eq!(tags, vec![("work", "#FF0000"), ("home", "#00AA00")]);
+        assert!(merged.iter().all(|m| m.message.uid == 1));
+        assert_eq!(cache.get_all_inboxes_counts().unwrap(), (2, 2, 2));
+    }
+
+    #[test]
+    fn test_receipt_status() {
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_receipts_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+        let inbox = cache.ensure_folder("acc", "INBOX").unwrap().id;
+        let id = cache.save_message(&CachedMessage {
+            id: 0, uid: 1, folder_id: inbox, message_id: "r1@example.com".to_string(),
+            subject: "Minutes".to_string(), from_addr: "boss@example.com".to_string(),
+            to_addr: String::new(), cc: None, date: "2024-01-01".to_string(),
+            body_plain: None, body_html: None, read: false, starred: false, deleted: false, priority: MessagePriority::Normal,
+        }).unwrap();
+
+        assert_eq!(cache.get_receipt_status(id).unwrap(), None);
+        cache.set_receipt_status(id, ReceiptStatus::Requested).unwrap();
+        assert_eq!(cache.get_receipt_status(id).unwrap(), Some(ReceiptStatus::Requested));
+        cache.set_receipt_status(id, ReceiptStatus::Sent).unwrap();
+        assert_eq!(cache.get_receipt_status(id).unwrap(), Some(ReceiptStatus::Sent));
+        assert_eq!(cache.get_receipt_status(9999).unwrap(), None);
+
+        assert_eq!(cache.get_raw_source(id).unwrap(), None);
+        cache.save_raw_source(id, "Subject: Minutes\r\n\r\nHi").unwrap();
+        assert_eq!(cache.get_raw_source(id).unwrap().as_deref(), Some("Subject: Minutes\r\n\r\nHi"));
+    }
+
+    #[test]
+    fn test_message_priority() {
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_priority_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+        let inbox = cache.ensure_folder("acc", "INBOX").unwrap().id;
+        let mut msg = CachedMessage {
+            id: 0, uid: 1, folder_id: inbox, message_id: "p1@example.com".to_string(),
+            subject: "Outage".to_string(), from_addr: "ops@example.com".to_string(),
+            to_addr: String::new(), cc: None, date: "2024-01-01".to_string(),
+            body_plain: None, body_html: None, read: false, starred: false, deleted: false, priority: MessagePriority::High,
+        };
+        let id = cache.save_message(&msg).unwrap();
+        assert_eq!(cache.get_message(id).unwrap().unwrap().priority, MessagePriority::High);
+
+        // A header refresh doesn't forget a priority learned from the full message
+        msg.priority = MessagePriority::Normal;
+        cache.save_message_header(&msg).unwrap();
+        assert_eq!(cache.get_messages_for_folder(inbox, "acc").unwrap()[0].priority, MessagePriority::High);
+
+        cache.set_message_priority(id, MessagePriority::Low).unwrap();
+        assert_eq!(cache.get_message(id).unwrap().unwrap().priority, MessagePriority::Low);
+    }
+
+    #[test]
+    fn test_list_ids() {
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_list_ids_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+        let inbox = cache.ensure_folder("acc", "INBOX").unwrap().id;
+        cache.save_message_headers(&batch(inbox, 3)).unwrap();
+
+        cache.set_list_id(inbox, 2, Some("announce.example.com")).unwrap();
+        cache.save_message_headers(&batch(inbox, 3)).unwrap();
+        assert_eq!(cache.get_list_ids(inbox).unwrap(), HashMap::from([(2, "announce.example.com".to_string())]));
+
+        cache.set_list_id(inbox, 2, None).unwrap();
+        assert!(cache.get_list_ids(inbox).unwrap().is_empty());
+    }
+    fn batch(folder_id: i64, count: u32) -> Vec<CachedMessage> {
+        (1..=count)
+            .map(|uid| CachedMessage {
+                id: 0, uid, folder_id, message_id: format!("b{}@example.com", uid),
+                subject: format!("Batch {}", uid), from_addr: "bulk@example.com".to_string(),
+                to_addr: String::new(), cc: None, date: "2024-01-01".to_string(),
+                body_plain: None, body_html: None, read: false, starred: false, deleted: false, priority: MessagePriority::Normal,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_save_messages_batch() {
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_batch_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+        let inbox = cache.ensure_folder("acc", "INBOX").unwrap().id;
+
+        let ids = cache.save_messages(&batch(inbox, 1000)).unwrap();
+        assert_eq!(ids.len(), 1000);
+        assert_eq!(cache.get_message(ids[499]).unwrap().unwrap().uid, 500);
+        assert_eq!(cache.get_messages_for_folder(inbox, "acc").unwrap().len(), 1000);
+
+        // A failure partway through leaves none of the batch behind
+        let archive = cache.ensure_folder("acc", "Archive").unwrap().id;
+        cache.conn.execute_batch(
+            "CREATE TEMP TRIGGER fail_uid_500 BEFORE INSERT ON messages WHEN NEW.uid = 500
+             BEGIN SELECT RAISE(ABORT, 'disk full'); END",
+        ).unwrap();
+        assert!(matches!(cache.save_messages(&batch(archive, 1000)), Err(Error::Database(_))));
+        assert!(cache.save_message_headers(&batch(archive, 1000)).is_err());
+        assert!(cache.get_messages_for_folder(archive, "acc").unwrap().is_empty());
+
+        // Batches join an enclosing transaction
+        cache.conn.execute_batch("DROP TRIGGER fail_uid_500").unwrap();
+        cache.in_transaction(|c| c.save_message_headers(&batch(archive, 10))).unwrap();
+        assert_eq!(cache.get_messages_for_folder(archive, "acc").unwrap().len(), 10);
+    }
+
+    /// Timing of 1,000 inserts one by one against one batch. Run with
+    /// `cargo test --release bench_save_messages -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_save_messages() {
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_bench_batch_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+        let (one_by_one, batched) = (cache.ensure_folder("acc", "One").unwrap().id, cache.ensure_folder("acc", "Batch").unwrap().id);
+
+        let start = std::time::Instant::now();
+        for msg in batch(one_by_one, 1000) {
+            cache.save_message(&msg).unwrap();
+        }
+        let single = start.elapsed();
+        let start = std::time::Instant::now();
+        cache.save_messages(&batch(batched, 1000)).unwrap();
+        let together = start.elapsed();
+        println!("1,000 messages: {:?} one by one, {:?} in one batch", single, together);
+    }
 }
@@ -0,0 +1,310 @@
+//! Numbered schema migrations
+//!
+//! `initialize_schema` creates the tables as they first shipped. Each
+//! migration then moves the schema one version forward in its own
+//! transaction, and the `schema_version` table records which have run, so
+//! a step can transform or drop data as well as add columns. Append new
+//! steps to `MIGRATIONS`; never renumber or edit one that has shipped.
+
+use super::retry_busy;
+use crate::common::{Error, Result};
+use rusqlite::{params, Connection, Transaction, TransactionBehavior};
+
+/// One step forward in the schema
+pub(super) struct Migration {
+    pub version: i32,
+    pub description: &'static str,
+    pub apply: fn(&Connection) -> rusqlite::Result<()>,
+}
+
+/// Every migration, in version order
+pub(super) const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Filter rule match type and case sensitivity",
+        apply: |c| {
+            add_column(c, "message_filter_rules", "match_type", "TEXT NOT NULL DEFAULT 'contains'")?;
+            add_column(c, "message_filter_rules", "case_sensitive", "BOOLEAN DEFAULT 0")
+        },
+    },
+    Migration {
+        version: 2,
+        description: "Contact details and provider sync",
+        apply: |c| {
+            for column in [
+                "provider_contact_id", "phone", "company", "job_title", "website", "address", "birthday", "avatar_url",
+                "avatar_data_base64", "source_provider", "last_synced_at", "vcard_raw",
+            ] {
+                add_column(c, "contacts", column, "TEXT")?;
+            }
+            Ok(())
+        },
+    },
+    Migration {
+        version: 3,
+        description: "Multi-value and extended contact fields",
+        apply: |c| {
+            for column in [
+                "nickname", "department", "relationship", "emails_json", "phones_json", "addresses_json",
+                "custom_fields_json",
+            ] {
+                add_column(c, "contacts", column, "TEXT")?;
+            }
+            Ok(())
+        },
+    },
+    Migration {
+        version: 4,
+        description: "OAuth token type, scope and expiry",
+        apply: |c| {
+            add_column(c, "oauth_tokens", "token_type", "TEXT NOT NULL DEFAULT 'Bearer'")?;
+            add_column(c, "oauth_tokens", "scope", "TEXT")?;
+            add_column(c, "oauth_tokens", "expires_at", "TEXT")
+        },
+    },
+    Migration {
+        version: 5,
+        description: "Folders kept for offline use",
+        apply: |c| add_column(c, "folders", "offline_sync", "BOOLEAN DEFAULT 0"),
+    },
+    Migration {
+        version: 6,
+        description: "Account sent copy, draft sync and delete mode",
+        apply: |c| {
+            add_column(c, "accounts", "save_sent_copy", "INTEGER NOT NULL DEFAULT 1")?;
+            add_column(c, "accounts", "sync_drafts", "INTEGER NOT NULL DEFAULT 0")?;
+            add_column(c, "accounts", "delete_mode", "TEXT NOT NULL DEFAULT 'move_to_trash'")
+        },
+    },
+    Migration {
+        version: 7,
+        description: "Certificate pinning",
+        apply: |c| {
+            add_column(c, "accounts", "pin_certificates", "INTEGER NOT NULL DEFAULT 0")?;
+            add_column(c, "accounts", "imap_pinned_fingerprint", "TEXT")?;
+            add_column(c, "accounts", "smtp_pinned_fingerprint", "TEXT")
+        },
+    },
+    Migration {
+        version: 8,
+        description: "Per-account new mail notifications",
+        apply: |c| add_column(c, "accounts", "notify_new_mail", "INTEGER NOT NULL DEFAULT 1"),
+    },
+    Migration {
+        version: 9,
+        description: "Account bounce address",
+        apply: |c| add_column(c, "accounts", "envelope_from", "TEXT"),
+    },
+    Migration {
+        version: 10,
+        description: "Snoozed message origin, read receipts and message source",
+        apply: |c| {
+            add_column(c, "messages", "original_folder_id", "INTEGER")?;
+            add_column(c, "messages", "receipt_status", "TEXT")?;
+            add_column(c, "messages", "raw_source", "TEXT")
+        },
+    },
+    Migration {
+        version: 11,
+        description: "Follow-up flags and priority",
+        apply: |c| {
+            add_column(c, "messages", "flagged", "INTEGER NOT NULL DEFAULT 0")?;
+            add_column(c, "messages", "flag_due", "TEXT")?;
+            add_column(c, "messages", "priority", "TEXT NOT NULL DEFAULT 'normal'")
+        },
+    },
+    Migration {
+        version: 12,
+        description: "Drafts saved on the server",
+        apply: |c| add_column(c, "drafts", "server_uid", "INTEGER"),
+    },
+    Migration {
+        version: 13,
+        description: "Tag shortcuts",
+        apply: |c| add_column(c, "tags", "shortcut", "TEXT"),
+    },
+    Migration {
+        version: 14,
+        description: "Mailing list ids",
+        apply: |c| add_column(c, "messages", "list_id", "TEXT"),
+    },
+    Migration {
+        version: 15,
+        description: "Drafts reopened after a restart",
+        apply: |c| add_column(c, "drafts", "open_in_composer", "INTEGER NOT NULL DEFAULT 0"),
+    },
+    Migration {
+        version: 16,
+        description: "Folder subscriptions and visibility",
+        apply: |c| {
+            add_column(c, "folders", "subscribed", "INTEGER NOT NULL DEFAULT 1")?;
+            add_column(c, "folders", "shown", "INTEGER")
+        },
+    },
+    Migration {
+        version: 17,
+        description: "Identity bounce address",
+        apply: |c| add_column(c, "identities", "envelope_from", "TEXT"),
+    },
+    Migration {
+        version: 18,
+        description: "Attachment part numbers",
+        apply: |c| add_column(c, "attachments", "section", "TEXT"),
+    },
+    Migration {
+        version: 19,
+        description: "Per-folder sort order",
+        apply: |c| add_column(c, "folders", "sort_order", "TEXT"),
+    },
+    Migration {
+        version: 20,
+        description: "Folder last sync time",
+        apply: |c| add_column(c, "folders", "last_sync", "TEXT"),
+    },
+    Migration {
+        version: 21,
+        description: "Outbox Cc, Bcc, identity and send options",
+        apply: |c| {
+            add_column(c, "outbox_queue", "cc", "TEXT NOT NULL DEFAULT ''")?;
+            add_column(c, "outbox_queue", "bcc", "TEXT NOT NULL DEFAULT ''")?;
+            add_column(c, "outbox_queue", "identity_id", "TEXT")?;
+            add_column(c, "outbox_queue", "request_receipt", "INTEGER NOT NULL DEFAULT 0")?;
+            add_column(c, "outbox_queue", "priority", "TEXT NOT NULL DEFAULT 'normal'")
+        },
+    },
+];
+
+/// Schema version this build creates
+pub const SCHEMA_VERSION: i32 = MIGRATIONS[MIGRATIONS.len() - 1].version;
+
+/// Highest version recorded in `schema_version`; 0 for a new or unversioned database
+pub(super) fn current_version(conn: &Connection) -> Result<i32> {
+    conn.query_row("SELECT COALESCE(MAX(version), 0) FROM schema_version", [], |row| row.get(0))
+        .map_err(|e| Error::Database(format!("Failed to read schema version: {}", e)))
+}
+
+/// Apply the steps of `migrations` above the recorded version, in order,
+/// each in its own transaction with its version. A failing step is rolled
+/// back and stops the run. Returns the version reached.
+pub(super) fn run_migrations(conn: &Connection, migrations: &[Migration], applied_at: &str) -> Result<i32> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            description TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        )",
+        [],
+    )
+    .map_err(|e| Error::Database(format!("Failed to create schema_version table: {}", e)))?;
+
+    for migration in migrations {
+        if current_version(conn)? >= migration.version {
+            continue;
+        }
+        let tx = retry_busy(|| Transaction::new_unchecked(conn, TransactionBehavior::Immediate))
+            .map_err(|e| Error::Database(format!("Failed to begin migration: {}", e)))?;
+        // Another connection may have migrated while this one waited for the lock
+        if current_version(conn)? >= migration.version {
+            continue;
+        }
+        (migration.apply)(conn).map_err(|e| {
+            Error::Database(format!("Schema migration {} ({}) failed: {}", migration.version, migration.description, e))
+        })?;
+        conn.execute(
+            "INSERT INTO schema_version (version, description, applied_at) VALUES (?1, ?2, ?3)",
+            params![migration.version, migration.description, applied_at],
+        )
+        .map_err(|e| Error::Database(format!("Failed to record schema version: {}", e)))?;
+        tx.commit()
+            .map_err(|e| Error::Database(format!("Failed to commit migration {}: {}", migration.version, e)))?;
+        tracing::info!("Cache schema migrated to version {}: {}", migration.version, migration.description);
+    }
+    current_version(conn)
+}
+
+/// Add `column` to `table` unless it is already there. Databases from
+/// before versions were recorded may have any of the columns.
+fn add_column(conn: &Connection, table: &str, column: &str, column_def: &str) -> rusqlite::Result<()> {
+    let exists: bool = conn.query_row(
+        &format!("SELECT COUNT(*) > 0 FROM pragma_table_info('{}') WHERE name = ?1", table),
+        params![column],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, column_def), [])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn columns(conn: &Connection, table: &str) -> Vec<String> {
+        let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table)).unwrap();
+        stmt.query_map([], |row| row.get(1)).unwrap().collect::<rusqlite::Result<_>>().unwrap()
+    }
+
+    #[test]
+    fn test_migrations_are_numbered_in_order() {
+        for (i, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, i as i32 + 1, "{}", migration.description);
+        }
+        assert_eq!(SCHEMA_VERSION, MIGRATIONS.len() as i32);
+    }
+
+    #[test]
+    fn test_run_migrations() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT);
+             INSERT INTO notes (body) VALUES ('Hi');",
+        )
+        .unwrap();
+        let steps = [
+            Migration { version: 1, description: "Add title", apply: |c| add_column(c, "notes", "title", "TEXT") },
+            Migration {
+                version: 2,
+                description: "Fill titles",
+                apply: |c| c.execute("UPDATE notes SET title = body", []).map(|_| ()),
+            },
+        ];
+        assert_eq!(run_migrations(&conn, &steps[..1], "t1").unwrap(), 1);
+        assert_eq!(run_migrations(&conn, &steps, "t2").unwrap(), 2);
+        // Running again applies nothing
+        assert_eq!(run_migrations(&conn, &steps, "t3").unwrap(), 2);
+        let title: String = conn.query_row("SELECT title FROM notes", [], |row| row.get(0)).unwrap();
+        assert_eq!(title, "Hi");
+        let applied: Vec<(i32, String)> = conn
+            .prepare("SELECT version, applied_at FROM schema_version ORDER BY version")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(applied, vec![(1, "t1".to_string()), (2, "t2".to_string())]);
+
+        // A failing step leaves neither its changes nor its version behind
+        let broken = [Migration {
+            version: 3,
+            description: "Drop and break",
+            apply: |c| {
+                c.execute("DROP TABLE notes", [])?;
+                c.execute("SELECT * FROM missing", []).map(|_| ())
+            },
+        }];
+        let err = run_migrations(&conn, &broken, "t4").unwrap_err();
+        assert!(err.to_string().contains("Schema migration 3 (Drop and break) failed"));
+        assert_eq!(current_version(&conn).unwrap(), 2);
+        assert!(columns(&conn, "notes").contains(&"title".to_string()));
+    }
+
+    #[test]
+    fn test_add_column_skips_existing() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE accounts (id TEXT PRIMARY KEY, save_sent_copy INTEGER)").unwrap();
+        add_column(&conn, "accounts", "save_sent_copy", "INTEGER NOT NULL DEFAULT 1").unwrap();
+        add_column(&conn, "accounts", "sync_drafts", "INTEGER NOT NULL DEFAULT 0").unwrap();
+        assert_eq!(columns(&conn, "accounts"), ["id", "save_sent_copy", "sync_drafts"]);
+    }
+}
@@ -4,25 +4,64 @@
 //! Split into domain-specific sub-modules for maintainability.
 
 mod accounts;
+mod attachments;
+mod backup;
+mod calendar;
 mod contacts;
+mod content;
 mod drafts;
 mod filters;
 mod folders;
+mod follow_up;
+mod identities;
+mod maintenance;
 mod messages;
+mod migrations;
 mod oauth;
 mod outbox;
+mod pool;
+mod quota;
+mod senders;
 mod signatures;
+mod snooze;
+mod spam;
 mod tags;
+mod templates;
+mod vacation;
 
+pub(crate) use crate::common::types::parse_message_date;
+pub use backup::{BackupSecrets, ImportConflict, ImportSummary, SettingsBackup};
+pub use content::ContentEncryption;
+pub use messages::thread_key;
+pub use migrations::SCHEMA_VERSION;
+pub use pool::CachePool;
+pub use senders::SenderList;
+
+use crate::common::clock::{SharedClock, SystemClock};
+use crate::common::types::{FolderType, MessagePriority};
 use crate::common::{Error, Result};
 use crate::service::security::SecurityService;
-use rusqlite::Connection;
+use rusqlite::{Connection, ErrorCode, OpenFlags, Transaction, TransactionBehavior};
 use std::path::PathBuf;
+use std::time::Duration;
+
+/// How long a statement waits for another connection's lock before failing
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+/// Further attempts to start a write transaction while the database stays busy
+const BUSY_RETRIES: u32 = 4;
 
 /// Message cache using SQLite
 pub struct MessageCache {
     conn: Connection,
+    /// The database file, for opening more connections to it
+    path: PathBuf,
     security: Option<SecurityService>,
+    /// A master password is set but not yet entered, so secrets can't be saved
+    secrets_locked: bool,
+    /// Message text encrypted when saved
+    content_encryption: ContentEncryption,
+    /// Stamps saved records and decides what is due
+    clock: SharedClock,
 }
 
 /// Cached folder information
@@ -37,6 +76,42 @@ pub struct CachedFolder {
     pub total_count: i32,
 }
 
+/// A folder as the server lists it, with the role found from its
+/// special-use flags or name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerFolder {
+    pub path: String,
+    pub subscribed: bool,
+    pub folder_type: FolderType,
+}
+
+/// A folder's place on the server's subscription list, and the user's
+/// choice to show or hide it regardless
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FolderSubscription {
+    pub path: String,
+    pub subscribed: bool,
+    /// Shown (`Some(true)`) or hidden (`Some(false)`) in the folder list
+    /// whatever the subscription; `None` follows it
+    pub shown: Option<bool>,
+}
+
+impl FolderSubscription {
+    /// Whether the folder belongs in the folder list. The Inbox always does.
+    pub fn is_visible(&self, subscribed_only: bool) -> bool {
+        self.path.eq_ignore_ascii_case("INBOX") || self.shown.unwrap_or(self.subscribed || !subscribed_only)
+    }
+
+    /// Record `visible` as the user's choice, keeping an override only when
+    /// it differs from what the subscription alone would show.
+    pub fn choose_visible(&mut self, visible: bool, subscribed_only: bool) {
+        self.shown = None;
+        if self.is_visible(subscribed_only) != visible {
+            self.shown = Some(visible);
+        }
+    }
+}
+
 /// Cached message information
 #[derive(Debug, Clone)]
 pub struct CachedMessage {
@@ -54,17 +129,72 @@ pub struct CachedMessage {
     pub read: bool,
     pub starred: bool,
     pub deleted: bool,
+    /// From the `X-Priority`/`Importance` headers
+    pub priority: MessagePriority,
+}
+
+/// Read receipt state of a cached message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReceiptStatus {
+    /// The sender asked for a receipt that hasn't been answered yet
+    Requested,
+    Sent,
+    /// The user or the receipt policy chose not to send one
+    Declined,
 }
 
-/// Cached attachment information
+impl ReceiptStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReceiptStatus::Requested => "requested",
+            ReceiptStatus::Sent => "sent",
+            ReceiptStatus::Declined => "declined",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "requested" => Some(ReceiptStatus::Requested),
+            "sent" => Some(ReceiptStatus::Sent),
+            "declined" => Some(ReceiptStatus::Declined),
+            _ => None,
+        }
+    }
+}
+
+/// A cached Inbox message tagged with the account it came from
+#[derive(Debug, Clone)]
+pub struct InboxMessage {
+    pub account_id: String,
+    /// The account's accent colour (`#RRGGBB`)
+    pub account_color: String,
+    pub message: CachedMessage,
+}
+
+/// Rules for trimming cached message data
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PrunePolicy {
+    /// Drop bodies of messages older than this many days
+    pub keep_bodies_days: Option<u32>,
+    /// Drop bodies larger than this many kilobytes
+    pub max_body_kb: Option<u32>,
+    /// Compact the database file after removing data
+    pub vacuum: bool,
+}
+
+/// Cached attachment information, listed from the message's structure
+/// before its content is downloaded
 #[derive(Debug, Clone)]
 pub struct CachedAttachment {
     pub id: i64,
     pub message_id: i64,
     pub filename: String,
     pub mime_type: String,
+    /// Bytes as sent, still in its transfer encoding
     pub size: i64,
     pub content_id: Option<String>,
+    /// IMAP section number to download it by
+    pub section: Option<String>,
 }
 
 /// Cached draft information
@@ -79,20 +209,38 @@ pub struct CachedDraft {
     pub body: String,
     pub created_at: String,
     pub updated_at: String,
+    /// UID of the copy in the server's Drafts folder, once uploaded
+    pub server_uid: Option<u32>,
+}
+
+/// An earlier version of a draft, kept when the draft is saved over
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DraftRevision {
+    pub id: i64,
+    pub draft_id: String,
+    pub to_addr: String,
+    pub cc: Option<String>,
+    pub bcc: Option<String>,
+    pub subject: String,
+    pub body: String,
+    /// When this version was the saved draft
+    pub saved_at: String,
 }
 
 /// Tag information for organizing messages
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Tag {
     pub id: String,
     pub account_id: String,
     pub name: String,
     pub color: String,
     pub created_at: String,
+    /// Single key that applies the tag to the selected messages
+    pub shortcut: Option<String>,
 }
 
 /// Email signature information
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Signature {
     pub id: String,
     pub account_id: String,
@@ -103,8 +251,62 @@ pub struct Signature {
     pub created_at: String,
 }
 
-/// Message filter rule for automatic organization
+/// Address an account can send as (an alias of the same mailbox)
 #[derive(Debug, Clone)]
+pub struct Identity {
+    pub id: String,
+    pub account_id: String,
+    pub from_name: String,
+    pub from_email: String,
+    pub reply_to: Option<String>,
+    /// Bounce address (SMTP `MAIL FROM`) for mail sent as this identity,
+    /// instead of the account's; see `Account::envelope_from`
+    pub envelope_from: Option<String>,
+    /// Signature used with this identity instead of the account default
+    pub signature_id: Option<String>,
+    pub created_at: String,
+}
+
+/// Event from a calendar invitation (or update or cancellation) received
+/// by an account, one row per event `UID`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedCalendarEvent {
+    pub id: i64,
+    pub account_id: String,
+    pub uid: String,
+    /// Cache id of the message that last updated the event
+    pub message_id: Option<i64>,
+    /// `REQUEST`, `CANCEL`, ...
+    pub method: String,
+    /// Revision number; older revisions don't overwrite newer ones
+    pub sequence: i64,
+    pub summary: String,
+    pub location: Option<String>,
+    /// RFC 3339 UTC time, or `YYYY-MM-DD` for all-day events
+    pub starts_at: String,
+    pub ends_at: Option<String>,
+    pub all_day: bool,
+    pub organizer: Option<String>,
+    /// The account's answer: `ACCEPTED`, `TENTATIVE` or `DECLINED`
+    pub response: Option<String>,
+    pub updated_at: String,
+}
+
+/// Reusable message (canned response) inserted while composing
+#[derive(Debug, Clone)]
+pub struct MessageTemplate {
+    pub id: String,
+    pub account_id: String,
+    pub name: String,
+    pub subject: String,
+    /// May contain `{{placeholder}}` fields filled in on insertion
+    pub body: String,
+    pub is_html: bool,
+    pub created_at: String,
+}
+
+/// Message filter rule for automatic organization
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct MessageFilterRule {
     pub id: String,
     pub account_id: String,
@@ -154,8 +356,18 @@ pub struct CustomFieldEntry {
     pub value: String,
 }
 
+/// Address seen in an account's mail, ranked by how often it appears
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrequentRecipient {
+    /// Display name from the headers, empty if none was given
+    pub name: String,
+    pub email: String,
+    /// Messages sent to or received from this address
+    pub count: u32,
+}
+
 /// Contact entry for account address book
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ContactEntry {
     pub id: String,
     pub account_id: String,
@@ -212,15 +424,57 @@ pub struct QueuedOutboxMessage {
     pub id: String,
     pub account_id: String,
     pub to_addr: String,
+    pub cc: String,
+    pub bcc: String,
+    /// Identity to send as; the account's own address when `None`
+    pub identity_id: Option<String>,
     pub subject: String,
     pub body: String,
+    pub request_receipt: bool,
+    pub priority: MessagePriority,
     pub attempt_count: i64,
     pub last_error: Option<String>,
     pub created_at: String,
 }
 
-/// Contact group (distribution list) for sending to multiple recipients
+/// A snoozed message that is due to wake, with where it waits and where it
+/// goes back to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnoozedMessage {
+    pub message_id: i64,
+    pub account_id: String,
+    pub uid: u32,
+    /// RFC 3339 time the message reappears
+    pub wake_at: String,
+    /// Folder the message waits in while snoozed
+    pub snoozed_folder: String,
+    /// Folder the message returns to
+    pub original_folder: String,
+}
+
+/// A message flagged for follow-up, with the folder it lives in
 #[derive(Debug, Clone)]
+pub struct FollowUpMessage {
+    pub message: CachedMessage,
+    pub folder_path: String,
+    /// RFC 3339 due time, if one was set
+    pub due: Option<String>,
+}
+
+/// An account's mailbox usage and limits as last reported by the server.
+/// Storage is in bytes; `None` where the server has no limit on a resource.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedQuota {
+    pub storage_used: Option<i64>,
+    pub storage_limit: Option<i64>,
+    pub messages_used: Option<i64>,
+    pub messages_limit: Option<i64>,
+    /// RFC 3339 time the quota was fetched
+    pub checked_at: String,
+}
+
+/// Contact group (distribution list) for sending to multiple recipients
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ContactGroup {
     pub id: String,
     pub account_id: String,
@@ -231,6 +485,25 @@ pub struct ContactGroup {
     pub member_ids: Vec<String>,
 }
 
+/// Run `op`, trying again after a short random pause while another
+/// connection keeps the database locked
+fn retry_busy<T>(mut op: impl FnMut() -> rusqlite::Result<T>) -> rusqlite::Result<T> {
+    use rand::Rng;
+    let mut attempt = 0;
+    loop {
+        match op() {
+            Err(e) if attempt < BUSY_RETRIES
+                && matches!(e.sqlite_error_code(), Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked)) =>
+            {
+                attempt += 1;
+                let pause = rand::thread_rng().gen_range(10..50) * u64::from(attempt);
+                std::thread::sleep(Duration::from_millis(pause));
+            }
+            result => return result,
+        }
+    }
+}
+
 impl MessageCache {
     /// Create a new message cache
     ///
@@ -241,8 +514,10 @@ impl MessageCache {
             .map_err(|e| Error::Other(format!("Failed to create cache directory: {}", e)))?;
 
         let db_path = cache_dir.join("message_cache.db");
-        let conn = Connection::open(db_path)
-            .map_err(|e| Error::Other(format!("Failed to open database: {}", e)))?;
+        let conn = Connection::open(&db_path)
+            .map_err(|e| Error::Database(format!("Failed to open database: {}", e)))?;
+        conn.busy_timeout(BUSY_TIMEOUT)
+            .map_err(|e| Error::Database(format!("Failed to set busy timeout: {}", e)))?;
 
         // Performance pragmas for large mailboxes
         conn.execute_batch(
@@ -250,16 +525,82 @@ impl MessageCache {
              PRAGMA synchronous=NORMAL;
              PRAGMA cache_size=-8000;",
         )
-        .map_err(|e| Error::Other(format!("Failed to set pragmas: {}", e)))?;
+        .map_err(|e| Error::Database(format!("Failed to set pragmas: {}", e)))?;
 
-        let cache = Self { conn, security };
+        let cache = Self {
+            conn,
+            path: db_path,
+            security,
+            secrets_locked: false,
+            content_encryption: ContentEncryption::Off,
+            clock: SystemClock::shared(),
+        };
         cache.initialize_schema()?;
 
         Ok(cache)
     }
 
-    /// Encrypt a string value for storage. Falls back to base64 if no SecurityService.
+    /// A connection to the database at `path` that can only read. With WAL,
+    /// it reads the last committed state while another connection writes.
+    fn connect_reader(path: PathBuf, security: Option<SecurityService>, clock: SharedClock) -> Result<Self> {
+        let conn = Connection::open_with_flags(
+            &path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX | OpenFlags::SQLITE_OPEN_URI,
+        )
+        .map_err(|e| Error::Database(format!("Failed to open database for reading: {}", e)))?;
+        conn.busy_timeout(BUSY_TIMEOUT)
+            .map_err(|e| Error::Database(format!("Failed to set busy timeout: {}", e)))?;
+        Ok(Self {
+            conn,
+            path,
+            security,
+            secrets_locked: false,
+            content_encryption: ContentEncryption::Off,
+            clock,
+        })
+    }
+
+    /// Refuse to save passwords and tokens until `set_security` supplies the
+    /// master password key. Without this, saving while locked would fall back
+    /// to base64 and overwrite the encrypted values.
+    pub fn with_secrets_locked(mut self) -> Self {
+        self.secrets_locked = true;
+        self
+    }
+
+    /// Take the time from `clock` instead of the system clock
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The current time by the cache's clock
+    pub fn now(&self) -> chrono::DateTime<chrono::Utc> {
+        self.clock.now()
+    }
+
+    /// Write the WAL back into the database file
+    pub fn checkpoint(&self) -> Result<()> {
+        self.conn
+            .execute_batch("PRAGMA wal_checkpoint(TRUNCATE);")
+            .map_err(|e| Error::Database(format!("Failed to checkpoint database: {}", e)))
+    }
+
+    /// Write the WAL back into the database file and close the connection,
+    /// so the next start opens a single, complete file
+    pub fn close(self) -> Result<()> {
+        self.checkpoint()?;
+        self.conn
+            .close()
+            .map_err(|(_, e)| Error::Database(format!("Failed to close database: {}", e)))
+    }
+
+    /// Encrypt a string value for storage. Falls back to base64 if no
+    /// SecurityService, unless the master password has not been entered yet.
     fn encrypt_value(&self, value: &str) -> Result<String> {
+        if self.secrets_locked {
+            return Err(Error::Security("Saved passwords are locked; enter the master password first".to_string()));
+        }
         if let Some(ref sec) = self.security {
             let encrypted = sec.encrypt(value.as_bytes())?;
             Ok(String::from_utf8(encrypted)
@@ -289,6 +630,148 @@ impl MessageCache {
             .ok_or_else(|| Error::Security("Failed to decode stored value".to_string()))
     }
 
+    /// Replace the encryption service without touching stored data.
+    ///
+    /// Used after unlocking with the master password at startup.
+    pub fn set_security(&mut self, security: Option<SecurityService>) {
+        self.secrets_locked = self.secrets_locked && security.is_none();
+        self.security = security;
+    }
+
+    /// Re-encrypt stored account passwords, OAuth tokens and encrypted
+    /// message text under `new`, then switch to it. Runs in one transaction:
+    /// any value that fails to decrypt aborts the whole rekey and leaves the
+    /// database unchanged.
+    pub fn rekey(&mut self, new: SecurityService) -> Result<()> {
+        self.rekey_then(new, || Ok(()))
+    }
+
+    /// `rekey`, running `before_commit` as the last step of its transaction.
+    /// If `before_commit` fails, nothing is re-encrypted.
+    pub fn rekey_then(&mut self, new: SecurityService, before_commit: impl FnOnce() -> Result<()>) -> Result<()> {
+        let encrypt = |value: &str| -> Result<String> {
+            String::from_utf8(new.encrypt(value.as_bytes())?)
+                .map_err(|e| Error::Security(format!("Encrypted output not UTF-8: {}", e)))
+        };
+        self.in_transaction(|c| {
+            let passwords: Vec<(String, String)> = c
+                .conn
+                .prepare("SELECT id, password FROM accounts")
+                .and_then(|mut stmt| {
+                    stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                        .collect()
+                })
+                .map_err(|e| Error::Database(format!("Failed to read account secrets: {}", e)))?;
+            for (id, stored) in passwords {
+                let plain = c.decrypt_value(&stored)?;
+                c.conn
+                    .execute(
+                        "UPDATE accounts SET password = ?1 WHERE id = ?2",
+                        rusqlite::params![encrypt(&plain)?, id],
+                    )
+                    .map_err(|e| Error::Database(format!("Failed to re-encrypt account: {}", e)))?;
+            }
+
+            let tokens: Vec<(String, String, Option<String>)> = c
+                .conn
+                .prepare("SELECT id, access_token, refresh_token FROM oauth_tokens")
+                .and_then(|mut stmt| {
+                    stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+                        .collect()
+                })
+                .map_err(|e| Error::Database(format!("Failed to read oauth tokens: {}", e)))?;
+            for (id, access, refresh) in tokens {
+                let access = encrypt(&c.decrypt_value(&access)?)?;
+                let refresh = refresh
+                    .map(|rt| c.decrypt_value(&rt).and_then(|plain| encrypt(&plain)))
+                    .transpose()?;
+                c.conn
+                    .execute(
+                        "UPDATE oauth_tokens SET access_token = ?1, refresh_token = ?2 WHERE id = ?3",
+                        rusqlite::params![access, refresh, id],
+                    )
+                    .map_err(|e| Error::Database(format!("Failed to re-encrypt oauth token: {}", e)))?;
+            }
+            c.reseal_content(&encrypt)?;
+            before_commit()
+        })?;
+        self.security = Some(new);
+        self.secrets_locked = false;
+        Ok(())
+    }
+
+    /// Stored secrets still in legacy base64 form, as (table, column, row id, value).
+    fn plaintext_secrets(&self) -> Result<Vec<(&'static str, &'static str, String, String)>> {
+        let mut found = Vec::new();
+        for (table, column) in [
+            ("accounts", "password"),
+            ("oauth_tokens", "access_token"),
+            ("oauth_tokens", "refresh_token"),
+        ] {
+            let sql = format!(
+                "SELECT id, {col} FROM {table} WHERE {col} IS NOT NULL AND {col} != '' AND {col} NOT LIKE 'WXM2:%'",
+                col = column,
+                table = table
+            );
+            let rows: Vec<(String, String)> = self
+                .conn
+                .prepare(&sql)
+                .and_then(|mut stmt| {
+                    stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                        .collect()
+                })
+                .map_err(|e| Error::Database(format!("Failed to scan {}: {}", table, e)))?;
+            found.extend(rows.into_iter().map(|(id, value)| (table, column, id, value)));
+        }
+        Ok(found)
+    }
+
+    /// Dry run for `reencrypt_plaintext_secrets`: how many values would be upgraded.
+    pub fn count_plaintext_secrets(&self) -> Result<usize> {
+        Ok(self.plaintext_secrets()?.len())
+    }
+
+    /// Upgrade legacy base64 passwords and OAuth tokens to the encrypted
+    /// `WXM2:` form in one transaction. Returns the number of values rewritten.
+    pub fn reencrypt_plaintext_secrets(&self) -> Result<usize> {
+        if self.security.is_none() {
+            return Err(Error::Security("No encryption key available".to_string()));
+        }
+        self.in_transaction(|c| {
+            let secrets = c.plaintext_secrets()?;
+            for (table, column, id, stored) in &secrets {
+                let encrypted = c.encrypt_value(&c.decrypt_value(stored)?)?;
+                let sql = format!("UPDATE {} SET {} = ?1 WHERE id = ?2", table, column);
+                c.conn
+                    .execute(&sql, rusqlite::params![encrypted, id])
+                    .map_err(|e| Error::Database(format!("Failed to upgrade {} secret: {}", table, e)))?;
+            }
+            Ok(secrets.len())
+        })
+    }
+
+    /// Run `f` inside a single SQLite transaction.
+    ///
+    /// Changes are committed only if `f` succeeds; any error rolls back every
+    /// write made through `self` inside the closure. Inside another
+    /// transaction, `f` simply joins it.
+    ///
+    /// The write lock is taken up front, so a transaction that reads before
+    /// writing can't fail halfway because another connection wrote first.
+    /// While another connection holds the lock, starting is retried after a
+    /// short random pause.
+    pub fn in_transaction<T>(&self, f: impl FnOnce(&Self) -> Result<T>) -> Result<T> {
+        if !self.conn.is_autocommit() {
+            return f(self);
+        }
+        let tx = retry_busy(|| Transaction::new_unchecked(&self.conn, TransactionBehavior::Immediate))
+            .map_err(|e| Error::Database(format!("Failed to begin transaction: {}", e)))?;
+        let value = f(self)?;
+        tx.commit()
+            .map_err(|e| Error::Database(format!("Failed to commit transaction: {}", e)))?;
+        Ok(value)
+    }
+
     /// Initialize database schema
     fn initialize_schema(&self) -> Result<()> {
         self.conn
@@ -305,7 +788,7 @@ impl MessageCache {
             )",
                 [],
             )
-            .map_err(|e| Error::Other(format!("Failed to create folders table: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to create folders table: {}", e)))?;
 
         self.conn
             .execute(
@@ -329,7 +812,7 @@ impl MessageCache {
             )",
                 [],
             )
-            .map_err(|e| Error::Other(format!("Failed to create messages table: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to create messages table: {}", e)))?;
 
         self.conn
             .execute(
@@ -344,7 +827,7 @@ impl MessageCache {
             )",
                 [],
             )
-            .map_err(|e| Error::Other(format!("Failed to create attachments table: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to create attachments table: {}", e)))?;
 
         self.conn
             .execute(
@@ -361,7 +844,23 @@ impl MessageCache {
             )",
                 [],
             )
-            .map_err(|e| Error::Other(format!("Failed to create drafts table: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to create drafts table: {}", e)))?;
+
+        self.conn
+            .execute(
+                "CREATE TABLE IF NOT EXISTS draft_revisions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                draft_id TEXT NOT NULL,
+                to_addr TEXT NOT NULL,
+                cc TEXT,
+                bcc TEXT,
+                subject TEXT NOT NULL,
+                body TEXT NOT NULL,
+                saved_at TEXT NOT NULL
+            )",
+                [],
+            )
+            .map_err(|e| Error::Database(format!("Failed to create draft_revisions table: {}", e)))?;
 
         self.conn
             .execute(
@@ -375,7 +874,7 @@ impl MessageCache {
             )",
                 [],
             )
-            .map_err(|e| Error::Other(format!("Failed to create tags table: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to create tags table: {}", e)))?;
 
         self.conn
             .execute(
@@ -389,7 +888,7 @@ impl MessageCache {
             )",
                 [],
             )
-            .map_err(|e| Error::Other(format!("Failed to create message_tags table: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to create message_tags table: {}", e)))?;
 
         self.conn
             .execute(
@@ -405,7 +904,149 @@ impl MessageCache {
             )",
                 [],
             )
-            .map_err(|e| Error::Other(format!("Failed to create signatures table: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to create signatures table: {}", e)))?;
+
+        self.conn
+            .execute(
+                "CREATE TABLE IF NOT EXISTS templates (
+                id TEXT PRIMARY KEY,
+                account_id TEXT NOT NULL,
+                name TEXT NOT NULL,
+                subject TEXT NOT NULL DEFAULT '',
+                body TEXT NOT NULL,
+                is_html BOOLEAN DEFAULT 0,
+                created_at TEXT NOT NULL,
+                UNIQUE(account_id, name)
+            )",
+                [],
+            )
+            .map_err(|e| Error::Database(format!("Failed to create templates table: {}", e)))?;
+
+        self.conn
+            .execute(
+                "CREATE TABLE IF NOT EXISTS identities (
+                id TEXT PRIMARY KEY,
+                account_id TEXT NOT NULL,
+                from_name TEXT NOT NULL DEFAULT '',
+                from_email TEXT NOT NULL,
+                reply_to TEXT,
+                signature_id TEXT,
+                created_at TEXT NOT NULL,
+                UNIQUE(account_id, from_email)
+            )",
+                [],
+            )
+            .map_err(|e| Error::Database(format!("Failed to create identities table: {}", e)))?;
+
+        self.conn
+            .execute(
+                "CREATE TABLE IF NOT EXISTS calendar_events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                account_id TEXT NOT NULL,
+                uid TEXT NOT NULL,
+                message_id INTEGER,
+                method TEXT NOT NULL,
+                sequence INTEGER NOT NULL DEFAULT 0,
+                summary TEXT NOT NULL DEFAULT '',
+                location TEXT,
+                starts_at TEXT NOT NULL,
+                ends_at TEXT,
+                all_day BOOLEAN NOT NULL DEFAULT 0,
+                organizer TEXT,
+                response TEXT,
+                updated_at TEXT NOT NULL,
+                UNIQUE(account_id, uid)
+            )",
+                [],
+            )
+            .map_err(|e| Error::Database(format!("Failed to create calendar_events table: {}", e)))?;
+
+        self.conn
+            .execute(
+                "CREATE TABLE IF NOT EXISTS rule_sends (
+                account_id TEXT NOT NULL,
+                rule_id TEXT NOT NULL,
+                target TEXT NOT NULL,
+                sent_at TEXT NOT NULL,
+                UNIQUE(account_id, rule_id, target)
+            )",
+                [],
+            )
+            .map_err(|e| Error::Database(format!("Failed to create rule_sends table: {}", e)))?;
+
+        self.conn
+            .execute(
+                "CREATE TABLE IF NOT EXISTS vacation_replied (
+                account_id TEXT NOT NULL,
+                sender TEXT NOT NULL,
+                replied_at TEXT NOT NULL,
+                PRIMARY KEY(account_id, sender)
+            )",
+                [],
+            )
+            .map_err(|e| Error::Database(format!("Failed to create vacation_replied table: {}", e)))?;
+
+        self.conn
+            .execute(
+                "CREATE TABLE IF NOT EXISTS blocked_senders (
+                account_id TEXT NOT NULL,
+                address TEXT NOT NULL,
+                added_at TEXT NOT NULL,
+                PRIMARY KEY(account_id, address)
+            )",
+                [],
+            )
+            .map_err(|e| Error::Database(format!("Failed to create blocked_senders table: {}", e)))?;
+
+        self.conn
+            .execute(
+                "CREATE TABLE IF NOT EXISTS trusted_senders (
+                account_id TEXT NOT NULL,
+                address TEXT NOT NULL,
+                added_at TEXT NOT NULL,
+                PRIMARY KEY(account_id, address)
+            )",
+                [],
+            )
+            .map_err(|e| Error::Database(format!("Failed to create trusted_senders table: {}", e)))?;
+
+        self.conn
+            .execute(
+                "CREATE TABLE IF NOT EXISTS junk_senders (
+                account_id TEXT NOT NULL,
+                address TEXT NOT NULL,
+                added_at TEXT NOT NULL,
+                PRIMARY KEY(account_id, address)
+            )",
+                [],
+            )
+            .map_err(|e| Error::Database(format!("Failed to create junk_senders table: {}", e)))?;
+
+        self.conn
+            .execute(
+                "CREATE TABLE IF NOT EXISTS spam_tokens (
+                account_id TEXT NOT NULL,
+                token TEXT NOT NULL,
+                spam_count INTEGER NOT NULL DEFAULT 0,
+                ham_count INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY(account_id, token)
+            )",
+                [],
+            )
+            .map_err(|e| Error::Database(format!("Failed to create spam_tokens table: {}", e)))?;
+
+        self.conn
+            .execute(
+                "CREATE TABLE IF NOT EXISTS spam_trained (
+                account_id TEXT NOT NULL,
+                message_key TEXT NOT NULL,
+                spam INTEGER NOT NULL,
+                tokens TEXT NOT NULL,
+                PRIMARY KEY(account_id, message_key)
+            )",
+                [],
+            )
+            .map_err(|e| Error::Database(format!("Failed to create spam_trained table: {}", e)))?;
 
         self.conn
             .execute(
@@ -427,7 +1068,7 @@ impl MessageCache {
                 [],
             )
             .map_err(|e| {
-                Error::Other(format!(
+                Error::Database(format!(
                     "Failed to create message_filter_rules table: {}",
                     e
                 ))
@@ -460,7 +1101,7 @@ impl MessageCache {
             )",
                 [],
             )
-            .map_err(|e| Error::Other(format!("Failed to create contacts table: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to create contacts table: {}", e)))?;
 
         self.conn
             .execute(
@@ -479,7 +1120,7 @@ impl MessageCache {
             )",
                 [],
             )
-            .map_err(|e| Error::Other(format!("Failed to create oauth_tokens table: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to create oauth_tokens table: {}", e)))?;
 
         self.conn
             .execute(
@@ -495,7 +1136,18 @@ impl MessageCache {
             )",
                 [],
             )
-            .map_err(|e| Error::Other(format!("Failed to create outbox_queue table: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to create outbox_queue table: {}", e)))?;
+
+        self.conn
+            .execute(
+                "CREATE TABLE IF NOT EXISTS snoozed_messages (
+                message_id INTEGER PRIMARY KEY,
+                wake_at TEXT NOT NULL,
+                original_folder_id INTEGER NOT NULL
+            )",
+                [],
+            )
+            .map_err(|e| Error::Database(format!("Failed to create snoozed_messages table: {}", e)))?;
 
         self.conn
             .execute(
@@ -509,7 +1161,7 @@ impl MessageCache {
             )",
                 [],
             )
-            .map_err(|e| Error::Other(format!("Failed to create contact_groups table: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to create contact_groups table: {}", e)))?;
 
         self.conn
             .execute(
@@ -521,7 +1173,7 @@ impl MessageCache {
             )",
                 [],
             )
-            .map_err(|e| Error::Other(format!("Failed to create contact_group_members table: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to create contact_group_members table: {}", e)))?;
 
         self.conn
             .execute(
@@ -547,34 +1199,24 @@ impl MessageCache {
             )",
                 [],
             )
-            .map_err(|e| Error::Other(format!("Failed to create accounts table: {}", e)))?;
-
-        // Schema migrations
-        self.ensure_column_exists("message_filter_rules", "match_type", "TEXT NOT NULL DEFAULT 'contains'")?;
-        self.ensure_column_exists("message_filter_rules", "case_sensitive", "BOOLEAN DEFAULT 0")?;
-        self.ensure_column_exists("contacts", "provider_contact_id", "TEXT")?;
-        self.ensure_column_exists("contacts", "phone", "TEXT")?;
-        self.ensure_column_exists("contacts", "company", "TEXT")?;
-        self.ensure_column_exists("contacts", "job_title", "TEXT")?;
-        self.ensure_column_exists("contacts", "website", "TEXT")?;
-        self.ensure_column_exists("contacts", "address", "TEXT")?;
-        self.ensure_column_exists("contacts", "birthday", "TEXT")?;
-        self.ensure_column_exists("contacts", "avatar_url", "TEXT")?;
-        self.ensure_column_exists("contacts", "avatar_data_base64", "TEXT")?;
-        self.ensure_column_exists("contacts", "source_provider", "TEXT")?;
-        self.ensure_column_exists("contacts", "last_synced_at", "TEXT")?;
-        self.ensure_column_exists("contacts", "vcard_raw", "TEXT")?;
-        // Multi-value / extended contact fields
-        self.ensure_column_exists("contacts", "nickname", "TEXT")?;
-        self.ensure_column_exists("contacts", "department", "TEXT")?;
-        self.ensure_column_exists("contacts", "relationship", "TEXT")?;
-        self.ensure_column_exists("contacts", "emails_json", "TEXT")?;
-        self.ensure_column_exists("contacts", "phones_json", "TEXT")?;
-        self.ensure_column_exists("contacts", "addresses_json", "TEXT")?;
-        self.ensure_column_exists("contacts", "custom_fields_json", "TEXT")?;
-        self.ensure_column_exists("oauth_tokens", "token_type", "TEXT NOT NULL DEFAULT 'Bearer'")?;
-        self.ensure_column_exists("oauth_tokens", "scope", "TEXT")?;
-        self.ensure_column_exists("oauth_tokens", "expires_at", "TEXT")?;
+            .map_err(|e| Error::Database(format!("Failed to create accounts table: {}", e)))?;
+
+        self.conn
+            .execute(
+                "CREATE TABLE IF NOT EXISTS account_quota (
+                account_id TEXT PRIMARY KEY,
+                storage_used INTEGER,
+                storage_limit INTEGER,
+                messages_used INTEGER,
+                messages_limit INTEGER,
+                checked_at TEXT NOT NULL
+            )",
+                [],
+            )
+            .map_err(|e| Error::Database(format!("Failed to create account_quota table: {}", e)))?;
+
+        // Columns added since; the tag shortcut index below needs them
+        migrations::run_migrations(&self.conn, migrations::MIGRATIONS, &self.now().to_rfc3339())?;
 
         // Indexes for performance
         let indexes = [
@@ -582,54 +1224,17 @@ impl MessageCache {
             "CREATE INDEX IF NOT EXISTS idx_messages_uid ON messages(uid)",
             "CREATE INDEX IF NOT EXISTS idx_message_tags_tag_id ON message_tags(tag_id)",
             "CREATE INDEX IF NOT EXISTS idx_message_tags_message_id ON message_tags(message_id)",
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_tags_account_shortcut ON tags(account_id, shortcut) WHERE shortcut IS NOT NULL",
             "CREATE INDEX IF NOT EXISTS idx_contacts_account_email ON contacts(account_id, email)",
             "CREATE INDEX IF NOT EXISTS idx_oauth_tokens_account_provider ON oauth_tokens(account_id, provider)",
             "CREATE INDEX IF NOT EXISTS idx_outbox_queue_account_created ON outbox_queue(account_id, created_at)",
+            "CREATE INDEX IF NOT EXISTS idx_draft_revisions_draft_id ON draft_revisions(draft_id)",
+            "CREATE INDEX IF NOT EXISTS idx_attachments_message_id ON attachments(message_id)",
         ];
         for idx in indexes {
             self.conn
                 .execute(idx, [])
-                .map_err(|e| Error::Other(format!("Failed to create index: {}", e)))?;
-        }
-
-        Ok(())
-    }
-
-    fn ensure_column_exists(&self, table: &str, column: &str, column_def: &str) -> Result<()> {
-        fn is_safe_identifier(value: &str) -> bool {
-            value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
-        }
-        if !is_safe_identifier(table) || !is_safe_identifier(column) {
-            return Err(Error::Other(
-                "Unsafe identifier in schema migration".to_string(),
-            ));
-        }
-
-        let mut stmt = self
-            .conn
-            .prepare(&format!("PRAGMA table_info({})", table))
-            .map_err(|e| Error::Other(format!("Failed to inspect schema for {}: {}", table, e)))?;
-
-        let columns = stmt
-            .query_map([], |row| row.get::<_, String>(1))
-            .map_err(|e| Error::Other(format!("Failed to read schema for {}: {}", table, e)))?
-            .collect::<std::result::Result<Vec<_>, _>>()
-            .map_err(|e| {
-                Error::Other(format!(
-                    "Failed to collect schema info for {}: {}",
-                    table, e
-                ))
-            })?;
-
-        if !columns.iter().any(|c| c == column) {
-            self.conn
-                .execute(
-                    &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, column_def),
-                    [],
-                )
-                .map_err(|e| {
-                    Error::Other(format!("Failed to add column {}.{}: {}", table, column, e))
-                })?;
+                .map_err(|e| Error::Database(format!("Failed to create index: {}", e)))?;
         }
 
         Ok(())
@@ -647,4 +1252,93 @@ mod tests {
         let cache = MessageCache::new(temp_dir, None);
         assert!(cache.is_ok());
     }
+    #[test]
+    fn test_creates_all_tables() {
+        let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos();
+        let cache = MessageCache::new(env::temp_dir().join(format!("wixen_mail_test_tables_{}", nanos)), None).unwrap();
+        let mut stmt = cache.conn.prepare("SELECT name FROM sqlite_master WHERE type = 'table'").unwrap();
+        let tables: Vec<String> =
+            stmt.query_map([], |row| row.get(0)).unwrap().collect::<rusqlite::Result<_>>().unwrap();
+        for expected in [
+            "accounts", "account_quota", "attachments", "blocked_senders", "calendar_events", "contact_group_members",
+            "contact_groups", "contacts", "draft_revisions", "drafts", "folders", "identities", "junk_senders",
+            "message_filter_rules", "message_tags", "messages", "oauth_tokens", "outbox_queue", "rule_sends",
+            "schema_version", "signatures", "snoozed_messages", "spam_tokens", "spam_trained", "tags", "templates",
+            "trusted_senders", "vacation_replied",
+        ] {
+            assert!(tables.iter().any(|t| t == expected), "missing table {}", expected);
+        }
+    }
+
+    #[test]
+    fn test_upgrades_unversioned_database() {
+        let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos();
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_upgrade_{}", nanos));
+        std::fs::create_dir_all(&temp_dir).unwrap();
+        // Written before versions were recorded, with some later columns already added
+        let old = Connection::open(temp_dir.join("message_cache.db")).unwrap();
+        old.execute_batch(
+            "CREATE TABLE tags (id TEXT PRIMARY KEY, account_id TEXT NOT NULL, name TEXT NOT NULL,
+                                color TEXT NOT NULL, created_at TEXT NOT NULL, shortcut TEXT);",
+        )
+        .unwrap();
+        drop(old);
+
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+        assert_eq!(cache.schema_version().unwrap(), SCHEMA_VERSION);
+        let applied: i64 =
+            cache.conn.query_row("SELECT COUNT(*) FROM schema_version", [], |row| row.get(0)).unwrap();
+        assert_eq!(applied, SCHEMA_VERSION as i64);
+    }
+
+    #[test]
+    fn test_close_checkpoints_wal() {
+        let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos();
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_close_{}", nanos));
+        let cache = MessageCache::new(temp_dir.clone(), None).unwrap();
+        cache.ensure_folder("acc", "INBOX").unwrap();
+        let wal = temp_dir.join("message_cache.db-wal");
+        assert!(std::fs::metadata(&wal).unwrap().len() > 0);
+
+        cache.close().unwrap();
+        assert!(std::fs::metadata(&wal).map(|m| m.len() == 0).unwrap_or(true));
+        let reopened = MessageCache::new(temp_dir, None).unwrap();
+        assert_eq!(reopened.get_folders_for_account("acc").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_writes_from_two_connections() {
+        let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos();
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_busy_{}", nanos));
+        let first = MessageCache::new(temp_dir.clone(), None).unwrap();
+        let folder_id = first.ensure_folder("acc", "INBOX").unwrap().id;
+
+        let writers: Vec<_> = [first, MessageCache::new(temp_dir, None).unwrap()]
+            .into_iter()
+            .map(|cache| {
+                std::thread::spawn(move || {
+                    for _ in 0..100 {
+                        // Read, then write: the pattern that fails when two
+                        // deferred transactions race for the write lock
+                        cache.in_transaction(|c| {
+                            let uid = c.next_local_uid(folder_id)?;
+                            c.save_message(&CachedMessage {
+                                id: 0, uid, folder_id, message_id: format!("busy{}@example.com", uid),
+                                subject: "Busy".to_string(), from_addr: "a@example.com".to_string(),
+                                to_addr: String::new(), cc: None, date: "2024-01-01".to_string(),
+                                body_plain: None, body_html: None, read: false, starred: false, deleted: false,
+                                priority: MessagePriority::Normal,
+                            })?;
+                            c.recompute_folder_counts(folder_id)
+                        })
+                        .unwrap();
+                        cache.update_account_last_sync("acc").unwrap();
+                    }
+                    cache
+                })
+            })
+            .collect();
+        let caches: Vec<MessageCache> = writers.into_iter().map(|w| w.join().unwrap()).collect();
+        assert_eq!(caches[0].get_messages_for_folder(folder_id, "acc").unwrap().len(), 200);
+    }
 }
@@ -7,7 +7,7 @@ use rusqlite::{params, OptionalExtension};
 impl MessageCache {
     /// Save or update OAuth token set for an account/provider
     pub fn save_oauth_token(&self, token: &OAuthTokenEntry) -> Result<()> {
-        let now = chrono::Utc::now().to_rfc3339();
+        let now = self.now().to_rfc3339();
         let encrypted_access = self.encrypt_value(&token.access_token)?;
         let encrypted_refresh = token
             .refresh_token
@@ -32,7 +32,7 @@ impl MessageCache {
                 &token.token_type, &token.scope, &token.expires_at,
                 &token.created_at, &now,
             ],
-        ).map_err(|e| Error::Other(format!("Failed to save oauth token: {}", e)))?;
+        ).map_err(|e| Error::Database(format!("Failed to save oauth token: {}", e)))?;
         Ok(())
     }
 
@@ -46,7 +46,7 @@ impl MessageCache {
             "SELECT id, account_id, provider, access_token, refresh_token, token_type, scope, expires_at, created_at
              FROM oauth_tokens
              WHERE account_id = ?1 AND provider = ?2"
-        ).map_err(|e| Error::Other(format!("Failed to prepare oauth token query: {}", e)))?;
+        ).map_err(|e| Error::Database(format!("Failed to prepare oauth token query: {}", e)))?;
 
         let row = stmt
             .query_row(params![account_id, provider], |row| {
@@ -67,7 +67,7 @@ impl MessageCache {
                 ))
             })
             .optional()
-            .map_err(|e| Error::Other(format!("Failed to load oauth token: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to load oauth token: {}", e)))?;
 
         match row {
             Some((enc_access, enc_refresh, mut token)) => {
@@ -90,7 +90,7 @@ impl MessageCache {
                 "DELETE FROM oauth_tokens WHERE account_id = ?1 AND provider = ?2",
                 params![account_id, provider],
             )
-            .map_err(|e| Error::Other(format!("Failed to delete oauth token: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to delete oauth token: {}", e)))?;
         Ok(())
     }
 }
@@ -1,6 +1,7 @@
 //! Offline outbox queue persistence operations
 
 use super::{MessageCache, QueuedOutboxMessage};
+use crate::common::types::MessagePriority;
 use crate::common::{Error, Result};
 use rusqlite::params;
 
@@ -8,24 +9,27 @@ impl MessageCache {
     /// Queue message for later sending when offline
     pub fn queue_outbox_message(&self, item: &QueuedOutboxMessage) -> Result<()> {
         self.conn.execute(
-            "INSERT INTO outbox_queue (id, account_id, to_addr, subject, body, attempt_count, last_error, created_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            "INSERT INTO outbox_queue (id, account_id, to_addr, subject, body, attempt_count, last_error, created_at,
+                                       cc, bcc, identity_id, request_receipt, priority)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
             params![
                 &item.id, &item.account_id, &item.to_addr, &item.subject,
                 &item.body, &item.attempt_count, &item.last_error, &item.created_at,
+                &item.cc, &item.bcc, &item.identity_id, item.request_receipt, item.priority.as_str(),
             ],
-        ).map_err(|e| Error::Other(format!("Failed to queue outbox message: {}", e)))?;
+        ).map_err(|e| Error::Database(format!("Failed to queue outbox message: {}", e)))?;
         Ok(())
     }
 
     /// Load queued outbox messages for an account
     pub fn load_outbox_messages(&self, account_id: &str) -> Result<Vec<QueuedOutboxMessage>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, account_id, to_addr, subject, body, attempt_count, last_error, created_at
+            "SELECT id, account_id, to_addr, subject, body, attempt_count, last_error, created_at,
+                    cc, bcc, identity_id, request_receipt, priority
              FROM outbox_queue
              WHERE account_id = ?1
              ORDER BY created_at ASC"
-        ).map_err(|e| Error::Other(format!("Failed to prepare outbox query: {}", e)))?;
+        ).map_err(|e| Error::Database(format!("Failed to prepare outbox query: {}", e)))?;
 
         let rows = stmt
             .query_map(params![account_id], |row| {
@@ -33,11 +37,13 @@ impl MessageCache {
                     id: row.get(0)?, account_id: row.get(1)?, to_addr: row.get(2)?,
                     subject: row.get(3)?, body: row.get(4)?, attempt_count: row.get(5)?,
                     last_error: row.get(6)?, created_at: row.get(7)?,
+                    cc: row.get(8)?, bcc: row.get(9)?, identity_id: row.get(10)?,
+                    request_receipt: row.get(11)?, priority: MessagePriority::parse(&row.get::<_, String>(12)?),
                 })
             })
-            .map_err(|e| Error::Other(format!("Failed to query outbox messages: {}", e)))?
+            .map_err(|e| Error::Database(format!("Failed to query outbox messages: {}", e)))?
             .collect::<std::result::Result<Vec<_>, _>>()
-            .map_err(|e| Error::Other(format!("Failed to collect outbox messages: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to collect outbox messages: {}", e)))?;
         Ok(rows)
     }
 
@@ -45,7 +51,7 @@ impl MessageCache {
     pub fn delete_outbox_message(&self, id: &str) -> Result<()> {
         self.conn
             .execute("DELETE FROM outbox_queue WHERE id = ?1", params![id])
-            .map_err(|e| Error::Other(format!("Failed to delete outbox message: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to delete outbox message: {}", e)))?;
         Ok(())
     }
 
@@ -58,7 +64,7 @@ impl MessageCache {
              WHERE id = ?1",
                 params![id, last_error],
             )
-            .map_err(|e| Error::Other(format!("Failed to update outbox failure: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to update outbox failure: {}", e)))?;
         Ok(())
     }
 }
@@ -77,8 +83,11 @@ mod tests {
 
         let item = QueuedOutboxMessage {
             id: "outbox-1".to_string(), account_id: "acc-1".to_string(),
-            to_addr: "user@example.com".to_string(), subject: "Queued".to_string(),
-            body: "Queued body".to_string(), attempt_count: 0, last_error: None,
+            to_addr: "user@example.com".to_string(), cc: "copy@example.com".to_string(),
+            bcc: "hidden@example.com".to_string(), identity_id: Some("id-work".to_string()),
+            subject: "Queued".to_string(), body: "Queued body".to_string(),
+            request_receipt: true, priority: MessagePriority::High,
+            attempt_count: 0, last_error: None,
             created_at: chrono::Utc::now().to_rfc3339(),
         };
         cache.queue_outbox_message(&item).unwrap();
@@ -86,6 +95,11 @@ mod tests {
         let loaded = cache.load_outbox_messages("acc-1").unwrap();
         assert_eq!(loaded.len(), 1);
         assert_eq!(loaded[0].subject, "Queued");
+        // Everything the compose window set comes back for the send
+        assert_eq!((loaded[0].cc.as_str(), loaded[0].bcc.as_str()), ("copy@example.com", "hidden@example.com"));
+        assert_eq!(loaded[0].identity_id.as_deref(), Some("id-work"));
+        assert!(loaded[0].request_receipt);
+        assert_eq!(loaded[0].priority, MessagePriority::High);
 
         cache.update_outbox_failure("outbox-1", "network down").unwrap();
         let loaded2 = cache.load_outbox_messages("acc-1").unwrap();
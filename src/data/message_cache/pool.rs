@@ -0,0 +1,183 @@
+//! Sharing one cache between threads
+//!
+//! A [`MessageCache`] is a single SQLite connection, usable by one thread at
+//! a time. [`CachePool`] is the cloneable handle threads share instead:
+//! writes go through the one writer connection, behind a lock, while reads
+//! use read-only connections to the same WAL database. Background sync and
+//! the UI can then query at the same time, and neither waits for a write in
+//! progress; readers see the last committed state.
+
+use super::MessageCache;
+use crate::common::clock::SharedClock;
+use crate::common::{Error, Result};
+use crate::service::security::SecurityService;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Read connections kept open between reads
+const MAX_IDLE_READERS: usize = 4;
+
+/// A [`MessageCache`] shared between threads
+#[derive(Clone)]
+pub struct CachePool {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    writer: Mutex<MessageCache>,
+    readers: Mutex<Vec<MessageCache>>,
+    path: PathBuf,
+    /// The writer's key, for read connections opened from now on
+    security: Mutex<Option<SecurityService>>,
+    /// Bumped when the key changes, so readers opened with the old key
+    /// aren't kept for reuse
+    generation: AtomicU64,
+    clock: SharedClock,
+}
+
+fn unavailable<T>(_: T) -> Error {
+    Error::Database("The message cache is unavailable".into())
+}
+
+impl CachePool {
+    /// Share `writer`. Read connections open to its database as needed.
+    pub fn new(writer: MessageCache) -> Self {
+        let (path, security, clock) = (writer.path.clone(), writer.security.clone(), writer.clock.clone());
+        Self {
+            inner: Arc::new(Inner {
+                writer: Mutex::new(writer),
+                readers: Mutex::new(Vec::new()),
+                path,
+                security: Mutex::new(security),
+                generation: AtomicU64::new(0),
+                clock,
+            }),
+        }
+    }
+
+    /// Run `f` on a read-only connection. Writes made through it fail.
+    pub fn read<T>(&self, f: impl FnOnce(&MessageCache) -> Result<T>) -> Result<T> {
+        let generation = self.inner.generation.load(Ordering::Acquire);
+        let idle = self.inner.readers.lock().map_err(unavailable)?.pop();
+        let reader = match idle {
+            Some(reader) => reader,
+            None => MessageCache::connect_reader(
+                self.inner.path.clone(),
+                self.inner.security.lock().map_err(unavailable)?.clone(),
+                self.inner.clock.clone(),
+            )?,
+        };
+        let result = f(&reader);
+        if let Ok(mut readers) = self.inner.readers.lock() {
+            if readers.len() < MAX_IDLE_READERS && self.inner.generation.load(Ordering::Acquire) == generation {
+                readers.push(reader);
+            }
+        }
+        result
+    }
+
+    /// Run `f` on the writer connection, after any write in progress
+    pub fn write<T>(&self, f: impl FnOnce(&MessageCache) -> Result<T>) -> Result<T> {
+        f(&*self.inner.writer.lock().map_err(unavailable)?)
+    }
+
+    /// Run `f` on the writer connection with leave to change how it stores
+    /// data, such as its encryption key. Reads after it use the new key.
+    pub fn reconfigure<T>(&self, f: impl FnOnce(&mut MessageCache) -> Result<T>) -> Result<T> {
+        let mut writer = self.inner.writer.lock().map_err(unavailable)?;
+        let result = f(&mut writer);
+        *self.inner.security.lock().map_err(unavailable)? = writer.security.clone();
+        self.inner.generation.fetch_add(1, Ordering::AcqRel);
+        self.inner.readers.lock().map_err(unavailable)?.clear();
+        result
+    }
+
+    /// Close the idle read connections and write the WAL back into the
+    /// database file, so the next start opens a single, complete file.
+    /// The pool can still be used afterwards.
+    pub fn checkpoint(&self) -> Result<()> {
+        self.inner.readers.lock().map_err(unavailable)?.clear();
+        self.write(|c| c.checkpoint())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::types::MessagePriority;
+    use crate::data::message_cache::CachedMessage;
+    use std::env;
+
+    fn batch(folder_id: i64, first_uid: u32) -> Vec<CachedMessage> {
+        (first_uid..first_uid + 50)
+            .map(|uid| CachedMessage {
+                id: 0, uid, folder_id, message_id: format!("p{}-{}@example.com", folder_id, uid),
+                subject: "Pooled".to_string(), from_addr: "a@example.com".to_string(),
+                to_addr: String::new(), cc: None, date: "2024-01-01".to_string(),
+                body_plain: None, body_html: None, read: false, starred: false, deleted: false, priority: MessagePriority::Normal,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_concurrent_readers_and_writers() {
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_pool_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        let pool = CachePool::new(MessageCache::new(temp_dir, None).unwrap());
+        let folders: Vec<i64> =
+            ["INBOX", "Archive"].iter().map(|path| pool.write(|c| c.ensure_folder("acc", path)).unwrap().id).collect();
+
+        let mut threads = Vec::new();
+        for &folder_id in &folders {
+            let pool = pool.clone();
+            threads.push(std::thread::spawn(move || {
+                for round in 0..20 {
+                    pool.write(|c| c.save_messages(&batch(folder_id, round * 50 + 1))).unwrap();
+                }
+            }));
+        }
+        for reader in 0..4 {
+            let (pool, folder_id) = (pool.clone(), folders[reader % 2]);
+            threads.push(std::thread::spawn(move || {
+                for _ in 0..50 {
+                    // Batches are committed whole, so readers never see part of one
+                    let seen = pool.read(|c| c.get_messages_for_folder(folder_id, "acc")).unwrap().len();
+                    assert_eq!(seen % 50, 0);
+                }
+            }));
+        }
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        for folder_id in folders {
+            assert_eq!(pool.read(|c| c.get_messages_for_folder(folder_id, "acc")).unwrap().len(), 1000);
+        }
+        assert!(pool.read(|c| c.ensure_folder("acc", "Drafts")).is_err());
+    }
+
+    #[test]
+    fn test_readers_follow_a_new_key() {
+        use crate::data::message_cache::ContentEncryption;
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_pool_key_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        let pool = CachePool::new(MessageCache::new(temp_dir, None).unwrap());
+        let folder_id = pool.write(|c| c.ensure_folder("acc", "INBOX")).unwrap().id;
+        // An idle reader opened before the key was set
+        pool.read(|c| c.get_messages_for_folder(folder_id, "acc")).unwrap();
+
+        pool.reconfigure(|c| {
+            c.set_security(Some(SecurityService::from_master_password("pw", b"0123456789abcdef")?));
+            c.set_content_encryption(ContentEncryption::Bodies);
+            Ok(())
+        })
+        .unwrap();
+        let mut messages = batch(folder_id, 1);
+        messages[0].body_plain = Some("Sealed".to_string());
+        pool.write(|c| c.save_messages(&messages[..1])).unwrap();
+
+        let read = pool.read(|c| c.get_messages_for_folder(folder_id, "acc")).unwrap();
+        assert_eq!(read[0].body_plain.as_deref(), Some("Sealed"));
+    }
+}
@@ -0,0 +1,89 @@
+//! Mailbox quota persistence operations
+
+use super::{CachedQuota, MessageCache};
+use crate::common::{Error, Result};
+use rusqlite::{params, OptionalExtension};
+
+impl MessageCache {
+    /// Remember an account's last reported quota, or forget it when the
+    /// server reports none so the usage indicator is hidden.
+    pub fn save_quota(&self, account_id: &str, quota: Option<&CachedQuota>) -> Result<()> {
+        let Some(quota) = quota else {
+            self.conn
+                .execute("DELETE FROM account_quota WHERE account_id = ?1", params![account_id])
+                .map_err(|e| Error::Database(format!("Failed to clear quota: {}", e)))?;
+            return Ok(());
+        };
+        self.conn
+            .execute(
+                "INSERT INTO account_quota
+                    (account_id, storage_used, storage_limit, messages_used, messages_limit, checked_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT(account_id) DO UPDATE SET
+                    storage_used = excluded.storage_used, storage_limit = excluded.storage_limit,
+                    messages_used = excluded.messages_used, messages_limit = excluded.messages_limit,
+                    checked_at = excluded.checked_at",
+                params![
+                    account_id,
+                    quota.storage_used,
+                    quota.storage_limit,
+                    quota.messages_used,
+                    quota.messages_limit,
+                    quota.checked_at
+                ],
+            )
+            .map_err(|e| Error::Database(format!("Failed to save quota: {}", e)))?;
+        Ok(())
+    }
+
+    /// An account's last reported quota
+    pub fn get_quota(&self, account_id: &str) -> Result<Option<CachedQuota>> {
+        self.conn
+            .query_row(
+                "SELECT storage_used, storage_limit, messages_used, messages_limit, checked_at
+                 FROM account_quota WHERE account_id = ?1",
+                params![account_id],
+                |row| {
+                    Ok(CachedQuota {
+                        storage_used: row.get(0)?,
+                        storage_limit: row.get(1)?,
+                        messages_used: row.get(2)?,
+                        messages_limit: row.get(3)?,
+                        checked_at: row.get(4)?,
+                    })
+                },
+            )
+            .optional()
+            .map_err(|e| Error::Database(format!("Failed to get quota: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_quota_roundtrip() {
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_quota_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+        assert_eq!(cache.get_quota("acc").unwrap(), None);
+
+        let mut quota = CachedQuota {
+            storage_used: Some(900),
+            storage_limit: Some(1000),
+            messages_used: None,
+            messages_limit: None,
+            checked_at: "2025-03-12T15:30:00Z".to_string(),
+        };
+        cache.save_quota("acc", Some(&quota)).unwrap();
+        quota.storage_used = Some(950);
+        cache.save_quota("acc", Some(&quota)).unwrap();
+        assert_eq!(cache.get_quota("acc").unwrap(), Some(quota));
+        assert_eq!(cache.get_quota("other").unwrap(), None);
+
+        cache.save_quota("acc", None).unwrap();
+        assert_eq!(cache.get_quota("acc").unwrap(), None);
+    }
+}
@@ -0,0 +1,198 @@
+//! Blocked, trusted and junk senders
+//!
+//! A sender can be on one list at a time. The trusted list also takes
+//! domains, which trust everyone at the domain and its subdomains; a
+//! blocked or junk entry for the address or domain always wins over trust.
+
+use super::MessageCache;
+use crate::common::{Error, Result};
+use rusqlite::params;
+
+/// Which per-account sender list an address is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SenderList {
+    /// Mail from these senders goes straight to Trash
+    Blocked,
+    /// Mail from these senders, or anyone at these domains, is trusted:
+    /// remote images load, and there are no link warnings, spam scoring or
+    /// prompt before opening attachments
+    Trusted,
+    /// Mail from these senders goes to the Junk folder; senders land here
+    /// when their messages are marked as spam
+    Junk,
+}
+
+impl SenderList {
+    pub const ALL: [SenderList; 3] = [SenderList::Blocked, SenderList::Trusted, SenderList::Junk];
+
+    fn table(self) -> &'static str {
+        match self {
+            SenderList::Blocked => "blocked_senders",
+            SenderList::Trusted => "trusted_senders",
+            SenderList::Junk => "junk_senders",
+        }
+    }
+}
+
+/// The stored form of an address or domain: trimmed and lowercased, with
+/// the `@` of "@example.com" dropped
+fn normalize(address: &str) -> String {
+    let address = address.trim().to_lowercase();
+    address.strip_prefix('@').map(str::to_string).unwrap_or(address)
+}
+
+/// The entries that cover `sender`: the address itself, its domain and
+/// each parent domain down to two labels, so "ana@mail.example.com"
+/// is covered by "mail.example.com" and "example.com" but not by "com"
+fn covering_entries(sender: &str) -> Vec<String> {
+    let sender = normalize(sender);
+    let domain = sender.rsplit_once('@').map_or(sender.as_str(), |(_, domain)| domain).to_string();
+    let labels: Vec<&str> = domain.split('.').collect();
+    let mut entries: Vec<String> = (0..labels.len().saturating_sub(1)).map(|i| labels[i..].join(".")).collect();
+    if sender != domain {
+        entries.insert(0, sender);
+    }
+    entries
+}
+
+impl MessageCache {
+    /// Put `address` on `list` for the account, taking it off the other
+    /// lists, since a sender can't be both blocked and trusted
+    pub fn add_sender(&self, account_id: &str, list: SenderList, address: &str) -> Result<()> {
+        let address = normalize(address);
+        for other in SenderList::ALL.into_iter().filter(|other| *other != list) {
+            self.remove_sender(account_id, other, &address)?;
+        }
+        self.conn
+            .execute(
+                &format!(
+                    "INSERT OR IGNORE INTO {} (account_id, address, added_at) VALUES (?1, ?2, ?3)",
+                    list.table()
+                ),
+                params![account_id, address, self.now().to_rfc3339()],
+            )
+            .map_err(|e| Error::Database(format!("Failed to add sender to {}: {}", list.table(), e)))?;
+        Ok(())
+    }
+
+    /// Take `address` off `list` for the account
+    pub fn remove_sender(&self, account_id: &str, list: SenderList, address: &str) -> Result<()> {
+        self.conn
+            .execute(
+                &format!("DELETE FROM {} WHERE account_id = ?1 AND address = ?2", list.table()),
+                params![account_id, normalize(address)],
+            )
+            .map_err(|e| Error::Database(format!("Failed to remove sender from {}: {}", list.table(), e)))?;
+        Ok(())
+    }
+
+    /// Addresses on `list` for the account, in alphabetical order
+    pub fn get_senders(&self, account_id: &str, list: SenderList) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!("SELECT address FROM {} WHERE account_id = ?1 ORDER BY address", list.table()))
+            .map_err(|e| Error::Database(format!("Failed to prepare sender query: {}", e)))?;
+        let rows = stmt
+            .query_map(params![account_id], |row| row.get(0))
+            .map_err(|e| Error::Database(format!("Failed to query {}: {}", list.table(), e)))?;
+        rows.collect::<std::result::Result<Vec<String>, _>>()
+            .map_err(|e| Error::Database(format!("Failed to read {}: {}", list.table(), e)))
+    }
+
+    /// Whether `address` is on `list` for the account
+    pub fn is_sender_on(&self, account_id: &str, list: SenderList, address: &str) -> Result<bool> {
+        self.conn
+            .query_row(
+                &format!("SELECT EXISTS(SELECT 1 FROM {} WHERE account_id = ?1 AND address = ?2)", list.table()),
+                params![account_id, normalize(address)],
+                |row| row.get(0),
+            )
+            .map_err(|e| Error::Database(format!("Failed to check {}: {}", list.table(), e)))
+    }
+
+    /// Whether mail from `address_or_domain` is trusted for the account:
+    /// the address or one of its domains is on the trusted list, and
+    /// neither is blocked or junk. Every feature that relaxes a check for
+    /// trusted senders asks this.
+    pub fn is_trusted_sender(&self, account_id: &str, address_or_domain: &str) -> Result<bool> {
+        let entries = covering_entries(address_or_domain);
+        if entries.is_empty() {
+            return Ok(false);
+        }
+        for list in [SenderList::Blocked, SenderList::Junk] {
+            if self.is_any_on(account_id, list, &entries)? {
+                return Ok(false);
+            }
+        }
+        self.is_any_on(account_id, SenderList::Trusted, &entries)
+    }
+
+    fn is_any_on(&self, account_id: &str, list: SenderList, entries: &[String]) -> Result<bool> {
+        for entry in entries {
+            if self.is_sender_on(account_id, list, entry)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn test_sender_lists() {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_senders_{}", nanos));
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+
+        cache.add_sender("acc", SenderList::Blocked, " Spam@Example.com").unwrap();
+        cache.add_sender("acc", SenderList::Blocked, "spam@example.com").unwrap();
+        cache.add_sender("acc", SenderList::Trusted, "ana@example.com").unwrap();
+        assert_eq!(cache.get_senders("acc", SenderList::Blocked).unwrap(), vec!["spam@example.com"]);
+        assert!(cache.is_sender_on("acc", SenderList::Trusted, "ANA@example.com").unwrap());
+        assert!(!cache.is_sender_on("other", SenderList::Trusted, "ana@example.com").unwrap());
+
+        // Blocking a trusted sender takes them off the trusted list
+        cache.add_sender("acc", SenderList::Blocked, "ana@example.com").unwrap();
+        assert!(cache.get_senders("acc", SenderList::Trusted).unwrap().is_empty());
+        cache.remove_sender("acc", SenderList::Blocked, "spam@example.com").unwrap();
+        assert_eq!(cache.get_senders("acc", SenderList::Blocked).unwrap(), vec!["ana@example.com"]);
+
+        // Marking mail as spam moves a sender to the junk list
+        cache.add_sender("acc", SenderList::Junk, "ana@example.com").unwrap();
+        assert!(cache.get_senders("acc", SenderList::Blocked).unwrap().is_empty());
+        assert!(cache.is_sender_on("acc", SenderList::Junk, "ana@example.com").unwrap());
+    }
+
+    #[test]
+    fn test_trusted_senders() {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_trusted_{}", nanos));
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+
+        cache.add_sender("acc", SenderList::Trusted, "ana@example.com").unwrap();
+        cache.add_sender("acc", SenderList::Trusted, " @Work.org").unwrap();
+        assert_eq!(cache.get_senders("acc", SenderList::Trusted).unwrap(), vec!["ana@example.com", "work.org"]);
+        assert!(cache.is_trusted_sender("acc", "Ana@Example.com").unwrap());
+        assert!(!cache.is_trusted_sender("acc", "bob@example.com").unwrap());
+        assert!(!cache.is_trusted_sender("other", "ana@example.com").unwrap());
+
+        // A trusted domain covers its addresses and subdomains, not lookalikes
+        assert!(cache.is_trusted_sender("acc", "bob@work.org").unwrap());
+        assert!(cache.is_trusted_sender("acc", "alerts@mail.work.org").unwrap());
+        assert!(cache.is_trusted_sender("acc", "work.org").unwrap());
+        assert!(!cache.is_trusted_sender("acc", "bob@notwork.org").unwrap());
+        assert!(!cache.is_trusted_sender("acc", "").unwrap());
+
+        // Blocking wins over trusting the domain
+        cache.add_sender("acc", SenderList::Blocked, "spam@work.org").unwrap();
+        assert!(!cache.is_trusted_sender("acc", "spam@work.org").unwrap());
+        assert!(cache.is_trusted_sender("acc", "bob@work.org").unwrap());
+        cache.add_sender("acc", SenderList::Junk, "mail.work.org").unwrap();
+        assert!(!cache.is_trusted_sender("acc", "alerts@mail.work.org").unwrap());
+    }
+}
@@ -15,7 +15,7 @@ impl MessageCache {
                 &signature.content_plain, &signature.content_html,
                 &signature.is_default, &signature.created_at,
             ],
-        ).map_err(|e| Error::Other(format!("Failed to create signature: {}", e)))?;
+        ).map_err(|e| Error::Database(format!("Failed to create signature: {}", e)))?;
 
         if signature.is_default {
             self.conn
@@ -23,7 +23,7 @@ impl MessageCache {
                     "UPDATE signatures SET is_default = 0 WHERE account_id = ?1 AND id != ?2",
                     params![&signature.account_id, &signature.id],
                 )
-                .map_err(|e| Error::Other(format!("Failed to update defaults: {}", e)))?;
+                .map_err(|e| Error::Database(format!("Failed to update defaults: {}", e)))?;
         }
         Ok(())
     }
@@ -33,7 +33,7 @@ impl MessageCache {
         let mut stmt = self.conn.prepare(
             "SELECT id, account_id, name, content_plain, content_html, is_default, created_at
              FROM signatures WHERE account_id = ?1 ORDER BY name",
-        ).map_err(|e| Error::Other(format!("Failed to prepare statement: {}", e)))?;
+        ).map_err(|e| Error::Database(format!("Failed to prepare statement: {}", e)))?;
 
         let signatures = stmt
             .query_map(params![account_id], |row| {
@@ -43,9 +43,9 @@ impl MessageCache {
                     is_default: row.get(5)?, created_at: row.get(6)?,
                 })
             })
-            .map_err(|e| Error::Other(format!("Failed to query signatures: {}", e)))?
+            .map_err(|e| Error::Database(format!("Failed to query signatures: {}", e)))?
             .collect::<std::result::Result<Vec<_>, _>>()
-            .map_err(|e| Error::Other(format!("Failed to collect signatures: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to collect signatures: {}", e)))?;
         Ok(signatures)
     }
 
@@ -54,7 +54,7 @@ impl MessageCache {
         let mut stmt = self.conn.prepare(
             "SELECT id, account_id, name, content_plain, content_html, is_default, created_at
              FROM signatures WHERE id = ?1",
-        ).map_err(|e| Error::Other(format!("Failed to prepare statement: {}", e)))?;
+        ).map_err(|e| Error::Database(format!("Failed to prepare statement: {}", e)))?;
 
         let signature = stmt
             .query_row(params![signature_id], |row| {
@@ -65,7 +65,7 @@ impl MessageCache {
                 })
             })
             .optional()
-            .map_err(|e| Error::Other(format!("Failed to get signature: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to get signature: {}", e)))?;
         Ok(signature)
     }
 
@@ -74,7 +74,7 @@ impl MessageCache {
         let mut stmt = self.conn.prepare(
             "SELECT id, account_id, name, content_plain, content_html, is_default, created_at
              FROM signatures WHERE account_id = ?1 AND is_default = 1",
-        ).map_err(|e| Error::Other(format!("Failed to prepare statement: {}", e)))?;
+        ).map_err(|e| Error::Database(format!("Failed to prepare statement: {}", e)))?;
 
         let signature = stmt
             .query_row(params![account_id], |row| {
@@ -85,7 +85,7 @@ impl MessageCache {
                 })
             })
             .optional()
-            .map_err(|e| Error::Other(format!("Failed to get default signature: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to get default signature: {}", e)))?;
         Ok(signature)
     }
 
@@ -101,7 +101,7 @@ impl MessageCache {
                     &signature.is_default, &signature.id
                 ],
             )
-            .map_err(|e| Error::Other(format!("Failed to update signature: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to update signature: {}", e)))?;
 
         if signature.is_default {
             self.conn
@@ -109,16 +109,19 @@ impl MessageCache {
                     "UPDATE signatures SET is_default = 0 WHERE account_id = ?1 AND id != ?2",
                     params![&signature.account_id, &signature.id],
                 )
-                .map_err(|e| Error::Other(format!("Failed to update defaults: {}", e)))?;
+                .map_err(|e| Error::Database(format!("Failed to update defaults: {}", e)))?;
         }
         Ok(())
     }
 
-    /// Delete a signature
+    /// Delete a signature; identities using it fall back to the default
     pub fn delete_signature(&self, signature_id: &str) -> Result<()> {
         self.conn
             .execute("DELETE FROM signatures WHERE id = ?1", params![signature_id])
-            .map_err(|e| Error::Other(format!("Failed to delete signature: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to delete signature: {}", e)))?;
+        self.conn
+            .execute("UPDATE identities SET signature_id = NULL WHERE signature_id = ?1", params![signature_id])
+            .map_err(|e| Error::Database(format!("Failed to update identities: {}", e)))?;
         Ok(())
     }
 }
@@ -0,0 +1,154 @@
+//! Snoozed message persistence operations
+
+use super::{MessageCache, SnoozedMessage};
+use crate::common::{Error, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::params;
+
+/// Wake times are stored in one fixed format so they compare as text.
+fn wake_key(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+impl MessageCache {
+    /// Move a message into the snooze folder until `wake_at`.
+    ///
+    /// Snoozing an already snoozed message only changes its wake time; it
+    /// still returns to the folder it was first snoozed from.
+    pub fn snooze_message(&self, message_id: i64, snooze_folder_id: i64, wake_at: DateTime<Utc>) -> Result<()> {
+        let inserted = self
+            .conn
+            .execute(
+                "INSERT INTO snoozed_messages (message_id, wake_at, original_folder_id)
+                 SELECT id, ?2, folder_id FROM messages WHERE id = ?1
+                 ON CONFLICT(message_id) DO UPDATE SET wake_at = excluded.wake_at",
+                params![message_id, wake_key(wake_at)],
+            )
+            .map_err(|e| Error::Database(format!("Failed to snooze message: {}", e)))?;
+        if inserted == 0 {
+            return Err(Error::NotFound(format!("Message {} is not cached", message_id)));
+        }
+        self.move_message(message_id, snooze_folder_id)
+    }
+
+    /// Return every message whose wake time has passed to its original
+    /// folder, marked unread. Returns the messages that woke.
+    pub fn wake_due_messages(&self, now: DateTime<Utc>) -> Result<Vec<SnoozedMessage>> {
+        self.in_transaction(|c| {
+            // Messages deleted while snoozed never wake
+            c.conn
+                .execute(
+                    "DELETE FROM snoozed_messages WHERE message_id NOT IN (SELECT id FROM messages)",
+                    [],
+                )
+                .map_err(|e| Error::Database(format!("Failed to clean up snoozed messages: {}", e)))?;
+
+            let mut stmt = c
+                .conn
+                .prepare(
+                    "SELECT s.message_id, home.account_id, m.uid, s.wake_at, here.path, home.path, home.id
+                     FROM snoozed_messages s
+                     JOIN messages m ON m.id = s.message_id
+                     JOIN folders here ON here.id = m.folder_id
+                     JOIN folders home ON home.id = s.original_folder_id
+                     WHERE s.wake_at <= ?1
+                     ORDER BY s.wake_at",
+                )
+                .map_err(|e| Error::Database(format!("Failed to prepare snooze query: {}", e)))?;
+            let due = stmt
+                .query_map(params![wake_key(now)], |row| {
+                    Ok((
+                        SnoozedMessage {
+                            message_id: row.get(0)?,
+                            account_id: row.get(1)?,
+                            uid: row.get(2)?,
+                            wake_at: row.get(3)?,
+                            snoozed_folder: row.get(4)?,
+                            original_folder: row.get(5)?,
+                        },
+                        row.get::<_, i64>(6)?,
+                    ))
+                })
+                .map_err(|e| Error::Database(format!("Failed to query snoozed messages: {}", e)))?
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .map_err(|e| Error::Database(format!("Failed to read snoozed messages: {}", e)))?;
+            drop(stmt);
+
+            for (message, folder_id) in &due {
+                c.move_message(message.message_id, *folder_id)?;
+                c.set_message_read(message.message_id, false)?;
+                c.conn
+                    .execute("DELETE FROM snoozed_messages WHERE message_id = ?1", params![message.message_id])
+                    .map_err(|e| Error::Database(format!("Failed to wake message: {}", e)))?;
+            }
+            Ok(due.into_iter().map(|(message, _)| message).collect())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::types::MessagePriority;
+    use crate::data::message_cache::CachedMessage;
+    use std::env;
+
+    #[test]
+    fn test_snooze_and_wake() {
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_snooze_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+        let inbox = cache.ensure_folder("acc", "INBOX").unwrap().id;
+        let snoozed = cache.ensure_folder("acc", "Snoozed").unwrap().id;
+        let save = |uid: u32| {
+            cache.save_message(&CachedMessage {
+                id: 0, uid, folder_id: inbox, message_id: format!("s{}@example.com", uid),
+                subject: "Later".to_string(), from_addr: "a@example.com".to_string(),
+                to_addr: String::new(), cc: None, date: "2024-01-01".to_string(),
+                body_plain: None, body_html: None, read: true, starred: false, deleted: false, priority: MessagePriority::Normal,
+            }).unwrap()
+        };
+        let (soon, later) = (save(1), save(2));
+        let now = Utc::now();
+
+        cache.snooze_message(soon, snoozed, now + chrono::Duration::hours(1)).unwrap();
+        cache.snooze_message(later, snoozed, now + chrono::Duration::days(2)).unwrap();
+        assert_eq!(cache.get_message(soon).unwrap().unwrap().folder_id, snoozed);
+        assert!(cache.wake_due_messages(now).unwrap().is_empty());
+
+        let woken = cache.wake_due_messages(now + chrono::Duration::hours(2)).unwrap();
+        assert_eq!(woken.len(), 1);
+        assert_eq!(woken[0].message_id, soon);
+        assert_eq!((woken[0].snoozed_folder.as_str(), woken[0].original_folder.as_str()), ("Snoozed", "INBOX"));
+        let message = cache.get_message(soon).unwrap().unwrap();
+        assert_eq!(message.folder_id, inbox);
+        assert!(!message.read);
+
+        // Already woken messages don't wake twice
+        assert!(cache.wake_due_messages(now + chrono::Duration::hours(2)).unwrap().is_empty());
+        assert!(cache.snooze_message(9999, snoozed, now).is_err());
+    }
+    #[test]
+    fn test_wake_by_cache_clock() {
+        use crate::common::clock::MockClock;
+        use chrono::TimeZone;
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_snooze_clock_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        let clock = MockClock::new(Utc.with_ymd_and_hms(2025, 3, 12, 9, 0, 0).unwrap());
+        let cache = MessageCache::new(temp_dir, None).unwrap().with_clock(clock.clone());
+        let inbox = cache.ensure_folder("acc", "INBOX").unwrap().id;
+        let snoozed = cache.ensure_folder("acc", "Snoozed").unwrap().id;
+        let id = cache.save_message(&CachedMessage {
+            id: 0, uid: 1, folder_id: inbox, message_id: "c1@example.com".to_string(),
+            subject: "Later".to_string(), from_addr: "a@example.com".to_string(),
+            to_addr: String::new(), cc: None, date: "2024-01-01".to_string(),
+            body_plain: None, body_html: None, read: true, starred: false, deleted: false, priority: MessagePriority::Normal,
+        }).unwrap();
+        cache.snooze_message(id, snoozed, cache.now() + chrono::Duration::hours(1)).unwrap();
+
+        clock.advance(chrono::Duration::minutes(59));
+        assert!(cache.wake_due_messages(cache.now()).unwrap().is_empty());
+        clock.advance(chrono::Duration::minutes(1));
+        assert_eq!(cache.wake_due_messages(cache.now()).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,134 @@
+//! Spam classifier training data
+//!
+//! Token counts learned from messages marked as spam or not spam. Each
+//! trained message is remembered with its tokens, so marking it the other
+//! way later moves its tokens to the other side instead of counting twice.
+
+use super::MessageCache;
+use crate::common::{Error, Result};
+use rusqlite::{params, OptionalExtension};
+use std::collections::HashMap;
+
+impl MessageCache {
+    /// Learn `tokens` from a message (`message_key`, usually its
+    /// Message-ID) as spam or not spam. A message trained the other way
+    /// before is untrained first. Returns false if it was already trained
+    /// this way.
+    pub fn train_spam(&self, account_id: &str, message_key: &str, tokens: &[String], spam: bool) -> Result<bool> {
+        self.in_transaction(|c| {
+            let previous: Option<(bool, String)> = c
+                .conn
+                .query_row(
+                    "SELECT spam, tokens FROM spam_trained WHERE account_id = ?1 AND message_key = ?2",
+                    params![account_id, message_key],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()
+                .map_err(|e| Error::Database(format!("Failed to check spam training: {}", e)))?;
+            match previous {
+                Some((was_spam, _)) if was_spam == spam => return Ok(false),
+                Some((was_spam, old_tokens)) => {
+                    let old_tokens: Vec<String> = old_tokens.split_whitespace().map(str::to_string).collect();
+                    c.count_spam_tokens(account_id, &old_tokens, was_spam, -1)?;
+                }
+                None => {}
+            }
+            c.count_spam_tokens(account_id, tokens, spam, 1)?;
+            c.conn
+                .execute(
+                    "INSERT INTO spam_trained (account_id, message_key, spam, tokens) VALUES (?1, ?2, ?3, ?4)
+                     ON CONFLICT(account_id, message_key) DO UPDATE SET spam = excluded.spam, tokens = excluded.tokens",
+                    params![account_id, message_key, spam, tokens.join(" ")],
+                )
+                .map_err(|e| Error::Database(format!("Failed to record spam training: {}", e)))?;
+            Ok(true)
+        })
+    }
+
+    fn count_spam_tokens(&self, account_id: &str, tokens: &[String], spam: bool, delta: i64) -> Result<()> {
+        let column = if spam { "spam_count" } else { "ham_count" };
+        let mut insert = self
+            .conn
+            .prepare(
+                "INSERT INTO spam_tokens (account_id, token, spam_count, ham_count) VALUES (?1, ?2, 0, 0)
+                 ON CONFLICT(account_id, token) DO NOTHING",
+            )
+            .map_err(|e| Error::Database(format!("Failed to prepare spam token insert: {}", e)))?;
+        let mut update = self
+            .conn
+            .prepare(&format!(
+                "UPDATE spam_tokens SET {column} = MAX(0, {column} + ?3) WHERE account_id = ?1 AND token = ?2"
+            ))
+            .map_err(|e| Error::Database(format!("Failed to prepare spam token update: {}", e)))?;
+        for token in tokens {
+            insert
+                .execute(params![account_id, token])
+                .map_err(|e| Error::Database(format!("Failed to add spam token: {}", e)))?;
+            update
+                .execute(params![account_id, token, delta])
+                .map_err(|e| Error::Database(format!("Failed to count spam token: {}", e)))?;
+        }
+        Ok(())
+    }
+
+    /// How many messages the account has trained as spam and as not spam
+    pub fn spam_training_counts(&self, account_id: &str) -> Result<(u32, u32)> {
+        self.conn
+            .query_row(
+                "SELECT COALESCE(SUM(spam), 0), COALESCE(SUM(1 - spam), 0) FROM spam_trained WHERE account_id = ?1",
+                params![account_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(|e| Error::Database(format!("Failed to count spam training: {}", e)))
+    }
+
+    /// Spam and not-spam counts of the known `tokens`; tokens never seen
+    /// are left out
+    pub fn spam_token_counts(&self, account_id: &str, tokens: &[String]) -> Result<HashMap<String, (u32, u32)>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT spam_count, ham_count FROM spam_tokens WHERE account_id = ?1 AND token = ?2")
+            .map_err(|e| Error::Database(format!("Failed to prepare spam token query: {}", e)))?;
+        let mut counts = HashMap::new();
+        for token in tokens {
+            let found: Option<(u32, u32)> = stmt
+                .query_row(params![account_id, token], |row| Ok((row.get(0)?, row.get(1)?)))
+                .optional()
+                .map_err(|e| Error::Database(format!("Failed to read spam token: {}", e)))?;
+            if let Some(found) = found.filter(|(spam, ham)| spam + ham > 0) {
+                counts.insert(token.clone(), found);
+            }
+        }
+        Ok(counts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_spam_training_is_reversible() {
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_spam_{}", std::process::id()));
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+        let tokens = |t: &[&str]| t.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+
+        assert!(cache.train_spam("acc", "<1@example.com>", &tokens(&["viagra", "offer"]), true).unwrap());
+        assert!(!cache.train_spam("acc", "<1@example.com>", &tokens(&["viagra", "offer"]), true).unwrap());
+        assert!(cache.train_spam("acc", "<2@example.com>", &tokens(&["meeting", "offer"]), false).unwrap());
+        assert_eq!(cache.spam_training_counts("acc").unwrap(), (1, 1));
+        let counts = cache.spam_token_counts("acc", &tokens(&["viagra", "offer", "unseen"])).unwrap();
+        assert_eq!(counts.get("viagra"), Some(&(1, 0)));
+        assert_eq!(counts.get("offer"), Some(&(1, 1)));
+        assert!(!counts.contains_key("unseen"));
+        assert_eq!(cache.spam_training_counts("other").unwrap(), (0, 0));
+
+        // Correcting a message moves its tokens to the other side
+        assert!(cache.train_spam("acc", "<1@example.com>", &tokens(&["viagra", "offer"]), false).unwrap());
+        assert_eq!(cache.spam_training_counts("acc").unwrap(), (0, 2));
+        let counts = cache.spam_token_counts("acc", &tokens(&["viagra", "offer"])).unwrap();
+        assert_eq!(counts.get("viagra"), Some(&(0, 1)));
+        assert_eq!(counts.get("offer"), Some(&(0, 2)));
+    }
+}
@@ -1,6 +1,7 @@
 //! Tag and message-tag junction persistence operations
 
 use super::{CachedMessage, MessageCache, Tag};
+use crate::common::types::MessagePriority;
 use crate::common::{Error, Result};
 use rusqlite::{params, OptionalExtension};
 
@@ -9,11 +10,11 @@ impl MessageCache {
     pub fn create_tag(&self, tag: &Tag) -> Result<()> {
         self.conn
             .execute(
-                "INSERT INTO tags (id, account_id, name, color, created_at)
-             VALUES (?1, ?2, ?3, ?4, ?5)",
-                params![&tag.id, &tag.account_id, &tag.name, &tag.color, &tag.created_at],
+                "INSERT INTO tags (id, account_id, name, color, created_at, shortcut)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![&tag.id, &tag.account_id, &tag.name, &tag.color, &tag.created_at, &tag.shortcut],
             )
-            .map_err(|e| Error::Other(format!("Failed to create tag: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to create tag: {}", e)))?;
         Ok(())
     }
 
@@ -22,10 +23,10 @@ impl MessageCache {
         let mut stmt = self
             .conn
             .prepare(
-                "SELECT id, account_id, name, color, created_at
+                "SELECT id, account_id, name, color, created_at, shortcut
              FROM tags WHERE account_id = ?1 ORDER BY name",
             )
-            .map_err(|e| Error::Other(format!("Failed to prepare statement: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to prepare statement: {}", e)))?;
 
         let tags = stmt
             .query_map(params![account_id], |row| {
@@ -35,11 +36,12 @@ impl MessageCache {
                     name: row.get(2)?,
                     color: row.get(3)?,
                     created_at: row.get(4)?,
+                    shortcut: row.get(5)?,
                 })
             })
-            .map_err(|e| Error::Other(format!("Failed to query tags: {}", e)))?
+            .map_err(|e| Error::Database(format!("Failed to query tags: {}", e)))?
             .collect::<std::result::Result<Vec<_>, _>>()
-            .map_err(|e| Error::Other(format!("Failed to collect tags: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to collect tags: {}", e)))?;
         Ok(tags)
     }
 
@@ -47,8 +49,8 @@ impl MessageCache {
     pub fn get_tag(&self, tag_id: &str) -> Result<Option<Tag>> {
         let mut stmt = self
             .conn
-            .prepare("SELECT id, account_id, name, color, created_at FROM tags WHERE id = ?1")
-            .map_err(|e| Error::Other(format!("Failed to prepare statement: {}", e)))?;
+            .prepare("SELECT id, account_id, name, color, created_at, shortcut FROM tags WHERE id = ?1")
+            .map_err(|e| Error::Database(format!("Failed to prepare statement: {}", e)))?;
 
         let tag = stmt
             .query_row(params![tag_id], |row| {
@@ -58,10 +60,37 @@ impl MessageCache {
                     name: row.get(2)?,
                     color: row.get(3)?,
                     created_at: row.get(4)?,
+                    shortcut: row.get(5)?,
                 })
             })
             .optional()
-            .map_err(|e| Error::Other(format!("Failed to get tag: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to get tag: {}", e)))?;
+        Ok(tag)
+    }
+
+    /// The account's tag assigned to a shortcut key, if any
+    pub fn get_tag_by_shortcut(&self, account_id: &str, key: &str) -> Result<Option<Tag>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, account_id, name, color, created_at, shortcut
+             FROM tags WHERE account_id = ?1 AND shortcut = ?2",
+            )
+            .map_err(|e| Error::Database(format!("Failed to prepare statement: {}", e)))?;
+
+        let tag = stmt
+            .query_row(params![account_id, key], |row| {
+                Ok(Tag {
+                    id: row.get(0)?,
+                    account_id: row.get(1)?,
+                    name: row.get(2)?,
+                    color: row.get(3)?,
+                    created_at: row.get(4)?,
+                    shortcut: row.get(5)?,
+                })
+            })
+            .optional()
+            .map_err(|e| Error::Database(format!("Failed to get tag: {}", e)))?;
         Ok(tag)
     }
 
@@ -69,10 +98,10 @@ impl MessageCache {
     pub fn update_tag(&self, tag: &Tag) -> Result<()> {
         self.conn
             .execute(
-                "UPDATE tags SET name = ?1, color = ?2 WHERE id = ?3",
-                params![&tag.name, &tag.color, &tag.id],
+                "UPDATE tags SET name = ?1, color = ?2, shortcut = ?3 WHERE id = ?4",
+                params![&tag.name, &tag.color, &tag.shortcut, &tag.id],
             )
-            .map_err(|e| Error::Other(format!("Failed to update tag: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to update tag: {}", e)))?;
         Ok(())
     }
 
@@ -80,20 +109,20 @@ impl MessageCache {
     pub fn delete_tag(&self, tag_id: &str) -> Result<()> {
         self.conn
             .execute("DELETE FROM tags WHERE id = ?1", params![tag_id])
-            .map_err(|e| Error::Other(format!("Failed to delete tag: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to delete tag: {}", e)))?;
         Ok(())
     }
 
     /// Add a tag to a message
     pub fn add_tag_to_message(&self, message_id: i64, tag_id: &str) -> Result<()> {
-        let now = chrono::Utc::now().to_rfc3339();
+        let now = self.now().to_rfc3339();
         self.conn
             .execute(
                 "INSERT OR IGNORE INTO message_tags (message_id, tag_id, created_at)
              VALUES (?1, ?2, ?3)",
                 params![message_id, tag_id, now],
             )
-            .map_err(|e| Error::Other(format!("Failed to add tag to message: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to add tag to message: {}", e)))?;
         Ok(())
     }
 
@@ -104,7 +133,7 @@ impl MessageCache {
                 "DELETE FROM message_tags WHERE message_id = ?1 AND tag_id = ?2",
                 params![message_id, tag_id],
             )
-            .map_err(|e| Error::Other(format!("Failed to remove tag from message: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to remove tag from message: {}", e)))?;
         Ok(())
     }
 
@@ -113,13 +142,13 @@ impl MessageCache {
         let mut stmt = self
             .conn
             .prepare(
-                "SELECT t.id, t.account_id, t.name, t.color, t.created_at
+                "SELECT t.id, t.account_id, t.name, t.color, t.created_at, t.shortcut
              FROM tags t
              INNER JOIN message_tags mt ON t.id = mt.tag_id
              WHERE mt.message_id = ?1
              ORDER BY t.name",
             )
-            .map_err(|e| Error::Other(format!("Failed to prepare statement: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to prepare statement: {}", e)))?;
 
         let tags = stmt
             .query_map(params![message_id], |row| {
@@ -129,11 +158,12 @@ impl MessageCache {
                     name: row.get(2)?,
                     color: row.get(3)?,
                     created_at: row.get(4)?,
+                    shortcut: row.get(5)?,
                 })
             })
-            .map_err(|e| Error::Other(format!("Failed to query message tags: {}", e)))?
+            .map_err(|e| Error::Database(format!("Failed to query message tags: {}", e)))?
             .collect::<std::result::Result<Vec<_>, _>>()
-            .map_err(|e| Error::Other(format!("Failed to collect message tags: {}", e)))?;
+            .map_err(|e| Error::Database(format!("Failed to collect message tags: {}", e)))?;
         Ok(tags)
     }
 
@@ -141,12 +171,12 @@ impl MessageCache {
     pub fn get_messages_by_tag(&self, tag_id: &str) -> Result<Vec<CachedMessage>> {
         let mut stmt = self.conn.prepare(
             "SELECT m.id, m.uid, m.folder_id, m.message_id, m.subject, m.from_addr, m.to_addr, m.cc, m.date,
-                    m.body_plain, m.body_html, m.read, m.starred, m.deleted
+                    m.body_plain, m.body_html, m.read, m.starred, m.deleted, m.priority
              FROM messages m
              INNER JOIN message_tags mt ON m.id = mt.message_id
              WHERE mt.tag_id = ?1 AND m.deleted = 0
              ORDER BY m.date DESC"
-        ).map_err(|e| Error::Other(format!("Failed to prepare statement: {}", e)))?;
+        ).map_err(|e| Error::Database(format!("Failed to prepare statement: {}", e)))?;
 
         let messages = stmt
             .query_map(params![tag_id], |row| {
@@ -165,12 +195,13 @@ impl MessageCache {
                     read: row.get(11)?,
                     starred: row.get(12)?,
                     deleted: row.get(13)?,
+                    priority: MessagePriority::parse(&row.get::<_, String>(14)?),
                 })
             })
-            .map_err(|e| Error::Other(format!("Failed to query messages by tag: {}", e)))?
+            .map_err(|e| Error::Database(format!("Failed to query messages by tag: {}", e)))?
             .collect::<std::result::Result<Vec<_>, _>>()
-            .map_err(|e| Error::Other(format!("Failed to collect messages by tag: {}", e)))?;
-        Ok(messages)
+            .map_err(|e| Error::Database(format!("Failed to collect messages by tag: {}", e)))?;
+        self.open_messages(messages)
     }
 }
 
@@ -192,6 +223,7 @@ mod tests {
             name: "Work".to_string(),
             color: "#FF0000".to_string(),
             created_at: chrono::Utc::now().to_rfc3339(),
+            shortcut: None,
         };
 
         cache.create_tag(&tag).unwrap();
@@ -236,23 +268,30 @@ mod tests {
             from_addr: "sender@example.com".to_string(), to_addr: "recipient@example.com".to_string(),
             cc: None, date: chrono::Utc::now().to_rfc3339(),
             body_plain: Some("Test body".to_string()), body_html: None,
-            read: false, starred: false, deleted: false,
+            read: false, starred: false, deleted: false, priority: MessagePriority::Normal,
         };
         let message_id = cache.save_message(&message).unwrap();
 
         let tag1 = Tag {
             id: "tag-important".to_string(), account_id: "test@example.com".to_string(),
             name: "Important".to_string(), color: "#FF0000".to_string(),
-            created_at: chrono::Utc::now().to_rfc3339(),
+            created_at: chrono::Utc::now().to_rfc3339(), shortcut: None,
         };
         let tag2 = Tag {
             id: "tag-personal".to_string(), account_id: "test@example.com".to_string(),
             name: "Personal".to_string(), color: "#00FF00".to_string(),
-            created_at: chrono::Utc::now().to_rfc3339(),
+            created_at: chrono::Utc::now().to_rfc3339(), shortcut: Some("2".to_string()),
         };
         cache.create_tag(&tag1).unwrap();
         cache.create_tag(&tag2).unwrap();
 
+        let by_key = cache.get_tag_by_shortcut("test@example.com", "2").unwrap().unwrap();
+        assert_eq!(by_key.id, "tag-personal");
+        assert!(cache.get_tag_by_shortcut("other@example.com", "2").unwrap().is_none());
+        // A key belongs to at most one tag per account
+        let clash = Tag { shortcut: Some("2".to_string()), ..tag1.clone() };
+        assert!(cache.update_tag(&clash).is_err());
+
         cache.add_tag_to_message(message_id, "tag-important").unwrap();
         cache.add_tag_to_message(message_id, "tag-personal").unwrap();
 
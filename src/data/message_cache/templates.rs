@@ -0,0 +1,116 @@
+//! Message template persistence operations
+
+use super::{MessageCache, MessageTemplate};
+use crate::common::{Error, Result};
+use rusqlite::{params, OptionalExtension, Row};
+
+fn template_from_row(row: &Row<'_>) -> rusqlite::Result<MessageTemplate> {
+    Ok(MessageTemplate {
+        id: row.get(0)?, account_id: row.get(1)?, name: row.get(2)?,
+        subject: row.get(3)?, body: row.get(4)?, is_html: row.get(5)?,
+        created_at: row.get(6)?,
+    })
+}
+
+impl MessageCache {
+    /// Create a new message template
+    pub fn create_template(&self, template: &MessageTemplate) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO templates (id, account_id, name, subject, body, is_html, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                &template.id, &template.account_id, &template.name,
+                &template.subject, &template.body, &template.is_html, &template.created_at,
+            ],
+        ).map_err(|e| Error::Database(format!("Failed to create template: {}", e)))?;
+        Ok(())
+    }
+
+    /// Get all templates for an account, by name
+    pub fn get_templates_for_account(&self, account_id: &str) -> Result<Vec<MessageTemplate>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, account_id, name, subject, body, is_html, created_at
+             FROM templates WHERE account_id = ?1 ORDER BY name COLLATE NOCASE",
+        ).map_err(|e| Error::Database(format!("Failed to prepare statement: {}", e)))?;
+
+        let templates = stmt
+            .query_map(params![account_id], template_from_row)
+            .map_err(|e| Error::Database(format!("Failed to query templates: {}", e)))?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::Database(format!("Failed to collect templates: {}", e)))?;
+        Ok(templates)
+    }
+
+    /// Get a specific template by ID
+    pub fn get_template(&self, template_id: &str) -> Result<Option<MessageTemplate>> {
+        self.conn
+            .query_row(
+                "SELECT id, account_id, name, subject, body, is_html, created_at
+                 FROM templates WHERE id = ?1",
+                params![template_id],
+                template_from_row,
+            )
+            .optional()
+            .map_err(|e| Error::Database(format!("Failed to get template: {}", e)))
+    }
+
+    /// Update a template's name and content
+    pub fn update_template(&self, template: &MessageTemplate) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE templates SET name = ?1, subject = ?2, body = ?3, is_html = ?4 WHERE id = ?5",
+                params![&template.name, &template.subject, &template.body, &template.is_html, &template.id],
+            )
+            .map_err(|e| Error::Database(format!("Failed to update template: {}", e)))?;
+        Ok(())
+    }
+
+    /// Delete a template
+    pub fn delete_template(&self, template_id: &str) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM templates WHERE id = ?1", params![template_id])
+            .map_err(|e| Error::Database(format!("Failed to delete template: {}", e)))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn test_template_operations() {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_templates_{}", nanos));
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+
+        let template = MessageTemplate {
+            id: "tpl-thanks".to_string(), account_id: "test@example.com".to_string(),
+            name: "Thanks".to_string(), subject: "Thank you".to_string(),
+            body: "Hi {{recipient_first_name}},\n\nThanks for your order.".to_string(),
+            is_html: false, created_at: chrono::Utc::now().to_rfc3339(),
+        };
+        cache.create_template(&template).unwrap();
+        cache.create_template(&MessageTemplate {
+            id: "tpl-away".to_string(), name: "away notice".to_string(), ..template.clone()
+        }).unwrap();
+
+        let names: Vec<String> = cache
+            .get_templates_for_account("test@example.com").unwrap()
+            .into_iter().map(|t| t.name).collect();
+        assert_eq!(names, ["away notice", "Thanks"]);
+        assert!(cache.get_templates_for_account("other@example.com").unwrap().is_empty());
+
+        let mut updated = template.clone();
+        updated.body = "<p>Thanks!</p>".to_string();
+        updated.is_html = true;
+        cache.update_template(&updated).unwrap();
+        let loaded = cache.get_template("tpl-thanks").unwrap().unwrap();
+        assert_eq!((loaded.body.as_str(), loaded.is_html), ("<p>Thanks!</p>", true));
+
+        cache.delete_template("tpl-thanks").unwrap();
+        assert!(cache.get_template("tpl-thanks").unwrap().is_none());
+    }
+}
@@ -0,0 +1,77 @@
+//! Vacation responder history
+
+use super::MessageCache;
+use crate::common::{Error, Result};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use rusqlite::params;
+
+/// Reply times are stored in one fixed format so they compare as text.
+fn reply_key(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+impl MessageCache {
+    /// Record a vacation reply to `sender` at `now`, unless they were
+    /// already answered in the last `resend_after_days` days. Returns
+    /// whether to reply.
+    pub fn claim_vacation_reply(&self, account_id: &str, sender: &str, resend_after_days: u32, now: DateTime<Utc>) -> Result<bool> {
+        let cutoff = now - Duration::days(resend_after_days as i64);
+        let changed = self
+            .conn
+            .execute(
+                "INSERT INTO vacation_replied (account_id, sender, replied_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(account_id, sender) DO UPDATE SET replied_at = excluded.replied_at
+                 WHERE replied_at <= ?4",
+                params![account_id, sender.to_lowercase(), reply_key(now), reply_key(cutoff)],
+            )
+            .map_err(|e| Error::Database(format!("Failed to record vacation reply: {}", e)))?;
+        Ok(changed > 0)
+    }
+
+    /// Forget a vacation reply that could not be sent, so the sender's
+    /// next message is answered
+    pub fn release_vacation_reply(&self, account_id: &str, sender: &str) -> Result<()> {
+        self.conn
+            .execute(
+                "DELETE FROM vacation_replied WHERE account_id = ?1 AND sender = ?2",
+                params![account_id, sender.to_lowercase()],
+            )
+            .map_err(|e| Error::Database(format!("Failed to release vacation reply: {}", e)))?;
+        Ok(())
+    }
+
+    /// Forget everyone who was answered, so the next absence answers them
+    /// again. Returns how many senders were forgotten.
+    pub fn clear_vacation_replies(&self) -> Result<usize> {
+        self.conn
+            .execute("DELETE FROM vacation_replied", [])
+            .map_err(|e| Error::Database(format!("Failed to clear vacation replies: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::env;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn test_vacation_replies_are_suppressed_per_sender() {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let temp_dir = env::temp_dir().join(format!("wixen_mail_test_vacation_{}", nanos));
+        let cache = MessageCache::new(temp_dir, None).unwrap();
+        let monday = Utc.with_ymd_and_hms(2025, 3, 10, 9, 0, 0).unwrap();
+
+        assert!(cache.claim_vacation_reply("me@example.org", "Ana@example.com", 7, monday).unwrap());
+        assert!(!cache.claim_vacation_reply("me@example.org", "ana@example.com", 7, monday + Duration::days(3)).unwrap());
+        assert!(cache.claim_vacation_reply("work@example.org", "ana@example.com", 7, monday).unwrap());
+        // A week later she is answered again, and the week starts over
+        assert!(cache.claim_vacation_reply("me@example.org", "ana@example.com", 7, monday + Duration::days(7)).unwrap());
+        assert!(!cache.claim_vacation_reply("me@example.org", "ana@example.com", 7, monday + Duration::days(10)).unwrap());
+
+        cache.release_vacation_reply("me@example.org", "ana@example.com").unwrap();
+        assert!(cache.claim_vacation_reply("me@example.org", "ana@example.com", 7, monday + Duration::days(10)).unwrap());
+        assert_eq!(cache.clear_vacation_replies().unwrap(), 2);
+    }
+}
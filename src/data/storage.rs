@@ -2,11 +2,25 @@
 //!
 //! Manages file system operations for storing attachments and cache.
 
-use crate::common::Result;
+use crate::common::mime::{decode_header, message_texts};
+use crate::common::types::MessagePriority;
+use crate::common::{Error, Result};
+use crate::data::message_cache::{parse_message_date, CachedDraft, CachedMessage, MessageCache};
+use base64::Engine;
 use std::fs;
 use std::path::PathBuf;
 use std::path::{Component, Path};
 
+/// Outcome of an mbox/eml import
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub imported: usize,
+    /// Skipped because a message with the same Message-ID is already cached
+    pub duplicates: usize,
+    /// Entries that could not be parsed as a message
+    pub failed: usize,
+}
+
 /// File system storage manager
 pub struct Storage {
     base_path: PathBuf,
@@ -49,6 +63,108 @@ impl Storage {
         Ok(())
     }
 
+    /// Export every cached message in a folder as an mbox file (mboxrd).
+    ///
+    /// Returns the number of messages written. Attachment contents are not
+    /// cached, so only headers and bodies are exported.
+    pub fn export_folder_mbox(
+        cache: &MessageCache,
+        account_id: &str,
+        folder_id: i64,
+        path: &Path,
+    ) -> Result<usize> {
+        let messages = cache.get_messages_for_folder(folder_id, account_id)?;
+        let mut out = String::new();
+        for msg in &messages {
+            out.push_str(&mbox_separator(msg));
+            for line in message_to_rfc5322(msg).lines() {
+                // mboxrd: quote "From " lines, including already-quoted ones
+                if line.trim_start_matches('>').starts_with("From ") {
+                    out.push('>');
+                }
+                out.push_str(line);
+                out.push('\n');
+            }
+            out.push('\n');
+        }
+        fs::write(path, out)?;
+        Ok(messages.len())
+    }
+
+    /// Export a single cached message as an `.eml` file.
+    pub fn export_message_eml(cache: &MessageCache, message_id: i64, path: &Path) -> Result<()> {
+        let msg = cache
+            .get_message(message_id)?
+            .ok_or_else(|| Error::NotFound(format!("Message {} is not cached", message_id)))?;
+        let raw = message_to_rfc5322(&msg).replace('\n', "\r\n");
+        fs::write(path, raw)?;
+        Ok(())
+    }
+
+    /// Import every message in an mbox file into a cached folder, creating
+    /// the folder if needed and skipping duplicates by Message-ID.
+    pub fn import_mbox(
+        cache: &MessageCache,
+        account_id: &str,
+        folder_path: &str,
+        path: &Path,
+    ) -> Result<ImportSummary> {
+        let data = fs::read(path)?;
+        let text = String::from_utf8_lossy(&data);
+        Self::import_raw_messages(cache, account_id, folder_path, split_mbox(&text))
+    }
+
+    /// Import a single `.eml` file into a cached folder.
+    pub fn import_eml(
+        cache: &MessageCache,
+        account_id: &str,
+        folder_path: &str,
+        path: &Path,
+    ) -> Result<ImportSummary> {
+        let data = fs::read(path)?;
+        let text = String::from_utf8_lossy(&data).into_owned();
+        Self::import_raw_messages(cache, account_id, folder_path, vec![text])
+    }
+
+    /// File a sent message's MIME in a cached folder (the Sent folder).
+    pub fn store_sent_message(
+        cache: &MessageCache,
+        account_id: &str,
+        folder_path: &str,
+        raw: &[u8],
+    ) -> Result<()> {
+        let text = String::from_utf8_lossy(raw).into_owned();
+        Self::import_raw_messages(cache, account_id, folder_path, vec![text]).map(|_| ())
+    }
+
+    fn import_raw_messages(
+        cache: &MessageCache,
+        account_id: &str,
+        folder_path: &str,
+        raw_messages: Vec<String>,
+    ) -> Result<ImportSummary> {
+        let folder_id = cache.ensure_folder(account_id, folder_path)?.id;
+        cache.in_transaction(|c| {
+            let mut summary = ImportSummary::default();
+            let mut uid = c.next_local_uid(folder_id)?;
+            for raw in &raw_messages {
+                let Some(msg) = parse_raw_message(raw.as_bytes(), folder_id, uid) else {
+                    summary.failed += 1;
+                    continue;
+                };
+                if !msg.message_id.is_empty() && c.has_message_id(folder_id, &msg.message_id)? {
+                    summary.duplicates += 1;
+                    continue;
+                }
+                c.save_message(&msg)?;
+                summary.imported += 1;
+                uid += 1;
+            }
+            c.recompute_folder_counts(folder_id)?;
+            Ok(summary)
+        })
+    }
+
     fn resolve_path(&self, path: &str) -> Result<PathBuf> {
         let relative = Path::new(path);
         if relative.is_absolute()
@@ -64,6 +180,284 @@ impl Storage {
     }
 }
 
+/// Rebuild an RFC 5322 message from cached headers and bodies (LF line endings).
+pub fn message_to_rfc5322(msg: &CachedMessage) -> String {
+    let mut out = String::new();
+    let mut header = |name: &str, value: &str| {
+        let encoded = match name {
+            "From" | "To" | "Cc" => encode_address_header(value),
+            _ => encode_header(value),
+        };
+        out.push_str(&format!("{}: {}\n", name, encoded));
+    };
+    header("From", &msg.from_addr);
+    if !msg.to_addr.is_empty() {
+        header("To", &msg.to_addr);
+    }
+    if let Some(cc) = msg.cc.as_deref().filter(|c| !c.is_empty()) {
+        header("Cc", cc);
+    }
+    header("Subject", &msg.subject);
+    header("Date", &msg.date);
+    if !msg.message_id.is_empty() {
+        header("Message-ID", &format!("<{}>", msg.message_id.trim_matches(|c| c == '<' || c == '>')));
+    }
+    if let Some((x_priority, importance)) = msg.priority.header_values() {
+        header("X-Priority", x_priority);
+        header("Importance", importance);
+    }
+    out.push_str("MIME-Version: 1.0\n");
+
+    let part = |content_type: &str, body: &str| {
+        format!(
+            "Content-Type: {}; charset=utf-8\nContent-Transfer-Encoding: 8bit\n\n{}\n",
+            content_type,
+            body.replace("\r\n", "\n")
+        )
+    };
+    match (msg.body_plain.as_deref(), msg.body_html.as_deref()) {
+        (Some(plain), Some(html)) => {
+            let boundary = format!("=_wixen_{}_{}", msg.folder_id, msg.uid);
+            out.push_str(&format!("Content-Type: multipart/alternative; boundary=\"{}\"\n\n", boundary));
+            out.push_str(&format!("--{}\n{}", boundary, part("text/plain", plain)));
+            out.push_str(&format!("--{}\n{}", boundary, part("text/html", html)));
+            out.push_str(&format!("--{}--\n", boundary));
+        }
+        (None, Some(html)) => out.push_str(&part("text/html", html)),
+        (plain, None) => out.push_str(&part("text/plain", plain.unwrap_or(""))),
+    }
+    out
+}
+
+/// Header carrying the local draft id on server copies of drafts.
+pub const DRAFT_ID_HEADER: &str = "X-Wixen-Draft-Id";
+
+/// Render a draft as the RFC 5322 message stored in the server's Drafts
+/// folder. The `Date` header carries the draft's `updated_at`.
+pub fn draft_to_rfc5322(draft: &CachedDraft, from: &str) -> String {
+    let mut out = String::new();
+    let mut header = |name: &str, value: &str| {
+        let encoded = match name {
+            "From" | "To" | "Cc" | "Bcc" => encode_address_header(value),
+            _ => encode_header(value),
+        };
+        out.push_str(&format!("{}: {}\r\n", name, encoded));
+    };
+    header("From", from);
+    header("To", &draft.to_addr);
+    for (name, value) in [("Cc", &draft.cc), ("Bcc", &draft.bcc)] {
+        if let Some(value) = value.as_deref().filter(|v| !v.is_empty()) {
+            header(name, value);
+        }
+    }
+    header("Subject", &draft.subject);
+    let updated = parse_message_date(&draft.updated_at).unwrap_or_else(chrono::Utc::now);
+    header("Date", &updated.to_rfc2822());
+    header(DRAFT_ID_HEADER, &draft.id);
+    out.push_str("MIME-Version: 1.0\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n");
+    out.push_str(&draft.body.replace("\r\n", "\n").replace('\n', "\r\n"));
+    out
+}
+
+/// Parse a message from the server's Drafts folder back into a draft.
+///
+/// Drafts written by other clients have no draft id header and get one
+/// derived from their UID.
+pub fn parse_draft(account_id: &str, server_uid: u32, raw: &[u8]) -> Option<CachedDraft> {
+    let parsed = mail_parser::MessageParser::default().parse(raw)?;
+    let msg = parse_raw_message(raw, 0, server_uid)?;
+    let id = parsed
+        .header_raw(DRAFT_ID_HEADER)
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| format!("server-{}", server_uid));
+    let bcc = parsed.header_raw("Bcc").map(|v| decode_header(v.trim())).filter(|v| !v.is_empty());
+    let updated_at = parse_message_date(&msg.date).unwrap_or_else(chrono::Utc::now).to_rfc3339();
+    Some(CachedDraft {
+        id,
+        account_id: account_id.to_string(),
+        to_addr: msg.to_addr,
+        cc: msg.cc,
+        bcc,
+        subject: msg.subject,
+        body: msg.body_plain.unwrap_or_default(),
+        created_at: updated_at.clone(),
+        updated_at,
+        server_uid: Some(server_uid),
+    })
+}
+
+/// Split mbox text into raw messages, tolerating CRLF line endings, a
+/// missing leading separator, and `From ` lines without a date.
+/// Quoted `>From ` lines are unquoted (mboxrd).
+fn split_mbox(text: &str) -> Vec<String> {
+    let mut messages = Vec::new();
+    let mut current = String::new();
+    let mut previous_blank = true;
+    for line in text.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if line.starts_with("From ") && previous_blank {
+            if !current.trim().is_empty() {
+                messages.push(std::mem::take(&mut current));
+            }
+            current.clear();
+            previous_blank = false;
+            continue;
+        }
+        let unquoted = if line.trim_start_matches('>').starts_with("From ") && line.starts_with('>') {
+            &line[1..]
+        } else {
+            line
+        };
+        current.push_str(unquoted);
+        current.push_str("\r\n");
+        previous_blank = line.is_empty();
+    }
+    if !current.trim().is_empty() {
+        messages.push(current);
+    }
+    messages
+}
+
+/// Convert a raw RFC 5322 message into a cache row.
+pub fn parse_raw_message(raw: &[u8], folder_id: i64, uid: u32) -> Option<CachedMessage> {
+    let parsed = mail_parser::MessageParser::default().parse(raw)?;
+    // A message needs at least one recognisable header to be worth keeping
+    if parsed.from().is_none() && parsed.subject().is_none() && parsed.message_id().is_none() {
+        return None;
+    }
+    // Encoded words the parser leaves alone, such as inside a quoted
+    // display name, are decoded too
+    let addresses = |addr: Option<&mail_parser::Address>| -> String {
+        addr.map(|a| {
+            a.iter()
+                .map(|a| match (a.name(), a.address()) {
+                    (Some(name), Some(address)) => format!("{} <{}>", name, address),
+                    (None, Some(address)) => address.to_string(),
+                    (Some(name), None) => name.to_string(),
+                    (None, None) => String::new(),
+                })
+                .filter(|a| !a.is_empty())
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .map(|list| decode_header(&list))
+        .unwrap_or_default()
+    };
+    let (plain, html) = message_texts(&parsed);
+    let cc = addresses(parsed.cc());
+    let priority = MessagePriority::from_headers(parsed.header_raw("X-Priority"), parsed.header_raw("Importance"));
+
+    Some(CachedMessage {
+        id: 0,
+        uid,
+        folder_id,
+        message_id: parsed.message_id().unwrap_or_default().to_string(),
+        subject: decode_header(parsed.subject().unwrap_or_default()),
+        from_addr: addresses(parsed.from()),
+        to_addr: addresses(parsed.to()),
+        cc: (!cc.is_empty()).then_some(cc),
+        date: parsed.date().map(|d| d.to_rfc822()).unwrap_or_default(),
+        body_plain: plain,
+        body_html: html,
+        read: true,
+        starred: false,
+        deleted: false,
+        priority,
+    })
+}
+
+/// The `From ` line that starts each message in an mbox file.
+fn mbox_separator(msg: &CachedMessage) -> String {
+    let sender = match (msg.from_addr.find('<'), msg.from_addr.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &msg.from_addr[start + 1..end],
+        _ => msg.from_addr.trim(),
+    };
+    let sender = if sender.is_empty() || sender.contains(char::is_whitespace) {
+        "MAILER-DAEMON"
+    } else {
+        sender
+    };
+    let date = parse_message_date(&msg.date).unwrap_or_else(chrono::Utc::now);
+    format!("From {} {}\n", sender, date.format("%a %b %e %H:%M:%S %Y"))
+}
+
+/// RFC 2047-encode a header value when it contains non-ASCII text, as
+/// space-separated encoded words of at most 75 characters each.
+fn encode_header(value: &str) -> String {
+    // 45 bytes make 60 base64 characters, which with `=?UTF-8?B?` and `?=`
+    // is 72
+    const MAX_CHUNK: usize = 45;
+    let value = value.replace(['\r', '\n'], " ");
+    if value.is_ascii() {
+        return value;
+    }
+    let mut words = Vec::new();
+    let mut chunk = String::new();
+    for c in value.chars() {
+        if chunk.len() + c.len_utf8() > MAX_CHUNK {
+            words.push(std::mem::take(&mut chunk));
+        }
+        chunk.push(c);
+    }
+    words.push(chunk);
+    words
+        .iter()
+        .map(|w| format!("=?UTF-8?B?{}?=", base64::engine::general_purpose::STANDARD.encode(w)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Encode an address list header (`From`, `To`, `Cc`, `Bcc`). Only
+/// display names are RFC 2047-encoded; addresses and the commas between
+/// mailboxes stay as they are.
+fn encode_address_header(value: &str) -> String {
+    let value = value.replace(['\r', '\n'], " ");
+    if value.is_ascii() {
+        return value;
+    }
+    split_mailboxes(&value)
+        .into_iter()
+        .map(|mailbox| match mailbox.rfind('<') {
+            Some(open) => {
+                let name = mailbox[..open].trim().trim_matches('"').trim();
+                let addr = mailbox[open..].trim();
+                if name.is_empty() {
+                    addr.to_string()
+                } else if name.is_ascii() {
+                    format!("{} {}", mailbox[..open].trim(), addr)
+                } else {
+                    format!("{} {}", encode_header(name), addr)
+                }
+            }
+            None => mailbox.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Split an address list on the commas between mailboxes. Commas in
+/// quoted display names, inside angle brackets, or before a mailbox's
+/// address (an unquoted "Doe, Jane <jane@example.com>") don't split.
+fn split_mailboxes(value: &str) -> Vec<&str> {
+    let mut mailboxes = Vec::new();
+    let (mut quoted, mut bracketed, mut start) = (false, false, 0);
+    for (i, c) in value.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '<' if !quoted => bracketed = true,
+            '>' if !quoted => bracketed = false,
+            ',' if !quoted && !bracketed && value[start..i].contains('@') => {
+                mailboxes.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    mailboxes.push(&value[start..]);
+    mailboxes.into_iter().map(str::trim).filter(|m| !m.is_empty()).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -98,4 +492,156 @@ mod tests {
         let storage = Storage::new(base).unwrap();
         assert!(storage.write("../escape.txt", b"x").is_err());
     }
+
+    #[test]
+    fn test_export_folder_mbox_and_eml() {
+        use crate::data::message_cache::CachedFolder;
+
+        let base = std::env::temp_dir().join(format!("wixen_storage_export_{}", std::process::id()));
+        let cache = MessageCache::new(base.join("cache"), None).unwrap();
+        let folder_id = cache.save_folder(&CachedFolder {
+            id: 0, account_id: "acc".to_string(),
+            name: "INBOX".to_string(), path: "INBOX".to_string(),
+            folder_type: "Inbox".to_string(), unread_count: 0, total_count: 0,
+        }).unwrap();
+        for (uid, html) in [(1, None), (2, Some("<p>Hi</p>".to_string()))] {
+            cache.save_message(&CachedMessage {
+                id: 0, uid, folder_id,
+                message_id: format!("m{}@example.com", uid), subject: "Caf\u{e9} plans".to_string(),
+                from_addr: "Alice <alice@example.com>".to_string(), to_addr: "bob@example.com".to_string(),
+                cc: None, date: "Mon, 1 Jan 2024 10:00:00 +0000".to_string(),
+                body_plain: Some("Hello\nFrom the team\n>From before".to_string()), body_html: html,
+                read: false, starred: false, deleted: false, priority: MessagePriority::Normal,
+            }).unwrap();
+        }
+
+        let mbox_path = base.join("inbox.mbox");
+        assert_eq!(Storage::export_folder_mbox(&cache, "acc", folder_id, &mbox_path).unwrap(), 2);
+        let mbox = fs::read_to_string(&mbox_path).unwrap();
+        let separators: Vec<&str> = mbox.lines().filter(|l| l.starts_with("From ")).collect();
+        assert_eq!(separators, vec!["From alice@example.com Mon Jan  1 10:00:00 2024"; 2]);
+        assert!(mbox.contains("\n>From the team\n>>From before\n"));
+        assert!(mbox.contains("multipart/alternative"));
+
+        let id = cache.get_messages_for_folder(folder_id, "acc").unwrap()[0].id;
+        let eml_path = base.join("message.eml");
+        Storage::export_message_eml(&cache, id, &eml_path).unwrap();
+        let raw = fs::read(&eml_path).unwrap();
+        let parsed = mail_parser::MessageParser::default().parse(&raw).unwrap();
+        assert_eq!(parsed.subject(), Some("Caf\u{e9} plans"));
+        assert!(parsed.message_id().is_some_and(|id| id.ends_with("@example.com")));
+        assert!(parsed.body_text(0).is_some_and(|b| b.contains("From the team")));
+    }
+
+    #[test]
+    fn test_import_mbox_dedups_and_tolerates_crlf() {
+        let base = std::env::temp_dir().join(format!("wixen_storage_import_{}", std::process::id()));
+        let cache = MessageCache::new(base.join("cache"), None).unwrap();
+        // Content before the first separator, a dateless "From " line and a
+        // repeated Message-ID
+        let mbox = "Subject: stray header only\r\n\r\n\
+From alice@example.com Mon Jan  1 10:00:00 2024\r\n\
+From: Alice <alice@example.com>\r\nTo: bob@example.com\r\nSubject: One\r\n\
+Message-ID: <one@example.com>\r\nDate: Mon, 1 Jan 2024 10:00:00 +0000\r\n\r\n\
+Hello\r\n>From the team\r\n\r\n\
+From \r\n\
+From: bob@example.com\r\nSubject: Two\r\nX-Priority: 1 (Highest)\r\nMessage-ID: <two@example.com>\r\n\r\nSecond\r\n\r\n\
+From MAILER-DAEMON Mon Jan  1 10:00:00 2024\r\n\
+From: Alice <alice@example.com>\r\nSubject: One again\r\nMessage-ID: <one@example.com>\r\n\r\nDup\r\n";
+        let path = base.join("in.mbox");
+        fs::create_dir_all(&base).unwrap();
+        fs::write(&path, mbox).unwrap();
+
+        let summary = Storage::import_mbox(&cache, "acc", "Imported", &path).unwrap();
+        assert_eq!(summary, ImportSummary { imported: 3, duplicates: 1, failed: 0 });
+
+        let folder = cache.get_folder("acc", "Imported").unwrap().unwrap();
+        let messages = cache.get_messages_for_folder(folder.id, "acc").unwrap();
+        let one = messages.iter().find(|m| m.subject == "One").unwrap();
+        assert_eq!(one.from_addr, "Alice <alice@example.com>");
+        assert!(one.body_plain.as_deref().unwrap().contains("From the team"));
+        assert_eq!(one.priority, MessagePriority::Normal);
+        let two = messages.iter().find(|m| m.subject == "Two").unwrap();
+        assert_eq!(two.priority, MessagePriority::High);
+        assert!(message_to_rfc5322(two).contains("X-Priority: 1 (Highest)\nImportance: high\n"));
+
+        // Re-importing the same file only finds duplicates
+        let again = Storage::import_mbox(&cache, "acc", "Imported", &path).unwrap();
+        assert_eq!(again.imported, 1);
+        assert_eq!(again.duplicates, 3);
+    }
+
+    #[test]
+    fn test_parse_raw_message_decodes_headers() {
+        let raw = "From: \"=?ISO-8859-1?Q?Ren=E9e?=\" <renee@example.fr>\r\n\
+                   To: =?UTF-8?B?SsO8cmdlbg==?= <jurgen@example.de>\r\n\
+                   Cc: =?UTF-8?Q?Zo=C3=AB?= <zoe@example.com>\r\n\
+                   Subject: =?UTF-8?Q?R=C3=A9sum=C3=A9?=\r\n =?ISO-8859-1?Q?_r=E9vis=E9?=\r\n\r\nBody";
+        let msg = parse_raw_message(raw.as_bytes(), 0, 1).unwrap();
+        assert_eq!(msg.subject, "Résumé révisé");
+        assert_eq!(msg.from_addr, "Renée <renee@example.fr>");
+        assert_eq!(msg.to_addr, "Jürgen <jurgen@example.de>");
+        assert_eq!(msg.cc.as_deref(), Some("Zoë <zoe@example.com>"));
+    }
+
+    #[test]
+    fn test_export_encodes_display_names_only() {
+        let long_name = "Ærøskøbing Fællesforening for Søndagsudflugter og Kaffemøder";
+        let msg = CachedMessage {
+            id: 0, uid: 1, folder_id: 1,
+            message_id: "m1@example.com".to_string(), subject: "Grüße".to_string(),
+            from_addr: "José Müller <jose@example.com>".to_string(),
+            to_addr: format!("\"Doe, Zoë\" <zoe@example.com>, bob@example.com, {} <club@example.dk>", long_name),
+            cc: Some("Ünal, Ayşe <ayse@example.com>".to_string()),
+            date: "Mon, 1 Jan 2024 10:00:00 +0000".to_string(),
+            body_plain: Some("Hi".to_string()), body_html: None,
+            read: false, starred: false, deleted: false, priority: MessagePriority::Normal,
+        };
+        let raw = message_to_rfc5322(&msg);
+        // Addresses and separators stay readable; no encoded word is too long
+        assert!(raw.contains("<jose@example.com>\n"));
+        assert!(raw.contains("<zoe@example.com>, bob@example.com, =?UTF-8?B?"));
+        assert!(raw.split_whitespace().filter(|w| w.starts_with("=?")).all(|w| w.len() <= 75));
+
+        let parsed = parse_raw_message(raw.as_bytes(), 1, 1).unwrap();
+        assert_eq!(parsed.subject, "Grüße");
+        assert_eq!(parsed.from_addr, "José Müller <jose@example.com>");
+        assert_eq!(
+            parsed.to_addr,
+            format!("Doe, Zoë <zoe@example.com>, bob@example.com, {} <club@example.dk>", long_name)
+        );
+        assert_eq!(parsed.cc.as_deref(), Some("Ünal, Ayşe <ayse@example.com>"));
+    }
+
+    #[test]
+    fn test_draft_round_trip() {
+        let draft = CachedDraft {
+            id: "draft-1".to_string(),
+            account_id: "acc".to_string(),
+            to_addr: "Björn <bjorn@example.com>, bob@example.com".to_string(),
+            cc: Some("carol@example.com".to_string()),
+            bcc: Some("Zoë <zoe@example.com>".to_string()),
+            subject: "Caf\u{e9} plans".to_string(),
+            body: "Line one\nLine two".to_string(),
+            created_at: "2024-01-01T09:00:00+00:00".to_string(),
+            updated_at: "2024-01-01T10:00:00+00:00".to_string(),
+            server_uid: None,
+        };
+        let raw = draft_to_rfc5322(&draft, "Åsa <alice@example.com>");
+        assert!(raw.contains("<alice@example.com>\r\nTo: =?UTF-8?B?"));
+        assert!(raw.contains(" <bjorn@example.com>, bob@example.com\r\n"));
+        let parsed = parse_draft("acc", 7, raw.as_bytes()).unwrap();
+        assert_eq!(parsed.to_addr, "Björn <bjorn@example.com>, bob@example.com");
+        assert_eq!(parsed.bcc.as_deref(), Some("Zoë <zoe@example.com>"));
+        assert_eq!(parsed.id, "draft-1");
+        assert_eq!(parsed.server_uid, Some(7));
+        assert_eq!(parsed.subject, "Caf\u{e9} plans");
+        assert_eq!(parsed.cc.as_deref(), Some("carol@example.com"));
+        assert!(parsed.body.contains("Line two"));
+        assert_eq!(parse_message_date(&parsed.updated_at), parse_message_date(&draft.updated_at));
+
+        // Drafts from other clients get an id from their UID
+        let foreign = parse_draft("acc", 9, b"From: a@example.com\r\nSubject: Hi\r\n\r\nBody").unwrap();
+        assert_eq!(foreign.id, "server-9");
+    }
 }
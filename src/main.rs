@@ -1,11 +1,19 @@
 use wixen_mail::common::logging::{init_logging, LoggerConfig};
+use wixen_mail::common::profile::ProfilePaths;
 use wixen_mail::presentation::WxMailApp;
 
 fn main() {
     let _log_guard = init_logging(LoggerConfig::default()).ok();
     tracing::info!("Starting Wixen Mail v{}", env!("CARGO_PKG_VERSION"));
 
-    let app = WxMailApp::new().expect("Failed to initialize Wixen Mail");
+    let profile = ProfilePaths::resolve_from_env_and_args(std::env::args().skip(1))
+        .unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(2)
+        })
+        .activate();
+
+    let app = WxMailApp::new(profile).expect("Failed to initialize Wixen Mail");
     if let Err(e) = app.run() {
         eprintln!("Error: {}", e);
         std::process::exit(1);
@@ -12,6 +12,33 @@ pub mod shortcuts;
 
 use crate::common::Result;
 
+/// One message list row as a screen reader hears it: the states from the
+/// Status column, then the From, Subject and Date fields
+pub struct MessageRowSpeech<'a> {
+    /// "unread", "starred" and the like, in Status column order
+    pub states: &'a [String],
+    pub from: &'a str,
+    pub subject: &'a str,
+    /// The date as the Date column shows it
+    pub date: &'a str,
+}
+
+impl MessageRowSpeech<'_> {
+    /// "Unread, starred, from Ana, subject Lunch, Today 12:30, 2 of 5"
+    pub fn text(&self, position: usize, total: usize) -> String {
+        let subject = if self.subject.trim().is_empty() { "(no subject)" } else { self.subject };
+        let fields = [
+            format!("from {}", self.from),
+            format!("subject {}", subject),
+            self.date.to_string(),
+            format!("{} of {}", position, total),
+        ];
+        let text = self.states.iter().cloned().chain(fields).collect::<Vec<_>>().join(", ");
+        let mut chars = text.chars();
+        chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+    }
+}
+
 /// Main accessibility manager
 #[allow(dead_code)]
 pub struct Accessibility {
@@ -43,6 +70,8 @@ impl Accessibility {
         self.keyboard
             .register_shortcut("Ctrl+F", "search_messages")?;
         self.keyboard.register_shortcut("F1", "open_help")?;
+        self.keyboard.register_shortcut("J", "next_message")?;
+        self.keyboard.register_shortcut("K", "previous_message")?;
         self.register_node(automation::AutomationNode {
             id: "main_window".to_string(),
             parent_id: None,
@@ -115,11 +144,20 @@ impl Accessibility {
     }
 
     /// Queue and flush announcement through native bridge.
-    pub fn announce(&self, text: &str, priority: announcements::Priority) -> Result<()> {
-        self.announcements.announce(text, priority)?;
+    ///
+    /// Accepts either a queue `Priority` or a live-region `Politeness`.
+    pub fn announce(&self, text: &str, priority: impl Into<announcements::Priority>) -> Result<()> {
+        self.announcements.announce(text, priority.into())?;
         self.flush_announcements()
     }
 
+    /// Announce the message row that just received keyboard focus in the
+    /// list, e.g. "Unread, high priority, from Alice, subject Quarterly
+    /// report, Today 09:15, 3 of 12".
+    pub fn announce_message_focus(&self, row: &MessageRowSpeech<'_>, position: usize, total: usize) -> Result<()> {
+        self.announce(&row.text(position, total), announcements::Priority::Normal)
+    }
+
     /// Emit live region update.
     pub fn live_region_update(&self, region_id: &str, text: &str) -> Result<()> {
         self.screen_reader
@@ -201,6 +239,26 @@ mod tests {
         }));
     }
 
+    #[test]
+    fn test_announce_message_focus() {
+        let a11y = Accessibility::new().unwrap();
+        let unread = ["unread".to_string()];
+        let row = |states, from, subject, date| MessageRowSpeech { states, from, subject, date };
+        a11y.announce_message_focus(&row(&unread, "alice@example.com", "Hello", "Today 09:15"), 2, 5).unwrap();
+        let flagged = ["high priority".to_string(), "starred".to_string(), "Overdue".to_string()];
+        a11y.announce_message_focus(&row(&flagged, "boss@example.com", "Outage", "Mar 3"), 3, 5).unwrap();
+        a11y.announce_message_focus(&row(&[], "carol@example.com", " ", "Mar 2"), 4, 5).unwrap();
+        let events = a11y.screen_reader.events().unwrap();
+        let announced = |expected: &str| {
+            events.iter().any(|event| {
+                matches!(event, automation::AutomationEvent::LiveRegion(_, text) if text == expected)
+            })
+        };
+        assert!(announced("Unread, from alice@example.com, subject Hello, Today 09:15, 2 of 5"));
+        assert!(announced("High priority, starred, Overdue, from boss@example.com, subject Outage, Mar 3, 3 of 5"));
+        assert!(announced("From carol@example.com, subject (no subject), Mar 2, 4 of 5"));
+    }
+
     #[test]
     fn test_flush_announcements_priority_order() {
         const GLOBAL_REGION_ID: &str = "global";
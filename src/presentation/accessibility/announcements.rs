@@ -14,6 +14,25 @@ pub enum Priority {
     Urgent,
 }
 
+/// ARIA-style politeness for live announcements
+///
+/// `Polite` waits for the screen reader to finish speaking; `Assertive`
+/// interrupts it and is reserved for failures the user must hear now.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Politeness {
+    Polite,
+    Assertive,
+}
+
+impl From<Politeness> for Priority {
+    fn from(politeness: Politeness) -> Self {
+        match politeness {
+            Politeness::Polite => Priority::Normal,
+            Politeness::Assertive => Priority::Urgent,
+        }
+    }
+}
+
 /// Queues and manages screen reader announcements
 pub struct AnnouncementQueue {
     queue: Mutex<BinaryHeap<PrioritizedAnnouncement>>,
@@ -104,6 +123,14 @@ mod tests {
         assert_eq!(queue.pop_next().unwrap().as_deref(), Some("normal"));
     }
 
+    #[test]
+    fn test_assertive_announcement_jumps_queue() {
+        let queue = AnnouncementQueue::new().unwrap();
+        queue.announce("polite", Politeness::Polite.into()).unwrap();
+        queue.announce("assertive", Politeness::Assertive.into()).unwrap();
+        assert_eq!(queue.pop_next().unwrap().as_deref(), Some("assertive"));
+    }
+
     #[test]
     fn test_announcement_fifo_within_priority() {
         let queue = AnnouncementQueue::new().unwrap();
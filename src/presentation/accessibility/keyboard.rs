@@ -37,6 +37,78 @@ impl KeyboardHandler {
     }
 }
 
+/// Selection movement within a keyboard-navigable list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListMove {
+    Previous,
+    Next,
+    First,
+    Last,
+}
+
+impl ListMove {
+    /// Map an unmodified key name to a list move (arrows, Home/End, and j/k).
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "Up" | "k" | "K" => Some(ListMove::Previous),
+            "Down" | "j" | "J" => Some(ListMove::Next),
+            "Home" => Some(ListMove::First),
+            "End" => Some(ListMove::Last),
+            _ => None,
+        }
+    }
+
+    /// Compute the new selection index in a list of `len` items.
+    ///
+    /// Movement stops at either end rather than wrapping, so holding a key
+    /// never jumps the reader back to the top unexpectedly.
+    pub fn apply(self, current: Option<usize>, len: usize) -> Option<usize> {
+        if len == 0 {
+            return None;
+        }
+        let last = len - 1;
+        Some(match (self, current) {
+            (ListMove::First, _) | (ListMove::Next, None) => 0,
+            (ListMove::Last, _) | (ListMove::Previous, None) => last,
+            (ListMove::Next, Some(i)) => (i + 1).min(last),
+            (ListMove::Previous, Some(i)) => i.saturating_sub(1).min(last),
+        })
+    }
+}
+
+/// The main window's panes, in the order F6 moves through them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pane {
+    Folders,
+    Messages,
+    Preview,
+}
+
+impl Pane {
+    pub const ALL: [Pane; 3] = [Pane::Folders, Pane::Messages, Pane::Preview];
+
+    /// The pane F6 moves to; wraps from the preview back to the folders
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|&p| p == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    /// The pane Shift+F6 moves to
+    pub fn previous(self) -> Self {
+        let i = Self::ALL.iter().position(|&p| p == self).unwrap_or(0);
+        Self::ALL[(i + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+
+    /// Spoken when the pane gets focus
+    pub fn name(self) -> &'static str {
+        match self {
+            Pane::Folders => "Folders",
+            Pane::Messages => "Messages",
+            Pane::Preview => "Message preview",
+        }
+    }
+}
+
 impl Default for KeyboardHandler {
     fn default() -> Self {
         Self {
@@ -58,4 +130,23 @@ mod tests {
             Some("compose")
         );
     }
+
+    #[test]
+    fn test_list_move_clamps_at_ends() {
+        assert_eq!(ListMove::from_key("j"), Some(ListMove::Next));
+        assert_eq!(ListMove::from_key("Up"), Some(ListMove::Previous));
+        assert_eq!(ListMove::Next.apply(None, 3), Some(0));
+        assert_eq!(ListMove::Next.apply(Some(2), 3), Some(2));
+        assert_eq!(ListMove::Previous.apply(Some(0), 3), Some(0));
+        assert_eq!(ListMove::Last.apply(Some(0), 3), Some(2));
+        assert_eq!(ListMove::Next.apply(Some(0), 0), None);
+    }
+
+    #[test]
+    fn test_pane_cycle_wraps() {
+        assert_eq!(Pane::Folders.next(), Pane::Messages);
+        assert_eq!(Pane::Preview.next(), Pane::Folders);
+        assert_eq!(Pane::Folders.previous(), Pane::Preview);
+        assert_eq!(Pane::Messages.previous(), Pane::Folders);
+    }
 }
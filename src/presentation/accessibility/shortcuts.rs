@@ -2,6 +2,7 @@
 //!
 //! Defines and manages keyboard shortcuts for accessibility.
 
+use crate::common::{Error, Result};
 use std::collections::HashMap;
 use std::fmt;
 
@@ -129,6 +130,7 @@ pub enum Action {
     Archive,
     MoveToFolder,
     AddTag,
+    Undo,
 
     // Navigation
     NextMessage,
@@ -166,6 +168,11 @@ pub enum Action {
     FocusInbox,
     FocusToolbar,
 
+    // View
+    ZoomIn,
+    ZoomOut,
+    ZoomReset,
+
     // Custom action
     Custom(String),
 }
@@ -191,6 +198,7 @@ impl fmt::Display for Action {
             Action::Archive => write!(f, "Archive"),
             Action::MoveToFolder => write!(f, "Move to Folder"),
             Action::AddTag => write!(f, "Add Tag"),
+            Action::Undo => write!(f, "Undo"),
             Action::NextMessage => write!(f, "Next Message"),
             Action::PreviousMessage => write!(f, "Previous Message"),
             Action::NextUnread => write!(f, "Next Unread"),
@@ -215,6 +223,9 @@ impl fmt::Display for Action {
             Action::OpenContactManager => write!(f, "Open Contact Manager"),
             Action::FocusInbox => write!(f, "Focus Inbox"),
             Action::FocusToolbar => write!(f, "Focus Toolbar"),
+            Action::ZoomIn => write!(f, "Zoom In"),
+            Action::ZoomOut => write!(f, "Zoom Out"),
+            Action::ZoomReset => write!(f, "Reset Zoom"),
             Action::Custom(name) => write!(f, "{}", name),
         }
     }
@@ -256,6 +267,10 @@ impl ShortcutManager {
             KeyboardShortcut::new(vec![], Key::FunctionKey(6)),
             Action::CyclePanes,
         );
+        self.register(
+            KeyboardShortcut::new(vec![Modifier::Shift], Key::FunctionKey(6)),
+            Action::CyclePanesReverse,
+        );
 
         // Message actions
         self.register(
@@ -275,12 +290,24 @@ impl ShortcutManager {
             Action::Forward,
         );
         self.register(KeyboardShortcut::new(vec![], Key::Delete), Action::Delete);
+        self.register(
+            KeyboardShortcut::simple(Modifier::Ctrl, Key::Character('z')),
+            Action::Undo,
+        );
         self.register(
             KeyboardShortcut::new(vec![], Key::Character('s')),
             Action::Star,
         );
 
         // Navigation
+        self.register(
+            KeyboardShortcut::new(vec![], Key::Character('j')),
+            Action::NextMessage,
+        );
+        self.register(
+            KeyboardShortcut::new(vec![], Key::Character('k')),
+            Action::PreviousMessage,
+        );
         self.register(
             KeyboardShortcut::new(vec![], Key::Character('n')),
             Action::NextUnread,
@@ -347,6 +374,20 @@ impl ShortcutManager {
             KeyboardShortcut::simple(Modifier::Ctrl, Key::Character('\\')),
             Action::FocusToolbar,
         );
+
+        // View
+        self.register(
+            KeyboardShortcut::simple(Modifier::Ctrl, Key::Character('=')),
+            Action::ZoomIn,
+        );
+        self.register(
+            KeyboardShortcut::simple(Modifier::Ctrl, Key::Character('-')),
+            Action::ZoomOut,
+        );
+        self.register(
+            KeyboardShortcut::simple(Modifier::Ctrl, Key::Character('0')),
+            Action::ZoomReset,
+        );
     }
 
     /// Register a keyboard shortcut
@@ -369,6 +410,23 @@ impl ShortcutManager {
         &self.shortcuts
     }
 
+    /// Check a key for a tag quick action: a single letter or digit that
+    /// isn't already an unmodified shortcut. Returns it lower-cased.
+    pub fn validate_tag_key(&self, key: &str) -> Result<char> {
+        let mut chars = key.trim().chars();
+        let (Some(c), None) = (chars.next(), chars.next()) else {
+            return Err(Error::Other("A tag shortcut must be a single letter or digit".to_string()));
+        };
+        if !c.is_ascii_alphanumeric() {
+            return Err(Error::Other("A tag shortcut must be a single letter or digit".to_string()));
+        }
+        let c = c.to_ascii_lowercase();
+        match self.get_action(&KeyboardShortcut::new(vec![], Key::Character(c))) {
+            Some(action) => Err(Error::Other(format!("{} is already the shortcut for {}", Key::Character(c), action))),
+            None => Ok(c),
+        }
+    }
+
     /// Get shortcuts for an action
     pub fn get_shortcuts_for_action(&self, action: &Action) -> Vec<&KeyboardShortcut> {
         self.shortcuts
@@ -432,6 +490,19 @@ mod tests {
         assert!(manager.get_action(&shortcut).is_none());
     }
 
+    #[test]
+    fn test_validate_tag_key() {
+        let manager = ShortcutManager::new();
+        assert_eq!(manager.validate_tag_key(" W ").unwrap(), 'w');
+        assert_eq!(manager.validate_tag_key("1").unwrap(), '1');
+        // Taken by Star and Next Message
+        assert!(manager.validate_tag_key("s").unwrap_err().to_string().contains("Star"));
+        assert!(manager.validate_tag_key("J").is_err());
+        assert!(manager.validate_tag_key("ab").is_err());
+        assert!(manager.validate_tag_key("").is_err());
+        assert!(manager.validate_tag_key("#").is_err());
+    }
+
     #[test]
     fn test_get_shortcuts_for_action() {
         let manager = ShortcutManager::new();
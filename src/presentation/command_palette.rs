@@ -0,0 +1,161 @@
+//! Command palette entries and matching
+//!
+//! The palette lists every menu command by name, with its menu and
+//! shortcut, and narrows the list as the user types. Matching is fuzzy:
+//! the typed letters must appear in order, and commands where they start
+//! words or run together come first, so "mar" and "mkr" both find
+//! "Mark as Read".
+
+/// A command as the palette shows it
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaletteCommand<T> {
+    /// What running the command does, e.g. the menu item's id
+    pub command: T,
+    /// "Mark as Read", without the mnemonic
+    pub name: String,
+    /// Where the command is on the menu bar, e.g. "View → Sort Messages"
+    pub menu: String,
+    pub shortcut: Option<String>,
+    pub help: String,
+}
+
+impl<T> PaletteCommand<T> {
+    /// The command for a menu item labelled like "Mark as &Read\tCtrl+M"
+    pub fn from_menu_label(command: T, menu: &str, label: &str, help: &str) -> Self {
+        let (name, shortcut) = match label.split_once('\t') {
+            Some((name, shortcut)) => (name, Some(shortcut.trim().to_string())),
+            None => (label, None),
+        };
+        Self {
+            command,
+            name: strip_mnemonic(name),
+            menu: strip_mnemonic(menu),
+            shortcut: shortcut.filter(|s| !s.is_empty()),
+            help: help.to_string(),
+        }
+    }
+
+    /// List line: "Mark as Read (Message), Ctrl+M"
+    pub fn display(&self) -> String {
+        match &self.shortcut {
+            Some(shortcut) => format!("{} ({}), {}", self.name, self.menu, shortcut),
+            None => format!("{} ({})", self.name, self.menu),
+        }
+    }
+}
+
+/// A menu label without its `&` mnemonic marker or trailing "...";
+/// "&&" stands for a literal ampersand
+pub fn strip_mnemonic(label: &str) -> String {
+    let mut out = String::with_capacity(label.len());
+    let mut chars = label.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '&' {
+            if chars.peek() == Some(&'&') {
+                out.push('&');
+                chars.next();
+            }
+            continue;
+        }
+        out.push(c);
+    }
+    out.trim().trim_end_matches("...").trim_end_matches('…').trim().to_string()
+}
+
+/// How well `query` matches `text`, lower being better, or `None` when
+/// the query's letters don't all appear in order. Spaces in the query
+/// are ignored and case doesn't matter.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<u32> {
+    let query: Vec<char> = query.to_lowercase().chars().filter(|c| !c.is_whitespace()).collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let word_start = |i: usize| i == 0 || !text[i - 1].is_alphanumeric();
+    let mut score = 0;
+    let mut next = 0;
+    for (n, q) in query.iter().enumerate() {
+        let found = (next..text.len()).find(|&i| text[i] == *q)?;
+        // A letter right after the last one, or starting a word, costs
+        // nothing; a gap inside a word costs its length
+        let after_last = n > 0 && found == next;
+        if !after_last && !word_start(found) {
+            score += (found - next) as u32 + 1;
+        }
+        next = found + 1;
+    }
+    Some(score)
+}
+
+/// The commands matching `query`, best first; ties keep menu order.
+/// Both the name alone and the menu followed by the name are tried, so
+/// "view zoom" finds View's Zoom In.
+pub fn filter<'a, T>(commands: &'a [PaletteCommand<T>], query: &str) -> Vec<&'a PaletteCommand<T>> {
+    let mut matches: Vec<(u32, &PaletteCommand<T>)> = commands
+        .iter()
+        .filter_map(|c| {
+            let by_name = fuzzy_score(query, &c.name);
+            let by_menu = fuzzy_score(query, &format!("{} {}", c.menu, c.name));
+            let score = match (by_name, by_menu) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            }?;
+            Some((score, c))
+        })
+        .collect();
+    matches.sort_by_key(|(score, _)| *score);
+    matches.into_iter().map(|(_, c)| c).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(id: u32, menu: &str, label: &str) -> PaletteCommand<u32> {
+        PaletteCommand::from_menu_label(id, menu, label, "")
+    }
+
+    #[test]
+    fn test_from_menu_label() {
+        let read = PaletteCommand::from_menu_label(1, "&Message", "Mark as &Read\tCtrl+M", "Mark it read");
+        assert_eq!(read.name, "Mark as Read");
+        assert_eq!(read.menu, "Message");
+        assert_eq!(read.shortcut.as_deref(), Some("Ctrl+M"));
+        assert_eq!(read.display(), "Mark as Read (Message), Ctrl+M");
+
+        let block = command(2, "Message", "&Block Sender...");
+        assert_eq!(block.name, "Block Sender");
+        assert_eq!(block.shortcut, None);
+        assert_eq!(block.display(), "Block Sender (Message)");
+        assert_eq!(strip_mnemonic("Tom && &Jerry"), "Tom & Jerry");
+    }
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert_eq!(fuzzy_score("", "Reply"), Some(0));
+        assert_eq!(fuzzy_score("reply", "Reply All"), Some(0));
+        assert_eq!(fuzzy_score("MAR", "Mark as Read"), Some(0));
+        // Word starts are free; gaps inside words cost
+        assert_eq!(fuzzy_score("mar", "Mark as Read"), fuzzy_score("m a r", "Mark as Read"));
+        assert!(fuzzy_score("ra", "Mark as Read").unwrap() > 0);
+        assert_eq!(fuzzy_score("xyz", "Mark as Read"), None);
+        assert_eq!(fuzzy_score("dear", "Read"), None);
+    }
+
+    #[test]
+    fn test_filter() {
+        let commands = [
+            command(1, "Message", "&Reply\tCtrl+R"),
+            command(2, "Message", "Reply &All\tCtrl+Shift+R"),
+            command(3, "Message", "Mark as &Read"),
+            command(4, "View", "Zoom &In\tCtrl+="),
+            command(5, "Edit", "&Undo\tCtrl+Z"),
+        ];
+        let ids = |query: &str| filter(&commands, query).iter().map(|c| c.command).collect::<Vec<_>>();
+        assert_eq!(ids(""), vec![1, 2, 3, 4, 5]);
+        assert_eq!(ids("reply"), vec![1, 2]);
+        assert_eq!(ids("ra"), vec![2, 3]);
+        assert_eq!(ids("view zoom"), vec![4]);
+        assert!(ids("nothing like it").is_empty());
+    }
+}
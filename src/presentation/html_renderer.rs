@@ -2,10 +2,31 @@
 //!
 //! Renders HTML email content with security (XSS protection) and accessibility features.
 
-use ammonia::clean;
+use crate::common::mime::message_texts;
+use mail_parser::MimeHeaders;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::sync::OnceLock;
 
 const SAFE_URL_SCHEMES: [&str; 3] = ["http://", "https://", "mailto:"];
+/// Elements the sanitizer keeps; all others are removed, keeping their text
+const ALLOWED_TAGS: [&str; 45] = [
+    "a", "abbr", "b", "blockquote", "br", "caption", "center", "cite", "code", "col", "colgroup",
+    "dd", "del", "div", "dl", "dt", "em", "font", "h1", "h2", "h3", "h4", "h5", "h6", "hr", "i",
+    "img", "ins", "li", "ol", "p", "pre", "q", "s", "small", "span", "strong", "sub", "sup",
+    "table", "tbody", "td", "th", "tr", "u",
+];
+/// Elements removed together with their content
+const REMOVED_WITH_CONTENT: [&str; 6] = ["script", "style", "iframe", "object", "embed", "template"];
+/// Attributes kept on any allowed element
+const ALLOWED_ATTRIBUTES: [&str; 4] = ["title", "lang", "dir", "align"];
+/// URL schemes allowed in `href` and `src`
+const ALLOWED_URL_SCHEMES: [&str; 4] = ["http", "https", "mailto", "cid"];
+/// Banks and mail providers whose names make an `http://` link suspicious
+const KNOWN_BRANDS: [&str; 16] = [
+    "paypal", "google", "gmail", "microsoft", "outlook", "office365", "apple", "icloud",
+    "amazon", "yahoo", "chase", "bankofamerica", "wellsfargo", "citibank", "hsbc", "barclays",
+];
 
 fn html_tag_re() -> &'static regex::Regex {
     static RE: OnceLock<regex::Regex> = OnceLock::new();
@@ -33,25 +54,83 @@ fn link_re() -> &'static regex::Regex {
     })
 }
 
+fn text_url_re() -> &'static regex::Regex {
+    static RE: OnceLock<regex::Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        regex::Regex::new(r#"(?i)\b(?:https?://|www\.|mailto:)[^\s<>"]+"#).expect("valid text URL regex")
+    })
+}
+
+fn domain_re() -> &'static regex::Regex {
+    static RE: OnceLock<regex::Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        regex::Regex::new(r"(?i)^\s*(?:https?://)?((?:[a-z0-9](?:[a-z0-9-]*[a-z0-9])?\.)+[a-z]{2,})(?:[:/]\S*)?\s*$")
+            .expect("valid domain regex")
+    })
+}
+
+/// Start tags of elements that can run code, load other content or submit
+/// data. Other removed elements, such as layout tags, aren't worth a warning.
+fn dangerous_tag_re() -> &'static regex::Regex {
+    static RE: OnceLock<regex::Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        regex::Regex::new(
+            r"(?i)<\s*(script|iframe|frame|frameset|object|embed|applet|form|input|button|textarea|select|svg|math|base|meta|link)\b",
+        )
+        .expect("valid dangerous tag regex")
+    })
+}
+
+fn event_attribute_re() -> &'static regex::Regex {
+    static RE: OnceLock<regex::Regex> = OnceLock::new();
+    RE.get_or_init(|| regex::Regex::new(r"(?i)[\s/]on[a-z]+\s*=").expect("valid event attribute regex"))
+}
+
+fn unsafe_url_re() -> &'static regex::Regex {
+    static RE: OnceLock<regex::Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        regex::Regex::new(r#"(?i)(?:href|src|action|formaction)\s*=\s*["']?\s*(?:javascript|vbscript|data):"#)
+            .expect("valid unsafe url regex")
+    })
+}
+
 fn img_tag_re() -> &'static regex::Regex {
     static RE: OnceLock<regex::Regex> = OnceLock::new();
     RE.get_or_init(|| regex::Regex::new(r"(?is)<img\b").expect("valid image tag regex"))
 }
 
-fn anchor_tag_re() -> &'static regex::Regex {
+fn image_tag_re() -> &'static regex::Regex {
     static RE: OnceLock<regex::Regex> = OnceLock::new();
-    RE.get_or_init(|| regex::Regex::new(r"(?is)<a\b").expect("valid anchor tag regex"))
+    RE.get_or_init(|| regex::Regex::new(r"(?is)<img\b[^>]*>").expect("valid whole image tag regex"))
 }
 
-fn script_tag_re() -> &'static regex::Regex {
+fn image_src_re() -> &'static regex::Regex {
     static RE: OnceLock<regex::Regex> = OnceLock::new();
-    RE.get_or_init(|| regex::Regex::new(r"(?is)<\s*script\b").expect("valid script tag regex"))
+    RE.get_or_init(|| {
+        regex::Regex::new(r#"(?is)<img\b[^>]*?\bsrc\s*=\s*(?:"([^"]*)"|'([^']*)')"#).expect("valid image source regex")
+    })
+}
+
+/// Whether an image source would be fetched from the web
+fn is_remote_image(src: &str) -> bool {
+    let src = src.trim().to_ascii_lowercase();
+    src.starts_with("http://") || src.starts_with("https://") || src.starts_with("//")
+}
+
+fn anchor_tag_re() -> &'static regex::Regex {
+    static RE: OnceLock<regex::Regex> = OnceLock::new();
+    RE.get_or_init(|| regex::Regex::new(r"(?is)<a\b").expect("valid anchor tag regex"))
 }
 
 /// HTML renderer with sanitization
 pub struct HtmlRenderer {
     /// Whether to strip all HTML and return plain text
     plain_text_only: bool,
+    /// Whether to mark links that look like phishing
+    warn_links: bool,
+    /// Whether to drop web addresses from images, so nothing showing the
+    /// sanitized HTML fetches them
+    block_remote_images: bool,
 }
 
 impl HtmlRenderer {
@@ -59,6 +138,8 @@ impl HtmlRenderer {
     pub fn new() -> Self {
         Self {
             plain_text_only: false,
+            warn_links: true,
+            block_remote_images: false,
         }
     }
 
@@ -66,19 +147,65 @@ impl HtmlRenderer {
     pub fn plain_text_only() -> Self {
         Self {
             plain_text_only: true,
+            warn_links: true,
+            block_remote_images: false,
         }
     }
 
+    /// Turn phishing warnings on links on or off; they are left off for
+    /// mail from senders the user trusts
+    pub fn with_link_warnings(mut self, warn: bool) -> Self {
+        self.warn_links = warn;
+        self
+    }
+
+    /// Load images from the web or block them, per the "Load remote
+    /// images" setting. Blocked images lose their address and are marked
+    /// as blocked in the accessible text.
+    pub fn with_remote_images(mut self, load: bool) -> Self {
+        self.block_remote_images = !load;
+        self
+    }
+
     /// Sanitize HTML content for safe display
     ///
-    /// This removes potentially dangerous HTML/JavaScript while preserving
-    /// safe formatting and structure.
+    /// Allowlist-based: only the elements, attributes and URL schemes listed
+    /// at the top of this file survive. Scripts, frames, event handlers,
+    /// `style` attributes (and so CSS `expression()`), and `javascript:` or
+    /// `data:` URLs are all dropped, as are web images when they are blocked.
     pub fn sanitize_html(&self, html: &str) -> String {
+        self.clean(html, self.block_remote_images)
+    }
+
+    fn clean(&self, html: &str, block_remote_images: bool) -> String {
         if self.plain_text_only {
             return self.html_to_plain_text(html);
         }
 
-        clean(html)
+        let tag_attributes = HashMap::from([
+            ("a", HashSet::from(["href"])),
+            ("img", HashSet::from(["src", "alt", "width", "height"])),
+            ("td", HashSet::from(["colspan", "rowspan", "headers", "scope"])),
+            ("th", HashSet::from(["colspan", "rowspan", "headers", "scope"])),
+            ("font", HashSet::from(["color", "size"])),
+        ]);
+        ammonia::Builder::empty()
+            .tags(HashSet::from(ALLOWED_TAGS))
+            .clean_content_tags(HashSet::from(REMOVED_WITH_CONTENT))
+            .generic_attributes(HashSet::from(ALLOWED_ATTRIBUTES))
+            .tag_attributes(tag_attributes)
+            .url_schemes(HashSet::from(ALLOWED_URL_SCHEMES))
+            .url_relative(ammonia::UrlRelative::Deny)
+            .link_rel(Some("noopener noreferrer"))
+            .attribute_filter(move |element, attribute, value| {
+                if block_remote_images && element == "img" && attribute == "src" && is_remote_image(value) {
+                    None
+                } else {
+                    Some(value.into())
+                }
+            })
+            .clean(html)
+            .to_string()
     }
 
     /// Convert HTML to accessible plain text
@@ -114,12 +241,57 @@ impl HtmlRenderer {
         text.trim().to_string()
     }
 
+    /// Convert HTML to plain text, keeping link targets inline as
+    /// `[text](url)`. Unsafe links keep only their text.
+    pub fn html_to_text_with_links(&self, html: &str) -> String {
+        let sanitized = self.sanitize_html(html);
+        let with_links = link_re().replace_all(&sanitized, |caps: &regex::Captures| {
+            let href = caps.get(1).or_else(|| caps.get(2)).map(|m| m.as_str()).unwrap_or("");
+            let text = self.html_to_plain_text(caps.get(3).map(|m| m.as_str()).unwrap_or(""));
+            match Self::sanitize_url(&html_escape::decode_html_entities(href)) {
+                Some(url) if text.trim().is_empty() || text.trim() == url => url,
+                Some(url) => format!("[{}]({})", text.trim(), url),
+                None => text,
+            }
+        });
+        let with_images = image_alt_re().replace_all(&with_links, |caps: &regex::Captures| {
+            let alt = caps.get(1).or_else(|| caps.get(2)).map(|m| m.as_str()).unwrap_or("image");
+            format!("[Image: {}]", alt)
+        });
+        self.html_to_plain_text(&img_tag_re().replace_all(&with_images, ""))
+    }
+
+    /// Plain-text view of a message body.
+    ///
+    /// A raw MIME message shows its `text/plain` part when it has one;
+    /// HTML (a bare document or a message with only an HTML part) is
+    /// converted with `html_to_text_with_links`. Anything else is returned
+    /// unchanged.
+    pub fn plain_text_view(&self, body: &str) -> String {
+        let parsed = mail_parser::MessageParser::default()
+            .parse(body.as_bytes())
+            .filter(|m| m.content_type().is_some());
+        if let Some(message) = parsed {
+            match message_texts(&message) {
+                (Some(text), _) => return text.trim_end().to_string(),
+                (None, Some(html)) => return self.html_to_text_with_links(&html),
+                (None, None) => {}
+            }
+        }
+        if body.contains('<') && body.contains('>') {
+            self.html_to_text_with_links(body)
+        } else {
+            body.to_string()
+        }
+    }
+
     /// Render HTML into an accessible text representation for RichTextCtrl.
     ///
     /// Produces plain text with link annotations and image descriptions
     /// suitable for screen readers. Links are shown inline as "text [URL]".
     pub fn render_for_accessibility(&self, html: &str) -> AccessibleRenderedContent {
-        let sanitized = self.sanitize_html(html);
+        // Nothing is fetched for text, so web images are kept to say which were blocked
+        let sanitized = self.clean(html, false);
 
         // Replace links with accessible inline format: "text [URL]"
         let with_links = link_re().replace_all(&sanitized, |caps: &regex::Captures| {
@@ -127,10 +299,11 @@ impl HtmlRenderer {
             let text = caps.get(3).map(|m| m.as_str()).unwrap_or("");
             let clean_text = self.html_to_plain_text(text);
             if let Some(safe_url) = Self::sanitize_url(href) {
+                let marker = if !self.warn_links || link_risks(&safe_url, &clean_text).is_empty() { "" } else { " [⚠ suspicious link]" };
                 if clean_text.trim() == safe_url.trim() {
-                    clean_text // Don't duplicate if link text is already the URL
+                    format!("{}{}", clean_text, marker) // Don't duplicate if link text is already the URL
                 } else {
-                    format!("{} [{}]", clean_text, safe_url)
+                    format!("{} [{}]{}", clean_text, safe_url, marker)
                 }
             } else {
                 clean_text
@@ -138,13 +311,23 @@ impl HtmlRenderer {
         }).to_string();
 
         // Replace images with alt text descriptions
+        let label = |tag: &str| {
+            let blocked = self.block_remote_images
+                && image_src_re()
+                    .captures(tag)
+                    .and_then(|caps| caps.get(1).or_else(|| caps.get(2)))
+                    .is_some_and(|src| is_remote_image(src.as_str()));
+            if blocked { "Remote image blocked" } else { "Image" }
+        };
         let with_images = image_alt_re().replace_all(&with_links, |caps: &regex::Captures| {
             let alt = caps.get(1).or_else(|| caps.get(2)).map(|m| m.as_str()).unwrap_or("image");
-            format!("[Image: {}]", alt)
+            format!("[{}: {}]", label(&caps[0]), alt)
         }).to_string();
 
         // Remove remaining img tags without alt text
-        let cleaned = img_tag_re().replace_all(&with_images, "[Image]").to_string();
+        let cleaned = image_tag_re().replace_all(&with_images, |caps: &regex::Captures| {
+            format!("[{}]", label(&caps[0]))
+        }).to_string();
 
         let plain = self.html_to_plain_text(&cleaned);
         let links = self.extract_link_texts(&sanitized);
@@ -154,7 +337,17 @@ impl HtmlRenderer {
         let link_summary = if !links.is_empty() {
             let mut summary = String::from("\n\n--- Links ---\n");
             for (i, link) in links.iter().enumerate() {
-                summary.push_str(&format!("  {}. {} — {}\n", i + 1, link.text, link.url));
+                if self.warn_links && link.is_suspicious() {
+                    summary.push_str(&format!(
+                        "  {}. ⚠ {} — {} (suspicious: {})\n",
+                        i + 1,
+                        link.text,
+                        link.url,
+                        link.describe_risks()
+                    ));
+                } else {
+                    summary.push_str(&format!("  {}. {} — {}\n", i + 1, link.text, link.url));
+                }
             }
             summary
         } else {
@@ -192,6 +385,39 @@ impl HtmlRenderer {
         }
     }
 
+    /// A plain text body in the same form as `render_for_egui`'s, with
+    /// the web and mail addresses written in it as its links
+    pub fn render_plain_text(&self, text: &str) -> RenderedContent {
+        let links = self.extract_text_links(text);
+        RenderedContent {
+            html: String::new(),
+            plain_text: text.to_string(),
+            has_images: false,
+            has_links: !links.is_empty(),
+            links,
+            image_alt_texts: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Addresses written out in plain text, each once. Punctuation ending
+    /// the sentence is left off, and "www." addresses get `https://`.
+    pub fn extract_text_links(&self, text: &str) -> Vec<LinkInfo> {
+        let mut seen = HashSet::new();
+        text_url_re()
+            .find_iter(text)
+            .filter_map(|m| {
+                let written = m.as_str().trim_end_matches(['.', ',', ';', ':', '!', '?', ')', ']', '\'']);
+                let url = match written.get(..4) {
+                    Some(start) if start.eq_ignore_ascii_case("www.") => format!("https://{}", written),
+                    _ => written.to_string(),
+                };
+                let url = Self::sanitize_url(&url)?;
+                seen.insert(url.clone()).then(|| LinkInfo { risks: Vec::new(), text: written.to_string(), url })
+            })
+            .collect()
+    }
+
     /// Extract alt text from images for accessibility
     pub fn extract_image_alt_texts(&self, html: &str) -> Vec<String> {
         let mut alt_texts = Vec::new();
@@ -213,9 +439,11 @@ impl HtmlRenderer {
         for cap in link_re().captures_iter(html) {
             if let (Some(href), Some(text)) = (cap.get(1).or_else(|| cap.get(2)), cap.get(3)) {
                 if let Some(safe_url) = Self::sanitize_url(href.as_str()) {
+                    let text = self.html_to_plain_text(text.as_str());
                     links.push(LinkInfo {
+                        risks: link_risks(&safe_url, &text),
                         url: safe_url,
-                        text: self.html_to_plain_text(text.as_str()),
+                        text,
                     });
                 }
             }
@@ -259,13 +487,33 @@ impl HtmlRenderer {
     ) -> Vec<String> {
         let mut warnings = Vec::new();
         let original_lower = original_html.to_lowercase();
-        let sanitized_lower = sanitized_html.to_lowercase();
-        if script_tag_re().is_match(&original_lower) && !script_tag_re().is_match(&sanitized_lower)
-        {
-            warnings.push("Potentially unsafe scripts were removed.".to_string());
+        let removed = dangerous_tag_re()
+            .find_iter(original_html)
+            .count()
+            .saturating_sub(dangerous_tag_re().find_iter(sanitized_html).count());
+        if removed > 0 {
+            let noun = if removed == 1 { "element was" } else { "elements were" };
+            warnings.push(format!("{} unsafe {} removed.", removed, noun));
         }
-        if original_lower.contains("onerror=") || original_lower.contains("onclick=") {
-            warnings.push("Inline event handlers were removed for safety.".to_string());
+        let handlers = event_attribute_re().find_iter(original_html).count();
+        if handlers > 0 {
+            warnings.push(format!("{} event handler attribute(s) removed.", handlers));
+        }
+        let unsafe_urls = unsafe_url_re().find_iter(original_html).count();
+        if unsafe_urls > 0 {
+            warnings.push(format!("{} javascript:, vbscript: or data: address(es) removed.", unsafe_urls));
+        }
+        if original_lower.contains("expression(") {
+            warnings.push("Styles containing CSS expressions were removed.".to_string());
+        }
+        if self.block_remote_images {
+            let blocked = image_src_re()
+                .captures_iter(original_html)
+                .filter(|caps| caps.get(1).or_else(|| caps.get(2)).is_some_and(|src| is_remote_image(src.as_str())))
+                .count();
+            if blocked > 0 {
+                warnings.push(format!("{} remote image(s) blocked.", blocked));
+            }
         }
         let image_count = img_tag_re().find_iter(original_html).count();
         if image_count > image_alt_texts.len() {
@@ -274,6 +522,12 @@ impl HtmlRenderer {
         if anchor_tag_re().is_match(original_html) && links.is_empty() {
             warnings.push("Unsupported/unsafe links were omitted from preview.".to_string());
         }
+        for link in links.iter().filter(|l| self.warn_links && l.is_suspicious()) {
+            warnings.push(format!(
+                "Suspicious link \"{}\" goes to {}: {}.",
+                link.text, link.url, link.describe_risks()
+            ));
+        }
         warnings
     }
 }
@@ -314,6 +568,15 @@ pub struct RenderedContent {
     pub warnings: Vec<String>,
 }
 
+#[cfg(feature = "openpgp")]
+impl RenderedContent {
+    /// Put the outcome of OpenPGP decryption and signature checks ahead of
+    /// the other warnings.
+    pub fn add_pgp_status(&mut self, pgp: &crate::service::openpgp::IncomingPgp) {
+        self.warnings.splice(0..0, pgp.warnings());
+    }
+}
+
 /// Link information for accessibility
 #[derive(Debug, Clone)]
 pub struct LinkInfo {
@@ -321,6 +584,104 @@ pub struct LinkInfo {
     pub url: String,
     /// Link text
     pub text: String,
+    /// Phishing signs found in the link, empty when it looks safe
+    pub risks: Vec<LinkRisk>,
+}
+
+impl LinkInfo {
+    pub fn is_suspicious(&self) -> bool {
+        !self.risks.is_empty()
+    }
+
+    /// The link's risks as a readable list.
+    pub fn describe_risks(&self) -> String {
+        self.risks.iter().map(|r| r.describe()).collect::<Vec<_>>().join("; ")
+    }
+}
+
+/// A sign that a link may be phishing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkRisk {
+    /// The visible text names a different domain than the link opens
+    DisplayMismatch,
+    /// The link opens a raw IP address instead of a domain
+    IpAddress,
+    /// The domain is punycode, which can imitate another domain
+    Homograph,
+    /// An unencrypted `http://` link naming a bank or mail provider
+    InsecureBrand,
+}
+
+impl LinkRisk {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            LinkRisk::DisplayMismatch => "the text shows a different site than the link opens",
+            LinkRisk::IpAddress => "it opens a numeric IP address",
+            LinkRisk::Homograph => "the domain may imitate another one with look-alike characters",
+            LinkRisk::InsecureBrand => "it claims to be a bank or provider but is not encrypted",
+        }
+    }
+}
+
+/// Check a link's target and visible text for common phishing signs.
+pub fn link_risks(url: &str, text: &str) -> Vec<LinkRisk> {
+    let Ok(parsed) = url::Url::parse(url) else {
+        return Vec::new();
+    };
+    let Some(host) = parsed.host() else {
+        return Vec::new();
+    };
+    let mut risks = Vec::new();
+    let host = match host {
+        url::Host::Domain(domain) => domain.to_ascii_lowercase(),
+        url::Host::Ipv4(_) | url::Host::Ipv6(_) => {
+            risks.push(LinkRisk::IpAddress);
+            String::new()
+        }
+    };
+    // Only link text that is itself an address counts, so "report.pdf"
+    // inside a sentence isn't mistaken for a domain.
+    let shown = domain_re().captures(text).and_then(|c| c.get(1)).map(|m| m.as_str().to_ascii_lowercase());
+    if let Some(shown) = &shown {
+        if host.is_empty() || base_domain(shown) != base_domain(&host) {
+            risks.push(LinkRisk::DisplayMismatch);
+        }
+    }
+    if host.split('.').any(|label| label.starts_with("xn--")) {
+        risks.push(LinkRisk::Homograph);
+    }
+    if parsed.scheme() == "http" {
+        let text_lower = text.to_lowercase();
+        if KNOWN_BRANDS.iter().any(|brand| host.contains(brand) || text_lower.contains(brand)) {
+            risks.push(LinkRisk::InsecureBrand);
+        }
+    }
+    risks
+}
+
+/// The registrable part of a domain: the last two labels, or three for
+/// two-letter country domains like `co.uk`.
+fn base_domain(domain: &str) -> String {
+    let labels: Vec<&str> = domain.trim_end_matches('.').split('.').collect();
+    let keep = match labels.as_slice() {
+        [.., second, tld] if tld.len() == 2 && ["co", "com", "org", "net", "ac", "gov"].contains(second) => 3,
+        _ => 2,
+    };
+    labels[labels.len().saturating_sub(keep)..].join(".")
+}
+
+/// What the preview shows of a message body: the HTML of a raw MIME
+/// message, else its plain text, decoded from its transfer encoding and
+/// charset. A body that isn't a MIME message is returned as it is.
+pub fn displayed_body(body: &str) -> Cow<'_, str> {
+    let parsed = mail_parser::MessageParser::default()
+        .parse(body.as_bytes())
+        .filter(|m| m.content_type().is_some());
+    match parsed.map(|message| message_texts(&message)) {
+        Some((_, Some(html))) => Cow::Owned(html),
+        Some((Some(text), None)) => Cow::Owned(text),
+        _ => Cow::Borrowed(body),
+    }
 }
 
 #[cfg(test)]
@@ -344,6 +705,88 @@ mod tests {
         assert!(safe_html.contains("Hello"));
     }
 
+    #[test]
+    fn test_sanitize_known_xss_payloads() {
+        let renderer = HtmlRenderer::new();
+        let payloads = [
+            r#"<img src=x onerror=alert(1)>"#,
+            r#"<svg/onload=alert(1)>"#,
+            r#"<iframe src="javascript:alert(1)"></iframe>"#,
+            r#"<a href="javascript:alert(1)">click</a>"#,
+            r#"<a href="JaVaScRiPt&#58;alert(1)">click</a>"#,
+            r#"<a href=" &#x6A;avascript:alert(1)">click</a>"#,
+            r#"<a href="data:text/html;base64,PHNjcmlwdD5hbGVydCgxKTwvc2NyaXB0Pg==">click</a>"#,
+            r#"<div style="width: expression(alert(1))">styled</div>"#,
+            r#"<body onload=alert(1)>"#,
+            r#"<math><mtext><table><mglyph><style><img src=x onerror=alert(1)>"#,
+            r#"<object data="javascript:alert(1)"></object><embed src="javascript:alert(1)">"#,
+            r#"<form action="javascript:alert(1)"><button formaction="javascript:alert(1)">x</button></form>"#,
+            r#"<scr<script>ipt>alert(1)</script>"#,
+        ];
+        for payload in payloads {
+            let content = renderer.render_for_egui(payload);
+            let lower = content.html.to_lowercase();
+            for bad in ["<script", "<iframe", "<svg", "<object", "<embed", "<form", "onerror", "onload", "javascript:", "data:", "expression(", "style="] {
+                assert!(!lower.contains(bad), "{} survived in {:?}", bad, content.html);
+            }
+            assert!(content.links.iter().all(|l| l.url.starts_with("https://") || l.url.starts_with("http://") || l.url.starts_with("mailto:")));
+            assert!(!content.warnings.is_empty(), "no warning for {}", payload);
+        }
+
+        let content = renderer.render_for_egui(r#"<p>Hi</p><script>x()</script><iframe></iframe><b onclick="y()">B</b>"#);
+        assert!(content.warnings.contains(&"2 unsafe elements were removed.".to_string()));
+        assert!(content.warnings.contains(&"1 event handler attribute(s) removed.".to_string()));
+        assert_eq!(content.html, "<p>Hi</p><b>B</b>");
+        assert_eq!(content.warnings.len(), 2, "{:?}", content.warnings);
+
+        // Harmless markup the preview doesn't keep is dropped without a warning
+        let layout = renderer.render_for_egui("<section><u>Note</u> <label>x</label></section>");
+        assert_eq!(layout.html, "<u>Note</u> x");
+        assert!(layout.warnings.is_empty(), "{:?}", layout.warnings);
+    }
+
+    #[test]
+    fn test_plain_text_view() {
+        let renderer = HtmlRenderer::new();
+        let html = r#"<p>See <a href="https://example.com/a?x=1&amp;y=2">the report</a> or
+            <a href="https://example.com">https://example.com</a>.</p>
+            <a href="javascript:alert(1)">click</a><img src="cid:1" alt="Logo">"#;
+        let text = renderer.html_to_text_with_links(html);
+        assert!(text.contains("[the report](https://example.com/a?x=1&y=2)"), "{}", text);
+        assert!(text.contains(" https://example.com."));
+        assert!(text.contains("click"));
+        assert!(!text.contains("javascript"));
+        assert!(text.contains("[Image: Logo]"));
+
+        let alternative = "From: a@example.com\r\n\
+            Content-Type: multipart/alternative; boundary=\"b\"\r\n\r\n\
+            --b\r\nContent-Type: text/plain\r\n\r\nPlain version\r\n\
+            --b\r\nContent-Type: text/html\r\n\r\n<p>HTML version</p>\r\n\
+            --b--\r\n";
+        assert_eq!(renderer.plain_text_view(alternative), "Plain version");
+
+        let html_only = "From: a@example.com\r\nContent-Type: text/html\r\n\r\n<p>Go <a href=\"https://x.example\">here</a></p>";
+        assert_eq!(renderer.plain_text_view(html_only), "Go [here](https://x.example)");
+        assert_eq!(renderer.plain_text_view("<b>Hi</b> there"), "Hi there");
+        assert_eq!(renderer.plain_text_view("Just text"), "Just text");
+
+        let broken = "Content-Type: text/plain; charset=iso-8859-1\r\n\
+            Content-Transfer-Encoding: quoted-printable\r\n\r\nPr=E9vu =C0 10h, 100% s=FBr=\r\n !";
+        assert_eq!(renderer.plain_text_view(broken), "Prévu À 10h, 100% sûr !");
+    }
+
+    #[test]
+    fn test_displayed_body() {
+        let alternative = "Content-Type: multipart/alternative; boundary=\"b\"\r\n\r\n\
+            --b\r\nContent-Type: text/plain\r\nContent-Transfer-Encoding: quoted-printable\r\n\r\nx=3D1\r\n\
+            --b\r\nContent-Type: text/html; charset=utf-8\r\nContent-Transfer-Encoding: base64\r\n\r\nPHA+eD0xPC9wPg==\r\n\
+            --b--\r\n";
+        assert_eq!(displayed_body(alternative), "<p>x=1</p>");
+        let plain = "Content-Type: text/plain; charset=windows-1252\r\nContent-Transfer-Encoding: quoted-printable\r\n\r\n=93Hi=94";
+        assert_eq!(displayed_body(plain), "\u{201c}Hi\u{201d}");
+        assert_eq!(displayed_body("From: a@example.com\r\n\r\nJust text"), "From: a@example.com\r\n\r\nJust text");
+    }
+
     #[test]
     fn test_html_to_plain_text() {
         let renderer = HtmlRenderer::new();
@@ -393,6 +836,72 @@ mod tests {
         assert_eq!(content.links.len(), 1);
     }
 
+    #[test]
+    fn test_remote_images_follow_setting() {
+        let html = r#"<p>News</p><img src="https://track.example/p.gif" alt="Banner"><img src="http://x.example/1.png"><img src="cid:logo" alt="Logo">"#;
+
+        let blocked = HtmlRenderer::new().with_remote_images(false);
+        let sanitized = blocked.sanitize_html(html);
+        assert!(!sanitized.contains("track.example") && !sanitized.contains("x.example"), "{}", sanitized);
+        assert!(sanitized.contains(r#"src="cid:logo""#));
+        let text = blocked.render_for_accessibility(html).accessible_text;
+        assert!(text.contains("[Remote image blocked: Banner]"), "{}", text);
+        assert!(text.contains("[Remote image blocked]"), "{}", text);
+        assert!(text.contains("[Image: Logo]"));
+        assert!(blocked.render_for_egui(html).warnings.contains(&"2 remote image(s) blocked.".to_string()));
+
+        let loaded = HtmlRenderer::new().with_remote_images(true);
+        assert!(loaded.sanitize_html(html).contains(r#"src="https://track.example/p.gif""#));
+        let text = loaded.render_for_accessibility(html).accessible_text;
+        assert!(text.contains("[Image: Banner]") && !text.contains("blocked"), "{}", text);
+        assert!(!loaded.render_for_egui(html).warnings.iter().any(|w| w.contains("blocked")));
+    }
+
+    #[test]
+    fn test_link_risks() {
+        assert!(link_risks("https://www.paypal.com/signin", "paypal.com").is_empty());
+        assert!(link_risks("https://mail.example.co.uk/", "example.co.uk/help").is_empty());
+        assert!(link_risks("https://files.example.com/", "Download report.pdf now").is_empty());
+        assert!(link_risks("https://example.com", "Click here").is_empty());
+        assert_eq!(
+            link_risks("https://paypal.com.evil.io/login", "https://www.paypal.com"),
+            vec![LinkRisk::DisplayMismatch]
+        );
+        assert_eq!(link_risks("http://192.168.10.5/a", "Open"), vec![LinkRisk::IpAddress]);
+        assert_eq!(link_risks("https://xn--pypal-4ve.com/", "Sign in"), vec![LinkRisk::Homograph]);
+        assert_eq!(link_risks("http://chase-secure.net/", "Your Chase account"), vec![LinkRisk::InsecureBrand]);
+        assert_eq!(base_domain("a.b.example.co.uk"), "example.co.uk");
+
+        let renderer = HtmlRenderer::new();
+        let html = r#"<a href="https://evil.example/login">www.mybank.com</a><a href="https://ok.com">Fine</a>"#;
+        let content = renderer.render_for_egui(html);
+        assert!(content.links[0].is_suspicious() && !content.links[1].is_suspicious());
+        assert!(content.warnings.iter().any(|w| w.starts_with("Suspicious link \"www.mybank.com\" goes to https://evil.example/login")));
+        let accessible = renderer.render_for_accessibility(html).accessible_text;
+        assert!(accessible.contains("1. ⚠ www.mybank.com — https://evil.example/login (suspicious: the text shows a different site"));
+        assert!(accessible.contains("2. Fine — https://ok.com\n"));
+
+
+        // Mail from trusted senders isn't flagged
+        let trusted = HtmlRenderer::new().with_link_warnings(false);
+        assert!(trusted.render_for_egui(html).warnings.iter().all(|w| !w.starts_with("Suspicious link")));
+        let accessible = trusted.render_for_accessibility(html).accessible_text;
+        assert!(!accessible.contains('⚠') && accessible.contains("1. www.mybank.com — https://evil.example/login\n"));
+    }
+
+    #[test]
+    fn test_extract_text_links() {
+        let renderer = HtmlRenderer::new();
+        let text = "See https://example.com/a?b=1. Or www.example.org, (https://example.com/a?b=1)\n\
+                    Mail mailto:ann@example.com or javascript:alert(1)";
+        let urls: Vec<String> = renderer.extract_text_links(text).into_iter().map(|l| l.url).collect();
+        assert_eq!(urls, ["https://example.com/a?b=1", "https://www.example.org", "mailto:ann@example.com"]);
+
+        let content = renderer.render_plain_text("No links here");
+        assert_eq!(content.plain_text, "No links here");
+        assert!(!content.has_links && content.links.is_empty());
+    }
+
     #[test]
     fn test_extract_links_filters_unsafe_schemes() {
         let renderer = HtmlRenderer::new();
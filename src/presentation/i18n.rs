@@ -0,0 +1,140 @@
+//! UI string lookup
+//!
+//! Menus, buttons and status messages are looked up by key with [`tr`]
+//! instead of being written inline. The English table ships with the app;
+//! other languages are `<language>.json` files in the `locales` folder of
+//! the configuration directory and fall back to English key by key.
+
+use crate::common::types::{parse_message_date, RelativeDate};
+use crate::service::I18n;
+use chrono::{DateTime, Datelike, Local, NaiveDate, TimeZone};
+use std::path::Path;
+use std::sync::{OnceLock, RwLock};
+
+static STRINGS: OnceLock<RwLock<I18n>> = OnceLock::new();
+
+fn strings() -> &'static RwLock<I18n> {
+    STRINGS.get_or_init(|| RwLock::new(I18n::new()))
+}
+
+/// Load the locale tables in `locale_dir` and switch to `locale`, or to
+/// the system locale when none is chosen. Call once at startup, before
+/// any window is built.
+pub fn init(locale: Option<&str>, locale_dir: Option<&Path>) {
+    let mut i18n = strings().write().unwrap_or_else(|e| e.into_inner());
+    if let Some(dir) = locale_dir {
+        i18n.load_locale_dir(dir);
+    }
+    let code = locale.map(str::to_string).unwrap_or_else(system_locale);
+    i18n.set_locale(&code);
+    tracing::info!("Interface language: {}", i18n.locale().display_name);
+}
+
+/// The UI string for `key` in the active locale
+pub fn tr(key: &str) -> String {
+    strings().read().unwrap_or_else(|e| e.into_inner()).t(key)
+}
+
+/// [`tr`] with `{0}`, `{1}`, … replaced by `args`
+pub fn trf(key: &str, args: &[&str]) -> String {
+    strings().read().unwrap_or_else(|e| e.into_inner()).tf(key, args)
+}
+
+/// Language codes the interface can be shown in, English first
+pub fn available_locales() -> Vec<String> {
+    strings().read().unwrap_or_else(|e| e.into_inner()).available_locales()
+}
+
+/// Message list date: "Today 14:32", "Yesterday", "Mar 3", or "Mar 3, 2023"
+/// for other years. Dates that can't be parsed are shown as stored.
+pub fn format_date(raw: &str) -> String {
+    format_date_at(raw, &Local::now())
+}
+
+/// Full date and time for the date column's tooltip, e.g.
+/// "Monday, March 3, 2025 at 14:32"
+pub fn format_full_date(raw: &str) -> String {
+    format_full_date_in(raw, &Local::now())
+}
+
+/// A day written out, e.g. "March 3, 2025"
+pub fn format_long_date(day: NaiveDate) -> String {
+    trf("date.long", &[&tr(&format!("date.month.{}", day.month())), &day.day().to_string(), &day.year().to_string()])
+}
+
+fn format_date_at<Tz: TimeZone>(raw: &str, now: &DateTime<Tz>) -> String {
+    let Some(date) = parse_message_date(raw) else { return raw.to_string() };
+    let month = |d: chrono::NaiveDate| tr(&format!("date.month_short.{}", d.month()));
+    match RelativeDate::of(date, now) {
+        RelativeDate::Today(time) => trf("date.today", &[&time.format("%H:%M").to_string()]),
+        RelativeDate::Yesterday(_) => tr("date.yesterday"),
+        RelativeDate::ThisYear(day) => trf("date.this_year", &[&month(day), &day.day().to_string()]),
+        RelativeDate::Older(day) => {
+            trf("date.older", &[&month(day), &day.day().to_string(), &day.year().to_string()])
+        }
+    }
+}
+
+fn format_full_date_in<Tz: TimeZone>(raw: &str, now: &DateTime<Tz>) -> String {
+    let Some(date) = parse_message_date(raw) else { return raw.to_string() };
+    let local = date.with_timezone(&now.timezone()).naive_local();
+    trf(
+        "date.full",
+        &[
+            &tr(&format!("date.weekday.{}", local.weekday().number_from_monday())),
+            &tr(&format!("date.month.{}", local.month())),
+            &local.day().to_string(),
+            &local.year().to_string(),
+            &local.format("%H:%M").to_string(),
+        ],
+    )
+}
+
+/// Locale from the environment (`LC_ALL`, `LC_MESSAGES`, then `LANG`),
+/// or English when none is set
+pub fn system_locale() -> String {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find_map(|value| locale_from_env(&value))
+        .unwrap_or_else(|| "en".to_string())
+}
+
+/// `de_DE.UTF-8` or `de_DE@euro` → `de-DE`. The POSIX `C` locale names
+/// no language.
+fn locale_from_env(value: &str) -> Option<String> {
+    let name = value.split(['.', '@']).next()?.trim();
+    if name.is_empty() || name == "C" || name == "POSIX" {
+        return None;
+    }
+    Some(name.replace('_', "-"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn test_locale_from_env() {
+        assert_eq!(locale_from_env("de_DE.UTF-8").as_deref(), Some("de-DE"));
+        assert_eq!(locale_from_env("fr_FR@euro").as_deref(), Some("fr-FR"));
+        assert_eq!(locale_from_env("es").as_deref(), Some("es"));
+        assert_eq!(locale_from_env("C.UTF-8"), None);
+        assert_eq!(locale_from_env("POSIX"), None);
+        assert_eq!(locale_from_env(""), None);
+        assert_eq!(tr("menu.quit.help"), I18n::new().t("menu.quit.help"));
+    }
+
+    #[test]
+    fn test_format_date() {
+        let now = Utc.with_ymd_and_hms(2025, 3, 5, 10, 0, 0).unwrap();
+        assert_eq!(format_date_at("Wed, 5 Mar 2025 08:07:00 +0000", &now), "Today 08:07");
+        assert_eq!(format_date_at("2025-03-04T22:00:00Z", &now), "Yesterday");
+        assert_eq!(format_date_at("2025-03-03", &now), "Mar 3");
+        assert_eq!(format_date_at("Tue, 31 Dec 2024 12:00:00 +0000", &now), "Dec 31, 2024");
+        assert_eq!(format_date_at("sometime", &now), "sometime");
+        assert_eq!(format_full_date_in("2025-03-03 14:32", &now), "Monday, March 3, 2025 at 14:32");
+        assert_eq!(format_long_date(now.date_naive()), "March 5, 2025");
+    }
+}
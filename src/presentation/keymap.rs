@@ -0,0 +1,304 @@
+//! Keyboard shortcut map
+//!
+//! Every menu command starts with the shortcut its menu label gives it.
+//! Tools > Keyboard Shortcuts can change or remove any of them; only the
+//! changes are saved (`AppConfig::shortcuts`), so commands nobody changed
+//! keep following the defaults.
+
+use crate::common::{Error, Result};
+use std::collections::HashMap;
+
+/// Shortcuts the system, screen readers or the main window itself already
+/// use, with what they do
+const RESERVED: &[(&str, &str)] = &[
+    ("Alt+F4", "closes the window"),
+    ("Alt+Tab", "switches between programs"),
+    ("Alt+Space", "opens the window menu"),
+    ("Ctrl+Esc", "opens the Start menu"),
+    ("Ctrl+Alt+Del", "is reserved by the system"),
+    ("Ctrl+Alt+N", "starts the NVDA screen reader"),
+    ("F10", "moves to the menu bar"),
+    ("Shift+F10", "opens the context menu"),
+    ("Ctrl+1", "moves to the message list"),
+    ("Ctrl+\\", "moves to the toolbar"),
+    ("Ctrl+C", "copies the selected text"),
+    ("Ctrl+X", "cuts the selected text"),
+    ("Ctrl+V", "pastes"),
+];
+
+/// What a reserved shortcut does, e.g. "closes the window" for Alt+F4
+pub fn reserved_use(shortcut: &str) -> Option<&'static str> {
+    RESERVED.iter().find(|(s, _)| *s == shortcut).map(|(_, what)| *what)
+}
+
+/// A key name in the form menu labels use: "Del", "PgUp", "F6", "K"
+fn key_name(token: &str) -> Option<String> {
+    let lower = token.to_lowercase();
+    let named = match lower.as_str() {
+        "del" | "delete" => "Del",
+        "ins" | "insert" => "Ins",
+        "enter" | "return" => "Enter",
+        "esc" | "escape" => "Esc",
+        "up" => "Up",
+        "down" => "Down",
+        "left" => "Left",
+        "right" => "Right",
+        "home" => "Home",
+        "end" => "End",
+        "pgup" | "pageup" => "PgUp",
+        "pgdn" | "pagedown" => "PgDn",
+        "space" => "Space",
+        "tab" => "Tab",
+        "back" | "backspace" => "Back",
+        _ => "",
+    };
+    if !named.is_empty() {
+        return Some(named.to_string());
+    }
+    if let Some(n) = lower.strip_prefix('f').and_then(|n| n.parse::<u32>().ok()) {
+        return (1..=24).contains(&n).then(|| format!("F{}", n));
+    }
+    let mut chars = token.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_alphanumeric() => Some(c.to_ascii_uppercase().to_string()),
+        (Some(c), None) if "-=,./;'[]\\`".contains(c) => Some(c.to_string()),
+        _ => None,
+    }
+}
+
+/// Whether `key` can be a shortcut on its own, without Ctrl or Alt;
+/// anything else would stop working for typing
+fn standalone(key: &str) -> bool {
+    key == "Del" || key == "Ins" || (key.len() > 1 && key.starts_with('F'))
+}
+
+/// `text` written the way menus show shortcuts, e.g. "ctrl+shift+k"
+/// becomes "Ctrl+Shift+K"
+pub fn normalize_shortcut(text: &str) -> Result<String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(Error::Config("Type a shortcut such as Ctrl+Shift+K".to_string()));
+    }
+    let (mut ctrl, mut alt, mut shift) = (false, false, false);
+    let mut key = None;
+    for token in text.split('+').map(str::trim) {
+        match token.to_lowercase().as_str() {
+            "ctrl" | "control" => ctrl = true,
+            "alt" => alt = true,
+            "shift" => shift = true,
+            _ => {
+                let name = key_name(token).ok_or_else(|| Error::Config(format!("\"{}\" is not a key", token)))?;
+                if key.replace(name).is_some() {
+                    return Err(Error::Config("A shortcut has one key besides Ctrl, Alt and Shift".to_string()));
+                }
+            }
+        }
+    }
+    let key = key.ok_or_else(|| Error::Config("A shortcut needs a key besides Ctrl, Alt and Shift".to_string()))?;
+    if !ctrl && !alt && !standalone(&key) {
+        return Err(Error::Config(format!("{} needs Ctrl or Alt, or it would stop working for typing", key)));
+    }
+    let mut parts = Vec::new();
+    if ctrl {
+        parts.push("Ctrl");
+    }
+    if alt {
+        parts.push("Alt");
+    }
+    if shift {
+        parts.push("Shift");
+    }
+    parts.push(&key);
+    Ok(parts.join("+"))
+}
+
+/// The shortcut of every menu command
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    /// Each command's string key and its default shortcut, in menu order
+    defaults: Vec<(String, Option<String>)>,
+    /// Changed shortcuts by command; empty for a removed shortcut
+    overrides: HashMap<String, String>,
+}
+
+impl Keymap {
+    /// The keymap for `defaults` with the saved changes applied; changes
+    /// for commands that no longer exist are dropped
+    pub fn new(defaults: Vec<(String, Option<String>)>, saved: &HashMap<String, String>) -> Self {
+        let defaults: Vec<_> = defaults
+            .into_iter()
+            .map(|(command, shortcut)| {
+                let shortcut = shortcut.map(|s| normalize_shortcut(&s).unwrap_or(s));
+                (command, shortcut)
+            })
+            .collect();
+        let overrides = saved
+            .iter()
+            .filter(|(command, _)| defaults.iter().any(|(c, _)| c == *command))
+            .filter_map(|(command, shortcut)| {
+                let shortcut = if shortcut.is_empty() { String::new() } else { normalize_shortcut(shortcut).ok()? };
+                Some((command.clone(), shortcut))
+            })
+            .collect();
+        Self { defaults, overrides }
+    }
+
+    /// The shortcut the command's menu label gives it
+    pub fn default_shortcut(&self, command: &str) -> Option<&str> {
+        self.defaults.iter().find(|(c, _)| c == command).and_then(|(_, s)| s.as_deref())
+    }
+
+    /// The command's current shortcut
+    pub fn shortcut(&self, command: &str) -> Option<&str> {
+        match self.overrides.get(command) {
+            Some(s) if s.is_empty() => None,
+            Some(s) => Some(s),
+            None => self.default_shortcut(command),
+        }
+    }
+
+    /// Whether the command's shortcut differs from its default
+    pub fn is_changed(&self, command: &str) -> bool {
+        self.overrides.contains_key(command)
+    }
+
+    /// The other command that `shortcut` already runs
+    pub fn used_by(&self, command: &str, shortcut: &str) -> Option<&str> {
+        self.defaults
+            .iter()
+            .map(|(c, _)| c.as_str())
+            .find(|c| *c != command && self.shortcut(c) == Some(shortcut))
+    }
+
+    /// Give the command `shortcut`, or no shortcut for `None`. A shortcut
+    /// another command had moves to this one; reserved shortcuts are refused.
+    pub fn set(&mut self, command: &str, shortcut: Option<&str>) -> Result<()> {
+        let shortcut = shortcut.map(normalize_shortcut).transpose()?;
+        if let Some(what) = shortcut.as_deref().and_then(reserved_use) {
+            return Err(Error::Config(format!("{} {}", shortcut.unwrap_or_default(), what)));
+        }
+        if let Some(other) = shortcut.as_deref().and_then(|s| self.used_by(command, s)).map(str::to_string) {
+            self.assign(&other, None);
+        }
+        self.assign(command, shortcut);
+        Ok(())
+    }
+
+    fn assign(&mut self, command: &str, shortcut: Option<String>) {
+        if shortcut.as_deref() == self.default_shortcut(command) {
+            self.overrides.remove(command);
+        } else {
+            self.overrides.insert(command.to_string(), shortcut.unwrap_or_default());
+        }
+    }
+
+    /// Put the command back on its default shortcut, taking it from any
+    /// command it was moved to
+    pub fn reset(&mut self, command: &str) {
+        self.overrides.remove(command);
+        if let Some(default) = self.default_shortcut(command).map(str::to_string) {
+            if let Some(other) = self.used_by(command, &default).map(str::to_string) {
+                self.assign(&other, None);
+            }
+        }
+    }
+
+    /// Put every command back on its default shortcut
+    pub fn reset_all(&mut self) {
+        self.overrides.clear();
+    }
+
+    /// The changes to save in `AppConfig::shortcuts`
+    pub fn saved(&self) -> HashMap<String, String> {
+        self.overrides.clone()
+    }
+
+    /// A menu label with the command's current shortcut in place of the
+    /// one after its tab, e.g. "&Reply\tCtrl+R"
+    pub fn label(&self, command: &str, label: &str) -> String {
+        let name = label.split_once('\t').map_or(label, |(name, _)| name);
+        match self.shortcut(command) {
+            Some(shortcut) => format!("{}\t{}", name, shortcut),
+            None => name.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keymap(saved: &[(&str, &str)]) -> Keymap {
+        let defaults = vec![
+            ("menu.reply".to_string(), Some("Ctrl+R".to_string())),
+            ("menu.forward".to_string(), Some("Ctrl+L".to_string())),
+            ("menu.delete".to_string(), Some("Del".to_string())),
+            ("menu.block_sender".to_string(), None),
+        ];
+        let saved = saved.iter().map(|(c, s)| (c.to_string(), s.to_string())).collect();
+        Keymap::new(defaults, &saved)
+    }
+
+    #[test]
+    fn test_normalize_shortcut() {
+        assert_eq!(normalize_shortcut("ctrl+shift+k").unwrap(), "Ctrl+Shift+K");
+        assert_eq!(normalize_shortcut("Shift + Alt + down").unwrap(), "Alt+Shift+Down");
+        assert_eq!(normalize_shortcut("Delete").unwrap(), "Del");
+        assert_eq!(normalize_shortcut("shift+f6").unwrap(), "Shift+F6");
+        assert_eq!(normalize_shortcut("Ctrl+-").unwrap(), "Ctrl+-");
+        assert!(normalize_shortcut("").is_err());
+        assert!(normalize_shortcut("Ctrl+Shift").is_err());
+        assert!(normalize_shortcut("Ctrl+A+B").is_err());
+        assert!(normalize_shortcut("Ctrl+Banana").is_err());
+        // A bare typing key would be swallowed in text boxes
+        assert!(normalize_shortcut("Shift+K").is_err());
+        assert!(normalize_shortcut("F25").is_err());
+    }
+
+    #[test]
+    fn test_keymap_defaults_and_saved_changes() {
+        let map = keymap(&[("menu.reply", "ctrl+shift+y"), ("menu.delete", ""), ("menu.gone", "Ctrl+G")]);
+        assert_eq!(map.shortcut("menu.reply"), Some("Ctrl+Shift+Y"));
+        assert_eq!(map.shortcut("menu.forward"), Some("Ctrl+L"));
+        assert_eq!(map.shortcut("menu.delete"), None);
+        assert_eq!(map.shortcut("menu.block_sender"), None);
+        assert!(map.is_changed("menu.reply"));
+        assert!(!map.is_changed("menu.forward"));
+        assert!(!map.saved().contains_key("menu.gone"));
+        assert_eq!(map.label("menu.reply", "&Reply\tCtrl+R"), "&Reply\tCtrl+Shift+Y");
+        assert_eq!(map.label("menu.delete", "&Delete\tDel"), "&Delete");
+    }
+
+    #[test]
+    fn test_keymap_set_moves_conflicting_shortcut() {
+        let mut map = keymap(&[]);
+        assert_eq!(map.used_by("menu.block_sender", "Ctrl+R"), Some("menu.reply"));
+        assert_eq!(map.used_by("menu.reply", "Ctrl+R"), None);
+
+        map.set("menu.block_sender", Some("ctrl+r")).unwrap();
+        assert_eq!(map.shortcut("menu.block_sender"), Some("Ctrl+R"));
+        assert_eq!(map.shortcut("menu.reply"), None);
+
+        // Resetting Reply takes its shortcut back
+        map.reset("menu.reply");
+        assert_eq!(map.shortcut("menu.reply"), Some("Ctrl+R"));
+        assert_eq!(map.shortcut("menu.block_sender"), None);
+        assert!(map.saved().is_empty());
+
+        map.set("menu.forward", None).unwrap();
+        assert_eq!(map.saved().get("menu.forward").map(String::as_str), Some(""));
+        map.reset_all();
+        assert_eq!(map.shortcut("menu.forward"), Some("Ctrl+L"));
+    }
+
+    #[test]
+    fn test_keymap_refuses_reserved_shortcuts() {
+        let mut map = keymap(&[]);
+        let err = map.set("menu.reply", Some("alt+f4")).unwrap_err();
+        assert!(err.to_string().contains("Alt+F4 closes the window"));
+        assert!(map.set("menu.reply", Some("Ctrl+1")).is_err());
+        assert!(map.set("menu.reply", Some("ctrl+c")).is_err());
+        assert!(map.set("menu.reply", Some("Shift+K")).is_err());
+        assert_eq!(map.shortcut("menu.reply"), Some("Ctrl+R"));
+    }
+}
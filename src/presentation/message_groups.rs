@@ -0,0 +1,322 @@
+//! Grouped message list
+//!
+//! Clusters the message list by sender, sender domain or mailing list, so
+//! newsletters can be triaged apart from personal mail. Each group gets a
+//! header row with its counts; collapsed groups show only the header. The
+//! list control then shows [`ListRow`]s rather than messages directly, and
+//! [`MessageRows`] maps between the two. The quick filter hides rows the
+//! same way, leaving the messages themselves alone.
+
+use crate::common::types::EmailAddress;
+use crate::presentation::ui_types::MessageItem;
+use std::collections::{HashMap, HashSet};
+
+/// How the message list is grouped
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GroupBy {
+    #[default]
+    None,
+    Sender,
+    Domain,
+    List,
+}
+
+impl GroupBy {
+    /// Parse a session key, falling back to no grouping
+    pub fn from_config_key(key: &str) -> Self {
+        match key {
+            "sender" => GroupBy::Sender,
+            "domain" => GroupBy::Domain,
+            "list" => GroupBy::List,
+            _ => GroupBy::None,
+        }
+    }
+
+    /// The session key for this grouping
+    pub fn as_config_key(&self) -> &'static str {
+        match self {
+            GroupBy::None => "none",
+            GroupBy::Sender => "sender",
+            GroupBy::Domain => "domain",
+            GroupBy::List => "list",
+        }
+    }
+
+    /// Group key and header label for a message. Keys are lowercased so
+    /// differently cased addresses share a group.
+    fn key(&self, message: &MessageItem) -> (String, String) {
+        let sender = EmailAddress::parse_list(&message.from).into_iter().next();
+        let address = sender.as_ref().map(|a| a.address.to_lowercase()).unwrap_or_default();
+        match self {
+            GroupBy::None => (String::new(), String::new()),
+            GroupBy::Sender => {
+                let label = sender.map(|a| a.to_string()).unwrap_or_else(|| "(unknown sender)".to_string());
+                (address, label)
+            }
+            GroupBy::Domain => {
+                let domain = address.rsplit_once('@').map(|(_, d)| d.to_string()).unwrap_or_default();
+                let label = if domain.is_empty() { "(unknown domain)".to_string() } else { domain.clone() };
+                (domain, label)
+            }
+            GroupBy::List => match &message.list_id {
+                Some(id) => (id.to_lowercase(), id.clone()),
+                None => (String::new(), "Not from a mailing list".to_string()),
+            },
+        }
+    }
+}
+
+/// A row of the message list
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ListRow {
+    /// A group's header
+    Header(GroupHeader),
+    /// The message at this index of the (grouped) message list
+    Message(usize),
+}
+
+/// Header row of a group
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GroupHeader {
+    /// Identifies the group across reloads, for remembering it collapsed
+    pub key: String,
+    pub label: String,
+    pub count: usize,
+    pub unread: usize,
+    pub collapsed: bool,
+    /// Index of the group's first message; the rest follow it
+    pub first: usize,
+}
+
+impl GroupHeader {
+    /// Subject column text, e.g. "▾ example.com (5, 2 unread)"
+    pub fn title(&self) -> String {
+        let marker = if self.collapsed { "▸" } else { "▾" };
+        match self.unread {
+            0 => format!("{} {} ({})", marker, self.label, self.count),
+            n => format!("{} {} ({}, {} unread)", marker, self.label, self.count, n),
+        }
+    }
+
+    /// What screen readers hear when the header is focused
+    pub fn description(&self) -> String {
+        let messages = if self.count == 1 { "1 message".to_string() } else { format!("{} messages", self.count) };
+        let state = if self.collapsed { "collapsed" } else { "expanded" };
+        format!("Group {}, {}, {} unread, {}", self.label, messages, self.unread, state)
+    }
+
+    /// Indexes of the group's messages
+    pub fn messages(&self) -> std::ops::Range<usize> {
+        self.first..self.first + self.count
+    }
+}
+
+/// Rows shown in the message list, and where each message is among them
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MessageRows {
+    rows: Vec<ListRow>,
+}
+
+impl MessageRows {
+    /// One row per message, in order
+    pub fn ungrouped(count: usize) -> Self {
+        Self { rows: (0..count).map(ListRow::Message).collect() }
+    }
+
+    /// Reorder `messages` so each group is contiguous and lay out its rows.
+    /// Groups keep the order of their first message, and messages keep
+    /// their order within a group, so the current sort still applies.
+    /// Groups whose key is in `collapsed` show only their header.
+    pub fn group(messages: &mut Vec<MessageItem>, by: GroupBy, collapsed: &HashSet<String>) -> Self {
+        if by == GroupBy::None {
+            return Self::ungrouped(messages.len());
+        }
+        let mut order: Vec<(String, String)> = Vec::new();
+        let mut members: HashMap<String, Vec<MessageItem>> = HashMap::new();
+        for message in messages.drain(..) {
+            let (key, label) = by.key(&message);
+            if !members.contains_key(&key) {
+                order.push((key.clone(), label));
+            }
+            members.entry(key).or_default().push(message);
+        }
+        let mut rows = Vec::new();
+        for (key, label) in order {
+            let group = members.remove(&key).unwrap_or_default();
+            let header = GroupHeader {
+                collapsed: collapsed.contains(&key),
+                key,
+                label,
+                count: group.len(),
+                unread: group.iter().filter(|m| !m.read).count(),
+                first: messages.len(),
+            };
+            let shown = if header.collapsed { 0..0 } else { header.messages() };
+            rows.push(ListRow::Header(header));
+            rows.extend(shown.map(ListRow::Message));
+            messages.extend(group);
+        }
+        Self { rows }
+    }
+
+    /// Drop the rows of messages `keep` rejects, and the headers of groups
+    /// left with none. Collapsed groups stay while any message in them
+    /// matches.
+    pub fn filter(&mut self, messages: &[MessageItem], keep: impl Fn(&MessageItem) -> bool) {
+        self.rows.retain(|row| match row {
+            ListRow::Header(header) => messages.get(header.messages()).is_some_and(|group| group.iter().any(&keep)),
+            ListRow::Message(index) => messages.get(*index).is_some_and(&keep),
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ListRow> {
+        self.rows.iter()
+    }
+
+    pub fn get(&self, row: usize) -> Option<&ListRow> {
+        self.rows.get(row)
+    }
+
+    /// Message shown in `row`; `None` for a header
+    pub fn message_at(&self, row: usize) -> Option<usize> {
+        match self.rows.get(row)? {
+            ListRow::Message(index) => Some(*index),
+            ListRow::Header(_) => None,
+        }
+    }
+
+    /// Row showing message `index`; `None` while its group is collapsed
+    pub fn row_of(&self, index: usize) -> Option<usize> {
+        self.rows.iter().position(|r| *r == ListRow::Message(index))
+    }
+
+    /// Count unread messages again after read flags change. Returns the
+    /// header rows whose counts changed, with their new titles.
+    pub fn recount(&mut self, messages: &[MessageItem]) -> Vec<(usize, String)> {
+        let mut changed = Vec::new();
+        for (row, r) in self.rows.iter_mut().enumerate() {
+            if let ListRow::Header(header) = r {
+                let unread = messages.get(header.messages()).map_or(0, |m| m.iter().filter(|m| !m.read).count());
+                if unread != header.unread {
+                    header.unread = unread;
+                    changed.push((row, header.title()));
+                }
+            }
+        }
+        changed
+    }
+
+    /// Header of the group `row` belongs to
+    pub fn header_for(&self, row: usize) -> Option<(usize, &GroupHeader)> {
+        self.rows[..=row.min(self.rows.len().checked_sub(1)?)].iter().enumerate().rev().find_map(|(i, r)| match r {
+            ListRow::Header(header) => Some((i, header)),
+            ListRow::Message(_) => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(uid: u32, from: &str, list_id: Option<&str>, read: bool) -> MessageItem {
+        MessageItem {
+            uid,
+            from: from.to_string(),
+            subject: format!("Message {}", uid),
+            read,
+            list_id: list_id.map(str::to_string),
+            ..MessageItem::default()
+        }
+    }
+
+    fn inbox() -> Vec<MessageItem> {
+        vec![
+            message(1, "News <news@shop.example.com>", Some("offers.shop.example.com"), false),
+            message(2, "ana@example.org", None, false),
+            message(3, "deals@SHOP.example.com", Some("offers.shop.example.com"), true),
+            message(4, "Ana <Ana@example.org>", None, true),
+        ]
+    }
+
+    #[test]
+    fn test_group_by_domain() {
+        let mut messages = inbox();
+        let rows = MessageRows::group(&mut messages, GroupBy::Domain, &HashSet::new());
+        let uids: Vec<u32> = messages.iter().map(|m| m.uid).collect();
+        assert_eq!(uids, [1, 3, 2, 4]);
+        assert_eq!(rows.len(), 6);
+        let Some(ListRow::Header(shop)) = rows.get(0) else { panic!("expected a header") };
+        assert_eq!(shop.title(), "▾ shop.example.com (2, 1 unread)");
+        assert_eq!(rows.message_at(1), Some(0));
+        assert_eq!(rows.message_at(3), None);
+        assert_eq!(rows.row_of(2), Some(4));
+        assert_eq!(rows.header_for(5).map(|(row, h)| (row, h.label.as_str())), Some((3, "example.org")));
+
+        let mut rows = rows;
+        messages[0].read = true;
+        assert_eq!(rows.recount(&messages), [(0, "▾ shop.example.com (2)".to_string())]);
+        assert!(rows.recount(&messages).is_empty());
+    }
+
+    #[test]
+    fn test_collapsed_groups_and_keys() {
+        let mut messages = inbox();
+        let collapsed = HashSet::from(["ana@example.org".to_string()]);
+        let rows = MessageRows::group(&mut messages, GroupBy::Sender, &collapsed);
+        // Ana's two messages sit under one collapsed header between the shop senders
+        assert_eq!(rows.len(), 5);
+        let Some(ListRow::Header(ana)) = rows.get(2) else { panic!("expected a header") };
+        assert!(ana.collapsed);
+        assert_eq!(ana.messages(), 1..3);
+        assert_eq!(ana.description(), "Group ana@example.org, 2 messages, 1 unread, collapsed");
+        assert_eq!(rows.row_of(1), None);
+        assert_eq!(rows.row_of(3), Some(4));
+
+        let mut messages = inbox();
+        let rows = MessageRows::group(&mut messages, GroupBy::List, &HashSet::new());
+        let labels: Vec<String> = rows
+            .iter()
+            .filter_map(|r| match r {
+                ListRow::Header(h) => Some(h.label.clone()),
+                ListRow::Message(_) => None,
+            })
+            .collect();
+        assert_eq!(labels, ["offers.shop.example.com", "Not from a mailing list"]);
+
+        let mut messages = inbox();
+        assert_eq!(MessageRows::group(&mut messages, GroupBy::None, &HashSet::new()), MessageRows::ungrouped(4));
+        assert_eq!(GroupBy::from_config_key(GroupBy::List.as_config_key()), GroupBy::List);
+    }
+
+    #[test]
+    fn test_filter_rows() {
+        let mut messages = inbox();
+        let mut rows = MessageRows::group(&mut messages, GroupBy::Domain, &HashSet::new());
+        rows.filter(&messages, |m| m.from.contains("Ana"));
+        // The shop group has no match, so its header goes too
+        assert_eq!(rows.len(), 2);
+        assert!(matches!(rows.get(0), Some(ListRow::Header(h)) if h.label == "example.org"));
+        assert_eq!(rows.row_of(3), Some(1));
+        assert_eq!(rows.row_of(2), None);
+        assert_eq!(messages.len(), 4);
+
+        let collapsed = HashSet::from(["example.org".to_string()]);
+        let mut rows = MessageRows::group(&mut messages, GroupBy::Domain, &collapsed);
+        rows.filter(&messages, |m| m.from.contains("Ana"));
+        assert_eq!(rows.len(), 1);
+
+        let mut rows = MessageRows::ungrouped(4);
+        rows.filter(&messages, |m| m.read);
+        assert_eq!(rows.message_at(0), Some(1));
+        assert_eq!(rows.len(), 2);
+    }
+}
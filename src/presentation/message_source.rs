@@ -0,0 +1,163 @@
+//! Message source viewer
+//!
+//! Lays out a message's raw headers and MIME for the "View Source" window,
+//! with the headers that matter for verification (authentication results,
+//! DKIM signatures and the `Received` chain) summarised first and marked in
+//! the full listing.
+
+/// Headers worth pointing out when checking where a message came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderKind {
+    AuthenticationResults,
+    DkimSignature,
+    Received,
+    Other,
+}
+
+impl HeaderKind {
+    fn of(name: &str) -> Self {
+        match name.to_ascii_lowercase().as_str() {
+            "authentication-results" | "arc-authentication-results" => HeaderKind::AuthenticationResults,
+            "dkim-signature" => HeaderKind::DkimSignature,
+            "received" => HeaderKind::Received,
+            _ => HeaderKind::Other,
+        }
+    }
+}
+
+/// One header with continuation lines unfolded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceHeader {
+    pub name: String,
+    pub value: String,
+    pub kind: HeaderKind,
+}
+
+/// A raw message split into headers and body
+#[derive(Debug, Clone)]
+pub struct MessageSource {
+    pub headers: Vec<SourceHeader>,
+    /// Everything after the header block, as received
+    pub body: String,
+}
+
+impl MessageSource {
+    /// Split a raw RFC 5322 message. Text without a header block is kept
+    /// entirely as the body.
+    pub fn parse(raw: &str) -> Self {
+        let (head, body) = raw
+            .find("\r\n\r\n")
+            .map(|i| (&raw[..i], &raw[i + 4..]))
+            .or_else(|| raw.find("\n\n").map(|i| (&raw[..i], &raw[i + 2..])))
+            .unwrap_or((raw, ""));
+
+        let mut headers: Vec<SourceHeader> = Vec::new();
+        for line in head.lines() {
+            if line.starts_with([' ', '\t']) {
+                if let Some(last) = headers.last_mut() {
+                    last.value.push(' ');
+                    last.value.push_str(line.trim());
+                    continue;
+                }
+            }
+            let Some((name, value)) = line.split_once(':').filter(|(n, _)| is_header_name(n)) else {
+                return Self { headers: Vec::new(), body: raw.to_string() };
+            };
+            headers.push(SourceHeader {
+                name: name.to_string(),
+                value: value.trim().to_string(),
+                kind: HeaderKind::of(name),
+            });
+        }
+        Self { headers, body: body.to_string() }
+    }
+
+    /// Text for the source window: a summary of the verification headers,
+    /// then every header (highlighted ones marked with `»`), then the body.
+    pub fn render(&self) -> String {
+        let mut out = String::from("KEY HEADERS\n");
+        let of_kind = |kind| self.headers.iter().filter(move |h| h.kind == kind);
+
+        let mut results = of_kind(HeaderKind::AuthenticationResults).peekable();
+        if results.peek().is_none() {
+            out.push_str("Authentication results: none\n");
+        }
+        for header in results {
+            out.push_str(&format!("Authentication results: {}\n", header.value));
+        }
+
+        let mut signatures = of_kind(HeaderKind::DkimSignature).peekable();
+        if signatures.peek().is_none() {
+            out.push_str("DKIM signature: none\n");
+        }
+        for header in signatures {
+            let tag = |name| dkim_tag(&header.value, name).unwrap_or("?");
+            out.push_str(&format!("DKIM signature: domain {}, selector {}\n", tag("d"), tag("s")));
+        }
+
+        // Received headers are prepended by each server, so the last is the first hop
+        let hops: Vec<_> = of_kind(HeaderKind::Received).collect();
+        for (i, header) in hops.iter().rev().enumerate() {
+            out.push_str(&format!("Hop {} of {}: {}\n", i + 1, hops.len(), header.value));
+        }
+
+        out.push_str("\nFULL HEADERS\n");
+        for header in &self.headers {
+            let marker = if header.kind == HeaderKind::Other { "  " } else { "» " };
+            out.push_str(&format!("{}{}: {}\n", marker, header.name, header.value));
+        }
+        if !self.body.is_empty() {
+            out.push_str("\nBODY\n");
+            out.push_str(&self.body);
+        }
+        out
+    }
+}
+
+/// RFC 5322 field name: printable ASCII without spaces or colons
+fn is_header_name(name: &str) -> bool {
+    !name.is_empty() && name.bytes().all(|b| (33..=126).contains(&b))
+}
+
+/// Value of a `tag=value` pair in a DKIM-Signature header
+fn dkim_tag<'a>(signature: &'a str, tag: &str) -> Option<&'a str> {
+    signature.split(';').find_map(|pair| {
+        let (name, value) = pair.split_once('=')?;
+        (name.trim() == tag).then(|| value.trim())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RAW: &str = "Received: from mx.example.com by mail.local; Mon, 1 Jan 2024 10:00:02 +0000\r\n\
+        Received: from sender.example.org\r\n\tby mx.example.com; Mon, 1 Jan 2024 10:00:01 +0000\r\n\
+        Authentication-Results: mx.example.com; spf=pass; dkim=pass header.d=example.org\r\n\
+        DKIM-Signature: v=1; a=rsa-sha256; d=example.org; s=mail2024;\r\n bh=abc=; b=def=\r\n\
+        From: alice@example.org\r\n\
+        Subject: Hi\r\n\
+        \r\n\
+        Hello\r\n";
+
+    #[test]
+    fn test_parse_and_render_source() {
+        let source = MessageSource::parse(RAW);
+        assert_eq!(source.headers.len(), 6);
+        assert_eq!(source.headers[1].value, "from sender.example.org by mx.example.com; Mon, 1 Jan 2024 10:00:01 +0000");
+        assert_eq!(source.headers[2].kind, HeaderKind::AuthenticationResults);
+        assert_eq!(source.body, "Hello\r\n");
+
+        let text = source.render();
+        assert!(text.contains("Authentication results: mx.example.com; spf=pass; dkim=pass header.d=example.org\n"));
+        assert!(text.contains("DKIM signature: domain example.org, selector mail2024\n"));
+        assert!(text.contains("Hop 1 of 2: from sender.example.org"));
+        assert!(text.contains("Hop 2 of 2: from mx.example.com"));
+        assert!(text.contains("» DKIM-Signature: v=1;"));
+        assert!(text.contains("  Subject: Hi\n"));
+
+        let bare = MessageSource::parse("<p>Only a body</p>");
+        assert!(bare.headers.is_empty());
+        assert!(bare.render().contains("Authentication results: none"));
+    }
+}
@@ -3,14 +3,28 @@
 //! Native wxdragon (wxWidgets) UI with built-in accessibility support.
 
 pub mod accessibility;
+pub mod command_palette;
 pub mod html_renderer;
+pub mod i18n;
+pub mod keymap;
+pub mod message_groups;
+pub mod message_source;
+pub mod printing;
+pub mod theme;
+pub mod thread_reader;
 pub mod ui_types;
 pub mod wx_account_manager;
 pub mod wx_app;
+pub mod wx_backup;
+pub mod wx_command_palette;
 pub mod wx_compose;
+pub mod wx_log_viewer;
 pub mod wx_managers;
+pub mod wx_master_password;
 pub mod wx_oauth;
+pub mod wx_readiness;
 pub mod wx_settings;
+pub mod wx_shortcuts;
 
 pub use accessibility::Accessibility;
 pub use html_renderer::HtmlRenderer;
@@ -0,0 +1,621 @@
+//! Printing and PDF export
+//!
+//! Lays out a message, with its headers, text, attachment list and image
+//! attachments, for printing or saving as PDF. Printing goes through a
+//! page the browser opens with its print dialog. PDFs are written here
+//! directly, in the standard Helvetica font every PDF reader has, so no
+//! font or PDF library is needed. Helvetica only covers Western European
+//! text, so a message in another script isn't saved as PDF at all
+//! (`UnsupportedText`); printing it through the browser keeps every script.
+//!
+//! The print page holds the message decrypted, so it is written for the
+//! current user only and removed once the browser has had time to load it,
+//! or on quit.
+
+use crate::service::attachments::ImagePreview;
+use base64::Engine;
+use std::fmt;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// How long a print page stays on disk for the browser to load it
+pub const PRINT_PAGE_LIFETIME: Duration = Duration::from_secs(60);
+
+/// Print pages written and not yet removed
+static PRINT_PAGES: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// A message as printed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PrintedMessage {
+    pub subject: String,
+    /// Label and value, e.g. `("From", "Ann <ann@example.com>")`, in order
+    pub headers: Vec<(String, String)>,
+    /// Plain text of the body
+    pub body: String,
+    /// Each attachment as listed, e.g. "report.pdf (120 KB)"
+    pub attachments: Vec<String>,
+}
+
+/// A4, in points
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 56.0;
+const SUBJECT_SIZE: f32 = 15.0;
+const TEXT_SIZE: f32 = 10.5;
+const FOOTER_SIZE: f32 = 8.0;
+const LINE_SPACING: f32 = 1.35;
+
+/// Helvetica advance widths for ' ' to '~', in thousandths of the font size
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556, 556, 556, 556, 556,
+    556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833,
+    722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556,
+    556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334,
+    260, 334, 584,
+];
+
+/// The page the browser prints: it opens the print dialog once loaded
+pub fn to_html(message: &PrintedMessage, images: &[ImagePreview]) -> String {
+    let text = |s: &str| html_escape::encode_text(s).to_string();
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n\
+         body {{ font-family: sans-serif; margin: 2em; }}\n\
+         h1 {{ font-size: 1.4em; }}\n\
+         th {{ text-align: left; vertical-align: top; padding-right: 1em; }}\n\
+         pre {{ white-space: pre-wrap; font-family: inherit; }}\n\
+         img {{ max-width: 100%; }}\n\
+         </style>\n</head>\n<body onload=\"window.print()\">\n<h1>{}</h1>\n<table>\n",
+        text(&message.subject),
+        text(&message.subject)
+    );
+    for (label, value) in &message.headers {
+        html.push_str(&format!("<tr><th>{}</th><td>{}</td></tr>\n", text(label), text(value)));
+    }
+    html.push_str(&format!("</table>\n<hr>\n<pre>{}</pre>\n", text(&message.body)));
+    if !message.attachments.is_empty() {
+        html.push_str("<h2>Attachments</h2>\n<ul>\n");
+        for attachment in &message.attachments {
+            html.push_str(&format!("<li>{}</li>\n", text(attachment)));
+        }
+        html.push_str("</ul>\n");
+    }
+    for image in images {
+        let Some(data) = png_base64(image) else { continue };
+        let label = text(&image.label);
+        html.push_str(&format!(
+            "<figure>\n<img src=\"data:image/png;base64,{}\" alt=\"{}\">\n<figcaption>{}</figcaption>\n</figure>\n",
+            data, label, label
+        ));
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// Write a print page to a new file in `dir` that only the current user
+/// can read, returning its path. Remove it with `remove_print_page`.
+pub fn write_print_page(dir: &Path, html: &str) -> std::io::Result<PathBuf> {
+    let path = dir.join(format!("wixen-mail-print-{}.html", uuid::Uuid::new_v4()));
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&path)?;
+    if let Ok(mut pages) = PRINT_PAGES.lock() {
+        pages.push(path.clone());
+    }
+    if let Err(e) = file.write_all(html.as_bytes()) {
+        remove_print_page(&path);
+        return Err(e);
+    }
+    Ok(path)
+}
+
+/// Delete a print page written by `write_print_page`.
+pub fn remove_print_page(path: &Path) {
+    if let Ok(mut pages) = PRINT_PAGES.lock() {
+        pages.retain(|p| p != path);
+    }
+    if let Err(e) = std::fs::remove_file(path) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            tracing::warn!("Could not delete print page {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Delete every print page still on disk, as on quit. Returns how many
+/// there were.
+pub fn remove_print_pages() -> usize {
+    let pages = PRINT_PAGES.lock().map(|mut pages| std::mem::take(&mut *pages)).unwrap_or_default();
+    for path in &pages {
+        remove_print_page(path);
+    }
+    pages.len()
+}
+
+/// The image as a base64 PNG
+fn png_base64(image: &ImagePreview) -> Option<String> {
+    let pixels = image::RgbaImage::from_raw(image.width, image.height, image.rgba.clone())?;
+    let mut png = Cursor::new(Vec::new());
+    pixels.write_to(&mut png, image::ImageFormat::Png).ok()?;
+    Some(base64::engine::general_purpose::STANDARD.encode(png.into_inner()))
+}
+
+/// What is drawn on a page
+#[derive(Debug, Clone, PartialEq)]
+enum Mark {
+    Text { x: f32, y: f32, size: f32, text: String },
+    /// A rule across the text column
+    Rule { y: f32 },
+    /// One of the images, by index, with its lower left corner and size
+    Image { index: usize, x: f32, y: f32, width: f32, height: f32 },
+}
+
+/// Places everything top to bottom, starting a page when one is full
+struct Layout {
+    pages: Vec<Vec<Mark>>,
+    y: f32,
+}
+
+impl Layout {
+    fn new() -> Self {
+        Self { pages: vec![Vec::new()], y: PAGE_HEIGHT - MARGIN }
+    }
+
+    /// Make room for `height`, on a new page if this one can't take it
+    fn reserve(&mut self, height: f32) {
+        if self.y - height < MARGIN && !self.pages.last().is_some_and(Vec::is_empty) {
+            self.pages.push(Vec::new());
+            self.y = PAGE_HEIGHT - MARGIN;
+        }
+    }
+
+    fn mark(&mut self, mark: Mark) {
+        self.pages.last_mut().expect("layout always has a page").push(mark);
+    }
+
+    /// `text` in lines that fit the column; blank lines are kept
+    fn paragraph(&mut self, text: &str, size: f32) {
+        let line_height = size * LINE_SPACING;
+        for line in text.lines() {
+            for line in wrap(line, size, PAGE_WIDTH - 2.0 * MARGIN) {
+                self.reserve(line_height);
+                self.y -= line_height;
+                if !line.is_empty() {
+                    self.mark(Mark::Text { x: MARGIN, y: self.y, size, text: line });
+                }
+            }
+        }
+    }
+
+    fn space(&mut self, height: f32) {
+        self.y -= height;
+    }
+
+    fn rule(&mut self) {
+        self.reserve(TEXT_SIZE);
+        self.y -= TEXT_SIZE / 2.0;
+        let y = self.y;
+        self.mark(Mark::Rule { y });
+        self.y -= TEXT_SIZE / 2.0;
+    }
+
+    /// The image scaled down to fit the column and a page, with its label
+    fn image(&mut self, index: usize, image: &ImagePreview) {
+        if image.width == 0 || image.height == 0 {
+            return;
+        }
+        let (width, height) = (image.width as f32, image.height as f32);
+        let max_width = PAGE_WIDTH - 2.0 * MARGIN;
+        let max_height = PAGE_HEIGHT - 2.0 * MARGIN - 2.0 * TEXT_SIZE * LINE_SPACING;
+        let scale = (max_width / width).min(max_height / height).min(1.0);
+        let (width, height) = (width * scale, height * scale);
+        self.reserve(height + TEXT_SIZE * LINE_SPACING);
+        self.y -= height;
+        let y = self.y;
+        self.mark(Mark::Image { index, x: MARGIN, y, width, height });
+        self.paragraph(&image.label, FOOTER_SIZE);
+        self.space(TEXT_SIZE);
+    }
+}
+
+/// A character `to_pdf` can't write: it isn't in Helvetica's Western
+/// European character set, as with Greek, Cyrillic or Chinese text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnsupportedText(pub char);
+
+impl fmt::Display for UnsupportedText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\"{}\" (U+{:04X}) can't be shown in the PDF's font", self.0, self.0 as u32)
+    }
+}
+
+/// The message as a PDF file. Fails on the first character the PDF's font
+/// has no glyph for, rather than printing it as "?".
+pub fn to_pdf(message: &PrintedMessage, images: &[ImagePreview]) -> Result<Vec<u8>, UnsupportedText> {
+    let texts = std::iter::once(&message.subject)
+        .chain(message.headers.iter().flat_map(|(label, value)| [label, value]))
+        .chain([&message.body])
+        .chain(&message.attachments)
+        .chain(images.iter().map(|image| &image.label));
+    for text in texts {
+        if let Some(c) = text.chars().find(|c| !is_invisible(*c) && win_ansi(*c).is_none()) {
+            return Err(UnsupportedText(c));
+        }
+    }
+
+    let mut layout = Layout::new();
+    layout.paragraph(&message.subject, SUBJECT_SIZE);
+    layout.space(TEXT_SIZE / 2.0);
+    for (label, value) in &message.headers {
+        layout.paragraph(&format!("{}: {}", label, value), TEXT_SIZE);
+    }
+    layout.rule();
+    layout.paragraph(&message.body, TEXT_SIZE);
+    if !message.attachments.is_empty() {
+        layout.space(TEXT_SIZE);
+        layout.paragraph("Attachments", TEXT_SIZE);
+        for attachment in &message.attachments {
+            layout.paragraph(&format!("\u{2022} {}", attachment), TEXT_SIZE);
+        }
+    }
+    if !images.is_empty() {
+        layout.space(TEXT_SIZE);
+    }
+    for (index, image) in images.iter().enumerate() {
+        layout.image(index, image);
+    }
+
+    // Page numbers go on once the page count is known
+    let count = layout.pages.len();
+    for (n, page) in layout.pages.iter_mut().enumerate() {
+        let footer = format!("Page {} of {}", n + 1, count);
+        let x = PAGE_WIDTH - MARGIN - text_width(&footer, FOOTER_SIZE);
+        page.push(Mark::Text { x, y: MARGIN / 2.0, size: FOOTER_SIZE, text: footer });
+    }
+    Ok(write_pdf(&message.subject, &layout.pages, images))
+}
+
+/// Objects 1 to 4 are the catalog, page tree, font and document info;
+/// the images follow, then each page and its contents
+fn write_pdf(title: &str, pages: &[Vec<Mark>], images: &[ImagePreview]) -> Vec<u8> {
+    let first_image = 5;
+    let first_page = first_image + images.len();
+    let page_object = |n: usize| first_page + 2 * n;
+
+    let mut objects: Vec<Vec<u8>> = Vec::new();
+    objects.push(b"<< /Type /Catalog /Pages 2 0 R >>".to_vec());
+    let kids: Vec<String> = (0..pages.len()).map(|n| format!("{} 0 R", page_object(n))).collect();
+    objects.push(format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages.len()).into_bytes());
+    objects.push(b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>".to_vec());
+    let mut info = b"<< /Title ".to_vec();
+    info.extend(pdf_string(title));
+    info.extend(b" /Producer (Wixen Mail) >>");
+    objects.push(info);
+
+    for image in images {
+        // Transparent pixels are printed over white
+        let rgb: Vec<u8> = image
+            .rgba
+            .chunks_exact(4)
+            .flat_map(|p| {
+                let alpha = p[3] as u32;
+                [0, 1, 2].map(|i| ((p[i] as u32 * alpha + 255 * (255 - alpha)) / 255) as u8)
+            })
+            .collect();
+        objects.push(stream(
+            &format!(
+                "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB /BitsPerComponent 8",
+                image.width, image.height
+            ),
+            &rgb,
+        ));
+    }
+
+    let xobjects: String = (0..images.len()).map(|i| format!("/Im{} {} 0 R ", i, first_image + i)).collect();
+    for (n, page) in pages.iter().enumerate() {
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Contents {} 0 R \
+                 /Resources << /Font << /F1 3 0 R >> /XObject << {}>> >> >>",
+                PAGE_WIDTH,
+                PAGE_HEIGHT,
+                page_object(n) + 1,
+                xobjects
+            )
+            .into_bytes(),
+        );
+        objects.push(stream("", &page_contents(page)));
+    }
+
+    let mut pdf = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend(format!("{} 0 obj\n", i + 1).into_bytes());
+        pdf.extend(object);
+        pdf.extend(b"\nendobj\n");
+    }
+    let xref = pdf.len();
+    pdf.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).into_bytes());
+    for offset in offsets {
+        pdf.extend(format!("{:010} 00000 n \n", offset).into_bytes());
+    }
+    pdf.extend(
+        format!("trailer\n<< /Size {} /Root 1 0 R /Info 4 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref)
+            .into_bytes(),
+    );
+    pdf
+}
+
+/// Drawing operators for one page
+fn page_contents(page: &[Mark]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for mark in page {
+        match mark {
+            Mark::Text { x, y, size, text } => {
+                out.extend(format!("BT /F1 {:.1} Tf {:.2} {:.2} Td ", size, x, y).into_bytes());
+                out.extend(pdf_string(text));
+                out.extend(b" Tj ET\n");
+            }
+            Mark::Rule { y } => {
+                out.extend(format!("0.5 w {:.2} {:.2} m {:.2} {:.2} l S\n", MARGIN, y, PAGE_WIDTH - MARGIN, y).into_bytes());
+            }
+            Mark::Image { index, x, y, width, height } => {
+                out.extend(format!("q {:.2} 0 0 {:.2} {:.2} {:.2} cm /Im{} Do Q\n", width, height, x, y, index).into_bytes());
+            }
+        }
+    }
+    out
+}
+
+fn stream(dictionary: &str, data: &[u8]) -> Vec<u8> {
+    let mut out = format!("<< {} /Length {} >>\nstream\n", dictionary, data.len()).into_bytes();
+    out.extend(data);
+    out.extend(b"\nendstream");
+    out
+}
+
+/// `text` as a PDF string literal in the font's encoding. `to_pdf` has
+/// already turned away text with characters outside it.
+fn pdf_string(text: &str) -> Vec<u8> {
+    let mut out = vec![b'('];
+    for byte in text.chars().filter(|c| !is_invisible(*c)).filter_map(win_ansi) {
+        match byte {
+            b'(' | b')' | b'\\' => out.extend([b'\\', byte]),
+            0x20..=0x7e => out.push(byte),
+            _ => out.extend(format!("\\{:03o}", byte).into_bytes()),
+        }
+    }
+    out.push(b')');
+    out
+}
+
+/// Control characters and zero-width formatting, which the PDF leaves out
+fn is_invisible(c: char) -> bool {
+    c.is_control() || matches!(c, '\u{200b}'..='\u{200f}' | '\u{2060}' | '\u{feff}')
+}
+
+/// The character's code in Windows-1252, which PDF calls WinAnsiEncoding.
+/// Other spaces become plain ones; `None` for anything else it lacks.
+fn win_ansi(c: char) -> Option<u8> {
+    Some(match c {
+        ' '..='~' => c as u8,
+        '\u{a0}'..='\u{ff}' => c as u32 as u8,
+        '€' => 0x80,
+        '‚' => 0x82,
+        'ƒ' => 0x83,
+        '„' => 0x84,
+        '…' => 0x85,
+        '†' => 0x86,
+        '‡' => 0x87,
+        'ˆ' => 0x88,
+        '‰' => 0x89,
+        'Š' => 0x8a,
+        '‹' => 0x8b,
+        'Œ' => 0x8c,
+        'Ž' => 0x8e,
+        '‘' => 0x91,
+        '’' => 0x92,
+        '“' => 0x93,
+        '”' => 0x94,
+        '•' => 0x95,
+        '–' => 0x96,
+        '—' => 0x97,
+        '˜' => 0x98,
+        '™' => 0x99,
+        'š' => 0x9a,
+        '›' => 0x9b,
+        'œ' => 0x9c,
+        'ž' => 0x9e,
+        'Ÿ' => 0x9f,
+        c if c.is_whitespace() => b' ',
+        _ => return None,
+    })
+}
+
+/// Width of `text` in Helvetica at `size` points
+fn text_width(text: &str, size: f32) -> f32 {
+    let units: u32 = text
+        .chars()
+        .filter(|c| !is_invisible(*c))
+        .map(|c| match win_ansi(c) {
+            Some(byte @ 0x20..=0x7e) => HELVETICA_WIDTHS[(byte - 0x20) as usize] as u32,
+            Some(0x95) => 350,
+            Some(0x85 | 0x89 | 0x97 | 0x99) => 1000,
+            _ => 556,
+        })
+        .sum();
+    units as f32 * size / 1000.0
+}
+
+/// `line` broken at spaces into lines no wider than `width`; a word too
+/// long for a line of its own is broken wherever it has to be. Tabs
+/// become four spaces. An empty line stays one empty line.
+fn wrap(line: &str, size: f32, width: f32) -> Vec<String> {
+    let line = line.replace('\t', "    ");
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for word in line.split(' ') {
+        let candidate = if current.is_empty() { word.to_string() } else { format!("{} {}", current, word) };
+        if text_width(&candidate, size) <= width {
+            current = candidate;
+            continue;
+        }
+        if !current.is_empty() {
+            lines.push(std::mem::take(&mut current));
+        }
+        for c in word.chars() {
+            current.push(c);
+            if text_width(&current, size) > width && current.chars().count() > 1 {
+                current.pop();
+                lines.push(std::mem::replace(&mut current, c.to_string()));
+            }
+        }
+    }
+    lines.push(current);
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message() -> PrintedMessage {
+        PrintedMessage {
+            subject: "Q3 (draft) report".to_string(),
+            headers: vec![
+                ("From".to_string(), "Ann <ann@example.com>".to_string()),
+                ("Date".to_string(), "Mon, 5 Oct 2026 09:30".to_string()),
+            ],
+            body: "Hi,\n\nThe figures are attached.\n".to_string(),
+            attachments: vec!["report.pdf (120 KB)".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(wrap("", TEXT_SIZE, 100.0), vec![""]);
+        assert_eq!(wrap("Short line", TEXT_SIZE, 400.0), vec!["Short line"]);
+        let lines = wrap("one two three four five six seven", TEXT_SIZE, 60.0);
+        assert!(lines.len() > 1);
+        assert!(lines.iter().all(|l| text_width(l, TEXT_SIZE) <= 60.0));
+        assert_eq!(lines.join(" "), "one two three four five six seven");
+        // A long word is broken, not lost
+        let lines = wrap(&"x".repeat(100), TEXT_SIZE, 50.0);
+        assert!(lines.len() > 1);
+        assert_eq!(lines.concat(), "x".repeat(100));
+    }
+
+    #[test]
+    fn test_pdf_string() {
+        assert_eq!(pdf_string("a (b) \\"), b"(a \\(b\\) \\\\)".to_vec());
+        assert_eq!(pdf_string("café – 5 €"), b"(caf\\351 \\226 5 \\200)".to_vec());
+        // Other spaces are plain ones, zero-width characters are dropped
+        assert_eq!(pdf_string("a\u{2003}b\u{200b}c"), b"(a bc)".to_vec());
+    }
+
+    #[test]
+    fn test_to_pdf_refuses_other_scripts() {
+        for text in ["Привет", "Γειά σου", "日本語"] {
+            let first = text.chars().next().unwrap();
+            let body = PrintedMessage { body: format!("Hi,\n{}\n", text), ..message() };
+            assert_eq!(to_pdf(&body, &[]), Err(UnsupportedText(first)));
+            let subject = PrintedMessage { subject: text.to_string(), ..message() };
+            assert_eq!(to_pdf(&subject, &[]), Err(UnsupportedText(first)));
+        }
+        let image = ImagePreview { label: "図.png".to_string(), width: 1, height: 1, rgba: vec![0, 0, 0, 255] };
+        assert_eq!(to_pdf(&message(), &[image]), Err(UnsupportedText('図')));
+        // Western European text, typographic punctuation included, is fine
+        let western = PrintedMessage { body: "Grüße – „Café“ für 5 €\r\n".to_string(), ..message() };
+        assert!(to_pdf(&western, &[]).is_ok());
+    }
+
+    #[test]
+    fn test_to_pdf() {
+        let pdf = to_pdf(&message(), &[]).unwrap();
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(pdf.starts_with(b"%PDF-1.4\n"));
+        assert!(text.ends_with("%%EOF\n"));
+        assert!(text.contains("(Q3 \\(draft\\) report)"));
+        assert!(text.contains("(From: Ann <ann@example.com>)"));
+        assert!(text.contains("(\\225 report.pdf \\(120 KB\\))"));
+        assert!(text.contains("(Page 1 of 1)"));
+
+        // Every object is where the cross-reference table says
+        let find = |needle: &[u8]| pdf.windows(needle.len()).rposition(|w| w == needle).unwrap();
+        let start = find(b"startxref\n") + "startxref\n".len();
+        let xref: usize = String::from_utf8_lossy(&pdf[start..]).lines().next().unwrap().parse().unwrap();
+        let table = String::from_utf8_lossy(&pdf[xref..]).into_owned();
+        assert!(table.starts_with("xref\n"));
+        let entries: Vec<&str> = table.lines().skip(3).take_while(|l| l.ends_with(" n ")).collect();
+        assert_eq!(entries.len(), 6);
+        for (i, entry) in entries.iter().enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(format!("{} 0 obj\n", i + 1).as_bytes()));
+        }
+
+        // A long body runs onto more pages, each numbered
+        let long = PrintedMessage { body: "A line of text\n".repeat(200), ..message() };
+        let text = String::from_utf8_lossy(&to_pdf(&long, &[]).unwrap()).into_owned();
+        let count: usize = text.split("/Count ").nth(1).unwrap().split(' ').next().unwrap().parse().unwrap();
+        assert!(count > 1);
+        assert!(text.contains(&format!("(Page {} of {})", count, count)));
+        assert!(!text.contains(&format!("(Page {} of", count + 1)));
+    }
+
+    #[test]
+    fn test_to_pdf_with_image() {
+        let image = ImagePreview { label: "chart.png".to_string(), width: 2, height: 1, rgba: vec![255, 0, 0, 255, 0, 0, 0, 0] };
+        let pdf = to_pdf(&message(), std::slice::from_ref(&image)).unwrap();
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.contains("/Subtype /Image /Width 2 /Height 1"));
+        assert!(text.contains("/Im0 5 0 R"));
+        assert!(text.contains("/Im0 Do"));
+        assert!(text.contains("(chart.png)"));
+        // The transparent pixel is white
+        let pixels = [255u8, 0, 0, 255, 255, 255];
+        assert!(pdf.windows(pixels.len()).any(|w| w == pixels));
+
+        let html = to_html(&message(), &[image]);
+        assert!(html.contains("<img src=\"data:image/png;base64,"));
+        assert!(html.contains("<figcaption>chart.png</figcaption>"));
+    }
+
+    #[test]
+    fn test_print_page_is_private_and_removed() {
+        let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos();
+        let dir = std::env::temp_dir().join(format!("wixen_print_{}", nanos));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let first = write_print_page(&dir, &to_html(&message(), &[])).unwrap();
+        assert!(std::fs::read_to_string(&first).unwrap().contains("Q3 (draft) report"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&first).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        remove_print_page(&first);
+        assert!(!first.exists());
+
+        // Pages left behind go on quit
+        let second = write_print_page(&dir, "<html></html>").unwrap();
+        assert!(remove_print_pages() >= 1);
+        assert!(!second.exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_to_html() {
+        let html = to_html(&message(), &[]);
+        assert!(html.contains("<body onload=\"window.print()\">"));
+        assert!(html.contains("<h1>Q3 (draft) report</h1>"));
+        assert!(html.contains("<tr><th>From</th><td>Ann &lt;ann@example.com&gt;</td></tr>"));
+        assert!(html.contains("<pre>Hi,\n\nThe figures are attached.\n</pre>"));
+        assert!(html.contains("<li>report.pdf (120 KB)</li>"));
+    }
+}
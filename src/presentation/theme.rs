@@ -0,0 +1,187 @@
+//! Colour palettes for the configured theme
+//!
+//! Maps `data::config::Theme` to concrete widget colours. `Theme::System`
+//! has no palette so native controls keep the operating-system colours
+//! (including Windows high-contrast mode).
+
+use crate::data::config::Theme;
+
+/// An sRGB colour
+pub type Rgb = (u8, u8, u8);
+
+/// Minimum WCAG contrast for tag colours on the normal themes (AA).
+const MIN_TAG_CONTRAST: f64 = 4.5;
+/// Minimum WCAG contrast for tag colours on the high-contrast theme (AAA).
+const MIN_TAG_CONTRAST_HIGH: f64 = 7.0;
+/// Background assumed for `Theme::System` when judging contrast.
+const SYSTEM_BACKGROUND: Rgb = (255, 255, 255);
+/// Text colour for warnings, before contrast adjustment.
+const WARNING: &str = "#C62828";
+
+/// Widget colours for a theme
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palette {
+    pub background: Rgb,
+    pub foreground: Rgb,
+    /// Background for side panes such as the folder tree
+    pub pane_background: Rgb,
+    pub selection_background: Rgb,
+    pub selection_foreground: Rgb,
+    min_tag_contrast: f64,
+}
+
+impl Palette {
+    /// Palette for a theme, or `None` to keep native colours.
+    pub fn for_theme(theme: Theme) -> Option<Self> {
+        match theme {
+            Theme::System => None,
+            Theme::Light => Some(Self {
+                background: (255, 255, 255),
+                foreground: (33, 33, 33),
+                pane_background: (245, 245, 250),
+                selection_background: (30, 136, 229),
+                selection_foreground: (255, 255, 255),
+                min_tag_contrast: MIN_TAG_CONTRAST,
+            }),
+            Theme::Dark => Some(Self {
+                background: (30, 30, 30),
+                foreground: (230, 230, 230),
+                pane_background: (40, 40, 46),
+                selection_background: (66, 133, 244),
+                selection_foreground: (255, 255, 255),
+                min_tag_contrast: MIN_TAG_CONTRAST,
+            }),
+            Theme::HighContrast => Some(Self {
+                background: (0, 0, 0),
+                foreground: (255, 255, 255),
+                pane_background: (0, 0, 0),
+                selection_background: (255, 255, 0),
+                selection_foreground: (0, 0, 0),
+                min_tag_contrast: MIN_TAG_CONTRAST_HIGH,
+            }),
+        }
+    }
+
+    /// Colour to draw a tag with so it stays readable on this background.
+    ///
+    /// The tag's own colour is kept when it has enough contrast; otherwise it
+    /// is lightened or darkened toward the foreground until it does.
+    pub fn tag_color(&self, hex: &str) -> Rgb {
+        let Some(mut color) = parse_hex(hex) else {
+            return self.foreground;
+        };
+        for _ in 0..10 {
+            if contrast_ratio(color, self.background) >= self.min_tag_contrast {
+                return color;
+            }
+            color = mix(color, self.foreground, 0.25);
+        }
+        self.foreground
+    }
+}
+
+/// Colour to draw an account accent with on a theme.
+///
+/// Palette themes adjust it for contrast like tag colours; the system theme
+/// keeps it as entered. `None` if `hex` isn't a valid colour.
+pub fn account_accent(theme: Theme, hex: &str) -> Option<Rgb> {
+    let color = parse_hex(hex)?;
+    Some(match Palette::for_theme(theme) {
+        Some(palette) => palette.tag_color(hex),
+        None => color,
+    })
+}
+
+/// Colour for warnings such as overdue follow-ups and a nearly full
+/// mailbox, readable on the theme's background.
+pub fn warning_colour(theme: Theme) -> Rgb {
+    match Palette::for_theme(theme) {
+        Some(palette) => palette.tag_color(WARNING),
+        None => parse_hex(WARNING).expect("valid colour"),
+    }
+}
+
+/// Contrast of an unadjusted colour against the theme's background.
+pub fn contrast_on_theme(theme: Theme, hex: &str) -> Option<f64> {
+    let background = Palette::for_theme(theme).map_or(SYSTEM_BACKGROUND, |p| p.background);
+    Some(contrast_ratio(parse_hex(hex)?, background))
+}
+
+/// Normalise a user-entered colour to `#RRGGBB`, or `None` if invalid.
+pub fn normalize_hex(hex: &str) -> Option<String> {
+    let (r, g, b) = parse_hex(hex)?;
+    Some(format!("#{:02X}{:02X}{:02X}", r, g, b))
+}
+
+/// Parse `#RRGGBB` (leading `#` optional).
+pub fn parse_hex(hex: &str) -> Option<Rgb> {
+    let hex = hex.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// WCAG 2 contrast ratio between two colours (1.0 – 21.0).
+pub fn contrast_ratio(a: Rgb, b: Rgb) -> f64 {
+    let (la, lb) = (relative_luminance(a), relative_luminance(b));
+    let (hi, lo) = if la > lb { (la, lb) } else { (lb, la) };
+    (hi + 0.05) / (lo + 0.05)
+}
+
+fn relative_luminance((r, g, b): Rgb) -> f64 {
+    let linear = |c: u8| {
+        let c = c as f64 / 255.0;
+        if c <= 0.03928 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+    };
+    0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+}
+
+fn mix(from: Rgb, to: Rgb, amount: f64) -> Rgb {
+    let lerp = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * amount).round() as u8;
+    (lerp(from.0, to.0), lerp(from.1, to.1), lerp(from.2, to.2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_system_theme_has_no_palette() {
+        assert!(Palette::for_theme(Theme::System).is_none());
+        let hc = Palette::for_theme(Theme::HighContrast).unwrap();
+        assert!(contrast_ratio(hc.foreground, hc.background) > 20.9);
+        assert!(contrast_ratio(hc.selection_foreground, hc.selection_background) >= 7.0);
+    }
+
+    #[test]
+    fn test_tag_colors_stay_readable() {
+        let hc = Palette::for_theme(Theme::HighContrast).unwrap();
+        let tags = ["#E53935", "#1E88E5", "#8E24AA", "#757575", "#FDD835"];
+        for tag in tags {
+            assert!(contrast_ratio(hc.tag_color(tag), hc.background) >= 7.0, "{}", tag);
+        }
+        // Already-readable colours are left alone
+        let light = Palette::for_theme(Theme::Light).unwrap();
+        assert_eq!(light.tag_color("#8E24AA"), (0x8E, 0x24, 0xAA));
+        assert_eq!(light.tag_color("not-a-colour"), light.foreground);
+    }
+
+    #[test]
+    fn test_account_accent() {
+        assert_eq!(normalize_hex(" 4a90e2 ").as_deref(), Some("#4A90E2"));
+        assert_eq!(normalize_hex("#4A90E"), None);
+        assert_eq!(normalize_hex("#GGGGGG"), None);
+        assert_eq!(normalize_hex("aé123"), None);
+
+        assert_eq!(account_accent(Theme::System, "#4A90E2"), Some((0x4A, 0x90, 0xE2)));
+        let dark = account_accent(Theme::Dark, "#1A1A80").unwrap();
+        assert!(contrast_ratio(dark, (30, 30, 30)) >= 4.5);
+        assert!(account_accent(Theme::Light, "blue").is_none());
+        assert!(contrast_on_theme(Theme::HighContrast, "#FFFFFF").unwrap() > 20.0);
+
+        assert_eq!(warning_colour(Theme::System), (0xC6, 0x28, 0x28));
+        assert!(contrast_ratio(warning_colour(Theme::HighContrast), (0, 0, 0)) >= 7.0);
+    }
+}
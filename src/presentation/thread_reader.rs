@@ -0,0 +1,173 @@
+//! Conversation reader
+//!
+//! Stacks every message of a thread in the preview pane, oldest first,
+//! with quoted history collapsed, and tracks which message is current for
+//! next/previous-in-thread navigation.
+
+/// One message of a conversation, with its body as plain text
+#[derive(Debug, Clone)]
+pub struct ThreadEntry {
+    pub from: String,
+    pub date: String,
+    pub subject: String,
+    /// `None` when the body hasn't been downloaded
+    pub body: Option<String>,
+}
+
+/// A conversation shown in the preview pane
+#[derive(Debug, Clone)]
+pub struct ThreadReader {
+    entries: Vec<ThreadEntry>,
+    current: usize,
+    expand_quotes: bool,
+}
+
+impl ThreadReader {
+    /// Create a reader positioned on `current` (clamped to the thread).
+    pub fn new(entries: Vec<ThreadEntry>, current: usize) -> Self {
+        let current = current.min(entries.len().saturating_sub(1));
+        Self { entries, current, expand_quotes: false }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Move to the next message. Returns `false` at the end of the thread.
+    pub fn move_next(&mut self) -> bool {
+        if self.current + 1 < self.entries.len() {
+            self.current += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Move to the previous message. Returns `false` at the start.
+    pub fn move_previous(&mut self) -> bool {
+        if self.current > 0 {
+            self.current -= 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Show or hide quoted history; returns the new setting.
+    pub fn toggle_quotes(&mut self) -> bool {
+        self.expand_quotes = !self.expand_quotes;
+        self.expand_quotes
+    }
+
+    /// Render the whole conversation, returning the text and the character
+    /// offset of the current message's heading.
+    pub fn render(&self) -> (String, usize) {
+        let total = self.entries.len();
+        let mut text = String::new();
+        let mut offset = 0;
+        for (i, entry) in self.entries.iter().enumerate() {
+            if i == self.current {
+                offset = text.chars().count();
+            }
+            text.push_str(&format!(
+                "Message {} of {}: from {}, {}\n{}\n\n",
+                i + 1,
+                total,
+                entry.from,
+                entry.date,
+                entry.subject
+            ));
+            match &entry.body {
+                Some(body) if self.expand_quotes => text.push_str(body.trim_end()),
+                Some(body) => text.push_str(&collapse_quotes(body)),
+                None => text.push_str("(This message has not been downloaded.)"),
+            }
+            text.push_str("\n\n");
+            if i + 1 < total {
+                text.push_str("────────────────────\n\n");
+            }
+        }
+        (text, offset)
+    }
+
+    /// Screen reader summary of the current message.
+    pub fn announcement(&self) -> String {
+        match self.entries.get(self.current) {
+            Some(entry) => format!(
+                "Message {} of {} in conversation, from {}, {}",
+                self.current + 1,
+                self.entries.len(),
+                entry.from,
+                entry.date
+            ),
+            None => "Conversation is empty".to_string(),
+        }
+    }
+}
+
+/// Replace each run of `>`-quoted lines with a one-line placeholder.
+fn collapse_quotes(body: &str) -> String {
+    let mut out: Vec<String> = Vec::new();
+    let mut quoted = 0;
+    let flush = |out: &mut Vec<String>, quoted: &mut usize| {
+        if *quoted > 0 {
+            let noun = if *quoted == 1 { "line" } else { "lines" };
+            out.push(format!("[{} quoted {} hidden]", quoted, noun));
+            *quoted = 0;
+        }
+    };
+    for line in body.trim_end().lines() {
+        if line.trim_start().starts_with('>') {
+            quoted += 1;
+        } else {
+            flush(&mut out, &mut quoted);
+            out.push(line.to_string());
+        }
+    }
+    flush(&mut out, &mut quoted);
+    out.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(from: &str, body: Option<&str>) -> ThreadEntry {
+        ThreadEntry {
+            from: from.to_string(),
+            date: "Mon, 1 Jan 2024".to_string(),
+            subject: "Lunch".to_string(),
+            body: body.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_render_collapses_quotes_and_tracks_current() {
+        let mut reader = ThreadReader::new(
+            vec![
+                entry("Alice", Some("Noon?")),
+                entry("Bob", Some("Sure.\nOn Mon, Alice wrote:\n> Noon?\n>\n")),
+                entry("Carol", None),
+            ],
+            1,
+        );
+        let (text, offset) = reader.render();
+        assert!(text.contains("Sure.\nOn Mon, Alice wrote:\n[2 quoted lines hidden]"));
+        assert!(text.contains("(This message has not been downloaded.)"));
+        let heading: String = text.chars().skip(offset).take(14).collect();
+        assert_eq!(heading, "Message 2 of 3");
+
+        assert!(reader.toggle_quotes());
+        assert!(reader.render().0.contains("> Noon?"));
+
+        assert!(reader.move_next());
+        assert!(!reader.move_next());
+        assert_eq!(reader.announcement(), "Message 3 of 3 in conversation, from Carol, Mon, 1 Jan 2024");
+        assert!(reader.move_previous() && reader.move_previous());
+        assert!(!reader.move_previous());
+    }
+}
@@ -3,21 +3,192 @@
 //! These types are framework-agnostic and define the data contracts between
 //! the async backend (application/service layers) and the UI presentation layer.
 
+use crate::application::calendar::{CalendarInvite, InviteResponse};
+use crate::application::follow_up::FollowUp;
+use crate::application::messages::{DeliveryFailure, MailingList, SenderAuthentication};
+use crate::application::notifications::MailPreview;
+use crate::common::types::MessagePriority;
+use crate::data::config::Theme;
+use crate::data::message_cache::{
+    thread_key, CachedAttachment, CachedFolder, CachedMessage, CachedQuota, FollowUpMessage, InboxMessage,
+};
+use crate::service::attachments::ImagePreview;
+use crate::service::protocols::smtp::ReadReceipt;
+
+/// Folder path of the virtual All Inboxes folder. Never a real IMAP path.
+pub const ALL_INBOXES_PATH: &str = "::all-inboxes";
+/// Folder path of the virtual Follow-up folder. Never a real IMAP path.
+pub const FOLLOW_UP_PATH: &str = "::follow-up";
+
+/// Folder entry for the folder tree, with cached message counts
+#[derive(Clone, Debug, PartialEq)]
+pub struct FolderItem {
+    pub path: String,
+    pub name: String,
+    pub unread: u32,
+    pub total: u32,
+}
+
+impl FolderItem {
+    /// Tree label: `name (unread/total)`
+    pub fn label(&self) -> String {
+        format!("{} ({}/{})", self.name, self.unread, self.total)
+    }
+}
+
+impl FolderItem {
+    /// The virtual All Inboxes folder with combined counts
+    pub fn all_inboxes(unread: i64, total: i64) -> Self {
+        Self {
+            path: ALL_INBOXES_PATH.to_string(),
+            name: "All Inboxes".to_string(),
+            unread: unread.max(0) as u32,
+            total: total.max(0) as u32,
+        }
+    }
+
+    /// The virtual Follow-up folder; its "unread" count is the overdue one
+    pub fn follow_up(flagged: i64, overdue: i64) -> Self {
+        Self {
+            path: FOLLOW_UP_PATH.to_string(),
+            name: "Follow-up".to_string(),
+            unread: overdue.max(0) as u32,
+            total: flagged.max(0) as u32,
+        }
+    }
+}
+
+impl From<&CachedFolder> for FolderItem {
+    fn from(f: &CachedFolder) -> Self {
+        Self {
+            path: f.path.clone(),
+            name: f.name.clone(),
+            unread: f.unread_count.max(0) as u32,
+            total: f.total_count.max(0) as u32,
+        }
+    }
+}
+
 /// Message item for display in the message list
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct MessageItem {
     pub uid: u32,
     pub message_id: i64,
     pub subject: String,
     pub from: String,
+    /// Original To and Cc header values, used for reply-all
+    pub to: String,
+    pub cc: String,
     pub date: String,
     pub read: bool,
     pub starred: bool,
+    pub priority: MessagePriority,
     pub has_attachments: bool,
     pub attachments: Vec<AttachmentItem>,
     pub thread_depth: usize,
     pub is_thread_parent: bool,
     pub thread_id: Option<String>,
+    /// Originating account, set in the All Inboxes view
+    pub account_id: Option<String>,
+    /// Originating account's accent colour (`#RRGGBB`), set with `account_id`
+    pub account_color: Option<String>,
+    /// Follow-up flag, separate from `starred`
+    pub follow_up: Option<FollowUp>,
+    /// Folder the message lives in, set in the Follow-up view
+    pub folder: Option<String>,
+    /// Mailing list the message came from, for grouping by list
+    pub list_id: Option<String>,
+}
+
+impl MessageItem {
+    /// Status column text: "NEW" for unread, "HIGH PRIORITY", a paperclip
+    /// for attachments, then any follow-up state
+    pub fn status(&self, now: chrono::DateTime<chrono::Utc>) -> String {
+        let unread = (!self.read).then(|| "NEW".to_string());
+        let priority = self.priority.is_high().then(|| "HIGH PRIORITY".to_string());
+        let starred = self.starred.then(|| "STARRED".to_string());
+        let attached = self.has_attachments.then(|| "📎".to_string());
+        let follow_up = self.follow_up.map(|f| f.label(now));
+        unread.into_iter().chain(priority).chain(starred).chain(attached).chain(follow_up).collect::<Vec<_>>().join(", ")
+    }
+
+    /// The Status column in words a screen reader speaks before the row's
+    /// fields, e.g. `["unread", "starred", "has attachments"]`
+    pub fn spoken_states(&self, now: chrono::DateTime<chrono::Utc>) -> Vec<String> {
+        let flags = [
+            (!self.read, "unread"),
+            (self.priority.is_high(), "high priority"),
+            (self.starred, "starred"),
+            (self.has_attachments, "has attachments"),
+        ];
+        flags
+            .iter()
+            .filter(|(on, _)| *on)
+            .map(|(_, state)| state.to_string())
+            .chain(self.follow_up.map(|f| f.label(now)))
+            .collect()
+    }
+
+    /// The message with the attachments listed for it in the cache
+    pub fn with_attachments(self, attachments: &[CachedAttachment]) -> Self {
+        Self {
+            has_attachments: !attachments.is_empty(),
+            attachments: attachments.iter().map(AttachmentItem::from).collect(),
+            ..self
+        }
+    }
+
+    /// Whether the message's follow-up due time has passed
+    pub fn is_overdue(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        self.follow_up.is_some_and(|f| f.is_overdue(now))
+    }
+}
+
+impl From<&CachedMessage> for MessageItem {
+    fn from(m: &CachedMessage) -> Self {
+        Self {
+            uid: m.uid,
+            message_id: m.id,
+            subject: m.subject.clone(),
+            from: m.from_addr.clone(),
+            to: m.to_addr.clone(),
+            cc: m.cc.clone().unwrap_or_default(),
+            date: m.date.clone(),
+            read: m.read,
+            starred: m.starred,
+            priority: m.priority,
+            has_attachments: false,
+            attachments: Vec::new(),
+            thread_depth: 0,
+            is_thread_parent: false,
+            thread_id: Some(thread_key(&m.subject)),
+            account_id: None,
+            account_color: None,
+            follow_up: None,
+            folder: None,
+            list_id: None,
+        }
+    }
+}
+
+impl From<&InboxMessage> for MessageItem {
+    fn from(m: &InboxMessage) -> Self {
+        Self {
+            account_id: Some(m.account_id.clone()),
+            account_color: Some(m.account_color.clone()),
+            ..Self::from(&m.message)
+        }
+    }
+}
+
+impl From<&FollowUpMessage> for MessageItem {
+    fn from(m: &FollowUpMessage) -> Self {
+        Self {
+            follow_up: Some(FollowUp::from_stored(m.due.as_deref())),
+            folder: Some(m.folder_path.clone()),
+            ..Self::from(&m.message)
+        }
+    }
 }
 
 /// Attachment item for display
@@ -28,6 +199,12 @@ pub struct AttachmentItem {
     pub size: usize,
 }
 
+impl From<&CachedAttachment> for AttachmentItem {
+    fn from(a: &CachedAttachment) -> Self {
+        Self { filename: a.filename.clone(), mime_type: a.mime_type.clone(), size: a.size.max(0) as usize }
+    }
+}
+
 /// Mail list sort options
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum MailSortOption {
@@ -40,6 +217,34 @@ pub enum MailSortOption {
     UnreadFirst,
 }
 
+impl MailSortOption {
+    /// Parse the `AppConfig::default_sort_order` key, falling back to newest first.
+    pub fn from_config_key(key: &str) -> Self {
+        match key {
+            "date_oldest" => MailSortOption::DateOldestFirst,
+            "sender_az" => MailSortOption::SenderAZ,
+            "sender_za" => MailSortOption::SenderZA,
+            "subject_az" => MailSortOption::SubjectAZ,
+            "subject_za" => MailSortOption::SubjectZA,
+            "unread_first" => MailSortOption::UnreadFirst,
+            _ => MailSortOption::DateNewestFirst,
+        }
+    }
+
+    /// The `AppConfig::default_sort_order` key for this order
+    pub fn as_config_key(&self) -> &'static str {
+        match self {
+            MailSortOption::DateNewestFirst => "date_newest",
+            MailSortOption::DateOldestFirst => "date_oldest",
+            MailSortOption::SenderAZ => "sender_az",
+            MailSortOption::SenderZA => "sender_za",
+            MailSortOption::SubjectAZ => "subject_az",
+            MailSortOption::SubjectZA => "subject_za",
+            MailSortOption::UnreadFirst => "unread_first",
+        }
+    }
+}
+
 /// Connection status
 #[derive(Clone, Debug, PartialEq)]
 pub enum ConnectionStatus {
@@ -72,14 +277,90 @@ pub struct CompositionData {
     pub bcc: String,
     pub subject: String,
     pub body: String,
+    pub request_receipt: bool,
+    pub priority: MessagePriority,
+}
+
+/// Result of checking a message signature
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureState {
+    Valid,
+    /// The signature matches, but the signer's certificate isn't trusted
+    Untrusted,
+    /// The message was altered after signing, or the signature is unreadable
+    Invalid,
+}
+
+/// Signature badge shown at the top of an opened message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureBadge {
+    pub state: SignatureState,
+    pub signer: Option<String>,
+}
+
+impl SignatureBadge {
+    /// One-line badge text, also used for the screen reader announcement.
+    pub fn text(&self) -> String {
+        let signer = self.signer.as_deref().unwrap_or("an unknown signer");
+        match self.state {
+            SignatureState::Valid => format!("✔ Signature valid: signed by {}", signer),
+            SignatureState::Untrusted => format!(
+                "⚠ Signature untrusted: signed by {}, but the certificate is not from a trusted authority",
+                signer
+            ),
+            SignatureState::Invalid => {
+                "✖ Signature INVALID: this message may have been altered after it was signed".to_string()
+            }
+        }
+    }
+}
+
+/// Sender authentication (SPF/DKIM/DMARC) badge shown at the top of an
+/// opened message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrustBadge {
+    pub verified: bool,
+    /// One-line badge text, also used for the screen reader announcement
+    pub text: String,
+}
+
+impl From<&SenderAuthentication> for TrustBadge {
+    fn from(auth: &SenderAuthentication) -> Self {
+        let text = match (auth.warning(), &auth.results) {
+            (Some(warning), _) => format!("⚠ {}", warning),
+            (None, Some(results)) => format!("✔ Sender verified by {} ({})", results.authserv_id, results.summary()),
+            (None, None) => "✔ Sender verified".to_string(),
+        };
+        Self { verified: auth.verified(), text }
+    }
+}
+
+#[cfg(feature = "smime")]
+impl From<&crate::service::smime::SmimeVerification> for SignatureBadge {
+    fn from(v: &crate::service::smime::SmimeVerification) -> Self {
+        use crate::service::smime::SmimeStatus;
+        let state = match v.status {
+            SmimeStatus::Valid => SignatureState::Valid,
+            SmimeStatus::Untrusted => SignatureState::Untrusted,
+            SmimeStatus::Invalid => SignatureState::Invalid,
+        };
+        Self { state, signer: v.signer.clone() }
+    }
 }
 
+pub use crate::service::protocols::certificate_pin::CertificateChange;
+
 /// UI update messages sent from async tasks to the UI thread
 #[derive(Clone, Debug)]
 pub enum UIUpdate {
-    FoldersLoaded(Vec<String>),
+    FoldersLoaded(Vec<FolderItem>),
     MessagesLoaded(Vec<MessageItem>),
     MessageBodyLoaded(String),
+    /// Only the text of the opened message (cache id) was downloaded; the
+    /// whole message is `full_size` bytes
+    TextOnlyLoaded { message_id: i64, full_size: u64 },
+    /// The message with this cache id was marked read after opening
+    MessageMarkedRead(i64),
     ConnectionStatusChanged(ConnectionStatus),
     ErrorOccurred(String),
     StatusUpdated(String),
@@ -95,6 +376,128 @@ pub enum UIUpdate {
     OutboxQueueCount(usize),
     /// Queue flush completed (sent_count, failed_count)
     OutboxFlushComplete(usize, usize),
+    /// Number of messages currently selected in the message list
+    SelectionChanged(usize),
+    /// Keyboard focus/selection moved to the message at this list index
+    MessageFocused(usize),
+    /// Keyboard focus moved to a group header in the message list (its
+    /// description)
+    GroupFocused(String),
+    /// A different folder was selected in the folder tree, with when it
+    /// was last fetched from the server (RFC 3339), if ever
+    FolderChanged { name: String, last_sync: Option<String> },
+    /// A message was opened in the preview pane (subject)
+    MessageOpened(String),
+    /// A mail check found this many new unread messages for the account,
+    /// `from_lists` more from mailing lists, the newest of them previewed
+    NewMailArrived { account_id: String, count: usize, from_lists: usize, preview: Option<MailPreview> },
+    /// This many snoozed messages reached their wake time and are back
+    SnoozedMessagesWoke(usize),
+    /// A tag's shortcut key applied it to this many messages
+    TagApplied { tag: String, count: usize },
+    /// The colour theme setting changed
+    ThemeChanged(Theme),
+    /// The base font size (zoom) changed
+    FontSizeChanged(u32),
+    /// Offline download progress for a folder (messages done, total)
+    OfflineSyncProgress {
+        folder: String,
+        done: usize,
+        total: usize,
+    },
+    /// Sync Now fetched a folder's messages from the server
+    FolderSynced {
+        folder: String,
+        messages: usize,
+        last_sync: Option<String>,
+    },
+    /// A folder finished downloading for offline use
+    OfflineSyncComplete {
+        folder: String,
+        messages: usize,
+        bodies: usize,
+    },
+    /// The conversation reader was (re)rendered; `position` is the character
+    /// offset of the current message
+    ThreadRendered {
+        text: String,
+        position: usize,
+        announcement: String,
+    },
+    /// The signature of the opened message (cache id) was checked
+    SignatureChecked {
+        message_id: i64,
+        badge: SignatureBadge,
+    },
+    /// The sender authentication of the opened message (cache id) was read
+    AuthenticationChecked {
+        message_id: i64,
+        badge: TrustBadge,
+    },
+    /// The priority of the opened message (cache id) was read from its headers
+    PriorityChecked {
+        message_id: i64,
+        priority: MessagePriority,
+    },
+    /// The opened message (cache id) has attachments with these names
+    AttachmentsFound {
+        message_id: i64,
+        names: Vec<String>,
+    },
+    /// Attachments of the opened message (cache id) that could be harmful,
+    /// one warning each
+    AttachmentWarnings {
+        message_id: i64,
+        warnings: Vec<String>,
+    },
+    /// The opened message (cache id) carries a calendar event, with the
+    /// answer the account has given to it so far
+    InviteFound {
+        message_id: i64,
+        account_id: String,
+        invite: Box<CalendarInvite>,
+        response: Option<InviteResponse>,
+    },
+    /// The opened message (cache id) came from a mailing list that can be
+    /// unsubscribed from
+    MailingListFound {
+        message_id: i64,
+        account_id: String,
+        list: MailingList,
+    },
+    /// Previews of the opened message's (cache id) image attachments;
+    /// empty when it has none
+    ImagePreviews {
+        message_id: i64,
+        previews: Vec<ImagePreview>,
+    },
+    /// The spam filter rates the opened message (cache id) likely spam,
+    /// with this score from 0 to 1
+    SpamScored {
+        message_id: i64,
+        score: f64,
+    },
+    /// The opened message (cache id) is a bounce; `original` is the cached
+    /// copy of the undelivered message, if found
+    DeliveryFailed {
+        message_id: i64,
+        failure: DeliveryFailure,
+        original: Option<Box<CachedMessage>>,
+    },
+    /// An account's mailbox quota was fetched; `None` if the server has no
+    /// quota support
+    QuotaChecked {
+        account_id: String,
+        quota: Option<CachedQuota>,
+    },
+    /// A pinned server certificate changed and the connection was refused
+    CertificateChanged(CertificateChange),
+    /// An opened message asks for a read receipt and the policy is to ask
+    ReadReceiptRequested {
+        message_id: i64,
+        account_id: String,
+        receipt: ReadReceipt,
+    },
 }
 
 impl std::fmt::Display for ConnectionStatus {
@@ -7,7 +7,9 @@
 //! user adds such an account (press OK), the browser opens immediately
 //! for authorization with no extra steps or checkboxes.
 
-use crate::data::account::{requires_oauth, Account};
+use crate::data::account::{is_bare_address, provider_saves_sent, requires_oauth, Account, DeleteMode};
+use crate::data::config::Theme;
+use crate::presentation::theme::{account_accent, contrast_on_theme, normalize_hex};
 use crate::presentation::wx_managers::get_selected;
 use crate::service::oauth::{AuthManager, OAuthService};
 use crate::service::oauth_credentials;
@@ -29,6 +31,7 @@ pub fn show_account_manager_dialog(
     parent: &Frame,
     accounts: &[Account],
     active_account_id: Option<&str>,
+    theme: Theme,
 ) -> AccountManagerAction {
     let dlg = Dialog::builder(parent, "Account Manager")
         .with_size(650, 450)
@@ -68,7 +71,7 @@ pub fn show_account_manager_dialog(
     let mut working = accounts.to_vec();
     let mut active_id: Option<String> = active_account_id.map(|s| s.to_string());
     let mut changed = false;
-    populate(&list, &working, active_id.as_deref());
+    populate(&list, &working, active_id.as_deref(), theme);
 
     add.on_click({ let d = dlg; move |_| { d.end_modal(ID_ADD); } });
     edit.on_click({ let d = dlg; move |_| { d.end_modal(ID_EDIT); } });
@@ -80,7 +83,7 @@ pub fn show_account_manager_dialog(
     loop {
         match dlg.show_modal() {
             r if r == ID_ADD => {
-                if let Some(mut a) = show_edit(&dlg, None) {
+                if let Some(mut a) = show_edit(&dlg, None, theme) {
                     if working.is_empty() { active_id = Some(a.id.clone()); }
 
                     // OAuth is automatic — if this is a Gmail/Microsoft account,
@@ -108,12 +111,12 @@ pub fn show_account_manager_dialog(
 
                     working.push(a);
                     changed = true;
-                    populate(&list, &working, active_id.as_deref());
+                    populate(&list, &working, active_id.as_deref(), theme);
                 }
             }
             r if r == ID_EDIT => {
                 if let Some(idx) = get_selected(&list) {
-                    if let Some(mut u) = show_edit(&dlg, Some(&working[idx])) {
+                    if let Some(mut u) = show_edit(&dlg, Some(&working[idx]), theme) {
                         // Run OAuth if needed and no tokens yet
                         if u.use_oauth && u.oauth_access_token.is_empty() {
                             match run_oauth_flow(&mut u) {
@@ -132,7 +135,7 @@ pub fn show_account_manager_dialog(
                         }
                         working[idx] = u;
                         changed = true;
-                        populate(&list, &working, active_id.as_deref());
+                        populate(&list, &working, active_id.as_deref(), theme);
                     }
                 } else { status.set_label("Select an account to edit"); }
             }
@@ -154,7 +157,7 @@ pub fn show_account_manager_dialog(
                     if active_id.as_deref() == Some(&rid) {
                         active_id = working.first().map(|a| a.id.clone());
                     }
-                    populate(&list, &working, active_id.as_deref());
+                    populate(&list, &working, active_id.as_deref(), theme);
                     status.set_label(&format!("Deleted: {}", name));
                 } else { status.set_label("Select an account to delete"); }
             }
@@ -162,7 +165,7 @@ pub fn show_account_manager_dialog(
                 if let Some(idx) = get_selected(&list) {
                     active_id = Some(working[idx].id.clone());
                     changed = true;
-                    populate(&list, &working, active_id.as_deref());
+                    populate(&list, &working, active_id.as_deref(), theme);
                     status.set_label(&format!("Active: {}", working[idx].name));
                 } else { status.set_label("Select an account"); }
             }
@@ -180,10 +183,10 @@ pub fn show_account_manager_dialog(
 
 // ── Account Edit Sub-Dialog ─────────────────────────────────────────────────
 
-fn show_edit(parent: &Dialog, existing: Option<&Account>) -> Option<Account> {
+fn show_edit(parent: &Dialog, existing: Option<&Account>, theme: Theme) -> Option<Account> {
     let title = if existing.is_some() { "Edit Account" } else { "Add Account" };
     let dlg = Dialog::builder(parent, title)
-        .with_size(480, 480)
+        .with_size(480, 540)
         .with_style(DialogStyle::DefaultDialogStyle | DialogStyle::ResizeBorder)
         .build();
 
@@ -236,6 +239,15 @@ fn show_edit(parent: &Dialog, existing: Option<&Account>) -> Option<Account> {
     let smtp_f = tf("&SMTP Server:", "");
     let smtp_port_f = tf("SM&TP Port:", "465");
     let smtp_tls = cb("Use TL&S", true);
+    // The envelope sender (MAIL FROM); readers still see the email address
+    let bounce_f = tf("Bounce &address (optional):", "");
+    let bounce_hint = {
+        let l = StaticText::builder(&dlg).with_label("").build();
+        let h = StaticText::builder(&dlg).with_label("Where undeliverable mail is returned; empty uses the email address").build();
+        fields.add(&l, 0, SizerFlag::All, 4);
+        fields.add(&h, 0, SizerFlag::Expand | SizerFlag::All, 4);
+        h
+    };
 
     section("── Authentication ──");
     let user_f = tf("&Username:", "");
@@ -250,6 +262,20 @@ fn show_edit(parent: &Dialog, existing: Option<&Account>) -> Option<Account> {
     section("── Settings ──");
     let interval_f = tf("Check &Interval (min):", "5");
     let enabled = cb("Ena&ble this account", true);
+    let save_sent = cb("Save copies of sent mail to the Sent fol&der", true);
+    let sync_drafts = cb("S&ync drafts with the server's Drafts folder", false);
+    // Ignored for Gmail, where expunging only removes a label
+    let expunge = cb("E&xpunge deleted mail instead of moving it to Trash", false);
+    let pin_certs = cb("Remember server certificates and &warn if they change", false);
+    let notify = cb("Show a &notification when new mail arrives", true);
+    let color_f = tf("Accent co&lor (#RRGGBB):", "#4A90E2");
+    let color_preview = {
+        let l = StaticText::builder(&dlg).with_label("").build();
+        let p = StaticText::builder(&dlg).with_label("").build();
+        fields.add(&l, 0, SizerFlag::All, 4);
+        fields.add(&p, 1, SizerFlag::Expand | SizerFlag::All, 4);
+        p
+    };
 
     sizer.add_sizer(&fields, 1, SizerFlag::Expand | SizerFlag::All, 4);
 
@@ -271,10 +297,17 @@ fn show_edit(parent: &Dialog, existing: Option<&Account>) -> Option<Account> {
         smtp_f.set_value(&a.smtp_server);
         smtp_port_f.set_value(&a.smtp_port);
         smtp_tls.set_value(a.smtp_use_tls);
+        bounce_f.set_value(a.envelope_from.as_deref().unwrap_or_default());
         user_f.set_value(&a.username);
         pass_f.set_value(&a.password);
         interval_f.set_value(&a.check_interval_minutes.to_string());
         enabled.set_value(a.enabled);
+        save_sent.set_value(a.save_sent_copy);
+        sync_drafts.set_value(a.sync_drafts);
+        expunge.set_value(a.delete_mode == DeleteMode::Expunge);
+        pin_certs.set_value(a.pin_certificates);
+        notify.set_value(a.notify_new_mail);
+        color_f.set_value(&a.color);
         // Show hint for existing accounts
         if a.use_oauth {
             auth_hint.set_label("(Gmail/Microsoft — browser authorization on save)");
@@ -287,9 +320,12 @@ fn show_edit(parent: &Dialog, existing: Option<&Account>) -> Option<Account> {
         let imap_port_f = imap_port_f; let smtp_port_f = smtp_port_f;
         let user_f = user_f; let email_f = email_f;
         let auth_hint = auth_hint;
+        let save_sent = save_sent;
         move |_| {
             let email = email_f.get_value();
             if let Some(domain) = email.split('@').nth(1) {
+                // Gmail files SMTP submissions in Sent itself
+                save_sent.set_value(!provider_saves_sent(&email));
                 let (imap, smtp, ip, sp) = detect_provider(domain);
                 if !imap.is_empty() {
                     imap_f.set_value(imap);
@@ -313,52 +349,93 @@ fn show_edit(parent: &Dialog, existing: Option<&Account>) -> Option<Account> {
         }
     });
 
+    // Preview the accent as it will be drawn, with its contrast on the theme
+    let update_preview = move || {
+        let hex = color_f.get_value();
+        match (account_accent(theme, &hex), contrast_on_theme(theme, &hex)) {
+            (Some((r, g, b)), Some(ratio)) => {
+                let verdict = if ratio >= 4.5 { "readable" } else { "low contrast, will be adjusted" };
+                color_preview.set_foreground_color(Colour::rgb(r, g, b));
+                color_preview.set_label(&format!("Sample: {:.1}:1 on the {} theme ({})", ratio, theme.label(), verdict));
+            }
+            _ => color_preview.set_label("Not a valid color"),
+        }
+    };
+    update_preview();
+    color_f.on_text_changed(move |_| update_preview());
+
     ok.on_click({ let d = dlg; move |_| { d.end_modal(ID_OK); } });
     cancel.on_click({ let d = dlg; move |_| { d.end_modal(ID_CANCEL); } });
 
-    if dlg.show_modal() == ID_OK {
-        let interval: u32 = interval_f.get_value().parse().unwrap_or(5).clamp(1, 60);
-        let email_val = email_f.get_value();
-        let is_oauth = requires_oauth(&email_val);
-
-        let provider = email_val.split('@').nth(1).and_then(|domain| {
-            match domain.to_lowercase().as_str() {
-                "gmail.com" | "googlemail.com" => Some("Gmail".to_string()),
-                "outlook.com" | "hotmail.com" | "live.com" | "msn.com" => Some("Outlook".to_string()),
-                "yahoo.com" | "ymail.com" => Some("Yahoo".to_string()),
-                "icloud.com" | "mac.com" | "me.com" => Some("iCloud".to_string()),
-                "aol.com" => Some("AOL".to_string()),
-                "zoho.com" => Some("Zoho".to_string()),
-                "protonmail.com" | "pm.me" | "proton.me" => Some("ProtonMail".to_string()),
-                _ => None,
-            }
-        });
-
-        Some(Account {
-            id: existing.map(|a| a.id.clone()).unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
-            name: name_f.get_value(),
-            email: email_val,
-            provider,
-            imap_server: imap_f.get_value(),
-            imap_port: imap_port_f.get_value(),
-            imap_use_tls: imap_tls.get_value(),
-            smtp_server: smtp_f.get_value(),
-            smtp_port: smtp_port_f.get_value(),
-            smtp_use_tls: smtp_tls.get_value(),
-            username: user_f.get_value(),
-            password: pass_f.get_value(),
-            use_oauth: is_oauth,
-            oauth_access_token: existing.map(|a| a.oauth_access_token.clone()).unwrap_or_default(),
-            oauth_refresh_token: existing.map(|a| a.oauth_refresh_token.clone()).unwrap_or_default(),
-            oauth_token_expires_at: existing.and_then(|a| a.oauth_token_expires_at.clone()),
-            enabled: enabled.get_value(),
-            check_interval_minutes: interval,
-            color: existing.map(|a| a.color.clone()).unwrap_or_else(|| "#4A90E2".into()),
-            last_sync: existing.and_then(|a| a.last_sync),
-        })
-    } else {
-        None
+    loop {
+        if dlg.show_modal() != ID_OK {
+            return None;
+        }
+        let bounce = bounce_f.get_value();
+        if !bounce.trim().is_empty() && !is_bare_address(&bounce) {
+            bounce_hint.set_label("Enter the bounce address alone, for example bounces@example.com.");
+            bounce_f.set_focus();
+            continue;
+        }
+        if normalize_hex(&color_f.get_value()).is_some() {
+            break;
+        }
+        color_preview.set_label("Enter the color as #RRGGBB, for example #4A90E2.");
+        color_f.set_focus();
     }
+
+    let interval: u32 = interval_f.get_value().parse().unwrap_or(5).clamp(1, 60);
+    let email_val = email_f.get_value();
+    let is_oauth = requires_oauth(&email_val);
+
+    let provider = email_val.split('@').nth(1).and_then(|domain| {
+        match domain.to_lowercase().as_str() {
+            "gmail.com" | "googlemail.com" => Some("Gmail".to_string()),
+            "outlook.com" | "hotmail.com" | "live.com" | "msn.com" => Some("Outlook".to_string()),
+            "yahoo.com" | "ymail.com" => Some("Yahoo".to_string()),
+            "icloud.com" | "mac.com" | "me.com" => Some("iCloud".to_string()),
+            "aol.com" => Some("AOL".to_string()),
+            "zoho.com" => Some("Zoho".to_string()),
+            "protonmail.com" | "pm.me" | "proton.me" => Some("ProtonMail".to_string()),
+            _ => None,
+        }
+    });
+
+    Some(Account {
+        id: existing.map(|a| a.id.clone()).unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+        name: name_f.get_value(),
+        email: email_val,
+        provider,
+        imap_server: imap_f.get_value(),
+        imap_port: imap_port_f.get_value(),
+        imap_use_tls: imap_tls.get_value(),
+        smtp_server: smtp_f.get_value(),
+        smtp_port: smtp_port_f.get_value(),
+        smtp_use_tls: smtp_tls.get_value(),
+        username: user_f.get_value(),
+        password: pass_f.get_value(),
+        use_oauth: is_oauth,
+        oauth_access_token: existing.map(|a| a.oauth_access_token.clone()).unwrap_or_default(),
+        oauth_refresh_token: existing.map(|a| a.oauth_refresh_token.clone()).unwrap_or_default(),
+        oauth_token_expires_at: existing.and_then(|a| a.oauth_token_expires_at.clone()),
+        enabled: enabled.get_value(),
+        check_interval_minutes: interval,
+        color: normalize_hex(&color_f.get_value()).unwrap_or_else(|| "#4A90E2".into()),
+        save_sent_copy: save_sent.get_value(),
+        sync_drafts: sync_drafts.get_value(),
+        delete_mode: if expunge.get_value() { DeleteMode::Expunge } else { DeleteMode::MoveToTrash },
+        pin_certificates: pin_certs.get_value(),
+        // A pin only holds while pinning stays on and the server is the same
+        imap_pinned_fingerprint: existing
+            .filter(|a| pin_certs.get_value() && a.imap_server == imap_f.get_value() && a.imap_port == imap_port_f.get_value())
+            .and_then(|a| a.imap_pinned_fingerprint.clone()),
+        smtp_pinned_fingerprint: existing
+            .filter(|a| pin_certs.get_value() && a.smtp_server == smtp_f.get_value() && a.smtp_port == smtp_port_f.get_value())
+            .and_then(|a| a.smtp_pinned_fingerprint.clone()),
+        notify_new_mail: notify.get_value(),
+        envelope_from: Some(bounce_f.get_value().trim().to_string()).filter(|b| !b.is_empty()),
+        last_sync: existing.and_then(|a| a.last_sync),
+    })
 }
 
 // ── Automatic OAuth Flow ────────────────────────────────────────────────────
@@ -412,7 +489,7 @@ fn run_oauth_flow(account: &mut Account) -> OAuthFlowResult {
 
 // ── Helpers ─────────────────────────────────────────────────────────────────
 
-fn populate(list: &ListCtrl, accounts: &[Account], active_id: Option<&str>) {
+fn populate(list: &ListCtrl, accounts: &[Account], active_id: Option<&str>, theme: Theme) {
     list.delete_all_items();
     for (i, a) in accounts.iter().enumerate() {
         let idx = i as i64;
@@ -423,6 +500,9 @@ fn populate(list: &ListCtrl, accounts: &[Account], active_id: Option<&str>) {
             else if active_id == Some(a.id.as_str()) { "★ Active" }
             else { "Enabled" };
         list.set_item_text_by_column(idx, 3, status);
+        if let Some((r, g, b)) = account_accent(theme, &a.color) {
+            list.set_item_text_colour(idx, Colour::rgb(r, g, b));
+        }
     }
 }
 
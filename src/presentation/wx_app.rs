@@ -3,20 +3,77 @@
 //! Main application window using wxdragon (wxWidgets bindings).
 //! Native Windows UI with first-class accessibility support.
 
-use crate::application::mail_controller::MailController;
+use crate::application::bulk::BulkAction;
+use crate::application::history::{ActionHistory, UndoableAction};
+use crate::application::composition::{fill_template, quote_original, reply_all_recipients, reply_body, DraftSession, TemplateValues};
+use crate::application::contacts::address_suggestions;
+use crate::application::filters::{FilterAction, FilterEngine, FilterRule};
+use crate::application::mail_controller::{FetchedBody, MailController, MessagePreview, MovedMessages, SendEmailRequest, SpecialFolder, SNOOZED_FOLDER};
+use crate::application::identities::{identity_for_reply, validate_identity};
+use crate::application::calendar::{Attendee, CalendarInvite, InviteResponse};
+use crate::application::messages::{message_priority, DeliveryFailure, MailingList, SenderAuthentication, Unsubscribe};
+use crate::application::notifications::{MailPreview, NewMailNotifier, Notification};
+use crate::application::quota::{format_size, MailboxUsage};
+use crate::application::readiness;
+use crate::application::MailEngine;
+use crate::application::responder::IncomingMail;
+use crate::application::search::SearchQuery;
+use crate::application::shutdown::{Shutdown, SHUTDOWN_WAIT};
+use crate::application::follow_up::{parse_due_date, FollowUp, FollowUpDue};
+use crate::application::snooze::{parse_wake_time, SnoozePreset};
+use crate::application::spam::SpamClassifier;
+use crate::common::types::{parse_message_date, EmailAddress, ReadingStats};
+use crate::common::clock::{SharedClock, SystemClock};
+use crate::common::logging;
+use crate::common::profile::ProfilePaths;
 use crate::common::Result;
-use crate::data::account::Account;
-use crate::data::message_cache::MessageCache;
-use crate::presentation::accessibility::Accessibility;
-use crate::presentation::html_renderer::HtmlRenderer;
+use crate::data::config::{
+    AppConfig, ConfigManager, ReceiptPolicy, SessionState, Theme, VacationResponder, DEFAULT_FONT_SIZE,
+};
+use crate::data::account::{Account, DeleteMode};
+use crate::data::message_cache::{
+    BackupSecrets, CachePool, CachedAttachment, CachedDraft, CachedMessage, CachedQuota, ContentEncryption, Identity, MessageCache,
+    MessageFilterRule, MessageTemplate, QueuedOutboxMessage, ReceiptStatus, SenderList, SettingsBackup, Tag, SCHEMA_VERSION,
+};
+use crate::data::storage::{self, Storage};
+use crate::presentation::accessibility::keyboard::{ListMove, Pane};
+use crate::presentation::accessibility::{Accessibility, MessageRowSpeech};
+use crate::presentation::command_palette::{strip_mnemonic, PaletteCommand};
+use crate::presentation::html_renderer::{displayed_body, HtmlRenderer};
+use crate::presentation::i18n::{self, tr, trf};
+use crate::presentation::keymap::Keymap;
+use crate::presentation::message_groups::{GroupBy, ListRow, MessageRows};
+use crate::presentation::message_source::MessageSource;
+use crate::presentation::printing::{self, PrintedMessage};
+use crate::presentation::theme::{account_accent, warning_colour, Palette};
+use crate::presentation::thread_reader::{ThreadEntry, ThreadReader};
 use crate::presentation::ui_types::*;
 use crate::presentation::wx_account_manager::{self, AccountManagerAction};
-use crate::presentation::wx_compose::{self, ComposeMode, ComposeResult};
-use crate::presentation::wx_managers;
+use crate::presentation::wx_backup;
+use crate::presentation::wx_command_palette;
+use crate::presentation::wx_log_viewer;
+use crate::presentation::wx_readiness;
+use crate::presentation::wx_compose::{self, ComposeMode, ComposeResult, FromChoice};
+use crate::presentation::wx_managers::{
+    self, IdentityEntry, IdentityManagerAction, SenderEntry, SenderManagerAction, TagEntry, TagManagerAction, TemplateEntry,
+    TemplateManagerAction,
+};
+use crate::presentation::wx_master_password;
+use crate::service::attachments::{extract_attachments, filename_warning, Attachment, AttachmentHandler, ImagePreview};
+use crate::service::protocols::{certificate_pin, rate_limit};
+use crate::service::oauth::{AuthManager, OAuthService};
+use crate::service::protocols::network::NetworkTimeouts;
+use crate::service::protocols::smtp::ReadReceipt;
+use crate::service::security::{MasterPasswordStore, SecurityService};
+use crate::service::unsubscribe;
 use crate::presentation::wx_settings;
+use crate::presentation::wx_shortcuts;
 
 use async_channel::{Receiver, Sender};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
 use std::sync::Arc;
 use std::sync::Mutex as StdMutex;
 use tokio::runtime::Runtime;
@@ -26,9 +83,31 @@ use wxdragon::prelude::*;
 // ── Constants ────────────────────────────────────────────────────────────────
 
 const POLL_MS: i32 = 50;
+/// Let the system decide how long a notification stays up
+const NOTIFICATION_TIMEOUT_AUTO: i32 = -1;
 const WIN_W: i32 = 1280;
 const WIN_H: i32 = 800;
 const FOLDER_W: i32 = 220;
+/// Arrow key codes, which collapse and expand message list groups
+const WXK_LEFT: i32 = 314;
+const WXK_RIGHT: i32 = 316;
+const WXK_DOWN: i32 = 317;
+const WXK_RETURN: i32 = 13;
+const WXK_ESCAPE: i32 = 27;
+/// "/" in the message list moves to the quick filter
+const QUICK_FILTER_KEY: i32 = '/' as i32;
+/// Pause after the last keystroke before the quick filter applies
+const QUICK_FILTER_DELAY_MS: i32 = 250;
+/// Message list column widths at 100% zoom
+const LIST_COLUMN_W: [i32; 4] = [300, 200, 150, 60];
+/// Most recipient suggestions shown while composing
+const ADDRESS_SUGGESTIONS: usize = 8;
+/// Longest side of an image attachment preview, in pixels
+const IMAGE_PREVIEW_SIDE: u32 = 200;
+/// Most image attachments previewed under a message
+const MAX_IMAGE_PREVIEWS: usize = 4;
+/// Longest side of an image attachment when printed, in pixels
+const PRINT_IMAGE_SIDE: u32 = 1200;
 
 // Menu IDs
 const ID_CHECK_MAIL: Id = ID_HIGHEST + 1;
@@ -49,6 +128,19 @@ const ID_ABOUT: Id = ID_HIGHEST + 15;
 const ID_THREAD_VIEW: Id = ID_HIGHEST + 16;
 const ID_OFFLINE_MODE: Id = ID_HIGHEST + 17;
 const ID_FLUSH_OUTBOX: Id = ID_HIGHEST + 18;
+const ID_UNDO: Id = ID_HIGHEST + 19;
+// Multi-select / bulk action IDs
+const ID_SELECT_ALL: Id = ID_HIGHEST + 20;
+const ID_MARK_UNREAD: Id = ID_HIGHEST + 21;
+const ID_STAR: Id = ID_HIGHEST + 22;
+const ID_UNSTAR: Id = ID_HIGHEST + 23;
+const ID_MOVE: Id = ID_HIGHEST + 24;
+const ID_ADD_TAG: Id = ID_HIGHEST + 25;
+const ID_REMOVE_TAG: Id = ID_HIGHEST + 26;
+// Zoom IDs
+const ID_ZOOM_IN: Id = ID_HIGHEST + 27;
+const ID_ZOOM_OUT: Id = ID_HIGHEST + 28;
+const ID_ZOOM_RESET: Id = ID_HIGHEST + 29;
 // Sort menu IDs
 const ID_SORT_DATE_NEWEST: Id = ID_HIGHEST + 30;
 const ID_SORT_DATE_OLDEST: Id = ID_HIGHEST + 31;
@@ -57,16 +149,301 @@ const ID_SORT_SENDER_ZA: Id = ID_HIGHEST + 33;
 const ID_SORT_SUBJECT_AZ: Id = ID_HIGHEST + 34;
 const ID_SORT_SUBJECT_ZA: Id = ID_HIGHEST + 35;
 const ID_SORT_UNREAD_FIRST: Id = ID_HIGHEST + 36;
+const ID_RELOAD_MESSAGE: Id = ID_HIGHEST + 37;
 const ID_SETTINGS: Id = ID_HIGHEST + 40;
+const ID_MASTER_PASSWORD: Id = ID_HIGHEST + 41;
+const ID_OFFLINE_FOLDER: Id = ID_HIGHEST + 42;
+const ID_PURGE_OFFLINE: Id = ID_HIGHEST + 43;
+const ID_EXPORT_FOLDER: Id = ID_HIGHEST + 44;
+const ID_EXPORT_MESSAGE: Id = ID_HIGHEST + 45;
+const ID_IMPORT: Id = ID_HIGHEST + 46;
+// Conversation reader IDs
+const ID_READ_THREAD: Id = ID_HIGHEST + 47;
+const ID_THREAD_NEXT: Id = ID_HIGHEST + 48;
+const ID_THREAD_PREV: Id = ID_HIGHEST + 49;
+const ID_EXPAND_QUOTES: Id = ID_HIGHEST + 50;
+const ID_TOGGLE_STAR: Id = ID_HIGHEST + 51;
+const ID_ARCHIVE: Id = ID_HIGHEST + 52;
+const ID_RESTORE: Id = ID_HIGHEST + 53;
+const ID_EMPTY_TRASH: Id = ID_HIGHEST + 54;
+const ID_PLAIN_TEXT: Id = ID_HIGHEST + 55;
+const ID_REMEMBER_VIEW: Id = ID_HIGHEST + 56;
+const ID_VIEW_SOURCE: Id = ID_HIGHEST + 57;
+const ID_SNOOZE: Id = ID_HIGHEST + 58;
+const ID_FOLLOW_UP: Id = ID_HIGHEST + 59;
+const ID_RESEND: Id = ID_HIGHEST + 60;
+const ID_TRUST_CERTIFICATE: Id = ID_HIGHEST + 61;
+const ID_TEMPLATE_MGR: Id = ID_HIGHEST + 62;
+const ID_IDENTITY_MGR: Id = ID_HIGHEST + 63;
+const ID_SAVE_ATTACHMENTS: Id = ID_HIGHEST + 64;
+const ID_VACATION: Id = ID_HIGHEST + 65;
+const ID_BLOCK_SENDER: Id = ID_HIGHEST + 66;
+const ID_SENDER_MGR: Id = ID_HIGHEST + 67;
+const ID_UNSUBSCRIBE: Id = ID_HIGHEST + 68;
+const ID_GROUP_NONE: Id = ID_HIGHEST + 69;
+const ID_GROUP_SENDER: Id = ID_HIGHEST + 70;
+const ID_GROUP_DOMAIN: Id = ID_HIGHEST + 71;
+const ID_GROUP_LIST: Id = ID_HIGHEST + 72;
+const ID_MANAGE_FOLDERS: Id = ID_HIGHEST + 73;
+const ID_NEW_FOLDER: Id = ID_HIGHEST + 74;
+const ID_NEW_SUBFOLDER: Id = ID_HIGHEST + 75;
+const ID_RENAME_FOLDER: Id = ID_HIGHEST + 76;
+const ID_DELETE_FOLDER: Id = ID_HIGHEST + 77;
+const ID_MARK_SPAM: Id = ID_HIGHEST + 78;
+const ID_NOT_SPAM: Id = ID_HIGHEST + 79;
+const ID_BACKUP_SETTINGS: Id = ID_HIGHEST + 80;
+const ID_RESTORE_SETTINGS: Id = ID_HIGHEST + 81;
+const ID_VIEW_LOGS: Id = ID_HIGHEST + 82;
+const ID_READINESS: Id = ID_HIGHEST + 83;
+const ID_OPEN_ATTACHMENT: Id = ID_HIGHEST + 84;
+const ID_SYNC_FOLDER: Id = ID_HIGHEST + 85;
+const ID_TRUST_SENDER: Id = ID_HIGHEST + 86;
+const ID_NEXT_PANE: Id = ID_HIGHEST + 87;
+const ID_PREV_PANE: Id = ID_HIGHEST + 88;
+const ID_COMMAND_PALETTE: Id = ID_HIGHEST + 89;
+const ID_SHORTCUTS: Id = ID_HIGHEST + 90;
+const ID_PRINT: Id = ID_HIGHEST + 91;
+const ID_SAVE_PDF: Id = ID_HIGHEST + 92;
+const ID_COPY_TEXT: Id = ID_HIGHEST + 93;
+const ID_COPY_SENDER: Id = ID_HIGHEST + 94;
+const ID_COPY_LINK: Id = ID_HIGHEST + 95;
+
+// ── Menu bar ─────────────────────────────────────────────────────────────────
+
+/// One entry on a menu, named by its string key; the help text is the
+/// key followed by ".help"
+enum MenuEntry {
+    Item(Id, &'static str),
+    Check(Id, &'static str),
+    Radio(Id, &'static str),
+    Separator,
+    Submenu(&'static str, &'static [MenuEntry]),
+}
+
+use MenuEntry::{Check, Item, Radio, Separator, Submenu};
+
+/// Every menu command. The menu bar and the command palette are both
+/// built from this, so a command added here shows up in both.
+const MENU_BAR: &[(&str, &[MenuEntry])] = &[
+    ("menu.file", &[
+        Item(ID_CHECK_MAIL, "menu.check_mail"),
+        Item(ID_NEW_MESSAGE, "menu.new_message"),
+        Separator,
+        Item(ID_EXPORT_FOLDER, "menu.export_folder"),
+        Item(ID_EXPORT_MESSAGE, "menu.export_message"),
+        Item(ID_IMPORT, "menu.import"),
+        Separator,
+        Item(ID_PRINT, "menu.print"),
+        Item(ID_SAVE_PDF, "menu.save_pdf"),
+        Separator,
+        Item(ID_QUIT, "menu.quit"),
+    ]),
+    ("menu.edit", &[
+        Item(ID_UNDO, "menu.undo"),
+        Separator,
+        Item(ID_SELECT_ALL, "menu.select_all"),
+        Separator,
+        Item(ID_SEARCH, "menu.search"),
+    ]),
+    ("menu.view", &[
+        Item(ID_NEXT_PANE, "menu.next_pane"),
+        Item(ID_PREV_PANE, "menu.prev_pane"),
+        Item(ID_COMMAND_PALETTE, "menu.command_palette"),
+        Separator,
+        Check(ID_THREAD_VIEW, "menu.thread_view"),
+        Item(ID_READ_THREAD, "menu.read_thread"),
+        Item(ID_THREAD_NEXT, "menu.thread_next"),
+        Item(ID_THREAD_PREV, "menu.thread_prev"),
+        Item(ID_EXPAND_QUOTES, "menu.expand_quotes"),
+        Separator,
+        Item(ID_PLAIN_TEXT, "menu.plain_text"),
+        Item(ID_REMEMBER_VIEW, "menu.remember_view"),
+        Separator,
+        Item(ID_ZOOM_IN, "menu.zoom_in"),
+        Item(ID_ZOOM_OUT, "menu.zoom_out"),
+        Item(ID_ZOOM_RESET, "menu.zoom_reset"),
+        Separator,
+        Item(ID_MANAGE_FOLDERS, "menu.manage_folders"),
+        Check(ID_OFFLINE_MODE, "menu.offline_mode"),
+        Item(ID_OFFLINE_FOLDER, "menu.offline_folder"),
+        Item(ID_PURGE_OFFLINE, "menu.purge_offline"),
+        Separator,
+        Submenu("menu.sort", &[
+            Radio(ID_SORT_DATE_NEWEST, "menu.sort_date_newest"),
+            Radio(ID_SORT_DATE_OLDEST, "menu.sort_date_oldest"),
+            Separator,
+            Radio(ID_SORT_SENDER_AZ, "menu.sort_sender_az"),
+            Radio(ID_SORT_SENDER_ZA, "menu.sort_sender_za"),
+            Separator,
+            Radio(ID_SORT_SUBJECT_AZ, "menu.sort_subject_az"),
+            Radio(ID_SORT_SUBJECT_ZA, "menu.sort_subject_za"),
+            Separator,
+            Radio(ID_SORT_UNREAD_FIRST, "menu.sort_unread_first"),
+        ]),
+        Submenu("menu.group_by", &[
+            Radio(ID_GROUP_NONE, "menu.group_none"),
+            Radio(ID_GROUP_SENDER, "menu.group_sender"),
+            Radio(ID_GROUP_DOMAIN, "menu.group_domain"),
+            Radio(ID_GROUP_LIST, "menu.group_list"),
+        ]),
+    ]),
+    ("menu.message", &[
+        Item(ID_REPLY, "menu.reply"),
+        Item(ID_REPLY_ALL, "menu.reply_all"),
+        Item(ID_FORWARD, "menu.forward"),
+        Item(ID_RELOAD_MESSAGE, "menu.reload_message"),
+        Item(ID_VIEW_SOURCE, "menu.view_source"),
+        Item(ID_OPEN_ATTACHMENT, "menu.open_attachment"),
+        Item(ID_SAVE_ATTACHMENTS, "menu.save_attachments"),
+        Item(ID_RESEND, "menu.resend"),
+        Separator,
+        Item(ID_COPY_TEXT, "menu.copy_text"),
+        Item(ID_COPY_SENDER, "menu.copy_sender"),
+        Item(ID_COPY_LINK, "menu.copy_link"),
+        Separator,
+        Item(ID_MARK_READ, "menu.mark_read"),
+        Item(ID_MARK_UNREAD, "menu.mark_unread"),
+        Item(ID_STAR, "menu.star"),
+        Item(ID_UNSTAR, "menu.unstar"),
+        Separator,
+        Item(ID_MOVE, "menu.move"),
+        Item(ID_ADD_TAG, "menu.add_tag"),
+        Item(ID_REMOVE_TAG, "menu.remove_tag"),
+        Separator,
+        Item(ID_DELETE, "menu.delete"),
+        Item(ID_ARCHIVE, "menu.archive"),
+        Item(ID_SNOOZE, "menu.snooze"),
+        Item(ID_FOLLOW_UP, "menu.follow_up"),
+        Item(ID_MARK_SPAM, "menu.mark_spam"),
+        Item(ID_NOT_SPAM, "menu.not_spam"),
+        Item(ID_BLOCK_SENDER, "menu.block_sender"),
+        Item(ID_TRUST_SENDER, "menu.trust_sender"),
+        Item(ID_UNSUBSCRIBE, "menu.unsubscribe"),
+        Item(ID_RESTORE, "menu.restore"),
+        Item(ID_EMPTY_TRASH, "menu.empty_trash"),
+    ]),
+    ("menu.tools", &[
+        Item(ID_ACCOUNT_MGR, "menu.account_mgr"),
+        Item(ID_IDENTITY_MGR, "menu.identity_mgr"),
+        Item(ID_TRUST_CERTIFICATE, "menu.trust_certificate"),
+        Separator,
+        Item(ID_CONTACT_MGR, "menu.contact_mgr"),
+        Item(ID_FILTER_MGR, "menu.filter_mgr"),
+        Item(ID_SENDER_MGR, "menu.sender_mgr"),
+        Item(ID_TAG_MGR, "menu.tag_mgr"),
+        Item(ID_SIG_MGR, "menu.sig_mgr"),
+        Item(ID_TEMPLATE_MGR, "menu.template_mgr"),
+        Item(ID_VACATION, "menu.vacation"),
+        Separator,
+        Item(ID_FLUSH_OUTBOX, "menu.flush_outbox"),
+        Separator,
+        Item(ID_SETTINGS, "menu.settings"),
+        Item(ID_SHORTCUTS, "menu.shortcuts"),
+        Item(ID_MASTER_PASSWORD, "menu.master_password"),
+        Separator,
+        Item(ID_BACKUP_SETTINGS, "menu.backup_settings"),
+        Item(ID_RESTORE_SETTINGS, "menu.restore_settings"),
+    ]),
+    ("menu.help", &[
+        Item(ID_READINESS, "menu.readiness"),
+        Item(ID_VIEW_LOGS, "menu.view_logs"),
+        Separator,
+        Item(ID_ABOUT, "menu.about"),
+    ]),
+];
+
+fn help_key(key: &str) -> String {
+    format!("{}.help", key)
+}
+
+/// Every command on the menu bar: its id, string key and menu, e.g.
+/// "View → Sort Messages" for the sort orders
+fn menu_commands() -> Vec<(Id, &'static str, String)> {
+    fn walk(entries: &'static [MenuEntry], menu: &str, out: &mut Vec<(Id, &'static str, String)>) {
+        for entry in entries {
+            match *entry {
+                Item(id, key) | Check(id, key) | Radio(id, key) => out.push((id, key, menu.to_string())),
+                Submenu(key, sub) => walk(sub, &format!("{} → {}", menu, strip_mnemonic(&tr(key))), out),
+                Separator => {}
+            }
+        }
+    }
+    let mut commands = Vec::new();
+    for (key, entries) in MENU_BAR {
+        walk(entries, &strip_mnemonic(&tr(key)), &mut commands);
+    }
+    commands
+}
+
+/// Each menu command's shortcut: the one its label gives it, unless the
+/// user changed it in Tools > Keyboard Shortcuts
+fn keymap(state: &Arc<StdMutex<WxUIState>>) -> Keymap {
+    let defaults = menu_commands()
+        .into_iter()
+        .map(|(_, key, _)| {
+            let label = tr(key);
+            let shortcut = label.split_once('\t').map(|(_, s)| s.trim().to_string()).filter(|s| !s.is_empty());
+            (key.to_string(), shortcut)
+        })
+        .collect();
+    let saved = state.lock().map(|s| s.settings.shortcuts.clone()).unwrap_or_default();
+    Keymap::new(defaults, &saved)
+}
+
+/// A menu item's label, with its shortcut from `keymap`
+fn menu_label(keymap: &Keymap, key: &str) -> String {
+    keymap.label(key, &tr(key))
+}
+
+/// Build a menu from its entries. Submenus can only be added once the menu
+/// is built, so they come after its other entries.
+fn build_menu(entries: &[MenuEntry], keymap: &Keymap) -> Menu {
+    let mut builder = Menu::builder();
+    for entry in entries {
+        builder = match *entry {
+            Item(id, key) => builder.append_item(id, &menu_label(keymap, key), &tr(&help_key(key))),
+            Check(id, key) => builder.append_check_item(id, &menu_label(keymap, key), &tr(&help_key(key))),
+            Radio(id, key) => builder.append_radio_item(id, &menu_label(keymap, key), &tr(&help_key(key))),
+            Separator => builder.append_separator(),
+            Submenu(..) => builder,
+        };
+    }
+    let menu = builder.build();
+    for entry in entries {
+        if let Submenu(key, sub) = *entry {
+            menu.append_submenu(build_menu(sub, keymap), &tr(key), &tr(&help_key(key)));
+        }
+    }
+    menu
+}
+
+/// The menu bar, with the shortcuts in `keymap`
+fn build_menu_bar(keymap: &Keymap) -> MenuBar {
+    MENU_BAR.iter()
+        .fold(MenuBar::builder(), |bar, (key, entries)| bar.append(build_menu(entries, keymap), &tr(key)))
+        .build()
+}
+
+/// The commands the palette offers: everything on the menu bar but the
+/// palette itself
+fn palette_commands(keymap: &Keymap) -> Vec<PaletteCommand<Id>> {
+    menu_commands()
+        .into_iter()
+        .filter(|(id, _, _)| *id != ID_COMMAND_PALETTE)
+        .map(|(id, key, menu)| PaletteCommand::from_menu_label(id, &menu, &menu_label(keymap, key), &tr(&help_key(key))))
+        .collect()
+}
 
 // ── UI State ─────────────────────────────────────────────────────────────────
 
 #[derive(Debug)]
 pub struct WxUIState {
     pub folders: Vec<String>,
+    pub folder_items: Vec<FolderItem>,
     pub messages: Vec<MessageItem>,
     pub selected_folder: Option<String>,
     pub selected_message_index: Option<usize>,
+    pub selected_uids: HashSet<u32>,
     pub message_preview: String,
     pub connection_status: ConnectionStatus,
     pub status_message: String,
@@ -76,15 +453,97 @@ pub struct WxUIState {
     pub offline_mode: bool,
     pub outbox_count: usize,
     pub sort_order: MailSortOption,
+    pub history: ActionHistory,
+    pub settings: AppConfig,
+    pub thread_view: bool,
+    /// How the message list is grouped
+    pub group_by: GroupBy,
+    /// Keys of the groups shown collapsed
+    pub collapsed_groups: HashSet<String>,
+    /// Rows of the message list, group headers included
+    pub rows: MessageRows,
+    /// List row with keyboard focus, which may be a group header
+    pub focused_row: Option<usize>,
+    /// Row to focus once the list is next redrawn
+    pub refocus_row: Option<usize>,
+    /// Conversation shown in the preview pane, if reading a whole thread
+    pub thread_reader: Option<ThreadReader>,
+    /// A master password is set but has not been entered yet
+    pub secrets_locked: bool,
+    /// Plain-text or HTML view chosen for the open message, overriding the
+    /// settings until another message is opened
+    pub plain_text_override: Option<bool>,
+    /// The last opened bounce, for Resend Undelivered Message
+    pub bounce: Option<Bounce>,
+    /// Last reported mailbox quota per account id
+    pub quotas: HashMap<String, CachedQuota>,
+    /// A pinned server certificate that changed, for Trust New Certificate
+    pub certificate_change: Option<CertificateChange>,
+    /// The calendar invitation in the open message, for the invite buttons
+    pub invite: Option<OpenInvite>,
+    /// The mailing list the open message came from, for Unsubscribe
+    pub mailing_list: Option<OpenMailingList>,
+    /// The open message's sender is trusted, so its links aren't flagged
+    pub sender_trusted: bool,
+    /// Length of the open message, announced when it opens
+    pub reading_stats: Option<ReadingStats>,
+    /// The search whose results are listed, highlighted in opened messages
+    pub search: Option<SearchQuery>,
+    /// Text in the quick filter box; only messages whose subject or sender
+    /// contains it are listed. Empty shows them all.
+    pub quick_filter: String,
+    /// Time source for due dates and the status column, shared with the cache
+    pub clock: SharedClock,
+    /// Stop signal for background work; sends hold a guard so quitting waits
+    pub shutdown: Shutdown,
+    /// The last session, whose folder is selected once the folder tree loads
+    pub restore_session: Option<SessionState>,
+    /// Main window size, saved with the session
+    pub window_size: (i32, i32),
+    /// New mail waiting to be announced with a desktop notification
+    pub notifier: NewMailNotifier,
+    /// Drafts the composer had open when the app last stopped without
+    /// closing it, offered back by New Message
+    pub recovered_drafts: Vec<CachedDraft>,
+}
+
+/// A calendar invitation and the message and account it arrived in
+#[derive(Debug, Clone)]
+pub struct OpenInvite {
+    /// Cache id of the message
+    pub message_id: i64,
+    pub account_id: String,
+    pub invite: CalendarInvite,
+}
+
+/// A mailing list and the message and account it arrived in
+#[derive(Debug, Clone)]
+pub struct OpenMailingList {
+    /// Cache id of the message
+    pub message_id: i64,
+    pub account_id: String,
+    pub list: MailingList,
+}
+
+/// A delivery failure report and the sent message it is about
+#[derive(Debug, Clone)]
+pub struct Bounce {
+    /// Cache id of the report
+    pub message_id: i64,
+    pub failure: DeliveryFailure,
+    /// Cached copy of the undelivered message, if found
+    pub original: Option<CachedMessage>,
 }
 
 impl Default for WxUIState {
     fn default() -> Self {
         Self {
             folders: Vec::new(),
+            folder_items: Vec::new(),
             messages: Vec::new(),
             selected_folder: None,
             selected_message_index: None,
+            selected_uids: HashSet::new(),
             message_preview: String::new(),
             connection_status: ConnectionStatus::Disconnected,
             status_message: "Ready".into(),
@@ -94,8 +553,89 @@ impl Default for WxUIState {
             offline_mode: false,
             outbox_count: 0,
             sort_order: MailSortOption::DateNewestFirst,
+            history: ActionHistory::default(),
+            settings: AppConfig::default(),
+            thread_view: false,
+            group_by: GroupBy::None,
+            collapsed_groups: HashSet::new(),
+            rows: MessageRows::default(),
+            focused_row: None,
+            refocus_row: None,
+            thread_reader: None,
+            secrets_locked: false,
+            plain_text_override: None,
+            bounce: None,
+            quotas: HashMap::new(),
+            certificate_change: None,
+            invite: None,
+            mailing_list: None,
+            sender_trusted: false,
+            reading_stats: None,
+            search: None,
+            quick_filter: String::new(),
+            clock: SystemClock::shared(),
+            shutdown: Shutdown::new(),
+            restore_session: None,
+            window_size: (WIN_W, WIN_H),
+            notifier: NewMailNotifier::default(),
+            recovered_drafts: Vec::new(),
+        }
+    }
+}
+
+impl WxUIState {
+    /// Whether the virtual All Inboxes folder is shown
+    pub fn viewing_all_inboxes(&self) -> bool {
+        self.selected_folder.as_deref() == Some(ALL_INBOXES_PATH)
+    }
+
+    /// Whether the open message is shown as plain text
+    pub fn plain_text_view(&self) -> bool {
+        self.plain_text_override.unwrap_or_else(|| {
+            let sender = self.selected_message_index.and_then(|i| self.messages.get(i)).map(|m| m.from.as_str());
+            self.settings.prefers_plain_text(sender.unwrap_or_default())
+        })
+    }
+
+    /// Whether the virtual Follow-up folder is shown
+    pub fn viewing_follow_up(&self) -> bool {
+        self.selected_folder.as_deref() == Some(FOLLOW_UP_PATH)
+    }
+
+    /// IMAP folder the listed messages live in (the Inbox for All Inboxes,
+    /// the focused message's folder for Follow-up).
+    pub fn mailbox(&self) -> String {
+        match self.selected_folder.as_deref() {
+            None | Some(ALL_INBOXES_PATH) => "INBOX".into(),
+            Some(FOLLOW_UP_PATH) => self
+                .selected_message_index
+                .and_then(|i| self.messages.get(i))
+                .and_then(|m| m.folder.clone())
+                .unwrap_or_else(|| "INBOX".into()),
+            Some(folder) => folder.to_string(),
+        }
+    }
+
+    /// Where the user is now, to restore at the next start
+    pub fn session(&self) -> SessionState {
+        SessionState {
+            account_id: self.active_account_id.clone(),
+            folder: self.selected_folder.clone(),
+            sort_order: Some(self.sort_order.as_config_key().to_string()),
+            thread_view: Some(self.thread_view),
+            group_by: Some(self.group_by.as_config_key().to_string()),
+            window_width: Some(self.window_size.0),
+            window_height: Some(self.window_size.1),
         }
     }
+
+    /// Whether the selected messages all belong to the active account.
+    /// Only messages in the All Inboxes view can belong to another one.
+    pub fn selection_in_active_account(&self) -> bool {
+        self.messages.iter()
+            .filter(|m| self.selected_uids.contains(&m.uid))
+            .all(|m| m.account_id.is_none() || m.account_id == self.active_account_id)
+    }
 }
 
 // ── WxMailApp ───────────────────────────────────────────────────────────────
@@ -105,34 +645,92 @@ pub struct WxMailApp {
     ui_tx: Sender<UIUpdate>,
     ui_rx: Receiver<UIUpdate>,
     state: Arc<StdMutex<WxUIState>>,
-    #[allow(dead_code)]
     mail_controllers: HashMap<String, Arc<TokioMutex<MailController>>>,
     accessibility: Accessibility,
-    #[allow(dead_code)] // Held for lifetime; will be used for cache reads
     message_cache: Option<MessageCache>,
+    /// Window title, naming the profile unless it is the default one
+    title: String,
 }
 
 impl WxMailApp {
-    pub fn new() -> Result<Self> {
+    /// The app for `profile`, with its settings and cached mail
+    pub fn new(profile: &ProfilePaths) -> Result<Self> {
         let runtime = Arc::new(
             Runtime::new().map_err(|e| crate::common::Error::Other(format!("Runtime: {}", e)))?,
         );
         let (ui_tx, ui_rx) = async_channel::unbounded();
 
-        let cache_dir = dirs::cache_dir()
-            .ok_or_else(|| crate::common::Error::Other("No cache dir".into()))?
-            .join("wixen-mail");
-        let security = crate::service::security::SecurityService::new().ok();
-        let message_cache = MessageCache::new(cache_dir, security).ok();
-
+        let cache_dir = profile.cache_dir.clone();
+        // With a master password, secrets stay locked until the user enters
+        // it (or a remembered key is found); otherwise use the machine key.
         let mut state = WxUIState::default();
+        let security = match MasterPasswordStore::default_location() {
+            Ok(store) if store.is_set() => {
+                let remembered = store.recall();
+                state.secrets_locked = remembered.is_none();
+                remembered
+            }
+            _ => SecurityService::new().ok(),
+        };
+        let mut message_cache = MessageCache::new(cache_dir, security).ok().map(|c| {
+            let c = c.with_clock(state.clock.clone());
+            if state.secrets_locked { c.with_secrets_locked() } else { c }
+        });
+
+        let mut config = ConfigManager::with_dir(profile.config_dir.clone());
+        if let Err(e) = config.load() {
+            tracing::warn!("Using default settings: {}", e);
+        }
+        state.settings = config.app_config().clone();
+        i18n::init(state.settings.ui_locale.as_deref(), Some(&config.locale_dir()));
+        rate_limit::configure(state.settings.rate_limits());
+        logging::set_log_level(state.settings.logging_level());
+        if let Some(ref mut cache) = message_cache {
+            cache.set_content_encryption(content_encryption(&state.settings));
+        }
+        let session = config.load_session().unwrap_or_else(|e| {
+            tracing::warn!("Starting a new session: {}", e);
+            SessionState::default()
+        });
+        state.sort_order = MailSortOption::from_config_key(
+            session.sort_order.as_deref().unwrap_or(&state.settings.default_sort_order),
+        );
+        state.thread_view = session.thread_view.unwrap_or(state.settings.thread_view_default);
+        state.group_by = session.group_by.as_deref().map(GroupBy::from_config_key).unwrap_or_default();
+        if let Some(size) = session.window_size() {
+            state.window_size = size;
+        }
         if let Some(ref cache) = message_cache {
-            if let Ok(accounts) = cache.load_accounts() {
-                state.active_account_id = accounts.first().map(|a| a.id.clone());
+            if !state.secrets_locked {
+                let upgraded = upgrade_plaintext_secrets(cache);
+                if upgraded > 0 {
+                    tracing::info!("Upgraded {} legacy secrets to encrypted storage", upgraded);
+                }
+            }
+            // Locked passwords would load as empty and could then be saved
+            // back over the real ones; accounts load once unlocked.
+            if let Some(accounts) = (!state.secrets_locked).then(|| cache.load_accounts().ok()).flatten() {
+                state.active_account_id = session
+                    .account_in(accounts.iter().map(|a| a.id.as_str()))
+                    .or_else(|| accounts.first().map(|a| a.id.clone()));
+                state.quotas = accounts
+                    .iter()
+                    .filter_map(|a| Some((a.id.clone(), cache.get_quota(&a.id).ok()??)))
+                    .collect();
                 state.accounts = accounts;
             }
+            state.recovered_drafts = cache.take_interrupted_drafts().unwrap_or_else(|e| {
+                tracing::warn!("Could not check for interrupted drafts: {}", e);
+                Vec::new()
+            });
+            let pruned = prune_cache(cache, &state.accounts, &state.settings);
+            if pruned > 0 {
+                tracing::info!("Pruned {} cached message bodies", pruned);
+            }
         }
 
+        state.restore_session = Some(session);
+
         let accessibility = Accessibility::new()?;
         accessibility.initialize().unwrap_or_else(|e| {
             tracing::warn!("Accessibility init: {}", e);
@@ -146,6 +744,10 @@ impl WxMailApp {
             mail_controllers: HashMap::new(),
             accessibility,
             message_cache,
+            title: match &profile.name {
+                Some(name) => format!("Wixen Mail ({})", name),
+                None => "Wixen Mail".to_string(),
+            },
         })
     }
 
@@ -155,14 +757,22 @@ impl WxMailApp {
         let ui_tx = self.ui_tx.clone();
         let runtime = self.runtime.clone();
         let a11y = Arc::new(self.accessibility);
+        let cache = self.message_cache.map(CachePool::new);
+        // Account changes and search go through the headless engine, over
+        // the same cache
+        let engine = cache.clone().map(|pool| Arc::new(MailEngine::with_pool(pool)));
+        let controllers = Arc::new(self.mail_controllers);
+        let title = self.title;
+        let window_size = self.state.lock().map(|s| s.window_size).unwrap_or((WIN_W, WIN_H));
 
         let _ = wxdragon::main(move |_| {
             let frame = Frame::builder()
-                .with_title("Wixen Mail")
-                .with_size(Size::new(WIN_W, WIN_H))
+                .with_title(&title)
+                .with_size(Size::new(window_size.0, window_size.1))
                 .build();
 
-            frame.set_menu_bar(Self::build_menu_bar());
+            let shortcuts = keymap(&state);
+            frame.set_menu_bar(build_menu_bar(&shortcuts));
 
             // ── Main toolbar ─────────────────────────────────────────────
             let toolbar_handle = if let Some(toolbar) = frame.create_tool_bar(
@@ -174,28 +784,38 @@ impl WxMailApp {
                         .or_else(|| Bitmap::new(16, 16))
                         .expect("toolbar bitmap")
                 };
-                toolbar.add_tool(ID_CHECK_MAIL, "Get Mail", &bmp(ArtId::GoDown), "Check for new messages (F9)");
-                toolbar.add_tool(ID_NEW_MESSAGE, "New", &bmp(ArtId::New), "Compose new message (Ctrl+N)");
+                // Tips name the tool's shortcut, as set when the app started
+                let tip = |text: &str, key: &str| match shortcuts.shortcut(key) {
+                    Some(shortcut) => format!("{} ({})", text, shortcut),
+                    None => text.to_string(),
+                };
+                toolbar.add_tool(ID_CHECK_MAIL, "Get Mail", &bmp(ArtId::GoDown), &tip("Check for new messages", "menu.check_mail"));
+                toolbar.add_tool(ID_NEW_MESSAGE, "New", &bmp(ArtId::New), &tip("Compose new message", "menu.new_message"));
                 toolbar.add_separator();
-                toolbar.add_tool(ID_REPLY, "Reply", &bmp(ArtId::GoBack), "Reply to sender (Ctrl+R)");
-                toolbar.add_tool(ID_REPLY_ALL, "Reply All", &bmp(ArtId::GoBack), "Reply to all (Ctrl+Shift+R)");
-                toolbar.add_tool(ID_FORWARD, "Forward", &bmp(ArtId::GoForward), "Forward message (Ctrl+L)");
+                toolbar.add_tool(ID_REPLY, "Reply", &bmp(ArtId::GoBack), &tip("Reply to sender", "menu.reply"));
+                toolbar.add_tool(ID_REPLY_ALL, "Reply All", &bmp(ArtId::GoBack), &tip("Reply to all", "menu.reply_all"));
+                toolbar.add_tool(ID_FORWARD, "Forward", &bmp(ArtId::GoForward), &tip("Forward message", "menu.forward"));
                 toolbar.add_separator();
-                toolbar.add_tool(ID_DELETE, "Delete", &bmp(ArtId::Delete), "Delete message (Del)");
-                toolbar.add_tool(ID_MARK_READ, "Mark Read", &bmp(ArtId::TickMark), "Mark as read");
+                toolbar.add_tool(ID_DELETE, "Delete", &bmp(ArtId::Delete), &tip("Delete message", "menu.delete"));
+                toolbar.add_tool(ID_MARK_READ, "Mark Read", &bmp(ArtId::TickMark), &tip("Mark selected as read", "menu.mark_read"));
+                toolbar.add_tool(ID_MARK_UNREAD, "Mark Unread", &bmp(ArtId::Cross), &tip("Mark selected as unread", "menu.mark_unread"));
+                toolbar.add_tool(ID_STAR, "Star", &bmp(ArtId::AddBookmark), &tip("Star selected messages", "menu.star"));
+                toolbar.add_tool(ID_MOVE, "Move", &bmp(ArtId::FileSaveAs), &tip("Move selected messages to a folder", "menu.move"));
                 toolbar.add_separator();
-                toolbar.add_tool(ID_SEARCH, "Search", &bmp(ArtId::Find), "Search messages (Ctrl+F)");
+                toolbar.add_tool(ID_SEARCH, "Search", &bmp(ArtId::Find), &tip("Search messages", "menu.search"));
                 toolbar.realize();
                 Some(toolbar)
             } else {
                 None
             };
 
-            let status_bar = frame.create_status_bar(3, 0, ID_ANY as i32, "statusbar");
-            status_bar.set_status_widths(&[-3, -1, -1]);
-            frame.set_status_text("Ready", 0);
-            frame.set_status_text("Disconnected", 1);
+            let status_bar = frame.create_status_bar(5, 0, ID_ANY as i32, "statusbar");
+            status_bar.set_status_widths(&[-3, -1, -1, -2, -2]);
+            frame.set_status_text(&tr("status.ready"), 0);
+            frame.set_status_text(&tr("status.disconnected"), 1);
             frame.set_status_text("", 2);
+            frame.set_status_text("", 3);
+            frame.set_status_text("", 4);
 
             // ── Three-pane layout ────────────────────────────────────────
             let panel = Panel::builder(&frame).build();
@@ -211,48 +831,93 @@ impl WxMailApp {
             let root_id = folder_tree.add_root("Mail Folders", None, None).expect("tree root");
             folder_tree.expand(&root_id);
 
-            let msg_list = ListCtrl::builder(&inner)
-                .with_style(ListCtrlStyle::Report | ListCtrlStyle::SingleSel | ListCtrlStyle::HRules)
+            // The quick filter box sits above the message list
+            let list_pane = Panel::builder(&inner).build();
+            let filter_label = StaticText::builder(&list_pane).with_label("F&ilter:").build();
+            let quick_filter = TextCtrl::builder(&list_pane).build();
+            quick_filter.set_tooltip("Show only messages whose subject or sender contains this text. Esc clears it.");
+            let msg_list = ListCtrl::builder(&list_pane)
+                .with_style(ListCtrlStyle::Report | ListCtrlStyle::HRules)
                 .build();
-            if let Some(list_font) = Font::new_with_details(
-                10,
-                FontFamily::Swiss.as_i32(),
-                FontStyle::Normal.as_i32(),
-                FontWeight::Normal.as_i32(),
-                false,
-                "",
-            ) {
-                msg_list.set_font(&list_font);
-            }
-            msg_list.insert_column(0, "Subject", ListColumnFormat::Left, 300);
-            msg_list.insert_column(1, "From", ListColumnFormat::Left, 200);
-            msg_list.insert_column(2, "Date", ListColumnFormat::Left, 150);
-            msg_list.insert_column(3, "Status", ListColumnFormat::Centre, 60);
+            msg_list.insert_column(0, "Subject", ListColumnFormat::Left, LIST_COLUMN_W[0]);
+            msg_list.insert_column(1, "From", ListColumnFormat::Left, LIST_COLUMN_W[1]);
+            msg_list.insert_column(2, "Date", ListColumnFormat::Left, LIST_COLUMN_W[2]);
+            msg_list.insert_column(3, "Status", ListColumnFormat::Centre, LIST_COLUMN_W[3]);
+            let filter_row = BoxSizer::builder(Orientation::Horizontal).build();
+            filter_row.add(&filter_label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+            filter_row.add(&quick_filter, 1, SizerFlag::Expand | SizerFlag::All, 4);
+            let list_sizer = BoxSizer::builder(Orientation::Vertical).build();
+            list_sizer.add_sizer(&filter_row, 0, SizerFlag::Expand, 0);
+            list_sizer.add(&msg_list, 1, SizerFlag::Expand, 0);
+            list_pane.set_sizer(list_sizer, true);
 
             // RichTextCtrl for message preview — supports formatted content and is
-            // accessible to screen readers via the wxWidgets UIA bridge.
-            let preview = RichTextCtrl::builder(&inner)
+            // accessible to screen readers via the wxWidgets UIA bridge. Image
+            // attachments are previewed in a strip below it.
+            let preview_pane = Panel::builder(&inner).build();
+            let preview = RichTextCtrl::builder(&preview_pane)
                 .with_style(RichTextCtrlStyle::MultiLine | RichTextCtrlStyle::ReadOnly)
                 .build();
-            if let Some(preview_font) = Font::new_with_details(
-                11,
-                FontFamily::Roman.as_i32(),
-                FontStyle::Normal.as_i32(),
-                FontWeight::Normal.as_i32(),
-                false,
-                "",
-            ) {
-                preview.set_font(&preview_font);
-            }
+            let invite_bar = InviteBar::new(&preview_pane);
+            let list_bar = UnsubscribeBar::new(&preview_pane);
+            let full_bar = FullMessageBar::new(&preview_pane);
+            let image_strip = ImageStrip::new(&preview_pane);
+            let preview_sizer = BoxSizer::builder(Orientation::Vertical).build();
+            preview_sizer.add(&invite_bar.panel, 0, SizerFlag::Expand, 0);
+            preview_sizer.add(&list_bar.panel, 0, SizerFlag::Expand, 0);
+            preview_sizer.add(&full_bar.panel, 0, SizerFlag::Expand, 0);
+            preview_sizer.add(&preview, 1, SizerFlag::Expand, 0);
+            preview_sizer.add(&image_strip.panel, 0, SizerFlag::Expand, 0);
+            preview_pane.set_sizer(preview_sizer, true);
 
-            inner.split_horizontally(&msg_list, &preview, 300);
+            let (theme, font_size) = state.lock()
+                .map(|s| (s.settings.theme_preference(), s.settings.font_size))
+                .unwrap_or((Theme::System, DEFAULT_FONT_SIZE));
+            apply_theme(theme, &folder_tree, &msg_list, &preview);
+            apply_font_size(font_size, &folder_tree, &msg_list, &preview);
+
+            inner.split_horizontally(&list_pane, &preview_pane, 300);
+            for (button, response) in invite_bar.buttons.iter().zip(InviteResponse::ALL) {
+                button.on_click({
+                    let state = state.clone();
+                    let cache = cache.clone();
+                    let ui_tx = ui_tx.clone();
+                    let runtime = runtime.clone();
+                    move |_| respond_to_invite(&state, &cache, &ui_tx, &runtime, response)
+                });
+            }
+            list_bar.button.on_click({
+                let state = state.clone();
+                let cache = cache.clone();
+                let controllers = controllers.clone();
+                let ui_tx = ui_tx.clone();
+                let runtime = runtime.clone();
+                move |_| unsubscribe_from_list(&frame, &state, &cache, &controllers, &ui_tx, &runtime)
+            });
+            full_bar.button.on_click({
+                let state = state.clone();
+                let cache = cache.clone();
+                let controllers = controllers.clone();
+                let ui_tx = ui_tx.clone();
+                let runtime = runtime.clone();
+                move |_| {
+                    let idx = state.lock().ok().and_then(|s| s.selected_message_index);
+                    if let Some(idx) = idx {
+                        open_message(&state, &cache, &controllers, &ui_tx, &runtime, idx, BodySource::FullMessage);
+                    }
+                }
+            });
             outer.split_vertically(&folder_tree, &inner, FOLDER_W);
             panel_sizer.add(&outer, 1, SizerFlag::Expand | SizerFlag::All, 0);
             panel.set_sizer(panel_sizer, true);
 
             // ── Keyboard shortcuts for focus navigation ──────────────────
+            // F6 and Shift+F6 (View menu) step through the panes from the
+            // one last moved to, clicked or typed in
+            let pane = Rc::new(Cell::new(Pane::Messages));
             panel.on_key_down({
                 let msg_list = msg_list;
+                let pane = pane.clone();
                 let toolbar_handle = toolbar_handle.clone();
                 move |event| {
                     if let WindowEventData::Keyboard(ref kbd) = event {
@@ -262,6 +927,7 @@ impl WxMailApp {
                             // Ctrl+1 → focus inbox / message list
                             (true, 49) => {
                                 msg_list.set_focus();
+                                pane.set(Pane::Messages);
                                 return;
                             }
                             // Ctrl+\ → focus toolbar
@@ -279,20 +945,86 @@ impl WxMailApp {
                 }
             });
 
+            // Ctrl+C copies the preview's selected text, and says so
+            preview.on_key_down({
+                let a11y = a11y.clone();
+                move |event| {
+                    if let WindowEventData::Keyboard(ref kbd) = event {
+                        if kbd.control_down() && !kbd.shift_down() && kbd.get_key_code() == Some(67) && preview.can_copy() {
+                            preview.copy();
+                            let _ = a11y.announce(&tr("status.copied_selection"), crate::presentation::accessibility::announcements::Politeness::Polite);
+                            return;
+                        }
+                    }
+                    event.skip(true);
+                }
+            });
+
+            // Right-click (or the context menu key) selects the folder and
+            // offers to sync it now, or to create, rename or delete folders.
+            folder_tree.on_item_menu({
+                let folder_tree = folder_tree;
+                move |event| {
+                    if let Some(item) = event.get_item() {
+                        folder_tree.select_item(&item);
+                    }
+                    let mut menu = Menu::builder()
+                        .append_item(ID_SYNC_FOLDER, &tr("menu.sync_folder"), &tr("menu.sync_folder.help"))
+                        .append_separator()
+                        .append_item(ID_NEW_FOLDER, &tr("menu.new_folder"), &tr("menu.new_folder.help"))
+                        .append_item(ID_NEW_SUBFOLDER, &tr("menu.new_subfolder"), &tr("menu.new_subfolder.help"))
+                        .append_item(ID_RENAME_FOLDER, &tr("menu.rename_folder"), &tr("menu.rename_folder.help"))
+                        .append_item(ID_DELETE_FOLDER, &tr("menu.delete_folder"), &tr("menu.delete_folder.help"))
+                        .append_separator()
+                        .append_item(ID_MANAGE_FOLDERS, &tr("menu.manage_folders"), &tr("menu.manage_folders.help"))
+                        .build();
+                    folder_tree.popup_menu(&mut menu, None);
+                }
+            });
+
             // ── Folder selection ─────────────────────────────────────────
             folder_tree.on_selection_changed({
                 let state = state.clone();
                 let ui_tx = ui_tx.clone();
                 let runtime = runtime.clone();
+                let cache = cache.clone();
+                let pane = pane.clone();
                 let folder_tree = folder_tree;
                 move |event| {
+                    pane.set(Pane::Folders);
                     if let Some(item) = event.get_item() {
-                        if let Some(name) = folder_tree.get_item_text(&item) {
-                            if name == "Mail Folders" { return; }
-                            if let Ok(mut s) = state.lock() { s.selected_folder = Some(name.clone()); }
+                        if let Some(label) = folder_tree.get_item_text(&item) {
+                            if label == "Mail Folders" { return; }
+                            let name = state.lock().ok()
+                                .and_then(|s| s.folder_items.iter().find(|f| f.label() == label).map(|f| f.path.clone()))
+                                .unwrap_or(label);
+                            let order = folder_sort_order(&state, &cache, &name);
+                            let offline = state.lock().map(|mut s| {
+                                s.selected_folder = Some(name.clone());
+                                s.search = None;
+                                if let Some(order) = order {
+                                    s.sort_order = order;
+                                }
+                                s.offline_mode
+                            }).unwrap_or(false);
+                            save_session(&state);
+                            if name == ALL_INBOXES_PATH {
+                                load_all_inboxes(&state, &cache, &ui_tx, &runtime);
+                            } else if name == FOLLOW_UP_PATH {
+                                load_follow_ups(&state, &cache, &ui_tx, &runtime);
+                            } else if offline {
+                                load_cached_folder(&state, &cache, &ui_tx, &runtime, &name);
+                            }
+                            let last_sync = folder_last_sync(&state, &cache, &name);
+                            folder_tree.set_tooltip(&describe_last_sync(last_sync.as_deref()));
+                            let label = match name.as_str() {
+                                ALL_INBOXES_PATH => "All Inboxes".to_string(),
+                                FOLLOW_UP_PATH => "Follow-up".to_string(),
+                                _ => name,
+                            };
                             let tx = ui_tx.clone();
                             runtime.spawn(async move {
-                                let _ = tx.send(UIUpdate::StatusUpdated(format!("Loading {}...", name))).await;
+                                let _ = tx.send(UIUpdate::FolderChanged { name: label, last_sync }).await;
                             });
                         }
                     }
@@ -305,76 +1037,415 @@ impl WxMailApp {
                 let ui_tx = ui_tx.clone();
                 let runtime = runtime.clone();
                 move |event| {
-                    let idx = event.get_item_index() as usize;
-                    if let Ok(mut s) = state.lock() { s.selected_message_index = Some(idx); }
+                    let row = event.get_item_index() as usize;
+                    let (idx, count, group) = state.lock().map(|mut guard| {
+                        let s = &mut *guard;
+                        s.focused_row = Some(row);
+                        s.selected_message_index = s.rows.message_at(row);
+                        match s.rows.get(row) {
+                            // A header stands for its whole group, collapsed or not
+                            Some(ListRow::Header(header)) => {
+                                let members = s.messages.get(header.messages()).unwrap_or_default();
+                                s.selected_uids.extend(members.iter().map(|m| m.uid));
+                                (None, s.selected_uids.len(), Some(header.description()))
+                            }
+                            _ => {
+                                let idx = s.selected_message_index;
+                                if let Some(uid) = idx.and_then(|i| s.messages.get(i)).map(|m| m.uid) { s.selected_uids.insert(uid); }
+                                (idx, s.selected_uids.len(), None)
+                            }
+                        }
+                    }).unwrap_or((None, 0, None));
                     let tx = ui_tx.clone();
                     runtime.spawn(async move {
-                        let _ = tx.send(UIUpdate::StatusUpdated(format!("Loading message {}...", idx))).await;
+                        if let Some(group) = group {
+                            let _ = tx.send(UIUpdate::GroupFocused(group)).await;
+                            return;
+                        }
+                        let _ = tx.send(UIUpdate::SelectionChanged(count)).await;
+                        if let Some(idx) = idx.filter(|_| count == 1) {
+                            let _ = tx.send(UIUpdate::MessageFocused(idx)).await;
+                        }
                     });
                 }
             });
 
+            // ── Keyboard message navigation ──────────────────────────────
+            // Arrows/Home/End are handled natively by the list (selection,
+            // focus and scrolling); j/k mirror them for one-handed use. Every
+            // move fires on_item_selected, which announces the new message.
+            // Other letters and digits apply the tag assigned to that key.
+            msg_list.on_key_down({
+                let state = state.clone();
+                let cache = cache.clone();
+                let controllers = controllers.clone();
+                let ui_tx = ui_tx.clone();
+                let runtime = runtime.clone();
+                let msg_list = msg_list;
+                let pane = pane.clone();
+                move |event| {
+                    pane.set(Pane::Messages);
+                    if let WindowEventData::Keyboard(ref kbd) = event {
+                        let key = kbd.get_key_code().unwrap_or(0);
+                        let name = match key { 74 => "j", 75 => "k", _ => "" };
+                        if !kbd.control_down() && !kbd.alt_down() {
+                            if let Some(mv) = ListMove::from_key(name) {
+                                let (current, len) = state.lock()
+                                    .map(|s| (s.focused_row, s.rows.len()))
+                                    .unwrap_or((None, 0));
+                                if let Some(target) = mv.apply(current, len) {
+                                    select_single_row(&msg_list, current, target);
+                                }
+                                return;
+                            }
+                            if key == QUICK_FILTER_KEY {
+                                quick_filter.set_focus();
+                                return;
+                            }
+                            // Left collapses the focused group, Right expands it
+                            if (key == WXK_LEFT || key == WXK_RIGHT)
+                                && collapse_group(&state, &ui_tx, &runtime, key == WXK_LEFT)
+                            {
+                                return;
+                            }
+                            let letter = u8::try_from(key).ok().map(char::from).filter(char::is_ascii_alphanumeric);
+                            if let Some(letter) = letter {
+                                if apply_tag_shortcut(&state, &cache, &controllers, &ui_tx, &runtime, letter) {
+                                    return;
+                                }
+                            }
+                        }
+                    }
+                    event.skip(true);
+                }
+            });
+
+            // ── Quick filter ─────────────────────────────────────────────
+            // Typing narrows the list once the user pauses, so long lists
+            // aren't redrawn on every keystroke. Esc clears the filter, or
+            // returns to the list when it is already empty; Enter and Down
+            // go to the list.
+            let filter_timer = Rc::new(Timer::new(&list_pane));
+            filter_timer.on_tick({
+                let state = state.clone();
+                let ui_tx = ui_tx.clone();
+                let runtime = runtime.clone();
+                move |_| set_quick_filter(&state, &ui_tx, &runtime, &quick_filter.get_value())
+            });
+            quick_filter.on_text_changed({
+                let filter_timer = filter_timer.clone();
+                move |_| { filter_timer.start(QUICK_FILTER_DELAY_MS, true); }
+            });
+            quick_filter.on_key_down({
+                let state = state.clone();
+                let ui_tx = ui_tx.clone();
+                let runtime = runtime.clone();
+                move |event| {
+                    if let WindowEventData::Keyboard(ref kbd) = event {
+                        match kbd.get_key_code() {
+                            Some(WXK_ESCAPE) if !quick_filter.get_value().is_empty() => {
+                                filter_timer.stop();
+                                quick_filter.set_value("");
+                                set_quick_filter(&state, &ui_tx, &runtime, "");
+                                return;
+                            }
+                            Some(WXK_ESCAPE | WXK_RETURN | WXK_DOWN) => {
+                                msg_list.set_focus();
+                                return;
+                            }
+                            _ => {}
+                        }
+                    }
+                    event.skip(true);
+                }
+            });
+
+            // Right-click offers the reply and flag actions for the clicked message.
+            msg_list.on_item_right_click({
+                let msg_list = msg_list;
+                let state = state.clone();
+                move |_| {
+                    let keymap = keymap(&state);
+                    let label = |key: &str| menu_label(&keymap, key);
+                    let mut menu = Menu::builder()
+                        .append_item(ID_REPLY, &label("menu.reply"), &tr("menu.reply.help"))
+                        .append_item(ID_REPLY_ALL, &label("menu.reply_all"), &tr("context.reply_all.help"))
+                        .append_item(ID_FORWARD, &label("menu.forward"), &tr("menu.forward.help"))
+                        .append_separator()
+                        .append_item(ID_MARK_UNREAD, &label("menu.mark_unread"), &tr("menu.mark_unread.help"))
+                        .append_item(ID_TOGGLE_STAR, &tr("context.toggle_star"), &tr("context.toggle_star.help"))
+                        .append_separator()
+                        .append_item(ID_DELETE, &label("menu.delete"), &tr("context.delete.help"))
+                        .append_item(ID_ARCHIVE, &label("menu.archive"), &tr("context.archive.help"))
+                        .append_item(ID_SNOOZE, &label("menu.snooze"), &tr("context.snooze.help"))
+                        .append_item(ID_FOLLOW_UP, &tr("context.follow_up"), &tr("context.follow_up.help"))
+                        .append_separator()
+                        .append_item(ID_MARK_SPAM, &label("menu.mark_spam"), &tr("menu.mark_spam.help"))
+                        .append_item(ID_NOT_SPAM, &label("menu.not_spam"), &tr("menu.not_spam.help"))
+                        .append_item(ID_BLOCK_SENDER, &label("menu.block_sender"), &tr("menu.block_sender.help"))
+                        .append_item(ID_TRUST_SENDER, &label("menu.trust_sender"), &tr("menu.trust_sender.help"))
+                        .build();
+                    msg_list.popup_menu(&mut menu, None);
+                }
+            });
+
+            // Enter (or double-click) opens the message in the preview pane.
+            msg_list.on_item_activated({
+                let state = state.clone();
+                let ui_tx = ui_tx.clone();
+                let runtime = runtime.clone();
+                let cache = cache.clone();
+                let controllers = controllers.clone();
+                move |event| {
+                    let row = event.get_item_index() as usize;
+                    // Enter on a group header collapses or expands it
+                    match state.lock().ok().and_then(|s| s.rows.get(row).cloned()) {
+                        Some(ListRow::Message(idx)) => open_message(&state, &cache, &controllers, &ui_tx, &runtime, idx, BodySource::Cached),
+                        Some(ListRow::Header(_)) => toggle_group(&state, &ui_tx, &runtime, row),
+                        None => {}
+                    }
+                }
+            });
+            msg_list.on_item_deselected({
+                let state = state.clone();
+                let ui_tx = ui_tx.clone();
+                let runtime = runtime.clone();
+                move |event| {
+                    let row = event.get_item_index() as usize;
+                    let count = state.lock().map(|mut guard| {
+                        let s = &mut *guard;
+                        let shown = match s.rows.get(row) {
+                            Some(ListRow::Header(header)) => header.messages(),
+                            Some(ListRow::Message(idx)) => *idx..*idx + 1,
+                            None => 0..0,
+                        };
+                        for m in s.messages.get(shown).unwrap_or_default() {
+                            s.selected_uids.remove(&m.uid);
+                        }
+                        s.selected_uids.len()
+                    }).unwrap_or(0);
+                    let tx = ui_tx.clone();
+                    runtime.spawn(async move { let _ = tx.send(UIUpdate::SelectionChanged(count)).await; });
+                }
+            });
+
             // ── Menu events ─────────────────────────────────────────────
             frame.on_menu({
                 let frame = frame;
                 let state = state.clone();
                 let ui_tx = ui_tx.clone();
                 let runtime = runtime.clone();
+                let cache = cache.clone();
+                let engine = engine.clone();
+                let controllers = controllers.clone();
+                let a11y = a11y.clone();
+                let pane = pane.clone();
                 move |event| {
-                    let id = event.get_id();
+                    let mut id = event.get_id();
+                    // The palette runs the chosen command as if it had been
+                    // picked from its menu
+                    if id == ID_COMMAND_PALETTE {
+                        match wx_command_palette::show_command_palette(&frame, &palette_commands(&keymap(&state))) {
+                            Some(chosen) => id = chosen,
+                            None => return,
+                        }
+                    }
                     match id {
                         _ if id == ID_QUIT => frame.close(false),
-                        _ if id == ID_CHECK_MAIL => send_status(&ui_tx, &runtime, "Checking for new mail..."),
-                        _ if id == ID_NEW_MESSAGE => open_compose(&frame, &state, &ui_tx, &runtime, ComposeMode::New),
+                        _ if id == ID_NEXT_PANE || id == ID_PREV_PANE => {
+                            let to = if id == ID_NEXT_PANE { pane.get().next() } else { pane.get().previous() };
+                            pane.set(to);
+                            match to {
+                                Pane::Folders => folder_tree.set_focus(),
+                                Pane::Messages => msg_list.set_focus(),
+                                Pane::Preview => preview.set_focus(),
+                            }
+                            let _ = a11y.announce(to.name(), crate::presentation::accessibility::announcements::Politeness::Polite);
+                        }
+                        _ if id == ID_CHECK_MAIL => check_mail(&state, &cache, &controllers, &ui_tx, &runtime),
+                        _ if id == ID_NEW_MESSAGE => open_compose(&frame, &state, &cache, &controllers, &ui_tx, &runtime, ComposeMode::New),
                         _ if id == ID_REPLY => {
-                            let (to, subj, body) = msg_info(&state);
-                            open_compose(&frame, &state, &ui_tx, &runtime, ComposeMode::Reply { to, subject: subj, quoted_body: body });
+                            let (to, subj) = msg_info(&state);
+                            let quoted_body = reply_quote(&state, &cache, &controllers, &runtime);
+                            open_compose(&frame, &state, &cache, &controllers, &ui_tx, &runtime, ComposeMode::Reply { to, subject: subj, quoted_body });
                         }
                         _ if id == ID_REPLY_ALL => {
-                            let (to, subj, body) = msg_info(&state);
-                            open_compose(&frame, &state, &ui_tx, &runtime, ComposeMode::ReplyAll { to, cc: String::new(), subject: subj, quoted_body: body });
+                            let (to, cc, subj) = reply_all_info(&state, &cache);
+                            let quoted_body = reply_quote(&state, &cache, &controllers, &runtime);
+                            open_compose(&frame, &state, &cache, &controllers, &ui_tx, &runtime, ComposeMode::ReplyAll { to, cc, subject: subj, quoted_body });
                         }
                         _ if id == ID_FORWARD => {
-                            let (_to, subj, body) = msg_info(&state);
-                            open_compose(&frame, &state, &ui_tx, &runtime, ComposeMode::Forward { subject: subj, body });
+                            let (_from, subj) = msg_info(&state);
+                            let body = selected_body_text(&state, &cache, &controllers, &runtime);
+                            open_compose(&frame, &state, &cache, &controllers, &ui_tx, &runtime, ComposeMode::Forward { subject: subj, body });
+                        }
+                        _ if id == ID_DELETE => {
+                            let (count, confirm) = state.lock()
+                                .map(|s| (s.selected_uids.len(), s.settings.confirm_before_delete))
+                                .unwrap_or((0, false));
+                            if confirm && count > 0 && !confirm_delete(&frame, count) {
+                                return;
+                            }
+                            dispose_selected(&state, &cache, &controllers, &ui_tx, &runtime, Disposal::Trash);
+                        }
+                        _ if id == ID_ARCHIVE => dispose_selected(&state, &cache, &controllers, &ui_tx, &runtime, Disposal::Archive),
+                        _ if id == ID_FOLLOW_UP => follow_up_selected(&frame, &state, &cache, &controllers, &ui_tx, &runtime),
+                        _ if id == ID_SNOOZE => snooze_selected(&frame, &state, &cache, &controllers, &ui_tx, &runtime),
+                        _ if id == ID_MARK_SPAM => mark_spam(&state, &cache, &controllers, &ui_tx, &runtime, true),
+                        _ if id == ID_NOT_SPAM => mark_spam(&state, &cache, &controllers, &ui_tx, &runtime, false),
+                        _ if id == ID_BLOCK_SENDER => block_sender(&frame, &state, &cache, &controllers, &ui_tx, &runtime),
+                        _ if id == ID_TRUST_SENDER => trust_sender(&frame, &state, &cache, &ui_tx, &runtime),
+                        _ if id == ID_UNSUBSCRIBE => unsubscribe_from_list(&frame, &state, &cache, &controllers, &ui_tx, &runtime),
+                        _ if id == ID_RESEND => resend_undelivered(&frame, &state, &cache, &controllers, &ui_tx, &runtime),
+                        _ if id == ID_COPY_TEXT || id == ID_COPY_SENDER || id == ID_COPY_LINK => {
+                            // Nothing on screen changes, so the result is spoken too
+                            if let Some(done) = copy_from_message(&frame, &state, id) {
+                                frame.set_status_text(&done, 0);
+                                let _ = a11y.announce(&done, crate::presentation::accessibility::announcements::Politeness::Polite);
+                            }
+                        }
+                        _ if id == ID_TRUST_CERTIFICATE => trust_new_certificate(&frame, &state, &cache, &ui_tx, &runtime),
+                        _ if id == ID_RESTORE => restore_selected(&state, &cache, &controllers, &ui_tx, &runtime),
+                        _ if id == ID_EMPTY_TRASH => {
+                            let dlg = MessageDialog::builder(&frame, "Permanently delete every message in Trash?", "Empty Trash")
+                                .with_style(MessageDialogStyle::YesNo | MessageDialogStyle::IconWarning)
+                                .build();
+                            if dlg.show_modal() == ID_YES {
+                                empty_trash(&state, &cache, &controllers, &ui_tx, &runtime);
+                            }
+                        }
+                        _ if id == ID_SELECT_ALL => {
+                            let (count, rows) = state.lock().map(|mut s| {
+                                s.selected_uids = s.messages.iter().map(|m| m.uid).collect();
+                                (s.messages.len(), s.rows.len())
+                            }).unwrap_or((0, 0));
+                            for row in 0..rows {
+                                msg_list.set_item_state(row as i64, ListItemState::Selected, ListItemState::Selected);
+                            }
+                            let tx = ui_tx.clone();
+                            runtime.spawn(async move { let _ = tx.send(UIUpdate::SelectionChanged(count)).await; });
+                        }
+                        _ if id == ID_MARK_UNREAD => run_bulk_action(&state, &cache, &controllers, &ui_tx, &runtime, BulkAction::MarkUnread),
+                        _ if id == ID_STAR => run_bulk_action(&state, &cache, &controllers, &ui_tx, &runtime, BulkAction::Star),
+                        _ if id == ID_UNSTAR => run_bulk_action(&state, &cache, &controllers, &ui_tx, &runtime, BulkAction::Unstar),
+                        _ if id == ID_TOGGLE_STAR => {
+                            let all_starred = {
+                                let s = state.lock().unwrap();
+                                let mut selected = s.messages.iter().filter(|m| s.selected_uids.contains(&m.uid)).peekable();
+                                selected.peek().is_some() && selected.all(|m| m.starred)
+                            };
+                            let action = if all_starred { BulkAction::Unstar } else { BulkAction::Star };
+                            run_bulk_action(&state, &cache, &controllers, &ui_tx, &runtime, action);
+                        }
+                        _ if id == ID_MOVE => {
+                            if let Some(action) = choose_move_target(&frame, &state, &cache) {
+                                run_bulk_action(&state, &cache, &controllers, &ui_tx, &runtime, action);
+                            }
+                        }
+                        _ if id == ID_ADD_TAG || id == ID_REMOVE_TAG => {
+                            if let Some(tag_id) = choose_tag(&frame, &state, &cache) {
+                                let action = if id == ID_ADD_TAG { BulkAction::AddTag(tag_id) } else { BulkAction::RemoveTag(tag_id) };
+                                run_bulk_action(&state, &cache, &controllers, &ui_tx, &runtime, action);
+                            }
+                        }
+                        _ if id == ID_UNDO => undo_last(&state, &cache, &controllers, &ui_tx, &runtime),
+                        _ if id == ID_RELOAD_MESSAGE => {
+                            let idx = state.lock().ok().and_then(|s| s.selected_message_index);
+                            if let Some(idx) = idx {
+                                open_message(&state, &cache, &controllers, &ui_tx, &runtime, idx, BodySource::Server);
+                            }
                         }
-                        _ if id == ID_DELETE => tracing::info!("Delete requested"),
-                        _ if id == ID_MARK_READ => tracing::info!("Mark Read requested"),
+                        _ if id == ID_VIEW_SOURCE => view_source(&frame, &state, &cache, &controllers, &ui_tx, &runtime),
+                        _ if id == ID_SAVE_ATTACHMENTS => save_all_attachments(&frame, &state, &cache, &controllers, &ui_tx, &runtime),
+                        _ if id == ID_OPEN_ATTACHMENT => open_attachment(&frame, &state, &cache, &controllers, &ui_tx, &runtime),
+                        _ if id == ID_MARK_READ => run_bulk_action(&state, &cache, &controllers, &ui_tx, &runtime, BulkAction::MarkRead),
                         _ if id == ID_SEARCH => {
-                            if let Some(q) = show_search_dialog(&frame) {
-                                let tx = ui_tx.clone();
-                                runtime.spawn(async move {
-                                    let _ = tx.send(UIUpdate::StatusUpdated(format!("Searching: {}...", q))).await;
-                                });
+                            if let Some(query) = show_search_dialog(&frame) {
+                                search_messages(&state, &engine, &ui_tx, &runtime, &query);
                             }
                         }
-                        _ if id == ID_ACCOUNT_MGR => handle_account_mgr(&frame, &state),
+                        _ if id == ID_ACCOUNT_MGR => {
+                            handle_account_mgr(&frame, &state, &engine, &ui_tx, &runtime);
+                            show_active_account(&frame, &status_bar, &state);
+                        }
                         _ if id == ID_CONTACT_MGR => { wx_managers::show_contact_manager_dialog(&frame, &[]); }
-                        _ if id == ID_FILTER_MGR => { wx_managers::show_filter_manager_dialog(&frame, &[]); }
-                        _ if id == ID_TAG_MGR => { wx_managers::show_tag_manager_dialog(&frame, &[]); }
+                        _ if id == ID_FILTER_MGR => handle_filter_mgr(&frame, &state, &cache, &ui_tx, &runtime),
+                        _ if id == ID_SENDER_MGR => handle_sender_mgr(&frame, &state, &cache, &ui_tx, &runtime),
+                        _ if id == ID_TAG_MGR => handle_tag_mgr(&frame, &state, &cache, &ui_tx, &runtime),
                         _ if id == ID_SIG_MGR => { wx_managers::show_signature_manager_dialog(&frame, &[]); }
-                        _ if id == ID_SETTINGS => handle_settings(&frame, &ui_tx, &runtime),
+                        _ if id == ID_TEMPLATE_MGR => handle_template_mgr(&frame, &state, &cache, &ui_tx, &runtime),
+                        _ if id == ID_VACATION => handle_vacation(&frame, &state, &cache, &ui_tx, &runtime),
+                        _ if id == ID_IDENTITY_MGR => handle_identity_mgr(&frame, &state, &cache, &ui_tx, &runtime),
+                        _ if id == ID_SHORTCUTS => edit_shortcuts(&frame, &state, &ui_tx, &runtime),
+                        _ if id == ID_SETTINGS => {
+                            handle_settings(&frame, &state, &cache, &ui_tx, &runtime);
+                            show_active_account(&frame, &status_bar, &state);
+                            show_vacation_status(&frame, &state);
+                        }
+                        _ if id == ID_MASTER_PASSWORD => handle_master_password(&frame, &state, &cache, &ui_tx, &runtime),
+                        _ if id == ID_BACKUP_SETTINGS => backup_settings(&frame, &state, &cache, &ui_tx, &runtime),
+                        _ if id == ID_RESTORE_SETTINGS => restore_settings(&frame, &state, &cache, &ui_tx, &runtime),
+                        _ if id == ID_ZOOM_IN => zoom(&state, &ui_tx, &runtime, 1),
+                        _ if id == ID_ZOOM_OUT => zoom(&state, &ui_tx, &runtime, -1),
+                        _ if id == ID_ZOOM_RESET => zoom(&state, &ui_tx, &runtime, 0),
+                        _ if id == ID_THREAD_VIEW => {
+                            let enabled = state.lock().map(|mut s| {
+                                s.thread_view = !s.thread_view;
+                                s.thread_view
+                            }).unwrap_or(false);
+                            save_session(&state);
+                            send_status(&ui_tx, &runtime, if enabled { "Thread view on" } else { "Thread view off" });
+                        }
+                        _ if id == ID_PLAIN_TEXT => toggle_plain_text(&state, &ui_tx, &runtime),
+                        _ if id == ID_REMEMBER_VIEW => remember_view_for_sender(&state, &ui_tx, &runtime),
                         _ if id == ID_OFFLINE_MODE => {
-                            let new_mode = {
+                            let (new_mode, folder) = {
                                 let mut s = state.lock().unwrap();
                                 s.offline_mode = !s.offline_mode;
-                                s.offline_mode
+                                (s.offline_mode, s.selected_folder.clone())
                             };
                             let label = if new_mode { "Offline mode enabled - outgoing mail will be queued" } else { "Online mode - outgoing mail will be sent immediately" };
                             send_status(&ui_tx, &runtime, label);
+                            if let Some(folder) = folder.filter(|_| new_mode) {
+                                load_cached_folder(&state, &cache, &ui_tx, &runtime, &folder);
+                            }
                         }
+                        _ if id == ID_EXPORT_FOLDER => export_folder(&frame, &state, &cache, &ui_tx, &runtime),
+                        _ if id == ID_EXPORT_MESSAGE => export_message(&frame, &state, &cache, &ui_tx, &runtime),
+                        _ if id == ID_PRINT => print_message(&frame, &state, &cache, &controllers, &ui_tx, &runtime),
+                        _ if id == ID_SAVE_PDF => save_message_pdf(&frame, &state, &cache, &controllers, &ui_tx, &runtime),
+                        _ if id == ID_IMPORT => import_messages(&frame, &state, &cache, &ui_tx, &runtime),
+                        _ if id == ID_READ_THREAD => read_thread(&state, &cache, &ui_tx, &runtime),
+                        _ if id == ID_THREAD_NEXT => step_thread(&state, &ui_tx, &runtime, ThreadStep::Next),
+                        _ if id == ID_THREAD_PREV => step_thread(&state, &ui_tx, &runtime, ThreadStep::Previous),
+                        _ if id == ID_EXPAND_QUOTES => step_thread(&state, &ui_tx, &runtime, ThreadStep::ToggleQuotes),
+                        _ if id == ID_OFFLINE_FOLDER => make_folder_offline(&frame, &state, &cache, &controllers, &ui_tx, &runtime),
+                        _ if id == ID_SYNC_FOLDER => sync_folder_now(&state, &cache, &controllers, &ui_tx, &runtime),
+                        _ if id == ID_PURGE_OFFLINE => purge_offline_folder(&state, &cache, &ui_tx, &runtime),
+                        _ if id == ID_MANAGE_FOLDERS => manage_folders(&frame, &state, &cache, &controllers, &ui_tx, &runtime),
+                        _ if id == ID_NEW_FOLDER || id == ID_NEW_SUBFOLDER => {
+                            new_folder(&frame, &state, &cache, &controllers, &ui_tx, &runtime, id == ID_NEW_SUBFOLDER)
+                        }
+                        _ if id == ID_RENAME_FOLDER => rename_selected_folder(&frame, &state, &cache, &controllers, &ui_tx, &runtime),
+                        _ if id == ID_DELETE_FOLDER => delete_selected_folder(&frame, &state, &cache, &controllers, &ui_tx, &runtime),
                         _ if id == ID_FLUSH_OUTBOX => {
-                            send_status(&ui_tx, &runtime, "Flushing outbox queue...");
-                            flush_outbox(&state, &ui_tx, &runtime);
-                        }
-                        _ if id == ID_SORT_DATE_NEWEST => apply_sort(&state, &ui_tx, &runtime, MailSortOption::DateNewestFirst),
-                        _ if id == ID_SORT_DATE_OLDEST => apply_sort(&state, &ui_tx, &runtime, MailSortOption::DateOldestFirst),
-                        _ if id == ID_SORT_SENDER_AZ => apply_sort(&state, &ui_tx, &runtime, MailSortOption::SenderAZ),
-                        _ if id == ID_SORT_SENDER_ZA => apply_sort(&state, &ui_tx, &runtime, MailSortOption::SenderZA),
-                        _ if id == ID_SORT_SUBJECT_AZ => apply_sort(&state, &ui_tx, &runtime, MailSortOption::SubjectAZ),
-                        _ if id == ID_SORT_SUBJECT_ZA => apply_sort(&state, &ui_tx, &runtime, MailSortOption::SubjectZA),
-                        _ if id == ID_SORT_UNREAD_FIRST => apply_sort(&state, &ui_tx, &runtime, MailSortOption::UnreadFirst),
+                            send_status(&ui_tx, &runtime, &tr("status.flushing_outbox_queue"));
+                            flush_outbox(&state, &cache, &ui_tx, &runtime);
+                        }
+                        _ if id == ID_GROUP_NONE => apply_grouping(&state, &ui_tx, &runtime, GroupBy::None),
+                        _ if id == ID_GROUP_SENDER => apply_grouping(&state, &ui_tx, &runtime, GroupBy::Sender),
+                        _ if id == ID_GROUP_DOMAIN => apply_grouping(&state, &ui_tx, &runtime, GroupBy::Domain),
+                        _ if id == ID_GROUP_LIST => apply_grouping(&state, &ui_tx, &runtime, GroupBy::List),
+                        _ if id == ID_SORT_DATE_NEWEST => sort_folder(&state, &cache, &ui_tx, &runtime, MailSortOption::DateNewestFirst),
+                        _ if id == ID_SORT_DATE_OLDEST => sort_folder(&state, &cache, &ui_tx, &runtime, MailSortOption::DateOldestFirst),
+                        _ if id == ID_SORT_SENDER_AZ => sort_folder(&state, &cache, &ui_tx, &runtime, MailSortOption::SenderAZ),
+                        _ if id == ID_SORT_SENDER_ZA => sort_folder(&state, &cache, &ui_tx, &runtime, MailSortOption::SenderZA),
+                        _ if id == ID_SORT_SUBJECT_AZ => sort_folder(&state, &cache, &ui_tx, &runtime, MailSortOption::SubjectAZ),
+                        _ if id == ID_SORT_SUBJECT_ZA => sort_folder(&state, &cache, &ui_tx, &runtime, MailSortOption::SubjectZA),
+                        _ if id == ID_SORT_UNREAD_FIRST => sort_folder(&state, &cache, &ui_tx, &runtime, MailSortOption::UnreadFirst),
                         _ if id == ID_ABOUT => show_about_dialog(&frame),
+                        _ if id == ID_VIEW_LOGS => view_logs(&frame, &ui_tx, &runtime),
+                        _ if id == ID_READINESS => check_readiness(&frame, &state, &cache, &runtime),
                         _ => tracing::debug!("Unhandled menu ID: {:?}", id),
                     }
                 }
@@ -384,98 +1455,191 @@ impl WxMailApp {
             let timer = Timer::new(&frame);
             timer.on_tick({
                 let state = state.clone();
+                let cache = cache.clone();
+                let runtime = runtime.clone();
+                let ui_tx = ui_tx.clone();
                 let ui_rx = ui_rx.clone();
                 let a11y = a11y.clone();
                 move |_| {
                     while let Ok(update) = ui_rx.try_recv() {
-                        handle_update(&update, &state, &folder_tree, &msg_list, &preview, &frame, &a11y);
+                        if let UIUpdate::ReadReceiptRequested { message_id, account_id, receipt } = update {
+                            ask_read_receipt(&frame, &state, &cache, &ui_tx, &runtime, message_id, &account_id, receipt);
+                            continue;
+                        }
+                        if let UIUpdate::ImagePreviews { message_id, previews } = &update {
+                            if is_open(&state, *message_id) {
+                                image_strip.show(previews);
+                            }
+                            continue;
+                        }
+                        if let UIUpdate::InviteFound { message_id, account_id, invite, response } = update {
+                            if is_open(&state, message_id) {
+                                invite_bar.show(Some((invite.as_ref(), response)));
+                                let _ = a11y.announce(&invite.description(), crate::presentation::accessibility::announcements::Politeness::Polite);
+                                if let Ok(mut s) = state.lock() {
+                                    s.invite = Some(OpenInvite { message_id, account_id, invite: *invite });
+                                }
+                            }
+                            continue;
+                        }
+                        if let UIUpdate::MailingListFound { message_id, account_id, list } = update {
+                            if is_open(&state, message_id) {
+                                list_bar.show(Some(&list));
+                                if let Ok(mut s) = state.lock() {
+                                    s.mailing_list = Some(OpenMailingList { message_id, account_id, list });
+                                }
+                            }
+                            continue;
+                        }
+                        if let UIUpdate::TextOnlyLoaded { message_id, full_size } = update {
+                            if is_open(&state, message_id) {
+                                full_bar.show(Some(full_size));
+                                let _ = a11y.announce(
+                                    &trf("preview.text_only", &[&format_size(full_size as i64)]),
+                                    crate::presentation::accessibility::announcements::Politeness::Polite,
+                                );
+                            }
+                            continue;
+                        }
+                        if let UIUpdate::MessageBodyLoaded(_) = update {
+                            image_strip.show(&[]);
+                            invite_bar.show(None);
+                            list_bar.show(None);
+                            full_bar.show(None);
+                            if let Ok(mut s) = state.lock() {
+                                s.invite = None;
+                                s.mailing_list = None;
+                            }
+                        }
+                        handle_update(&update, &state, &folder_tree, &msg_list, &preview, &frame, &status_bar, &a11y);
                     }
+                    show_due_notification(&frame, &state);
                 }
             });
             timer.start(POLL_MS, false);
+            refresh_folder_tree(&state, &cache, &ui_tx, &runtime);
 
-            // ── Initial status ──────────────────────────────────────────
-            if let Ok(s) = state.lock() {
-                if let Some(a) = s.accounts.first() {
-                    frame.set_status_text(&format!("Account: {}", a.email), 1);
+            // ── Automatic mail check ─────────────────────────────────────
+            // Re-reads the interval every minute so settings changes apply
+            // without a restart; 0 minutes or offline mode pauses checking.
+            runtime.spawn({
+                let state = state.clone();
+                let cache = cache.clone();
+                let controllers = controllers.clone();
+                let tx = ui_tx.clone();
+                let shutdown = state.lock().map(|s| s.shutdown.clone()).unwrap_or_default();
+                async move {
+                    let mut ticker = tokio::time::interval(std::time::Duration::from_secs(60));
+                    ticker.tick().await;
+                    let mut minutes = 0u32;
+                    loop {
+                        tokio::select! {
+                            _ = ticker.tick() => {}
+                            _ = shutdown.stopped() => break,
+                        }
+                        minutes += 1;
+                        let interval = state.lock()
+                            .map(|s| if s.offline_mode { 0 } else { s.settings.check_interval_minutes })
+                            .unwrap_or(0);
+                        if interval == 0 || minutes < interval {
+                            continue;
+                        }
+                        minutes = 0;
+                        if let Some(ctrl) = active_controller(&state, &controllers) {
+                            let check = check_mail_target(&state);
+                            let account_id = state.lock().ok().and_then(|s| s.active_account_id.clone()).unwrap_or_default();
+                            // Unsubscribed folders are only checked on request
+                            let subscribed = cache.with_reader(|c| c.is_folder_subscribed(&account_id, &check.folder).ok())
+                                .unwrap_or(true);
+                            if subscribed {
+                                check_mail_with(ctrl.clone(), cache.clone(), check, tx.clone()).await;
+                            }
+                            refresh_quota(ctrl, cache.clone(), account_id, tx.clone()).await;
+                        }
+                    }
+                }
+            });
+
+            // ── Snooze wake-ups ──────────────────────────────────────────
+            // Checked every minute; the first tick also wakes anything that
+            // came due while the app was closed.
+            runtime.spawn({
+                let state = state.clone();
+                let cache = cache.clone();
+                let controllers = controllers.clone();
+                let tx = ui_tx.clone();
+                let rt = runtime.clone();
+                let shutdown = state.lock().map(|s| s.shutdown.clone()).unwrap_or_default();
+                async move {
+                    let mut ticker = tokio::time::interval(std::time::Duration::from_secs(60));
+                    loop {
+                        tokio::select! {
+                            _ = ticker.tick() => {}
+                            _ = shutdown.stopped() => break,
+                        }
+                        wake_snoozed(&state, &cache, &controllers, &tx, &rt).await;
+                    }
+                }
+            });
+
+            // ── Quit ─────────────────────────────────────────────────────
+            // File > Quit closes the frame too, so both end up here.
+            frame.on_close({
+                let state = state.clone();
+                let cache = cache.clone();
+                let runtime = runtime.clone();
+                move |event| {
+                    // Messages still open in their own windows become drafts
+                    let closed = wx_compose::save_compose_windows();
+                    if closed > 0 {
+                        tracing::info!("Closed {} compose windows before quitting", closed);
+                    }
+                    shut_down(&frame, &state, &cache, &runtime);
+                    event.skip(true);
                 }
+            });
+
+            // ── Initial status ──────────────────────────────────────────
+            show_active_account(&frame, &status_bar, &state);
+            show_vacation_status(&frame, &state);
+            if state.lock().map(|s| !s.recovered_drafts.is_empty()).unwrap_or(false) {
+                send_status(&ui_tx, &runtime, &tr("status.draft_recovered"));
             }
 
             frame.show(true);
+
+            // ── Master password unlock ──────────────────────────────────
+            if state.lock().map(|s| s.secrets_locked).unwrap_or(false) {
+                unlock_secrets(&frame, &state, &cache, &ui_tx, &runtime);
+            }
         });
 
         Ok(())
     }
-
-    fn build_menu_bar() -> MenuBar {
-        let file = Menu::builder()
-            .append_item(ID_CHECK_MAIL, "Check &Mail\tF9", "Check for new messages")
-            .append_item(ID_NEW_MESSAGE, "&New Message\tCtrl+N", "Compose a new message")
-            .append_separator()
-            .append_item(ID_QUIT, "&Quit\tCtrl+Q", "Exit Wixen Mail")
-            .build();
-        let edit = Menu::builder()
-            .append_item(ID_SEARCH, "&Search\tCtrl+F", "Search messages")
-            .build();
-        // Sort submenu
-        let sort_menu = Menu::builder()
-            .append_radio_item(ID_SORT_DATE_NEWEST, "Date (Newest First)", "Sort by date, newest first")
-            .append_radio_item(ID_SORT_DATE_OLDEST, "Date (Oldest First)", "Sort by date, oldest first")
-            .append_separator()
-            .append_radio_item(ID_SORT_SENDER_AZ, "Sender (A-Z)", "Sort by sender ascending")
-            .append_radio_item(ID_SORT_SENDER_ZA, "Sender (Z-A)", "Sort by sender descending")
-            .append_separator()
-            .append_radio_item(ID_SORT_SUBJECT_AZ, "Subject (A-Z)", "Sort by subject ascending")
-            .append_radio_item(ID_SORT_SUBJECT_ZA, "Subject (Z-A)", "Sort by subject descending")
-            .append_separator()
-            .append_radio_item(ID_SORT_UNREAD_FIRST, "Unread First", "Show unread messages first")
-            .build();
-        let view = Menu::builder()
-            .append_check_item(ID_THREAD_VIEW, "&Thread View\tCtrl+T", "Toggle threaded view")
-            .append_separator()
-            .append_separator()  // placeholder — we insert the submenu below
-            .append_check_item(ID_OFFLINE_MODE, "&Offline Mode", "Toggle offline mode (queue outgoing mail)")
-            .build();
-        // Insert the sort sub-menu (MenuBuilder doesn't have append_sub_menu,
-        // but the built Menu does).
-        view.append_submenu(sort_menu, "&Sort Messages", "Change message sort order");
-        let message = Menu::builder()
-            .append_item(ID_REPLY, "&Reply\tCtrl+R", "Reply to sender")
-            .append_item(ID_REPLY_ALL, "Reply &All\tCtrl+Shift+R", "Reply to all")
-            .append_item(ID_FORWARD, "&Forward\tCtrl+L", "Forward message")
-            .append_separator()
-            .append_item(ID_MARK_READ, "Mark as &Read", "Mark as read")
-            .append_item(ID_DELETE, "&Delete\tDel", "Delete message")
-            .build();
-        let tools = Menu::builder()
-            .append_item(ID_ACCOUNT_MGR, "&Account Manager\tCtrl+A", "Manage email accounts")
-            .append_separator()
-            .append_item(ID_CONTACT_MGR, "&Contact Manager\tCtrl+2", "Manage contacts")
-            .append_item(ID_FILTER_MGR, "&Filter Manager", "Manage filter rules")
-            .append_item(ID_TAG_MGR, "&Tag Manager", "Manage tags")
-            .append_item(ID_SIG_MGR, "&Signature Manager", "Manage signatures")
-            .append_separator()
-            .append_item(ID_FLUSH_OUTBOX, "Flush &Outbox", "Send all queued messages now")
-            .append_separator()
-            .append_item(ID_SETTINGS, "&Settings\tCtrl+,", "Application preferences")
-            .build();
-        let help = Menu::builder()
-            .append_item(ID_ABOUT, "&About\tF1", "About Wixen Mail")
-            .build();
-
-        MenuBar::builder()
-            .append(file, "&File")
-            .append(edit, "&Edit")
-            .append(view, "&View")
-            .append(message, "&Message")
-            .append(tools, "&Tools")
-            .append(help, "&Help")
-            .build()
-    }
 }
 
 // ── Free Functions (avoid monomorphization bloat from Self:: methods) ────────
 
+/// The window's message cache, shared with background tasks. `None` when
+/// the cache could not be opened.
+trait SharedCache {
+    /// `f` on a read-only connection, so it doesn't wait for a write in
+    /// progress; `None` without a cache
+    fn with_reader<T>(&self, f: impl FnOnce(&MessageCache) -> Option<T>) -> Option<T>;
+    /// `f` on the writer connection, after any write in progress
+    fn with_writer<T>(&self, f: impl FnOnce(&MessageCache) -> Option<T>) -> Option<T>;
+}
+
+impl SharedCache for Option<CachePool> {
+    fn with_reader<T>(&self, f: impl FnOnce(&MessageCache) -> Option<T>) -> Option<T> {
+        self.as_ref()?.read(|c| Ok(f(c))).ok()?
+    }
+
+    fn with_writer<T>(&self, f: impl FnOnce(&MessageCache) -> Option<T>) -> Option<T> {
+        self.as_ref()?.write(|c| Ok(f(c))).ok()?
+    }
+}
+
 /// Send a simple status update through the async channel.
 fn send_status(tx: &Sender<UIUpdate>, rt: &Arc<Runtime>, msg: &str) {
     let tx = tx.clone();
@@ -483,52 +1647,4666 @@ fn send_status(tx: &Sender<UIUpdate>, rt: &Arc<Runtime>, msg: &str) {
     rt.spawn(async move { let _ = tx.send(UIUpdate::StatusUpdated(msg)).await; });
 }
 
+/// Error text for the status bar and screen reader, followed by what the
+/// user can do about it when the failure is one we recognise.
+fn describe_error(context: &str, error: &crate::common::Error) -> String {
+    match error.troubleshooting() {
+        Some(tip) => format!("{}: {} {}", context, error, tip),
+        None => format!("{}: {}", context, error),
+    }
+}
+
+/// What is wrong with something the user typed: the bare problem for
+/// invalid or unreadable input, the full error otherwise
+fn input_problem(error: crate::common::Error) -> String {
+    match error {
+        crate::common::Error::Config(problem) | crate::common::Error::Parse(problem) => problem,
+        e => e.to_string(),
+    }
+}
+
 /// Extract selected message info for reply/forward.
-fn msg_info(state: &Arc<StdMutex<WxUIState>>) -> (String, String, String) {
+fn msg_info(state: &Arc<StdMutex<WxUIState>>) -> (String, String) {
+    state.lock().map(|s| {
+        s.selected_message_index
+            .and_then(|i| s.messages.get(i))
+            .map(|m| (m.from.clone(), m.subject.clone()))
+            .unwrap_or_default()
+    }).unwrap_or_default()
+}
+
+/// Plain text of the selected message, from the cache or, when it hasn't
+/// been downloaded, fetched from the server before the composer opens.
+fn selected_body_text(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    rt: &Arc<Runtime>,
+) -> String {
+    let (item, folder, offline) = {
+        let s = state.lock().unwrap();
+        let item = s.selected_message_index.and_then(|i| s.messages.get(i)).cloned();
+        (item, s.mailbox(), s.offline_mode)
+    };
+    let Some(item) = item else { return String::new() };
+    let cached = cache.with_reader(|c| {
+        let folder_id = c.get_message(item.message_id).ok()??.folder_id;
+        c.get_message_body(folder_id, item.uid).ok().flatten()
+    });
+    let body = match (cached, message_controller(state, controllers, &item)) {
+        (Some(body), _) => body,
+        (None, Some(ctrl)) if !offline => rt
+            .block_on(async { ctrl.lock().await.fetch_message_body(&folder, item.uid).await })
+            .unwrap_or_else(|e| {
+                tracing::warn!("Could not fetch message to quote: {}", e);
+                String::new()
+            }),
+        _ => state.lock().map(|s| s.message_preview.clone()).unwrap_or_default(),
+    };
+    if looks_like_html(&body) {
+        HtmlRenderer::new().render_for_accessibility(&body).accessible_text
+    } else {
+        body
+    }
+}
+
+/// Initial reply body: the default signature above the quoted original.
+fn reply_quote(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    rt: &Arc<Runtime>,
+) -> String {
+    let (from, date, account_id) = state.lock().map(|s| {
+        let (from, date) = s.selected_message_index
+            .and_then(|i| s.messages.get(i))
+            .map(|m| (m.from.clone(), m.date.clone()))
+            .unwrap_or_default();
+        (from, date, s.active_account_id.clone().unwrap_or_default())
+    }).unwrap_or_default();
+    let body = selected_body_text(state, cache, controllers, rt);
+    let identity_signature = reply_identity(state, cache).and_then(|i| i.signature_id);
+    let signature = cache.with_reader(|c| {
+        let signature = match identity_signature {
+            Some(id) => c.get_signature(&id).ok().flatten(),
+            None => c.get_default_signature(&account_id).ok().flatten(),
+        };
+        signature.map(|s| s.content_plain)
+    });
+    reply_body(&quote_original(&body, &date, &from, false), signature.as_deref())
+}
+
+/// Reply-all recipients and subject for the selected message. The user's
+/// own addresses, identities included, are left out.
+fn reply_all_info(state: &Arc<StdMutex<WxUIState>>, cache: &Option<CachePool>) -> (String, String, String) {
+    let aliases: Vec<String> = account_identities(state, cache).into_iter().map(|i| i.from_email).collect();
     state.lock().map(|s| {
+        let own: Vec<String> = s.accounts.iter().map(|a| a.email.clone()).chain(aliases).collect();
         s.selected_message_index
             .and_then(|i| s.messages.get(i))
-            .map(|m| (m.from.clone(), m.subject.clone(), s.message_preview.clone()))
+            .map(|m| {
+                let (to, cc) = reply_all_recipients(&m.from, None, &m.to, &m.cc, &own);
+                (to, cc, m.subject.clone())
+            })
             .unwrap_or_default()
     }).unwrap_or_default()
 }
 
-/// Open the compose dialog and handle the result.
-fn open_compose(
+/// Open the compose dialog and handle the result.
+fn open_compose(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+    mode: ComposeMode,
+) {
+    let (senders, active, account_id) = from_choices(state, cache);
+    let recovered = match mode {
+        ComposeMode::New => offer_recovered_draft(frame, state),
+        _ => None,
+    };
+    // Replies go out from the identity the original was addressed to
+    let reply_from = match mode {
+        ComposeMode::Reply { .. } | ComposeMode::ReplyAll { .. } | ComposeMode::Unsubscribe(_) => reply_identity(state, cache),
+        _ => None,
+    };
+    let accounts = state.lock().map(|s| s.accounts.clone()).unwrap_or_default();
+    let recovered_from = recovered.as_ref().and_then(|draft| {
+        let index = accounts.iter().position(|a| a.id == draft.account_id)? as u32;
+        senders.iter().position(|s| s.account_index == index && s.identity_id.is_none())
+    });
+    let selected = reply_from
+        .and_then(|identity| senders.iter().position(|s| s.identity_id.as_ref() == Some(&identity.id)))
+        .or(recovered_from)
+        .map(|i| i as u32)
+        .unwrap_or(active);
+    let draft_id = recovered.as_ref().map(|d| d.id.clone()).unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let autosave_seconds = state.lock().map(|s| s.settings.autosave_seconds).unwrap_or(0);
+    let session = Rc::new(RefCell::new(DraftSession::new(&draft_id, recovered.is_some(), autosave_seconds, chrono::Utc::now())));
+    let mode = match recovered {
+        Some(draft) => ComposeMode::Draft(CompositionData {
+            to: draft.to_addr,
+            cc: draft.cc.unwrap_or_default(),
+            bcc: draft.bcc.unwrap_or_default(),
+            subject: draft.subject,
+            body: draft.body,
+            ..CompositionData::default()
+        }),
+        None => mode,
+    };
+
+    let templates = cache.with_reader(|c| c.get_templates_for_account(&account_id).ok())
+        .unwrap_or_default();
+    let suggest: wx_compose::SuggestFn = Rc::new({
+        let cache = cache.clone();
+        move |query: &str| {
+            cache.with_reader(|c| address_suggestions(c, &account_id, query, ADDRESS_SUGGESTIONS).ok())
+                .unwrap_or_default()
+        }
+    });
+    // The message is autosaved under `draft_id`, marked open so a crash
+    // leaves it for recovery
+    let composed = {
+        let accounts = accounts.clone();
+        let draft_id = draft_id.clone();
+        move |c: &MessageCache, data: &wx_compose::ComposeData| {
+            let account = data.account_index.and_then(|i| accounts.get(i as usize))?;
+            Some(composed_draft(c, &draft_id, &account.id, data.clone()))
+        }
+    };
+    let autosave = Rc::new({
+        let cache = cache.clone();
+        let session = session.clone();
+        let composed = composed.clone();
+        move |data: &wx_compose::ComposeData| {
+            let now = chrono::Utc::now();
+            if !session.borrow().should_auto_save(now) {
+                return;
+            }
+            let saved = cache.with_writer(|c| {
+                let draft = composed(c, data)?;
+                Some(session.borrow_mut().autosave(c, &draft, now))
+            });
+            if let Some(Err(e)) = saved {
+                tracing::warn!("Autosave failed: {}", e);
+            }
+        }
+    });
+    let revisions = Rc::new({
+        let cache = cache.clone();
+        let draft_id = draft_id.clone();
+        move || {
+            cache.with_reader(|c| c.list_draft_revisions(&draft_id).ok())
+                .unwrap_or_default()
+        }
+    });
+    let restore = Rc::new({
+        let cache = cache.clone();
+        let session = session.clone();
+        move |revision_id: i64, data: &wx_compose::ComposeData| {
+            let restored = cache.with_writer(|c| {
+                let current = composed(c, data)?;
+                Some(session.borrow_mut().restore(c, &current, revision_id, chrono::Utc::now()))
+            });
+            match restored {
+                Some(Ok(_)) => true,
+                Some(Err(e)) => {
+                    tracing::warn!("Could not restore draft version: {}", e);
+                    false
+                }
+                None => false,
+            }
+        }
+    });
+    let hooks = wx_compose::ComposeHooks { suggest, autosave, revisions, restore };
+    let checks = state.lock().map(|s| wx_compose::SendChecks {
+        preview: s.settings.preview_before_send,
+        reply_all_warning: s.settings.reply_all_warning_recipients as usize,
+    }).unwrap_or_default();
+    // The composer is closed: a message that went or was discarded needs
+    // no draft, unless it was saved on purpose or was a draft already
+    let finish = {
+        let state = state.clone();
+        let cache = cache.clone();
+        let controllers = controllers.clone();
+        let tx = tx.clone();
+        let rt = rt.clone();
+        move |result: ComposeResult| {
+            let close_draft = |sent: bool| {
+                let closed = cache.with_writer(|c| Some(session.borrow_mut().close(c, sent)));
+                if let Some(Err(e)) = closed {
+                    tracing::warn!("Could not close draft {}: {}", draft_id, e);
+                }
+            };
+            match result {
+                ComposeResult::Send(data) => {
+                    close_draft(true);
+                    send_composed(&state, &cache, &controllers, &tx, &rt, data);
+                }
+                ComposeResult::SaveDraft(data) => {
+                    save_composed_draft(&state, &cache, &controllers, &tx, &rt, &draft_id, data);
+                    session.borrow_mut().keep();
+                    close_draft(false);
+                }
+                ComposeResult::Cancelled => close_draft(false),
+            }
+        }
+    };
+    // In a window of its own the composer keeps its draft and autosave
+    // until it closes, however many others are open
+    if state.lock().map(|s| s.settings.compose_in_window).unwrap_or(false) {
+        wx_compose::open_compose_window(frame, mode, &senders, selected, checks, hooks, &templates, finish);
+    } else {
+        finish(wx_compose::show_compose_dialog_with_options(frame, mode, &senders, selected, checks, hooks, &templates));
+    }
+}
+
+/// Offer back a draft left open by a crash; it's taken off the list
+/// either way, staying an ordinary draft if declined.
+fn offer_recovered_draft(frame: &Frame, state: &Arc<StdMutex<WxUIState>>) -> Option<CachedDraft> {
+    let draft = state.lock().ok().and_then(|mut s| (!s.recovered_drafts.is_empty()).then(|| s.recovered_drafts.remove(0)))?;
+    let subject = if draft.subject.trim().is_empty() { tr("compose.no_subject") } else { draft.subject.clone() };
+    let answer = MessageDialog::builder(frame, &trf("compose.recover_draft", &[&subject]), &tr("compose.recover_draft.title"))
+        .with_style(MessageDialogStyle::YesNo | MessageDialogStyle::IconQuestion)
+        .build()
+        .show_modal();
+    (answer == ID_YES).then_some(draft)
+}
+
+/// From choices for the compose window: each account's own address
+/// followed by its identities. Also returns the index of the active
+/// account's own address and the active account id.
+fn from_choices(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+) -> (Vec<FromChoice>, u32, String) {
+    let (accounts, active_id) = state.lock()
+        .map(|s| (s.accounts.clone(), s.active_account_id.clone().unwrap_or_default()))
+        .unwrap_or_default();
+    let mut senders = Vec::new();
+    let mut active = 0;
+    for (index, account) in accounts.iter().enumerate() {
+        if account.id == active_id {
+            active = senders.len() as u32;
+        }
+        senders.push(FromChoice {
+            account_index: index as u32,
+            identity_id: None,
+            email: account.email.clone(),
+            label: account.email.clone(),
+        });
+        let identities = cache.with_reader(|c| c.get_identities_for_account(&account.id).ok())
+            .unwrap_or_default();
+        senders.extend(identities.into_iter().map(|i| FromChoice {
+            account_index: index as u32,
+            label: if i.from_name.trim().is_empty() { i.from_email.clone() } else { format!("{} <{}>", i.from_name, i.from_email) },
+            email: i.from_email,
+            identity_id: Some(i.id),
+        }));
+    }
+    (senders, active, active_id)
+}
+
+/// Identities of the active account
+fn account_identities(state: &Arc<StdMutex<WxUIState>>, cache: &Option<CachePool>) -> Vec<Identity> {
+    let account_id = state.lock().ok().and_then(|s| s.active_account_id.clone()).unwrap_or_default();
+    cache.with_reader(|c| c.get_identities_for_account(&account_id).ok())
+        .unwrap_or_default()
+}
+
+/// The identity a reply to the selected message should be sent from
+fn reply_identity(state: &Arc<StdMutex<WxUIState>>, cache: &Option<CachePool>) -> Option<Identity> {
+    let (to, cc) = state.lock().ok().and_then(|s| {
+        let m = s.messages.get(s.selected_message_index?)?;
+        Some((m.to.clone(), m.cc.clone()))
+    })?;
+    let identities = account_identities(state, cache);
+    identity_for_reply(&identities, &to, &cc).cloned()
+}
+
+/// Split a recipient field on commas/semicolons.
+fn split_addresses(field: &str) -> Vec<String> {
+    field
+        .split([',', ';'])
+        .map(str::trim)
+        .filter(|a| !a.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// A request through `account`'s SMTP server, sent as `identity` when one
+/// was chosen, with no recipients or content yet
+fn send_request_as(account: &Account, identity: Option<Identity>, timeouts: NetworkTimeouts) -> SendEmailRequest {
+    let base = SendEmailRequest::for_account(account, timeouts);
+    let Some(identity) = identity else { return base };
+    SendEmailRequest {
+        from: Some(identity.from_email),
+        from_name: Some(identity.from_name).filter(|n| !n.trim().is_empty()),
+        reply_to: identity.reply_to,
+        // An identity's bounce address wins over the account's
+        envelope_from: identity.envelope_from.or(base.envelope_from.clone()),
+        ..base
+    }
+}
+
+/// Send a composed message, queueing it instead while offline.
+///
+/// After a successful send the exact MIME is appended to the account's Sent
+/// folder (unless the provider files sent mail itself) and cached locally;
+/// if APPEND fails the copy is kept in the local Sent folder only.
+fn send_composed(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+    data: wx_compose::ComposeData,
+) {
+    let (account, offline, timeouts, shutdown) = {
+        let s = state.lock().unwrap();
+        let account = data.account_index
+            .and_then(|i| s.accounts.get(i as usize))
+            .or_else(|| s.accounts.iter().find(|a| Some(&a.id) == s.active_account_id.as_ref()))
+            .cloned();
+        (account, s.offline_mode, s.settings.network_timeouts(), s.shutdown.clone())
+    };
+    let Some(account) = account else {
+        send_status(tx, rt, &tr("status.add_an_account_before_sending_mail"));
+        return;
+    };
+
+    if offline {
+        let queued = QueuedOutboxMessage {
+            id: uuid::Uuid::new_v4().to_string(),
+            account_id: account.id.clone(),
+            to_addr: data.to.clone(),
+            cc: data.cc.clone(),
+            bcc: data.bcc.clone(),
+            identity_id: data.identity_id.clone(),
+            subject: data.subject.clone(),
+            body: data.body.clone(),
+            request_receipt: data.request_receipt,
+            priority: data.priority,
+            attempt_count: 0,
+            last_error: None,
+            created_at: chrono::Utc::now().to_rfc3339(),
+        };
+        let result = cache.with_writer(|c| Some(c.queue_outbox_message(&queued)));
+        let status = match result {
+            Some(Ok(())) => "Offline: message queued in the outbox".to_string(),
+            Some(Err(e)) => format!("Could not queue message: {}", e),
+            None => "Message cache is not available".to_string(),
+        };
+        send_status(tx, rt, &status);
+        return;
+    }
+
+    let identity = data.identity_id.as_ref().and_then(|id| {
+        cache.with_reader(|c| c.get_identity(id).ok().flatten())
+    });
+    let req = SendEmailRequest {
+        to: split_addresses(&data.to),
+        cc: split_addresses(&data.cc),
+        bcc: split_addresses(&data.bcc),
+        subject: data.subject.clone(),
+        body: data.body.clone(),
+        request_receipt: data.request_receipt,
+        priority: data.priority,
+        ..send_request_as(&account, identity, timeouts)
+    };
+    // Quitting waits for the send and the Sent copy
+    let Some(working) = shutdown.guard() else {
+        return;
+    };
+    let ctrl = controllers.get(&account.id).cloned();
+    let state = state.clone();
+    let cache = cache.clone();
+    let tx = tx.clone();
+    rt.spawn(async move {
+        let _working = working;
+        let _ = tx.send(UIUpdate::StatusUpdated(format!("Sending to {}...", data.to))).await;
+        let sender = ctrl.clone().unwrap_or_else(|| Arc::new(TokioMutex::new(MailController::new())));
+        let raw = match sender.lock().await.send_email(&req).await {
+            Ok(raw) => raw,
+            Err(e) => {
+                if let (crate::common::Error::Security(_), Some(pinned)) = (&e, &req.pinned_fingerprint) {
+                    report_changed_certificate(&req, &account.id, pinned, &tx).await;
+                }
+                let _ = tx.send(UIUpdate::ErrorOccurred(describe_error("Send failed", &e))).await;
+                return;
+            }
+        };
+        let _ = tx.send(UIUpdate::EmailSent).await;
+        if account.pin_certificates && account.smtp_use_tls && req.pinned_fingerprint.is_none() {
+            pin_smtp_certificate(&state, &cache, &account.id, &req).await;
+        }
+
+        if !account.save_sent_copy {
+            return;
+        }
+        let folder = match ctrl {
+            Some(ctrl) => ctrl.lock().await.append_to_sent(&raw).await,
+            None => Err(crate::common::Error::Other("not connected".into())),
+        };
+        let folder = folder.unwrap_or_else(|e| {
+            tracing::warn!("Sent copy kept locally only: {}", e);
+            "Sent".to_string()
+        });
+        if let Some(Err(e)) = cache.with_writer(|c| Some(Storage::store_sent_message(c, &account.id, &folder, &raw))) {
+            tracing::warn!("Failed to cache sent message: {}", e);
+        }
+    });
+}
+
+/// Trust on first use: remember the certificate of an SMTP server that
+/// just accepted a message.
+async fn pin_smtp_certificate(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    account_id: &str,
+    req: &SendEmailRequest,
+) {
+    let fingerprint = match certificate_pin::server_fingerprint(&req.server, req.port, req.timeouts).await {
+        Ok(fingerprint) => fingerprint,
+        Err(e) => {
+            tracing::warn!("Could not pin certificate of {}: {}", req.server, e);
+            return;
+        }
+    };
+    let account = state.lock().ok().and_then(|mut s| {
+        let account = s.accounts.iter_mut().find(|a| a.id == account_id)?;
+        account.smtp_pinned_fingerprint = Some(fingerprint.clone());
+        Some(account.clone())
+    });
+    if let Some(Err(e)) = account.and_then(|account| cache.with_writer(|c| Some(c.save_account(&account)))) {
+        tracing::warn!("Failed to save pinned certificate: {}", e);
+    }
+    tracing::info!("Pinned certificate of {}: {}", req.server, fingerprint);
+}
+
+/// After a send was refused for security reasons, check whether the SMTP
+/// server's certificate no longer matches its pin and offer to trust it.
+async fn report_changed_certificate(req: &SendEmailRequest, account_id: &str, pinned: &str, tx: &Sender<UIUpdate>) {
+    if let Some(change) = certificate_pin::changed_certificate(account_id, &req.server, req.port, req.timeouts, pinned).await {
+        let _ = tx.send(UIUpdate::CertificateChanged(change)).await;
+    }
+}
+
+/// Replace a changed certificate's pin after the user confirms the
+/// provider renewed it.
+fn trust_new_certificate(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let Some(change) = state.lock().ok().and_then(|s| s.certificate_change.clone()) else {
+        send_status(tx, rt, &tr("status.no_server_certificate_has_changed"));
+        return;
+    };
+    let text = format!(
+        "The certificate for {} has changed.\n\nPinned: {}\nNow presented: {}\n\n\
+         Trust the new certificate only if your provider announced a renewal or you have \
+         checked the fingerprint with them. Trust it?",
+        change.server, change.pinned, change.presented
+    );
+    let dlg = MessageDialog::builder(frame, &text, "Trust New Server Certificate")
+        .with_style(MessageDialogStyle::YesNo | MessageDialogStyle::IconWarning)
+        .build();
+    if dlg.show_modal() != ID_YES {
+        return;
+    }
+    let account = state.lock().ok().and_then(|mut s| {
+        s.certificate_change = None;
+        let account = s.accounts.iter_mut().find(|a| a.id == change.account_id)?;
+        if account.smtp_server == change.server {
+            account.smtp_pinned_fingerprint = Some(change.presented.clone());
+        }
+        if account.imap_server == change.server {
+            account.imap_pinned_fingerprint = Some(change.presented.clone());
+        }
+        Some(account.clone())
+    });
+    let saved = account
+        .and_then(|account| cache.with_writer(|c| Some(c.save_account(&account))))
+        .unwrap_or_else(|| Err(crate::common::Error::NotFound("account not found".into())));
+    match saved {
+        Ok(()) => send_status(tx, rt, &format!("Trusting the new certificate for {}", change.server)),
+        Err(e) => send_status(tx, rt, &format!("Could not save the new certificate: {}", e)),
+    }
+}
+
+/// Save a composed message as a local draft, uploading it to the server's
+/// Drafts folder when the account syncs drafts.
+fn save_composed_draft(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+    draft_id: &str,
+    data: wx_compose::ComposeData,
+) {
+    let (account, offline) = {
+        let s = state.lock().unwrap();
+        let account = data.account_index
+            .and_then(|i| s.accounts.get(i as usize))
+            .or_else(|| s.accounts.iter().find(|a| Some(&a.id) == s.active_account_id.as_ref()))
+            .cloned();
+        (account, s.offline_mode)
+    };
+    let Some(account) = account else {
+        send_status(tx, rt, &tr("status.add_an_account_before_saving_drafts"));
+        return;
+    };
+    let saved = cache.with_writer(|c| {
+        let draft = composed_draft(c, draft_id, &account.id, data);
+        Some(c.save_draft(&draft).map(|_| draft))
+    });
+    let draft = match saved {
+        Some(Ok(draft)) => {
+            send_status(tx, rt, &tr("status.draft_saved"));
+            draft
+        }
+        Some(Err(e)) => {
+            send_status(tx, rt, &format!("Could not save draft: {}", e));
+            return;
+        }
+        None => {
+            send_status(tx, rt, &tr("status.message_cache_is_not_available"));
+            return;
+        }
+    };
+
+    let ctrl = controllers.get(&account.id).cloned();
+    if let (true, false, Some(ctrl)) = (account.sync_drafts, offline, ctrl) {
+        let cache = cache.clone();
+        rt.spawn(async move {
+            upload_draft(&ctrl, &cache, &draft, &account.email).await;
+        });
+    }
+}
+
+/// The draft `draft_id` holding `data`. A draft saved before keeps its
+/// creation time and server copy.
+fn composed_draft(cache: &MessageCache, draft_id: &str, account_id: &str, data: wx_compose::ComposeData) -> CachedDraft {
+    let now = chrono::Utc::now().to_rfc3339();
+    let saved = cache.load_draft(draft_id).ok().flatten();
+    CachedDraft {
+        id: draft_id.to_string(),
+        account_id: account_id.to_string(),
+        to_addr: data.to,
+        cc: Some(data.cc).filter(|c| !c.is_empty()),
+        bcc: Some(data.bcc).filter(|b| !b.is_empty()),
+        subject: data.subject,
+        body: data.body,
+        created_at: saved.as_ref().map(|d| d.created_at.clone()).unwrap_or_else(|| now.clone()),
+        updated_at: now,
+        server_uid: saved.and_then(|d| d.server_uid),
+    }
+}
+
+/// Upload one draft and record its server UID; failures leave it local.
+async fn upload_draft(
+    ctrl: &Arc<TokioMutex<MailController>>,
+    cache: &Option<CachePool>,
+    draft: &CachedDraft,
+    from: &str,
+) {
+    let raw = storage::draft_to_rfc5322(draft, from);
+    match ctrl.lock().await.save_draft_to_server(&draft.id, raw.as_bytes(), draft.server_uid).await {
+        Ok(uid) => {
+            if let Some(Err(e)) = cache.with_writer(|c| Some(c.set_draft_server_uid(&draft.id, uid))) {
+                tracing::warn!("Failed to record draft UID: {}", e);
+            }
+        }
+        Err(e) => tracing::warn!("Draft kept locally only: {}", e),
+    }
+}
+
+/// Reconcile local drafts with the server's Drafts folder, then upload
+/// the drafts that are newer locally.
+async fn sync_drafts(
+    ctrl: Arc<TokioMutex<MailController>>,
+    cache: Option<CachePool>,
+    account: Account,
+) {
+    let server = match ctrl.lock().await.fetch_server_drafts().await {
+        Ok(server) => server,
+        Err(e) => {
+            tracing::warn!("Draft sync skipped: {}", e);
+            return;
+        }
+    };
+    let server: Vec<CachedDraft> = server
+        .iter()
+        .filter_map(|(uid, raw)| storage::parse_draft(&account.id, *uid, raw.as_bytes()))
+        .collect();
+    let upload = match cache.with_writer(|c| Some(c.reconcile_drafts(&account.id, &server))) {
+        Some(Ok(upload)) => upload,
+        Some(Err(e)) => {
+            tracing::warn!("Draft reconcile failed: {}", e);
+            return;
+        }
+        None => return,
+    };
+    for draft in &upload {
+        upload_draft(&ctrl, &cache, draft, &account.email).await;
+    }
+}
+
+/// Controller for the active account, if one is connected.
+fn active_controller(
+    state: &Arc<StdMutex<WxUIState>>,
+    controllers: &HashMap<String, Arc<TokioMutex<MailController>>>,
+) -> Option<Arc<TokioMutex<MailController>>> {
+    let id = state.lock().ok()?.active_account_id.clone()?;
+    controllers.get(&id).cloned()
+}
+
+/// Shown when an action in All Inboxes would touch another account's mail
+const OTHER_ACCOUNT_SELECTED: &str = "Some selected messages belong to another account. Switch to that account to change them.";
+
+/// Controller for the account a listed message came from: its own account in
+/// the All Inboxes view, otherwise the active one.
+fn message_controller(
+    state: &Arc<StdMutex<WxUIState>>,
+    controllers: &HashMap<String, Arc<TokioMutex<MailController>>>,
+    item: &MessageItem,
+) -> Option<Arc<TokioMutex<MailController>>> {
+    match &item.account_id {
+        Some(id) => controllers.get(id).cloned(),
+        None => active_controller(state, controllers),
+    }
+}
+
+/// Ask when the selected messages should reappear, then move them to the
+/// Snoozed folder until then.
+fn snooze_selected(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let (items, folder, account_id) = {
+        let s = state.lock().unwrap();
+        if !s.selection_in_active_account() {
+            drop(s);
+            send_status(tx, rt, OTHER_ACCOUNT_SELECTED);
+            return;
+        }
+        let mut items: Vec<MessageItem> = s.messages.iter().filter(|m| s.selected_uids.contains(&m.uid)).cloned().collect();
+        if items.is_empty() {
+            items.extend(s.selected_message_index.and_then(|i| s.messages.get(i)).cloned());
+        }
+        (items, s.mailbox(), s.active_account_id.clone().unwrap_or_default())
+    };
+    if items.is_empty() {
+        send_status(tx, rt, &tr("status.no_messages_selected"));
+        return;
+    }
+    if folder == SNOOZED_FOLDER {
+        send_status(tx, rt, &tr("status.these_messages_are_already_snoozed"));
+        return;
+    }
+
+    let mut choices: Vec<String> = SnoozePreset::ALL.iter().map(|p| p.label().to_string()).collect();
+    choices.push("Pick a date and time...".to_string());
+    let Some(choice) = show_choice_dialog(frame, "Snooze", "Snooze until:", &choices) else { return };
+    let now = state.lock().unwrap().clock.now().with_timezone(&chrono::Local);
+    let wake_at = match SnoozePreset::ALL.get(choice) {
+        Some(preset) => preset.wake_at(now),
+        None => {
+            let suggestion = SnoozePreset::Tomorrow.wake_at(now).format("%Y-%m-%d %H:%M").to_string();
+            let label = "Wake up at (YYYY-MM-DD HH:MM):";
+            let Some(text) = show_text_dialog(frame, "Snooze Until", label, &suggestion) else { return };
+            match parse_wake_time(&text, now) {
+                Ok(wake_at) => wake_at,
+                Err(e) => {
+                    send_status(tx, rt, &input_problem(e));
+                    return;
+                }
+            }
+        }
+    };
+
+    let remaining = {
+        let mut s = state.lock().unwrap();
+        let uids: HashSet<u32> = items.iter().map(|m| m.uid).collect();
+        s.messages.retain(|m| !uids.contains(&m.uid));
+        s.selected_uids.clear();
+        s.selected_message_index = None;
+        s.messages.clone()
+    };
+    let ctrl = active_controller(state, controllers).filter(|_| !state.lock().map(|s| s.offline_mode).unwrap_or(false));
+    let state = state.clone();
+    let cache = cache.clone();
+    let tx = tx.clone();
+    let rt2 = rt.clone();
+    rt.spawn(async move {
+        let _ = tx.send(UIUpdate::MessagesLoaded(remaining)).await;
+        let uids: Vec<u32> = items.iter().map(|m| m.uid).collect();
+        if let Some(ctrl) = ctrl {
+            if let Err(e) = ctrl.lock().await.snooze_messages(&folder, &uids).await {
+                let restored = {
+                    let mut s = state.lock().unwrap();
+                    s.messages.extend(items);
+                    let order = s.sort_order;
+                    sort_messages(&mut s.messages, order);
+                    s.messages.clone()
+                };
+                let _ = tx.send(UIUpdate::MessagesLoaded(restored)).await;
+                let _ = tx.send(UIUpdate::ErrorOccurred(format!("Could not snooze messages: {}", e))).await;
+                return;
+            }
+        }
+        let saved = cache.with_writer(|c| Some({
+            c.in_transaction(|c| {
+                let snoozed = c.ensure_folder(&account_id, SNOOZED_FOLDER)?;
+                for m in &items {
+                    c.snooze_message(m.message_id, snoozed.id, wake_at.with_timezone(&chrono::Utc))?;
+                }
+                Ok(())
+            })
+        }));
+        if let Some(Err(e)) = saved {
+            tracing::error!("Failed to record snooze: {}", e);
+        }
+        refresh_folder_tree(&state, &cache, &tx, &rt2);
+        let noun = if items.len() == 1 { "message" } else { "messages" };
+        let msg = format!("{} {} snoozed until {}", items.len(), noun, wake_at.format("%a %e %b %H:%M"));
+        let _ = tx.send(UIUpdate::StatusUpdated(msg)).await;
+    });
+}
+
+/// Flag the selected messages for follow-up with a chosen due date, or
+/// clear their flags. The star is left alone.
+fn follow_up_selected(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let (items, mailbox, viewing_follow_up) = {
+        let s = state.lock().unwrap();
+        if !s.selection_in_active_account() {
+            drop(s);
+            send_status(tx, rt, OTHER_ACCOUNT_SELECTED);
+            return;
+        }
+        let mut items: Vec<MessageItem> = s.messages.iter().filter(|m| s.selected_uids.contains(&m.uid)).cloned().collect();
+        if items.is_empty() {
+            items.extend(s.selected_message_index.and_then(|i| s.messages.get(i)).cloned());
+        }
+        (items, s.mailbox(), s.viewing_follow_up())
+    };
+    if items.is_empty() {
+        send_status(tx, rt, &tr("status.no_messages_selected"));
+        return;
+    }
+
+    let mut choices: Vec<String> = FollowUpDue::ALL.iter().map(|d| d.label().to_string()).collect();
+    choices.push("Pick a due date...".to_string());
+    if items.iter().any(|m| m.follow_up.is_some()) {
+        choices.push("Clear follow-up flag".to_string());
+    }
+    let Some(choice) = show_choice_dialog(frame, "Follow-up", "Due:", &choices) else { return };
+    let now = state.lock().unwrap().clock.now().with_timezone(&chrono::Local);
+    let flag = match FollowUpDue::ALL.get(choice) {
+        Some(preset) => Some(FollowUp { due: preset.due_at(now).map(|d| d.with_timezone(&chrono::Utc)) }),
+        None if choice == FollowUpDue::ALL.len() => {
+            let suggestion = now.date_naive().succ_opt().unwrap_or(now.date_naive()).format("%Y-%m-%d").to_string();
+            let label = "Due by (YYYY-MM-DD or YYYY-MM-DD HH:MM):";
+            let Some(text) = show_text_dialog(frame, "Follow-up Due Date", label, &suggestion) else { return };
+            match parse_due_date(&text, now) {
+                Ok(due) => Some(FollowUp { due: Some(due.with_timezone(&chrono::Utc)) }),
+                Err(e) => {
+                    send_status(tx, rt, &input_problem(e));
+                    return;
+                }
+            }
+        }
+        None => None,
+    };
+
+    let updated = {
+        let mut s = state.lock().unwrap();
+        let ids: HashSet<i64> = items.iter().map(|m| m.message_id).collect();
+        if flag.is_none() && viewing_follow_up {
+            s.messages.retain(|m| !ids.contains(&m.message_id));
+            s.selected_uids.clear();
+            s.selected_message_index = None;
+        }
+        for m in s.messages.iter_mut().filter(|m| ids.contains(&m.message_id)) {
+            m.follow_up = flag;
+        }
+        s.messages.clone()
+    };
+    let saved = cache.with_writer(|c| Some({
+        c.in_transaction(|c| {
+            for m in &items {
+                match flag {
+                    Some(flag) => c.set_follow_up(m.message_id, flag.due)?,
+                    None => c.clear_follow_up(m.message_id)?,
+                }
+            }
+            Ok(())
+        })
+    }));
+    if let Some(Err(e)) = saved {
+        tracing::error!("Failed to save follow-up flag: {}", e);
+    }
+    refresh_folder_tree(state, cache, tx, rt);
+
+    let noun = if items.len() == 1 { "message" } else { "messages" };
+    let status = match flag.map(|f| f.due) {
+        Some(Some(due)) => format!("{} {} flagged for follow-up, due {}", items.len(), noun, due.with_timezone(&chrono::Local).format("%a %e %b %H:%M")),
+        Some(None) => format!("{} {} flagged for follow-up", items.len(), noun),
+        None => format!("Follow-up flag cleared on {} {}", items.len(), noun),
+    };
+    let mut by_folder: HashMap<String, Vec<u32>> = HashMap::new();
+    for m in &items {
+        by_folder.entry(m.folder.clone().unwrap_or_else(|| mailbox.clone())).or_default().push(m.uid);
+    }
+    let ctrl = active_controller(state, controllers).filter(|_| !state.lock().map(|s| s.offline_mode).unwrap_or(false));
+    let tx = tx.clone();
+    rt.spawn(async move {
+        let _ = tx.send(UIUpdate::MessagesLoaded(updated)).await;
+        if let Some(ctrl) = ctrl {
+            for (folder, uids) in by_folder {
+                if let Err(e) = ctrl.lock().await.set_follow_up(&folder, &uids, flag.is_some()).await {
+                    let _ = tx.send(UIUpdate::ErrorOccurred(format!("Could not update follow-up flag on server: {}", e))).await;
+                }
+            }
+        }
+        let _ = tx.send(UIUpdate::StatusUpdated(status)).await;
+    });
+}
+
+/// Return snoozed messages whose time has come to their folders, unread,
+/// on the server too when online.
+async fn wake_snoozed(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let woken = cache.with_writer(|c| Some(c.wake_due_messages(c.now())));
+    let woken = match woken {
+        Some(Ok(woken)) if !woken.is_empty() => woken,
+        Some(Err(e)) => {
+            tracing::warn!("Could not wake snoozed messages: {}", e);
+            return;
+        }
+        _ => return,
+    };
+
+    let (offline, active_account, selected_folder) = state
+        .lock()
+        .map(|s| (s.offline_mode, s.active_account_id.clone(), s.selected_folder.clone()))
+        .unwrap_or((true, None, None));
+    if !offline {
+        let mut moves: HashMap<(&str, &str, &str), Vec<u32>> = HashMap::new();
+        for m in &woken {
+            moves.entry((m.account_id.as_str(), m.snoozed_folder.as_str(), m.original_folder.as_str())).or_default().push(m.uid);
+        }
+        for ((account_id, from, to), uids) in moves {
+            let Some(ctrl) = controllers.get(account_id) else { continue };
+            if let Err(e) = ctrl.lock().await.wake_messages(from, &uids, to).await {
+                tracing::warn!("Could not move woken messages back to {}: {}", to, e);
+            }
+        }
+    }
+
+    refresh_folder_tree(state, cache, tx, rt);
+    let shown = woken.iter().any(|m| {
+        active_account.as_deref() == Some(m.account_id.as_str())
+            && [&m.snoozed_folder, &m.original_folder].iter().any(|f| selected_folder.as_deref() == Some(f.as_str()))
+    });
+    if let Some(folder) = selected_folder.filter(|_| shown) {
+        load_cached_folder(state, cache, tx, rt, &folder);
+    }
+    let _ = tx.send(UIUpdate::SnoozedMessagesWoke(woken.len())).await;
+}
+
+/// Where `dispose_selected` sends messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Disposal {
+    Trash,
+    Archive,
+    /// Marked as spam: to the Junk folder
+    Junk,
+    /// Marked as not spam: back to the Inbox
+    NotJunk,
+}
+
+/// Move the selected messages to Trash (or remove them for good, per the
+/// account's delete mode), to Archive, or to Junk or the Inbox when marked
+/// as spam or not spam.
+///
+/// The list updates at once. The server picks the destination folder, then
+/// the cache follows. Moves are recorded in the undo history.
+fn dispose_selected(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+    disposal: Disposal,
+) {
+    let (items, folder, account_id, mode, report) = {
+        let s = state.lock().unwrap();
+        let account = s.active_account_id.as_ref().and_then(|id| s.accounts.iter().find(|a| &a.id == id));
+        (
+            selected_items(&s),
+            s.mailbox(),
+            s.active_account_id.clone().unwrap_or_default(),
+            account.map(|a| a.effective_delete_mode()).unwrap_or_default(),
+            s.settings.report_junk,
+        )
+    };
+    if items.is_empty() {
+        send_status(tx, rt, &tr("status.no_messages_selected"));
+        return;
+    }
+    if items.iter().any(|m| m.account_id.is_some() && m.account_id.as_deref() != Some(account_id.as_str())) {
+        send_status(tx, rt, OTHER_ACCOUNT_SELECTED);
+        return;
+    }
+
+    let remaining = {
+        let mut s = state.lock().unwrap();
+        let uids: HashSet<u32> = items.iter().map(|m| m.uid).collect();
+        s.messages.retain(|m| !uids.contains(&m.uid));
+        s.selected_uids.clear();
+        s.selected_message_index = None;
+        s.messages.clone()
+    };
+
+    let ctrl = active_controller(state, controllers);
+    let state = state.clone();
+    let cache = cache.clone();
+    let tx = tx.clone();
+    rt.spawn(async move {
+        let _ = tx.send(UIUpdate::MessagesLoaded(remaining)).await;
+        let uids: Vec<u32> = items.iter().map(|m| m.uid).collect();
+        let moved = match (ctrl, disposal) {
+            (Some(ctrl), Disposal::Trash) => ctrl.lock().await.trash_messages(&folder, &uids, mode).await,
+            (Some(ctrl), Disposal::Archive) => ctrl.lock().await.archive_messages(&folder, &uids).await.map(Some),
+            // Offline: only the local cache changes
+            (None, Disposal::Trash) => Ok(Some(cached_special_folder(&cache, &account_id, SpecialFolder::Trash))
+                .filter(|trash| mode == DeleteMode::MoveToTrash && *trash != folder)
+                .map(MovedMessages::without_uids)),
+            (None, Disposal::Archive) => Ok(Some(MovedMessages::without_uids(
+                cached_special_folder(&cache, &account_id, SpecialFolder::Archive),
+            ))),
+            (Some(ctrl), Disposal::Junk) => ctrl.lock().await.mark_as_spam(&folder, &uids, report).await.map(Some),
+            (Some(ctrl), Disposal::NotJunk) => ctrl.lock().await.mark_not_spam(&folder, &uids, report).await.map(Some),
+            (None, Disposal::Junk) => Ok(Some(MovedMessages::without_uids(
+                cached_special_folder(&cache, &account_id, SpecialFolder::Junk),
+            ))),
+            (None, Disposal::NotJunk) => Ok(Some(MovedMessages::without_uids("INBOX".to_string()))),
+        };
+        let (target, moved_uids) = match moved {
+            Ok(moved) => moved.map(|m| (Some(m.folder), m.uids)).unwrap_or_default(),
+            Err(e) => {
+                let restored = {
+                    let mut s = state.lock().unwrap();
+                    s.messages.extend(items);
+                    let order = s.sort_order;
+                    sort_messages(&mut s.messages, order);
+                    s.messages.clone()
+                };
+                let _ = tx.send(UIUpdate::MessagesLoaded(restored)).await;
+                let _ = tx.send(UIUpdate::ErrorOccurred(format!("Could not remove messages: {}", e))).await;
+                return;
+            }
+        };
+
+        let recorded = cache.with_writer(|c| Some({
+            c.in_transaction(|c| {
+                let Some(target) = &target else {
+                    for m in &items {
+                        c.delete_message(m.message_id)?;
+                    }
+                    return Ok(Vec::new());
+                };
+                let from = c.ensure_folder(&account_id, &folder)?;
+                let to = c.ensure_folder(&account_id, target)?;
+                let mut undo = Vec::new();
+                for m in &items {
+                    let message_id_header = c.get_message(m.message_id)?.map(|cached| cached.message_id).unwrap_or_default();
+                    match disposal {
+                        Disposal::Trash => c.trash_message(m.message_id, to.id)?,
+                        Disposal::Archive | Disposal::Junk | Disposal::NotJunk => c.move_message(m.message_id, to.id)?,
+                    }
+                    undo.push(UndoableAction::Move {
+                        message_id: m.message_id,
+                        uid: m.uid,
+                        to_uid: moved_uids.get(&m.uid).copied(),
+                        message_id_header,
+                        from_folder_id: from.id,
+                        from_folder: folder.clone(),
+                        to_folder_id: to.id,
+                        to_folder: target.clone(),
+                    });
+                }
+                Ok(undo)
+            })
+        }));
+        match recorded {
+            Some(Ok(undo)) => {
+                if let Ok(mut s) = state.lock() {
+                    for action in undo {
+                        s.history.record(action);
+                    }
+                }
+            }
+            Some(Err(e)) => tracing::error!("Cache update failed: {}", e),
+            None => {}
+        }
+
+        let noun = if items.len() == 1 { "message" } else { "messages" };
+        let summary = match &target {
+            Some(target) if disposal == Disposal::Archive => format!("{} {} archived to {} (Ctrl+Z to undo)", items.len(), noun, target),
+            Some(target) if disposal == Disposal::Junk => format!("{} {} marked as spam and moved to {} (Ctrl+Z to undo)", items.len(), noun, target),
+            Some(target) if disposal == Disposal::NotJunk => format!("{} {} marked as not spam and moved to {} (Ctrl+Z to undo)", items.len(), noun, target),
+            Some(target) => format!("{} {} moved to {} (Ctrl+Z to undo)", items.len(), noun, target),
+            None => format!("{} {} permanently deleted", items.len(), noun),
+        };
+        let _ = tx.send(UIUpdate::FoldersLoaded(cached_folder_items(&state, &cache, &account_id))).await;
+        let _ = tx.send(UIUpdate::StatusUpdated(summary)).await;
+    });
+}
+
+/// The selected messages, or the one open in the preview if none are
+/// selected
+fn selected_items(s: &WxUIState) -> Vec<MessageItem> {
+    let mut items: Vec<MessageItem> = s.messages.iter().filter(|m| s.selected_uids.contains(&m.uid)).cloned().collect();
+    if items.is_empty() {
+        items.extend(s.selected_message_index.and_then(|i| s.messages.get(i)).cloned());
+    }
+    items
+}
+
+/// Mark the selected messages as spam, moving them to Junk, or as not
+/// spam, moving them to the Inbox. Senders of spam go on the junk sender
+/// list so their next messages go straight to Junk; marking one of their
+/// messages as not spam takes them off it.
+fn mark_spam(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+    spam: bool,
+) {
+    let (items, active, train) = {
+        let s = state.lock().unwrap();
+        (selected_items(&s), s.active_account_id.clone().unwrap_or_default(), s.settings.spam_filter)
+    };
+    let senders: HashSet<(String, String)> = items
+        .iter()
+        .filter_map(|m| {
+            let sender = EmailAddress::parse_list(&m.from).into_iter().next()?;
+            Some((m.account_id.clone().unwrap_or_else(|| active.clone()), sender.address.to_lowercase()))
+        })
+        .collect();
+    let updated = cache.with_writer(|c| {
+        Some(c.in_transaction(|c| {
+            for (account_id, address) in &senders {
+                if spam {
+                    c.add_sender(account_id, SenderList::Junk, address)?;
+                } else {
+                    c.remove_sender(account_id, SenderList::Junk, address)?;
+                }
+            }
+            Ok(())
+        }))
+    });
+    if let Some(Err(e)) = updated {
+        tracing::warn!("Could not update the junk sender list: {}", e);
+    }
+    if train {
+        let trained: Vec<(String, i64)> = items
+            .iter()
+            .map(|m| (m.account_id.clone().unwrap_or_else(|| active.clone()), m.message_id))
+            .collect();
+        let cache = cache.clone();
+        rt.spawn_blocking(move || train_spam_filter(&cache, &trained, spam));
+    }
+    dispose_selected(state, cache, controllers, tx, rt, if spam { Disposal::Junk } else { Disposal::NotJunk });
+}
+
+/// Teach each account's spam filter that cached messages (account, cache
+/// id) are spam or not. Slow for many messages, so never run on the UI
+/// thread.
+fn train_spam_filter(cache: &Option<CachePool>, messages: &[(String, i64)], spam: bool) {
+    cache.with_writer(|c| {
+        for (account_id, message_id) in messages {
+            let trained = c.get_message(*message_id).and_then(|m| match m {
+                Some(m) => SpamClassifier::new(c, account_id).train(&m, spam),
+                None => Ok(false),
+            });
+            if let Err(e) = trained {
+                tracing::warn!("Could not train the spam filter: {}", e);
+            }
+        }
+        Some(())
+    });
+}
+
+/// Move the selected Trash messages back to the folders they were deleted from.
+fn restore_selected(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let (items, folder, account_id) = {
+        let s = state.lock().unwrap();
+        let items: Vec<MessageItem> = s.messages.iter().filter(|m| s.selected_uids.contains(&m.uid)).cloned().collect();
+        (items, s.mailbox(), s.active_account_id.clone().unwrap_or_default())
+    };
+    if items.is_empty() {
+        send_status(tx, rt, &tr("status.no_messages_selected"));
+        return;
+    }
+
+    // Group by original folder; messages with no recorded origin go to INBOX
+    let mut destinations: HashMap<String, (i64, Vec<u32>)> = HashMap::new();
+    cache.with_writer(|c| {
+        for m in &items {
+            let restored = c.restore_from_trash(m.message_id).and_then(|origin| match origin {
+                Some(path) => Ok(path),
+                None => {
+                    let inbox = c.ensure_folder(&account_id, "INBOX")?;
+                    c.move_message(m.message_id, inbox.id)?;
+                    Ok(inbox.path)
+                }
+            });
+            match restored.and_then(|path| Ok((c.ensure_folder(&account_id, &path)?.id, path))) {
+                Ok((folder_id, path)) => destinations.entry(path).or_insert((folder_id, Vec::new())).1.push(m.uid),
+                Err(e) => tracing::error!("Restore failed in cache: {}", e),
+            }
+        }
+        Some(())
+    });
+
+    let remaining = {
+        let mut s = state.lock().unwrap();
+        let uids: HashSet<u32> = items.iter().map(|m| m.uid).collect();
+        s.messages.retain(|m| !uids.contains(&m.uid));
+        s.selected_uids.clear();
+        s.selected_message_index = None;
+        s.messages.clone()
+    };
+    refresh_folder_tree(state, cache, tx, rt);
+
+    let ctrl = active_controller(state, controllers);
+    let tx = tx.clone();
+    let count = items.len();
+    rt.spawn(async move {
+        if let Some(ctrl) = ctrl {
+            for (destination, (folder_id, uids)) in destinations {
+                let action = BulkAction::Move { folder_id, folder: destination };
+                if let Err(e) = ctrl.lock().await.apply_bulk(&folder, &uids, &action).await {
+                    let _ = tx.send(UIUpdate::ErrorOccurred(format!("Restore failed on server: {}", e))).await;
+                }
+            }
+        }
+        let _ = tx.send(UIUpdate::MessagesLoaded(remaining)).await;
+        let noun = if count == 1 { "message" } else { "messages" };
+        let _ = tx.send(UIUpdate::StatusUpdated(format!("{} {} restored", count, noun))).await;
+    });
+}
+
+/// The folder with `role` as last listed by the server, for when it can't be
+/// asked; the role's usual name if the folder list was never fetched.
+fn cached_special_folder(cache: &Option<CachePool>, account_id: &str, role: SpecialFolder) -> String {
+    cache.with_reader(|c| c.get_special_folder(account_id, role.folder_type()).ok().flatten())
+        .unwrap_or_else(|| role.default_name().to_string())
+}
+
+/// Permanently delete everything in the account's Trash folder.
+fn empty_trash(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let account_id = state.lock().ok().and_then(|s| s.active_account_id.clone()).unwrap_or_default();
+    let ctrl = active_controller(state, controllers);
+    let state = state.clone();
+    let cache = cache.clone();
+    let tx = tx.clone();
+    rt.spawn(async move {
+        let trash = match ctrl {
+            Some(ctrl) => match ctrl.lock().await.empty_trash().await {
+                Ok(trash) => trash,
+                Err(e) => {
+                    let _ = tx.send(UIUpdate::ErrorOccurred(format!("Could not empty Trash: {}", e))).await;
+                    return;
+                }
+            },
+            None => cached_special_folder(&cache, &account_id, SpecialFolder::Trash),
+        };
+        let removed = cache
+            .with_writer(|c| {
+                let folder = c.get_folder(&account_id, &trash).ok()??;
+                c.empty_folder(folder.id).ok()
+            })
+            .unwrap_or(0);
+        let viewing_trash = state.lock().map(|mut s| {
+            let viewing = s.selected_folder.as_deref() == Some(trash.as_str());
+            if viewing {
+                s.selected_uids.clear();
+                s.selected_message_index = None;
+            }
+            viewing
+        }).unwrap_or(false);
+        if viewing_trash {
+            let _ = tx.send(UIUpdate::MessagesLoaded(Vec::new())).await;
+        }
+        let _ = tx.send(UIUpdate::FoldersLoaded(cached_folder_items(&state, &cache, &account_id))).await;
+        let noun = if removed == 1 { "message" } else { "messages" };
+        let _ = tx.send(UIUpdate::StatusUpdated(format!("{} emptied ({} {} deleted)", trash, removed, noun))).await;
+    });
+}
+
+/// Apply a bulk action to every selected message: one cache transaction and
+/// one batched server command. Deletes, moves and tag removals are recorded
+/// in the undo history.
+fn run_bulk_action(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+    action: BulkAction,
+) {
+    let summary = action.clone();
+    run_bulk_action_then(state, cache, controllers, tx, rt, action, move |count| {
+        UIUpdate::StatusUpdated(summary.summary(count))
+    });
+}
+
+/// `run_bulk_action`, finishing with `done(count)` instead of the action's
+/// summary.
+fn run_bulk_action_then(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+    action: BulkAction,
+    done: impl FnOnce(usize) -> UIUpdate,
+) {
+    let (ids, uids, folder) = {
+        let s = state.lock().unwrap();
+        if !s.selection_in_active_account() {
+            drop(s);
+            send_status(tx, rt, OTHER_ACCOUNT_SELECTED);
+            return;
+        }
+        let selected: Vec<&MessageItem> = s.messages.iter().filter(|m| s.selected_uids.contains(&m.uid)).collect();
+        (
+            selected.iter().map(|m| m.message_id).collect::<Vec<_>>(),
+            selected.iter().map(|m| m.uid).collect::<Vec<_>>(),
+            s.mailbox(),
+        )
+    };
+    if uids.is_empty() {
+        send_status(tx, rt, &tr("status.no_messages_selected"));
+        return;
+    }
+
+    let undo = match cache.with_writer(|c| Some(action.apply_with_undo(c, &ids, &folder))) {
+        Some(Ok(undo)) => undo,
+        Some(Err(e)) => {
+            tracing::error!("Bulk update failed: {}", e);
+            send_status(tx, rt, &format!("Bulk update failed: {}", e));
+            return;
+        }
+        None => Vec::new(),
+    };
+
+    let updated = {
+        let mut s = state.lock().unwrap();
+        let targets = s.selected_uids.clone();
+        if action.removes_from_view() {
+            s.messages.retain(|m| !targets.contains(&m.uid));
+            s.selected_uids.clear();
+            s.selected_message_index = None;
+        } else {
+            for m in s.messages.iter_mut().filter(|m| targets.contains(&m.uid)) {
+                match action {
+                    BulkAction::MarkRead => m.read = true,
+                    BulkAction::MarkUnread => m.read = false,
+                    BulkAction::Star => m.starred = true,
+                    BulkAction::Unstar => m.starred = false,
+                    _ => {}
+                }
+            }
+        }
+        s.messages.clone()
+    };
+    refresh_folder_tree(state, cache, tx, rt);
+
+    let ctrl = active_controller(state, controllers);
+    let state = state.clone();
+    let tx = tx.clone();
+    let done = done(uids.len());
+    rt.spawn(async move {
+        let mut moved = HashMap::new();
+        if let Some(ctrl) = ctrl {
+            match ctrl.lock().await.apply_bulk(&folder, &uids, &action).await {
+                Ok(uids) => moved = uids,
+                Err(e) => {
+                    let _ = tx.send(UIUpdate::ErrorOccurred(format!("Server update failed: {}", e))).await;
+                }
+            }
+        }
+        // Recorded once the server has reported where moved messages went
+        if let Ok(mut s) = state.lock() {
+            for entry in undo {
+                s.history.record(entry.with_moved_uids(&moved));
+            }
+        }
+        let _ = tx.send(UIUpdate::MessagesLoaded(updated)).await;
+        let _ = tx.send(done).await;
+    });
+}
+
+/// Apply the active account's tag bound to `key` to the selected messages.
+/// Returns false when no tag uses the key.
+fn apply_tag_shortcut(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+    key: char,
+) -> bool {
+    let account_id = state.lock().ok().and_then(|s| s.active_account_id.clone()).unwrap_or_default();
+    let key = key.to_ascii_lowercase().to_string();
+    let tag = cache.with_reader(|c| c.get_tag_by_shortcut(&account_id, &key).ok()?);
+    let Some(tag) = tag else { return false };
+    let name = tag.name;
+    run_bulk_action_then(state, cache, controllers, tx, rt, BulkAction::AddTag(tag.id), move |count| {
+        UIUpdate::TagApplied { tag: name, count }
+    });
+    true
+}
+
+/// Poll the selected folder and report how many unseen messages arrived,
+/// syncing drafts for accounts that mirror them to the server.
+fn check_mail(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let Some(ctrl) = active_controller(state, controllers) else {
+        send_status(tx, rt, &tr("status.no_connected_account_to_check"));
+        return;
+    };
+    let check = check_mail_target(state);
+    let account_id = check.account.as_ref().map(|a| a.id.clone()).unwrap_or_default();
+    if let Some(account) = check.account.clone().filter(|a| a.sync_drafts) {
+        rt.spawn(sync_drafts(ctrl.clone(), cache.clone(), account));
+    }
+    rt.spawn(check_mail_with(ctrl.clone(), cache.clone(), check, tx.clone()));
+    rt.spawn(refresh_quota(ctrl, cache.clone(), account_id, tx.clone()));
+}
+
+/// What a mail check polls, and whose filter rules run on new mail
+struct MailCheck {
+    folder: String,
+    /// UIDs already shown for the folder, which aren't new
+    known: HashSet<u32>,
+    account: Option<Account>,
+    timeouts: NetworkTimeouts,
+    vacation: VacationResponder,
+    /// Spam score at which new mail moves to Junk; `None` when the spam
+    /// filter is off
+    spam_threshold: Option<f64>,
+}
+
+/// Folder to poll and the UIDs already shown for it.
+fn check_mail_target(state: &Arc<StdMutex<WxUIState>>) -> MailCheck {
+    let s = state.lock().unwrap();
+    MailCheck {
+        folder: s.mailbox(),
+        known: s.messages.iter().map(|m| m.uid).collect(),
+        account: s.accounts.iter().find(|a| Some(&a.id) == s.active_account_id.as_ref()).cloned(),
+        timeouts: s.settings.network_timeouts(),
+        vacation: s.settings.vacation.clone(),
+        spam_threshold: s.settings.spam_threshold(),
+    }
+}
+
+async fn check_mail_with(
+    ctrl: Arc<TokioMutex<MailController>>,
+    cache: Option<CachePool>,
+    check: MailCheck,
+    tx: Sender<UIUpdate>,
+) {
+    let _ = tx.send(UIUpdate::StatusUpdated(tr("status.checking_mail"))).await;
+    let fetched = ctrl.lock().await.fetch_messages(&check.folder).await;
+    match fetched {
+        Ok(previews) => {
+            if let Some(account) = &check.account {
+                let synced = cache.with_writer(|c| {
+                    Some(c.ensure_folder(&account.id, &check.folder).and_then(|f| c.mark_folder_synced(f.id)))
+                });
+                if let Some(Err(e)) = synced {
+                    tracing::warn!("Could not record sync time of {}: {}", check.folder, e);
+                }
+            }
+            let new: Vec<MessagePreview> =
+                previews.into_iter().filter(|p| !p.read && !check.known.contains(&p.uid)).collect();
+            let handled = match &check.account {
+                Some(account) if !new.is_empty() => {
+                    let rules = RuleContext { ctrl: &ctrl, cache: &cache, account, timeouts: check.timeouts, folder: &check.folder };
+                    process_new_mail(&rules, &check.vacation, check.spam_threshold, &new, &tx).await
+                }
+                _ => HashSet::new(),
+            };
+            let (lists, direct): (Vec<&MessagePreview>, Vec<&MessagePreview>) =
+                new.iter().filter(|p| !handled.contains(&p.uid)).partition(|p| p.list_id.is_some());
+            // Mail sent to the user is more worth previewing than list mail
+            let preview = direct
+                .iter()
+                .max_by_key(|p| p.uid)
+                .or_else(|| lists.iter().max_by_key(|p| p.uid))
+                .map(|p| MailPreview { from: p.from.clone(), subject: p.subject.clone() });
+            let _ = tx
+                .send(UIUpdate::NewMailArrived {
+                    account_id: check.account.as_ref().map(|a| a.id.clone()).unwrap_or_default(),
+                    count: direct.len(),
+                    from_lists: lists.len(),
+                    preview,
+                })
+                .await;
+        }
+        Err(e) => {
+            let _ = tx.send(UIUpdate::ErrorOccurred(describe_error("Mail check failed", &e))).await;
+        }
+    }
+}
+
+/// Where new mail arrived, for filter rules and the vacation responder
+struct RuleContext<'a> {
+    ctrl: &'a Arc<TokioMutex<MailController>>,
+    cache: &'a Option<CachePool>,
+    account: &'a Account,
+    timeouts: NetworkTimeouts,
+    folder: &'a str,
+}
+
+/// What a rule's action did to the message it matched
+enum RuleEffect {
+    /// Left in the folder, with a status message for anything sent
+    Kept(Option<String>),
+    MarkedRead,
+    /// Moved or deleted, so later rules can't act on it
+    Removed,
+}
+
+/// Run the account's filter rules, the spam filter and the vacation
+/// responder on `new` mail. Returns the UIDs moved, deleted or marked
+/// read, which no longer count as new.
+async fn process_new_mail(
+    ctx: &RuleContext<'_>,
+    vacation: &VacationResponder,
+    spam_threshold: Option<f64>,
+    new: &[MessagePreview],
+    tx: &Sender<UIUpdate>,
+) -> HashSet<u32> {
+    let rules = ctx.cache.with_reader(|c| c.get_filter_rules_for_account(&ctx.account.id).ok())
+        .unwrap_or_default();
+    let blocked = ctx.cache.with_reader(|c| c.get_senders(&ctx.account.id, SenderList::Blocked).ok())
+        .unwrap_or_default();
+    let junk = ctx.cache.with_reader(|c| c.get_senders(&ctx.account.id, SenderList::Junk).ok())
+        .unwrap_or_default();
+    let junk_folder = cached_special_folder(ctx.cache, &ctx.account.id, SpecialFolder::Junk);
+    let mut engine = FilterEngine::default();
+    engine.load_from_persisted(&rules);
+    engine.block_senders(&blocked);
+    // Mail already in Junk is left to the user
+    let spam_threshold = spam_threshold.filter(|_| junk_folder != ctx.folder);
+    if junk_folder != ctx.folder {
+        engine.junk_senders(&junk, &junk_folder);
+    }
+    // Only mail delivered to the inbox gets a vacation reply
+    let today = ctx.cache.with_reader(|c| Some(c.now())).unwrap_or_else(chrono::Utc::now);
+    let away = vacation.is_active(today.with_timezone(&chrono::Local).date_naive()) && ctx.folder.eq_ignore_ascii_case("INBOX");
+    let mut handled = HashSet::new();
+    if !away && spam_threshold.is_none() && !engine.get_rules().iter().any(|r| r.enabled) {
+        return handled;
+    }
+    for preview in new {
+        // Rules can test recipients and the body, which previews lack
+        let raw = ctx.ctrl.lock().await.fetch_raw_message(ctx.folder, preview.uid).await;
+        let raw = match raw {
+            Ok(raw) => raw,
+            Err(e) => {
+                tracing::warn!("Could not fetch new message {}: {}", preview.uid, e);
+                continue;
+            }
+        };
+        match apply_filter_rules(ctx, &engine, preview, &raw, tx).await {
+            RuleEffect::Removed => {
+                handled.insert(preview.uid);
+                continue;
+            }
+            RuleEffect::MarkedRead => {
+                handled.insert(preview.uid);
+            }
+            RuleEffect::Kept(_) => {}
+        }
+        if let Some(threshold) = spam_threshold {
+            if move_likely_spam(ctx, threshold, preview, &raw, tx).await {
+                handled.insert(preview.uid);
+                continue;
+            }
+        }
+        if away {
+            send_vacation_reply(ctx, vacation, &raw, tx).await;
+        }
+    }
+    handled
+}
+
+/// Score a new message with the account's spam filter and move it to Junk
+/// if the score reaches `threshold`. Trusted senders' mail is never moved.
+/// Returns whether the message was moved.
+async fn move_likely_spam(
+    ctx: &RuleContext<'_>,
+    threshold: f64,
+    preview: &MessagePreview,
+    raw: &[u8],
+    tx: &Sender<UIUpdate>,
+) -> bool {
+    let Some(message) = storage::parse_raw_message(raw, 0, preview.uid) else { return false };
+    let subject = message.subject.clone();
+    let score = spam_score(ctx.cache.clone(), ctx.account.id.clone(), message).await;
+    let Some(score) = score.filter(|score| *score >= threshold) else { return false };
+    match ctx.ctrl.lock().await.mark_as_spam(ctx.folder, &[preview.uid], false).await {
+        Ok(junk) => {
+            let junk = junk.folder;
+            let percent = format!("{:.0}", score * 100.0);
+            let _ = tx.send(UIUpdate::StatusUpdated(trf("status.moved_likely_spam", &[&subject, &junk, &percent]))).await;
+            true
+        }
+        Err(e) => {
+            tracing::warn!("Could not move likely spam {}: {}", preview.uid, e);
+            false
+        }
+    }
+}
+
+/// The account's spam score for a message, scored off the async runtime;
+/// `None` for trusted senders or when the filter hasn't learned enough
+async fn spam_score(cache: Option<CachePool>, account_id: String, message: CachedMessage) -> Option<f64> {
+    tokio::task::spawn_blocking(move || {
+        cache.with_reader(|c| {
+            let trusted = EmailAddress::parse_list(&message.from_addr)
+                .first()
+                .is_some_and(|sender| c.is_trusted_sender(&account_id, &sender.address).unwrap_or(false));
+            if trusted {
+                return None;
+            }
+            SpamClassifier::new(c, &account_id).score(&message).ok().flatten()
+        })
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
+/// Run the rules matching one new message, in order, and say what became
+/// of it. Blocked senders' rules come first, so their mail goes to Trash
+/// before any other rule forwards or answers it.
+async fn apply_filter_rules(
+    ctx: &RuleContext<'_>,
+    engine: &FilterEngine,
+    preview: &MessagePreview,
+    raw: &[u8],
+    tx: &Sender<UIUpdate>,
+) -> RuleEffect {
+    let Some(message) = storage::parse_raw_message(raw, 0, preview.uid) else { return RuleEffect::Kept(None) };
+    let message = CachedMessage { read: preview.read, starred: preview.starred, ..message };
+    let mut effect = RuleEffect::Kept(None);
+    for rule in engine.matching_rules(&message) {
+        match run_filter_action(ctx, rule, &message, raw).await {
+            Ok(RuleEffect::Kept(Some(status))) => {
+                let _ = tx.send(UIUpdate::StatusUpdated(status)).await;
+            }
+            Ok(RuleEffect::Kept(None)) => {}
+            Ok(RuleEffect::MarkedRead) => effect = RuleEffect::MarkedRead,
+            Ok(RuleEffect::Removed) => return RuleEffect::Removed,
+            Err(e) => {
+                let what = trf("status.filter_rule_failed", &[&rule.name]);
+                let _ = tx.send(UIUpdate::ErrorOccurred(describe_error(&what, &e))).await;
+            }
+        }
+    }
+    effect
+}
+
+/// Answer the sender of new mail while the user is away, at most once in
+/// each `resend_after_days` window
+async fn send_vacation_reply(ctx: &RuleContext<'_>, vacation: &VacationResponder, raw: &[u8], tx: &Sender<UIUpdate>) {
+    let Some(incoming) = IncomingMail::from_raw(raw) else { return };
+    let own = own_addresses(ctx);
+    let own: Vec<&str> = own.iter().map(String::as_str).collect();
+    let Some(sender) = incoming.reply_address(&own).map(|s| s.address.clone()) else { return };
+    let now = ctx.cache.with_reader(|c| Some(c.now())).unwrap_or_else(chrono::Utc::now);
+    let claimed = ctx.cache.with_writer(|c| Some(c.claim_vacation_reply(&ctx.account.id, &sender, vacation.resend_after_days, now)));
+    match claimed {
+        Some(Ok(true)) => {}
+        Some(Err(e)) => {
+            tracing::warn!("Could not check vacation replies: {}", e);
+            return;
+        }
+        _ => return,
+    }
+    let subject = match vacation.subject.trim() {
+        "" => incoming.reply_subject(),
+        subject => subject.to_string(),
+    };
+    let from = EmailAddress::new(ctx.account.email.clone(), None);
+    let mime = incoming.reply_mime(&from, &sender, &subject, &vacation.message, false, now);
+    let req = SendEmailRequest::for_account(ctx.account, ctx.timeouts);
+    match MailController::new().send_prepared(&req, &sender, &mime).await {
+        Ok(()) => {
+            let _ = tx.send(UIUpdate::StatusUpdated(trf("status.vacation_reply_sent", &[&sender]))).await;
+        }
+        Err(e) => {
+            if let Some(Err(e)) = ctx.cache.with_writer(|c| Some(c.release_vacation_reply(&ctx.account.id, &sender))) {
+                tracing::warn!("Failed to release vacation reply: {}", e);
+            }
+            let _ = tx.send(UIUpdate::ErrorOccurred(describe_error(&tr("status.vacation_reply_failed"), &e))).await;
+        }
+    }
+}
+
+/// The account's address and those of its identities, which automatic
+/// replies never go to
+fn own_addresses(ctx: &RuleContext<'_>) -> Vec<String> {
+    let identities = ctx.cache.with_reader(|c| c.get_identities_for_account(&ctx.account.id).ok())
+        .unwrap_or_default();
+    std::iter::once(ctx.account.email.clone()).chain(identities.into_iter().map(|i| i.from_email)).collect()
+}
+
+async fn run_filter_action(ctx: &RuleContext<'_>, rule: &FilterRule, message: &CachedMessage, raw: &[u8]) -> Result<RuleEffect> {
+    let uids = [message.uid];
+    let flag = |action: BulkAction| async move { ctx.ctrl.lock().await.apply_bulk(ctx.folder, &uids, &action).await };
+    match &rule.action {
+        FilterAction::MarkAsRead => {
+            flag(BulkAction::MarkRead).await?;
+            Ok(RuleEffect::MarkedRead)
+        }
+        FilterAction::MarkAsUnread => flag(BulkAction::MarkUnread).await.map(|_| RuleEffect::Kept(None)),
+        FilterAction::Star => flag(BulkAction::Star).await.map(|_| RuleEffect::Kept(None)),
+        FilterAction::Unstar => flag(BulkAction::Unstar).await.map(|_| RuleEffect::Kept(None)),
+        FilterAction::Delete => {
+            let mode = ctx.account.effective_delete_mode();
+            ctx.ctrl.lock().await.trash_messages(ctx.folder, &uids, mode).await?;
+            Ok(RuleEffect::Removed)
+        }
+        FilterAction::MoveToFolder(destination) => {
+            ctx.ctrl.lock().await.move_message(ctx.folder, message.uid, destination).await?;
+            Ok(RuleEffect::Removed)
+        }
+        FilterAction::AddTag(name) => {
+            tag_by_rule(ctx, message, name)?;
+            Ok(RuleEffect::Kept(None))
+        }
+        FilterAction::Forward(address) => {
+            let Some(incoming) = IncomingMail::from_raw(raw).filter(|m| !m.auto_forwarded) else {
+                return Ok(RuleEffect::Kept(None));
+            };
+            let key = incoming.message_id.clone().unwrap_or_else(|| format!("{}/{}", ctx.folder, message.uid));
+            let from = EmailAddress::new(ctx.account.email.clone(), None);
+            let mime = incoming.forward_mime(raw, &from, address, chrono::Utc::now());
+            let sent = send_by_rule(ctx, rule, &key, address, &mime).await?;
+            Ok(RuleEffect::Kept(sent.then(|| trf("status.rule_forwarded", &[&rule.name, address]))))
+        }
+        FilterAction::AutoReply(template_name) => {
+            let Some(incoming) = IncomingMail::from_raw(raw) else { return Ok(RuleEffect::Kept(None)) };
+            let own = own_addresses(ctx);
+            let own: Vec<&str> = own.iter().map(String::as_str).collect();
+            let Some(sender) = incoming.reply_address(&own) else { return Ok(RuleEffect::Kept(None)) };
+            let templates = ctx.cache.with_reader(|c| c.get_templates_for_account(&ctx.account.id).ok())
+                .unwrap_or_default();
+            let template = templates.iter().find(|t| t.name.eq_ignore_ascii_case(template_name)).ok_or_else(|| {
+                crate::common::Error::Config(trf("status.auto_reply_template_missing", &[template_name]))
+            })?;
+            let values = TemplateValues {
+                recipient_name: sender.name.clone().unwrap_or_else(|| sender.address.clone()),
+                recipient_email: sender.address.clone(),
+                sender_email: ctx.account.email.clone(),
+                date: i18n::format_long_date(chrono::Local::now().date_naive()),
+            };
+            let subject = match fill_template(&template.subject, &values) {
+                subject if subject.trim().is_empty() => incoming.reply_subject(),
+                subject => subject,
+            };
+            let body = fill_template(&template.body, &values);
+            let from = EmailAddress::new(ctx.account.email.clone(), None);
+            let mime = incoming.reply_mime(&from, &sender.address, &subject, &body, template.is_html, chrono::Utc::now());
+            let sent = send_by_rule(ctx, rule, &sender.address, &sender.address, &mime).await?;
+            Ok(RuleEffect::Kept(sent.then(|| trf("status.rule_auto_replied", &[&rule.name, &sender.address]))))
+        }
+    }
+}
+
+/// Tag a new message with the account's tag called `name`. Tags live in
+/// the cache, so a message that isn't cached yet can't be tagged.
+fn tag_by_rule(ctx: &RuleContext<'_>, message: &CachedMessage, name: &str) -> Result<()> {
+    let Some(pool) = ctx.cache else { return Ok(()) };
+    pool.write(|cache| {
+        let tag = cache.get_tags_for_account(&ctx.account.id)?.into_iter().find(|t| t.name.eq_ignore_ascii_case(name));
+        let cached = cache.find_message_by_message_id(&ctx.account.id, &message.message_id)?;
+        if let (Some(tag), Some(cached)) = (tag, cached) {
+            cache.add_tag_to_message(cached.id, &tag.id)?;
+        }
+        Ok(())
+    })
+}
+
+/// Send `mime` for a rule unless it already sent mail about `key`, and
+/// say whether it was sent. The claim is dropped again if sending fails,
+/// so the next check retries.
+async fn send_by_rule(ctx: &RuleContext<'_>, rule: &FilterRule, key: &str, to: &str, mime: &[u8]) -> Result<bool> {
+    let claim = |cache: &MessageCache| cache.claim_rule_send(&ctx.account.id, &rule.id, key);
+    let claimed = ctx.cache.with_writer(|c| Some(claim(c))).transpose()?.unwrap_or(false);
+    if !claimed {
+        return Ok(false);
+    }
+    let req = SendEmailRequest::for_account(ctx.account, ctx.timeouts);
+    let sent = MailController::new().send_prepared(&req, to, mime).await;
+    if sent.is_err() {
+        if let Some(Err(e)) = ctx.cache.with_writer(|c| Some(c.release_rule_send(&ctx.account.id, &rule.id, key))) {
+            tracing::warn!("Failed to release rule send: {}", e);
+        }
+    }
+    sent.map(|_| true)
+}
+
+/// Fetch the account's mailbox quota and remember it, so the usage meter
+/// shows the last known figures offline. Failures keep the old figures.
+async fn refresh_quota(
+    ctrl: Arc<TokioMutex<MailController>>,
+    cache: Option<CachePool>,
+    account_id: String,
+    tx: Sender<UIUpdate>,
+) {
+    let quota = match ctrl.lock().await.fetch_quota().await {
+        Ok(quota) => quota.map(|q| {
+            let signed = |value: Option<u64>| value.map(|v| v.min(i64::MAX as u64) as i64);
+            CachedQuota {
+                storage_used: signed(q.storage_used_bytes),
+                storage_limit: signed(q.storage_limit_bytes),
+                messages_used: signed(q.messages_used),
+                messages_limit: signed(q.messages_limit),
+                checked_at: chrono::Utc::now().to_rfc3339(),
+            }
+        }),
+        Err(e) => {
+            tracing::warn!("Could not fetch mailbox quota: {}", e);
+            return;
+        }
+    };
+    if let Some(Err(e)) = cache.with_writer(|c| Some(c.save_quota(&account_id, quota.as_ref()))) {
+        tracing::warn!("Failed to cache mailbox quota: {}", e);
+    }
+    let _ = tx.send(UIUpdate::QuotaChecked { account_id, quota }).await;
+}
+
+/// Folder tree entries for an account, with counts from the cache, leaving
+/// out folders the user hid or isn't subscribed to (see Manage Folders).
+/// Falls back to a bare INBOX before anything has been cached. With more
+/// than one enabled account, All Inboxes comes first.
+fn cached_folder_items(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    account_id: &str,
+) -> Vec<FolderItem> {
+    let subscribed_only = state.lock().map(|s| s.settings.subscribed_folders_only).unwrap_or(false);
+    let (folders, all_inboxes) = cache
+        .with_reader(|c| Some((c.get_visible_folders(account_id, subscribed_only).ok()?, c.get_all_inboxes_counts().ok())))
+        .unwrap_or_default();
+    let mut items: Vec<FolderItem> = match all_inboxes {
+        Some((accounts, unread, total)) if accounts > 1 => vec![FolderItem::all_inboxes(unread, total)],
+        _ => Vec::new(),
+    };
+    let follow_ups = cache.with_reader(|c| c.get_follow_up_counts(account_id, c.now()).ok())
+        .unwrap_or_default();
+    items.push(FolderItem::follow_up(follow_ups.0, follow_ups.1));
+    if folders.is_empty() {
+        items.push(FolderItem { path: "INBOX".into(), name: "INBOX".into(), unread: 0, total: 0 });
+    } else {
+        items.extend(folders.iter().map(FolderItem::from));
+    }
+    items
+}
+
+/// Re-send the folder list so the tree shows current unread/total counts.
+fn refresh_folder_tree(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let account_id = state.lock().ok().and_then(|s| s.active_account_id.clone()).unwrap_or_default();
+    let items = cached_folder_items(state, cache, &account_id);
+    let tx = tx.clone();
+    rt.spawn(async move {
+        let _ = tx.send(UIUpdate::FoldersLoaded(items)).await;
+    });
+}
+
+/// Show the cached Inbox messages of every enabled account, merged in the
+/// current sort order. Works the same online and offline; background sync
+/// keeps each account's Inbox current.
+fn load_all_inboxes(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let cached = cache.with_reader(|c| {
+        let inbox = c.get_messages_for_all_accounts_inbox().ok()?;
+        let folders: HashSet<i64> = inbox.iter().map(|m| m.message.folder_id).collect();
+        let list_ids: HashMap<i64, HashMap<u32, String>> =
+            folders.iter().map(|&id| (id, c.get_list_ids(id).unwrap_or_default())).collect();
+        let attachments: HashMap<i64, HashMap<u32, Vec<CachedAttachment>>> =
+            folders.into_iter().map(|id| (id, c.get_attachments_for_folder(id).unwrap_or_default())).collect();
+        Some((inbox, list_ids, attachments))
+    });
+    let Some((cached, list_ids, attachments)) = cached else {
+        send_status(tx, rt, &tr("status.all_inboxes_is_not_available"));
+        return;
+    };
+    let mut messages: Vec<MessageItem> = cached
+        .iter()
+        .map(|m| {
+            let attached = attachments.get(&m.message.folder_id).and_then(|a| a.get(&m.message.uid));
+            MessageItem {
+                list_id: list_ids.get(&m.message.folder_id).and_then(|ids| ids.get(&m.message.uid)).cloned(),
+                ..MessageItem::from(m)
+            }
+            .with_attachments(attached.map(Vec::as_slice).unwrap_or_default())
+        })
+        .collect();
+    let order = state.lock().map(|s| s.sort_order).unwrap_or(MailSortOption::DateNewestFirst);
+    sort_messages(&mut messages, order);
+    let tx = tx.clone();
+    rt.spawn(async move {
+        let _ = tx.send(UIUpdate::MessagesLoaded(messages)).await;
+    });
+}
+
+/// Show the active account's messages flagged for follow-up, soonest due
+/// first. Works from the cache, online or offline.
+fn load_follow_ups(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let account_id = state.lock().ok().and_then(|s| s.active_account_id.clone()).unwrap_or_default();
+    let cached = cache.with_reader(|c| c.get_follow_up_messages(&account_id).ok());
+    let Some(cached) = cached else {
+        send_status(tx, rt, &tr("status.follow_up_is_not_available"));
+        return;
+    };
+    let messages: Vec<MessageItem> = cached.iter().map(MessageItem::from).collect();
+    let tx = tx.clone();
+    rt.spawn(async move {
+        let _ = tx.send(UIUpdate::MessagesLoaded(messages)).await;
+    });
+}
+
+/// Show a folder's messages from the cache (used while offline).
+fn load_cached_folder(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+    folder: &str,
+) {
+    let Some(messages) = cached_folder_messages(state, cache, folder) else {
+        send_status(tx, rt, &format!("{} is not available offline", folder));
+        return;
+    };
+    let tx = tx.clone();
+    rt.spawn(async move {
+        let _ = tx.send(UIUpdate::MessagesLoaded(messages)).await;
+    });
+}
+
+/// A folder's cached messages in the current sort order, or `None` when
+/// the folder or the cache isn't there.
+fn cached_folder_messages(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    folder: &str,
+) -> Option<Vec<MessageItem>> {
+    let account_id = state.lock().ok().and_then(|s| s.active_account_id.clone()).unwrap_or_default();
+    let cached = cache.with_reader(|c| {
+        let folder = c.get_folder(&account_id, folder).ok()??;
+        let follow_ups = c.get_follow_ups_for_folder(folder.id).unwrap_or_default();
+        let list_ids = c.get_list_ids(folder.id).unwrap_or_default();
+        let attachments = c.get_attachments_for_folder(folder.id).unwrap_or_default();
+        Some((c.get_messages_for_folder(folder.id, &account_id).ok()?, follow_ups, list_ids, attachments))
+    });
+    let (cached, follow_ups, list_ids, attachments) = cached?;
+    let mut messages: Vec<MessageItem> = cached
+        .iter()
+        .map(|m| {
+            MessageItem {
+                follow_up: follow_ups.get(&m.id).map(|due| FollowUp::from_stored(due.as_deref())),
+                list_id: list_ids.get(&m.uid).cloned(),
+                ..MessageItem::from(m)
+            }
+            .with_attachments(attachments.get(&m.uid).map(Vec::as_slice).unwrap_or_default())
+        })
+        .collect();
+    let order = state.lock().map(|s| s.sort_order).unwrap_or(MailSortOption::DateNewestFirst);
+    sort_messages(&mut messages, order);
+    Some(messages)
+}
+
+/// When the selected account's `folder` was last fetched from the server,
+/// as stored. Virtual folders have no sync time of their own.
+fn folder_last_sync(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    folder: &str,
+) -> Option<String> {
+    if folder == ALL_INBOXES_PATH || folder == FOLLOW_UP_PATH {
+        return None;
+    }
+    let account_id = state.lock().ok()?.active_account_id.clone()?;
+    cache.with_reader(|c| c.get_folder_last_sync(&account_id, folder).ok()?)
+}
+
+/// "Last synced Today 14:32", or "Not synced yet"
+fn describe_last_sync(last_sync: Option<&str>) -> String {
+    match last_sync {
+        Some(at) => trf("status.last_synced", &[&i18n::format_date(at)]),
+        None => tr("status.not_synced_yet"),
+    }
+}
+
+/// Fetch the selected folder from the server now, cache its headers and
+/// show them.
+fn sync_folder_now(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let (folder, account_id, offline) = {
+        let s = state.lock().unwrap();
+        (s.selected_folder.clone(), s.active_account_id.clone(), s.offline_mode)
+    };
+    let (Some(folder), Some(account_id)) = (folder, account_id) else {
+        send_status(tx, rt, &tr("status.select_a_folder_first"));
+        return;
+    };
+    if folder == ALL_INBOXES_PATH || folder == FOLLOW_UP_PATH {
+        send_status(tx, rt, &tr("status.sync_a_real_folder"));
+        return;
+    }
+    if offline {
+        send_status(tx, rt, &tr("status.go_online_to_sync_folders"));
+        return;
+    }
+    let Some(ctrl) = active_controller(state, controllers) else {
+        send_status(tx, rt, &tr("status.no_connected_account"));
+        return;
+    };
+    let (state, cache, tx) = (state.clone(), cache.clone(), tx.clone());
+    rt.spawn(async move {
+        let _ = tx.send(UIUpdate::StatusUpdated(trf("status.syncing_folder", &[&folder]))).await;
+        let previews = match ctrl.lock().await.fetch_messages(&folder).await {
+            Ok(previews) => previews,
+            Err(e) => {
+                let _ = tx.send(UIUpdate::ErrorOccurred(describe_error(&format!("Sync of {} failed", folder), &e))).await;
+                return;
+            }
+        };
+        let saved = cache.with_writer(|c| {
+            Some(c.ensure_folder(&account_id, &folder).and_then(|f| cache_previews(c, f.id, &previews)))
+        });
+        match saved {
+            Some(Ok(())) => {}
+            Some(Err(e)) => {
+                let _ = tx.send(UIUpdate::ErrorOccurred(describe_error(&format!("Sync of {} failed", folder), &e))).await;
+                return;
+            }
+            None => {
+                let _ = tx.send(UIUpdate::StatusUpdated(tr("status.message_cache_is_not_available"))).await;
+                return;
+            }
+        }
+        let _ = tx.send(UIUpdate::FoldersLoaded(cached_folder_items(&state, &cache, &account_id))).await;
+        // The user may have moved on while the folder was fetched
+        let selected = state.lock().map(|s| s.selected_folder.as_deref() == Some(folder.as_str())).unwrap_or(false);
+        if let Some(messages) = cached_folder_messages(&state, &cache, &folder).filter(|_| selected) {
+            let _ = tx.send(UIUpdate::MessagesLoaded(messages)).await;
+        }
+        let last_sync = folder_last_sync(&state, &cache, &folder);
+        let _ = tx.send(UIUpdate::FolderSynced { folder, messages: previews.len(), last_sync }).await;
+    });
+}
+
+/// Save fetched `previews` as the cached headers of folder `folder_id`,
+/// with their follow-up flags, list ids and attachments, and record the
+/// folder's sync time.
+fn cache_previews(c: &MessageCache, folder_id: i64, previews: &[MessagePreview]) -> Result<()> {
+    let headers: Vec<CachedMessage> = previews.iter().map(|p| p.cached_header(folder_id)).collect();
+    c.in_transaction(|c| {
+        c.save_message_headers(&headers)?;
+        for p in previews {
+            c.sync_follow_up(folder_id, p.uid, p.follow_up)?;
+            if p.list_id.is_some() {
+                c.set_list_id(folder_id, p.uid, p.list_id.as_deref())?;
+            }
+            if !p.attachments.is_empty() {
+                c.save_attachments(folder_id, p.uid, &p.cached_attachments())?;
+            }
+        }
+        c.mark_folder_synced(folder_id)?;
+        c.recompute_folder_counts(folder_id)
+    })
+}
+
+/// Mark the selected folder as available offline and download it.
+fn make_folder_offline(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let (folder, account_id, offline) = {
+        let s = state.lock().unwrap();
+        (s.selected_folder.clone(), s.active_account_id.clone(), s.offline_mode)
+    };
+    let (Some(folder), Some(account_id)) = (folder, account_id) else {
+        send_status(tx, rt, &tr("status.select_a_folder_first"));
+        return;
+    };
+    if offline {
+        send_status(tx, rt, &tr("status.go_online_to_download_folders_for"));
+        return;
+    }
+    let Some(ctrl) = active_controller(state, controllers) else {
+        send_status(tx, rt, &tr("status.no_connected_account"));
+        return;
+    };
+
+    let text = format!(
+        "Make {} available offline?\n\nChoose Yes to also download message bodies, or No for headers only.",
+        folder
+    );
+    let dlg = MessageDialog::builder(frame, &text, "Make Available Offline")
+        .with_style(MessageDialogStyle::YesNo | MessageDialogStyle::Cancel | MessageDialogStyle::IconQuestion)
+        .build();
+    let bodies = match dlg.show_modal() {
+        ID_YES => true,
+        ID_NO => false,
+        _ => return,
+    };
+
+    let flagged = cache.with_writer(|c| {
+        let id = c.ensure_folder(&account_id, &folder).ok()?.id;
+        c.set_folder_offline_sync(id, true).ok()
+    });
+    if flagged.is_none() {
+        send_status(tx, rt, &tr("status.message_cache_is_not_available"));
+        return;
+    }
+
+    let cache = cache.clone();
+    let tx = tx.clone();
+    rt.spawn(sync_folder_offline(state.clone(), ctrl, cache, account_id, folder, bodies, tx));
+}
+
+/// Download a folder's headers (and optionally bodies) into the cache,
+/// reporting progress through `UIUpdate::OfflineSyncProgress`.
+async fn sync_folder_offline(
+    state: Arc<StdMutex<WxUIState>>,
+    ctrl: Arc<TokioMutex<MailController>>,
+    cache: Option<CachePool>,
+    account_id: String,
+    folder: String,
+    bodies: bool,
+    tx: Sender<UIUpdate>,
+) {
+    let previews = match ctrl.lock().await.fetch_messages(&folder).await {
+        Ok(previews) => previews,
+        Err(e) => {
+            let _ = tx.send(UIUpdate::ErrorOccurred(describe_error(&format!("Offline download of {} failed", folder), &e))).await;
+            return;
+        }
+    };
+    let saved = cache.with_writer(|c| {
+        Some(c.ensure_folder(&account_id, &folder).and_then(|f| {
+            cache_previews(c, f.id, &previews)?;
+            Ok(f.id)
+        }))
+    });
+    let folder_id = match saved {
+        Some(Ok(folder_id)) => folder_id,
+        Some(Err(e)) => {
+            tracing::error!("Offline sync: {}", e);
+            return;
+        }
+        None => return,
+    };
+
+    let total = previews.len();
+    let mut downloaded = 0;
+    if bodies {
+        for (done, p) in previews.iter().enumerate() {
+            let cached = cache.with_reader(|c| c.get_message_body(folder_id, p.uid).ok()?).is_some();
+            if !cached {
+                match ctrl.lock().await.fetch_message_body(&folder, p.uid).await {
+                    Ok(body) => {
+                        let (plain, html) = if looks_like_html(&body) {
+                            (None, Some(body.as_str()))
+                        } else {
+                            (Some(body.as_str()), None)
+                        };
+                        if cache.with_writer(|c| c.save_message_body(folder_id, p.uid, plain, html).ok()).unwrap_or(false) {
+                            downloaded += 1;
+                        }
+                    }
+                    Err(e) => tracing::warn!("Offline sync: body {} in {}: {}", p.uid, folder, e),
+                }
+            }
+            let _ = tx.send(UIUpdate::OfflineSyncProgress { folder: folder.clone(), done: done + 1, total }).await;
+        }
+    }
+    let _ = tx.send(UIUpdate::FoldersLoaded(cached_folder_items(&state, &cache, &account_id))).await;
+    let _ = tx.send(UIUpdate::OfflineSyncComplete { folder, messages: total, bodies: downloaded }).await;
+}
+
+/// Remove downloaded bodies for the selected folder to reclaim space.
+fn purge_offline_folder(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let (folder, account_id) = {
+        let s = state.lock().unwrap();
+        (s.selected_folder.clone(), s.active_account_id.clone().unwrap_or_default())
+    };
+    let Some(folder) = folder else {
+        send_status(tx, rt, &tr("status.select_a_folder_first"));
+        return;
+    };
+    let result = cache.with_writer(|c| {
+        let cached = c.get_folder(&account_id, &folder).ok()??;
+        Some(c.purge_offline_data(cached.id).and_then(|count| c.compact().map(|_| count)))
+    });
+    let status = match result {
+        Some(Ok(count)) => format!("Removed {} downloaded message bodies from {}", count, folder),
+        Some(Err(e)) => format!("Could not purge offline data: {}", e),
+        None => format!("{} has no offline data", folder),
+    };
+    send_status(tx, rt, &status);
+}
+
+/// Choose which of the active account's folders the folder list shows and
+/// which are on the server's subscription list. Online, the server's
+/// folders and subscriptions are fetched first.
+fn manage_folders(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let (account_id, offline, subscribed_only) = {
+        let s = state.lock().unwrap();
+        (s.active_account_id.clone(), s.offline_mode, s.settings.subscribed_folders_only)
+    };
+    let Some(account_id) = account_id else {
+        send_status(tx, rt, &tr("status.no_connected_account"));
+        return;
+    };
+    let ctrl = active_controller(state, controllers).filter(|_| !offline);
+    if let Some(ctrl) = &ctrl {
+        match rt.block_on(async { ctrl.lock().await.fetch_server_folders().await }) {
+            Ok(listed) => {
+                if let Some(Err(e)) = cache.with_writer(|c| Some(c.record_server_folders(&account_id, &listed))) {
+                    tracing::warn!("Failed to cache the folder list: {}", e);
+                }
+            }
+            Err(e) => tracing::warn!("Could not list server folders: {}", e),
+        }
+    }
+    let folders = cache.with_reader(|c| c.get_folder_subscriptions(&account_id).ok())
+        .unwrap_or_default();
+    if folders.is_empty() {
+        send_status(tx, rt, &tr("status.message_cache_is_not_available"));
+        return;
+    }
+    let Some(chosen) = wx_managers::show_folder_manager_dialog(frame, &folders, subscribed_only) else { return };
+
+    // Showing and hiding is local; subscriptions change once the server agrees
+    let saved = cache.with_writer(|c| {
+        Some(c.in_transaction(|c| {
+            for f in &chosen.folders {
+                c.set_folder_shown(&account_id, &f.path, f.shown)?;
+            }
+            Ok(())
+        }))
+    });
+    if let Some(Err(e)) = saved {
+        tracing::error!("Cache update failed: {}", e);
+    }
+    if chosen.subscribed_only != subscribed_only {
+        let mut mgr = ConfigManager::default();
+        let _ = mgr.load();
+        let mut config = mgr.app_config().clone();
+        config.subscribed_folders_only = chosen.subscribed_only;
+        if let Err(e) = mgr.save_app_settings(&config) {
+            send_status(tx, rt, &format!("Settings save error: {}", e));
+        }
+        if let Ok(mut s) = state.lock() {
+            s.settings.subscribed_folders_only = chosen.subscribed_only;
+        }
+    }
+    refresh_folder_tree(state, cache, tx, rt);
+
+    let changes: Vec<(String, bool)> = chosen.folders.iter()
+        .filter(|f| folders.iter().any(|old| old.path == f.path && old.subscribed != f.subscribed))
+        .map(|f| (f.path.clone(), f.subscribed))
+        .collect();
+    if changes.is_empty() {
+        return;
+    }
+    let Some(ctrl) = ctrl else {
+        send_status(tx, rt, &tr("status.go_online_to_change_subscriptions"));
+        return;
+    };
+    let state = state.clone();
+    let cache = cache.clone();
+    let tx = tx.clone();
+    rt.spawn(async move {
+        let mut changed = 0;
+        for (path, subscribed) in changes {
+            if let Err(e) = ctrl.lock().await.set_subscribed(&path, subscribed).await {
+                let _ = tx.send(UIUpdate::ErrorOccurred(describe_error(&format!("Could not change the subscription to {}", path), &e))).await;
+                continue;
+            }
+            if let Some(Err(e)) = cache.with_writer(|c| Some(c.set_folder_subscribed(&account_id, &path, subscribed))) {
+                tracing::warn!("Failed to cache folder subscription: {}", e);
+            }
+            changed += 1;
+        }
+        let _ = tx.send(UIUpdate::FoldersLoaded(cached_folder_items(&state, &cache, &account_id))).await;
+        let _ = tx.send(UIUpdate::StatusUpdated(trf("status.subscriptions_changed", &[&changed.to_string()]))).await;
+    });
+}
+
+/// Where folders are created, renamed or deleted
+struct FolderTarget {
+    ctrl: Arc<TokioMutex<MailController>>,
+    account_id: String,
+    /// The selected folder; `None` for the virtual folders
+    folder: Option<String>,
+}
+
+/// Connection, account and selected folder for creating, renaming or
+/// deleting folders, which only the server can do. Reports why when
+/// unavailable.
+fn folder_management_target(
+    state: &Arc<StdMutex<WxUIState>>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) -> Option<FolderTarget> {
+    let (account_id, folder, offline) = {
+        let s = state.lock().unwrap();
+        let folder = s.selected_folder.clone().filter(|f| f != ALL_INBOXES_PATH && f != FOLLOW_UP_PATH);
+        (s.active_account_id.clone(), folder, s.offline_mode)
+    };
+    if offline {
+        send_status(tx, rt, &tr("status.go_online_to_manage_folders"));
+        return None;
+    }
+    match (active_controller(state, controllers), account_id) {
+        (Some(ctrl), Some(account_id)) => Some(FolderTarget { ctrl, account_id, folder }),
+        _ => {
+            send_status(tx, rt, &tr("status.no_connected_account"));
+            None
+        }
+    }
+}
+
+/// Create a folder at the top level, or inside the selected folder.
+fn new_folder(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+    subfolder: bool,
+) {
+    let Some(FolderTarget { ctrl, account_id, folder: selected }) = folder_management_target(state, controllers, tx, rt) else {
+        return;
+    };
+    let parent = match (subfolder, selected) {
+        (false, _) => None,
+        (true, Some(folder)) => Some(folder),
+        (true, None) => {
+            send_status(tx, rt, &tr("status.select_a_folder_first"));
+            return;
+        }
+    };
+    let title = match &parent {
+        Some(parent) => format!("New Folder in {}", parent),
+        None => "New Folder".to_string(),
+    };
+    let Some(name) = show_text_dialog(frame, &title, "Folder &name:", "") else { return };
+
+    let state = state.clone();
+    let cache = cache.clone();
+    let tx = tx.clone();
+    rt.spawn(async move {
+        let created = ctrl.lock().await.create_folder(parent.as_deref(), &name).await;
+        match created {
+            Ok(path) => {
+                if let Some(Err(e)) = cache.with_writer(|c| Some(c.ensure_folder(&account_id, &path))) {
+                    tracing::warn!("Failed to cache new folder: {}", e);
+                }
+                let _ = tx.send(UIUpdate::FoldersLoaded(cached_folder_items(&state, &cache, &account_id))).await;
+                let _ = tx.send(UIUpdate::StatusUpdated(trf("status.folder_created", &[&path]))).await;
+            }
+            Err(e) => {
+                let _ = tx.send(UIUpdate::ErrorOccurred(describe_error("Could not create the folder", &e))).await;
+            }
+        }
+    });
+}
+
+/// Rename the selected folder on the server and in the cache. Its
+/// subfolders move with it.
+fn rename_selected_folder(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let Some(FolderTarget { ctrl, account_id, folder }) = folder_management_target(state, controllers, tx, rt) else { return };
+    let Some(folder) = folder else {
+        send_status(tx, rt, &tr("status.select_a_folder_first"));
+        return;
+    };
+    let Some(delimiter) = changeable_folder(&ctrl, &folder, tx, rt) else { return };
+    let leaf = match delimiter.as_str() {
+        "" => folder.as_str(),
+        d => folder.rsplit(d).next().unwrap_or(&folder),
+    };
+    let Some(name) = show_text_dialog(frame, &format!("Rename {}", folder), "New &name:", leaf) else { return };
+
+    let state = state.clone();
+    let cache = cache.clone();
+    let tx = tx.clone();
+    rt.spawn(async move {
+        let renamed = ctrl.lock().await.rename_folder(&folder, &name).await;
+        match renamed {
+            Ok(new_path) => {
+                let cached = cache.with_writer(|c| Some(c.rename_cached_folder(&account_id, &folder, &new_path, &delimiter)));
+                if let Some(Err(e)) = cached {
+                    tracing::warn!("Failed to rename cached folder: {}", e);
+                }
+                if let Ok(mut s) = state.lock() {
+                    if s.selected_folder.as_deref() == Some(folder.as_str()) {
+                        s.selected_folder = Some(new_path.clone());
+                    }
+                }
+                let _ = tx.send(UIUpdate::FoldersLoaded(cached_folder_items(&state, &cache, &account_id))).await;
+                let _ = tx.send(UIUpdate::StatusUpdated(trf("status.folder_renamed", &[&folder, &new_path]))).await;
+            }
+            Err(e) => {
+                let _ = tx.send(UIUpdate::ErrorOccurred(describe_error("Could not rename the folder", &e))).await;
+            }
+        }
+    });
+}
+
+/// Delete the selected folder on the server and from the cache, asking
+/// first if it still holds messages.
+fn delete_selected_folder(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let Some(FolderTarget { ctrl, account_id, folder }) = folder_management_target(state, controllers, tx, rt) else { return };
+    let Some(folder) = folder else {
+        send_status(tx, rt, &tr("status.select_a_folder_first"));
+        return;
+    };
+    if changeable_folder(&ctrl, &folder, tx, rt).is_none() {
+        return;
+    }
+    let messages = cache.with_reader(|c| c.get_folder(&account_id, &folder).ok()?)
+        .map(|f| f.total_count.max(0))
+        .unwrap_or(0);
+    if messages > 0 {
+        let noun = if messages == 1 { "message" } else { "messages" };
+        let text = format!(
+            "Delete {} and the {} {} in it?\n\nThe messages are deleted from the server and can't be recovered.",
+            folder, messages, noun
+        );
+        let dlg = MessageDialog::builder(frame, &text, "Delete Folder")
+            .with_style(MessageDialogStyle::YesNo | MessageDialogStyle::NoDefault | MessageDialogStyle::IconWarning)
+            .build();
+        if dlg.show_modal() != ID_YES {
+            return;
+        }
+    }
+
+    let state = state.clone();
+    let cache = cache.clone();
+    let tx = tx.clone();
+    rt.spawn(async move {
+        if let Err(e) = ctrl.lock().await.delete_folder(&folder).await {
+            let _ = tx.send(UIUpdate::ErrorOccurred(describe_error("Could not delete the folder", &e))).await;
+            return;
+        }
+        if let Some(Err(e)) = cache.with_writer(|c| Some(c.delete_cached_folder(&account_id, &folder))) {
+            tracing::warn!("Failed to remove cached folder: {}", e);
+        }
+        let viewing = state.lock().map(|mut s| {
+            let viewing = s.selected_folder.as_deref() == Some(folder.as_str());
+            if viewing {
+                s.selected_folder = None;
+                s.selected_uids.clear();
+                s.selected_message_index = None;
+            }
+            viewing
+        }).unwrap_or(false);
+        if viewing {
+            let _ = tx.send(UIUpdate::MessagesLoaded(Vec::new())).await;
+        }
+        let _ = tx.send(UIUpdate::FoldersLoaded(cached_folder_items(&state, &cache, &account_id))).await;
+        let _ = tx.send(UIUpdate::StatusUpdated(trf("status.folder_deleted", &[&folder]))).await;
+    });
+}
+
+/// The hierarchy separator around `folder`, or `None` with the reason in
+/// the status bar when it is a special folder or the server can't be asked.
+fn changeable_folder(
+    ctrl: &Arc<TokioMutex<MailController>>,
+    folder: &str,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) -> Option<String> {
+    match rt.block_on(async { ctrl.lock().await.folder_details(folder).await }) {
+        Ok((_, true)) => {
+            send_status(tx, rt, &trf("status.special_folder", &[folder]));
+            None
+        }
+        Ok((delimiter, false)) => Some(delimiter),
+        Err(e) => {
+            send_status(tx, rt, &describe_error("Could not reach the server", &e));
+            None
+        }
+    }
+}
+
+/// Ask where to save an export; `None` when cancelled.
+fn choose_export_path(frame: &Frame, title: &str, default_file: &str, wildcard: &str) -> Option<PathBuf> {
+    let dlg = FileDialog::builder(frame)
+        .with_message(title)
+        .with_default_file(default_file)
+        .with_wildcard(wildcard)
+        .with_style(FileDialogStyle::Save | FileDialogStyle::OverwritePrompt)
+        .build();
+    if dlg.show_modal() != ID_OK {
+        return None;
+    }
+    dlg.get_path().map(PathBuf::from)
+}
+
+/// Export the selected folder's cached messages to an mbox file.
+fn export_folder(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let (folder, account_id) = {
+        let s = state.lock().unwrap();
+        (s.selected_folder.clone(), s.active_account_id.clone().unwrap_or_default())
+    };
+    let Some(folder) = folder else {
+        send_status(tx, rt, &tr("status.select_a_folder_first"));
+        return;
+    };
+    let Some(path) = choose_export_path(
+        frame,
+        "Export Folder",
+        &format!("{}.mbox", folder.replace(['/', '\\'], "_")),
+        "mbox files (*.mbox)|*.mbox|All files (*.*)|*.*",
+    ) else {
+        return;
+    };
+    let result = cache.with_reader(|c| {
+        let cached = c.get_folder(&account_id, &folder).ok()??;
+        Some(Storage::export_folder_mbox(c, &account_id, cached.id, &path))
+    });
+    let status = match result {
+        Some(Ok(count)) => format!("Exported {} messages from {} to {}", count, folder, path.display()),
+        Some(Err(e)) => format!("Export failed: {}", e),
+        None => format!("{} has no cached messages to export", folder),
+    };
+    send_status(tx, rt, &status);
+}
+
+/// Export the selected message to an .eml file.
+fn export_message(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let item = state.lock().ok().and_then(|s| s.messages.get(s.selected_message_index?).cloned());
+    let Some(item) = item else {
+        send_status(tx, rt, &tr("status.select_a_message_first"));
+        return;
+    };
+    let Some(path) = choose_export_path(
+        frame,
+        "Export Message",
+        &format!("{}.eml", export_file_name(&item.subject)),
+        "Email messages (*.eml)|*.eml|All files (*.*)|*.*",
+    ) else {
+        return;
+    };
+    let result = cache.with_reader(|c| Some(Storage::export_message_eml(c, item.message_id, &path)));
+    let status = match result {
+        Some(Ok(())) => format!("Saved message to {}", path.display()),
+        Some(Err(e)) => format!("Export failed: {}", e),
+        None => "Message cache is not available".to_string(),
+    };
+    send_status(tx, rt, &status);
+}
+
+/// A file name for a saved message, made from its subject
+fn export_file_name(subject: &str) -> String {
+    let name: String = subject.chars()
+        .map(|c| if c.is_alphanumeric() || c == ' ' || c == '-' { c } else { '_' })
+        .take(60)
+        .collect();
+    if name.trim().is_empty() { "message".to_string() } else { name.trim().to_string() }
+}
+
+/// The selected message laid out for printing: its headers, text and
+/// attachment list, with its image attachments when the message can be
+/// downloaded.
+fn printed_message(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    rt: &Arc<Runtime>,
+) -> Option<(MessageItem, PrintedMessage, Vec<ImagePreview>)> {
+    let item = state.lock().ok().and_then(|s| s.messages.get(s.selected_message_index?).cloned())?;
+    let body = selected_body_text(state, cache, controllers, rt);
+    let attachments = if item.has_attachments {
+        raw_source(state, cache, controllers, rt, &item).map(|raw| extract_attachments(raw.as_bytes())).unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    let listed = if attachments.is_empty() {
+        item.attachments.iter().map(|a| format!("{} ({})", a.filename, format_size(a.size as i64))).collect()
+    } else {
+        attachments.iter().map(|a| format!("{} ({})", a.label(), format_size(a.size as i64))).collect()
+    };
+    let images = attachments.iter().filter_map(|a| a.image_preview(PRINT_IMAGE_SIDE)).collect();
+    let headers = [("From", &item.from), ("To", &item.to), ("Cc", &item.cc), ("Date", &item.date)]
+        .into_iter()
+        .filter(|(_, value)| !value.trim().is_empty())
+        .map(|(label, value)| (label.to_string(), value.clone()))
+        .collect();
+    let subject = if item.subject.trim().is_empty() { tr("compose.no_subject") } else { item.subject.clone() };
+    let printed = PrintedMessage { subject, headers, body, attachments: listed };
+    Some((item, printed, images))
+}
+
+/// Print the selected message: it opens in the browser, laid out for
+/// paper, and the browser shows the system's print dialog. The page is
+/// deleted a minute later; with cache encryption on, the user is asked
+/// first, since it holds the message unencrypted.
+fn print_message(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    // The page the browser prints is the message in the clear
+    let encrypted = state.lock().map(|s| content_encryption(&s.settings) != ContentEncryption::Off).unwrap_or(false);
+    if encrypted {
+        let answer = MessageDialog::builder(frame, &tr("print.decrypted_copy"), &tr("print.decrypted_copy.title"))
+            .with_style(MessageDialogStyle::YesNo | MessageDialogStyle::NoDefault | MessageDialogStyle::IconWarning)
+            .build()
+            .show_modal();
+        if answer != ID_YES {
+            return;
+        }
+    }
+    let Some((_, message, images)) = printed_message(state, cache, controllers, rt) else {
+        send_status(tx, rt, &tr("status.select_a_message_first"));
+        return;
+    };
+    let path = match printing::write_print_page(&std::env::temp_dir(), &printing::to_html(&message, &images)) {
+        Ok(path) => path,
+        Err(e) => {
+            send_status(tx, rt, &trf("status.could_not_print", &[&e.to_string()]));
+            return;
+        }
+    };
+    if let Err(e) = open::that(&path) {
+        printing::remove_print_page(&path);
+        send_status(tx, rt, &trf("status.could_not_print", &[&e.to_string()]));
+        return;
+    }
+    // Whatever is still there on quit goes then
+    rt.spawn(async move {
+        tokio::time::sleep(printing::PRINT_PAGE_LIFETIME).await;
+        printing::remove_print_page(&path);
+    });
+    send_status(tx, rt, &tr("status.print_opened"));
+}
+
+/// Save the selected message as a PDF file. A message in a script the
+/// PDF's font lacks is offered to Print instead.
+fn save_message_pdf(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let Some((item, message, images)) = printed_message(state, cache, controllers, rt) else {
+        send_status(tx, rt, &tr("status.select_a_message_first"));
+        return;
+    };
+    // The PDF font only covers Western European text; the browser covers the rest
+    let pdf = match printing::to_pdf(&message, &images) {
+        Ok(pdf) => pdf,
+        Err(unsupported) => {
+            let answer = MessageDialog::builder(frame, &trf("print.pdf_unsupported", &[&unsupported.to_string()]), &tr("print.pdf_unsupported.title"))
+                .with_style(MessageDialogStyle::YesNo | MessageDialogStyle::IconQuestion)
+                .build()
+                .show_modal();
+            if answer == ID_YES {
+                print_message(frame, state, cache, controllers, tx, rt);
+            }
+            return;
+        }
+    };
+    let Some(path) = choose_export_path(
+        frame,
+        "Save as PDF",
+        &format!("{}.pdf", export_file_name(&item.subject)),
+        "PDF files (*.pdf)|*.pdf|All files (*.*)|*.*",
+    ) else {
+        return;
+    };
+    let status = match std::fs::write(&path, pdf) {
+        Ok(()) => format!("Saved message to {}", path.display()),
+        Err(e) => format!("Export failed: {}", e),
+    };
+    send_status(tx, rt, &status);
+}
+
+/// Import an mbox or .eml file into the selected folder (INBOX if none).
+fn import_messages(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let (folder, account_id) = {
+        let s = state.lock().unwrap();
+        (s.mailbox(), s.active_account_id.clone())
+    };
+    let Some(account_id) = account_id else {
+        send_status(tx, rt, &tr("status.add_an_account_before_importing_mail"));
+        return;
+    };
+    let dlg = FileDialog::builder(frame)
+        .with_message("Import Messages")
+        .with_wildcard("Mail files (*.mbox;*.eml)|*.mbox;*.eml|All files (*.*)|*.*")
+        .with_style(FileDialogStyle::Open | FileDialogStyle::FileMustExist)
+        .build();
+    if dlg.show_modal() != ID_OK {
+        return;
+    }
+    let Some(path) = dlg.get_path().map(PathBuf::from) else { return };
+    let is_eml = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("eml"));
+
+    let result = cache.with_writer(|c| {
+        Some(if is_eml {
+            Storage::import_eml(c, &account_id, &folder, &path)
+        } else {
+            Storage::import_mbox(c, &account_id, &folder, &path)
+        })
+    });
+    let status = match result {
+        Some(Ok(summary)) => {
+            let mut status = format!("Imported {} messages into {}", summary.imported, folder);
+            if summary.duplicates > 0 {
+                status.push_str(&format!(", {} duplicates skipped", summary.duplicates));
+            }
+            if summary.failed > 0 {
+                status.push_str(&format!(", {} could not be read", summary.failed));
+            }
+            status
+        }
+        Some(Err(e)) => format!("Import failed: {}", e),
+        None => "Message cache is not available".to_string(),
+    };
+    send_status(tx, rt, &status);
+    load_cached_folder(state, cache, tx, rt, &folder);
+}
+
+/// Back up accounts, tags, signatures, rules and contacts to a JSON file.
+fn backup_settings(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let Some(choice) = wx_backup::show_backup_dialog(frame) else { return };
+    let Some(path) = choose_export_path(
+        frame,
+        "Back Up Settings",
+        "wixen-mail-settings.json",
+        "Settings backups (*.json)|*.json|All files (*.*)|*.*",
+    ) else {
+        return;
+    };
+    let settings = if choice.include_settings { state.lock().ok().map(|s| s.settings.clone()) } else { None };
+    let secrets = match &choice.passphrase {
+        Some(passphrase) => BackupSecrets::Encrypt(passphrase),
+        None => BackupSecrets::Exclude,
+    };
+    let result = cache.with_reader(|c| {
+        let backup = c.export_settings(settings.as_ref(), secrets);
+        Some(backup.and_then(|b| {
+            std::fs::write(&path, b.to_json()?)?;
+            Ok(b.accounts.len())
+        }))
+    });
+    let status = match result {
+        Some(Ok(count)) => trf("status.settings_backed_up", &[&count.to_string(), &path.display().to_string()]),
+        Some(Err(e)) => describe_error("Backup failed", &e),
+        None => tr("status.message_cache_is_not_available"),
+    };
+    send_status(tx, rt, &status);
+}
+
+/// Restore a settings backup, then reload the accounts it brought back.
+fn restore_settings(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let dlg = FileDialog::builder(frame)
+        .with_message("Restore Settings")
+        .with_wildcard("Settings backups (*.json)|*.json|All files (*.*)|*.*")
+        .with_style(FileDialogStyle::Open | FileDialogStyle::FileMustExist)
+        .build();
+    if dlg.show_modal() != ID_OK {
+        return;
+    }
+    let Some(path) = dlg.get_path().map(PathBuf::from) else { return };
+    let backup = std::fs::read_to_string(&path).map_err(Into::into).and_then(|json| SettingsBackup::from_json(&json));
+    let backup = match backup {
+        Ok(backup) => backup,
+        Err(e) => {
+            send_status(tx, rt, &describe_error("Could not read the backup", &e));
+            return;
+        }
+    };
+    let Some(choice) = wx_backup::show_restore_dialog(frame, backup.has_secrets(), backup.settings.is_some()) else {
+        return;
+    };
+    let result = cache.with_writer(|c| {
+        let summary = c.import_settings(&backup, choice.passphrase.as_deref(), choice.on_conflict);
+        Some(summary.map(|summary| (summary, c.load_accounts().ok())))
+    });
+    let (summary, accounts) = match result {
+        Some(Ok(restored)) => restored,
+        Some(Err(e)) => {
+            send_status(tx, rt, &describe_error("Restore failed", &e));
+            return;
+        }
+        None => {
+            send_status(tx, rt, &tr("status.message_cache_is_not_available"));
+            return;
+        }
+    };
+    if let Ok(mut s) = state.lock() {
+        if let Some(accounts) = accounts {
+            if s.active_account_id.is_none() {
+                s.active_account_id = accounts.first().map(|a| a.id.clone());
+            }
+            s.accounts = accounts;
+        }
+    }
+    if let Some(settings) = backup.settings.filter(|_| choice.restore_settings) {
+        let mut mgr = ConfigManager::default();
+        let _ = mgr.load();
+        match mgr.save_app_settings(&settings) {
+            Ok(()) => {
+                if let Ok(mut s) = state.lock() {
+                    s.thread_view = settings.thread_view_default;
+                    s.settings = settings.clone();
+                }
+                rate_limit::configure(settings.rate_limits());
+                logging::set_log_level(settings.logging_level());
+                if let Err(e) = convert_cached_content(cache, &settings) {
+                    tracing::error!("Failed to convert cached messages: {}", e);
+                }
+                let tx = tx.clone();
+                rt.spawn(async move {
+                    let _ = tx.send(UIUpdate::ThemeChanged(settings.theme_preference())).await;
+                    let _ = tx.send(UIUpdate::FontSizeChanged(settings.font_size)).await;
+                });
+            }
+            Err(e) => tracing::error!("Failed to restore settings: {}", e),
+        }
+    }
+    refresh_folder_tree(state, cache, tx, rt);
+    let mut status = trf(
+        "status.settings_restored",
+        &[&summary.added.to_string(), &summary.replaced.to_string(), &summary.skipped.to_string()],
+    );
+    if !summary.without_password.is_empty() {
+        status.push_str(". ");
+        status.push_str(&trf("status.settings_restored_without_password", &[&summary.without_password.join(", ")]));
+    }
+    send_status(tx, rt, &status);
+}
+
+/// Ask before deleting when `confirm_before_delete` is enabled.
+fn confirm_delete(frame: &Frame, count: usize) -> bool {
+    let text = if count == 1 {
+        "Delete the selected message?".to_string()
+    } else {
+        format!("Delete {} selected messages?", count)
+    };
+    let dlg = MessageDialog::builder(frame, &text, "Confirm Delete")
+        .with_style(MessageDialogStyle::YesNo | MessageDialogStyle::IconWarning)
+        .build();
+    dlg.show_modal() == ID_YES
+}
+
+/// Move the list selection to a single row, keeping it focused and visible.
+fn select_single_row(msg_list: &ListCtrl, previous: Option<usize>, target: usize) {
+    if let Some(prev) = previous.filter(|p| *p != target) {
+        msg_list.set_item_state(prev as i64, ListItemState::None, ListItemState::Selected);
+    }
+    msg_list.set_item_state(target as i64, ListItemState::Focused, ListItemState::Focused);
+    msg_list.set_item_state(target as i64, ListItemState::Selected, ListItemState::Selected);
+    msg_list.ensure_visible(target as i64);
+}
+
+/// Where `open_message` gets a message from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BodySource {
+    /// The cache when it has the message, otherwise the server
+    Cached,
+    /// The server, even when the message is cached
+    Server,
+    /// The whole message from the server, after only its text was shown
+    FullMessage,
+}
+
+/// Open a message in the preview pane.
+///
+/// Bodies are served from the cache when present so reopening is instant
+/// and works offline; otherwise they are downloaded and written back to the
+/// cache. A large message shows just its text until the whole of it is
+/// asked for with [`BodySource::FullMessage`]; that text isn't cached.
+fn open_message(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+    idx: usize,
+    source: BodySource,
+) {
+    let force_refresh = source != BodySource::Cached;
+    let (item, folder, offline, active_account) = {
+        let s = state.lock().unwrap();
+        (
+            s.messages.get(idx).cloned(),
+            s.mailbox(),
+            s.offline_mode,
+            s.active_account_id.clone(),
+        )
+    };
+    let Some(item) = item else { return };
+    let account_id = item.account_id.clone().or(active_account).unwrap_or_default();
+    if !force_refresh {
+        if let Ok(mut s) = state.lock() { s.plain_text_override = None; }
+    }
+    let trusted = EmailAddress::parse_list(&item.from).first().is_some_and(|sender| {
+        cache.with_reader(|c| c.is_trusted_sender(&account_id, &sender.address).ok())
+            .unwrap_or(false)
+    });
+    if let Ok(mut s) = state.lock() { s.sender_trusted = trusted; }
+
+    let (folder_id, cached_body) = cache
+        .with_reader(|c| {
+            let folder_id = c.get_message(item.message_id).ok()??.folder_id;
+            Some((Some(folder_id), c.get_message_body(folder_id, item.uid).ok().flatten()))
+        })
+        .unwrap_or((None, None));
+
+    let ctrl = message_controller(state, controllers, &item);
+    let mark_read_delay = state.lock().ok()
+        .and_then(|s| s.settings.mark_read_delay())
+        .filter(|_| !item.read);
+    let spam_threshold = state.lock().ok().and_then(|s| s.settings.spam_threshold());
+    let state = state.clone();
+    let cache = cache.clone();
+    let tx = tx.clone();
+    rt.spawn(async move {
+        if let Some(body) = cached_body.filter(|_| !force_refresh || offline) {
+            if force_refresh {
+                let _ = tx.send(UIUpdate::StatusUpdated(tr("status.offline_showing_the_cached_copy"))).await;
+            }
+            let _ = tx.send(UIUpdate::MessageBodyLoaded(body.clone())).await;
+            let _ = tx.send(UIUpdate::MessageOpened(item.subject.clone())).await;
+            if let Some(badge) = signature_badge(&body) {
+                let _ = tx.send(UIUpdate::SignatureChecked { message_id: item.message_id, badge }).await;
+            }
+            if let Some(auth) = SenderAuthentication::of_message(&body) {
+                let badge = TrustBadge::from(&auth);
+                let _ = tx.send(UIUpdate::AuthenticationChecked { message_id: item.message_id, badge }).await;
+            }
+            report_priority(&cache, &tx, &item, &body).await;
+            report_attachments(&tx, &item, &body).await;
+            report_invite(&cache, &tx, item.message_id, &account_id, &body).await;
+            report_delivery_failure(&cache, &tx, item.message_id, &account_id, &body).await;
+            report_mailing_list(&cache, &tx, folder_id, &item, &account_id, &body).await;
+            report_spam_score(&cache, &tx, &item, &account_id, &body, spam_threshold).await;
+            if let Some(receipt) = ReadReceipt::requested_by(&body) {
+                handle_receipt_request(&state, &cache, &tx, &item, receipt).await;
+            }
+            if let Some(delay) = mark_read_delay {
+                let ctrl = ctrl.filter(|_| !offline);
+                mark_read_after_open(&state, &cache, ctrl, &tx, &folder, &item, delay).await;
+            }
+            return;
+        }
+        let ctrl = match ctrl {
+            Some(ctrl) if !offline => ctrl,
+            _ => {
+                let _ = tx.send(UIUpdate::StatusUpdated(
+                    "This message has not been downloaded yet. Go online to read it.".into(),
+                )).await;
+                return;
+            }
+        };
+        let fetched = if source == BodySource::FullMessage {
+            ctrl.lock().await.fetch_message_body(&folder, item.uid).await.map(FetchedBody::Full)
+        } else {
+            ctrl.lock().await.fetch_message_preview(&folder, item.uid).await
+        };
+        let body = match fetched {
+            Ok(FetchedBody::Full(body)) => body,
+            Ok(FetchedBody::TextOnly { text, full_size }) => {
+                let _ = tx.send(UIUpdate::MessageBodyLoaded(text)).await;
+                let _ = tx.send(UIUpdate::TextOnlyLoaded { message_id: item.message_id, full_size }).await;
+                let _ = tx.send(UIUpdate::MessageOpened(item.subject.clone())).await;
+                // Listed from the message's structure, so known before download
+                if item.has_attachments {
+                    let names: Vec<String> = item.attachments.iter().map(|a| a.filename.clone()).collect();
+                    let warnings: Vec<String> = names.iter().filter_map(|n| filename_warning(n)).collect();
+                    let _ = tx.send(UIUpdate::AttachmentsFound { message_id: item.message_id, names }).await;
+                    if !warnings.is_empty() {
+                        let _ = tx.send(UIUpdate::AttachmentWarnings { message_id: item.message_id, warnings }).await;
+                    }
+                }
+                if let Some(delay) = mark_read_delay {
+                    mark_read_after_open(&state, &cache, Some(ctrl), &tx, &folder, &item, delay).await;
+                }
+                return;
+            }
+            Err(e) => {
+                let _ = tx.send(UIUpdate::ErrorOccurred(describe_error("Could not open message", &e))).await;
+                return;
+            }
+        };
+        if let Some(folder_id) = folder_id {
+            let (plain, html) = if looks_like_html(&body) {
+                (None, Some(body.as_str()))
+            } else {
+                (Some(body.as_str()), None)
+            };
+            if let Some(Err(e)) = cache.with_writer(|c| Some(c.save_message_body(folder_id, item.uid, plain, html))) {
+                tracing::warn!("Failed to cache message body: {}", e);
+            }
+        }
+        let _ = tx.send(UIUpdate::MessageBodyLoaded(body.clone())).await;
+        let _ = tx.send(UIUpdate::MessageOpened(item.subject.clone())).await;
+        if let Some(badge) = signature_badge(&body) {
+            let _ = tx.send(UIUpdate::SignatureChecked { message_id: item.message_id, badge }).await;
+        }
+        if let Some(auth) = SenderAuthentication::of_message(&body) {
+            let badge = TrustBadge::from(&auth);
+            let _ = tx.send(UIUpdate::AuthenticationChecked { message_id: item.message_id, badge }).await;
+        }
+        report_priority(&cache, &tx, &item, &body).await;
+        report_attachments(&tx, &item, &body).await;
+        report_invite(&cache, &tx, item.message_id, &account_id, &body).await;
+        report_delivery_failure(&cache, &tx, item.message_id, &account_id, &body).await;
+        report_mailing_list(&cache, &tx, folder_id, &item, &account_id, &body).await;
+        report_spam_score(&cache, &tx, &item, &account_id, &body, spam_threshold).await;
+        if let Some(receipt) = ReadReceipt::requested_by(&body) {
+            handle_receipt_request(&state, &cache, &tx, &item, receipt).await;
+        }
+        if let Some(delay) = mark_read_delay {
+            mark_read_after_open(&state, &cache, Some(ctrl), &tx, &folder, &item, delay).await;
+        }
+    });
+}
+
+/// Signature badge for a raw message, when built with S/MIME support.
+fn signature_badge(raw: &str) -> Option<SignatureBadge> {
+    #[cfg(feature = "smime")]
+    {
+        crate::service::smime::verify(raw).map(|v| SignatureBadge::from(&v))
+    }
+    #[cfg(not(feature = "smime"))]
+    {
+        let _ = raw;
+        None
+    }
+}
+
+/// Answer a read receipt request on an opened message per the receipt
+/// policy. Requests already answered are left alone, and nothing is sent
+/// while offline.
+/// List an opened message's attachments above the preview, with a warning
+/// for any that could be harmful, then decode its images for the preview
+/// strip in the background so a large image doesn't hold up the rest of
+/// opening the message.
+async fn report_attachments(tx: &Sender<UIUpdate>, item: &MessageItem, raw: &str) {
+    let attachments = extract_attachments(raw.as_bytes());
+    let names: Vec<String> = attachments.iter().map(|a| a.filename.clone()).collect();
+    if !names.is_empty() {
+        let _ = tx.send(UIUpdate::AttachmentsFound { message_id: item.message_id, names }).await;
+    }
+    let warnings: Vec<String> = attachments.iter().filter_map(Attachment::safety_warning).collect();
+    if !warnings.is_empty() {
+        let _ = tx.send(UIUpdate::AttachmentWarnings { message_id: item.message_id, warnings }).await;
+    }
+    let images: Vec<Attachment> = attachments.into_iter().filter(Attachment::is_image).take(MAX_IMAGE_PREVIEWS).collect();
+    let message_id = item.message_id;
+    let tx = tx.clone();
+    tokio::spawn(async move {
+        let previews = tokio::task::spawn_blocking(move || {
+            images.iter().filter_map(|a| a.image_preview(IMAGE_PREVIEW_SIDE)).collect()
+        })
+        .await
+        .unwrap_or_default();
+        let _ = tx.send(UIUpdate::ImagePreviews { message_id, previews }).await;
+    });
+}
+
+/// Show a calendar invitation in an opened message, remembering the event
+/// for the account. Replies to the account's own invitations aren't
+/// stored, as they would replace the event with the attendee's copy.
+async fn report_invite(
+    cache: &Option<CachePool>,
+    tx: &Sender<UIUpdate>,
+    message_id: i64,
+    account_id: &str,
+    raw: &str,
+) {
+    let Some(invite) = CalendarInvite::of_message(raw) else { return };
+    let response = cache.with_writer(|c| {
+        if invite.method != "REPLY" {
+            if let Err(e) = c.save_calendar_event(&invite.to_cached(account_id, Some(message_id), None)) {
+                tracing::warn!("Failed to save calendar event: {}", e);
+            }
+        }
+        c.get_calendar_event(account_id, &invite.uid).ok()??.response
+    });
+    let response = response.as_deref().and_then(InviteResponse::from_partstat);
+    let _ = tx.send(UIUpdate::InviteFound {
+        message_id,
+        account_id: account_id.to_string(),
+        invite: Box::new(invite),
+        response,
+    }).await;
+}
+
+/// Answer the invitation in the open message as the account it was sent
+/// to (or the identity it was addressed to), and remember the answer.
+fn respond_to_invite(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+    response: InviteResponse,
+) {
+    let Some((open, account, timeouts, offline)) = state.lock().ok().and_then(|s| {
+        let open = s.invite.clone()?;
+        let account = s.accounts.iter().find(|a| a.id == open.account_id).cloned()?;
+        Some((open, account, s.settings.network_timeouts(), s.offline_mode))
+    }) else {
+        return;
+    };
+    if offline {
+        send_status(tx, rt, &tr("status.go_online_to_answer_invitations"));
+        return;
+    }
+    let identities = cache.with_reader(|c| c.get_identities_for_account(&account.id).ok())
+        .unwrap_or_default();
+    let mut addresses = vec![account.email.as_str()];
+    addresses.extend(identities.iter().map(|i| i.from_email.as_str()));
+    let attendee = open.invite.attendee_for(&addresses).cloned().unwrap_or_else(|| Attendee {
+        email: account.email.clone(),
+        name: None,
+        response: None,
+    });
+    let (Some(organizer), Some(raw)) =
+        (open.invite.organizer.clone(), open.invite.reply_mime(&attendee, response, chrono::Utc::now()))
+    else {
+        send_status(tx, rt, &tr("status.this_invitation_has_no_organizer"));
+        return;
+    };
+    let req = SendEmailRequest {
+        from: Some(attendee.email.clone()),
+        from_name: attendee.name.clone(),
+        envelope_from: identities
+            .iter()
+            .find(|i| i.from_email.eq_ignore_ascii_case(&attendee.email))
+            .and_then(|i| i.envelope_from.clone())
+            .or_else(|| account.envelope_from.clone()),
+        to: vec![organizer.email.clone()],
+        ..SendEmailRequest::for_account(&account, timeouts)
+    };
+    let cache = cache.clone();
+    let tx = tx.clone();
+    rt.spawn(async move {
+        match MailController::new().send_calendar_reply(&req, &organizer.email, &raw).await {
+            Ok(()) => {
+                if let Some(Err(e)) =
+                    cache.with_writer(|c| Some(c.set_calendar_response(&open.account_id, &open.invite.uid, response.partstat())))
+                {
+                    tracing::warn!("Failed to save calendar response: {}", e);
+                }
+                let status = trf("status.invitation_answered", &[response.label(), &open.invite.summary, &organizer.email]);
+                let _ = tx.send(UIUpdate::StatusUpdated(status)).await;
+                let _ = tx.send(UIUpdate::InviteFound {
+                    message_id: open.message_id,
+                    account_id: open.account_id,
+                    invite: Box::new(open.invite),
+                    response: Some(response),
+                }).await;
+            }
+            Err(e) => {
+                let _ = tx.send(UIUpdate::ErrorOccurred(describe_error("Could not answer the invitation", &e))).await;
+            }
+        }
+    });
+}
+
+/// Invitation card above the preview: the event, the answer given so far,
+/// and buttons to accept, tentatively accept or decline it. The buttons
+/// are only shown for invitations that ask for an answer.
+struct InviteBar {
+    panel: Panel,
+    /// Containing pane, laid out again when the card shows or hides
+    pane: Panel,
+    text: StaticText,
+    /// In [`InviteResponse::ALL`] order
+    buttons: Vec<Button>,
+}
+
+impl InviteBar {
+    fn new(pane: &Panel) -> Self {
+        let panel = Panel::builder(pane).build();
+        let sizer = BoxSizer::builder(Orientation::Horizontal).build();
+        let text = StaticText::builder(&panel).with_label("").build();
+        sizer.add(&text, 1, SizerFlag::AlignCenterVertical | SizerFlag::All, 6);
+        let buttons = ["invite.accept", "invite.tentative", "invite.decline"]
+            .iter()
+            .map(|key| {
+                let button = Button::builder(&panel).with_label(&tr(key)).build();
+                sizer.add(&button, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+                button
+            })
+            .collect();
+        panel.set_sizer(sizer, true);
+        panel.show(false);
+        Self { panel, pane: *pane, text, buttons }
+    }
+
+    fn show(&self, invite: Option<(&CalendarInvite, Option<InviteResponse>)>) {
+        match invite {
+            Some((invite, response)) => {
+                let mut label = invite.description();
+                if let Some(response) = response {
+                    label.push_str(&trf("invite.answered", &[response.label()]));
+                }
+                self.text.set_label(&label);
+                self.text.set_tooltip(&label);
+                for button in &self.buttons {
+                    button.show(invite.is_request());
+                }
+                self.panel.show(true);
+            }
+            None => self.panel.show(false),
+        }
+        self.pane.layout();
+    }
+}
+
+/// Notice above the preview that only the text of a large message was
+/// downloaded, with a button to download the rest
+struct FullMessageBar {
+    panel: Panel,
+    /// Containing pane, laid out again when the notice shows or hides
+    pane: Panel,
+    text: StaticText,
+    button: Button,
+}
+
+impl FullMessageBar {
+    fn new(pane: &Panel) -> Self {
+        let panel = Panel::builder(pane).build();
+        let sizer = BoxSizer::builder(Orientation::Horizontal).build();
+        let text = StaticText::builder(&panel).with_label("").build();
+        sizer.add(&text, 1, SizerFlag::AlignCenterVertical | SizerFlag::All, 6);
+        let button = Button::builder(&panel).with_label(&tr("preview.download_full")).build();
+        sizer.add(&button, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+        panel.set_sizer(sizer, true);
+        panel.show(false);
+        Self { panel, pane: *pane, text, button }
+    }
+
+    /// Show the notice for a message of `full_size` bytes, or hide it
+    fn show(&self, full_size: Option<u64>) {
+        match full_size {
+            Some(size) => {
+                let label = trf("preview.text_only", &[&format_size(size as i64)]);
+                self.text.set_label(&label);
+                self.text.set_tooltip(&label);
+                self.panel.show(true);
+            }
+            None => self.panel.show(false),
+        }
+        self.pane.layout();
+    }
+}
+
+/// Mailing list notice above the preview, with a button to unsubscribe
+struct UnsubscribeBar {
+    panel: Panel,
+    /// Containing pane, laid out again when the notice shows or hides
+    pane: Panel,
+    text: StaticText,
+    button: Button,
+}
+
+impl UnsubscribeBar {
+    fn new(pane: &Panel) -> Self {
+        let panel = Panel::builder(pane).build();
+        let sizer = BoxSizer::builder(Orientation::Horizontal).build();
+        let text = StaticText::builder(&panel).with_label("").build();
+        sizer.add(&text, 1, SizerFlag::AlignCenterVertical | SizerFlag::All, 6);
+        let button = Button::builder(&panel).with_label(&tr("list.unsubscribe")).build();
+        sizer.add(&button, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+        panel.set_sizer(sizer, true);
+        panel.show(false);
+        Self { panel, pane: *pane, text, button }
+    }
+
+    fn show(&self, list: Option<&MailingList>) {
+        match list {
+            Some(list) => {
+                let label = match &list.id {
+                    Some(id) => trf("list.from_named", &[id]),
+                    None => tr("list.from_unnamed"),
+                };
+                self.text.set_label(&label);
+                self.text.set_tooltip(&label);
+                self.panel.show(true);
+            }
+            None => self.panel.show(false),
+        }
+        self.pane.layout();
+    }
+}
+
+/// Thumbnails of a message's image attachments, each captioned with its
+/// filename and description. The caption is the text screen readers read;
+/// the strip is hidden when there is nothing to show.
+struct ImageStrip {
+    panel: Panel,
+    /// Containing pane, laid out again when the strip shows or hides
+    pane: Panel,
+    slots: Vec<(StaticBitmap, StaticText)>,
+}
+
+impl ImageStrip {
+    fn new(pane: &Panel) -> Self {
+        let panel = Panel::builder(pane).build();
+        let sizer = BoxSizer::builder(Orientation::Horizontal).build();
+        let slots = (0..MAX_IMAGE_PREVIEWS)
+            .map(|_| {
+                let column = BoxSizer::builder(Orientation::Vertical).build();
+                let image = StaticBitmap::builder(&panel).build();
+                let caption = StaticText::builder(&panel).with_label("").build();
+                column.add(&image, 0, SizerFlag::AlignCenterHorizontal | SizerFlag::All, 4);
+                column.add(&caption, 0, SizerFlag::AlignCenterHorizontal | SizerFlag::All, 2);
+                sizer.add_sizer(&column, 0, SizerFlag::All, 4);
+                (image, caption)
+            })
+            .collect();
+        panel.set_sizer(sizer, true);
+        panel.show(false);
+        Self { panel, pane: *pane, slots }
+    }
+
+    fn show(&self, previews: &[ImagePreview]) {
+        let mut shown = 0;
+        for (i, (image, caption)) in self.slots.iter().enumerate() {
+            let preview = previews.get(i);
+            let bitmap = preview.and_then(|p| Bitmap::from_rgba(&p.rgba, p.width, p.height));
+            match (preview, bitmap) {
+                (Some(preview), Some(bitmap)) => {
+                    image.set_bitmap(&bitmap);
+                    image.set_tooltip(&preview.label);
+                    caption.set_label(&preview.label);
+                    image.show(true);
+                    caption.show(true);
+                    shown += 1;
+                }
+                _ => {
+                    image.show(false);
+                    caption.show(false);
+                }
+            }
+        }
+        self.panel.show(shown > 0);
+        self.pane.layout();
+    }
+}
+
+/// Flag a high-priority message in the preview, remembering a priority
+/// only learned now that the full headers are downloaded.
+async fn report_priority(cache: &Option<CachePool>, tx: &Sender<UIUpdate>, item: &MessageItem, raw: &str) {
+    let priority = message_priority(raw).unwrap_or(item.priority);
+    if priority != item.priority {
+        if let Some(Err(e)) = cache.with_writer(|c| Some(c.set_message_priority(item.message_id, priority))) {
+            tracing::warn!("Failed to save message priority: {}", e);
+        }
+    }
+    if priority != item.priority || priority.is_high() {
+        let _ = tx.send(UIUpdate::PriorityChecked { message_id: item.message_id, priority }).await;
+    }
+}
+
+/// If the opened message is a bounce, report it along with the cached copy
+/// of the undelivered message, found by its Message-ID.
+async fn report_delivery_failure(
+    cache: &Option<CachePool>,
+    tx: &Sender<UIUpdate>,
+    message_id: i64,
+    account_id: &str,
+    raw: &str,
+) {
+    let Some(failure) = DeliveryFailure::of_message(raw) else { return };
+    let original = failure.original_message_id.as_deref().and_then(|id| {
+        cache.with_reader(|c| c.find_message_by_message_id(account_id, id).ok().flatten().map(Box::new))
+    });
+    let _ = tx.send(UIUpdate::DeliveryFailed { message_id, failure, original }).await;
+}
+
+/// Show an opened message's spam score when the spam filter is on and
+/// rates it likely spam
+async fn report_spam_score(
+    cache: &Option<CachePool>,
+    tx: &Sender<UIUpdate>,
+    item: &MessageItem,
+    account_id: &str,
+    raw: &str,
+    threshold: Option<f64>,
+) {
+    let Some(threshold) = threshold else { return };
+    let Some(message) = storage::parse_raw_message(raw.as_bytes(), 0, item.uid) else { return };
+    let Some(score) = spam_score(cache.clone(), account_id.to_string(), message).await else { return };
+    if score >= threshold {
+        let _ = tx.send(UIUpdate::SpamScored { message_id: item.message_id, score }).await;
+    }
+}
+
+/// Remember which mailing list an opened message came from, for grouping,
+/// and offer to unsubscribe when the list says how.
+async fn report_mailing_list(
+    cache: &Option<CachePool>,
+    tx: &Sender<UIUpdate>,
+    folder_id: Option<i64>,
+    item: &MessageItem,
+    account_id: &str,
+    raw: &str,
+) {
+    let Some(list) = MailingList::of_message(raw) else { return };
+    if let (Some(folder_id), Some(list_id)) = (folder_id, list.id.as_deref()) {
+        if let Some(Err(e)) = cache.with_writer(|c| Some(c.set_list_id(folder_id, item.uid, Some(list_id)))) {
+            tracing::warn!("Failed to save list id: {}", e);
+        }
+    }
+    if list.unsubscribe.is_some() {
+        let _ = tx.send(UIUpdate::MailingListFound { message_id: item.message_id, account_id: account_id.to_string(), list }).await;
+    }
+}
+
+async fn handle_receipt_request(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    tx: &Sender<UIUpdate>,
+    item: &MessageItem,
+    receipt: ReadReceipt,
+) {
+    let (policy, account_id, offline) = {
+        let s = state.lock().unwrap();
+        let account_id = item.account_id.clone().or_else(|| s.active_account_id.clone());
+        (s.settings.receipt_policy(), account_id, s.offline_mode)
+    };
+    let Some(account_id) = account_id else { return };
+    let answered = cache.with_writer(|c| {
+        let status = c.get_receipt_status(item.message_id).ok().flatten();
+        if status.is_none() {
+            if let Err(e) = c.set_receipt_status(item.message_id, ReceiptStatus::Requested) {
+                tracing::warn!("Failed to record read receipt request: {}", e);
+            }
+        }
+        status
+    });
+    if matches!(answered, Some(ReceiptStatus::Sent | ReceiptStatus::Declined)) || offline {
+        return;
+    }
+    match policy {
+        ReceiptPolicy::Always => send_read_receipt(state, cache, tx, item.message_id, &account_id, &receipt, true).await,
+        ReceiptPolicy::Never => record_receipt_status(cache, item.message_id, ReceiptStatus::Declined),
+        ReceiptPolicy::Ask => {
+            let _ = tx.send(UIUpdate::ReadReceiptRequested {
+                message_id: item.message_id,
+                account_id,
+                receipt,
+            }).await;
+        }
+    }
+}
+
+/// Ask whether to send a read receipt, then send or decline it.
+#[allow(clippy::too_many_arguments)]
+fn ask_read_receipt(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+    message_id: i64,
+    account_id: &str,
+    receipt: ReadReceipt,
+) {
+    let text = format!(
+        "The sender of \"{}\" asked to be notified when you read this message.\n\nSend a read receipt to {}?",
+        receipt.original_subject, receipt.to
+    );
+    let dlg = MessageDialog::builder(frame, &text, "Read Receipt")
+        .with_style(MessageDialogStyle::YesNo | MessageDialogStyle::IconQuestion)
+        .build();
+    if dlg.show_modal() != ID_YES {
+        record_receipt_status(cache, message_id, ReceiptStatus::Declined);
+        return;
+    }
+    let state = state.clone();
+    let cache = cache.clone();
+    let tx = tx.clone();
+    let account_id = account_id.to_string();
+    rt.spawn(async move {
+        send_read_receipt(&state, &cache, &tx, message_id, &account_id, &receipt, false).await;
+    });
+}
+
+/// Send a read receipt from `account_id` and record it as sent.
+async fn send_read_receipt(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    tx: &Sender<UIUpdate>,
+    message_id: i64,
+    account_id: &str,
+    receipt: &ReadReceipt,
+    automatic: bool,
+) {
+    let Some((account, timeouts)) = state.lock().ok().and_then(|s| {
+        let account = s.accounts.iter().find(|a| a.id == account_id).cloned()?;
+        Some((account, s.settings.network_timeouts()))
+    }) else {
+        return;
+    };
+    let req = SendEmailRequest { to: vec![receipt.to.clone()], ..SendEmailRequest::for_account(&account, timeouts) };
+    match MailController::new().send_read_receipt(&req, receipt, automatic).await {
+        Ok(()) => {
+            record_receipt_status(cache, message_id, ReceiptStatus::Sent);
+            let _ = tx.send(UIUpdate::StatusUpdated(format!("Read receipt sent to {}", receipt.to))).await;
+        }
+        Err(e) => {
+            let _ = tx.send(UIUpdate::ErrorOccurred(describe_error("Could not send read receipt", &e))).await;
+        }
+    }
+}
+
+fn record_receipt_status(cache: &Option<CachePool>, message_id: i64, status: ReceiptStatus) {
+    if let Some(Err(e)) = cache.with_writer(|c| Some(c.set_receipt_status(message_id, status))) {
+        tracing::warn!("Failed to update read receipt status: {}", e);
+    }
+}
+
+/// Mark an opened message read once `delay` has passed, if it is still the
+/// selected message: STORE `\Seen` on the server, update the cache, and
+/// refresh the list row and unread count.
+async fn mark_read_after_open(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    ctrl: Option<Arc<TokioMutex<MailController>>>,
+    tx: &Sender<UIUpdate>,
+    folder: &str,
+    item: &MessageItem,
+    delay: std::time::Duration,
+) {
+    if !delay.is_zero() {
+        tokio::time::sleep(delay).await;
+    }
+    let still_selected = state.lock().map(|s| {
+        s.selected_message_index.and_then(|i| s.messages.get(i)).is_some_and(|m| m.uid == item.uid)
+    }).unwrap_or(false);
+    if !still_selected {
+        return;
+    }
+    if let Some(ctrl) = ctrl {
+        if let Err(e) = ctrl.lock().await.mark_as_read(folder, item.uid).await {
+            let _ = tx.send(UIUpdate::ErrorOccurred(format!("Could not mark message read: {}", e))).await;
+            return;
+        }
+    }
+    if let Some(Err(e)) = cache.with_writer(|c| Some(c.set_message_read(item.message_id, true))) {
+        tracing::warn!("Failed to update read flag in cache: {}", e);
+    }
+    let _ = tx.send(UIUpdate::MessageMarkedRead(item.message_id)).await;
+    let account_id = state.lock().ok().and_then(|s| s.active_account_id.clone()).unwrap_or_default();
+    let _ = tx.send(UIUpdate::FoldersLoaded(cached_folder_items(state, cache, &account_id))).await;
+}
+
+/// Show the selected message's whole conversation in the preview pane.
+fn read_thread(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let (item, account_id, remote_images) = {
+        let s = state.lock().unwrap();
+        let item = s.selected_message_index.and_then(|i| s.messages.get(i)).cloned();
+        (item, s.active_account_id.clone().unwrap_or_default(), s.settings.load_remote_images)
+    };
+    let Some((item, thread_id)) = item.and_then(|m| m.thread_id.clone().map(|t| (m, t))) else {
+        send_status(tx, rt, &tr("status.select_a_message_to_read_its"));
+        return;
+    };
+    let thread = cache.with_reader(|c| c.get_thread_messages(&account_id, &thread_id).ok());
+    let Some(thread) = thread.filter(|t| !t.is_empty()) else {
+        send_status(tx, rt, &tr("status.this_conversation_is_not_in_the"));
+        return;
+    };
+    let current = thread.iter().position(|m| m.id == item.message_id).unwrap_or(thread.len() - 1);
+    let renderer = HtmlRenderer::new().with_remote_images(remote_images);
+    let entries = thread
+        .iter()
+        .map(|m| ThreadEntry {
+            from: m.from_addr.clone(),
+            date: m.date.clone(),
+            subject: m.subject.clone(),
+            body: match (&m.body_plain, &m.body_html) {
+                (Some(plain), _) => Some(plain.clone()),
+                (None, Some(html)) => Some(renderer.render_for_accessibility(html).accessible_text),
+                (None, None) => None,
+            },
+        })
+        .collect();
+    send_thread(state, tx, rt, ThreadReader::new(entries, current));
+}
+
+/// Raw source of a message, from the cache or else downloaded (and cached
+/// if the "keep message source" setting is on). `None` when offline with
+/// nothing cached or the download fails.
+fn raw_source(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    rt: &Arc<Runtime>,
+    item: &MessageItem,
+) -> Option<String> {
+    let (folder, offline, keep) = {
+        let s = state.lock().unwrap();
+        (s.mailbox(), s.offline_mode, s.settings.cache_message_source)
+    };
+    let cached = cache.with_reader(|c| c.get_raw_source(item.message_id).ok().flatten());
+    match (cached, message_controller(state, controllers, item)) {
+        (Some(raw), _) => Some(raw),
+        (None, Some(ctrl)) if !offline => {
+            match rt.block_on(async { ctrl.lock().await.fetch_raw_message(&folder, item.uid).await }) {
+                Ok(bytes) => {
+                    let raw = String::from_utf8_lossy(&bytes).into_owned();
+                    if keep {
+                        let saved = cache.with_writer(|c| Some(c.save_raw_source(item.message_id, &raw)));
+                        if let Some(Err(e)) = saved {
+                            tracing::warn!("Could not cache message source: {}", e);
+                        }
+                    }
+                    Some(raw)
+                }
+                Err(e) => {
+                    tracing::warn!("Could not fetch message source: {}", e);
+                    None
+                }
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Save every attachment of the selected message into a chosen folder,
+/// reporting each file in the status bar.
+fn save_all_attachments(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let (item, offline, download_folder) = {
+        let s = state.lock().unwrap();
+        let item = s.selected_message_index.and_then(|i| s.messages.get(i)).cloned();
+        (item, s.offline_mode, s.settings.download_folder.clone())
+    };
+    let Some(item) = item else {
+        send_status(tx, rt, &tr("status.select_a_message_first"));
+        return;
+    };
+    let Some(raw) = raw_source(state, cache, controllers, rt, &item) else {
+        let key = if offline { "status.attachments_arent_available_offline" } else { "status.could_not_download_the_attachments" };
+        send_status(tx, rt, &tr(key));
+        return;
+    };
+    let attachments = extract_attachments(raw.as_bytes());
+    if attachments.is_empty() {
+        send_status(tx, rt, &tr("status.this_message_has_no_attachments"));
+        return;
+    }
+    let dlg = DirDialog::builder(frame, "Save attachments to", &download_folder.to_string_lossy()).build();
+    if dlg.show_modal() != ID_OK {
+        return;
+    }
+    let Some(dir) = dlg.get_path().map(PathBuf::from) else { return };
+    let results = AttachmentHandler.save_all(&attachments, &dir);
+    let saved = results.iter().filter(|(_, r)| r.is_ok()).count();
+    for (name, result) in &results {
+        let status = match result {
+            Ok(path) => trf("status.saved_attachment", &[&path.display().to_string()]),
+            Err(e) => trf("status.could_not_save_attachment", &[name, &e.to_string()]),
+        };
+        send_status(tx, rt, &status);
+    }
+    let total = results.len().to_string();
+    send_status(tx, rt, &trf("status.saved_attachments", &[&saved.to_string(), &total, &dir.display().to_string()]));
+}
+
+/// Open one attachment of the selected message with the program the
+/// system uses for its type, after saving it to the download folder.
+/// Programs and scripts are refused outright; an attachment with a safety
+/// warning is only opened once the user confirms it, with No as the
+/// default, and any other asks first unless that is turned off.
+fn open_attachment(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let (item, offline, download_folder, confirm, active) = {
+        let s = state.lock().unwrap();
+        let item = s.selected_message_index.and_then(|i| s.messages.get(i)).cloned();
+        (item, s.offline_mode, s.settings.download_folder.clone(), s.settings.confirm_open_attachments, s.active_account_id.clone())
+    };
+    let Some(item) = item else {
+        send_status(tx, rt, &tr("status.select_a_message_first"));
+        return;
+    };
+    // Trusted senders' attachments open without the routine question;
+    // warnings about disguised files are still shown
+    let account_id = item.account_id.clone().or(active).unwrap_or_default();
+    let trusted = EmailAddress::parse_list(&item.from).first().is_some_and(|sender| {
+        cache.with_reader(|c| c.is_trusted_sender(&account_id, &sender.address).ok())
+            .unwrap_or(false)
+    });
+    let Some(raw) = raw_source(state, cache, controllers, rt, &item) else {
+        let key = if offline { "status.attachments_arent_available_offline" } else { "status.could_not_download_the_attachments" };
+        send_status(tx, rt, &tr(key));
+        return;
+    };
+    let attachments = extract_attachments(raw.as_bytes());
+    let index = match attachments.len() {
+        0 => {
+            send_status(tx, rt, &tr("status.this_message_has_no_attachments"));
+            return;
+        }
+        1 => 0,
+        _ => {
+            let labels: Vec<String> = attachments.iter().map(Attachment::label).collect();
+            let Some(index) = show_choice_dialog(frame, "Open Attachment", "Attachment:", &labels) else { return };
+            index
+        }
+    };
+    let attachment = &attachments[index];
+    // Programs and scripts are never launched from here, whatever the setting
+    if attachment.is_program() {
+        let text = format!(
+            "{}.\n\nWixen Mail doesn't open programs or scripts from email. If you trust the sender, save it with Save All Attachments and check it before running it.",
+            attachment.safety_warning().unwrap_or_default()
+        );
+        MessageDialog::builder(frame, &text, "Open Attachment")
+            .with_style(MessageDialogStyle::OK | MessageDialogStyle::IconError)
+            .build()
+            .show_modal();
+        return;
+    }
+    let detected = attachment.sniffed_type().unwrap_or(attachment.mime_type.as_str());
+    let question = match attachment.safety_warning() {
+        Some(warning) => Some((
+            format!(
+                "{}.\n\nOpening it could harm your computer. Open it only if you expected it from the sender. Open it anyway?",
+                warning
+            ),
+            MessageDialogStyle::YesNo | MessageDialogStyle::NoDefault | MessageDialogStyle::IconWarning,
+        )),
+        None if confirm && !trusted => Some((
+            format!(
+                "Open \"{}\" ({}, {}) with its usual program?\n\nIt will be saved to {} first.",
+                attachment.filename,
+                detected,
+                format_size(attachment.size as i64),
+                download_folder.display()
+            ),
+            MessageDialogStyle::YesNo | MessageDialogStyle::IconQuestion,
+        )),
+        None => None,
+    };
+    if let Some((text, style)) = question {
+        if MessageDialog::builder(frame, &text, "Open Attachment").with_style(style).build().show_modal() != ID_YES {
+            return;
+        }
+    }
+    let result = AttachmentHandler
+        .save_for_opening(attachment, &download_folder)
+        .and_then(|path| open::that(&path).map(|_| path).map_err(Into::into));
+    match result {
+        Ok(path) => send_status(tx, rt, &trf("status.opened_attachment", &[&attachment.filename, &path.display().to_string()])),
+        Err(e) => send_status(tx, rt, &trf("status.could_not_open_attachment", &[&attachment.filename, &e.to_string()])),
+    }
+}
+
+/// Show the selected message's raw source, downloading it if it isn't cached.
+fn view_source(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let (item, offline) = {
+        let s = state.lock().unwrap();
+        let item = s.selected_message_index.and_then(|i| s.messages.get(i)).cloned();
+        (item, s.offline_mode)
+    };
+    let Some(item) = item else {
+        send_status(tx, rt, &tr("status.select_a_message_to_view_its"));
+        return;
+    };
+    match raw_source(state, cache, controllers, rt, &item) {
+        Some(raw) => show_source_dialog(frame, &format!("Source: {}", item.subject), &MessageSource::parse(&raw).render()),
+        None if offline => send_status(tx, rt, &tr("status.the_message_source_isnt_available_offline")),
+        None => send_status(tx, rt, &tr("status.could_not_download_the_message_source")),
+    }
+}
+
+/// Show the sent message an open bounce is about, or reopen it in the
+/// composer addressed to the recipients that failed.
+fn resend_undelivered(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let bounce = state.lock().ok().and_then(|s| {
+        let open = s.selected_message_index.and_then(|i| s.messages.get(i))?.message_id;
+        s.bounce.clone().filter(|b| b.message_id == open)
+    });
+    let Some(Bounce { failure, original, .. }) = bounce else {
+        send_status(tx, rt, &tr("status.open_a_delivery_failure_report_to"));
+        return;
+    };
+    let cached_body = original.as_ref().and_then(|o| {
+        cache.with_reader(|c| c.get_message_body(o.folder_id, o.uid).ok().flatten())
+    });
+    let body = cached_body
+        .map(|body| {
+            if looks_like_html(&body) {
+                HtmlRenderer::new().render_for_accessibility(&body).accessible_text
+            } else {
+                body
+            }
+        })
+        .or(failure.original_text.clone())
+        .unwrap_or_default();
+    let subject = original.as_ref().map(|o| o.subject.clone())
+        .or(failure.original_subject.clone())
+        .unwrap_or_default();
+
+    let choices = ["View the original message".to_string(), "Edit and send again".to_string()];
+    match show_choice_dialog(frame, "Resend Undelivered Message", &failure.summary(), &choices) {
+        Some(0) if body.is_empty() => send_status(tx, rt, &tr("status.the_text_of_the_undelivered_message")),
+        Some(0) => show_source_dialog(frame, &format!("Undelivered: {}", subject), &body),
+        Some(1) => {
+            let to: Vec<&str> = failure.recipients.iter().map(|r| r.address.as_str()).collect();
+            let data = CompositionData { to: to.join(", "), subject, body, ..CompositionData::default() };
+            open_compose(frame, state, cache, controllers, tx, rt, ComposeMode::Resend(data));
+        }
+        _ => {}
+    }
+}
+
+/// Switch the open message between plain text and formatted view.
+fn toggle_plain_text(state: &Arc<StdMutex<WxUIState>>, tx: &Sender<UIUpdate>, rt: &Arc<Runtime>) {
+    let (body, plain_text) = {
+        let mut s = state.lock().unwrap();
+        if s.selected_message_index.is_none() || s.thread_reader.is_some() {
+            drop(s);
+            send_status(tx, rt, &tr("status.open_a_message_to_change_how"));
+            return;
+        }
+        let plain_text = !s.plain_text_view();
+        s.plain_text_override = Some(plain_text);
+        (s.message_preview.clone(), plain_text)
+    };
+    let tx = tx.clone();
+    rt.spawn(async move {
+        let _ = tx.send(UIUpdate::MessageBodyLoaded(body)).await;
+        let label = if plain_text { "Showing plain text" } else { "Showing formatted message" };
+        let _ = tx.send(UIUpdate::StatusUpdated(label.into())).await;
+    });
+}
+
+/// Save the open message's view mode as the default for its sender.
+fn remember_view_for_sender(state: &Arc<StdMutex<WxUIState>>, tx: &Sender<UIUpdate>, rt: &Arc<Runtime>) {
+    let remembered = {
+        let mut s = state.lock().unwrap();
+        let sender = s.selected_message_index.and_then(|i| s.messages.get(i)).map(|m| m.from.clone());
+        sender.map(|sender| {
+            let plain_text = s.plain_text_view();
+            s.settings.remember_plain_text(&sender, Some(plain_text));
+            (sender, plain_text)
+        })
+    };
+    let Some((sender, plain_text)) = remembered else {
+        send_status(tx, rt, &tr("status.select_a_message_first"));
+        return;
+    };
+    let mut mgr = ConfigManager::default();
+    let _ = mgr.load();
+    let mut saved = mgr.app_config().clone();
+    saved.remember_plain_text(&sender, Some(plain_text));
+    if let Err(e) = mgr.save_app_settings(&saved) {
+        tracing::warn!("Failed to save view mode: {}", e);
+    }
+    let mode = if plain_text { "plain text" } else { "formatted" };
+    send_status(tx, rt, &format!("Messages from {} will open as {}", sender, mode));
+}
+
+/// Navigation within the open conversation.
+enum ThreadStep {
+    Next,
+    Previous,
+    ToggleQuotes,
+}
+
+fn step_thread(state: &Arc<StdMutex<WxUIState>>, tx: &Sender<UIUpdate>, rt: &Arc<Runtime>, step: ThreadStep) {
+    let Some(mut reader) = state.lock().ok().and_then(|s| s.thread_reader.clone()) else {
+        send_status(tx, rt, &tr("status.open_a_conversation_first_view_read"));
+        return;
+    };
+    let moved = match step {
+        ThreadStep::Next => reader.move_next(),
+        ThreadStep::Previous => reader.move_previous(),
+        ThreadStep::ToggleQuotes => {
+            reader.toggle_quotes();
+            true
+        }
+    };
+    if !moved {
+        let edge = if matches!(step, ThreadStep::Next) { "Last" } else { "First" };
+        send_status(tx, rt, &format!("{} message in the conversation", edge));
+        return;
+    }
+    send_thread(state, tx, rt, reader);
+}
+
+/// Remember the reader and ask the UI thread to show it.
+fn send_thread(state: &Arc<StdMutex<WxUIState>>, tx: &Sender<UIUpdate>, rt: &Arc<Runtime>, reader: ThreadReader) {
+    let (text, position) = reader.render();
+    let announcement = reader.announcement();
+    if let Ok(mut s) = state.lock() {
+        s.thread_reader = Some(reader);
+    }
+    let tx = tx.clone();
+    rt.spawn(async move {
+        let _ = tx.send(UIUpdate::ThreadRendered { text, position, announcement }).await;
+    });
+}
+
+/// Heuristic used to decide whether a body should be rendered as HTML.
+fn looks_like_html(body: &str) -> bool {
+    body.contains('<') && body.contains('>')
+}
+
+/// Put `text` on the clipboard; false if the clipboard couldn't be used
+fn copy_to_clipboard(text: &str) -> bool {
+    Clipboard::get().set_text(text)
+}
+
+/// Copy the open message's text, without its HTML, its sender's address
+/// or one of its links, chosen when it has several. Returns what to tell
+/// the user, or `None` when no link was chosen.
+fn copy_from_message(frame: &Frame, state: &Arc<StdMutex<WxUIState>>, id: Id) -> Option<String> {
+    let open = state.lock().ok().and_then(|s| {
+        let item = s.messages.get(s.selected_message_index?)?.clone();
+        Some((item, s.message_preview.clone()))
+    });
+    let Some((item, body)) = open else {
+        return Some(tr("status.select_a_message_first"));
+    };
+    let content = || {
+        let shown = displayed_body(&body);
+        let renderer = HtmlRenderer::new();
+        if looks_like_html(&shown) { renderer.render_for_egui(&shown) } else { renderer.render_plain_text(&shown) }
+    };
+    let (text, done) = match id {
+        _ if id == ID_COPY_SENDER => {
+            let address = EmailAddress::parse_list(&item.from)
+                .into_iter()
+                .next()
+                .map(|a| a.address)
+                .unwrap_or_else(|| item.from.clone());
+            let done = trf("status.copied_sender", &[&address]);
+            (address, done)
+        }
+        _ if id == ID_COPY_LINK => {
+            let links = content().links;
+            let index = match links.len() {
+                0 => return Some(tr("status.no_links")),
+                1 => 0,
+                _ => {
+                    let choices: Vec<String> = links.iter()
+                        .map(|l| if l.text.trim().is_empty() || l.text.trim() == l.url { l.url.clone() } else { format!("{} ({})", l.text.trim(), l.url) })
+                        .collect();
+                    show_choice_dialog(frame, "Copy Link", "Link:", &choices)?
+                }
+            };
+            let url = links[index].url.clone();
+            let done = trf("status.copied_link", &[&url]);
+            (url, done)
+        }
+        _ => (content().plain_text, tr("status.copied_text")),
+    };
+    Some(if copy_to_clipboard(&text) { done } else { tr("status.could_not_copy") })
+}
+
+/// Ask which folder to move the selection to.
+fn choose_move_target(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+) -> Option<BulkAction> {
+    let (folders, account_id) = state.lock().map(|s| {
+        (s.folders.clone(), s.active_account_id.clone().unwrap_or_default())
+    }).ok()?;
+    let idx = show_choice_dialog(frame, "Move Messages", "Move to folder:", &folders)?;
+    let folder = folders.get(idx)?.clone();
+    let folder_id = cache.with_reader(|c| c.get_folder(&account_id, &folder).ok()?)?.id;
+    Some(BulkAction::Move { folder_id, folder })
+}
+
+/// Ask which tag to apply to or remove from the selection.
+fn choose_tag(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+) -> Option<String> {
+    let account_id = state.lock().ok()?.active_account_id.clone().unwrap_or_default();
+    let tags = cache.with_reader(|c| c.get_tags_for_account(&account_id).ok())?;
+    let names: Vec<String> = tags
+        .iter()
+        .map(|t| match &t.shortcut {
+            Some(key) => format!("{} (key {})", t.name, key.to_uppercase()),
+            None => t.name.clone(),
+        })
+        .collect();
+    let idx = show_choice_dialog(frame, "Tag Messages", "Tag:", &names)?;
+    tags.get(idx).map(|t| t.id.clone())
+}
+
+/// Pop the most recent undoable action and replay its inverse.
+fn undo_last(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let Some(action) = state.lock().ok().and_then(|mut s| s.history.pop()) else {
+        send_status(tx, rt, &tr("status.nothing_to_undo"));
+        return;
+    };
+
+    // Put a restored message back into the visible list when it belongs here.
+    let mut refreshed = None;
+    cache.with_writer(|c| {
+        if let Err(e) = action.revert_in_cache(c) {
+            tracing::error!("Undo failed in cache: {}", e);
+        }
+        let restored = match &action {
+            UndoableAction::Delete { message_id, folder, .. } => Some((*message_id, folder)),
+            UndoableAction::Move { message_id, from_folder, .. } => Some((*message_id, from_folder)),
+            UndoableAction::RemoveTag { .. } => None,
+        };
+        if let Some((Ok(Some(msg)), folder)) = restored.map(|(id, f)| (c.get_message(id), f)) {
+            let mut s = state.lock().unwrap();
+            if s.selected_folder.as_deref().unwrap_or("INBOX") == folder.as_str()
+                && !s.messages.iter().any(|m| m.uid == msg.uid)
+            {
+                s.messages.push(MessageItem::from(&msg));
+                let order = s.sort_order;
+                sort_messages(&mut s.messages, order);
+                refreshed = Some(s.messages.clone());
+            }
+        }
+        Some(())
+    });
+
+    refresh_folder_tree(state, cache, tx, rt);
+
+    let ctrl = active_controller(state, controllers);
+    let tx = tx.clone();
+    rt.spawn(async move {
+        if let Some(ctrl) = ctrl {
+            if let Err(e) = ctrl.lock().await.revert_action(&action).await {
+                let _ = tx.send(UIUpdate::ErrorOccurred(format!("Undo failed on server: {}", e))).await;
+            }
+        }
+        if let Some(messages) = refreshed {
+            let _ = tx.send(UIUpdate::MessagesLoaded(messages)).await;
+        }
+        let _ = tx.send(UIUpdate::StatusUpdated(format!("Undone: {}", action.description()))).await;
+    });
+}
+
+/// Tag Manager: edit the active account's tags and their shortcut keys.
+fn handle_tag_mgr(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let account_id = state.lock().ok().and_then(|s| s.active_account_id.clone()).unwrap_or_default();
+    let tags = cache.with_reader(|c| c.get_tags_for_account(&account_id).ok()).unwrap_or_default();
+    let entries: Vec<TagEntry> = tags
+        .iter()
+        .map(|t| TagEntry {
+            id: t.id.clone(),
+            name: t.name.clone(),
+            color: t.color.clone(),
+            shortcut: t.shortcut.as_deref().and_then(|k| k.chars().next()),
+        })
+        .collect();
+    let TagManagerAction::Updated(updated) = wx_managers::show_tag_manager_dialog(frame, &entries) else { return };
+
+    let saved = cache.with_writer(|c| Some({
+        c.in_transaction(|c| {
+            for old in &tags {
+                if !updated.iter().any(|t| t.id == old.id) {
+                    c.delete_tag(&old.id)?;
+                } else if old.shortcut.is_some() {
+                    // Free every key first so keys can move between tags
+                    c.update_tag(&Tag { shortcut: None, ..old.clone() })?;
+                }
+            }
+            for entry in &updated {
+                let existing = tags.iter().find(|t| t.id == entry.id);
+                let tag = Tag {
+                    id: entry.id.clone(),
+                    account_id: account_id.clone(),
+                    name: entry.name.clone(),
+                    color: entry.color.clone(),
+                    created_at: existing.map(|t| t.created_at.clone()).unwrap_or_else(|| chrono::Utc::now().to_rfc3339()),
+                    shortcut: entry.shortcut.map(String::from),
+                };
+                if existing.is_some() { c.update_tag(&tag)? } else { c.create_tag(&tag)? }
+            }
+            Ok(())
+        })
+    }));
+    match saved {
+        Some(Ok(())) => send_status(tx, rt, &tr("status.tags_saved")),
+        Some(Err(e)) => send_status(tx, rt, &format!("Could not save tags: {}", e)),
+        None => send_status(tx, rt, &tr("status.tags_are_not_available")),
+    }
+}
+
+/// Always trust the selected message's sender, or everyone at their
+/// domain; or, if they are trusted already, stop trusting them
+fn trust_sender(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let (item, active) = {
+        let s = state.lock().unwrap();
+        (s.selected_message_index.and_then(|i| s.messages.get(i)).cloned(), s.active_account_id.clone())
+    };
+    let Some(item) = item else {
+        send_status(tx, rt, &tr("status.no_messages_selected"));
+        return;
+    };
+    let Some(sender) = EmailAddress::parse_list(&item.from).into_iter().next() else {
+        send_status(tx, rt, &tr("status.no_sender_to_trust"));
+        return;
+    };
+    let account_id = item.account_id.clone().or(active).unwrap_or_default();
+    let address = sender.address.to_lowercase();
+    let domain = address.rsplit_once('@').map(|(_, domain)| domain.to_string());
+    let entries: Vec<String> = std::iter::once(address.clone()).chain(domain).collect();
+    let trusted_by = cache.with_reader(|c| {
+        entries.iter().find(|e| c.is_sender_on(&account_id, SenderList::Trusted, e).unwrap_or(false)).cloned()
+    });
+
+    let (result, status) = if let Some(entry) = trusted_by {
+        let text = format!(
+            "Stop trusting {}?\n\nLinks in their mail will be checked again, it will be scored for spam, and opening attachments will ask first.",
+            entry
+        );
+        let dlg = MessageDialog::builder(frame, &text, "Always Trust Sender")
+            .with_style(MessageDialogStyle::YesNo | MessageDialogStyle::IconQuestion)
+            .build();
+        if dlg.show_modal() != ID_YES {
+            return;
+        }
+        let removed = cache.with_writer(|c| Some(c.remove_sender(&account_id, SenderList::Trusted, &entry)));
+        (removed, trf("status.sender_untrusted", &[&entry]))
+    } else {
+        let mut choices = vec![trf("trust.address", &[&address])];
+        if let Some(domain) = entries.get(1) {
+            choices.push(trf("trust.domain", &[domain]));
+        }
+        let Some(index) = show_choice_dialog(frame, "Always Trust Sender", "&Trust:", &choices) else { return };
+        let entry = &entries[index];
+        let added = cache.with_writer(|c| {
+            Some(c.add_sender(&account_id, SenderList::Trusted, entry).and_then(|_| c.is_trusted_sender(&account_id, &address)))
+        });
+        let status = match added {
+            // A block on the address still wins over trusting its domain
+            Some(Ok(false)) => trf("status.sender_still_blocked", &[entry, &address]),
+            _ => trf("status.sender_trusted", &[entry]),
+        };
+        (added.map(|r| r.map(|_| ())), status)
+    };
+    match result {
+        Some(Ok(())) => {}
+        Some(Err(e)) => {
+            send_status(tx, rt, &describe_error(&tr("status.could_not_update_trusted_senders"), &e));
+            return;
+        }
+        None => {
+            send_status(tx, rt, &tr("status.sender_lists_are_not_available"));
+            return;
+        }
+    }
+    let trusted = cache.with_reader(|c| c.is_trusted_sender(&account_id, &address).ok())
+        .unwrap_or(false);
+    if let Ok(mut s) = state.lock() { s.sender_trusted = trusted; }
+    send_status(tx, rt, &status);
+}
+
+/// Block the selected message's sender: their messages in the open folder
+/// go to Trash now, and new mail from them as it arrives
+fn block_sender(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let (item, active) = {
+        let s = state.lock().unwrap();
+        (s.selected_message_index.and_then(|i| s.messages.get(i)).cloned(), s.active_account_id.clone())
+    };
+    let Some(item) = item else {
+        send_status(tx, rt, &tr("status.no_messages_selected"));
+        return;
+    };
+    let Some(sender) = EmailAddress::parse_list(&item.from).into_iter().next() else {
+        send_status(tx, rt, &tr("status.no_sender_to_block"));
+        return;
+    };
+    let account_id = item.account_id.clone().or(active).unwrap_or_default();
+    let text = format!(
+        "Block {}?\n\nTheir messages in this folder move to Trash now, and new mail from them will go there too.",
+        sender.address
+    );
+    let dlg = MessageDialog::builder(frame, &text, "Block Sender")
+        .with_style(MessageDialogStyle::YesNo | MessageDialogStyle::IconWarning)
+        .build();
+    if dlg.show_modal() != ID_YES {
+        return;
+    }
+    let added = cache.with_writer(|c| Some(c.add_sender(&account_id, SenderList::Blocked, &sender.address)));
+    match added {
+        Some(Ok(())) => {}
+        Some(Err(e)) => {
+            send_status(tx, rt, &describe_error(&tr("status.could_not_block_sender"), &e));
+            return;
+        }
+        None => {
+            send_status(tx, rt, &tr("status.sender_lists_are_not_available"));
+            return;
+        }
+    }
+    if let Ok(mut s) = state.lock() {
+        let from_sender: HashSet<u32> = s
+            .messages
+            .iter()
+            .filter(|m| m.account_id.as_deref().is_none_or(|a| a == account_id))
+            .filter(|m| EmailAddress::parse_list(&m.from).iter().any(|a| a.address.eq_ignore_ascii_case(&sender.address)))
+            .map(|m| m.uid)
+            .collect();
+        s.selected_uids = from_sender;
+    }
+    dispose_selected(state, cache, controllers, tx, rt, Disposal::Trash);
+    send_status(tx, rt, &trf("status.sender_blocked", &[&sender.address]));
+}
+
+/// Leave the mailing list the open message came from, the way its
+/// `List-Unsubscribe` header offers: a one-click request, a message to
+/// send, or a web page. Nothing happens without the user's say-so.
+fn unsubscribe_from_list(
     frame: &Frame,
     state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    controllers: &Arc<HashMap<String, Arc<TokioMutex<MailController>>>>,
     tx: &Sender<UIUpdate>,
     rt: &Arc<Runtime>,
-    mode: ComposeMode,
 ) {
-    let (names, active) = state.lock().map(|s| {
-        let names: Vec<String> = s.accounts.iter().map(|a| a.email.clone()).collect();
-        let active = s.active_account_id.as_ref().and_then(|id| {
-            s.accounts.iter().position(|a| &a.id == id)
-        }).unwrap_or(0) as u32;
-        (names, active)
-    }).unwrap_or_default();
-
-    match wx_compose::show_compose_dialog(frame, mode, &names, active) {
-        ComposeResult::Send(data) => {
+    let open = state.lock().ok().and_then(|s| {
+        let open = s.selected_message_index.and_then(|i| s.messages.get(i))?.message_id;
+        let list = s.mailing_list.clone().filter(|l| l.message_id == open)?;
+        Some((list, s.settings.network_timeouts(), s.offline_mode))
+    });
+    let Some((OpenMailingList { list: MailingList { id, unsubscribe: Some(how) }, .. }, timeouts, offline)) = open else {
+        send_status(tx, rt, &tr("status.no_unsubscribe_link"));
+        return;
+    };
+    let name = id.unwrap_or_else(|| "this mailing list".to_string());
+    let confirm = |text: String| {
+        MessageDialog::builder(frame, &text, "Unsubscribe")
+            .with_style(MessageDialogStyle::YesNo | MessageDialogStyle::IconQuestion)
+            .build()
+            .show_modal()
+            == ID_YES
+    };
+    match how {
+        Unsubscribe::OneClick(url) => {
+            if offline {
+                send_status(tx, rt, &tr("status.go_online_to_unsubscribe"));
+                return;
+            }
+            if !confirm(format!("Unsubscribe from {}?\n\nThe list will be asked to remove you at {}.", name, url)) {
+                return;
+            }
             let tx = tx.clone();
-            let to = data.to.clone();
             rt.spawn(async move {
-                let _ = tx.send(UIUpdate::StatusUpdated(format!("Sending to {}...", to))).await;
+                let update = match unsubscribe::one_click(&url, timeouts).await {
+                    Ok(()) => UIUpdate::StatusUpdated(trf("status.unsubscribed", &[&name])),
+                    Err(e) => UIUpdate::ErrorOccurred(describe_error("Could not unsubscribe", &e)),
+                };
+                let _ = tx.send(update).await;
             });
         }
-        ComposeResult::SaveDraft(_data) => send_status(tx, rt, "Draft saved"),
-        ComposeResult::Cancelled => {}
+        Unsubscribe::Mail { to, subject, body } => {
+            let data = CompositionData { to, subject, body, ..CompositionData::default() };
+            open_compose(frame, state, cache, controllers, tx, rt, ComposeMode::Unsubscribe(data));
+        }
+        Unsubscribe::Web(url) => {
+            if !confirm(format!("Unsubscribe from {}?\n\nThis opens the list's unsubscribe page in your browser:\n{}", name, url)) {
+                return;
+            }
+            if let Err(e) = open::that(&url) {
+                send_status(tx, rt, &format!("Could not open {}: {}", url, e));
+            }
+        }
+    }
+}
+
+fn handle_sender_mgr(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let account_id = state.lock().ok().and_then(|s| s.active_account_id.clone()).unwrap_or_default();
+    let entries: Vec<SenderEntry> = cache
+        .with_reader(|c| {
+            let mut entries = Vec::new();
+            for list in SenderList::ALL {
+                entries.extend(c.get_senders(&account_id, list).ok()?.into_iter().map(|address| SenderEntry { address, list }));
+            }
+            Some(entries)
+        })
+        .unwrap_or_default();
+    let SenderManagerAction::Updated(updated) = wx_managers::show_sender_manager_dialog(frame, &entries) else { return };
+
+    let saved = cache.with_writer(|c| Some({
+        c.in_transaction(|c| {
+            for old in entries.iter().filter(|e| !updated.contains(e)) {
+                c.remove_sender(&account_id, old.list, &old.address)?;
+            }
+            for new in updated.iter().filter(|e| !entries.contains(e)) {
+                c.add_sender(&account_id, new.list, &new.address)?;
+            }
+            Ok(())
+        })
+    }));
+    match saved {
+        Some(Ok(())) => send_status(tx, rt, &tr("status.sender_lists_saved")),
+        Some(Err(e)) => send_status(tx, rt, &format!("Could not save sender lists: {}", e)),
+        None => send_status(tx, rt, &tr("status.sender_lists_are_not_available")),
+    }
+}
+
+fn handle_template_mgr(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let account_id = state.lock().ok().and_then(|s| s.active_account_id.clone()).unwrap_or_default();
+    let templates = cache.with_reader(|c| c.get_templates_for_account(&account_id).ok()).unwrap_or_default();
+    let entries: Vec<TemplateEntry> = templates
+        .iter()
+        .map(|t| TemplateEntry {
+            id: t.id.clone(),
+            name: t.name.clone(),
+            subject: t.subject.clone(),
+            body: t.body.clone(),
+            is_html: t.is_html,
+        })
+        .collect();
+    let TemplateManagerAction::Updated(updated) = wx_managers::show_template_manager_dialog(frame, &entries) else { return };
+
+    let saved = cache.with_writer(|c| Some({
+        c.in_transaction(|c| {
+            for old in &templates {
+                if !updated.iter().any(|t| t.id == old.id) {
+                    c.delete_template(&old.id)?;
+                }
+            }
+            for entry in &updated {
+                let existing = templates.iter().find(|t| t.id == entry.id);
+                let template = MessageTemplate {
+                    id: entry.id.clone(),
+                    account_id: account_id.clone(),
+                    name: entry.name.clone(),
+                    subject: entry.subject.clone(),
+                    body: entry.body.clone(),
+                    is_html: entry.is_html,
+                    created_at: existing.map(|t| t.created_at.clone()).unwrap_or_else(|| chrono::Utc::now().to_rfc3339()),
+                };
+                if existing.is_some() { c.update_template(&template)? } else { c.create_template(&template)? }
+            }
+            Ok(())
+        })
+    }));
+    match saved {
+        Some(Ok(())) => send_status(tx, rt, &tr("status.templates_saved")),
+        Some(Err(e)) => send_status(tx, rt, &format!("Could not save templates: {}", e)),
+        None => send_status(tx, rt, &tr("status.templates_are_not_available")),
+    }
+}
+
+fn handle_filter_mgr(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let account_id = state.lock().ok().and_then(|s| s.active_account_id.clone()).unwrap_or_default();
+    let rules = cache.with_reader(|c| c.get_filter_rules_for_account(&account_id).ok()).unwrap_or_default();
+    let entries: Vec<wx_managers::FilterRule> = rules
+        .iter()
+        .map(|r| wx_managers::FilterRule {
+            id: r.id.clone(),
+            name: r.name.clone(),
+            field: r.field.clone(),
+            match_type: r.match_type.clone(),
+            pattern: r.pattern.clone(),
+            case_sensitive: r.case_sensitive,
+            action_type: r.action_type.clone(),
+            action_value: r.action_value.clone().unwrap_or_default(),
+            enabled: r.enabled,
+        })
+        .collect();
+    let wx_managers::FilterManagerAction::Updated(updated) = wx_managers::show_filter_manager_dialog(frame, &entries) else {
+        return;
+    };
+
+    let saved = cache.with_writer(|c| Some({
+        c.in_transaction(|c| {
+            for old in &rules {
+                if !updated.iter().any(|r| r.id == old.id) {
+                    c.delete_filter_rule(&old.id)?;
+                }
+            }
+            for entry in &updated {
+                let existing = rules.iter().find(|r| r.id == entry.id);
+                let value = entry.action_value.trim();
+                let rule = MessageFilterRule {
+                    id: entry.id.clone(),
+                    account_id: account_id.clone(),
+                    name: entry.name.clone(),
+                    field: entry.field.clone(),
+                    match_type: entry.match_type.clone(),
+                    pattern: entry.pattern.clone(),
+                    case_sensitive: entry.case_sensitive,
+                    action_type: entry.action_type.clone(),
+                    action_value: (!value.is_empty()).then(|| value.to_string()),
+                    enabled: entry.enabled,
+                    created_at: existing.map(|r| r.created_at.clone()).unwrap_or_else(|| chrono::Utc::now().to_rfc3339()),
+                };
+                if existing.is_some() { c.update_filter_rule(&rule)? } else { c.create_filter_rule(&rule)? }
+            }
+            Ok(())
+        })
+    }));
+    match saved {
+        Some(Ok(())) => send_status(tx, rt, &tr("status.rules_saved")),
+        Some(Err(e)) => send_status(tx, rt, &format!("Could not save rules: {}", e)),
+        None => send_status(tx, rt, &tr("status.rules_are_not_available")),
+    }
+}
+
+fn handle_identity_mgr(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let Some(account) = state.lock().ok().and_then(|s| {
+        s.accounts.iter().find(|a| Some(&a.id) == s.active_account_id.as_ref()).cloned()
+    }) else {
+        send_status(tx, rt, &tr("status.add_an_account_before_sending_mail"));
+        return;
+    };
+    let (identities, signatures) = cache
+        .with_reader(|c| Some((c.get_identities_for_account(&account.id).ok()?, c.get_signatures_for_account(&account.id).ok()?)))
+        .unwrap_or_default();
+    let entries: Vec<IdentityEntry> = identities
+        .iter()
+        .map(|i| IdentityEntry {
+            id: i.id.clone(),
+            from_name: i.from_name.clone(),
+            from_email: i.from_email.clone(),
+            reply_to: i.reply_to.clone(),
+            envelope_from: i.envelope_from.clone(),
+            signature_id: i.signature_id.clone(),
+        })
+        .collect();
+    let signature_names: Vec<(String, String)> = signatures.iter().map(|s| (s.id.clone(), s.name.clone())).collect();
+    let to_identity = |entry: &IdentityEntry| Identity {
+        id: entry.id.clone(),
+        account_id: account.id.clone(),
+        from_name: entry.from_name.clone(),
+        from_email: entry.from_email.clone(),
+        reply_to: entry.reply_to.clone(),
+        envelope_from: entry.envelope_from.clone(),
+        signature_id: entry.signature_id.clone(),
+        created_at: identities.iter()
+            .find(|i| i.id == entry.id)
+            .map(|i| i.created_at.clone())
+            .unwrap_or_else(|| chrono::Utc::now().to_rfc3339()),
+    };
+    let check = |entry: &IdentityEntry| {
+        validate_identity(&account.email, &to_identity(entry)).map_err(input_problem)
+    };
+    let IdentityManagerAction::Updated(updated) =
+        wx_managers::show_identity_manager_dialog(frame, &account.email, &entries, &signature_names, &check)
+    else {
+        return;
+    };
+
+    let saved = cache.with_writer(|c| Some({
+        c.in_transaction(|c| {
+            for old in &identities {
+                if !updated.iter().any(|i| i.id == old.id) {
+                    c.delete_identity(&old.id)?;
+                }
+            }
+            for entry in &updated {
+                let identity = to_identity(entry);
+                if identities.iter().any(|i| i.id == entry.id) { c.update_identity(&identity)? } else { c.create_identity(&identity)? }
+            }
+            Ok(())
+        })
+    }));
+    match saved {
+        Some(Ok(())) => send_status(tx, rt, &tr("status.identities_saved")),
+        Some(Err(e)) => send_status(tx, rt, &format!("Could not save identities: {}", e)),
+        None => send_status(tx, rt, &tr("status.identities_are_not_available")),
     }
 }
 
-/// Handle Account Manager dialog result.
-fn handle_account_mgr(frame: &Frame, state: &Arc<StdMutex<WxUIState>>) {
-    let (accounts, active_id) = {
+/// Handle Account Manager dialog result, saving the accounts and deleting
+/// removed ones with their cached mail.
+fn handle_account_mgr(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    engine: &Option<Arc<MailEngine>>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let (accounts, active_id, theme) = {
         let s = state.lock().unwrap();
-        (s.accounts.clone(), s.active_account_id.clone())
+        (s.accounts.clone(), s.active_account_id.clone(), s.settings.theme_preference())
     };
-    if let AccountManagerAction::Updated(new) = wx_account_manager::show_account_manager_dialog(frame, &accounts, active_id.as_deref()) {
+    if let AccountManagerAction::Updated(new) = wx_account_manager::show_account_manager_dialog(frame, &accounts, active_id.as_deref(), theme) {
         let mut s = state.lock().unwrap();
         if !new.is_empty() {
             if s.active_account_id.as_ref().map_or(true, |id| !new.iter().any(|a| &a.id == id)) {
@@ -538,31 +6316,520 @@ fn handle_account_mgr(frame: &Frame, state: &Arc<StdMutex<WxUIState>>) {
             s.active_account_id = None;
         }
         tracing::info!("Accounts updated: {}", new.len());
-        s.accounts = new;
+        s.accounts = new.clone();
+        drop(s);
+        save_session(state);
+
+        let removed: Vec<String> =
+            accounts.into_iter().filter(|a| !new.iter().any(|n| n.id == a.id)).map(|a| a.id).collect();
+        let Some(engine) = engine.clone() else { return };
+        let tx = tx.clone();
+        rt.spawn(async move {
+            for account in &new {
+                if let Err(e) = engine.save_account(account) {
+                    let _ = tx.send(UIUpdate::StatusUpdated(format!("Could not save {}: {}", account.email, e))).await;
+                }
+            }
+            for account_id in &removed {
+                if let Err(e) = engine.delete_account(account_id).await {
+                    let _ = tx.send(UIUpdate::StatusUpdated(format!("Could not delete the account: {}", e))).await;
+                }
+            }
+        });
+    }
+}
+
+/// Prompt for the master password at startup and reload decrypted accounts.
+fn unlock_secrets(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let Ok(store) = MasterPasswordStore::default_location() else { return };
+    let Some((service, remember)) = wx_master_password::prompt_unlock(frame, &store) else {
+        send_status(tx, rt, &tr("status.locked_saved_passwords_are_unavailable_until"));
+        return;
+    };
+    if remember {
+        if let Err(e) = MasterPasswordStore::remember(&service) {
+            tracing::warn!("Could not remember master password: {}", e);
+        }
+    }
+    let unlocked = cache.as_ref().and_then(|pool| {
+        pool.reconfigure(|c| {
+            c.set_security(Some(service));
+            let upgraded = upgrade_plaintext_secrets(c);
+            let accounts = c.load_accounts().ok();
+            let quotas: HashMap<String, CachedQuota> = accounts.iter().flatten()
+                .filter_map(|a| Some((a.id.clone(), c.get_quota(&a.id).ok()??)))
+                .collect();
+            Ok((accounts, quotas, upgraded))
+        }).ok()
+    });
+    let (accounts, quotas, upgraded) = unlocked.unwrap_or((None, HashMap::new(), 0));
+    if let Ok(mut s) = state.lock() {
+        s.secrets_locked = false;
+        if let Some(accounts) = accounts {
+            if s.active_account_id.is_none() {
+                s.active_account_id = s.restore_session.as_ref()
+                    .and_then(|session| session.account_in(accounts.iter().map(|a| a.id.as_str())))
+                    .or_else(|| accounts.first().map(|a| a.id.clone()));
+            }
+            s.accounts = accounts;
+            s.quotas = quotas;
+        }
+    }
+    refresh_folder_tree(state, cache, tx, rt);
+    if upgraded > 0 {
+        send_status(tx, rt, &format!("Unlocked; {} secrets upgraded to encrypted storage", upgraded));
+    } else {
+        send_status(tx, rt, &tr("status.unlocked"));
+    }
+}
+
+/// Encrypt any legacy base64 secrets, returning how many were upgraded.
+fn upgrade_plaintext_secrets(cache: &MessageCache) -> usize {
+    match cache.count_plaintext_secrets() {
+        Ok(0) | Err(_) => 0,
+        Ok(_) => cache.reencrypt_plaintext_secrets().unwrap_or_else(|e| {
+            tracing::error!("Failed to upgrade legacy secrets: {}", e);
+            0
+        }),
+    }
+}
+
+/// Set or change the master password and re-encrypt stored secrets.
+fn handle_master_password(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    if state.lock().map(|s| s.secrets_locked).unwrap_or(false) {
+        unlock_secrets(frame, state, cache, tx, rt);
+        return;
+    }
+    let store = match MasterPasswordStore::default_location() {
+        Ok(store) => store,
+        Err(e) => {
+            send_status(tx, rt, &format!("Master password unavailable: {}", e));
+            return;
+        }
+    };
+    let Some(change) = wx_master_password::show_master_password_dialog(frame, store.is_set()) else {
+        return;
+    };
+    let rekey = |service: &SecurityService, store_record: &dyn Fn() -> Result<()>| -> Result<()> {
+        match cache {
+            Some(pool) => pool.reconfigure(|c| c.rekey_then(service.clone(), store_record)),
+            None => Ok(()),
+        }
+    };
+    let result = match change.current {
+        Some(current) => store.change(&current, &change.new, rekey),
+        None => store.set(&change.new, rekey),
+    };
+    match result {
+        Ok(_) => {
+            let upgraded = cache.with_writer(|c| Some(upgrade_plaintext_secrets(c))).unwrap_or(0);
+            // Message text waits for a master password before it is encrypted
+            let settings = state.lock().map(|s| s.settings.clone()).unwrap_or_default();
+            match convert_cached_content(cache, &settings) {
+                Ok(0) => {}
+                Ok(n) => tracing::info!("Encrypted {} cached messages", n),
+                Err(e) => tracing::error!("Failed to encrypt cached messages: {}", e),
+            }
+            let msg = if upgraded > 0 {
+                format!("Master password updated; {} secrets upgraded to encrypted storage", upgraded)
+            } else {
+                "Master password updated; saved credentials re-encrypted".to_string()
+            };
+            send_status(tx, rt, &msg);
+        }
+        Err(e) => {
+            tracing::error!("Master password change failed: {}", e);
+            let tx = tx.clone();
+            rt.spawn(async move {
+                let _ = tx.send(UIUpdate::ErrorOccurred(format!("Master password not changed: {}", e))).await;
+            });
+        }
+    }
+}
+
+/// Tools > Keyboard Shortcuts: save the changed shortcuts and rebuild the
+/// menu bar so they work straight away
+fn edit_shortcuts(frame: &Frame, state: &Arc<StdMutex<WxUIState>>, tx: &Sender<UIUpdate>, rt: &Arc<Runtime>) {
+    let current = keymap(state);
+    let commands: Vec<_> = menu_commands()
+        .into_iter()
+        .map(|(_, key, menu)| PaletteCommand::from_menu_label(key, &menu, &tr(key), &tr(&help_key(key))))
+        .collect();
+    let Some(edited) = wx_shortcuts::show_shortcuts_dialog(frame, &commands, &current) else { return };
+    let mut mgr = ConfigManager::default();
+    let _ = mgr.load();
+    let mut config = mgr.app_config().clone();
+    config.shortcuts = edited.saved();
+    if let Err(e) = mgr.save_app_settings(&config) {
+        tracing::error!("Failed to save keyboard shortcuts: {}", e);
+        send_status(tx, rt, &describe_error(&tr("status.shortcuts_not_saved"), &e));
+        return;
+    }
+    if let Ok(mut s) = state.lock() {
+        s.settings.shortcuts = config.shortcuts;
     }
+    frame.set_menu_bar(build_menu_bar(&edited));
+    send_status(tx, rt, &tr("status.shortcuts_saved"));
 }
 
-/// Open the Settings dialog and persist changes.
-fn handle_settings(frame: &Frame, tx: &Sender<UIUpdate>, rt: &Arc<Runtime>) {
-    use crate::data::config::ConfigManager;
+/// Open the Settings dialog, persist changes, and apply them immediately.
+fn handle_settings(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
     let mut mgr = ConfigManager::default();
     let _ = mgr.load();
     let config = mgr.app_config().clone();
-    match wx_settings::show_settings_dialog(frame, &config) {
+    let cache_bytes = cache.with_reader(|c| c.cache_size_bytes().ok());
+    match wx_settings::show_settings_dialog(frame, &config, cache_bytes) {
         wx_settings::SettingsResult::Updated(new_config) => {
-            *mgr.app_config_mut() = new_config;
-            if let Err(e) = mgr.save() {
+            if let Err(e) = mgr.save_app_settings(&new_config) {
                 tracing::error!("Failed to save settings: {}", e);
                 send_status(tx, rt, &format!("Settings save error: {}", e));
-            } else {
-                send_status(tx, rt, "Settings saved");
+                return;
+            }
+            let sort_changed = new_config.default_sort_order != config.default_sort_order;
+            if let Ok(mut s) = state.lock() {
+                if new_config.thread_view_default != config.thread_view_default {
+                    s.thread_view = new_config.thread_view_default;
+                }
+                s.settings = new_config.clone();
+            }
+            rate_limit::configure(new_config.rate_limits());
+            if new_config.log_level != config.log_level {
+                logging::set_log_level(new_config.logging_level());
+            }
+            if sort_changed {
+                // Folders sorted before keep their order
+                let folder = state.lock().ok().and_then(|s| s.selected_folder.clone());
+                let order = folder.and_then(|f| folder_sort_order(state, cache, &f));
+                apply_sort(state, tx, rt, order.unwrap_or(MailSortOption::from_config_key(&new_config.default_sort_order)));
+            }
+            if new_config.theme != config.theme {
+                let tx = tx.clone();
+                let theme = new_config.theme_preference();
+                rt.spawn(async move { let _ = tx.send(UIUpdate::ThemeChanged(theme)).await; });
+            }
+            if new_config.font_size != config.font_size {
+                let tx = tx.clone();
+                let size = new_config.font_size;
+                rt.spawn(async move { let _ = tx.send(UIUpdate::FontSizeChanged(size)).await; });
+            }
+            if new_config.content_encryption() != config.content_encryption() {
+                if content_encryption(&new_config) != new_config.content_encryption() {
+                    send_status(tx, rt, &tr("status.content_encryption_needs_master_password"));
+                    return;
+                }
+                match convert_cached_content(cache, &new_config) {
+                    Ok(0) => {}
+                    Ok(n) => {
+                        send_status(tx, rt, &trf("status.cached_messages_converted", &[&n.to_string()]));
+                        return;
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to convert cached messages: {}", e);
+                        send_status(tx, rt, &describe_error("Settings saved, but cached messages were not converted", &e));
+                        return;
+                    }
+                }
+            }
+            if new_config.prune_policy() != config.prune_policy() {
+                let accounts = state.lock().map(|s| s.accounts.clone()).unwrap_or_default();
+                let pruned = cache.with_writer(|c| Some(prune_cache(c, &accounts, &new_config))).unwrap_or(0);
+                if pruned > 0 {
+                    send_status(tx, rt, &format!("Settings saved; {} cached message bodies removed", pruned));
+                    return;
+                }
             }
+            send_status(tx, rt, &tr("status.settings_saved"));
         }
         wx_settings::SettingsResult::Cancelled => {}
     }
 }
 
+/// Message text to encrypt: what `settings` ask for once a master password
+/// protects the key, otherwise none.
+fn content_encryption(settings: &AppConfig) -> ContentEncryption {
+    match MasterPasswordStore::default_location() {
+        Ok(store) if store.is_set() => settings.content_encryption(),
+        _ => ContentEncryption::Off,
+    }
+}
+
+/// Switch the cache to the message encryption in `settings` and convert the
+/// messages already cached, returning how many changed.
+fn convert_cached_content(cache: &Option<CachePool>, settings: &AppConfig) -> Result<usize> {
+    let Some(pool) = cache else { return Ok(0) };
+    pool.reconfigure(|c| {
+        c.set_content_encryption(content_encryption(settings));
+        c.apply_content_encryption()
+    })
+}
+
+/// Apply the storage limits from settings to every account's cache, then
+/// reclaim the space once all the accounts are pruned.
+fn prune_cache(cache: &MessageCache, accounts: &[Account], settings: &AppConfig) -> usize {
+    let Some(policy) = settings.prune_policy() else { return 0 };
+    let pruned: usize = accounts
+        .iter()
+        .map(|a| cache.prune(&a.id, &policy).unwrap_or_else(|e| {
+            tracing::warn!("Cache pruning failed for {}: {}", a.id, e);
+            0
+        }))
+        .sum();
+    if pruned > 0 {
+        let tidied = if policy.vacuum { cache.compact() } else { cache.optimize() };
+        if let Err(e) = tidied {
+            tracing::warn!("Could not compact the cache after pruning: {}", e);
+        }
+    }
+    pruned
+}
+
+/// Step the text size, persist it, and ask the UI thread to re-apply fonts.
+fn zoom(state: &Arc<StdMutex<WxUIState>>, tx: &Sender<UIUpdate>, rt: &Arc<Runtime>, steps: i32) {
+    let settings = {
+        let mut s = state.lock().unwrap();
+        s.settings.zoom_font(steps);
+        s.settings.clone()
+    };
+    let mut mgr = ConfigManager::default();
+    let _ = mgr.load();
+    let mut saved = mgr.app_config().clone();
+    saved.font_size = settings.font_size;
+    if let Err(e) = mgr.save_app_settings(&saved) {
+        tracing::warn!("Failed to save zoom level: {}", e);
+    }
+    let tx = tx.clone();
+    rt.spawn(async move {
+        let _ = tx.send(UIUpdate::FontSizeChanged(settings.font_size)).await;
+    });
+}
+
+/// Apply the configured text size to the main panes.
+///
+/// The list is a little smaller and the preview a little larger than the
+/// base size, matching the original 10pt/11pt layout at 100%. Column widths
+/// grow with the font so text scrolls horizontally instead of clipping.
+fn apply_font_size(size: u32, folder_tree: &TreeCtrl, msg_list: &ListCtrl, preview: &RichTextCtrl) {
+    let pt = |delta: i32| (size as i32 + delta).max(6);
+    let font = |points: i32, family: FontFamily| Font::new_with_details(
+        points,
+        family.as_i32(),
+        FontStyle::Normal.as_i32(),
+        FontWeight::Normal.as_i32(),
+        false,
+        "",
+    );
+    if let Some(f) = font(pt(-2), FontFamily::Swiss) {
+        folder_tree.set_font(&f);
+        msg_list.set_font(&f);
+    }
+    if let Some(f) = font(pt(-1), FontFamily::Roman) {
+        preview.set_font(&f);
+    }
+    for (col, width) in LIST_COLUMN_W.iter().enumerate() {
+        msg_list.set_column_width(col as i64, width * size as i32 / DEFAULT_FONT_SIZE as i32);
+    }
+    folder_tree.refresh(true, None);
+    msg_list.refresh(true, None);
+    preview.refresh(true, None);
+}
+
+/// Recolour the main panes for a theme.
+///
+/// `Theme::System` restores the native defaults so the OS (including
+/// Windows high-contrast mode) stays in control.
+fn apply_theme(theme: Theme, folder_tree: &TreeCtrl, msg_list: &ListCtrl, preview: &RichTextCtrl) {
+    let rgb = |(r, g, b): (u8, u8, u8)| Colour::rgb(r, g, b);
+    match Palette::for_theme(theme) {
+        Some(p) => {
+            folder_tree.set_background_color(rgb(p.pane_background));
+            folder_tree.set_foreground_color(rgb(p.foreground));
+            msg_list.set_background_color(rgb(p.background));
+            msg_list.set_foreground_color(rgb(p.foreground));
+            preview.set_background_color(rgb(p.background));
+            preview.set_foreground_color(rgb(p.foreground));
+        }
+        None => {
+            folder_tree.set_background_color(Colour::rgb(245, 245, 250));
+            folder_tree.set_foreground_color(SystemSettings::get_colour(SystemColour::WindowText));
+            msg_list.set_background_color(SystemSettings::get_colour(SystemColour::Window));
+            msg_list.set_foreground_color(SystemSettings::get_colour(SystemColour::WindowText));
+            preview.set_background_color(SystemSettings::get_colour(SystemColour::Window));
+            preview.set_foreground_color(SystemSettings::get_colour(SystemColour::WindowText));
+        }
+    }
+    folder_tree.refresh(true, None);
+    msg_list.refresh(true, None);
+    preview.refresh(true, None);
+}
+
+/// Name the active account in the status bar, drawn in its accent colour
+/// so accounts are easy to tell apart, with its mailbox usage beside it.
+/// A nearly full mailbox turns the bar the warning colour instead.
+fn show_active_account(frame: &Frame, status_bar: &StatusBar, state: &Arc<StdMutex<WxUIState>>) {
+    let Ok(s) = state.lock() else { return };
+    let active = s.active_account_id.as_ref().and_then(|id| s.accounts.iter().find(|a| &a.id == id));
+    let warning_percent = s.settings.quota_warning_percent;
+    let usage = active
+        .and_then(|a| s.quotas.get(&a.id))
+        .and_then(MailboxUsage::of)
+        .filter(|_| warning_percent > 0);
+    frame.set_status_text(&usage.as_ref().map(MailboxUsage::label).unwrap_or_default(), 3);
+    match active {
+        Some(a) => {
+            frame.set_status_text(&format!("Account: {}", a.email), 2);
+            let accent = if usage.as_ref().is_some_and(|u| u.near_limit(warning_percent)) {
+                Some(warning_colour(s.settings.theme_preference()))
+            } else {
+                account_accent(s.settings.theme_preference(), &a.color)
+            };
+            match accent {
+                Some((r, g, b)) => status_bar.set_foreground_color(Colour::rgb(r, g, b)),
+                None => status_bar.set_foreground_color(SystemSettings::get_colour(SystemColour::WindowText)),
+            }
+        }
+        None => frame.set_status_text(&tr("status.no_account"), 2),
+    }
+    status_bar.refresh(true, None);
+}
+
+/// Show the vacation responder in the status bar whenever it is on or
+/// about to be, so it isn't left on by accident
+fn show_vacation_status(frame: &Frame, state: &Arc<StdMutex<WxUIState>>) {
+    let Ok(s) = state.lock() else { return };
+    let vacation = &s.settings.vacation;
+    let today = s.clock.today();
+    let text = match (vacation.start, vacation.end) {
+        _ if !vacation.enabled => String::new(),
+        (Some(start), _) if start > today => trf("status.vacation_from", &[&i18n::format_long_date(start)]),
+        (_, Some(end)) if vacation.is_active(today) => trf("status.vacation_on_until", &[&i18n::format_long_date(end)]),
+        _ if vacation.is_active(today) => tr("status.vacation_on"),
+        _ => String::new(),
+    };
+    frame.set_status_text(&text, 4);
+}
+
+/// Edit the vacation responder, save it, and show it in the status bar
+fn handle_vacation(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+) {
+    let current = state.lock().map(|s| s.settings.vacation.clone()).unwrap_or_default();
+    let Some(vacation) = wx_settings::show_vacation_dialog(frame, &current) else { return };
+    let mut mgr = ConfigManager::default();
+    let _ = mgr.load();
+    let mut saved = mgr.app_config().clone();
+    saved.vacation = vacation.clone();
+    if let Err(e) = mgr.save_app_settings(&saved) {
+        send_status(tx, rt, &format!("Settings save error: {}", e));
+        return;
+    }
+    // Each time away starts over, so everyone is answered again
+    if vacation.enabled && !current.enabled {
+        if let Some(Err(e)) = cache.with_writer(|c| Some(c.clear_vacation_replies())) {
+            tracing::warn!("Failed to clear vacation replies: {}", e);
+        }
+    }
+    let enabled = vacation.enabled;
+    if let Ok(mut s) = state.lock() {
+        s.settings.vacation = vacation;
+    }
+    show_vacation_status(frame, state);
+    send_status(tx, rt, &tr(if enabled { "status.vacation_replies_on" } else { "status.vacation_replies_off" }));
+}
+
+/// "120 words, 640 characters, about 1 minute to read"
+fn describe_reading_stats(stats: &ReadingStats) -> String {
+    let minutes = match stats.minutes {
+        0 => return tr("preview.empty_message"),
+        1 => tr("preview.one_minute"),
+        n => trf("preview.minutes", &[&n.to_string()]),
+    };
+    trf("preview.reading_stats", &[&stats.words.to_string(), &stats.characters.to_string(), &minutes])
+}
+
+/// Whether the message (cache id) is still the one open in the preview
+fn is_open(state: &Arc<StdMutex<WxUIState>>, message_id: i64) -> bool {
+    state.lock().map(|s| {
+        s.selected_message_index.and_then(|i| s.messages.get(i)).is_some_and(|m| m.message_id == message_id)
+    }).unwrap_or(false)
+}
+
+/// The lines shown above a message opened from search results: how many
+/// times the search text appears and where, then the subject and sender
+/// when they contain it. `None` when the list isn't showing a search.
+fn search_summary(state: &Arc<StdMutex<WxUIState>>, body: &str) -> Option<String> {
+    let s = state.lock().ok()?;
+    let query = s.search.as_ref()?;
+    let m = s.selected_message_index.and_then(|i| s.messages.get(i))?;
+    let hits = query.highlights(&m.subject, &m.from, body);
+    let mut lines = vec![hits.summary()];
+    if !hits.subject.is_empty() {
+        lines.push(format!("Subject: {}", m.subject));
+    }
+    if !hits.from.is_empty() {
+        lines.push(format!("From: {}", m.from));
+    }
+    Some(lines.join("\n"))
+}
+
+/// Bold and underline each occurrence of the current search text in the
+/// preview. Setting the preview's text clears the styling, so this runs
+/// again whenever the text changes.
+fn highlight_search_matches(state: &Arc<StdMutex<WxUIState>>, preview: &RichTextCtrl) {
+    let Some(query) = state.lock().ok().and_then(|s| s.search.clone()) else { return };
+    let text = preview.get_value();
+    // The control counts characters, not bytes
+    for range in query.find_in(&text) {
+        let start = text[..range.start].chars().count() as i64;
+        let end = start + text[range].chars().count() as i64;
+        preview.set_selection(start, end);
+        preview.apply_bold_to_selection();
+        preview.apply_underline_to_selection();
+    }
+    preview.set_insertion_point(0);
+}
+
 /// Process a single UIUpdate, updating widgets + accessibility.
+/// Put a badge line above the opened message and announce it, unless the
+/// user has already moved on to another message.
+fn show_message_badge(
+    state: &Arc<StdMutex<WxUIState>>,
+    preview: &RichTextCtrl,
+    frame: &Frame,
+    a11y: &Accessibility,
+    message_id: i64,
+    text: &str,
+    politeness: crate::presentation::accessibility::announcements::Politeness,
+) {
+    if is_open(state, message_id) {
+        preview.set_value(&format!("{}\n\n{}", text, preview.get_value()));
+        highlight_search_matches(state, preview);
+        frame.set_status_text(text, 0);
+        let _ = a11y.announce(text, politeness);
+    }
+}
+
 fn handle_update(
     update: &UIUpdate,
     state: &Arc<StdMutex<WxUIState>>,
@@ -570,46 +6837,175 @@ fn handle_update(
     msg_list: &ListCtrl,
     preview: &RichTextCtrl,
     frame: &Frame,
+    status_bar: &StatusBar,
     a11y: &Accessibility,
 ) {
-    use crate::presentation::accessibility::announcements::Priority;
+    use crate::presentation::accessibility::announcements::{Politeness, Priority};
     match update {
         UIUpdate::FoldersLoaded(folders) => {
-            if let Ok(mut s) = state.lock() { s.folders = folders.clone(); }
+            let paths: Vec<String> = folders.iter().map(|f| f.path.clone()).collect();
+            let unchanged = state.lock().map(|mut s| {
+                let unchanged = s.folders == paths;
+                s.folders = paths;
+                s.folder_items = folders.clone();
+                unchanged
+            }).unwrap_or(false);
+            // Same folders as before: relabel in place so the selection and
+            // focus stay put while counts change.
+            if unchanged {
+                if let Some(root) = folder_tree.get_root_item() {
+                    let mut child = folder_tree.get_first_child(&root).map(|(item, _)| item);
+                    for f in folders {
+                        let Some(item) = child else { break };
+                        folder_tree.set_item_text(&item, &f.label());
+                        folder_tree.set_item_bold(&item, f.unread > 0);
+                        child = folder_tree.get_next_sibling(&item);
+                    }
+                }
+                return;
+            }
             folder_tree.delete_all_items();
             if let Some(root) = folder_tree.add_root("Mail Folders", None, None) {
-                for f in folders { folder_tree.append_item(&root, f, None, None); }
+                for f in folders {
+                    if let Some(item) = folder_tree.append_item(&root, &f.label(), None, None) {
+                        folder_tree.set_item_bold(&item, f.unread > 0);
+                    }
+                }
                 folder_tree.expand(&root);
+                // First load after startup: reopen the last session's
+                // folder, or the Inbox if it is gone
+                let restore = state.lock().ok().and_then(|mut s| s.restore_session.take());
+                if let Some(path) = restore.map(|session| session.folder_in(&paths)) {
+                    let mut child = folder_tree.get_first_child(&root).map(|(item, _)| item);
+                    for f in folders {
+                        let Some(item) = child else { break };
+                        if f.path == path {
+                            folder_tree.select_item(&item);
+                            break;
+                        }
+                        child = folder_tree.get_next_sibling(&item);
+                    }
+                }
             }
             let msg = format!("{} folders loaded", folders.len());
             frame.set_status_text(&msg, 0);
             let _ = a11y.announce(&msg, Priority::Low);
         }
-        UIUpdate::MessagesLoaded(messages) => {
-            if let Ok(mut s) = state.lock() { s.messages = messages.clone(); }
+        UIUpdate::MessagesLoaded(loaded) => {
+            let mut messages = loaded.clone();
+            let (rows, refocus, selected, accounts, theme, now, shown) = state.lock().map(|mut s| {
+                let open = s.selected_message_index.and_then(|i| s.messages.get(i)).map(|m| m.message_id);
+                let mut rows = MessageRows::group(&mut messages, s.group_by, &s.collapsed_groups);
+                // Grouping can reorder the list, so find the open message again
+                s.selected_message_index = open.and_then(|id| messages.iter().position(|m| m.message_id == id));
+                s.messages = messages.clone();
+                s.selected_uids.retain(|uid| messages.iter().any(|m| m.uid == *uid));
+                // The quick filter hides rows within the folder or view;
+                // hidden messages are deselected so actions skip them
+                let mut shown = None;
+                if !s.quick_filter.is_empty() {
+                    let query = SearchQuery { text: s.quick_filter.clone(), folder: None, high_priority_only: false };
+                    let keep = |m: &MessageItem| query.matches(&m.subject, &m.from, m.priority);
+                    rows.filter(&messages, keep);
+                    s.selected_uids.retain(|uid| messages.iter().any(|m| m.uid == *uid && keep(m)));
+                    shown = Some(messages.iter().filter(|m| keep(m)).count());
+                }
+                s.rows = rows.clone();
+                s.focused_row = None;
+                let refocus = s.refocus_row.take();
+                (rows, refocus, s.selected_uids.clone(), s.accounts.clone(), s.settings.theme_preference(), s.clock.now(), shown)
+            }).unwrap_or_else(|_| (MessageRows::ungrouped(messages.len()), None, HashSet::new(), Vec::new(), Theme::System, chrono::Utc::now(), None));
             msg_list.delete_all_items();
-            for (i, m) in messages.iter().enumerate() {
-                let idx = i as i64;
+            for (row, r) in rows.iter().enumerate() {
+                let idx = row as i64;
+                let m = match r {
+                    ListRow::Header(header) => {
+                        msg_list.insert_item(idx, &header.title(), None);
+                        continue;
+                    }
+                    ListRow::Message(i) => &messages[*i],
+                };
                 msg_list.insert_item(idx, &m.subject, None);
-                msg_list.set_item_text_by_column(idx, 1, &m.from);
-                msg_list.set_item_text_by_column(idx, 2, &m.date);
-                msg_list.set_item_text_by_column(idx, 3, if m.read { "" } else { "NEW" });
+                // In All Inboxes, name the receiving account and draw the row
+                // in its accent colour
+                match m.account_id.as_ref().and_then(|id| accounts.iter().find(|a| &a.id == id)) {
+                    Some(account) => {
+                        msg_list.set_item_text_by_column(idx, 1, &format!("{} (to {})", m.from, account.email));
+                        let accent = m.account_color.as_deref().and_then(|hex| account_accent(theme, hex));
+                        if let Some((r, g, b)) = accent {
+                            msg_list.set_item_text_colour(idx, Colour::rgb(r, g, b));
+                        }
+                    }
+                    None => msg_list.set_item_text_by_column(idx, 1, &m.from),
+                }
+                msg_list.set_item_text_by_column(idx, 2, &i18n::format_date(&m.date));
+                msg_list.set_item_text_by_column(idx, 3, &m.status(now));
+                if m.is_overdue(now) {
+                    let (r, g, b) = warning_colour(theme);
+                    msg_list.set_item_text_colour(idx, Colour::rgb(r, g, b));
+                }
+                if selected.contains(&m.uid) {
+                    msg_list.set_item_state(idx, ListItemState::Selected, ListItemState::Selected);
+                }
+            }
+            if let Some(row) = refocus.filter(|r| *r < rows.len()) {
+                select_single_row(msg_list, None, row);
             }
             let unread = messages.iter().filter(|m| !m.read).count();
-            let msg = format!("{} messages, {} unread", messages.len(), unread);
+            let mut msg = match shown {
+                Some(shown) => format!("{} of {} messages match the filter, {} unread", shown, messages.len(), unread),
+                None => format!("{} messages, {} unread", messages.len(), unread),
+            };
+            let overdue = messages.iter().filter(|m| m.is_overdue(now)).count();
+            if overdue > 0 {
+                msg.push_str(&format!(", {} overdue for follow-up", overdue));
+            }
             frame.set_status_text(&msg, 0);
             let _ = a11y.announce(&msg, Priority::Normal);
         }
+        UIUpdate::MessageMarkedRead(message_id) => {
+            if let Ok(mut s) = state.lock() {
+                if let Some(idx) = s.messages.iter().position(|m| m.message_id == *message_id) {
+                    s.messages[idx].read = true;
+                    if let Some(row) = s.rows.row_of(idx) {
+                        msg_list.set_item_text_by_column(row as i64, 3, &s.messages[idx].status(s.clock.now()));
+                    }
+                    let s = &mut *s;
+                    for (row, title) in s.rows.recount(&s.messages) {
+                        msg_list.set_item_text_by_column(row as i64, 0, &title);
+                    }
+                }
+                let unread = s.messages.iter().filter(|m| !m.read).count();
+                frame.set_status_text(&format!("{} messages, {} unread", s.messages.len(), unread), 0);
+            }
+        }
         UIUpdate::MessageBodyLoaded(body) => {
-            if let Ok(mut s) = state.lock() { s.message_preview = body.clone(); }
-            let renderer = HtmlRenderer::new();
-            let is_html = body.contains('<') && body.contains('>');
-            if is_html {
-                let rendered = renderer.render_for_accessibility(body);
-                preview.set_value(&rendered.accessible_text);
+            let (plain_text, trusted, remote_images) = state.lock().map(|mut s| {
+                s.message_preview = body.clone();
+                s.thread_reader = None;
+                (s.plain_text_view(), s.sender_trusted, s.settings.load_remote_images)
+            }).unwrap_or((false, false, false));
+            let renderer = HtmlRenderer::new().with_link_warnings(!trusted).with_remote_images(remote_images || trusted);
+            let shown = displayed_body(body);
+            let stats = if looks_like_html(&shown) {
+                ReadingStats::of(&renderer.render_for_egui(&shown).plain_text)
+            } else {
+                ReadingStats::of(&shown)
+            };
+            if let Ok(mut s) = state.lock() { s.reading_stats = Some(stats); }
+            let text = if plain_text {
+                renderer.plain_text_view(body)
+            } else if looks_like_html(&shown) {
+                renderer.render_for_accessibility(&shown).accessible_text
             } else {
-                preview.set_value(body);
+                shown.into_owned()
+            };
+            let stats = describe_reading_stats(&stats);
+            match search_summary(state, &text) {
+                Some(summary) => preview.set_value(&format!("{}\n\n{}\n\n{}", summary, stats, text)),
+                None => preview.set_value(&format!("{}\n\n{}", stats, text)),
             }
+            highlight_search_matches(state, preview);
         }
         UIUpdate::ConnectionStatusChanged(status) => {
             if let Ok(mut s) = state.lock() { s.connection_status = status.clone(); }
@@ -619,23 +7015,26 @@ fn handle_update(
             if let Ok(mut s) = state.lock() { s.error_message = Some(error.clone()); }
             let msg = format!("Error: {}", error);
             frame.set_status_text(&msg, 0);
-            let _ = a11y.announce(&msg, Priority::High);
+            let _ = a11y.announce(&msg, Politeness::Assertive);
         }
         UIUpdate::StatusUpdated(status) => {
             if let Ok(mut s) = state.lock() { s.status_message = status.clone(); }
             frame.set_status_text(status, 0);
         }
         UIUpdate::EmailSent => {
-            frame.set_status_text("Email sent successfully", 0);
-            let _ = a11y.announce("Email sent successfully", Priority::Normal);
+            frame.set_status_text(&tr("status.email_sent_successfully"), 0);
+            let _ = a11y.announce(&tr("status.email_sent_successfully"), Politeness::Polite);
         }
         UIUpdate::OutboxSendResult { queue_id, success, error } => {
             if *success {
-                frame.set_status_text("Queued message sent", 0);
+                frame.set_status_text(&tr("status.queued_message_sent"), 0);
+                let _ = a11y.announce(&tr("status.queued_message_sent"), Politeness::Polite);
             } else {
                 let err = error.as_deref().unwrap_or("Unknown error");
                 tracing::error!("Outbox {} failed: {}", queue_id, err);
-                frame.set_status_text(&format!("Send failed: {}", err), 0);
+                let msg = format!("Send failed: {}", err);
+                frame.set_status_text(&msg, 0);
+                let _ = a11y.announce(&msg, Politeness::Assertive);
             }
         }
         UIUpdate::OfflineModeChanged(enabled) => {
@@ -649,6 +7048,217 @@ fn handle_update(
                 frame.set_status_text(&format!("{} queued", count), 0);
             }
         }
+        UIUpdate::MessageFocused(idx) => {
+            let item = state.lock().ok().and_then(|s| {
+                s.messages.get(*idx).cloned().map(|m| (m, s.messages.len(), s.clock.now()))
+            });
+            if let Some((m, total, now)) = item {
+                // The date column is abbreviated; hovering the list shows it in full
+                msg_list.set_tooltip(&i18n::format_full_date(&m.date));
+                let row = MessageRowSpeech {
+                    states: &m.spoken_states(now),
+                    from: &m.from,
+                    subject: &m.subject,
+                    date: &i18n::format_date(&m.date),
+                };
+                let _ = a11y.announce_message_focus(&row, idx + 1, total);
+            }
+        }
+        UIUpdate::ThreadRendered { text, position, announcement } => {
+            preview.set_value(text);
+            highlight_search_matches(state, preview);
+            preview.set_insertion_point(*position as i64);
+            preview.show_position(*position as i64);
+            frame.set_status_text(announcement, 0);
+            let _ = a11y.announce(announcement, Politeness::Polite);
+        }
+        UIUpdate::SignatureChecked { message_id, badge } => {
+            let politeness = match badge.state {
+                SignatureState::Valid => Politeness::Polite,
+                SignatureState::Untrusted | SignatureState::Invalid => Politeness::Assertive,
+            };
+            show_message_badge(state, preview, frame, a11y, *message_id, &badge.text(), politeness);
+        }
+        UIUpdate::AuthenticationChecked { message_id, badge } => {
+            let politeness = if badge.verified { Politeness::Polite } else { Politeness::Assertive };
+            show_message_badge(state, preview, frame, a11y, *message_id, &badge.text, politeness);
+        }
+        UIUpdate::PriorityChecked { message_id, priority } => {
+            let row = state.lock().ok().and_then(|mut s| {
+                let now = s.clock.now();
+                let (idx, m) = s.messages.iter_mut().enumerate().find(|(_, m)| m.message_id == *message_id)?;
+                m.priority = *priority;
+                let status = m.status(now);
+                Some((s.rows.row_of(idx)?, status))
+            });
+            if let Some((row, status)) = row {
+                msg_list.set_item_text_by_column(row as i64, 3, &status);
+            }
+            if priority.is_high() {
+                show_message_badge(state, preview, frame, a11y, *message_id, "High priority", Politeness::Polite);
+            }
+        }
+        UIUpdate::AttachmentsFound { message_id, names } => {
+            let text = if names.len() > 1 {
+                trf("status.attachments_save_all", &[&names.len().to_string(), &names.join(", ")])
+            } else {
+                trf("status.attachment", &[&names.join(", ")])
+            };
+            show_message_badge(state, preview, frame, a11y, *message_id, &text, Politeness::Polite);
+        }
+        UIUpdate::AttachmentWarnings { message_id, warnings } => {
+            let text = trf("status.attachment_warning", &[&warnings.join(". ")]);
+            show_message_badge(state, preview, frame, a11y, *message_id, &text, Politeness::Assertive);
+        }
+        UIUpdate::DeliveryFailed { message_id, failure, original } => {
+            let mut text = failure.summary();
+            if let Some(original) = original {
+                text.push_str(&format!(", sent {}", original.date));
+            }
+            text.push_str(". Choose Resend Undelivered Message from the Message menu to view or resend it.");
+            if let Ok(mut s) = state.lock() {
+                s.bounce = Some(Bounce { message_id: *message_id, failure: failure.clone(), original: original.as_deref().cloned() });
+            }
+            show_message_badge(state, preview, frame, a11y, *message_id, &text, Politeness::Assertive);
+        }
+        UIUpdate::SpamScored { message_id, score } => {
+            let text = trf("status.likely_spam", &[&format!("{:.0}", score * 100.0)]);
+            show_message_badge(state, preview, frame, a11y, *message_id, &text, Politeness::Polite);
+        }
+        UIUpdate::CertificateChanged(change) => {
+            let msg = format!(
+                "The security certificate for {} has changed, so Wixen Mail did not connect. \
+                 If your provider renewed it, choose Trust New Server Certificate from the Tools menu.",
+                change.server
+            );
+            if let Ok(mut s) = state.lock() {
+                s.certificate_change = Some(change.clone());
+            }
+            frame.set_status_text(&msg, 0);
+            let _ = a11y.announce(&msg, Politeness::Assertive);
+        }
+        UIUpdate::QuotaChecked { account_id, quota } => {
+            let (warning, email) = state.lock().map(|mut s| {
+                let percent = s.settings.quota_warning_percent;
+                let was_near = s.quotas.get(account_id)
+                    .and_then(MailboxUsage::of)
+                    .is_some_and(|u| u.near_limit(percent));
+                match quota {
+                    Some(quota) => s.quotas.insert(account_id.clone(), quota.clone()),
+                    None => s.quotas.remove(account_id),
+                };
+                let now_near = quota.as_ref()
+                    .and_then(MailboxUsage::of)
+                    .filter(|u| percent > 0 && u.near_limit(percent) && !was_near);
+                let email = s.accounts.iter().find(|a| &a.id == account_id).map(|a| a.email.clone());
+                (now_near, email)
+            }).unwrap_or((None, None));
+            show_active_account(frame, status_bar, state);
+            if let Some(usage) = warning {
+                let msg = format!(
+                    "Mailbox {} is {}% full ({}). New mail may bounce once it is full.",
+                    email.unwrap_or_default(), usage.percent, usage.detail
+                );
+                frame.set_status_text(&msg, 0);
+                let _ = a11y.announce(&msg, Politeness::Assertive);
+            }
+        }
+        // Intercepted by the poll timer, which can reach the runtime
+        UIUpdate::ReadReceiptRequested { .. } => {}
+        // Intercepted by the poll timer, which owns the image strip and the bars above the preview
+        UIUpdate::ImagePreviews { .. }
+        | UIUpdate::InviteFound { .. }
+        | UIUpdate::MailingListFound { .. }
+        | UIUpdate::TextOnlyLoaded { .. } => {}
+        UIUpdate::OfflineSyncProgress { folder, done, total } => {
+            frame.set_status_text(&format!("Downloading {} for offline use: {} of {}", folder, done, total), 0);
+        }
+        UIUpdate::OfflineSyncComplete { folder, messages, bodies } => {
+            let msg = format!("{} is available offline ({} messages, {} bodies downloaded)", folder, messages, bodies);
+            frame.set_status_text(&msg, 0);
+            let _ = a11y.announce(&msg, Politeness::Polite);
+        }
+        UIUpdate::FolderSynced { folder, messages, last_sync } => {
+            if state.lock().map(|s| s.selected_folder.as_deref() == Some(folder.as_str())).unwrap_or(false) {
+                folder_tree.set_tooltip(&describe_last_sync(last_sync.as_deref()));
+            }
+            let msg = trf("status.folder_synced", &[&folder, &messages.to_string()]);
+            frame.set_status_text(&msg, 0);
+            let _ = a11y.announce(&msg, Politeness::Polite);
+        }
+        UIUpdate::FolderChanged { name, last_sync } => {
+            let msg = match last_sync {
+                Some(at) => trf("status.folder_last_synced", &[&name, &i18n::format_date(&at)]),
+                None => format!("Folder {}", name),
+            };
+            frame.set_status_text(&format!("Loading {}...", name), 0);
+            let _ = a11y.announce(&msg, Politeness::Polite);
+        }
+        UIUpdate::MessageOpened(subject) => {
+            let subject = if subject.trim().is_empty() { "(no subject)" } else { subject.as_str() };
+            let msg = match state.lock().ok().and_then(|s| s.reading_stats) {
+                Some(stats) => format!("Opened: {}. {}", subject, describe_reading_stats(&stats)),
+                None => format!("Opened: {}", subject),
+            };
+            frame.set_status_text(&msg, 0);
+            let _ = a11y.announce(&msg, Politeness::Polite);
+        }
+        UIUpdate::NewMailArrived { account_id, count, from_lists, preview } => {
+            let msg = match count + from_lists {
+                0 => "No new mail".to_string(),
+                1 => "1 new message".to_string(),
+                n => format!("{} new messages", n),
+            };
+            frame.set_status_text(&msg, 0);
+            let _ = a11y.announce(&msg, Politeness::Polite);
+            if let Ok(mut guard) = state.lock() {
+                let s = &mut *guard;
+                let account = s.accounts.iter().find(|a| &a.id == account_id);
+                let now = s.clock.now();
+                s.notifier.add(&s.settings, account, *count, *from_lists, preview.clone(), now);
+            }
+            // Mail is checked often enough to follow the responder's dates
+            show_vacation_status(frame, state);
+        }
+        UIUpdate::SnoozedMessagesWoke(count) => {
+            let msg = match count {
+                1 => "A snoozed message is back".to_string(),
+                n => format!("{} snoozed messages are back", n),
+            };
+            frame.set_status_text(&msg, 0);
+            let _ = a11y.announce(&msg, Politeness::Polite);
+        }
+        UIUpdate::TagApplied { tag, count } => {
+            let noun = if *count == 1 { "message" } else { "messages" };
+            let msg = format!("Tagged {}: {} {}", tag, count, noun);
+            frame.set_status_text(&msg, 0);
+            let _ = a11y.announce(&msg, Politeness::Polite);
+        }
+        UIUpdate::FontSizeChanged(size) => {
+            apply_font_size(*size, folder_tree, msg_list, preview);
+            let percent = size * 100 / DEFAULT_FONT_SIZE;
+            let msg = format!("Zoom {}%", percent);
+            frame.set_status_text(&msg, 0);
+            let _ = a11y.announce(&msg, Politeness::Polite);
+        }
+        UIUpdate::ThemeChanged(theme) => {
+            apply_theme(*theme, folder_tree, msg_list, preview);
+            show_active_account(frame, status_bar, state);
+            let msg = format!("{} theme applied", theme.label());
+            frame.set_status_text(&msg, 0);
+            let _ = a11y.announce(&msg, Politeness::Polite);
+        }
+        UIUpdate::GroupFocused(description) => {
+            frame.set_status_text(description, 0);
+            let _ = a11y.announce(description, Politeness::Polite);
+        }
+        UIUpdate::SelectionChanged(count) => {
+            if *count > 1 {
+                let msg = format!("{} messages selected", count);
+                frame.set_status_text(&msg, 0);
+                let _ = a11y.announce(&msg, Priority::Normal);
+            }
+        }
         UIUpdate::OutboxFlushComplete(sent, failed) => {
             let msg = format!("Outbox flush: {} sent, {} failed", sent, failed);
             frame.set_status_text(&msg, 0);
@@ -661,73 +7271,220 @@ fn handle_update(
 // in wx_account_manager::run_oauth_flow(). The standalone OAuth Manager
 // dialog (wx_oauth) is retained for advanced manual token management.
 
-/// Flush all queued outbox messages (attempt to send via SMTP).
+/// Flush all queued outbox messages, sending them over one SMTP
+/// connection unless that is turned off in Settings.
 fn flush_outbox(
     state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
     tx: &Sender<UIUpdate>,
     rt: &Arc<Runtime>,
 ) {
-    let account_id = state.lock().ok().and_then(|s| s.active_account_id.clone());
+    let (account, timeouts, reuse_connection, shutdown) = {
+        let s = state.lock().unwrap();
+        let account = s.accounts.iter().find(|a| Some(&a.id) == s.active_account_id.as_ref()).cloned();
+        (account, s.settings.network_timeouts(), s.settings.reuse_smtp_connection, s.shutdown.clone())
+    };
+    let Some(account) = account else {
+        send_status(tx, rt, &tr("status.add_an_account_before_sending_mail"));
+        return;
+    };
+    let Some(working) = shutdown.guard() else {
+        return;
+    };
     let tx = tx.clone();
-    let cache_dir = dirs::cache_dir().map(|d| d.join("wixen-mail"));
+    // The window's cache, so sent copies are stored with its encryption
+    let cache = cache.clone();
 
     rt.spawn(async move {
-        let Some(dir) = cache_dir else {
-            let _ = tx.send(UIUpdate::ErrorOccurred("No cache directory available".into())).await;
-            return;
-        };
-        let cache = match crate::data::message_cache::MessageCache::new(dir, None) {
-            Ok(c) => c,
-            Err(e) => {
-                let _ = tx.send(UIUpdate::ErrorOccurred(format!("Cache error: {}", e))).await;
+        let _working = working;
+        let queued = match cache.with_reader(|c| Some(c.load_outbox_messages(&account.id))) {
+            Some(Ok(msgs)) => msgs,
+            Some(Err(e)) => {
+                let _ = tx.send(UIUpdate::ErrorOccurred(format!("Outbox load error: {}", e))).await;
                 return;
             }
-        };
-
-        let aid = account_id.as_deref().unwrap_or("default");
-        let queued = match cache.load_outbox_messages(aid) {
-            Ok(msgs) => msgs,
-            Err(e) => {
-                let _ = tx.send(UIUpdate::ErrorOccurred(format!("Outbox load error: {}", e))).await;
+            None => {
+                let _ = tx.send(UIUpdate::ErrorOccurred("Message cache is not available".into())).await;
                 return;
             }
         };
 
         if queued.is_empty() {
-            let _ = tx.send(UIUpdate::StatusUpdated("Outbox is empty".into())).await;
+            let _ = tx.send(UIUpdate::StatusUpdated(tr("status.outbox_is_empty"))).await;
             return;
         }
 
         let total = queued.len();
         let _ = tx.send(UIUpdate::StatusUpdated(format!("Sending {} queued messages...", total))).await;
 
+        let requests: Vec<SendEmailRequest> = queued
+            .iter()
+            .map(|msg| {
+                let identity = msg.identity_id.as_ref().and_then(|id| cache.with_reader(|c| c.get_identity(id).ok().flatten()));
+                SendEmailRequest {
+                    to: split_addresses(&msg.to_addr),
+                    cc: split_addresses(&msg.cc),
+                    bcc: split_addresses(&msg.bcc),
+                    subject: msg.subject.clone(),
+                    body: msg.body.clone(),
+                    request_receipt: msg.request_receipt,
+                    priority: msg.priority,
+                    ..send_request_as(&account, identity, timeouts)
+                }
+            })
+            .collect();
+        // Failing to reach the server fails every message with its reason;
+        // a server asking to slow down is also worth a status of its own
+        let mut throttled = None;
+        let mut reason = |e: crate::common::Error| {
+            if matches!(e, crate::common::Error::RateLimited(_)) {
+                throttled.get_or_insert_with(|| describe_error("Sending paused", &e));
+            }
+            e.to_string()
+        };
+        let results: Vec<std::result::Result<Vec<u8>, String>> =
+            match MailController::new().send_batch(&requests, reuse_connection).await {
+                Ok(results) => results.into_iter().map(|r| r.map_err(&mut reason)).collect(),
+                Err(e) => vec![Err(reason(e)); queued.len()],
+            };
+        let sent_folder = cache
+            .with_reader(|c| c.get_special_folder(&account.id, SpecialFolder::Sent.folder_type()).ok().flatten())
+            .unwrap_or_else(|| SpecialFolder::Sent.default_name().to_string());
+
         let mut sent = 0usize;
         let mut failed = 0usize;
-
-        for msg in &queued {
-            // Attempt SMTP send via MailController
-            // For now, record the attempt and report result through the channel
-            let result_ok = false; // Placeholder: real send would go through MailController
-            if result_ok {
-                let _ = cache.delete_outbox_message(&msg.id);
-                sent += 1;
-            } else {
-                let _ = cache.update_outbox_failure(&msg.id, "SMTP send not yet wired");
-                failed += 1;
-            }
+        for (msg, result) in queued.iter().zip(results) {
+            let error = match result {
+                Ok(raw) => {
+                    let stored = cache.with_writer(|c| {
+                        let _ = c.delete_outbox_message(&msg.id);
+                        account.save_sent_copy.then(|| Storage::store_sent_message(c, &account.id, &sent_folder, &raw))
+                    });
+                    if let Some(Err(e)) = stored {
+                        tracing::warn!("Failed to cache sent message: {}", e);
+                    }
+                    sent += 1;
+                    None
+                }
+                Err(e) => {
+                    let _ = cache.with_writer(|c| c.update_outbox_failure(&msg.id, &e).ok());
+                    failed += 1;
+                    Some(e)
+                }
+            };
             let _ = tx.send(UIUpdate::OutboxSendResult {
                 queue_id: msg.id.clone(),
-                success: result_ok,
-                error: if result_ok { None } else { Some("SMTP send pending full wiring".into()) },
+                success: error.is_none(),
+                error,
             }).await;
         }
 
         let _ = tx.send(UIUpdate::OutboxFlushComplete(sent, failed)).await;
-        let remaining = cache.load_outbox_messages(aid).map(|v| v.len()).unwrap_or(0);
+        if let Some(status) = throttled {
+            let _ = tx.send(UIUpdate::StatusUpdated(status)).await;
+        }
+        let remaining = cache.with_reader(|c| c.load_outbox_messages(&account.id).ok()).map_or(0, |v| v.len());
         let _ = tx.send(UIUpdate::OutboxQueueCount(remaining)).await;
     });
 }
 
+/// Stop background work, wait up to [`SHUTDOWN_WAIT`] for sends in
+/// progress, save the session with the window size, and close the cache.
+fn shut_down(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    rt: &Arc<Runtime>,
+) {
+    let size = frame.get_size();
+    let Some(shutdown) = state.lock().ok().map(|mut s| {
+        s.window_size = (size.width, size.height);
+        s.shutdown.clone()
+    }) else {
+        return;
+    };
+    if shutdown.is_stopping() {
+        return;
+    }
+    if !rt.block_on(shutdown.stop(SHUTDOWN_WAIT)) {
+        tracing::warn!("Quitting with {} sends still in progress", shutdown.in_flight());
+    }
+    save_session(state);
+    let removed = printing::remove_print_pages();
+    if removed > 0 {
+        tracing::debug!("Deleted {} print pages", removed);
+    }
+    if let Some(Err(e)) = cache.as_ref().map(CachePool::checkpoint) {
+        tracing::warn!("Could not close message cache: {}", e);
+    }
+}
+
+/// Show the desktop notification for new mail, once bursts have been
+/// gathered into one.
+fn show_due_notification(frame: &Frame, state: &Arc<StdMutex<WxUIState>>) {
+    let due = state.lock().ok().and_then(|mut s| {
+        let now = s.clock.now();
+        s.notifier.take_due(now)
+    });
+    if let Some(note) = due {
+        show_desktop_notification(frame, &note);
+    }
+}
+
+/// Pop up `note` through the system's notification area.
+fn show_desktop_notification(frame: &Frame, note: &Notification) {
+    let popup = NotificationMessage::builder(frame)
+        .with_title(&note.title)
+        .with_message(&note.body)
+        .build();
+    popup.show(NOTIFICATION_TIMEOUT_AUTO);
+    if note.sound {
+        wxdragon::utils::bell();
+    }
+}
+
+/// Write where the user is now to `session.json`, for the next start.
+fn save_session(state: &Arc<StdMutex<WxUIState>>) {
+    let Some(session) = state.lock().ok().map(|s| s.session()) else { return };
+    if let Err(e) = ConfigManager::default().save_session(&session) {
+        tracing::warn!("Could not save session: {}", e);
+    }
+}
+
+/// Sort order for `folder` when it is selected: the one last chosen for it,
+/// or the default order for folders never sorted. `None` for All Inboxes
+/// and Follow-up, which keep the current order.
+fn folder_sort_order(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    folder: &str,
+) -> Option<MailSortOption> {
+    if folder == ALL_INBOXES_PATH || folder == FOLLOW_UP_PATH {
+        return None;
+    }
+    let (account_id, default) = state.lock().ok().map(|s| (s.active_account_id.clone(), s.settings.default_sort_order.clone()))?;
+    let stored = account_id.and_then(|id| cache.with_reader(|c| c.get_folder_sort_order(&id, folder).ok().flatten()));
+    Some(MailSortOption::from_config_key(stored.as_deref().unwrap_or(&default)))
+}
+
+/// Sort the message list from the View menu, remembering the order for
+/// the selected folder
+fn sort_folder(
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+    order: MailSortOption,
+) {
+    let target = state.lock().ok().and_then(|s| Some((s.active_account_id.clone()?, s.selected_folder.clone()?)));
+    if let Some((account_id, folder)) = target.filter(|(_, f)| f != ALL_INBOXES_PATH && f != FOLLOW_UP_PATH) {
+        if let Some(Err(e)) = cache.with_writer(|c| Some(c.set_folder_sort_order(&account_id, &folder, order.as_config_key()))) {
+            tracing::warn!("Could not remember the sort order for {}: {}", folder, e);
+        }
+    }
+    apply_sort(state, tx, rt, order);
+}
+
 /// Apply a sort order to the current message list and re-render.
 fn apply_sort(
     state: &Arc<StdMutex<WxUIState>>,
@@ -742,6 +7499,7 @@ fn apply_sort(
         sort_messages(&mut msgs, order);
         msgs
     };
+    save_session(state);
     let label = match order {
         MailSortOption::DateNewestFirst => "Sorted: Date (Newest First)",
         MailSortOption::DateOldestFirst => "Sorted: Date (Oldest First)",
@@ -759,11 +7517,86 @@ fn apply_sort(
     send_status(&tx3, rt, label);
 }
 
+/// Group the message list by `by` and re-render, with every group expanded.
+fn apply_grouping(state: &Arc<StdMutex<WxUIState>>, tx: &Sender<UIUpdate>, rt: &Arc<Runtime>, by: GroupBy) {
+    let messages = {
+        let mut s = state.lock().unwrap();
+        s.group_by = by;
+        s.collapsed_groups.clear();
+        s.messages.clone()
+    };
+    save_session(state);
+    let label = match by {
+        GroupBy::None => tr("status.messages_not_grouped"),
+        GroupBy::Sender => tr("status.grouped_by_sender"),
+        GroupBy::Domain => tr("status.grouped_by_domain"),
+        GroupBy::List => tr("status.grouped_by_list"),
+    };
+    let tx2 = tx.clone();
+    rt.spawn(async move {
+        let _ = tx2.send(UIUpdate::MessagesLoaded(messages)).await;
+    });
+    send_status(tx, rt, &label);
+}
+
+/// Collapse or expand the group whose header is in `row`.
+fn toggle_group(state: &Arc<StdMutex<WxUIState>>, tx: &Sender<UIUpdate>, rt: &Arc<Runtime>, row: usize) {
+    let collapsed = state.lock().ok().and_then(|s| match s.rows.get(row)? {
+        ListRow::Header(header) => Some(header.collapsed),
+        ListRow::Message(_) => None,
+    });
+    if let Some(collapsed) = collapsed {
+        set_group_collapsed(state, tx, rt, row, !collapsed);
+    }
+}
+
+/// Collapse the group holding the focused row, or expand the focused
+/// group header. Returns whether there was a group to change.
+fn collapse_group(state: &Arc<StdMutex<WxUIState>>, tx: &Sender<UIUpdate>, rt: &Arc<Runtime>, collapse: bool) -> bool {
+    let row = state.lock().ok().and_then(|s| {
+        let focused = s.focused_row?;
+        let (row, header) = s.rows.header_for(focused)?;
+        // Expanding only applies to the header itself
+        (collapse || row == focused).then_some((row, header.collapsed))
+    });
+    match row {
+        Some((row, collapsed)) => {
+            if collapsed != collapse {
+                set_group_collapsed(state, tx, rt, row, collapse);
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Collapse or expand the group whose header is in `row`, keeping the
+/// header focused once the list is redrawn.
+fn set_group_collapsed(state: &Arc<StdMutex<WxUIState>>, tx: &Sender<UIUpdate>, rt: &Arc<Runtime>, row: usize, collapse: bool) {
+    let messages = {
+        let mut guard = state.lock().unwrap();
+        let s = &mut *guard;
+        let Some(ListRow::Header(header)) = s.rows.get(row) else { return };
+        if collapse {
+            s.collapsed_groups.insert(header.key.clone());
+        } else {
+            s.collapsed_groups.remove(&header.key);
+        }
+        s.refocus_row = Some(row);
+        s.messages.clone()
+    };
+    let tx = tx.clone();
+    rt.spawn(async move {
+        let _ = tx.send(UIUpdate::MessagesLoaded(messages)).await;
+    });
+}
+
 /// Sort messages in-place according to the given sort option.
 fn sort_messages(messages: &mut [MessageItem], order: MailSortOption) {
     match order {
-        MailSortOption::DateNewestFirst => messages.sort_by(|a, b| b.date.cmp(&a.date)),
-        MailSortOption::DateOldestFirst => messages.sort_by(|a, b| a.date.cmp(&b.date)),
+        // Stored dates come in several formats, so compare them as times
+        MailSortOption::DateNewestFirst => messages.sort_by_key(|m| std::cmp::Reverse(parse_message_date(&m.date))),
+        MailSortOption::DateOldestFirst => messages.sort_by_key(|m| parse_message_date(&m.date)),
         MailSortOption::SenderAZ => messages.sort_by(|a, b| a.from.to_lowercase().cmp(&b.from.to_lowercase())),
         MailSortOption::SenderZA => messages.sort_by(|a, b| b.from.to_lowercase().cmp(&a.from.to_lowercase())),
         MailSortOption::SubjectAZ => messages.sort_by(|a, b| a.subject.to_lowercase().cmp(&b.subject.to_lowercase())),
@@ -793,7 +7626,247 @@ fn show_about_dialog(parent: &Frame) {
     dlg.show_modal();
 }
 
-fn show_search_dialog(parent: &Frame) -> Option<String> {
+/// Report whether the app is ready to send and receive mail. Servers,
+/// OAuth sign-ins, the cache database and free disk space are checked in
+/// the background and added to the window as each finishes.
+fn check_readiness(
+    frame: &Frame,
+    state: &Arc<StdMutex<WxUIState>>,
+    cache: &Option<CachePool>,
+    rt: &Arc<Runtime>,
+) {
+    let Ok((accounts, active_id, offline, timeouts)) = state
+        .lock()
+        .map(|s| (s.accounts.clone(), s.active_account_id.clone(), s.offline_mode, s.settings.network_timeouts()))
+    else {
+        return;
+    };
+    let (cache_available, queued_outbox) = cache
+        .with_reader(|c| {
+            Some((true, accounts.iter().filter_map(|a| c.load_outbox_messages(&a.id).ok()).map(|q| q.len()).sum()))
+        })
+        .unwrap_or((false, 0));
+    let checks = readiness::build_beta_readiness_report(&readiness::ReadinessSnapshot {
+        accounts: &accounts,
+        active_account_id: active_id.as_deref(),
+        cache_available,
+        offline,
+        queued_outbox,
+    });
+
+    let (tx, rx) = async_channel::unbounded();
+    let mut pending = 0;
+    for account in accounts.iter().filter(|a| a.enabled) {
+        let name = account.display_name();
+        for (label, host, port) in [
+            ("incoming server", account.imap_server.clone(), account.imap_port.clone()),
+            ("outgoing server", account.smtp_server.clone(), account.smtp_port.clone()),
+        ] {
+            let (tx, check) = (tx.clone(), format!("{}: {}", name, label));
+            pending += 1;
+            rt.spawn(async move {
+                let _ = tx.send(readiness::probe_server(&check, &host, &port, timeouts.connect).await).await;
+            });
+        }
+        if readiness::uses_oauth(account) {
+            let Some(provider) = OAuthService::detect_provider(&account.email) else { continue };
+            let (tx, account) = (tx.clone(), account.clone());
+            pending += 1;
+            rt.spawn_blocking(move || {
+                let tokens = AuthManager::new(&account.id, &provider, "", "").load_tokens().ok();
+                let _ = tx.send_blocking(readiness::oauth_token_check(&account, tokens.as_ref(), chrono::Utc::now()));
+            });
+        }
+    }
+    if cache_available {
+        let (tx, cache) = (tx.clone(), cache.clone());
+        pending += 2;
+        rt.spawn_blocking(move || {
+            let checked = cache.with_reader(|c| {
+                let schema = readiness::schema_check(c.schema_version(), SCHEMA_VERSION, c.integrity_problems());
+                Some((schema, c.database_path().parent().map(Path::to_path_buf)))
+            });
+            let Some((schema, dir)) = checked else { return };
+            let _ = tx.send_blocking(schema);
+            if let Some(dir) = dir {
+                let _ = tx.send_blocking(readiness::disk_space_check(&dir));
+            }
+        });
+    }
+    drop(tx);
+    wx_readiness::show_readiness_dialog(frame, checks, pending, rx);
+}
+
+/// Show recent log events and save them to a file on request.
+fn view_logs(frame: &Frame, tx: &Sender<UIUpdate>, rt: &Arc<Runtime>) {
+    let Some(text) = wx_log_viewer::show_log_viewer(frame) else { return };
+    let Some(path) = choose_export_path(frame, "Save Logs", "wixen-mail-log.txt", "Text files (*.txt)|*.txt|All files (*.*)|*.*")
+    else {
+        return;
+    };
+    let status = match std::fs::write(&path, text) {
+        Ok(()) => trf("status.logs_saved", &[&path.display().to_string()]),
+        Err(e) => describe_error("Could not save the logs", &e.into()),
+    };
+    send_status(tx, rt, &status);
+}
+
+/// Read-only window for a message's headers and raw MIME, or its text.
+fn show_source_dialog(parent: &Frame, title: &str, text: &str) {
+    let dlg = Dialog::builder(parent, title).with_size(760, 560).build();
+    let sizer = BoxSizer::builder(Orientation::Vertical).build();
+
+    let source = TextCtrl::builder(&dlg)
+        .with_style(TextCtrlStyle::MultiLine | TextCtrlStyle::ReadOnly)
+        .with_value(text)
+        .build();
+    sizer.add(&source, 1, SizerFlag::Expand | SizerFlag::All, 8);
+
+    let close = Button::builder(&dlg).with_label("&Close").with_id(ID_OK).build();
+    sizer.add(&close, 0, SizerFlag::AlignRight | SizerFlag::All, 8);
+    dlg.set_sizer(sizer, true);
+
+    close.on_click({ let d = dlg; move |_| { d.end_modal(ID_OK); } });
+    source.set_focus();
+    dlg.show_modal();
+}
+
+/// Small modal picker returning the chosen index.
+fn show_choice_dialog(parent: &Frame, title: &str, label: &str, choices: &[String]) -> Option<usize> {
+    if choices.is_empty() {
+        return None;
+    }
+    let dlg = Dialog::builder(parent, title).with_size(360, 160).build();
+    let sizer = BoxSizer::builder(Orientation::Vertical).build();
+
+    let row = BoxSizer::builder(Orientation::Horizontal).build();
+    let lbl = StaticText::builder(&dlg).with_label(label).build();
+    let choice = Choice::builder(&dlg)
+        .with_choices(choices.to_vec())
+        .with_selection(Some(0))
+        .build();
+    row.add(&lbl, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+    row.add(&choice, 1, SizerFlag::Expand | SizerFlag::All, 4);
+    sizer.add_sizer(&row, 1, SizerFlag::Expand | SizerFlag::All, 8);
+
+    let btns = BoxSizer::builder(Orientation::Horizontal).build();
+    let ok = Button::builder(&dlg).with_label("OK").with_id(ID_OK).build();
+    let cancel = Button::builder(&dlg).with_label("Cancel").with_id(ID_CANCEL).build();
+    btns.add_spacer(0);
+    btns.add(&ok, 0, SizerFlag::All, 4);
+    btns.add(&cancel, 0, SizerFlag::All, 4);
+    sizer.add_sizer(&btns, 0, SizerFlag::AlignRight | SizerFlag::All, 8);
+    dlg.set_sizer(sizer, true);
+
+    ok.on_click({ let d = dlg; move |_| { d.end_modal(ID_OK); } });
+    cancel.on_click({ let d = dlg; move |_| { d.end_modal(ID_CANCEL); } });
+
+    if dlg.show_modal() == ID_OK {
+        choice.get_selection().map(|i| i as usize)
+    } else {
+        None
+    }
+}
+
+/// Small modal text prompt; `None` if cancelled or left empty.
+fn show_text_dialog(parent: &Frame, title: &str, label: &str, initial: &str) -> Option<String> {
+    let dlg = Dialog::builder(parent, title).with_size(380, 150).build();
+    let sizer = BoxSizer::builder(Orientation::Vertical).build();
+
+    let row = BoxSizer::builder(Orientation::Horizontal).build();
+    let lbl = StaticText::builder(&dlg).with_label(label).build();
+    let field = TextCtrl::builder(&dlg).with_value(initial).build();
+    row.add(&lbl, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+    row.add(&field, 1, SizerFlag::Expand | SizerFlag::All, 4);
+    sizer.add_sizer(&row, 1, SizerFlag::Expand | SizerFlag::All, 8);
+
+    let btns = BoxSizer::builder(Orientation::Horizontal).build();
+    let ok = Button::builder(&dlg).with_label("OK").with_id(ID_OK).build();
+    let cancel = Button::builder(&dlg).with_label("Cancel").with_id(ID_CANCEL).build();
+    btns.add_spacer(0);
+    btns.add(&ok, 0, SizerFlag::All, 4);
+    btns.add(&cancel, 0, SizerFlag::All, 4);
+    sizer.add_sizer(&btns, 0, SizerFlag::AlignRight | SizerFlag::All, 8);
+    dlg.set_sizer(sizer, true);
+
+    ok.on_click({ let d = dlg; move |_| { d.end_modal(ID_OK); } });
+    cancel.on_click({ let d = dlg; move |_| { d.end_modal(ID_CANCEL); } });
+    field.set_focus();
+
+    if dlg.show_modal() == ID_OK {
+        let text = field.get_value();
+        (!text.trim().is_empty()).then_some(text)
+    } else {
+        None
+    }
+}
+
+/// List only the loaded messages whose subject or sender contains `text`,
+/// within the current folder or view. Empty `text` lists them all again.
+fn set_quick_filter(state: &Arc<StdMutex<WxUIState>>, tx: &Sender<UIUpdate>, rt: &Arc<Runtime>, text: &str) {
+    let messages = {
+        let Ok(mut s) = state.lock() else { return };
+        if s.quick_filter == text.trim() {
+            return;
+        }
+        s.quick_filter = text.trim().to_string();
+        s.messages.clone()
+    };
+    let tx = tx.clone();
+    rt.spawn(async move {
+        let _ = tx.send(UIUpdate::MessagesLoaded(messages)).await;
+    });
+}
+
+/// Narrow the message list to the loaded messages matching `query` in
+/// their subject, sender or cached body, and remember the search so opened
+/// messages show where it matched
+fn search_messages(
+    state: &Arc<StdMutex<WxUIState>>,
+    engine: &Option<Arc<MailEngine>>,
+    tx: &Sender<UIUpdate>,
+    rt: &Arc<Runtime>,
+    query: &SearchQuery,
+) {
+    let Some((messages, active_account_id, folder)) = state
+        .lock()
+        .map(|mut s| {
+            s.search = Some(query.clone());
+            (s.messages.clone(), s.active_account_id.clone(), s.selected_folder.clone())
+        })
+        .ok()
+    else {
+        return;
+    };
+    // All Inboxes and Follow-up span folders, and All Inboxes accounts
+    let scope = SearchQuery {
+        folder: folder.filter(|f| f != ALL_INBOXES_PATH && f != FOLLOW_UP_PATH),
+        ..query.clone()
+    };
+    let account_ids: HashSet<String> =
+        messages.iter().filter_map(|m| m.account_id.clone()).chain(active_account_id).collect();
+    let matched: HashSet<i64> = engine
+        .iter()
+        .flat_map(|engine| account_ids.iter().filter_map(|id| engine.search(id, &scope).ok()))
+        .flatten()
+        .map(|m| m.id)
+        .collect();
+    let found: Vec<MessageItem> = messages
+        .into_iter()
+        .filter(|m| query.matches(&m.subject, &m.from, m.priority) || matched.contains(&m.message_id))
+        .collect();
+    let status = match found.len() {
+        1 => "Found 1 message".to_string(),
+        n => format!("Found {} messages", n),
+    };
+    let tx2 = tx.clone();
+    rt.spawn(async move {
+        let _ = tx2.send(UIUpdate::MessagesLoaded(found)).await;
+    });
+    send_status(tx, rt, &status);
+}
+
+fn show_search_dialog(parent: &Frame) -> Option<SearchQuery> {
     let dlg = Dialog::builder(parent, "Search Messages").with_size(450, 200).build();
     let sizer = BoxSizer::builder(Orientation::Vertical).build();
 
@@ -815,6 +7888,9 @@ fn show_search_dialog(parent: &Frame) -> Option<String> {
 
     sizer.add_sizer(&fields, 1, SizerFlag::Expand | SizerFlag::All, 8);
 
+    let high_priority = CheckBox::builder(&dlg).with_label("&High priority only").build();
+    sizer.add(&high_priority, 0, SizerFlag::Left | SizerFlag::Right, 12);
+
     let btns = BoxSizer::builder(Orientation::Horizontal).build();
     let search = Button::builder(&dlg).with_label("Search").with_id(ID_OK).build();
     let cancel = Button::builder(&dlg).with_label("Cancel").with_id(ID_CANCEL).build();
@@ -828,8 +7904,8 @@ fn show_search_dialog(parent: &Frame) -> Option<String> {
     cancel.on_click({ let d = dlg; move |_| { d.end_modal(ID_CANCEL); } });
 
     if dlg.show_modal() == ID_OK {
-        let q = q_field.get_value();
-        if !q.trim().is_empty() { Some(q) } else { None }
+        let query = SearchQuery { text: q_field.get_value(), folder: None, high_priority_only: high_priority.get_value() };
+        (!query.text.trim().is_empty() || query.high_priority_only).then_some(query)
     } else {
         None
     }
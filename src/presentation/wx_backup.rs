@@ -0,0 +1,129 @@
+//! Settings backup dialogs
+//!
+//! Tools > Back Up Settings asks whether to include passwords, and
+//! Tools > Restore Settings how to treat items that already exist. The
+//! bundle itself is built and restored by `MessageCache::export_settings`
+//! and `MessageCache::import_settings`.
+
+use crate::data::message_cache::ImportConflict;
+use crate::presentation::wx_master_password::{ok_cancel, password_field};
+use wxdragon::prelude::*;
+
+/// What to put in a backup
+pub struct BackupChoice {
+    /// Passphrase that encrypts account passwords; `None` leaves them out
+    pub passphrase: Option<String>,
+    pub include_settings: bool,
+}
+
+/// How to restore a backup
+pub struct RestoreChoice {
+    /// Passphrase for the backup's passwords; `None` restores without them
+    pub passphrase: Option<String>,
+    pub on_conflict: ImportConflict,
+    pub restore_settings: bool,
+}
+
+/// Ask what to include in a backup. Returns `None` on cancel.
+pub fn show_backup_dialog(parent: &Frame) -> Option<BackupChoice> {
+    let dlg = Dialog::builder(parent, "Back Up Settings").with_size(460, 320).build();
+    let sizer = BoxSizer::builder(Orientation::Vertical).build();
+
+    let intro = StaticText::builder(&dlg)
+        .with_label("Accounts, tags, signatures, filter rules and contacts are saved to one file.")
+        .build();
+    sizer.add(&intro, 0, SizerFlag::All, 8);
+
+    let include_settings = CheckBox::builder(&dlg).with_label("Include application &settings").build();
+    include_settings.set_value(true);
+    sizer.add(&include_settings, 0, SizerFlag::All, 8);
+
+    let include_passwords = CheckBox::builder(&dlg).with_label("Include &passwords, encrypted with a passphrase").build();
+    include_passwords.set_value(false);
+    sizer.add(&include_passwords, 0, SizerFlag::All, 8);
+
+    let fields = FlexGridSizer::builder(0, 2).with_vgap(6).with_hgap(8).build();
+    fields.add_growable_col(1, 1);
+    let passphrase = password_field(&dlg, &fields, "Pass&phrase:");
+    let confirm = password_field(&dlg, &fields, "C&onfirm passphrase:");
+    sizer.add_sizer(&fields, 0, SizerFlag::Expand | SizerFlag::All, 4);
+
+    let hint = StaticText::builder(&dlg)
+        .with_label("Without passwords the file is safe to send to support; you enter\nthe passwords again after restoring.")
+        .build();
+    sizer.add(&hint, 0, SizerFlag::All, 8);
+
+    let error = StaticText::builder(&dlg).with_label("").build();
+    sizer.add(&error, 0, SizerFlag::Expand | SizerFlag::All, 8);
+
+    ok_cancel(&dlg, &sizer);
+    dlg.set_sizer(sizer, true);
+
+    loop {
+        if dlg.show_modal() != ID_OK {
+            return None;
+        }
+        if !include_passwords.get_value() {
+            return Some(BackupChoice { passphrase: None, include_settings: include_settings.get_value() });
+        }
+        let (value, confirm_value) = (passphrase.get_value(), confirm.get_value());
+        if value.is_empty() {
+            error.set_label("Enter a passphrase to encrypt the passwords.");
+        } else if value != confirm_value {
+            error.set_label("The passphrases do not match.");
+            confirm.set_value("");
+        } else {
+            return Some(BackupChoice { passphrase: Some(value), include_settings: include_settings.get_value() });
+        }
+    }
+}
+
+/// Ask how to restore a backup. `has_secrets` adds a passphrase field and
+/// `has_settings` the choice to restore application settings. Returns
+/// `None` on cancel.
+pub fn show_restore_dialog(parent: &Frame, has_secrets: bool, has_settings: bool) -> Option<RestoreChoice> {
+    let dlg = Dialog::builder(parent, "Restore Settings").with_size(460, 300).build();
+    let sizer = BoxSizer::builder(Orientation::Vertical).build();
+
+    let row = BoxSizer::builder(Orientation::Horizontal).build();
+    let label = StaticText::builder(&dlg).with_label("When an item already &exists:").build();
+    let conflict = Choice::builder(&dlg)
+        .with_choices(vec!["Keep the existing one".to_string(), "Replace it with the backup".to_string()])
+        .with_selection(Some(0))
+        .build();
+    row.add(&label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+    row.add(&conflict, 1, SizerFlag::Expand | SizerFlag::All, 4);
+    sizer.add_sizer(&row, 0, SizerFlag::Expand | SizerFlag::All, 8);
+
+    let restore_settings = has_settings.then(|| {
+        let check = CheckBox::builder(&dlg).with_label("Restore application &settings").build();
+        check.set_value(false);
+        sizer.add(&check, 0, SizerFlag::All, 8);
+        check
+    });
+
+    let passphrase = has_secrets.then(|| {
+        let fields = FlexGridSizer::builder(0, 2).with_vgap(6).with_hgap(8).build();
+        fields.add_growable_col(1, 1);
+        let field = password_field(&dlg, &fields, "Pass&phrase:");
+        sizer.add_sizer(&fields, 0, SizerFlag::Expand | SizerFlag::All, 4);
+        let hint = StaticText::builder(&dlg)
+            .with_label("Leave the passphrase empty to restore without passwords.")
+            .build();
+        sizer.add(&hint, 0, SizerFlag::All, 8);
+        field
+    });
+
+    ok_cancel(&dlg, &sizer);
+    dlg.set_sizer(sizer, true);
+
+    if dlg.show_modal() != ID_OK {
+        return None;
+    }
+    let on_conflict = if conflict.get_selection() == Some(1) { ImportConflict::Overwrite } else { ImportConflict::Skip };
+    Some(RestoreChoice {
+        passphrase: passphrase.map(|f| f.get_value()).filter(|p| !p.is_empty()),
+        on_conflict,
+        restore_settings: restore_settings.is_some_and(|c| c.get_value()),
+    })
+}
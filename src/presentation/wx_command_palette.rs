@@ -0,0 +1,129 @@
+//! Command palette window
+//!
+//! View > Command Palette lists every menu command with its menu and
+//! shortcut. Typing narrows the list; Enter or double-click runs the
+//! selected command, and Esc closes the window without running anything.
+
+use crate::presentation::command_palette::{filter, PaletteCommand};
+use crate::presentation::wx_managers::get_selected;
+use std::cell::RefCell;
+use std::rc::Rc;
+use wxdragon::prelude::*;
+
+const WXK_RETURN: i32 = 13;
+const WXK_ESCAPE: i32 = 27;
+const WXK_DOWN: i32 = 317;
+
+/// Show the palette. Returns the chosen command, or `None` when closed
+/// without choosing.
+pub fn show_command_palette<T: Copy + 'static>(parent: &Frame, commands: &[PaletteCommand<T>]) -> Option<T> {
+    let dlg = Dialog::builder(parent, "Command Palette").with_size(620, 460).build();
+    let sizer = BoxSizer::builder(Orientation::Vertical).build();
+
+    let row = BoxSizer::builder(Orientation::Horizontal).build();
+    let label = StaticText::builder(&dlg).with_label("&Command:").build();
+    let query = TextCtrl::builder(&dlg).build();
+    row.add(&label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+    row.add(&query, 1, SizerFlag::Expand | SizerFlag::All, 4);
+    sizer.add_sizer(&row, 0, SizerFlag::Expand | SizerFlag::All, 4);
+
+    let list = ListCtrl::builder(&dlg).with_style(ListCtrlStyle::Report | ListCtrlStyle::SingleSel).build();
+    list.insert_column(0, "Command", ListColumnFormat::Left, 260);
+    list.insert_column(1, "Menu", ListColumnFormat::Left, 180);
+    list.insert_column(2, "Shortcut", ListColumnFormat::Left, 130);
+    sizer.add(&list, 1, SizerFlag::Expand | SizerFlag::All, 8);
+
+    let help = StaticText::builder(&dlg).with_label(" ").build();
+    sizer.add(&help, 0, SizerFlag::Expand | SizerFlag::Left | SizerFlag::Right, 8);
+    let count = StaticText::builder(&dlg).with_label("").build();
+    sizer.add(&count, 0, SizerFlag::Expand | SizerFlag::All, 8);
+
+    let btns = BoxSizer::builder(Orientation::Horizontal).build();
+    let run = Button::builder(&dlg).with_label("&Run").with_id(ID_OK).build();
+    let cancel = Button::builder(&dlg).with_label("Cancel").with_id(ID_CANCEL).build();
+    btns.add(&run, 0, SizerFlag::All, 4);
+    btns.add(&cancel, 0, SizerFlag::All, 4);
+    sizer.add_sizer(&btns, 0, SizerFlag::AlignRight | SizerFlag::All, 8);
+    dlg.set_sizer(sizer, true);
+
+    // The window's callbacks outlive the borrow
+    let commands: Rc<[PaletteCommand<T>]> = commands.to_vec().into();
+    // The commands listed, in list order
+    let shown: Rc<RefCell<Vec<PaletteCommand<T>>>> = Rc::new(RefCell::new(Vec::new()));
+    let select = move |row: usize| {
+        list.set_item_state(row as i64, ListItemState::Selected | ListItemState::Focused, ListItemState::Selected | ListItemState::Focused);
+    };
+    let update = {
+        let shown = shown.clone();
+        move || {
+            let matches = filter(&commands, &query.get_value());
+            list.delete_all_items();
+            for (i, c) in matches.iter().enumerate() {
+                let idx = i as i64;
+                list.insert_item(idx, &c.name, None);
+                list.set_item_text_by_column(idx, 1, &c.menu);
+                list.set_item_text_by_column(idx, 2, c.shortcut.as_deref().unwrap_or(""));
+            }
+            count.set_label(&match matches.len() {
+                1 => "1 command".to_string(),
+                n => format!("{} commands", n),
+            });
+            let first = matches.first().map(|c| c.help.clone()).unwrap_or_default();
+            help.set_label(&first);
+            // Keep the best match selected so Enter in the box runs it
+            if !matches.is_empty() {
+                select(0);
+            }
+            *shown.borrow_mut() = matches.into_iter().cloned().collect();
+        }
+    };
+    update();
+
+    query.on_text_changed({
+        let update = update.clone();
+        move |_| update()
+    });
+    query.on_key_down({
+        let shown = shown.clone();
+        move |event| {
+            if let WindowEventData::Keyboard(ref kbd) = event {
+                match kbd.get_key_code() {
+                    Some(WXK_RETURN) => {
+                        dlg.end_modal(ID_OK);
+                        return;
+                    }
+                    Some(WXK_ESCAPE) => {
+                        dlg.end_modal(ID_CANCEL);
+                        return;
+                    }
+                    Some(WXK_DOWN) if !shown.borrow().is_empty() => {
+                        list.set_focus();
+                        select(get_selected(&list).unwrap_or(0));
+                        return;
+                    }
+                    _ => {}
+                }
+            }
+            event.skip(true);
+        }
+    });
+    list.on_item_selected({
+        let shown = shown.clone();
+        move |event| {
+            if let Some(c) = shown.borrow().get(event.get_item_index() as usize) {
+                help.set_label(&c.help);
+            }
+        }
+    });
+    list.on_item_activated({ let d = dlg; move |_| { d.end_modal(ID_OK); } });
+    run.on_click({ let d = dlg; move |_| { d.end_modal(ID_OK); } });
+    cancel.on_click({ let d = dlg; move |_| { d.end_modal(ID_CANCEL); } });
+
+    query.set_focus();
+    if dlg.show_modal() != ID_OK {
+        return None;
+    }
+    let index = get_selected(&list).unwrap_or(0);
+    let chosen = shown.borrow().get(index).map(|c| c.command);
+    chosen
+}
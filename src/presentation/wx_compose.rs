@@ -2,10 +2,62 @@
 //!
 //! Provides a modal dialog for composing, replying to, and forwarding emails.
 //! Uses RichTextCtrl for the message body with formatting toolbar support.
+//! The To, CC and BCC fields offer contact, group and history suggestions
+//! as you type. Their recipients are listed as removable chips below the
+//! fields, with addresses that don't look right flagged as you go.
+//! The message is autosaved as a draft while it changes, so it survives a
+//! crash, and earlier versions of the draft can be put back.
+//! The composer can also open in a window of its own instead, left open
+//! beside the main window; several can be open at once, each with its
+//! own draft, and any still open are saved as drafts on quitting.
 
+use crate::application::composition::{
+    check_recipients, complete_recipient, fill_template, mentions_attachment, partial_recipient, recipient_count,
+    remove_recipient, RecipientStatus, TemplateValues,
+};
+use crate::application::contacts::AddressSuggestion;
+use crate::common::types::{EmailAddress, MessagePriority};
+use crate::data::message_cache::{DraftRevision, MessageTemplate};
+use crate::presentation::html_renderer::HtmlRenderer;
+use crate::presentation::i18n::{format_full_date, format_long_date};
+use crate::presentation::i18n::tr;
 use crate::presentation::ui_types::CompositionData;
+use crate::presentation::wx_managers::get_selected;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
 use wxdragon::prelude::*;
 
+/// Looks up recipient suggestions for the text being typed
+pub type SuggestFn = Rc<dyn Fn(&str) -> Vec<AddressSuggestion>>;
+
+/// Calls back into the app while the composer is open
+#[derive(Clone)]
+pub struct ComposeHooks {
+    pub suggest: SuggestFn,
+    /// Offered the message every few seconds once it differs from what
+    /// the composer opened with; saves it when an autosave is due
+    pub autosave: Rc<dyn Fn(&ComposeData)>,
+    /// Earlier versions of the draft, newest first
+    pub revisions: Rc<dyn Fn() -> Vec<DraftRevision>>,
+    /// Save the message being written, then put an earlier version back as
+    /// the draft; false if that failed
+    pub restore: Rc<dyn Fn(i64, &ComposeData) -> bool>,
+}
+
+/// Priority choices, in the order shown
+const PRIORITIES: [MessagePriority; 3] = [MessagePriority::Normal, MessagePriority::High, MessagePriority::Low];
+
+/// Characters typed before suggestions appear
+const MIN_SUGGEST_CHARS: usize = 2;
+/// How often the message is offered for autosave
+const AUTOSAVE_TICK_MS: i32 = 5000;
+const WXK_ESCAPE: i32 = 27;
+const WXK_DOWN: i32 = 317;
+const WXK_DELETE: i32 = 127;
+
+/// Names of the recipient fields, in the order their chips are listed
+const RECIPIENT_FIELDS: [&str; 3] = ["To", "CC", "BCC"];
+
 // ── Formatting toolbar IDs ──────────────────────────────────────────────────
 
 // Button IDs: used as return codes via end_modal() or in button .with_id()
@@ -18,6 +70,9 @@ const ID_DISCARD: Id = ID_HIGHEST + 112;
 const ID_ATTACH: Id = ID_HIGHEST + 113;
 const ID_UNDO: Id = ID_HIGHEST + 114;
 const ID_REDO: Id = ID_HIGHEST + 115;
+const ID_INSERT_TEMPLATE: Id = ID_HIGHEST + 116;
+const ID_REMOVE_RECIPIENT: Id = ID_HIGHEST + 117;
+const ID_VERSIONS: Id = ID_HIGHEST + 118;
 
 /// Result of showing the compose dialog
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -40,6 +95,39 @@ pub struct ComposeData {
     pub body: String,
     pub html_mode: bool,
     pub account_index: Option<u32>,
+    /// Identity (alias) to send as; `None` for the account's own address
+    pub identity_id: Option<String>,
+    /// Ask the recipient's client to send a read receipt
+    pub request_receipt: bool,
+    /// Sent as `X-Priority`/`Importance` headers unless normal
+    pub priority: MessagePriority,
+}
+
+/// One entry in the From selector: an account's own address or one of
+/// its identities
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FromChoice {
+    pub account_index: u32,
+    pub identity_id: Option<String>,
+    pub email: String,
+    /// Shown in the selector, e.g. `Support <support@example.com>`
+    pub label: String,
+}
+
+/// Checks made when the user presses Send
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SendChecks {
+    /// Show the message for a last look before it goes
+    pub preview: bool,
+    /// Ask before a Reply All goes to more than this many recipients;
+    /// 0 never asks
+    pub reply_all_warning: usize,
+}
+
+impl Default for SendChecks {
+    fn default() -> Self {
+        Self { preview: true, reply_all_warning: 10 }
+    }
 }
 
 /// Mode for opening the compose dialog
@@ -67,6 +155,10 @@ pub enum ComposeMode {
     },
     /// Edit an existing draft
     Draft(CompositionData),
+    /// Send an undelivered message again
+    Resend(CompositionData),
+    /// Ask a mailing list to unsubscribe, as its `mailto:` link words it
+    Unsubscribe(CompositionData),
 }
 
 /// Show the composition dialog modally and return the user's action.
@@ -79,20 +171,90 @@ pub enum ComposeMode {
 pub fn show_compose_dialog(
     parent: &Frame,
     mode: ComposeMode,
-    account_names: &[String],
-    active_account_index: u32,
+    senders: &[FromChoice],
+    selected_sender: u32,
+    hooks: ComposeHooks,
+    templates: &[MessageTemplate],
 ) -> ComposeResult {
-    show_compose_dialog_with_options(parent, mode, account_names, active_account_index, true)
+    show_compose_dialog_with_options(parent, mode, senders, selected_sender, SendChecks::default(), hooks, templates)
 }
 
-/// Show the composition dialog with configurable preview-before-send.
+/// Show the composition dialog with configurable checks before sending.
 pub fn show_compose_dialog_with_options(
     parent: &Frame,
     mode: ComposeMode,
-    account_names: &[String],
-    active_account_index: u32,
-    preview_before_send: bool,
+    senders: &[FromChoice],
+    selected_sender: u32,
+    checks: SendChecks,
+    hooks: ComposeHooks,
+    templates: &[MessageTemplate],
 ) -> ComposeResult {
+    compose(parent, mode, senders, selected_sender, checks, hooks, templates, Host::Modal).unwrap_or(ComposeResult::Cancelled)
+}
+
+/// Open the composer in a window of its own, which can be moved, tiled
+/// and minimized while the main window is in use. Returns at once; `done`
+/// gets the user's action when the window closes.
+#[allow(clippy::too_many_arguments)]
+pub fn open_compose_window(
+    parent: &Frame,
+    mode: ComposeMode,
+    senders: &[FromChoice],
+    selected_sender: u32,
+    checks: SendChecks,
+    hooks: ComposeHooks,
+    templates: &[MessageTemplate],
+    done: impl Fn(ComposeResult) + 'static,
+) {
+    compose(parent, mode, senders, selected_sender, checks, hooks, templates, Host::Window(Rc::new(done)));
+}
+
+/// Composers open in their own windows, each with what saves its message
+/// as a draft and closes it. Only the UI thread opens composers.
+#[derive(Default)]
+struct OpenWindows {
+    next_key: u64,
+    windows: Vec<(u64, Rc<dyn Fn()>)>,
+}
+
+thread_local! {
+    static OPEN_WINDOWS: RefCell<OpenWindows> = RefCell::new(OpenWindows::default());
+}
+
+/// Close every composer window, saving each message as a draft unless it
+/// is still as the window opened it, e.g. before quitting. Returns how
+/// many windows were closed.
+pub fn save_compose_windows() -> usize {
+    let windows: Vec<Rc<dyn Fn()>> =
+        OPEN_WINDOWS.with(|open| open.borrow().windows.iter().map(|(_, save)| save.clone()).collect());
+    for save in &windows {
+        save();
+    }
+    windows.len()
+}
+
+/// Where the composer is shown
+enum Host {
+    /// Over the main window, which waits until it closes
+    Modal,
+    /// In its own window; the callback gets the result
+    Window(Rc<dyn Fn(ComposeResult)>),
+}
+
+/// Build the composer and show it in `host`. A modal composer returns its
+/// result; one in its own window returns `None` at once.
+#[allow(clippy::too_many_arguments)]
+fn compose(
+    parent: &Frame,
+    mode: ComposeMode,
+    senders: &[FromChoice],
+    selected_sender: u32,
+    checks: SendChecks,
+    hooks: ComposeHooks,
+    templates: &[MessageTemplate],
+    host: Host,
+) -> Option<ComposeResult> {
+    let suggest = hooks.suggest.clone();
     // ── Create Dialog ────────────────────────────────────────────────────
     let title = match &mode {
         ComposeMode::New => "Compose New Message",
@@ -100,13 +262,32 @@ pub fn show_compose_dialog_with_options(
         ComposeMode::ReplyAll { .. } => "Reply All",
         ComposeMode::Forward { .. } => "Forward",
         ComposeMode::Draft(_) => "Edit Draft",
+        ComposeMode::Resend(_) => "Resend",
+        ComposeMode::Unsubscribe(_) => "Unsubscribe",
     };
 
+    let style = DialogStyle::DefaultDialogStyle | DialogStyle::ResizeBorder | DialogStyle::MaximizeBox;
     let dialog = Dialog::builder(parent, title)
         .with_size(850, 700)
-        .with_style(DialogStyle::DefaultDialogStyle | DialogStyle::ResizeBorder | DialogStyle::MaximizeBox)
+        .with_style(match host {
+            Host::Modal => style,
+            Host::Window(_) => style | DialogStyle::MinimizeBox,
+        })
         .build();
 
+    // What the Send, Save Draft, Discard and Cancel buttons do; set once
+    // the composer is built
+    let action: Rc<RefCell<Option<Rc<dyn Fn(Id)>>>> = Rc::new(RefCell::new(None));
+    let act = {
+        let action = action.clone();
+        move |id: Id| {
+            let action = action.borrow().clone();
+            if let Some(action) = action {
+                action(id);
+            }
+        }
+    };
+
     // ── Layout ───────────────────────────────────────────────────────────
     let main_sizer = BoxSizer::builder(Orientation::Vertical).build();
 
@@ -117,49 +298,82 @@ pub fn show_compose_dialog_with_options(
         .build();
     fields_sizer.add_growable_col(1, 1);
 
-    // Account selector
-    let account_label = StaticText::builder(&dialog)
-        .with_label("&From:")
+    // From selector: each account's address followed by its identities
+    let from_label = StaticText::builder(&dialog)
+        .with_label(&tr("compose.from"))
         .build();
-    let account_choice = Choice::builder(&dialog)
-        .with_choices(account_names.iter().map(|s| s.to_string()).collect())
-        .with_selection(Some(active_account_index))
+    let from_choice = Choice::builder(&dialog)
+        .with_choices(senders.iter().map(|s| s.label.clone()).collect())
+        .with_selection(Some(selected_sender))
         .build();
-    fields_sizer.add(&account_label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
-    fields_sizer.add(&account_choice, 1, SizerFlag::Expand | SizerFlag::All, 4);
+    fields_sizer.add(&from_label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+    fields_sizer.add(&from_choice, 1, SizerFlag::Expand | SizerFlag::All, 4);
 
     // To field
-    let to_label = StaticText::builder(&dialog).with_label("&To:").build();
+    let to_label = StaticText::builder(&dialog).with_label(&tr("compose.to")).build();
     let to_field = TextCtrl::builder(&dialog).build();
     fields_sizer.add(&to_label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
     fields_sizer.add(&to_field, 1, SizerFlag::Expand | SizerFlag::All, 4);
 
     // CC field
-    let cc_label = StaticText::builder(&dialog).with_label("&CC:").build();
+    let cc_label = StaticText::builder(&dialog).with_label(&tr("compose.cc")).build();
     let cc_field = TextCtrl::builder(&dialog).build();
     fields_sizer.add(&cc_label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
     fields_sizer.add(&cc_field, 1, SizerFlag::Expand | SizerFlag::All, 4);
 
     // BCC field
-    let bcc_label = StaticText::builder(&dialog).with_label("&BCC:").build();
+    let bcc_label = StaticText::builder(&dialog).with_label(&tr("compose.bcc")).build();
     let bcc_field = TextCtrl::builder(&dialog).build();
     fields_sizer.add(&bcc_label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
     fields_sizer.add(&bcc_field, 1, SizerFlag::Expand | SizerFlag::All, 4);
 
     // Subject field
-    let subject_label = StaticText::builder(&dialog).with_label("Su&bject:").build();
+    let subject_label = StaticText::builder(&dialog).with_label(&tr("compose.subject")).build();
     let subject_field = TextCtrl::builder(&dialog).build();
     fields_sizer.add(&subject_label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
     fields_sizer.add(&subject_field, 1, SizerFlag::Expand | SizerFlag::All, 4);
 
     main_sizer.add_sizer(&fields_sizer, 0, SizerFlag::Expand | SizerFlag::All, 4);
 
+    // -- Recipient chips: one row per recipient, removable, with problems
+    // flagged; hidden while there are no recipients --
+    let recipient_note = StaticText::builder(&dialog).with_label("").build();
+    recipient_note.show(false);
+    main_sizer.add(&recipient_note, 0, SizerFlag::Expand | SizerFlag::Left | SizerFlag::Right, 8);
+    let chips_sizer = BoxSizer::builder(Orientation::Horizontal).build();
+    let chips = ListCtrl::builder(&dialog)
+        .with_style(ListCtrlStyle::Report | ListCtrlStyle::SingleSel)
+        .with_size(Size::new(-1, 90))
+        .build();
+    chips.insert_column(0, "Field", ListColumnFormat::Left, 60);
+    chips.insert_column(1, "Recipient", ListColumnFormat::Left, 480);
+    chips.insert_column(2, "Address check", ListColumnFormat::Left, 200);
+    let remove_chip_btn = Button::builder(&dialog)
+        .with_label(&tr("compose.remove_recipient"))
+        .with_id(ID_REMOVE_RECIPIENT)
+        .build();
+    chips_sizer.add(&chips, 1, SizerFlag::Expand | SizerFlag::All, 4);
+    chips_sizer.add(&remove_chip_btn, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+    main_sizer.add_sizer(&chips_sizer, 0, SizerFlag::Expand | SizerFlag::Left | SizerFlag::Right, 4);
+    chips.show(false);
+    remove_chip_btn.show(false);
+
+    // -- Recipient suggestions (shown while typing in To/CC/BCC) --
+    let suggest_list = ListCtrl::builder(&dialog)
+        .with_style(ListCtrlStyle::Report | ListCtrlStyle::SingleSel)
+        .with_size(Size::new(-1, 120))
+        .build();
+    suggest_list.insert_column(0, "Type", ListColumnFormat::Left, 100);
+    suggest_list.insert_column(1, "Suggestion", ListColumnFormat::Left, 600);
+    suggest_list.show(false);
+    main_sizer.add(&suggest_list, 0, SizerFlag::Expand | SizerFlag::Left | SizerFlag::Right, 8);
+
     // -- Compose toolbar --
     let toolbar_sizer = BoxSizer::builder(Orientation::Horizontal).build();
 
     // Prominent Send button (Outlook-style — first in toolbar)
     let send_toolbar_btn = Button::builder(&dialog)
-        .with_label("&Send")
+        .with_label(&tr("compose.send"))
         .with_id(ID_SEND)
         .with_size(Size::new(72, 30))
         .build();
@@ -209,6 +423,20 @@ pub fn show_compose_dialog_with_options(
         .build();
     toolbar_sizer.add(&attach_btn, 0, SizerFlag::All, 2);
 
+    // Insert a saved template (canned response)
+    let template_btn = Button::builder(&dialog)
+        .with_label(&tr("compose.insert_template"))
+        .with_id(ID_INSERT_TEMPLATE)
+        .build();
+    toolbar_sizer.add(&template_btn, 0, SizerFlag::All, 2);
+
+    // Earlier versions of the draft
+    let versions_btn = Button::builder(&dialog)
+        .with_label(&tr("compose.versions"))
+        .with_id(ID_VERSIONS)
+        .build();
+    toolbar_sizer.add(&versions_btn, 0, SizerFlag::All, 2);
+
     main_sizer.add_sizer(&toolbar_sizer, 0, SizerFlag::Expand | SizerFlag::Left | SizerFlag::Right, 8);
 
     // -- Rich text body --
@@ -224,19 +452,36 @@ pub fn show_compose_dialog_with_options(
         .build();
     main_sizer.add(&attachment_label, 0, SizerFlag::Expand | SizerFlag::Left | SizerFlag::Right, 8);
 
+    let receipt_cb = CheckBox::builder(&dialog)
+        .with_label("Request a read &receipt")
+        .build();
+    main_sizer.add(&receipt_cb, 0, SizerFlag::Left | SizerFlag::Right | SizerFlag::Top, 8);
+
+    let priority_sizer = BoxSizer::builder(Orientation::Horizontal).build();
+    let priority_label = StaticText::builder(&dialog)
+        .with_label(&tr("compose.priority"))
+        .build();
+    let priority_choice = Choice::builder(&dialog)
+        .with_choices(PRIORITIES.iter().map(|p| tr(&format!("compose.priority_{}", p.as_str()))).collect())
+        .with_selection(Some(0))
+        .build();
+    priority_sizer.add(&priority_label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+    priority_sizer.add(&priority_choice, 0, SizerFlag::All, 4);
+    main_sizer.add_sizer(&priority_sizer, 0, SizerFlag::Left | SizerFlag::Right, 4);
+
     // -- Action buttons (Send is in toolbar above) --
     let button_sizer = BoxSizer::builder(Orientation::Horizontal).build();
 
     let draft_btn = Button::builder(&dialog)
-        .with_label("Save &Draft")
+        .with_label(&tr("compose.save_draft"))
         .with_id(ID_SAVE_DRAFT)
         .build();
     let discard_btn = Button::builder(&dialog)
-        .with_label("Disc&ard")
+        .with_label(&tr("compose.discard"))
         .with_id(ID_DISCARD)
         .build();
     let cancel_btn = Button::builder(&dialog)
-        .with_label("&Cancel")
+        .with_label(&tr("compose.cancel"))
         .with_id(ID_CANCEL)
         .build();
 
@@ -264,7 +509,7 @@ pub fn show_compose_dialog_with_options(
                 format!("Re: {}", subject)
             };
             subject_field.set_value(&subj);
-            body_editor.set_value(&format!("\n\n--- Original Message ---\n{}", quoted_body));
+            body_editor.set_value(quoted_body);
             body_editor.set_insertion_point(0);
         }
         ComposeMode::ReplyAll {
@@ -281,7 +526,7 @@ pub fn show_compose_dialog_with_options(
                 format!("Re: {}", subject)
             };
             subject_field.set_value(&subj);
-            body_editor.set_value(&format!("\n\n--- Original Message ---\n{}", quoted_body));
+            body_editor.set_value(quoted_body);
             body_editor.set_insertion_point(0);
         }
         ComposeMode::Forward { subject, body } => {
@@ -299,15 +544,218 @@ pub fn show_compose_dialog_with_options(
             // Focus the To field since user needs to fill it
             to_field.set_focus();
         }
-        ComposeMode::Draft(data) => {
+        ComposeMode::Draft(data) | ComposeMode::Resend(data) | ComposeMode::Unsubscribe(data) => {
             to_field.set_value(&data.to);
             cc_field.set_value(&data.cc);
             bcc_field.set_value(&data.bcc);
             subject_field.set_value(&data.subject);
             body_editor.set_value(&data.body);
+            receipt_cb.set_value(data.request_receipt);
+            let index = PRIORITIES.iter().position(|p| *p == data.priority).unwrap_or(0);
+            priority_choice.set_selection(index as u32);
         }
     }
 
+    // ── Recipient autocomplete ───────────────────────────────────────────
+    // Wired after pre-population so reply addresses don't pop up suggestions.
+    // Down arrow moves into the list; Enter or double-click inserts the
+    // suggestion (a group inserts all its members); Escape goes back.
+    // The chips are refreshed on every change too.
+    let suggestions: Rc<RefCell<Vec<AddressSuggestion>>> = Rc::new(RefCell::new(Vec::new()));
+    let active_field: Rc<Cell<Option<TextCtrl>>> = Rc::new(Cell::new(None));
+    let recipient_fields = [to_field, cc_field, bcc_field];
+    let chip_refs: Rc<RefCell<Vec<ChipRef>>> = Rc::new(RefCell::new(Vec::new()));
+    *chip_refs.borrow_mut() = show_recipient_chips(&dialog, &chips, &remove_chip_btn, &recipient_note, &recipient_fields, None);
+    for (field_index, field) in recipient_fields.into_iter().enumerate() {
+        field.on_text_changed({
+            let suggest = suggest.clone();
+            let suggestions = suggestions.clone();
+            let active_field = active_field.clone();
+            let chip_refs = chip_refs.clone();
+            move |_| {
+                active_field.set(Some(field));
+                let value = field.get_value();
+                let partial = partial_recipient(&value);
+                let found = if partial.chars().count() >= MIN_SUGGEST_CHARS { suggest(partial) } else { Vec::new() };
+                show_suggestions(&dialog, &suggest_list, &found);
+                *suggestions.borrow_mut() = found;
+                *chip_refs.borrow_mut() =
+                    show_recipient_chips(&dialog, &chips, &remove_chip_btn, &recipient_note, &recipient_fields, Some(field_index));
+            }
+        });
+        field.on_key_down({
+            let suggestions = suggestions.clone();
+            move |event| {
+                if let WindowEventData::Keyboard(ref kbd) = event {
+                    if kbd.get_key_code() == Some(WXK_DOWN) && !suggestions.borrow().is_empty() {
+                        suggest_list.set_focus();
+                        suggest_list.set_item_state(0, ListItemState::Selected | ListItemState::Focused, ListItemState::Selected | ListItemState::Focused);
+                        return;
+                    }
+                }
+                event.skip(true);
+            }
+        });
+    }
+    suggest_list.on_item_activated({
+        let suggestions = suggestions.clone();
+        let active_field = active_field.clone();
+        move |event| {
+            let chosen = suggestions.borrow().get(event.get_item_index() as usize).cloned();
+            if let (Some(chosen), Some(field)) = (chosen, active_field.get()) {
+                field.set_value(&complete_recipient(&field.get_value(), &chosen.insert_text()));
+                show_suggestions(&dialog, &suggest_list, &[]);
+                field.set_focus();
+                field.set_insertion_point_end();
+            }
+        }
+    });
+    suggest_list.on_key_down({
+        let active_field = active_field.clone();
+        move |event| {
+            if let WindowEventData::Keyboard(ref kbd) = event {
+                if kbd.get_key_code() == Some(WXK_ESCAPE) {
+                    show_suggestions(&dialog, &suggest_list, &[]);
+                    if let Some(field) = active_field.get() {
+                        field.set_focus();
+                    }
+                    return;
+                }
+            }
+            event.skip(true);
+        }
+    });
+
+    // Remove the selected chip's recipient from its field
+    let remove_chip = {
+        let chip_refs = chip_refs.clone();
+        move || {
+            let Some((field_index, index)) = get_selected(&chips).and_then(|row| chip_refs.borrow().get(row).copied()) else { return };
+            let field = recipient_fields[field_index];
+            let rest = remove_recipient(&field.get_value(), index);
+            // A trailing separator keeps suggestions from popping up for the last recipient
+            field.set_value(&if rest.is_empty() { rest } else { format!("{}, ", rest) });
+            let remaining = chip_refs.borrow().len();
+            if remaining > 0 {
+                let row = get_selected(&chips).unwrap_or(0).min(remaining - 1) as i64;
+                chips.set_item_state(row, ListItemState::Selected | ListItemState::Focused, ListItemState::Selected | ListItemState::Focused);
+                chips.set_focus();
+            } else {
+                field.set_focus();
+            }
+        }
+    };
+    remove_chip_btn.on_click({
+        let remove_chip = remove_chip.clone();
+        move |_| remove_chip()
+    });
+    chips.on_key_down(move |event| {
+        if let WindowEventData::Keyboard(ref kbd) = event {
+            if kbd.get_key_code() == Some(WXK_DELETE) {
+                remove_chip();
+                return;
+            }
+        }
+        event.skip(true);
+    });
+
+    // ── Autosave ─────────────────────────────────────────────────────────
+    // The app decides when a save is due; a message still as the dialog
+    // opened it isn't offered at all.
+    let senders: Rc<[FromChoice]> = senders.into();
+    let read_fields = {
+        let senders = senders.clone();
+        move || {
+            let sender = from_choice.get_selection().and_then(|i| senders.get(i as usize));
+            ComposeData {
+                to: to_field.get_value(),
+                cc: cc_field.get_value(),
+                bcc: bcc_field.get_value(),
+                subject: subject_field.get_value(),
+                body: body_editor.get_value(),
+                html_mode: true, // RichTextCtrl is always rich text
+                account_index: sender.map(|s| s.account_index),
+                identity_id: sender.and_then(|s| s.identity_id.clone()),
+                request_receipt: receipt_cb.get_value(),
+                priority: priority_choice
+                    .get_selection()
+                    .and_then(|i| PRIORITIES.get(i as usize).copied())
+                    .unwrap_or_default(),
+            }
+        }
+    };
+    let opened = read_fields();
+    let autosave_timer = Timer::new(&dialog);
+    autosave_timer.on_tick({
+        let read_fields = read_fields.clone();
+        let autosave = hooks.autosave.clone();
+        let opened = opened.clone();
+        move |_| {
+            let data = read_fields();
+            if data != opened {
+                autosave(&data);
+            }
+        }
+    });
+    autosave_timer.start(AUTOSAVE_TICK_MS, false);
+
+    // ── Earlier versions ─────────────────────────────────────────────────
+    // The current text is saved first, so it becomes a version too and
+    // putting an old one back can be undone.
+    versions_btn.on_click({
+        let hooks = hooks.clone();
+        let read_fields = read_fields.clone();
+        move |_| {
+            let revisions = (hooks.revisions)();
+            let Some(revision) = choose_revision(&dialog, &revisions) else { return };
+            if !(hooks.restore)(revision.id, &read_fields()) {
+                return;
+            }
+            to_field.set_value(&revision.to_addr);
+            cc_field.set_value(revision.cc.as_deref().unwrap_or_default());
+            bcc_field.set_value(revision.bcc.as_deref().unwrap_or_default());
+            subject_field.set_value(&revision.subject);
+            body_editor.set_value(&revision.body);
+            show_suggestions(&dialog, &suggest_list, &[]);
+            body_editor.set_focus();
+        }
+    });
+
+    // ── Insert template ──────────────────────────────────────────────────
+    // Fills an empty subject and puts the template text above the body, with
+    // placeholders filled in for the first recipient.
+    template_btn.on_click({
+        let templates = templates.to_vec();
+        let senders = senders.clone();
+        let suggest = suggest.clone();
+        move |_| {
+            let Some(template) = choose_template(&dialog, &templates) else { return };
+            let (recipient_name, recipient_email) = first_recipient(&to_field.get_value(), &suggest);
+            let values = TemplateValues {
+                recipient_name,
+                recipient_email,
+                sender_email: from_choice
+                    .get_selection()
+                    .and_then(|i| senders.get(i as usize))
+                    .map(|s| s.email.clone())
+                    .unwrap_or_default(),
+                date: format_long_date(chrono::Local::now().date_naive()),
+            };
+            let text = if template.is_html {
+                HtmlRenderer::new().render_for_accessibility(&template.body).accessible_text
+            } else {
+                template.body.clone()
+            };
+            if subject_field.get_value().trim().is_empty() {
+                subject_field.set_value(&fill_template(&template.subject, &values));
+            }
+            let body = body_editor.get_value();
+            let text = fill_template(&text, &values);
+            body_editor.set_value(&if body.trim().is_empty() { text } else { format!("{}\n{}", text, body) });
+            body_editor.set_focus();
+        }
+    });
+
     // ── Wire formatting button events ────────────────────────────────────
     bold_btn.on_click({
         let body_editor = body_editor;
@@ -330,12 +778,10 @@ pub fn show_compose_dialog_with_options(
         }
     });
 
-    // Send button (in toolbar) closes dialog with ID_SEND
+    // Send button (in toolbar)
     send_toolbar_btn.on_click({
-        let dialog = dialog;
-        move |_| {
-            dialog.end_modal(ID_SEND);
-        }
+        let act = act.clone();
+        move |_| act(ID_SEND)
     });
 
     // Undo / Redo
@@ -354,64 +800,335 @@ pub fn show_compose_dialog_with_options(
 
     // Save Draft
     draft_btn.on_click({
-        let dialog = dialog;
-        move |_| {
-            dialog.end_modal(ID_SAVE_DRAFT);
-        }
+        let act = act.clone();
+        move |_| act(ID_SAVE_DRAFT)
     });
 
     // Discard
     discard_btn.on_click({
-        let dialog = dialog;
-        move |_| {
-            dialog.end_modal(ID_DISCARD);
-        }
+        let act = act.clone();
+        move |_| act(ID_DISCARD)
     });
 
     // Cancel
     cancel_btn.on_click({
-        let dialog = dialog;
-        move |_| {
-            dialog.end_modal(ID_CANCEL);
-        }
+        let act = act.clone();
+        move |_| act(ID_CANCEL)
     });
 
-    // ── Show dialog modally (loop for preview-then-send) ───────────────
-    loop {
-        let result = dialog.show_modal();
-
-        let data = ComposeData {
-            to: to_field.get_value(),
-            cc: cc_field.get_value(),
-            bcc: bcc_field.get_value(),
-            subject: subject_field.get_value(),
-            body: body_editor.get_value(),
-            html_mode: true, // RichTextCtrl is always rich text
-            account_index: account_choice.get_selection(),
-        };
-
-        match result {
-            _ if result == ID_SEND => {
+    // ── Closing ──────────────────────────────────────────────────────────
+    // The user's action, or `None` to keep writing: a send can be held
+    // back to fix the recipients or after a last look at the preview
+    let unchanged = {
+        let read_fields = read_fields.clone();
+        move || read_fields() == opened
+    };
+    let decide = move |id: Id| {
+        let data = read_fields();
+        let sender = from_choice.get_selection().and_then(|i| senders.get(i as usize));
+        match id {
+            _ if id == ID_SEND => {
                 if data.to.trim().is_empty() {
                     tracing::warn!("Send attempted with empty To field");
-                    return ComposeResult::Cancelled;
+                    return Some(ComposeResult::Cancelled);
+                }
+                if !recipients_ready(&dialog, &data) {
+                    return None;
                 }
-                if preview_before_send {
-                    // Show preview-before-send dialog
-                    match show_send_preview(&dialog, &data, account_names) {
-                        PreviewDecision::ConfirmSend => return ComposeResult::Send(data),
-                        PreviewDecision::GoBack => continue, // re-show compose dialog
+                let reply_all = matches!(mode, ComposeMode::ReplyAll { .. });
+                if !confirm_send_warnings(&dialog, &data, reply_all, checks.reply_all_warning) {
+                    return None;
+                }
+                if checks.preview {
+                    match show_send_preview(&dialog, &data, sender) {
+                        PreviewDecision::ConfirmSend => Some(ComposeResult::Send(data)),
+                        PreviewDecision::GoBack => None,
                     }
                 } else {
-                    return ComposeResult::Send(data);
+                    Some(ComposeResult::Send(data))
                 }
             }
-            _ if result == ID_SAVE_DRAFT => return ComposeResult::SaveDraft(data),
-            _ => return ComposeResult::Cancelled,
+            _ if id == ID_SAVE_DRAFT => Some(ComposeResult::SaveDraft(data)),
+            _ => Some(ComposeResult::Cancelled),
+        }
+    };
+
+    match host {
+        Host::Modal => {
+            *action.borrow_mut() = Some(Rc::new(move |id| dialog.end_modal(id)));
+            // Re-shown until there's a result
+            let result = loop {
+                if let Some(result) = decide(dialog.show_modal()) {
+                    break result;
+                }
+            };
+            autosave_timer.stop();
+            Some(result)
+        }
+        Host::Window(done) => {
+            let save: Rc<dyn Fn()> = Rc::new({
+                let act = act.clone();
+                move || act(if unchanged() { ID_CANCEL } else { ID_SAVE_DRAFT })
+            });
+            let key = OPEN_WINDOWS.with(|open| {
+                let mut open = open.borrow_mut();
+                open.next_key += 1;
+                let key = open.next_key;
+                open.windows.push((key, save));
+                key
+            });
+            *action.borrow_mut() = Some(Rc::new(move |id| {
+                if let Some(result) = decide(id) {
+                    OPEN_WINDOWS.with(|open| open.borrow_mut().windows.retain(|(k, _)| *k != key));
+                    autosave_timer.stop();
+                    dialog.destroy();
+                    done(result);
+                }
+            }));
+            // Closing the window from its title bar cancels, like Cancel
+            dialog.on_close(move |_| act(ID_CANCEL));
+            dialog.show(true);
+            None
         }
     }
 }
 
+/// Name and address of the first recipient in a To field. A bare address
+/// takes its name from the matching contact or correspondent, if any.
+fn first_recipient(field: &str, suggest: &SuggestFn) -> (String, String) {
+    let Some(first) = EmailAddress::parse_list(field).into_iter().next() else {
+        return (String::new(), String::new());
+    };
+    let known = || {
+        suggest(&first.address).into_iter().find_map(|s| match s {
+            AddressSuggestion::Contact { name, email } | AddressSuggestion::Recent { name, email, .. }
+                if email.eq_ignore_ascii_case(&first.address) && !name.trim().is_empty() =>
+            {
+                Some(name)
+            }
+            _ => None,
+        })
+    };
+    let name = first.name.clone().filter(|n| !n.trim().is_empty()).or_else(known);
+    (name.unwrap_or_else(|| first.address.clone()), first.address)
+}
+
+/// Pick an earlier version of the draft; `None` if cancelled or there
+/// are none yet.
+fn choose_revision<'a>(parent: &Dialog, revisions: &'a [DraftRevision]) -> Option<&'a DraftRevision> {
+    if revisions.is_empty() {
+        MessageDialog::builder(parent, "There are no earlier versions of this message yet.", "Earlier Versions")
+            .with_style(MessageDialogStyle::OK | MessageDialogStyle::IconInformation)
+            .build()
+            .show_modal();
+        return None;
+    }
+    let dlg = Dialog::builder(parent, "Earlier Versions").with_size(480, 160).build();
+    let sizer = BoxSizer::builder(Orientation::Vertical).build();
+
+    let row = BoxSizer::builder(Orientation::Horizontal).build();
+    let labels = revisions
+        .iter()
+        .map(|r| {
+            let subject = if r.subject.trim().is_empty() { tr("compose.no_subject") } else { r.subject.clone() };
+            format!("{}: {}", format_full_date(&r.saved_at), subject)
+        })
+        .collect();
+    let choice = Choice::builder(&dlg).with_choices(labels).with_selection(Some(0)).build();
+    let lbl = StaticText::builder(&dlg).with_label("&Saved:").build();
+    row.add(&lbl, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+    row.add(&choice, 1, SizerFlag::Expand | SizerFlag::All, 4);
+    sizer.add_sizer(&row, 1, SizerFlag::Expand | SizerFlag::All, 8);
+
+    let btns = BoxSizer::builder(Orientation::Horizontal).build();
+    let ok = Button::builder(&dlg).with_label("&Restore").with_id(ID_OK).build();
+    let cancel = Button::builder(&dlg).with_label("Cancel").with_id(ID_CANCEL).build();
+    btns.add_spacer(0);
+    btns.add(&ok, 0, SizerFlag::All, 4);
+    btns.add(&cancel, 0, SizerFlag::All, 4);
+    sizer.add_sizer(&btns, 0, SizerFlag::AlignRight | SizerFlag::All, 8);
+    dlg.set_sizer(sizer, true);
+
+    ok.on_click({ let d = dlg; move |_| { d.end_modal(ID_OK); } });
+    cancel.on_click({ let d = dlg; move |_| { d.end_modal(ID_CANCEL); } });
+
+    if dlg.show_modal() == ID_OK {
+        choice.get_selection().and_then(|i| revisions.get(i as usize))
+    } else {
+        None
+    }
+}
+
+/// Pick a template to insert; `None` if cancelled or there are none yet.
+fn choose_template<'a>(parent: &Dialog, templates: &'a [MessageTemplate]) -> Option<&'a MessageTemplate> {
+    let dlg = Dialog::builder(parent, "Insert Template").with_size(360, 160).build();
+    let sizer = BoxSizer::builder(Orientation::Vertical).build();
+
+    let row = BoxSizer::builder(Orientation::Horizontal).build();
+    let choice = Choice::builder(&dlg)
+        .with_choices(templates.iter().map(|t| t.name.clone()).collect())
+        .with_selection(Some(0))
+        .build();
+    if templates.is_empty() {
+        let note = StaticText::builder(&dlg)
+            .with_label("No templates yet. Add them with Tools, Template Manager.")
+            .build();
+        row.add(&note, 1, SizerFlag::Expand | SizerFlag::All, 4);
+        choice.show(false);
+    } else {
+        let lbl = StaticText::builder(&dlg).with_label("&Template:").build();
+        row.add(&lbl, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+        row.add(&choice, 1, SizerFlag::Expand | SizerFlag::All, 4);
+    }
+    sizer.add_sizer(&row, 1, SizerFlag::Expand | SizerFlag::All, 8);
+
+    let btns = BoxSizer::builder(Orientation::Horizontal).build();
+    let ok = Button::builder(&dlg).with_label("&Insert").with_id(ID_OK).build();
+    let cancel = Button::builder(&dlg).with_label("Cancel").with_id(ID_CANCEL).build();
+    ok.show(!templates.is_empty());
+    btns.add_spacer(0);
+    btns.add(&ok, 0, SizerFlag::All, 4);
+    btns.add(&cancel, 0, SizerFlag::All, 4);
+    sizer.add_sizer(&btns, 0, SizerFlag::AlignRight | SizerFlag::All, 8);
+    dlg.set_sizer(sizer, true);
+
+    ok.on_click({ let d = dlg; move |_| { d.end_modal(ID_OK); } });
+    cancel.on_click({ let d = dlg; move |_| { d.end_modal(ID_CANCEL); } });
+
+    if dlg.show_modal() == ID_OK {
+        choice.get_selection().and_then(|i| templates.get(i as usize))
+    } else {
+        None
+    }
+}
+
+/// A recipient chip: the index of its field in [`RECIPIENT_FIELDS`] and
+/// its position in that field
+type ChipRef = (usize, usize);
+
+/// List every recipient as a chip and note the addresses that don't look
+/// right. The recipient still being typed in field `typing` isn't judged
+/// yet. Returns what each chip row refers to.
+fn show_recipient_chips(
+    dialog: &Dialog,
+    chips: &ListCtrl,
+    remove: &Button,
+    note: &StaticText,
+    fields: &[TextCtrl; 3],
+    typing: Option<usize>,
+) -> Vec<ChipRef> {
+    chips.delete_all_items();
+    let mut refs = Vec::new();
+    let (mut invalid, mut unusual) = (Vec::new(), Vec::new());
+    for (field_index, field) in fields.iter().enumerate() {
+        let value = field.get_value();
+        let checked = check_recipients(&value);
+        let unfinished = typing == Some(field_index) && !partial_recipient(&value).is_empty();
+        for (index, (address, status)) in checked.iter().enumerate() {
+            let row = refs.len() as i64;
+            let judged = !(unfinished && index + 1 == checked.len());
+            let (marker, problem) = match status {
+                RecipientStatus::Invalid if judged => {
+                    invalid.push(address.address.clone());
+                    ("⚠ ", "Not a valid address")
+                }
+                RecipientStatus::Unusual if judged => {
+                    unusual.push(address.address.clone());
+                    ("⚠ ", "Unusual address")
+                }
+                _ => ("", ""),
+            };
+            chips.insert_item(row, RECIPIENT_FIELDS[field_index], None);
+            chips.set_item_text_by_column(row, 1, &format!("{}{} ✕", marker, address));
+            chips.set_item_text_by_column(row, 2, problem);
+            refs.push((field_index, index));
+        }
+    }
+    let message = match (invalid.is_empty(), unusual.is_empty()) {
+        (false, _) => format!("⚠ Not a valid address: {}", invalid.join(", ")),
+        (true, false) => format!("Check these addresses: {}", unusual.join(", ")),
+        (true, true) => String::new(),
+    };
+    note.set_label(&message);
+    note.show(!message.is_empty());
+    chips.show(!refs.is_empty());
+    remove.show(!refs.is_empty());
+    dialog.layout();
+    refs
+}
+
+/// Check every recipient before sending. Invalid addresses block the send;
+/// unusual ones only need confirming. Returns whether to go ahead.
+fn recipients_ready(dialog: &Dialog, data: &ComposeData) -> bool {
+    let checked: Vec<_> = [&data.to, &data.cc, &data.bcc].into_iter().flat_map(|field| check_recipients(field)).collect();
+    let with = |wanted: RecipientStatus| -> Vec<String> {
+        checked.iter().filter(|(_, status)| *status == wanted).map(|(a, _)| a.address.clone()).collect()
+    };
+    let invalid = with(RecipientStatus::Invalid);
+    if !invalid.is_empty() {
+        MessageDialog::builder(
+            dialog,
+            &format!("These addresses aren't valid, so the message can't be sent:\n\n{}\n\nCorrect or remove them and try again.", invalid.join("\n")),
+            "Invalid Recipients",
+        )
+        .with_style(MessageDialogStyle::OK | MessageDialogStyle::IconError)
+        .build()
+        .show_modal();
+        return false;
+    }
+    let unusual = with(RecipientStatus::Unusual);
+    unusual.is_empty()
+        || MessageDialog::builder(
+            dialog,
+            &format!("These addresses look unusual:\n\n{}\n\nSend anyway?", unusual.join("\n")),
+            "Check Recipients",
+        )
+        .with_style(MessageDialogStyle::YesNo | MessageDialogStyle::IconWarning)
+        .build()
+        .show_modal()
+            == ID_YES
+}
+
+/// Ask before sending a Reply All to a crowd, or a message that mentions
+/// an attachment without one. Returns whether to go ahead.
+fn confirm_send_warnings(dialog: &Dialog, data: &ComposeData, reply_all: bool, reply_all_warning: usize) -> bool {
+    let confirm = |message: String, title: &str| {
+        MessageDialog::builder(dialog, &message, title)
+            .with_style(MessageDialogStyle::YesNo | MessageDialogStyle::IconWarning)
+            .build()
+            .show_modal()
+            == ID_YES
+    };
+    let recipients = recipient_count(&data.to, &data.cc, &data.bcc);
+    if reply_all && reply_all_warning > 0 && recipients > reply_all_warning {
+        let message = format!("This reply goes to all {} recipients of the original message.\n\nSend it to everyone?", recipients);
+        if !confirm(message, "Reply to Everyone?") {
+            return false;
+        }
+    }
+    // The composer has no attachments of its own yet, so any mention of one
+    // means something is missing
+    if mentions_attachment(&data.body, &tr("compose.attachment_keywords")) {
+        let message = "The message mentions an attachment, but no file is attached.\n\nSend it anyway?".to_string();
+        if !confirm(message, "Missing Attachment?") {
+            return false;
+        }
+    }
+    true
+}
+
+/// Fill the suggestion list, hiding it when there is nothing to offer.
+fn show_suggestions(dialog: &Dialog, list: &ListCtrl, found: &[AddressSuggestion]) {
+    list.delete_all_items();
+    for (i, suggestion) in found.iter().enumerate() {
+        let idx = i as i64;
+        list.insert_item(idx, &format!("{} {}", suggestion.icon(), suggestion.kind()), None);
+        list.set_item_text_by_column(idx, 1, &suggestion.label());
+    }
+    list.show(!found.is_empty());
+    dialog.layout();
+}
+
 // ── Preview Before Send ─────────────────────────────────────────────────────
 
 enum PreviewDecision {
@@ -426,7 +1143,7 @@ const ID_GO_BACK: Id = ID_HIGHEST + 121;
 fn show_send_preview(
     parent: &Dialog,
     data: &ComposeData,
-    account_names: &[String],
+    sender: Option<&FromChoice>,
 ) -> PreviewDecision {
     let dlg = Dialog::builder(parent, "Preview Before Send")
         .with_size(650, 500)
@@ -439,10 +1156,8 @@ fn show_send_preview(
     let hdr = FlexGridSizer::builder(0, 2).with_vgap(2).with_hgap(8).build();
     hdr.add_growable_col(1, 1);
 
-    let from_display = data
-        .account_index
-        .and_then(|i| account_names.get(i as usize))
-        .cloned()
+    let from_display = sender
+        .map(|s| s.label.clone())
         .unwrap_or_else(|| "(default account)".to_string());
 
     for (label, value) in [
@@ -479,11 +1194,11 @@ fn show_send_preview(
     // Buttons
     let btn_sizer = BoxSizer::builder(Orientation::Horizontal).build();
     let back_btn = Button::builder(&dlg)
-        .with_label("&Go Back && Edit")
+        .with_label(&tr("compose.go_back"))
         .with_id(ID_GO_BACK)
         .build();
     let send_btn = Button::builder(&dlg)
-        .with_label("Confirm &Send")
+        .with_label(&tr("compose.confirm_send"))
         .with_id(ID_CONFIRM_SEND)
         .build();
     btn_sizer.add(&back_btn, 0, SizerFlag::All, 4);
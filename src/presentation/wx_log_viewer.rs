@@ -0,0 +1,115 @@
+//! Log viewer
+//!
+//! Help > View Logs lists the events `common::logging` kept in memory,
+//! filtered by level and text, so they can be copied or saved into a bug
+//! report without finding the log folder.
+
+use crate::common::logging::{recent_logs, LogEntry, LogLevel, LoggerConfig};
+use std::cell::RefCell;
+use std::rc::Rc;
+use wxdragon::prelude::*;
+
+const ID_LOG_SAVE: Id = ID_HIGHEST + 400;
+
+/// Level filter choices, least verbose first
+const LEVELS: [(&str, LogLevel); 5] = [
+    ("Errors", LogLevel::Error),
+    ("Warnings and errors", LogLevel::Warn),
+    ("Information", LogLevel::Info),
+    ("Debug", LogLevel::Debug),
+    ("Everything", LogLevel::Trace),
+];
+
+/// The entries shown for a filter, one per line, and how many there are.
+fn render(entries: &[LogEntry], level: LogLevel, query: &str) -> (String, usize) {
+    let shown: Vec<String> = entries.iter().filter(|e| e.matches(level, query)).map(|e| e.to_string()).collect();
+    (shown.join("\n"), shown.len())
+}
+
+/// Show recent log events. Returns the filtered text when the user chose
+/// Save, for the caller to write to a file.
+pub fn show_log_viewer(parent: &Frame) -> Option<String> {
+    let dlg = Dialog::builder(parent, "Logs").with_size(860, 580).build();
+    let sizer = BoxSizer::builder(Orientation::Vertical).build();
+
+    let row = BoxSizer::builder(Orientation::Horizontal).build();
+    let level_label = StaticText::builder(&dlg).with_label("&Show:").build();
+    let level = Choice::builder(&dlg)
+        .with_choices(LEVELS.iter().map(|(name, _)| name.to_string()).collect())
+        .with_selection(Some(LEVELS.len() as u32 - 1))
+        .build();
+    let find_label = StaticText::builder(&dlg).with_label("&Find:").build();
+    let find = TextCtrl::builder(&dlg).build();
+    row.add(&level_label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+    row.add(&level, 0, SizerFlag::All, 4);
+    row.add(&find_label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+    row.add(&find, 1, SizerFlag::Expand | SizerFlag::All, 4);
+    sizer.add_sizer(&row, 0, SizerFlag::Expand | SizerFlag::All, 4);
+
+    let text = TextCtrl::builder(&dlg).with_style(TextCtrlStyle::MultiLine | TextCtrlStyle::ReadOnly).build();
+    sizer.add(&text, 1, SizerFlag::Expand | SizerFlag::All, 8);
+
+    let count = StaticText::builder(&dlg).with_label("").build();
+    sizer.add(&count, 0, SizerFlag::Expand | SizerFlag::Left | SizerFlag::Right, 8);
+
+    let hint = StaticText::builder(&dlg)
+        .with_label(&format!(
+            "Select text and press Ctrl+C to copy it. Older events are in the log files in\n{}",
+            LoggerConfig::default().log_dir.display()
+        ))
+        .build();
+    sizer.add(&hint, 0, SizerFlag::All, 8);
+
+    let btns = BoxSizer::builder(Orientation::Horizontal).build();
+    let refresh = Button::builder(&dlg).with_label("&Refresh").build();
+    let save = Button::builder(&dlg).with_label("&Save...").with_id(ID_LOG_SAVE).build();
+    let close = Button::builder(&dlg).with_label("&Close").with_id(ID_CANCEL).build();
+    btns.add(&refresh, 0, SizerFlag::All, 4);
+    btns.add_spacer(0);
+    btns.add(&save, 0, SizerFlag::All, 4);
+    btns.add(&close, 0, SizerFlag::All, 4);
+    sizer.add_sizer(&btns, 0, SizerFlag::AlignRight | SizerFlag::All, 8);
+    dlg.set_sizer(sizer, true);
+
+    let entries = Rc::new(RefCell::new(recent_logs()));
+    let shown = Rc::new(RefCell::new(String::new()));
+    let update = {
+        let entries = entries.clone();
+        let shown = shown.clone();
+        move || {
+            let min = LEVELS.get(level.get_selection().unwrap_or(0) as usize).map_or(LogLevel::Trace, |(_, l)| *l);
+            let all = entries.borrow();
+            let (lines, n) = render(&all, min, &find.get_value());
+            text.set_value(&lines);
+            count.set_label(&format!("{} of {} events", n, all.len()));
+            *shown.borrow_mut() = lines;
+        }
+    };
+    update();
+
+    level.on_selection_changed({
+        let update = update.clone();
+        move |_| update()
+    });
+    find.on_text_changed({
+        let update = update.clone();
+        move |_| update()
+    });
+    refresh.on_click({
+        let entries = entries.clone();
+        move |_| {
+            *entries.borrow_mut() = recent_logs();
+            update();
+        }
+    });
+    save.on_click({ let d = dlg; move |_| { d.end_modal(ID_LOG_SAVE); } });
+    close.on_click({ let d = dlg; move |_| { d.end_modal(ID_CANCEL); } });
+
+    text.set_focus();
+    if dlg.show_modal() == ID_LOG_SAVE {
+        let lines = shown.borrow().clone();
+        Some(lines)
+    } else {
+        None
+    }
+}
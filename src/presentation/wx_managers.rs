@@ -1,8 +1,11 @@
 //! wxdragon Manager Dialogs
 //!
-//! Contact, Filter, Tag, and Signature managers sharing a generic modal loop.
+//! Contact, Filter, Tag, Signature, Template, Identity, and Sender managers sharing a generic modal loop,
+//! plus the Manage Folders dialog.
 
-use std::cell::RefCell;
+use crate::data::message_cache::{FolderSubscription, SenderList};
+use crate::presentation::accessibility::shortcuts::ShortcutManager;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 use wxdragon::prelude::*;
 
@@ -1141,13 +1144,14 @@ fn show_filter_edit(parent: &Dialog, existing: Option<&FilterRule>) -> Option<Fi
     fields.add(&cs_check, 0, SizerFlag::All, 4);
 
     let action_label = StaticText::builder(&dlg).with_label("&Action:").build();
-    let action_choices: Vec<String> = ["mark_as_read", "mark_as_unread", "star", "delete", "move_to_folder", "add_tag"]
-        .iter().map(|s| s.to_string()).collect();
+    let action_choices: Vec<String> =
+        ["mark_as_read", "mark_as_unread", "star", "delete", "move_to_folder", "add_tag", "forward", "auto_reply"]
+            .iter().map(|s| s.to_string()).collect();
     let action_choice = Choice::builder(&dlg).with_choices(action_choices).build();
     fields.add(&action_label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
     fields.add(&action_choice, 1, SizerFlag::Expand | SizerFlag::All, 4);
 
-    let action_value_f = add_field(&dlg, &fields, "Action &Value:");
+    let action_value_f = add_field(&dlg, &fields, "Action &Value (folder, tag, forward address or template name):");
 
     let en_label = StaticText::builder(&dlg).with_label("").build();
     let en_check = CheckBox::builder(&dlg).with_label("&Enabled").build();
@@ -1206,6 +1210,8 @@ pub struct TagEntry {
     pub id: String,
     pub name: String,
     pub color: String,
+    /// Key that applies the tag in the message list
+    pub shortcut: Option<char>,
 }
 
 #[derive(Debug, Clone)]
@@ -1224,14 +1230,20 @@ pub fn show_tag_manager_dialog(parent: &Frame, tags: &[TagEntry]) -> TagManagerA
 
     list.insert_column(0, "Tag", ListColumnFormat::Left, 200);
     list.insert_column(1, "Color", ListColumnFormat::Left, 100);
+    list.insert_column(2, "Key", ListColumnFormat::Left, 60);
     sizer.add(&list, 1, SizerFlag::Expand | SizerFlag::All, 8);
 
+    // The edit dialog checks new shortcut keys against the current list
+    let current = Rc::new(RefCell::new(tags.to_vec()));
     let mut working = tags.to_vec();
     let changed = run_manager_loop(
         &dialog, &sizer, &list, &status, &mut working,
-        populate_tags,
-        |d| show_tag_edit(d, None),
-        |d, t| show_tag_edit(d, Some(t)),
+        |list, tags| {
+            *current.borrow_mut() = tags.to_vec();
+            populate_tags(list, tags);
+        },
+        |d| show_tag_edit(d, None, &current.borrow()),
+        |d, t| show_tag_edit(d, Some(t), &current.borrow()),
         |t| t.name.clone(),
     );
 
@@ -1248,17 +1260,19 @@ fn populate_tags(list: &ListCtrl, tags: &[TagEntry]) {
             .map(|(name, _)| *name)
             .unwrap_or(&t.color);
         list.set_item_text_by_column(idx, 1, color_name);
+        let key = t.shortcut.map(|c| c.to_ascii_uppercase().to_string()).unwrap_or_default();
+        list.set_item_text_by_column(idx, 2, &key);
     }
 }
 
-fn show_tag_edit(parent: &Dialog, existing: Option<&TagEntry>) -> Option<TagEntry> {
+fn show_tag_edit(parent: &Dialog, existing: Option<&TagEntry>, tags: &[TagEntry]) -> Option<TagEntry> {
     let title = if existing.is_some() { "Edit Tag" } else { "Add Tag" };
-    let dlg = Dialog::builder(parent, title).with_size(350, 250).build();
+    let dlg = Dialog::builder(parent, title).with_size(350, 280).build();
     let sizer = BoxSizer::builder(Orientation::Vertical).build();
     let fields = FlexGridSizer::builder(0, 2).with_vgap(4).with_hgap(8).build();
     fields.add_growable_col(1, 1);
 
-    // Accelerators: N(Name), C(Color) — first letters, no conflicts
+    // Accelerators: N(Name), C(Color), K(Key) — no conflicts
     let name_f = add_field(&dlg, &fields, "Tag &Name:");
 
     let color_label = StaticText::builder(&dlg).with_label("&Color:").build();
@@ -1267,8 +1281,11 @@ fn show_tag_edit(parent: &Dialog, existing: Option<&TagEntry>) -> Option<TagEntr
     color_choice.set_selection(0);
     fields.add(&color_label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
     fields.add(&color_choice, 1, SizerFlag::Expand | SizerFlag::All, 4);
+    let key_f = add_field(&dlg, &fields, "Shortcut &Key (optional):");
 
     sizer.add_sizer(&fields, 1, SizerFlag::Expand | SizerFlag::All, 8);
+    let error = StaticText::builder(&dlg).with_label(" ").build();
+    sizer.add(&error, 0, SizerFlag::Expand | SizerFlag::All, 4);
 
     let btn_row = BoxSizer::builder(Orientation::Horizontal).build();
     let ok = Button::builder(&dlg).with_label("OK").with_id(ID_OK).build();
@@ -1284,22 +1301,41 @@ fn show_tag_edit(parent: &Dialog, existing: Option<&TagEntry>) -> Option<TagEntr
         if let Some(pos) = TAG_COLORS.iter().position(|(_, hex)| *hex == t.color) {
             color_choice.set_selection(pos as u32);
         }
+        if let Some(key) = t.shortcut {
+            key_f.set_value(&key.to_string());
+        }
     }
 
     ok.on_click({ let d = dlg; move |_| { d.end_modal(ID_OK); } });
     cancel.on_click({ let d = dlg; move |_| { d.end_modal(ID_CANCEL); } });
 
-    if dlg.show_modal() == ID_OK {
+    let id = existing.map(|t| t.id.clone()).unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let shortcuts = ShortcutManager::new();
+    while dlg.show_modal() == ID_OK {
+        let key = key_f.get_value();
+        let shortcut = if key.trim().is_empty() {
+            Ok(None)
+        } else {
+            shortcuts.validate_tag_key(&key).map_err(|e| e.to_string()).and_then(|c| {
+                match tags.iter().find(|t| t.id != id && t.shortcut == Some(c)) {
+                    Some(other) => Err(format!("{} is already the shortcut for the {} tag", c.to_ascii_uppercase(), other.name)),
+                    None => Ok(Some(c)),
+                }
+            })
+        };
+        let shortcut = match shortcut {
+            Ok(shortcut) => shortcut,
+            Err(message) => {
+                error.set_label(&message);
+                key_f.set_focus();
+                continue;
+            }
+        };
         let color_idx = color_choice.get_selection().unwrap_or(0) as usize;
         let color = TAG_COLORS.get(color_idx).map(|(_, hex)| hex.to_string()).unwrap_or_else(|| "#1E88E5".to_string());
-        Some(TagEntry {
-            id: existing.map(|t| t.id.clone()).unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
-            name: name_f.get_value(),
-            color,
-        })
-    } else {
-        None
+        return Some(TagEntry { id, name: name_f.get_value(), color, shortcut });
     }
+    None
 }
 
 // ══════════════════════════════════════════════════════════════════════════════
@@ -1427,3 +1463,482 @@ fn show_sig_edit(parent: &Dialog, existing: Option<&SignatureEntry>) -> Option<S
         None
     }
 }
+
+// ══════════════════════════════════════════════════════════════════════════════
+// Template Manager
+// ══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Debug, Clone)]
+pub struct TemplateEntry {
+    pub id: String,
+    pub name: String,
+    pub subject: String,
+    pub body: String,
+    pub is_html: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum TemplateManagerAction {
+    None,
+    Updated(Vec<TemplateEntry>),
+}
+
+pub fn show_template_manager_dialog(parent: &Frame, templates: &[TemplateEntry]) -> TemplateManagerAction {
+    let (dialog, sizer, list, status) = make_shell(parent, "Template Manager", 550, 450);
+
+    list.insert_column(0, "Name", ListColumnFormat::Left, 180);
+    list.insert_column(1, "Subject", ListColumnFormat::Left, 160);
+    list.insert_column(2, "Preview", ListColumnFormat::Left, 180);
+    sizer.add(&list, 1, SizerFlag::Expand | SizerFlag::All, 8);
+
+    let mut working = templates.to_vec();
+    let changed = run_manager_loop(
+        &dialog, &sizer, &list, &status, &mut working,
+        populate_templates,
+        |d| show_template_edit(d, None),
+        |d, t| show_template_edit(d, Some(t)),
+        |t| t.name.clone(),
+    );
+
+    if changed {
+        TemplateManagerAction::Updated(working)
+    } else {
+        TemplateManagerAction::None
+    }
+}
+
+fn populate_templates(list: &ListCtrl, templates: &[TemplateEntry]) {
+    list.delete_all_items();
+    for (i, t) in templates.iter().enumerate() {
+        let idx = i as i64;
+        list.insert_item(idx, &t.name, None);
+        list.set_item_text_by_column(idx, 1, &t.subject);
+        let preview: String = t.body.chars().take(50).collect();
+        list.set_item_text_by_column(idx, 2, &preview);
+    }
+}
+
+fn show_template_edit(parent: &Dialog, existing: Option<&TemplateEntry>) -> Option<TemplateEntry> {
+    let title = if existing.is_some() { "Edit Template" } else { "Add Template" };
+    let dlg = Dialog::builder(parent, title).with_size(500, 420).build();
+    let sizer = BoxSizer::builder(Orientation::Vertical).build();
+    let fields = FlexGridSizer::builder(0, 2).with_vgap(4).with_hgap(8).build();
+    fields.add_growable_col(1, 1);
+
+    // Accelerators: N(Name), B(Subject), H(HTML), T(Template text)
+    let name_f = add_field(&dlg, &fields, "&Name:");
+    let subject_f = add_field(&dlg, &fields, "Su&bject:");
+
+    let html_label = StaticText::builder(&dlg).with_label("").build();
+    let html_check = CheckBox::builder(&dlg).with_label("&HTML template").build();
+    fields.add(&html_label, 0, SizerFlag::All, 4);
+    fields.add(&html_check, 0, SizerFlag::All, 4);
+
+    sizer.add_sizer(&fields, 0, SizerFlag::Expand | SizerFlag::Left | SizerFlag::Right | SizerFlag::Top, 8);
+
+    let body_label = StaticText::builder(&dlg).with_label("&Template text:").build();
+    sizer.add(&body_label, 0, SizerFlag::Left | SizerFlag::All, 8);
+    let body_f = TextCtrl::builder(&dlg)
+        .with_style(TextCtrlStyle::MultiLine | TextCtrlStyle::WordWrap)
+        .build();
+    sizer.add(&body_f, 1, SizerFlag::Expand | SizerFlag::All, 8);
+
+    let hint = StaticText::builder(&dlg)
+        .with_label("Placeholders: {{recipient_name}}, {{recipient_first_name}}, {{recipient_email}}, {{sender_email}}, {{date}}")
+        .build();
+    sizer.add(&hint, 0, SizerFlag::Expand | SizerFlag::Left | SizerFlag::Right, 8);
+
+    let btn_row = BoxSizer::builder(Orientation::Horizontal).build();
+    let ok = Button::builder(&dlg).with_label("OK").with_id(ID_OK).build();
+    let cancel = Button::builder(&dlg).with_label("Cancel").with_id(ID_CANCEL).build();
+    btn_row.add_spacer(0);
+    btn_row.add(&ok, 0, SizerFlag::All, 4);
+    btn_row.add(&cancel, 0, SizerFlag::All, 4);
+    sizer.add_sizer(&btn_row, 0, SizerFlag::AlignRight | SizerFlag::All, 4);
+    dlg.set_sizer(sizer, true);
+
+    if let Some(t) = existing {
+        name_f.set_value(&t.name);
+        subject_f.set_value(&t.subject);
+        body_f.set_value(&t.body);
+        html_check.set_value(t.is_html);
+    }
+
+    ok.on_click({ let d = dlg; move |_| { d.end_modal(ID_OK); } });
+    cancel.on_click({ let d = dlg; move |_| { d.end_modal(ID_CANCEL); } });
+
+    if dlg.show_modal() == ID_OK && !name_f.get_value().trim().is_empty() {
+        Some(TemplateEntry {
+            id: existing.map(|t| t.id.clone()).unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+            name: name_f.get_value().trim().to_string(),
+            subject: subject_f.get_value(),
+            body: body_f.get_value(),
+            is_html: html_check.get_value(),
+        })
+    } else {
+        None
+    }
+}
+
+// ══════════════════════════════════════════════════════════════════════════════
+// Identity Manager
+// ══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Debug, Clone)]
+pub struct IdentityEntry {
+    pub id: String,
+    pub from_name: String,
+    pub from_email: String,
+    pub reply_to: Option<String>,
+    pub envelope_from: Option<String>,
+    pub signature_id: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum IdentityManagerAction {
+    None,
+    Updated(Vec<IdentityEntry>),
+}
+
+/// Manage the From addresses of `account_email`. `signatures` are the
+/// account's `(id, name)` pairs offered for each identity; `check` returns
+/// the problem to show when an identity can't be accepted.
+pub fn show_identity_manager_dialog(
+    parent: &Frame,
+    account_email: &str,
+    identities: &[IdentityEntry],
+    signatures: &[(String, String)],
+    check: &dyn Fn(&IdentityEntry) -> Result<(), String>,
+) -> IdentityManagerAction {
+    let (dialog, sizer, list, status) = make_shell(parent, &format!("Identities for {}", account_email), 550, 450);
+
+    list.insert_column(0, "From", ListColumnFormat::Left, 260);
+    list.insert_column(1, "Reply-To", ListColumnFormat::Left, 160);
+    list.insert_column(2, "Signature", ListColumnFormat::Left, 100);
+    sizer.add(&list, 1, SizerFlag::Expand | SizerFlag::All, 8);
+
+    let mut working = identities.to_vec();
+    let changed = run_manager_loop(
+        &dialog, &sizer, &list, &status, &mut working,
+        |l, items| populate_identities(l, items, signatures),
+        |d| show_identity_edit(d, None, signatures, check),
+        |d, i| show_identity_edit(d, Some(i), signatures, check),
+        |i| i.from_email.clone(),
+    );
+
+    if changed {
+        IdentityManagerAction::Updated(working)
+    } else {
+        IdentityManagerAction::None
+    }
+}
+
+fn populate_identities(list: &ListCtrl, identities: &[IdentityEntry], signatures: &[(String, String)]) {
+    list.delete_all_items();
+    for (i, identity) in identities.iter().enumerate() {
+        let idx = i as i64;
+        let from = if identity.from_name.trim().is_empty() {
+            identity.from_email.clone()
+        } else {
+            format!("{} <{}>", identity.from_name, identity.from_email)
+        };
+        list.insert_item(idx, &from, None);
+        list.set_item_text_by_column(idx, 1, identity.reply_to.as_deref().unwrap_or(""));
+        let signature = identity
+            .signature_id
+            .as_ref()
+            .and_then(|id| signatures.iter().find(|(sid, _)| sid == id))
+            .map(|(_, name)| name.as_str())
+            .unwrap_or("Default");
+        list.set_item_text_by_column(idx, 2, signature);
+    }
+}
+
+fn show_identity_edit(
+    parent: &Dialog,
+    existing: Option<&IdentityEntry>,
+    signatures: &[(String, String)],
+    check: &dyn Fn(&IdentityEntry) -> Result<(), String>,
+) -> Option<IdentityEntry> {
+    let title = if existing.is_some() { "Edit Identity" } else { "Add Identity" };
+    let dlg = Dialog::builder(parent, title).with_size(460, 290).build();
+    let sizer = BoxSizer::builder(Orientation::Vertical).build();
+    let fields = FlexGridSizer::builder(0, 2).with_vgap(4).with_hgap(8).build();
+    fields.add_growable_col(1, 1);
+
+    // Accelerators: N(Name), E(Email), R(Reply-To), B(Bounce), S(Signature)
+    let name_f = add_field(&dlg, &fields, "&Name:");
+    let email_f = add_field(&dlg, &fields, "&Email address:");
+    let reply_f = add_field(&dlg, &fields, "&Reply-To (optional):");
+    // SMTP MAIL FROM; empty falls back to the account's, then the From address
+    let bounce_f = add_field(&dlg, &fields, "&Bounce address (optional):");
+
+    let sig_label = StaticText::builder(&dlg).with_label("&Signature:").build();
+    let mut sig_choices = vec!["Default".to_string()];
+    sig_choices.extend(signatures.iter().map(|(_, name)| name.clone()));
+    let sig_choice = Choice::builder(&dlg).with_choices(sig_choices).with_selection(Some(0)).build();
+    fields.add(&sig_label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+    fields.add(&sig_choice, 1, SizerFlag::Expand | SizerFlag::All, 4);
+
+    sizer.add_sizer(&fields, 0, SizerFlag::Expand | SizerFlag::All, 8);
+    let error = StaticText::builder(&dlg).with_label(" ").build();
+    sizer.add(&error, 0, SizerFlag::Expand | SizerFlag::Left | SizerFlag::Right, 8);
+
+    let btn_row = BoxSizer::builder(Orientation::Horizontal).build();
+    let ok = Button::builder(&dlg).with_label("OK").with_id(ID_OK).build();
+    let cancel = Button::builder(&dlg).with_label("Cancel").with_id(ID_CANCEL).build();
+    btn_row.add_spacer(0);
+    btn_row.add(&ok, 0, SizerFlag::All, 4);
+    btn_row.add(&cancel, 0, SizerFlag::All, 4);
+    sizer.add_sizer(&btn_row, 0, SizerFlag::AlignRight | SizerFlag::All, 4);
+    dlg.set_sizer(sizer, true);
+
+    if let Some(i) = existing {
+        name_f.set_value(&i.from_name);
+        email_f.set_value(&i.from_email);
+        reply_f.set_value(i.reply_to.as_deref().unwrap_or(""));
+        bounce_f.set_value(i.envelope_from.as_deref().unwrap_or(""));
+        if let Some(pos) = i.signature_id.as_ref().and_then(|id| signatures.iter().position(|(sid, _)| sid == id)) {
+            sig_choice.set_selection(pos as u32 + 1);
+        }
+    }
+
+    ok.on_click({ let d = dlg; move |_| { d.end_modal(ID_OK); } });
+    cancel.on_click({ let d = dlg; move |_| { d.end_modal(ID_CANCEL); } });
+
+    // Keep the dialog open until the identity passes the check
+    while dlg.show_modal() == ID_OK {
+        let reply_to = reply_f.get_value().trim().to_string();
+        let envelope_from = bounce_f.get_value().trim().to_string();
+        let entry = IdentityEntry {
+            id: existing.map(|i| i.id.clone()).unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+            from_name: name_f.get_value().trim().to_string(),
+            from_email: email_f.get_value().trim().to_string(),
+            reply_to: (!reply_to.is_empty()).then_some(reply_to),
+            envelope_from: (!envelope_from.is_empty()).then_some(envelope_from),
+            signature_id: sig_choice
+                .get_selection()
+                .and_then(|i| (i as usize).checked_sub(1))
+                .and_then(|i| signatures.get(i))
+                .map(|(id, _)| id.clone()),
+        };
+        match check(&entry) {
+            Ok(()) => return Some(entry),
+            Err(problem) => {
+                error.set_label(&problem);
+                email_f.set_focus();
+            }
+        }
+    }
+    None
+}
+
+// ══════════════════════════════════════════════════════════════════════════════
+// Blocked, Trusted and Junk Senders
+// ══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SenderEntry {
+    pub address: String,
+    pub list: SenderList,
+}
+
+/// Name shown for each sender list, in `SenderList::ALL` order
+const SENDER_LIST_NAMES: [&str; 3] = ["Blocked", "Trusted", "Junk"];
+
+fn sender_list_name(list: SenderList) -> &'static str {
+    let index = SenderList::ALL.iter().position(|l| *l == list).unwrap_or(0);
+    SENDER_LIST_NAMES[index]
+}
+
+#[derive(Debug, Clone)]
+pub enum SenderManagerAction {
+    None,
+    Updated(Vec<SenderEntry>),
+}
+
+pub fn show_sender_manager_dialog(parent: &Frame, senders: &[SenderEntry]) -> SenderManagerAction {
+    let (dialog, sizer, list, status) = make_shell(parent, "Blocked, Trusted and Junk Senders", 450, 400);
+
+    list.insert_column(0, "Address", ListColumnFormat::Left, 280);
+    list.insert_column(1, "List", ListColumnFormat::Left, 100);
+    sizer.add(&list, 1, SizerFlag::Expand | SizerFlag::All, 8);
+
+    let mut working = senders.to_vec();
+    let changed = run_manager_loop(
+        &dialog, &sizer, &list, &status, &mut working,
+        populate_senders,
+        |d| show_sender_edit(d, None),
+        |d, e| show_sender_edit(d, Some(e)),
+        |e| e.address.clone(),
+    );
+
+    if changed { SenderManagerAction::Updated(working) } else { SenderManagerAction::None }
+}
+
+fn populate_senders(list: &ListCtrl, senders: &[SenderEntry]) {
+    list.delete_all_items();
+    for (i, e) in senders.iter().enumerate() {
+        let idx = i as i64;
+        list.insert_item(idx, &e.address, None);
+        list.set_item_text_by_column(idx, 1, sender_list_name(e.list));
+    }
+}
+
+fn show_sender_edit(parent: &Dialog, existing: Option<&SenderEntry>) -> Option<SenderEntry> {
+    let title = if existing.is_some() { "Edit Sender" } else { "Add Sender" };
+    let dlg = Dialog::builder(parent, title).with_size(380, 220).build();
+    let sizer = BoxSizer::builder(Orientation::Vertical).build();
+    let fields = FlexGridSizer::builder(0, 2).with_vgap(4).with_hgap(8).build();
+    fields.add_growable_col(1, 1);
+
+    let address_f = add_field(&dlg, &fields, "&Email address or domain:");
+    let list_label = StaticText::builder(&dlg).with_label("&List:").build();
+    let list_choice = Choice::builder(&dlg)
+        .with_choices(SENDER_LIST_NAMES.iter().map(|name| name.to_string()).collect())
+        .with_selection(Some(0))
+        .build();
+    fields.add(&list_label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+    fields.add(&list_choice, 1, SizerFlag::Expand | SizerFlag::All, 4);
+
+    sizer.add_sizer(&fields, 0, SizerFlag::Expand | SizerFlag::All, 8);
+    let error = StaticText::builder(&dlg).with_label(" ").build();
+    sizer.add(&error, 0, SizerFlag::Expand | SizerFlag::Left | SizerFlag::Right, 8);
+
+    let btn_row = BoxSizer::builder(Orientation::Horizontal).build();
+    let ok = Button::builder(&dlg).with_label("OK").with_id(ID_OK).build();
+    let cancel = Button::builder(&dlg).with_label("Cancel").with_id(ID_CANCEL).build();
+    btn_row.add_spacer(0);
+    btn_row.add(&ok, 0, SizerFlag::All, 4);
+    btn_row.add(&cancel, 0, SizerFlag::All, 4);
+    sizer.add_sizer(&btn_row, 0, SizerFlag::AlignRight | SizerFlag::All, 4);
+    dlg.set_sizer(sizer, true);
+
+    if let Some(e) = existing {
+        address_f.set_value(&e.address);
+        let index = SenderList::ALL.iter().position(|l| *l == e.list).unwrap_or(0);
+        list_choice.set_selection(index as u32);
+    }
+
+    ok.on_click({ let d = dlg; move |_| { d.end_modal(ID_OK); } });
+    cancel.on_click({ let d = dlg; move |_| { d.end_modal(ID_CANCEL); } });
+
+    while dlg.show_modal() == ID_OK {
+        let address = address_f.get_value().trim().trim_start_matches('@').to_lowercase();
+        let list = SenderList::ALL[list_choice.get_selection().unwrap_or(0) as usize % SenderList::ALL.len()];
+        // Only the trusted list takes whole domains
+        let domain = list == SenderList::Trusted && !address.contains('@') && address.contains('.');
+        if !(address.contains('@') || domain) || address.contains(char::is_whitespace) {
+            error.set_label(if list == SenderList::Trusted {
+                "Enter one email address or domain, such as name@example.com or example.com"
+            } else {
+                "Enter one email address, such as name@example.com"
+            });
+            address_f.set_focus();
+            continue;
+        }
+        return Some(SenderEntry { address, list });
+    }
+    None
+}
+
+// ══════════════════════════════════════════════════════════════════════════════
+// Manage Folders
+// ══════════════════════════════════════════════════════════════════════════════
+
+/// What the user chose in the Manage Folders dialog
+#[derive(Debug, Clone)]
+pub struct FolderManagerResult {
+    /// List only folders on the subscription list
+    pub subscribed_only: bool,
+    pub folders: Vec<FolderSubscription>,
+}
+
+/// Choose which folders the folder list shows. A folder's checkbox shows
+/// or hides it on this computer; Toggle Subscription adds it to or removes
+/// it from the server's subscription list. `None` when cancelled.
+pub fn show_folder_manager_dialog(
+    parent: &Frame,
+    folders: &[FolderSubscription],
+    subscribed_only: bool,
+) -> Option<FolderManagerResult> {
+    let dialog = Dialog::builder(parent, "Manage Folders").with_size(440, 460).build();
+    let sizer = BoxSizer::builder(Orientation::Vertical).build();
+
+    let only_check = CheckBox::builder(&dialog).with_label("Show only &subscribed folders").build();
+    only_check.set_value(subscribed_only);
+    sizer.add(&only_check, 0, SizerFlag::All, 8);
+    let label = StaticText::builder(&dialog).with_label("&Folders to show:").build();
+    sizer.add(&label, 0, SizerFlag::Left | SizerFlag::Right, 8);
+    let list = CheckListBox::builder(&dialog).build();
+    sizer.add(&list, 1, SizerFlag::Expand | SizerFlag::All, 8);
+
+    let btn_row = BoxSizer::builder(Orientation::Horizontal).build();
+    let subscribe = Button::builder(&dialog).with_label("&Toggle Subscription").build();
+    let ok = Button::builder(&dialog).with_label("OK").with_id(ID_OK).build();
+    let cancel = Button::builder(&dialog).with_label("Cancel").with_id(ID_CANCEL).build();
+    btn_row.add(&subscribe, 0, SizerFlag::All, 4);
+    btn_row.add_spacer(0);
+    btn_row.add(&ok, 0, SizerFlag::All, 4);
+    btn_row.add(&cancel, 0, SizerFlag::All, 4);
+    sizer.add_sizer(&btn_row, 0, SizerFlag::Expand | SizerFlag::All, 4);
+    dialog.set_sizer(sizer, true);
+
+    let working = Rc::new(RefCell::new(folders.to_vec()));
+    let only = Rc::new(Cell::new(subscribed_only));
+    populate_folder_choices(&list, &working.borrow(), subscribed_only);
+
+    // Keep the boxes ticked so far before anything redraws the list
+    let take_checks = {
+        let working = working.clone();
+        let only = only.clone();
+        move || {
+            for (i, folder) in working.borrow_mut().iter_mut().enumerate() {
+                folder.choose_visible(list.is_checked(i as u32), only.get());
+            }
+        }
+    };
+    only_check.on_toggled({
+        let working = working.clone();
+        let only = only.clone();
+        let take_checks = take_checks.clone();
+        move |_| {
+            take_checks();
+            only.set(only_check.get_value());
+            populate_folder_choices(&list, &working.borrow(), only.get());
+        }
+    });
+    subscribe.on_click({
+        let working = working.clone();
+        let only = only.clone();
+        let take_checks = take_checks.clone();
+        move |_| {
+            let Some(index) = list.get_selection() else { return };
+            take_checks();
+            if let Some(folder) = working.borrow_mut().get_mut(index as usize) {
+                folder.subscribed = !folder.subscribed;
+            }
+            populate_folder_choices(&list, &working.borrow(), only.get());
+            list.set_selection(index, true);
+            list.set_focus();
+        }
+    });
+    ok.on_click({ let d = dialog; move |_| { d.end_modal(ID_OK); } });
+    cancel.on_click({ let d = dialog; move |_| { d.end_modal(ID_CANCEL); } });
+
+    if dialog.show_modal() != ID_OK {
+        return None;
+    }
+    take_checks();
+    let folders = working.borrow().clone();
+    Some(FolderManagerResult { subscribed_only: only.get(), folders })
+}
+
+fn populate_folder_choices(list: &CheckListBox, folders: &[FolderSubscription], subscribed_only: bool) {
+    list.clear();
+    for (i, f) in folders.iter().enumerate() {
+        let label = if f.subscribed { f.path.clone() } else { format!("{} (not subscribed)", f.path) };
+        list.append(&label);
+        list.check(i as u32, f.is_visible(subscribed_only));
+    }
+}
@@ -0,0 +1,127 @@
+//! Master password dialogs
+//!
+//! Unlock prompt shown at startup when a master password protects stored
+//! credentials, and the Tools > Master Password dialog for setting or
+//! changing it. Key derivation and verification live in
+//! `service::security::MasterPasswordStore`.
+
+use crate::service::security::{MasterPasswordStore, SecurityService};
+use wxdragon::prelude::*;
+
+/// A validated request to set or change the master password.
+pub struct MasterPasswordChange {
+    /// Current password (only when one is already set)
+    pub current: Option<String>,
+    pub new: String,
+}
+
+/// Add a labelled password field to a two-column grid.
+pub(crate) fn password_field(dlg: &Dialog, fields: &FlexGridSizer, label: &str) -> TextCtrl {
+    let l = StaticText::builder(dlg).with_label(label).build();
+    let f = TextCtrl::builder(dlg).with_style(TextCtrlStyle::Password).build();
+    fields.add(&l, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+    fields.add(&f, 1, SizerFlag::Expand | SizerFlag::All, 4);
+    f
+}
+
+/// Add OK/Cancel buttons to a dialog sizer.
+pub(crate) fn ok_cancel(dlg: &Dialog, sizer: &BoxSizer) {
+    let btn_row = BoxSizer::builder(Orientation::Horizontal).build();
+    let ok = Button::builder(dlg).with_label("OK").with_id(ID_OK).build();
+    let cancel = Button::builder(dlg).with_label("Cancel").with_id(ID_CANCEL).build();
+    btn_row.add_spacer(0);
+    btn_row.add(&ok, 0, SizerFlag::All, 4);
+    btn_row.add(&cancel, 0, SizerFlag::All, 4);
+    sizer.add_sizer(&btn_row, 0, SizerFlag::AlignRight | SizerFlag::All, 8);
+    ok.on_click({ let d = *dlg; move |_| { d.end_modal(ID_OK); } });
+    cancel.on_click({ let d = *dlg; move |_| { d.end_modal(ID_CANCEL); } });
+}
+
+/// Ask for the master password until it unlocks the store or the user cancels.
+///
+/// Returns the unlocked service and whether to remember it on this device
+/// (otherwise the key is kept for this session only).
+pub fn prompt_unlock(parent: &Frame, store: &MasterPasswordStore) -> Option<(SecurityService, bool)> {
+    let dlg = Dialog::builder(parent, "Unlock Wixen Mail").with_size(420, 220).build();
+    let sizer = BoxSizer::builder(Orientation::Vertical).build();
+
+    let intro = StaticText::builder(&dlg)
+        .with_label("Enter your master password to unlock saved account passwords.")
+        .build();
+    sizer.add(&intro, 0, SizerFlag::All, 8);
+
+    let fields = FlexGridSizer::builder(0, 2).with_vgap(6).with_hgap(8).build();
+    fields.add_growable_col(1, 1);
+    let password = password_field(&dlg, &fields, "Master &password:");
+    sizer.add_sizer(&fields, 0, SizerFlag::Expand | SizerFlag::All, 4);
+
+    let remember = CheckBox::builder(&dlg).with_label("&Remember on this device").build();
+    remember.set_value(false);
+    sizer.add(&remember, 0, SizerFlag::All, 8);
+
+    let error = StaticText::builder(&dlg).with_label("").build();
+    sizer.add(&error, 0, SizerFlag::Expand | SizerFlag::All, 8);
+
+    ok_cancel(&dlg, &sizer);
+    dlg.set_sizer(sizer, true);
+
+    loop {
+        password.set_focus();
+        if dlg.show_modal() != ID_OK {
+            return None;
+        }
+        match store.unlock(&password.get_value()) {
+            Ok(service) => return Some((service, remember.get_value())),
+            Err(e) => {
+                tracing::warn!("Master password unlock failed: {}", e);
+                password.set_value("");
+                error.set_label("Incorrect master password. Try again.");
+            }
+        }
+    }
+}
+
+/// Show the set/change master password dialog.
+///
+/// `is_set` adds a "Current password" field. Returns `None` on cancel.
+pub fn show_master_password_dialog(parent: &Frame, is_set: bool) -> Option<MasterPasswordChange> {
+    let title = if is_set { "Change Master Password" } else { "Set Master Password" };
+    let dlg = Dialog::builder(parent, title).with_size(440, 260).build();
+    let sizer = BoxSizer::builder(Orientation::Vertical).build();
+
+    let fields = FlexGridSizer::builder(0, 2).with_vgap(6).with_hgap(8).build();
+    fields.add_growable_col(1, 1);
+    let current = is_set.then(|| password_field(&dlg, &fields, "&Current password:"));
+    let new = password_field(&dlg, &fields, "&New password:");
+    let confirm = password_field(&dlg, &fields, "C&onfirm password:");
+    sizer.add_sizer(&fields, 0, SizerFlag::Expand | SizerFlag::All, 4);
+
+    let hint = StaticText::builder(&dlg)
+        .with_label("Saved account passwords and tokens will be re-encrypted.\nThere is no way to recover a forgotten master password.")
+        .build();
+    sizer.add(&hint, 0, SizerFlag::All, 8);
+
+    let error = StaticText::builder(&dlg).with_label("").build();
+    sizer.add(&error, 0, SizerFlag::Expand | SizerFlag::All, 8);
+
+    ok_cancel(&dlg, &sizer);
+    dlg.set_sizer(sizer, true);
+
+    loop {
+        if dlg.show_modal() != ID_OK {
+            return None;
+        }
+        let (new_value, confirm_value) = (new.get_value(), confirm.get_value());
+        if new_value.is_empty() {
+            error.set_label("The new password cannot be empty.");
+        } else if new_value != confirm_value {
+            error.set_label("The new passwords do not match.");
+            confirm.set_value("");
+        } else {
+            return Some(MasterPasswordChange {
+                current: current.map(|f| f.get_value()),
+                new: new_value,
+            });
+        }
+    }
+}
@@ -0,0 +1,88 @@
+//! Beta readiness window
+//!
+//! Shows the in-memory checks straight away and adds the server, sign-in,
+//! database and disk checks as the background probes report them.
+
+use crate::application::readiness::{CheckStatus, ReadinessCheck};
+use async_channel::Receiver;
+use std::cell::RefCell;
+use std::rc::Rc;
+use wxdragon::prelude::*;
+
+/// How often the window picks up finished probes
+const POLL_MS: i32 = 200;
+
+/// "5 passed, 1 warning, 0 failed; 3 checks still running"
+fn summary(checks: &[ReadinessCheck], pending: usize) -> String {
+    let count = |status| checks.iter().filter(|c| c.status == status).count();
+    let warnings = count(CheckStatus::Warn);
+    let mut text = format!(
+        "{} passed, {} {}, {} failed",
+        count(CheckStatus::Pass),
+        warnings,
+        if warnings == 1 { "warning" } else { "warnings" },
+        count(CheckStatus::Fail)
+    );
+    match pending {
+        0 => text.push_str("; all checks finished"),
+        1 => text.push_str("; 1 check still running"),
+        n => text.push_str(&format!("; {} checks still running", n)),
+    }
+    text
+}
+
+/// Show `checks` and add the `pending` results that arrive on `updates`
+/// while the window is open.
+pub fn show_readiness_dialog(
+    parent: &Frame,
+    checks: Vec<ReadinessCheck>,
+    pending: usize,
+    updates: Receiver<ReadinessCheck>,
+) {
+    let dlg = Dialog::builder(parent, "Beta Readiness Check").with_size(720, 520).build();
+    let sizer = BoxSizer::builder(Orientation::Vertical).build();
+
+    let status = StaticText::builder(&dlg).with_label(&summary(&checks, pending)).build();
+    sizer.add(&status, 0, SizerFlag::Expand | SizerFlag::All, 8);
+
+    let report = TextCtrl::builder(&dlg)
+        .with_style(TextCtrlStyle::MultiLine | TextCtrlStyle::ReadOnly)
+        .with_value(&checks.iter().map(|c| c.to_string()).collect::<Vec<_>>().join("\n"))
+        .build();
+    sizer.add(&report, 1, SizerFlag::Expand | SizerFlag::All, 8);
+
+    let close = Button::builder(&dlg).with_label("&Close").with_id(ID_OK).build();
+    sizer.add(&close, 0, SizerFlag::AlignRight | SizerFlag::All, 8);
+    dlg.set_sizer(sizer, true);
+
+    let state = Rc::new(RefCell::new((checks, pending)));
+    let timer = Timer::new(&dlg);
+    timer.on_tick({
+        let state = state.clone();
+        move |_| {
+            let mut state = state.borrow_mut();
+            let mut changed = false;
+            while let Ok(check) = updates.try_recv() {
+                state.0.push(check);
+                state.1 = state.1.saturating_sub(1);
+                changed = true;
+            }
+            // A probe that panicked never reports
+            if state.1 > 0 && updates.is_closed() && updates.is_empty() {
+                state.1 = 0;
+                changed = true;
+            }
+            if changed {
+                let lines: Vec<String> = state.0.iter().map(|c| c.to_string()).collect();
+                report.set_value(&lines.join("\n"));
+                status.set_label(&summary(&state.0, state.1));
+            }
+        }
+    });
+    timer.start(POLL_MS, false);
+
+    close.on_click({ let d = dlg; move |_| { d.end_modal(ID_OK); } });
+    report.set_focus();
+    dlg.show_modal();
+    timer.stop();
+}
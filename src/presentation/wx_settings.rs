@@ -4,8 +4,10 @@
 //! commonly used email client configuration options.  Settings are read from
 //! and persisted through `AppConfig` / `ConfigManager`.
 
-use crate::data::config::AppConfig;
-use crate::service::spellcheck::supported_languages;
+use crate::data::config::{AppConfig, ReceiptPolicy, Theme, VacationResponder, MAX_FONT_SIZE, MIN_FONT_SIZE};
+use chrono::{NaiveDate, NaiveTime};
+use crate::presentation::i18n::{self, tr};
+use crate::service::spellcheck::{supported_languages, Locale};
 use wxdragon::prelude::*;
 
 // ── Result type ──────────────────────────────────────────────────────────────
@@ -26,17 +28,64 @@ struct SettingsWidgets {
     // General
     theme: Choice,
     font_size: TextCtrl,
-    notifications: CheckBox,
+    notifications: NotificationWidgets,
     check_updates: CheckBox,
+    check_interval: TextCtrl,
+    confirm_delete: CheckBox,
+    quota_warning: TextCtrl,
     // Compose
     preview_before_send: CheckBox,
+    reply_all_warning: TextCtrl,
+    autosave_seconds: TextCtrl,
+    compose_in_window: CheckBox,
     // Reading
     sort_order: Choice,
+    thread_view: CheckBox,
+    mark_read: Choice,
+    remote_images: CheckBox,
+    plain_text: CheckBox,
+    receipt_policy: Choice,
+    junk: JunkWidgets,
     // Language
+    ui_locale: Choice,
     language: Choice,
     // Advanced
     log_level: Choice,
     download_folder: TextCtrl,
+    confirm_open: CheckBox,
+    keep_body_days: TextCtrl,
+    max_body_kb: TextCtrl,
+    cache_source: CheckBox,
+    encryption: EncryptionWidgets,
+    connect_timeout: TextCtrl,
+    read_timeout: TextCtrl,
+    reuse_smtp: CheckBox,
+    sends_per_minute: TextCtrl,
+    connections_per_minute: TextCtrl,
+    fetch_concurrency: TextCtrl,
+}
+
+/// New-mail notification controls on the General tab
+struct NotificationWidgets {
+    enabled: CheckBox,
+    sound: CheckBox,
+    lists: CheckBox,
+    quiet: CheckBox,
+    quiet_from: TextCtrl,
+    quiet_to: TextCtrl,
+}
+
+/// Cached message encryption controls on the Advanced tab
+struct EncryptionWidgets {
+    bodies: CheckBox,
+    subjects: CheckBox,
+}
+
+/// Junk mail controls on the Reading tab
+struct JunkWidgets {
+    report: CheckBox,
+    filter: CheckBox,
+    threshold: TextCtrl,
 }
 
 /// Helper: unwrap get_selection() returning 0 if None.
@@ -54,8 +103,10 @@ fn section(parent: &Panel, label: &str) -> StaticBoxSizer {
 // ── Public entry point ───────────────────────────────────────────────────────
 
 /// Show the Settings dialog and return the (possibly updated) configuration.
-pub fn show_settings_dialog(parent: &Frame, config: &AppConfig) -> SettingsResult {
-    let dlg = Dialog::builder(parent, "Settings")
+///
+/// `cache_bytes` is the current message cache size shown on the Advanced tab.
+pub fn show_settings_dialog(parent: &Frame, config: &AppConfig, cache_bytes: Option<u64>) -> SettingsResult {
+    let dlg = Dialog::builder(parent, &tr("settings.title"))
         .with_size(560, 520)
         .build();
 
@@ -66,37 +117,41 @@ pub fn show_settings_dialog(parent: &Frame, config: &AppConfig) -> SettingsResul
 
     // ── Tab 1: General
     let general_panel = Panel::builder(&notebook).build();
-    let (theme, font_size, notifications, check_updates) =
+    let (theme, font_size, notifications, check_updates, check_interval, confirm_delete, quota_warning) =
         build_general_tab(&general_panel, config);
-    notebook.add_page(&general_panel, "General", true, None);
+    notebook.add_page(&general_panel, &tr("settings.general"), true, None);
 
     // ── Tab 2: Compose
     let compose_panel = Panel::builder(&notebook).build();
-    let preview_before_send = build_compose_tab(&compose_panel, config);
-    notebook.add_page(&compose_panel, "Compose", false, None);
+    let (preview_before_send, reply_all_warning, autosave_seconds, compose_in_window) = build_compose_tab(&compose_panel, config);
+    notebook.add_page(&compose_panel, &tr("settings.compose"), false, None);
 
     // ── Tab 3: Reading
     let reading_panel = Panel::builder(&notebook).build();
-    let sort_order = build_reading_tab(&reading_panel, config);
-    notebook.add_page(&reading_panel, "Reading", false, None);
+    let (sort_order, thread_view, mark_read, remote_images, plain_text, receipt_policy, junk) =
+        build_reading_tab(&reading_panel, config);
+    notebook.add_page(&reading_panel, &tr("settings.reading"), false, None);
 
     // ── Tab 4: Language & Spelling
     let lang_panel = Panel::builder(&notebook).build();
-    let language = build_language_tab(&lang_panel, config);
-    notebook.add_page(&lang_panel, "Language", false, None);
+    let (ui_locale, language) = build_language_tab(&lang_panel, config);
+    notebook.add_page(&lang_panel, &tr("settings.language"), false, None);
 
     // ── Tab 5: Advanced
     let advanced_panel = Panel::builder(&notebook).build();
-    let (log_level, download_folder) = build_advanced_tab(&advanced_panel, config);
-    notebook.add_page(&advanced_panel, "Advanced", false, None);
+    let (
+        log_level, download_folder, confirm_open, keep_body_days, max_body_kb, cache_source, encryption, connect_timeout,
+        read_timeout, reuse_smtp, sends_per_minute, connections_per_minute, fetch_concurrency,
+    ) = build_advanced_tab(&advanced_panel, config, cache_bytes);
+    notebook.add_page(&advanced_panel, &tr("settings.advanced"), false, None);
 
     root_sizer.add(&notebook, 1, SizerFlag::Expand | SizerFlag::All, 8);
 
     // ── OK / Cancel buttons
     let btn_sizer = BoxSizer::builder(Orientation::Horizontal).build();
     btn_sizer.add_spacer(0);
-    let ok_btn = Button::builder(&dlg).with_label("OK").with_id(ID_OK).build();
-    let cancel_btn = Button::builder(&dlg).with_label("Cancel").with_id(ID_CANCEL).build();
+    let ok_btn = Button::builder(&dlg).with_label(&tr("action.ok")).with_id(ID_OK).build();
+    let cancel_btn = Button::builder(&dlg).with_label(&tr("action.cancel")).with_id(ID_CANCEL).build();
     btn_sizer.add(&ok_btn, 0, SizerFlag::All, 4);
     btn_sizer.add(&cancel_btn, 0, SizerFlag::All, 4);
     root_sizer.add_sizer(&btn_sizer, 0, SizerFlag::AlignRight | SizerFlag::All, 8);
@@ -107,9 +162,11 @@ pub fn show_settings_dialog(parent: &Frame, config: &AppConfig) -> SettingsResul
     cancel_btn.on_click({ let d = dlg; move |_ev| { d.end_modal(ID_CANCEL); } });
 
     let widgets = SettingsWidgets {
-        theme, font_size, notifications, check_updates,
-        preview_before_send, sort_order, language,
-        log_level, download_folder,
+        theme, font_size, notifications, check_updates, check_interval, confirm_delete, quota_warning,
+        preview_before_send, reply_all_warning, autosave_seconds, compose_in_window, sort_order, thread_view, mark_read, remote_images, plain_text,
+        receipt_policy, junk, ui_locale, language,
+        log_level, download_folder, confirm_open, keep_body_days, max_body_kb, cache_source, encryption, connect_timeout,
+        read_timeout, reuse_smtp, sends_per_minute, connections_per_minute, fetch_concurrency,
     };
 
     if dlg.show_modal() == ID_OK {
@@ -119,13 +176,99 @@ pub fn show_settings_dialog(parent: &Frame, config: &AppConfig) -> SettingsResul
     }
 }
 
+/// Show the Vacation Responder dialog and return the new settings, or
+/// `None` if cancelled. The dialog stays open until the settings are valid.
+pub fn show_vacation_dialog(parent: &Frame, current: &VacationResponder) -> Option<VacationResponder> {
+    let dlg = Dialog::builder(parent, "Vacation Responder").with_size(520, 480).build();
+    let sizer = BoxSizer::builder(Orientation::Vertical).build();
+
+    let enabled_cb = CheckBox::builder(&dlg).with_label("&Send automatic replies while I'm away").build();
+    enabled_cb.set_value(current.enabled);
+    sizer.add(&enabled_cb, 0, SizerFlag::All, 8);
+
+    let fields = FlexGridSizer::builder(0, 2).with_vgap(4).with_hgap(8).build();
+    fields.add_growable_col(1, 1);
+    let field = |label: &str, value: &str| {
+        let lbl = StaticText::builder(&dlg).with_label(label).build();
+        let text = TextCtrl::builder(&dlg).with_value(value).build();
+        fields.add(&lbl, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+        fields.add(&text, 1, SizerFlag::Expand | SizerFlag::All, 4);
+        text
+    };
+    let date = |d: Option<NaiveDate>| d.map(|d| d.format("%Y-%m-%d").to_string()).unwrap_or_default();
+    let start_f = field("&First day (YYYY-MM-DD, optional):", &date(current.start));
+    let end_f = field("&Last day (YYYY-MM-DD, optional):", &date(current.end));
+    let subject_f = field("S&ubject (optional):", &current.subject);
+    let resend_f = field("&Reply to the same person again after (days):", &current.resend_after_days.to_string());
+    sizer.add_sizer(&fields, 0, SizerFlag::Expand | SizerFlag::All, 4);
+
+    let message_label = StaticText::builder(&dlg).with_label("&Message:").build();
+    sizer.add(&message_label, 0, SizerFlag::Left | SizerFlag::Top, 8);
+    let message_f = TextCtrl::builder(&dlg)
+        .with_style(TextCtrlStyle::MultiLine | TextCtrlStyle::WordWrap)
+        .with_value(&current.message)
+        .build();
+    sizer.add(&message_f, 1, SizerFlag::Expand | SizerFlag::All, 8);
+
+    let note = StaticText::builder(&dlg)
+        .with_label("Mailing lists, automatic mail, no-reply addresses and your own addresses are never answered.")
+        .build();
+    sizer.add(&note, 0, SizerFlag::Expand | SizerFlag::Left | SizerFlag::Right, 8);
+    let error = StaticText::builder(&dlg).with_label(" ").build();
+    sizer.add(&error, 0, SizerFlag::Expand | SizerFlag::All, 8);
+
+    let btn_sizer = BoxSizer::builder(Orientation::Horizontal).build();
+    let ok_btn = Button::builder(&dlg).with_label(&tr("action.ok")).with_id(ID_OK).build();
+    let cancel_btn = Button::builder(&dlg).with_label(&tr("action.cancel")).with_id(ID_CANCEL).build();
+    btn_sizer.add(&ok_btn, 0, SizerFlag::All, 4);
+    btn_sizer.add(&cancel_btn, 0, SizerFlag::All, 4);
+    sizer.add_sizer(&btn_sizer, 0, SizerFlag::AlignRight | SizerFlag::All, 8);
+    dlg.set_sizer(sizer, true);
+
+    ok_btn.on_click({ let d = dlg; move |_ev| { d.end_modal(ID_OK); } });
+    cancel_btn.on_click({ let d = dlg; move |_ev| { d.end_modal(ID_CANCEL); } });
+
+    let parse_date = |field: &TextCtrl| -> Result<Option<NaiveDate>, String> {
+        let text = field.get_value();
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(None);
+        }
+        NaiveDate::parse_from_str(text, "%Y-%m-%d")
+            .map(Some)
+            .map_err(|_| format!("\"{}\" is not a date like 2025-07-04", text))
+    };
+    while dlg.show_modal() == ID_OK {
+        let read = || -> Result<VacationResponder, String> {
+            let vacation = VacationResponder {
+                enabled: enabled_cb.get_value(),
+                start: parse_date(&start_f)?,
+                end: parse_date(&end_f)?,
+                subject: subject_f.get_value().trim().to_string(),
+                message: message_f.get_value(),
+                resend_after_days: resend_f.get_value().trim().parse().unwrap_or(0),
+            };
+            vacation.validate().map_err(|e| match e {
+                crate::common::Error::Config(problem) => problem,
+                e => e.to_string(),
+            })?;
+            Ok(vacation)
+        };
+        match read() {
+            Ok(vacation) => return Some(vacation),
+            Err(problem) => error.set_label(&problem),
+        }
+    }
+    None
+}
+
 // ── Tab builders ─────────────────────────────────────────────────────────────
 
-/// General settings: theme, font size, notifications, check-for-updates.
+/// General settings: theme, font size, mail checking, notifications, updates.
 fn build_general_tab(
     panel: &Panel,
     config: &AppConfig,
-) -> (Choice, TextCtrl, CheckBox, CheckBox) {
+) -> (Choice, TextCtrl, NotificationWidgets, CheckBox, TextCtrl, CheckBox, TextCtrl) {
     let sizer = BoxSizer::builder(Orientation::Vertical).build();
 
     // -- Appearance
@@ -133,11 +276,10 @@ fn build_general_tab(
 
     let theme_row = BoxSizer::builder(Orientation::Horizontal).build();
     let theme_label = StaticText::builder(panel).with_label("Theme:").build();
-    let theme_choices: Vec<String> = ["Default", "Light", "Dark", "High Contrast"]
-        .iter().map(|s| s.to_string()).collect();
-    let theme_idx: u32 = match config.theme.as_str() {
-        "light" => 1, "dark" => 2, "high_contrast" => 3, _ => 0,
-    };
+    let theme_choices: Vec<String> = Theme::ALL.iter().map(|t| t.label().to_string()).collect();
+    let theme_idx = Theme::ALL.iter()
+        .position(|t| *t == config.theme_preference())
+        .unwrap_or(0) as u32;
     let theme_choice = Choice::builder(panel)
         .with_choices(theme_choices)
         .with_selection(Some(theme_idx))
@@ -156,12 +298,64 @@ fn build_general_tab(
 
     sizer.add_sizer(&app_sec, 0, SizerFlag::Expand | SizerFlag::All, 8);
 
+    // -- Mail
+    let mail_sec = section(panel, "Mail");
+
+    let interval_row = BoxSizer::builder(Orientation::Horizontal).build();
+    let interval_label = StaticText::builder(panel)
+        .with_label("Check for new mail every (minutes, 0 = never):")
+        .build();
+    let interval_field = TextCtrl::builder(panel).build();
+    interval_field.set_value(&config.check_interval_minutes.to_string());
+    interval_row.add(&interval_label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+    interval_row.add(&interval_field, 0, SizerFlag::All, 4);
+    mail_sec.add_sizer(&interval_row, 0, SizerFlag::Expand, 0);
+
+    let confirm_cb = CheckBox::builder(panel).with_label("&Confirm before deleting messages").build();
+    confirm_cb.set_value(config.confirm_before_delete);
+    mail_sec.add(&confirm_cb, 0, SizerFlag::All, 4);
+
+    let quota_row = BoxSizer::builder(Orientation::Horizontal).build();
+    let quota_label = StaticText::builder(panel)
+        .with_label("Warn when a mailbox is this full (percent, 0 = hide usage):")
+        .build();
+    let quota_field = TextCtrl::builder(panel).build();
+    quota_field.set_value(&config.quota_warning_percent.to_string());
+    quota_row.add(&quota_label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+    quota_row.add(&quota_field, 0, SizerFlag::All, 4);
+    mail_sec.add_sizer(&quota_row, 0, SizerFlag::Expand, 0);
+
+    sizer.add_sizer(&mail_sec, 0, SizerFlag::Expand | SizerFlag::All, 8);
+
     // -- Notifications
     let notif_sec = section(panel, "Notifications");
     let notif_cb = CheckBox::builder(panel).with_label("Enable &new-mail notifications").build();
     notif_cb.set_value(config.enable_notifications);
     notif_sec.add(&notif_cb, 0, SizerFlag::All, 4);
+    let sound_cb = CheckBox::builder(panel).with_label("Play a &sound with notifications").build();
+    sound_cb.set_value(config.notification_sound);
+    notif_sec.add(&sound_cb, 0, SizerFlag::All, 4);
+    let lists_cb = CheckBox::builder(panel).with_label("Notify for mai&ling list mail too").build();
+    lists_cb.set_value(config.notify_mailing_lists);
+    notif_sec.add(&lists_cb, 0, SizerFlag::All, 4);
+    let quiet_cb = CheckBox::builder(panel).with_label("&Do not disturb between these times (HH:MM):").build();
+    quiet_cb.set_value(config.quiet_hours.enabled);
+    notif_sec.add(&quiet_cb, 0, SizerFlag::All, 4);
+    let quiet_row = BoxSizer::builder(Orientation::Horizontal).build();
+    let quiet_from_label = StaticText::builder(panel).with_label("Fro&m:").build();
+    let quiet_from = TextCtrl::builder(panel).build();
+    quiet_from.set_value(&config.quiet_hours.start.format("%H:%M").to_string());
+    let quiet_to_label = StaticText::builder(panel).with_label("&To:").build();
+    let quiet_to = TextCtrl::builder(panel).build();
+    quiet_to.set_value(&config.quiet_hours.end.format("%H:%M").to_string());
+    quiet_row.add(&quiet_from_label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+    quiet_row.add(&quiet_from, 0, SizerFlag::All, 4);
+    quiet_row.add(&quiet_to_label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+    quiet_row.add(&quiet_to, 0, SizerFlag::All, 4);
+    notif_sec.add_sizer(&quiet_row, 0, SizerFlag::Expand, 0);
     sizer.add_sizer(&notif_sec, 0, SizerFlag::Expand | SizerFlag::All, 8);
+    let notifications =
+        NotificationWidgets { enabled: notif_cb, sound: sound_cb, lists: lists_cb, quiet: quiet_cb, quiet_from, quiet_to };
 
     // -- Updates
     let upd_sec = section(panel, "Updates");
@@ -171,11 +365,12 @@ fn build_general_tab(
     sizer.add_sizer(&upd_sec, 0, SizerFlag::Expand | SizerFlag::All, 8);
 
     panel.set_sizer(sizer, true);
-    (theme_choice, font_field, notif_cb, update_cb)
+    (theme_choice, font_field, notifications, update_cb, interval_field, confirm_cb, quota_field)
 }
 
-/// Compose settings: preview-before-send, default format, signatures.
-fn build_compose_tab(panel: &Panel, config: &AppConfig) -> CheckBox {
+/// Compose settings: preview-before-send, Reply All warning, default
+/// format, autosave, signatures.
+fn build_compose_tab(panel: &Panel, config: &AppConfig) -> (CheckBox, TextCtrl, TextCtrl, CheckBox) {
     let sizer = BoxSizer::builder(Orientation::Vertical).build();
 
     // -- Sending
@@ -184,6 +379,16 @@ fn build_compose_tab(panel: &Panel, config: &AppConfig) -> CheckBox {
     preview_cb.set_value(config.preview_before_send);
     send_sec.add(&preview_cb, 0, SizerFlag::All, 4);
 
+    let reply_all_row = BoxSizer::builder(Orientation::Horizontal).build();
+    let reply_all_label = StaticText::builder(panel)
+        .with_label("Ask before Reply &All goes to more than (recipients, 0 = never):")
+        .build();
+    let reply_all_field = TextCtrl::builder(panel).build();
+    reply_all_field.set_value(&config.reply_all_warning_recipients.to_string());
+    reply_all_row.add(&reply_all_label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+    reply_all_row.add(&reply_all_field, 0, SizerFlag::All, 4);
+    send_sec.add_sizer(&reply_all_row, 0, SizerFlag::Expand, 0);
+
     let format_row = BoxSizer::builder(Orientation::Horizontal).build();
     let format_label = StaticText::builder(panel).with_label("Default format:").build();
     let format_choices: Vec<String> = ["HTML", "Plain Text"]
@@ -196,13 +401,23 @@ fn build_compose_tab(panel: &Panel, config: &AppConfig) -> CheckBox {
     format_row.add(&format_choice, 1, SizerFlag::Expand | SizerFlag::All, 4);
     send_sec.add_sizer(&format_row, 0, SizerFlag::Expand, 0);
 
+    let window_cb = CheckBox::builder(panel).with_label("Open the composer in its own &window").build();
+    window_cb.set_value(config.compose_in_window);
+    send_sec.add(&window_cb, 0, SizerFlag::All, 4);
+
     sizer.add_sizer(&send_sec, 0, SizerFlag::Expand | SizerFlag::All, 8);
 
     // -- Drafts
     let draft_sec = section(panel, "Drafts");
-    let autosave_cb = CheckBox::builder(panel).with_label("&Auto-save drafts every 60 seconds").build();
-    autosave_cb.set_value(true);
-    draft_sec.add(&autosave_cb, 0, SizerFlag::All, 4);
+    let autosave_row = BoxSizer::builder(Orientation::Horizontal).build();
+    let autosave_label = StaticText::builder(panel)
+        .with_label("&Auto-save the message being written every (seconds, 0 = never):")
+        .build();
+    let autosave_field = TextCtrl::builder(panel).build();
+    autosave_field.set_value(&config.autosave_seconds.to_string());
+    autosave_row.add(&autosave_label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+    autosave_row.add(&autosave_field, 0, SizerFlag::All, 4);
+    draft_sec.add_sizer(&autosave_row, 0, SizerFlag::Expand, 0);
     sizer.add_sizer(&draft_sec, 0, SizerFlag::Expand | SizerFlag::All, 8);
 
     // -- Signatures
@@ -213,11 +428,11 @@ fn build_compose_tab(panel: &Panel, config: &AppConfig) -> CheckBox {
     sizer.add_sizer(&sig_sec, 0, SizerFlag::Expand | SizerFlag::All, 8);
 
     panel.set_sizer(sizer, true);
-    preview_cb
+    (preview_cb, reply_all_field, autosave_field, window_cb)
 }
 
 /// Reading settings: sort order, mark-as-read, threading.
-fn build_reading_tab(panel: &Panel, config: &AppConfig) -> Choice {
+fn build_reading_tab(panel: &Panel, config: &AppConfig) -> (Choice, CheckBox, Choice, CheckBox, CheckBox, Choice, JunkWidgets) {
     let sizer = BoxSizer::builder(Orientation::Vertical).build();
 
     // -- Message List
@@ -245,7 +460,7 @@ fn build_reading_tab(panel: &Panel, config: &AppConfig) -> Choice {
     list_sec.add_sizer(&sort_row, 0, SizerFlag::Expand, 0);
 
     let thread_cb = CheckBox::builder(panel).with_label("Enable &threaded view by default").build();
-    thread_cb.set_value(false);
+    thread_cb.set_value(config.thread_view_default);
     list_sec.add(&thread_cb, 0, SizerFlag::All, 4);
     sizer.add_sizer(&list_sec, 0, SizerFlag::Expand | SizerFlag::All, 8);
 
@@ -256,33 +471,92 @@ fn build_reading_tab(panel: &Panel, config: &AppConfig) -> Choice {
     let markread_label = StaticText::builder(panel).with_label("Mark as read after:").build();
     let markread_choices: Vec<String> = ["Immediately", "After 2 seconds", "After 5 seconds", "Manually"]
         .iter().map(|s| s.to_string()).collect();
+    let markread_idx: u32 = match config.mark_read_delay_seconds {
+        Some(0) => 0, Some(1..=3) => 1, Some(_) => 2, None => 3,
+    };
     let markread_choice = Choice::builder(panel)
         .with_choices(markread_choices)
-        .with_selection(Some(0))
+        .with_selection(Some(markread_idx))
         .build();
     markread_row.add(&markread_label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
     markread_row.add(&markread_choice, 1, SizerFlag::Expand | SizerFlag::All, 4);
     read_sec.add_sizer(&markread_row, 0, SizerFlag::Expand, 0);
 
     let external_cb = CheckBox::builder(panel).with_label("Load remote &images in messages").build();
-    external_cb.set_value(false);
+    external_cb.set_value(config.load_remote_images);
     read_sec.add(&external_cb, 0, SizerFlag::All, 4);
 
+    let plain_cb = CheckBox::builder(panel).with_label("Show messages as &plain text").build();
+    plain_cb.set_value(config.plain_text_default);
+    read_sec.add(&plain_cb, 0, SizerFlag::All, 4);
+
+    let receipt_row = BoxSizer::builder(Orientation::Horizontal).build();
+    let receipt_label = StaticText::builder(panel).with_label("Read receipt requests:").build();
+    let receipt_choices: Vec<String> = ReceiptPolicy::ALL.iter().map(|p| p.label().to_string()).collect();
+    let receipt_idx = ReceiptPolicy::ALL.iter()
+        .position(|p| *p == config.receipt_policy())
+        .unwrap_or(0) as u32;
+    let receipt_choice = Choice::builder(panel)
+        .with_choices(receipt_choices)
+        .with_selection(Some(receipt_idx))
+        .build();
+    receipt_row.add(&receipt_label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+    receipt_row.add(&receipt_choice, 1, SizerFlag::Expand | SizerFlag::All, 4);
+    read_sec.add_sizer(&receipt_row, 0, SizerFlag::Expand, 0);
+
     sizer.add_sizer(&read_sec, 0, SizerFlag::Expand | SizerFlag::All, 8);
 
+    // -- Junk Mail
+    let junk_sec = section(panel, "Junk Mail");
+    let report_cb = CheckBox::builder(panel).with_label("Tell the mail &server when I mark messages as spam or not spam").build();
+    report_cb.set_value(config.report_junk);
+    junk_sec.add(&report_cb, 0, SizerFlag::All, 4);
+
+    let filter_cb = CheckBox::builder(panel).with_label("Move likely spam to &Junk, learning from what I mark").build();
+    filter_cb.set_value(config.spam_filter);
+    junk_sec.add(&filter_cb, 0, SizerFlag::All, 4);
+
+    let threshold_row = BoxSizer::builder(Orientation::Horizontal).build();
+    let threshold_label = StaticText::builder(panel).with_label("Spam score that counts as likely spam (percent):").build();
+    let threshold_field = TextCtrl::builder(panel).build();
+    threshold_field.set_value(&config.spam_threshold_percent.to_string());
+    threshold_row.add(&threshold_label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+    threshold_row.add(&threshold_field, 0, SizerFlag::All, 4);
+    junk_sec.add_sizer(&threshold_row, 0, SizerFlag::Expand, 0);
+    sizer.add_sizer(&junk_sec, 0, SizerFlag::Expand | SizerFlag::All, 8);
+
     panel.set_sizer(sizer, true);
-    sort_choice
+    let junk = JunkWidgets { report: report_cb, filter: filter_cb, threshold: threshold_field };
+    (sort_choice, thread_cb, markread_choice, external_cb, plain_cb, receipt_choice, junk)
 }
 
-/// Language & Spelling: language, spell-check toggle.
-fn build_language_tab(panel: &Panel, config: &AppConfig) -> Choice {
+/// Language & Spelling: interface language, spell-check language and toggle.
+fn build_language_tab(panel: &Panel, config: &AppConfig) -> (Choice, Choice) {
     let sizer = BoxSizer::builder(Orientation::Vertical).build();
 
     // -- Language
     let lang_sec = section(panel, "Language");
 
+    let ui_row = BoxSizer::builder(Orientation::Horizontal).build();
+    let ui_label = StaticText::builder(panel).with_label(&tr("settings.interface_language")).build();
+    let locales = interface_locales(config);
+    let mut ui_names = vec![tr("settings.system_default")];
+    ui_names.extend(locales.iter().map(|code| Locale::from_code(code).display_name));
+    let ui_idx = config.ui_locale.as_ref()
+        .and_then(|code| locales.iter().position(|c| c == code))
+        .map_or(0, |i| i + 1) as u32;
+    let ui_choice = Choice::builder(panel)
+        .with_choices(ui_names)
+        .with_selection(Some(ui_idx))
+        .build();
+    ui_row.add(&ui_label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+    ui_row.add(&ui_choice, 1, SizerFlag::Expand | SizerFlag::All, 4);
+    lang_sec.add_sizer(&ui_row, 0, SizerFlag::Expand, 0);
+    let restart_note = StaticText::builder(panel).with_label(&tr("settings.restart_note")).build();
+    lang_sec.add(&restart_note, 0, SizerFlag::All, 4);
+
     let lang_row = BoxSizer::builder(Orientation::Horizontal).build();
-    let lang_label = StaticText::builder(panel).with_label("Interface language:").build();
+    let lang_label = StaticText::builder(panel).with_label(&tr("settings.spelling_language")).build();
 
     let languages = supported_languages();
     let lang_names: Vec<String> = languages.iter()
@@ -320,11 +594,31 @@ fn build_language_tab(panel: &Panel, config: &AppConfig) -> Choice {
     sizer.add_sizer(&spell_sec, 0, SizerFlag::Expand | SizerFlag::All, 8);
 
     panel.set_sizer(sizer, true);
-    lang_choice
+    (ui_choice, lang_choice)
 }
 
-/// Advanced: log level, download folder, cache info.
-fn build_advanced_tab(panel: &Panel, config: &AppConfig) -> (Choice, TextCtrl) {
+/// Interface languages offered besides the system default: every loaded
+/// table, plus the saved choice if its file has since been removed.
+fn interface_locales(config: &AppConfig) -> Vec<String> {
+    let mut locales = i18n::available_locales();
+    if let Some(code) = &config.ui_locale {
+        if !locales.contains(code) {
+            locales.push(code.clone());
+        }
+    }
+    locales
+}
+
+/// Advanced: log level, download folder and attachment opening, cache size,
+/// pruning limits and encryption, server timeouts and connection reuse.
+fn build_advanced_tab(
+    panel: &Panel,
+    config: &AppConfig,
+    cache_bytes: Option<u64>,
+) -> (
+    Choice, TextCtrl, CheckBox, TextCtrl, TextCtrl, CheckBox, EncryptionWidgets, TextCtrl, TextCtrl, CheckBox, TextCtrl,
+    TextCtrl, TextCtrl,
+) {
     let sizer = BoxSizer::builder(Orientation::Vertical).build();
 
     // -- Logging
@@ -374,15 +668,134 @@ fn build_advanced_tab(panel: &Panel, config: &AppConfig) -> (Choice, TextCtrl) {
     dl_row.add(&dl_browse, 0, SizerFlag::All, 4);
     store_sec.add_sizer(&dl_row, 0, SizerFlag::Expand, 0);
 
+    let open_cb = CheckBox::builder(panel).with_label("As&k before opening an attachment").build();
+    open_cb.set_value(config.confirm_open_attachments);
+    store_sec.add(&open_cb, 0, SizerFlag::All, 4);
+    let open_hint = StaticText::builder(panel)
+        .with_label("Attachments are saved to the download folder before they open. Programs and scripts never open.")
+        .build();
+    store_sec.add(&open_hint, 0, SizerFlag::All, 4);
+
     let cache_hint = StaticText::builder(panel)
         .with_label("Message cache is stored in the system cache directory.\nClearing cache will require re-downloading messages.")
         .build();
     store_sec.add(&cache_hint, 0, SizerFlag::All, 4);
 
+    let usage = match cache_bytes {
+        Some(bytes) => format!("Cache size: {:.1} MB", bytes as f64 / (1024.0 * 1024.0)),
+        None => "Cache size: unavailable".to_string(),
+    };
+    let usage_label = StaticText::builder(panel).with_label(&usage).build();
+    store_sec.add(&usage_label, 0, SizerFlag::All, 4);
+
+    let days_row = BoxSizer::builder(Orientation::Horizontal).build();
+    let days_label = StaticText::builder(panel)
+        .with_label("Keep downloaded message bodies for (days, 0 = forever):")
+        .build();
+    let days_field = TextCtrl::builder(panel).build();
+    days_field.set_value(&config.cache_keep_body_days.to_string());
+    days_row.add(&days_label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+    days_row.add(&days_field, 0, SizerFlag::All, 4);
+    store_sec.add_sizer(&days_row, 0, SizerFlag::Expand, 0);
+
+    let kb_row = BoxSizer::builder(Orientation::Horizontal).build();
+    let kb_label = StaticText::builder(panel)
+        .with_label("Don't keep bodies larger than (KB, 0 = no limit):")
+        .build();
+    let kb_field = TextCtrl::builder(panel).build();
+    kb_field.set_value(&config.cache_max_body_kb.to_string());
+    kb_row.add(&kb_label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+    kb_row.add(&kb_field, 0, SizerFlag::All, 4);
+    store_sec.add_sizer(&kb_row, 0, SizerFlag::Expand, 0);
+
+    let source_cb = CheckBox::builder(panel).with_label("Keep message &source after viewing it").build();
+    source_cb.set_value(config.cache_message_source);
+    store_sec.add(&source_cb, 0, SizerFlag::All, 4);
+
+    let bodies_cb = CheckBox::builder(panel).with_label("&Encrypt cached message bodies").build();
+    bodies_cb.set_value(config.encrypt_message_bodies);
+    store_sec.add(&bodies_cb, 0, SizerFlag::All, 4);
+    let subjects_cb = CheckBox::builder(panel).with_label("Encrypt cached s&ubjects too").build();
+    subjects_cb.set_value(config.encrypt_message_subjects);
+    store_sec.add(&subjects_cb, 0, SizerFlag::Left | SizerFlag::Right, 20);
+    let encryption_hint = StaticText::builder(panel)
+        .with_label("Uses the master password, so messages can't be read until it is entered.
+Search still works; opening a conversation is slower with encrypted subjects.")
+        .build();
+    store_sec.add(&encryption_hint, 0, SizerFlag::All, 4);
+
     sizer.add_sizer(&store_sec, 0, SizerFlag::Expand | SizerFlag::All, 8);
 
+    // -- Network
+    let net_sec = section(panel, "Network");
+
+    let connect_row = BoxSizer::builder(Orientation::Horizontal).build();
+    let connect_label = StaticText::builder(panel)
+        .with_label("Give up connecting to a server after (seconds):")
+        .build();
+    let connect_field = TextCtrl::builder(panel).build();
+    connect_field.set_value(&config.connect_timeout_seconds.to_string());
+    connect_row.add(&connect_label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+    connect_row.add(&connect_field, 0, SizerFlag::All, 4);
+    net_sec.add_sizer(&connect_row, 0, SizerFlag::Expand, 0);
+
+    let read_row = BoxSizer::builder(Orientation::Horizontal).build();
+    let read_label = StaticText::builder(panel)
+        .with_label("Wait for a server to answer for (seconds):")
+        .build();
+    let read_field = TextCtrl::builder(panel).build();
+    read_field.set_value(&config.read_timeout_seconds.to_string());
+    read_row.add(&read_label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+    read_row.add(&read_field, 0, SizerFlag::All, 4);
+    net_sec.add_sizer(&read_row, 0, SizerFlag::Expand, 0);
+
+    let reuse_cb = CheckBox::builder(panel).with_label("Send queued mail over one server &connection").build();
+    reuse_cb.set_value(config.reuse_smtp_connection);
+    net_sec.add(&reuse_cb, 0, SizerFlag::All, 4);
+
+    let sends_row = BoxSizer::builder(Orientation::Horizontal).build();
+    let sends_label = StaticText::builder(panel)
+        .with_label("Send at most (messages a minute, 0 = no limit):")
+        .build();
+    let sends_field = TextCtrl::builder(panel).build();
+    sends_field.set_value(&config.sends_per_minute.to_string());
+    sends_row.add(&sends_label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+    sends_row.add(&sends_field, 0, SizerFlag::All, 4);
+    net_sec.add_sizer(&sends_row, 0, SizerFlag::Expand, 0);
+
+    let conns_row = BoxSizer::builder(Orientation::Horizontal).build();
+    let conns_label = StaticText::builder(panel)
+        .with_label("Connect at most (times a minute per server, 0 = no limit):")
+        .build();
+    let conns_field = TextCtrl::builder(panel).build();
+    conns_field.set_value(&config.connections_per_minute.to_string());
+    conns_row.add(&conns_label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+    conns_row.add(&conns_field, 0, SizerFlag::All, 4);
+    net_sec.add_sizer(&conns_row, 0, SizerFlag::Expand, 0);
+
+    let fetch_row = BoxSizer::builder(Orientation::Horizontal).build();
+    let fetch_label = StaticText::builder(panel)
+        .with_label("Download at most (messages at once):")
+        .build();
+    let fetch_field = TextCtrl::builder(panel).build();
+    fetch_field.set_value(&config.fetch_concurrency.to_string());
+    fetch_row.add(&fetch_label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+    fetch_row.add(&fetch_field, 0, SizerFlag::All, 4);
+    net_sec.add_sizer(&fetch_row, 0, SizerFlag::Expand, 0);
+
+    let limits_hint = StaticText::builder(panel)
+        .with_label("The defaults stay below what Gmail and Outlook.com allow.\nRaising them can get an account locked out for a while.")
+        .build();
+    net_sec.add(&limits_hint, 0, SizerFlag::All, 4);
+
+    sizer.add_sizer(&net_sec, 0, SizerFlag::Expand | SizerFlag::All, 8);
+
     panel.set_sizer(sizer, true);
-    (log_choice, dl_field)
+    (
+        log_choice, dl_field, open_cb, days_field, kb_field, source_cb,
+        EncryptionWidgets { bodies: bodies_cb, subjects: subjects_cb }, connect_field, read_field, reuse_cb,
+        sends_field, conns_field, fetch_field,
+    )
 }
 
 // ── Read settings back from widget references ────────────────────────────────
@@ -392,18 +805,49 @@ fn read_settings(w: &SettingsWidgets, base: &AppConfig) -> AppConfig {
     let mut cfg = base.clone();
 
     // General
-    cfg.theme = match sel(&w.theme) {
-        1 => "light", 2 => "dark", 3 => "high_contrast", _ => "default",
-    }.to_string();
+    cfg.theme = Theme::ALL.get(sel(&w.theme) as usize)
+        .copied()
+        .unwrap_or(Theme::System)
+        .as_key()
+        .to_string();
     cfg.font_size = w.font_size.get_value()
         .parse::<u32>()
         .unwrap_or(base.font_size)
-        .max(8).min(72);
-    cfg.enable_notifications = w.notifications.get_value();
+        .clamp(MIN_FONT_SIZE, MAX_FONT_SIZE);
+    cfg.enable_notifications = w.notifications.enabled.get_value();
+    cfg.notification_sound = w.notifications.sound.get_value();
+    cfg.notify_mailing_lists = w.notifications.lists.get_value();
+    let time = |field: &TextCtrl, fallback: NaiveTime| {
+        NaiveTime::parse_from_str(field.get_value().trim(), "%H:%M").unwrap_or(fallback)
+    };
+    cfg.quiet_hours.enabled = w.notifications.quiet.get_value();
+    cfg.quiet_hours.start = time(&w.notifications.quiet_from, base.quiet_hours.start);
+    cfg.quiet_hours.end = time(&w.notifications.quiet_to, base.quiet_hours.end);
     cfg.check_updates = w.check_updates.get_value();
+    cfg.check_interval_minutes = w.check_interval.get_value()
+        .trim()
+        .parse::<u32>()
+        .unwrap_or(base.check_interval_minutes)
+        .min(1440);
+    cfg.confirm_before_delete = w.confirm_delete.get_value();
+    cfg.quota_warning_percent = w.quota_warning.get_value()
+        .trim()
+        .parse::<u32>()
+        .unwrap_or(base.quota_warning_percent)
+        .min(100);
 
     // Compose
     cfg.preview_before_send = w.preview_before_send.get_value();
+    cfg.reply_all_warning_recipients = w.reply_all_warning.get_value()
+        .trim()
+        .parse::<u32>()
+        .unwrap_or(base.reply_all_warning_recipients);
+    cfg.autosave_seconds = w.autosave_seconds.get_value()
+        .trim()
+        .parse::<u32>()
+        .unwrap_or(base.autosave_seconds)
+        .min(3600);
+    cfg.compose_in_window = w.compose_in_window.get_value();
 
     // Reading
     cfg.default_sort_order = match sel(&w.sort_order) {
@@ -411,8 +855,30 @@ fn read_settings(w: &SettingsWidgets, base: &AppConfig) -> AppConfig {
         4 => "subject_az", 5 => "subject_za", 6 => "unread_first",
         _ => "date_newest",
     }.to_string();
+    cfg.thread_view_default = w.thread_view.get_value();
+    cfg.mark_read_delay_seconds = match sel(&w.mark_read) {
+        1 => Some(2), 2 => Some(5), 3 => None, _ => Some(0),
+    };
+    cfg.load_remote_images = w.remote_images.get_value();
+    cfg.plain_text_default = w.plain_text.get_value();
+    cfg.read_receipt_policy = ReceiptPolicy::ALL.get(sel(&w.receipt_policy) as usize)
+        .copied()
+        .unwrap_or(ReceiptPolicy::Ask)
+        .as_key()
+        .to_string();
+    cfg.report_junk = w.junk.report.get_value();
+    cfg.spam_filter = w.junk.filter.get_value();
+    cfg.spam_threshold_percent = w.junk.threshold.get_value()
+        .trim()
+        .parse::<u32>()
+        .unwrap_or(base.spam_threshold_percent)
+        .clamp(50, 100);
 
     // Language
+    cfg.ui_locale = match sel(&w.ui_locale) as usize {
+        0 => None,
+        idx => interface_locales(base).get(idx - 1).cloned(),
+    };
     let languages = supported_languages();
     let idx = sel(&w.language) as usize;
     if idx < languages.len() {
@@ -427,6 +893,48 @@ fn read_settings(w: &SettingsWidgets, base: &AppConfig) -> AppConfig {
     if !path.is_empty() {
         cfg.download_folder = std::path::PathBuf::from(path);
     }
+    cfg.confirm_open_attachments = w.confirm_open.get_value();
+    cfg.cache_keep_body_days = w.keep_body_days.get_value()
+        .trim()
+        .parse::<u32>()
+        .unwrap_or(base.cache_keep_body_days);
+    cfg.cache_max_body_kb = w.max_body_kb.get_value()
+        .trim()
+        .parse::<u32>()
+        .unwrap_or(base.cache_max_body_kb);
+    cfg.cache_message_source = w.cache_source.get_value();
+    cfg.encrypt_message_bodies = w.encryption.bodies.get_value();
+    cfg.encrypt_message_subjects = w.encryption.subjects.get_value();
+    cfg.connect_timeout_seconds = w.connect_timeout.get_value()
+        .trim()
+        .parse::<u32>()
+        .ok()
+        .filter(|&secs| secs > 0)
+        .unwrap_or(base.connect_timeout_seconds)
+        .min(600);
+    cfg.read_timeout_seconds = w.read_timeout.get_value()
+        .trim()
+        .parse::<u32>()
+        .ok()
+        .filter(|&secs| secs > 0)
+        .unwrap_or(base.read_timeout_seconds)
+        .min(600);
+    cfg.reuse_smtp_connection = w.reuse_smtp.get_value();
+    cfg.sends_per_minute = w.sends_per_minute.get_value()
+        .trim()
+        .parse::<u32>()
+        .unwrap_or(base.sends_per_minute)
+        .min(1000);
+    cfg.connections_per_minute = w.connections_per_minute.get_value()
+        .trim()
+        .parse::<u32>()
+        .unwrap_or(base.connections_per_minute)
+        .min(100);
+    cfg.fetch_concurrency = w.fetch_concurrency.get_value()
+        .trim()
+        .parse::<u32>()
+        .unwrap_or(base.fetch_concurrency)
+        .clamp(1, 20);
 
     cfg
 }
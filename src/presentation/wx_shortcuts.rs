@@ -0,0 +1,212 @@
+//! Keyboard Shortcuts window
+//!
+//! Tools > Keyboard Shortcuts lists every menu command with its shortcut.
+//! Change types a new shortcut for the selected command, Remove leaves it
+//! without one and Reset goes back to the default. Taking a shortcut from
+//! another command asks first; shortcuts Windows or screen readers use are
+//! refused.
+
+use crate::common::Error;
+use crate::presentation::command_palette::PaletteCommand;
+use crate::presentation::keymap::{normalize_shortcut, reserved_use, Keymap};
+use crate::presentation::wx_managers::get_selected;
+use std::cell::RefCell;
+use std::rc::Rc;
+use wxdragon::prelude::*;
+
+/// Show the commands and their shortcuts. Returns the edited keymap, or
+/// `None` when cancelled.
+pub fn show_shortcuts_dialog(parent: &Frame, commands: &[PaletteCommand<&'static str>], keymap: &Keymap) -> Option<Keymap> {
+    let dlg = Dialog::builder(parent, "Keyboard Shortcuts")
+        .with_size(720, 520)
+        .with_style(DialogStyle::DefaultDialogStyle | DialogStyle::ResizeBorder)
+        .build();
+    let sizer = BoxSizer::builder(Orientation::Vertical).build();
+
+    let list = ListCtrl::builder(&dlg)
+        .with_style(ListCtrlStyle::Report | ListCtrlStyle::SingleSel | ListCtrlStyle::HRules)
+        .build();
+    list.insert_column(0, "Command", ListColumnFormat::Left, 250);
+    list.insert_column(1, "Menu", ListColumnFormat::Left, 170);
+    list.insert_column(2, "Shortcut", ListColumnFormat::Left, 130);
+    list.insert_column(3, "Default", ListColumnFormat::Left, 130);
+    sizer.add(&list, 1, SizerFlag::Expand | SizerFlag::All, 8);
+
+    let hint = StaticText::builder(&dlg)
+        .with_label("Shortcuts with Ctrl+Alt can clash with screen reader commands.")
+        .build();
+    sizer.add(&hint, 0, SizerFlag::Expand | SizerFlag::Left | SizerFlag::Right, 8);
+
+    let btns = BoxSizer::builder(Orientation::Horizontal).build();
+    let change = Button::builder(&dlg).with_label("&Change...").build();
+    let remove = Button::builder(&dlg).with_label("&Remove").build();
+    let reset = Button::builder(&dlg).with_label("Re&set").build();
+    let reset_all = Button::builder(&dlg).with_label("Reset &All").build();
+    let ok = Button::builder(&dlg).with_label("OK").with_id(ID_OK).build();
+    let cancel = Button::builder(&dlg).with_label("Cancel").with_id(ID_CANCEL).build();
+    for button in [&change, &remove, &reset, &reset_all] {
+        btns.add(button, 0, SizerFlag::All, 4);
+    }
+    sizer.add_sizer(&btns, 0, SizerFlag::All, 4);
+    let ok_row = BoxSizer::builder(Orientation::Horizontal).build();
+    ok_row.add(&ok, 0, SizerFlag::All, 4);
+    ok_row.add(&cancel, 0, SizerFlag::All, 4);
+    sizer.add_sizer(&ok_row, 0, SizerFlag::AlignRight | SizerFlag::All, 8);
+    dlg.set_sizer(sizer, true);
+
+    let commands: Rc<[PaletteCommand<&'static str>]> = commands.to_vec().into();
+    let keymap = Rc::new(RefCell::new(keymap.clone()));
+    let refresh = {
+        let commands = commands.clone();
+        let keymap = keymap.clone();
+        move || {
+            let keymap = keymap.borrow();
+            for (i, c) in commands.iter().enumerate() {
+                let idx = i as i64;
+                let shortcut = keymap.shortcut(c.command).unwrap_or(if keymap.is_changed(c.command) { "None" } else { "" });
+                list.set_item_text_by_column(idx, 2, shortcut);
+                list.set_item_text_by_column(idx, 3, keymap.default_shortcut(c.command).unwrap_or(""));
+            }
+        }
+    };
+    for (i, c) in commands.iter().enumerate() {
+        let idx = i as i64;
+        list.insert_item(idx, &c.name, None);
+        list.set_item_text_by_column(idx, 1, &c.menu);
+    }
+    refresh();
+    if !commands.is_empty() {
+        list.set_item_state(0, ListItemState::Selected | ListItemState::Focused, ListItemState::Selected | ListItemState::Focused);
+    }
+
+    let edit = {
+        let commands = commands.clone();
+        let keymap = keymap.clone();
+        let refresh = refresh.clone();
+        move || {
+            let Some(command) = get_selected(&list).and_then(|i| commands.get(i)) else { return };
+            let shortcut = ask_shortcut(&dlg, &commands, command, &keymap.borrow());
+            if let Some(shortcut) = shortcut {
+                if keymap.borrow_mut().set(command.command, Some(&shortcut)).is_ok() {
+                    refresh();
+                }
+            }
+        }
+    };
+    change.on_click({
+        let edit = edit.clone();
+        move |_| edit()
+    });
+    list.on_item_activated(move |_| edit());
+    remove.on_click({
+        let commands = commands.clone();
+        let keymap = keymap.clone();
+        let refresh = refresh.clone();
+        move |_| {
+            if let Some(command) = get_selected(&list).and_then(|i| commands.get(i)) {
+                let _ = keymap.borrow_mut().set(command.command, None);
+                refresh();
+            }
+        }
+    });
+    reset.on_click({
+        let commands = commands.clone();
+        let keymap = keymap.clone();
+        let refresh = refresh.clone();
+        move |_| {
+            if let Some(command) = get_selected(&list).and_then(|i| commands.get(i)) {
+                keymap.borrow_mut().reset(command.command);
+                refresh();
+            }
+        }
+    });
+    reset_all.on_click({
+        let keymap = keymap.clone();
+        move |_| {
+            keymap.borrow_mut().reset_all();
+            refresh();
+        }
+    });
+    ok.on_click({ let d = dlg; move |_| { d.end_modal(ID_OK); } });
+    cancel.on_click({ let d = dlg; move |_| { d.end_modal(ID_CANCEL); } });
+
+    list.set_focus();
+    if dlg.show_modal() != ID_OK {
+        return None;
+    }
+    let edited = keymap.borrow().clone();
+    Some(edited)
+}
+
+/// Ask for `command`'s new shortcut. Shortcuts that can't be used are
+/// explained in the window; one another command has is only taken once
+/// the user agrees.
+fn ask_shortcut(
+    parent: &Dialog,
+    commands: &[PaletteCommand<&'static str>],
+    command: &PaletteCommand<&'static str>,
+    keymap: &Keymap,
+) -> Option<String> {
+    let dlg = Dialog::builder(parent, "Change Shortcut").with_size(400, 170).build();
+    let sizer = BoxSizer::builder(Orientation::Vertical).build();
+
+    let row = BoxSizer::builder(Orientation::Horizontal).build();
+    let label = StaticText::builder(&dlg).with_label(&format!("&Shortcut for {}:", command.name)).build();
+    let field = TextCtrl::builder(&dlg).with_value(keymap.shortcut(command.command).unwrap_or("")).build();
+    field.set_tooltip("Type the keys joined with +, for example Ctrl+Shift+K, Alt+F2 or F7");
+    row.add(&label, 0, SizerFlag::AlignCenterVertical | SizerFlag::All, 4);
+    row.add(&field, 1, SizerFlag::Expand | SizerFlag::All, 4);
+    sizer.add_sizer(&row, 0, SizerFlag::Expand | SizerFlag::All, 8);
+    let error = StaticText::builder(&dlg).with_label(" ").build();
+    sizer.add(&error, 0, SizerFlag::Expand | SizerFlag::Left | SizerFlag::Right, 8);
+
+    let btns = BoxSizer::builder(Orientation::Horizontal).build();
+    let ok = Button::builder(&dlg).with_label("OK").with_id(ID_OK).build();
+    let cancel = Button::builder(&dlg).with_label("Cancel").with_id(ID_CANCEL).build();
+    btns.add(&ok, 0, SizerFlag::All, 4);
+    btns.add(&cancel, 0, SizerFlag::All, 4);
+    sizer.add_sizer(&btns, 0, SizerFlag::AlignRight | SizerFlag::All, 8);
+    dlg.set_sizer(sizer, true);
+
+    ok.on_click({ let d = dlg; move |_| { d.end_modal(ID_OK); } });
+    cancel.on_click({ let d = dlg; move |_| { d.end_modal(ID_CANCEL); } });
+    field.set_focus();
+
+    while dlg.show_modal() == ID_OK {
+        let shortcut = match normalize_shortcut(&field.get_value()) {
+            Ok(shortcut) => shortcut,
+            Err(Error::Config(problem)) => {
+                error.set_label(&problem);
+                field.set_focus();
+                continue;
+            }
+            Err(e) => {
+                error.set_label(&e.to_string());
+                continue;
+            }
+        };
+        if let Some(what) = reserved_use(&shortcut) {
+            error.set_label(&format!("{} {}, so it can't be used here", shortcut, what));
+            field.set_focus();
+            continue;
+        }
+        let other = keymap
+            .used_by(command.command, &shortcut)
+            .and_then(|other| commands.iter().find(|c| c.command == other));
+        if let Some(other) = other {
+            let question = format!(
+                "{} is already the shortcut for {} ({}). Use it for {} instead? {} will have no shortcut.",
+                shortcut, other.name, other.menu, command.name, other.name
+            );
+            let confirm = MessageDialog::builder(&dlg, &question, "Shortcut In Use")
+                .with_style(MessageDialogStyle::YesNo | MessageDialogStyle::IconQuestion)
+                .build();
+            if confirm.show_modal() != ID_YES {
+                field.set_focus();
+                continue;
+            }
+        }
+        return Some(shortcut);
+    }
+    None
+}
@@ -2,10 +2,37 @@
 //!
 //! Manages email attachments.
 
-use crate::common::Result;
+use crate::common::{Error, Result};
+use mail_parser::MimeHeaders;
+use std::collections::HashSet;
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 
+/// Name used for an attachment that has none, or none that survives sanitizing
+const DEFAULT_FILENAME: &str = "attachment";
+
+/// Largest attachment decoded for a preview
+const MAX_PREVIEW_BYTES: usize = 20 * 1024 * 1024;
+
+/// Names Windows reserves for devices, with or without an extension
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9", "LPT1",
+    "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Extensions of files that run as programs when opened
+const EXECUTABLE_EXTENSIONS: [&str; 27] = [
+    "app", "apk", "bat", "cmd", "com", "cpl", "dll", "exe", "gadget", "hta", "inf", "jar", "js", "jse", "lnk", "msc",
+    "msi", "msp", "pif", "ps1", "reg", "scf", "scr", "sh", "vbe", "vbs", "wsf",
+];
+
+/// Extensions of harmless-looking files that programs pretend to be
+const DECOY_EXTENSIONS: [&str; 25] = [
+    "avi", "bmp", "csv", "doc", "docx", "gif", "htm", "html", "jpeg", "jpg", "mov", "mp3", "mp4", "odt", "pdf", "png",
+    "ppt", "pptx", "rar", "rtf", "txt", "wav", "xls", "xlsx", "zip",
+];
+
 /// Email attachment
 #[derive(Debug, Clone)]
 pub struct Attachment {
@@ -13,6 +40,77 @@ pub struct Attachment {
     pub mime_type: String,
     pub size: usize,
     pub data: Vec<u8>,
+    /// The part's `Content-Description`, if any
+    pub description: Option<String>,
+}
+
+/// A scaled-down image attachment for the preview pane
+#[derive(Debug, Clone)]
+pub struct ImagePreview {
+    /// Filename and description, for the caption and screen readers
+    pub label: String,
+    pub width: u32,
+    pub height: u32,
+    /// RGBA pixels, row by row
+    pub rgba: Vec<u8>,
+}
+
+impl Attachment {
+    /// Whether the MIME type is an image
+    pub fn is_image(&self) -> bool {
+        self.mime_type.starts_with("image/")
+    }
+
+    /// The filename, followed by the description when there is one that
+    /// says more than the filename
+    pub fn label(&self) -> String {
+        match self.description.as_deref().map(str::trim) {
+            Some(text) if !text.is_empty() && text != self.filename => format!("{} ({})", self.filename, text),
+            _ => self.filename.clone(),
+        }
+    }
+
+    /// MIME type recognised from the attachment's first bytes, whatever it
+    /// is labelled as; `None` for formats without a signature, such as text
+    pub fn sniffed_type(&self) -> Option<&'static str> {
+        infer::get(&self.data).map(|kind| kind.mime_type())
+    }
+
+    /// Why opening the attachment could be dangerous: a program hiding
+    /// behind a second extension, contents that don't match the declared
+    /// type, or a program. `None` when it is what it claims to be.
+    pub fn safety_warning(&self) -> Option<String> {
+        if let Some(warning) = hidden_extension_warning(&self.filename) {
+            return Some(warning);
+        }
+        if let Some(kind) = infer::get(&self.data).filter(|kind| !types_agree(&self.mime_type, kind.mime_type())) {
+            return Some(if kind.matcher_type() == infer::MatcherType::App {
+                format!("\"{}\" is labelled {} but is really a program", self.filename, self.mime_type)
+            } else {
+                format!("\"{}\" is labelled {} but contains {}", self.filename, self.mime_type, kind.mime_type())
+            });
+        }
+        self.is_program().then(|| format!("\"{}\" is a program", self.filename))
+    }
+
+    /// Whether the attachment runs as a program or script when opened, by
+    /// its extension (even one hidden behind a second extension) or its
+    /// contents
+    pub fn is_program(&self) -> bool {
+        has_executable_extension(&self.filename) || infer::is_app(&self.data) || self.data.starts_with(b"#!")
+    }
+
+    /// Decode an image attachment and scale it to fit `max_side` pixels,
+    /// keeping its proportions. `None` for other types, images too large
+    /// to decode safely and formats that aren't supported.
+    pub fn image_preview(&self, max_side: u32) -> Option<ImagePreview> {
+        if !self.is_image() || self.data.len() > MAX_PREVIEW_BYTES {
+            return None;
+        }
+        let image = image::load_from_memory(&self.data).ok()?.thumbnail(max_side, max_side).to_rgba8();
+        let (width, height) = image.dimensions();
+        Some(ImagePreview { label: self.label(), width, height, rgba: image.into_raw() })
+    }
 }
 
 /// Attachment handler
@@ -34,6 +132,53 @@ impl AttachmentHandler {
         Ok(())
     }
 
+    /// Save every attachment into `dir` without overwriting anything.
+    ///
+    /// Names are sanitized, and a name already in `dir` or used earlier in
+    /// the batch gets a number: `report.pdf`, `report (2).pdf`, ... Returns
+    /// each attachment's name with where it was saved or why it wasn't.
+    pub fn save_all(&self, attachments: &[Attachment], dir: &Path) -> Vec<(String, Result<PathBuf>)> {
+        if let Err(e) = fs::create_dir_all(dir) {
+            let message = e.to_string();
+            return attachments
+                .iter()
+                .map(|a| (a.filename.clone(), Err(crate::common::Error::Other(message.clone()))))
+                .collect();
+        }
+        let mut taken = HashSet::new();
+        attachments
+            .iter()
+            .map(|attachment| {
+                let target = unique_path(dir, &sanitize_filename(&attachment.filename), &mut taken);
+                let result = fs::write(&target, &attachment.data).map(|_| target).map_err(Into::into);
+                (attachment.filename.clone(), result)
+            })
+            .collect()
+    }
+
+    /// Write `attachment` into `dir` for another program to open, returning
+    /// the path. The name is sanitized and numbered rather than overwriting
+    /// a file, and nothing is written outside `dir`: it must not be a link
+    /// to another folder, a link already at the target is not followed, and
+    /// the saved file must resolve to a path inside it.
+    pub fn save_for_opening(&self, attachment: &Attachment, dir: &Path) -> Result<PathBuf> {
+        fs::create_dir_all(dir)?;
+        if fs::symlink_metadata(dir)?.file_type().is_symlink() {
+            return Err(Error::Security(format!("{} is a link to another folder", dir.display())));
+        }
+        let root = dir.canonicalize()?;
+        let target = unique_path(&root, &sanitize_filename(&attachment.filename), &mut HashSet::new());
+        // `create_new` fails on anything already at the target, links included
+        let mut file = fs::OpenOptions::new().write(true).create_new(true).open(&target)?;
+        file.write_all(&attachment.data)?;
+        let saved = target.canonicalize()?;
+        if !saved.starts_with(&root) {
+            let _ = fs::remove_file(&target);
+            return Err(Error::Security(format!("{} is outside {}", saved.display(), root.display())));
+        }
+        Ok(saved)
+    }
+
     /// Load attachment from disk
     pub fn load(&self, path: &str) -> Result<Attachment> {
         let target = PathBuf::from(path);
@@ -49,8 +194,127 @@ impl AttachmentHandler {
             mime_type,
             size: data.len(),
             data,
+            description: None,
+        })
+    }
+}
+
+/// Decode the attachments of a raw RFC 5322 message, in message order.
+/// Unnamed parts are called `attachment`; sanitizing happens on save.
+/// Parts sent as `application/octet-stream` get a type from their
+/// extension, so an image sent that way still previews.
+pub fn extract_attachments(raw: &[u8]) -> Vec<Attachment> {
+    let Some(parsed) = mail_parser::MessageParser::default().parse(raw) else {
+        return Vec::new();
+    };
+    parsed
+        .attachments()
+        .map(|part| {
+            let data = part.contents().to_vec();
+            let mime_type = part
+                .content_type()
+                .map(|ct| match ct.subtype() {
+                    Some(subtype) => format!("{}/{}", ct.ctype(), subtype),
+                    None => ct.ctype().to_string(),
+                })
+                .unwrap_or_else(|| "application/octet-stream".to_string())
+                .to_ascii_lowercase();
+            let filename = part.attachment_name().unwrap_or(DEFAULT_FILENAME).to_string();
+            let mime_type = match mime_type.as_str() {
+                "application/octet-stream" => infer_mime_type(Path::new(&filename)).to_string(),
+                _ => mime_type,
+            };
+            Attachment {
+                filename,
+                mime_type,
+                size: data.len(),
+                data,
+                description: part.content_description().map(str::to_string),
+            }
         })
+        .collect()
+}
+
+/// A file name that is safe to create on any platform and can't escape
+/// the folder it is saved in. Path separators and characters Windows
+/// rejects become `_`, leading dots and trailing dots and spaces are
+/// dropped, and device names such as `CON` get a `_` prefix.
+pub fn sanitize_filename(name: &str) -> String {
+    let replaced: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let trimmed = replaced.trim().trim_start_matches('.').trim_end_matches(['.', ' ']);
+    if trimmed.is_empty() {
+        return DEFAULT_FILENAME.to_string();
+    }
+    let stem = trimmed.split('.').next().unwrap_or_default();
+    if RESERVED_NAMES.iter().any(|r| r.eq_ignore_ascii_case(stem)) {
+        return format!("_{}", trimmed);
     }
+    trimmed.to_string()
+}
+
+/// The warning for an attachment known only by name, before it is
+/// downloaded: a disguised or plain program. See [`Attachment::safety_warning`].
+pub fn filename_warning(name: &str) -> Option<String> {
+    hidden_extension_warning(name)
+        .or_else(|| has_executable_extension(name).then(|| format!("\"{}\" is a program", name)))
+}
+
+fn extension(name: &str) -> Option<String> {
+    let (stem, ext) = name.trim_end_matches(['.', ' ']).rsplit_once('.')?;
+    (!stem.is_empty()).then(|| ext.trim().to_ascii_lowercase())
+}
+
+fn has_executable_extension(name: &str) -> bool {
+    extension(name).is_some_and(|ext| EXECUTABLE_EXTENSIONS.contains(&ext.as_str()))
+}
+
+/// `invoice.pdf.exe`, often with spaces before the last dot so the real
+/// extension is pushed out of sight
+fn hidden_extension_warning(name: &str) -> Option<String> {
+    let (stem, _) = name.trim_end_matches(['.', ' ']).rsplit_once('.')?;
+    let real = extension(name).filter(|ext| EXECUTABLE_EXTENSIONS.contains(&ext.as_str()))?;
+    let shown = extension(stem.trim_end()).filter(|ext| DECOY_EXTENSIONS.contains(&ext.as_str()))?;
+    Some(format!("\"{}\" is a .{} program disguised as a .{} file", name.trim_end(), real, shown))
+}
+
+/// Whether contents recognised as `sniffed` fit the `declared` type.
+/// Nothing declared fits anything; a type with the same top level (such as
+/// `image/jpg` for `image/jpeg`) fits, as do office documents, which are zip
+/// or OLE containers.
+fn types_agree(declared: &str, sniffed: &str) -> bool {
+    let declared = declared.trim().to_ascii_lowercase();
+    let top = |mime: &str| mime.split('/').next().unwrap_or_default().to_string();
+    match sniffed {
+        _ if declared.is_empty() || declared == "application/octet-stream" || declared == sniffed => true,
+        _ if top(&declared) != "application" && top(&declared) == top(sniffed) => true,
+        "application/zip" => declared.contains("zip") || declared.starts_with("application/vnd."),
+        "application/x-ole-storage" => declared.starts_with("application/vnd.ms-") || declared == "application/msword",
+        _ => false,
+    }
+}
+
+/// `dir/name`, numbered when that file exists or `taken` already holds the
+/// name. Names are compared case-insensitively, as Windows and macOS do.
+fn unique_path(dir: &Path, name: &str, taken: &mut HashSet<String>) -> PathBuf {
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem, format!(".{}", ext)),
+        _ => (name, String::new()),
+    };
+    let mut candidate = name.to_string();
+    let mut n = 1;
+    while taken.contains(&candidate.to_lowercase()) || dir.join(&candidate).exists() {
+        n += 1;
+        candidate = format!("{} ({}){}", stem, n, extension);
+    }
+    taken.insert(candidate.to_lowercase());
+    dir.join(candidate)
 }
 
 fn infer_mime_type(path: &Path) -> &'static str {
@@ -64,6 +328,7 @@ fn infer_mime_type(path: &Path) -> &'static str {
         Some("html" | "htm") => "text/html",
         Some("jpg" | "jpeg") => "image/jpeg",
         Some("png") => "image/png",
+        Some("gif") => "image/gif",
         Some("pdf") => "application/pdf",
         _ => "application/octet-stream",
     }
@@ -99,6 +364,7 @@ mod tests {
             mime_type: "text/plain".to_string(),
             size: 5,
             data: b"hello".to_vec(),
+            description: None,
         };
         handler.save(&attachment, path.to_str().unwrap()).unwrap();
         let loaded = handler.load(path.to_str().unwrap()).unwrap();
@@ -106,4 +372,157 @@ mod tests {
         assert_eq!(loaded.mime_type, "text/plain");
         let _ = std::fs::remove_file(path);
     }
+
+    #[test]
+    fn test_sanitize_filename() {
+        assert_eq!(sanitize_filename("report.pdf"), "report.pdf");
+        assert_eq!(sanitize_filename("../../etc/passwd"), "_.._etc_passwd");
+        assert_eq!(sanitize_filename("C:\\Windows\\win.ini"), "C__Windows_win.ini");
+        assert_eq!(sanitize_filename("what?.txt"), "what_.txt");
+        assert_eq!(sanitize_filename(".hidden"), "hidden");
+        assert_eq!(sanitize_filename("notes. "), "notes");
+        assert_eq!(sanitize_filename("con.txt"), "_con.txt");
+        assert_eq!(sanitize_filename(" .. "), "attachment");
+    }
+
+    #[test]
+    fn test_extract_and_save_all() {
+        let raw = "From: alice@example.com\r\n\
+            Subject: Files\r\n\
+            MIME-Version: 1.0\r\n\
+            Content-Type: multipart/mixed; boundary=\"b\"\r\n\
+            \r\n\
+            --b\r\n\
+            Content-Type: text/plain\r\n\
+            \r\n\
+            See attached.\r\n\
+            --b\r\n\
+            Content-Type: text/plain; name=\"notes.txt\"\r\n\
+            Content-Disposition: attachment; filename=\"notes.txt\"\r\n\
+            \r\n\
+            first\r\n\
+            --b\r\n\
+            Content-Type: application/pdf\r\n\
+            Content-Disposition: attachment; filename=\"notes.txt\"\r\n\
+            Content-Transfer-Encoding: base64\r\n\
+            \r\n\
+            c2Vjb25k\r\n\
+            --b\r\n\
+            Content-Type: application/octet-stream\r\n\
+            Content-Disposition: attachment; filename=\"../secret.gif\"\r\n\
+            Content-Description: Team photo\r\n\
+            \r\n\
+            third\r\n\
+            --b--\r\n";
+        let attachments = extract_attachments(raw.as_bytes());
+        assert_eq!(attachments.len(), 3);
+        assert_eq!(attachments[1].mime_type, "application/pdf");
+        assert_eq!(attachments[1].data, b"second");
+        assert!(attachments[2].is_image() && !attachments[1].is_image());
+        assert_eq!(attachments[2].label(), "../secret.gif (Team photo)");
+        assert_eq!(attachments[0].label(), "notes.txt");
+        // Not a decodable image, so no preview rather than an error
+        assert!(attachments[2].image_preview(64).is_none());
+        assert!(attachments[0].image_preview(64).is_none());
+
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let dir = std::env::temp_dir().join(format!("wixen_attachments_{}", nanos));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("notes.txt"), b"already here").unwrap();
+
+        let handler = AttachmentHandler::new().unwrap();
+        let saved = handler.save_all(&attachments, &dir);
+        let names: Vec<_> = saved
+            .iter()
+            .map(|(_, r)| r.as_ref().unwrap().file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, ["notes (2).txt", "notes (3).txt", "_secret.gif"]);
+        assert_eq!(std::fs::read(dir.join("notes.txt")).unwrap(), b"already here");
+        assert_eq!(std::fs::read(dir.join("notes (3).txt")).unwrap(), b"second");
+        assert!(extract_attachments(b"Subject: none\r\n\r\nNo attachments").is_empty());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+    #[test]
+    fn test_safety_warnings() {
+        let attachment = |filename: &str, mime_type: &str, data: &[u8]| Attachment {
+            filename: filename.to_string(),
+            mime_type: mime_type.to_string(),
+            size: data.len(),
+            data: data.to_vec(),
+            description: None,
+        };
+        let exe = b"MZ\x90\x00\x03\x00\x00\x00\x04\x00\x00\x00\xff\xff\x00\x00";
+        let pdf = b"%PDF-1.7\n%\xe2\xe3\xcf\xd3\n";
+        let png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR";
+
+        assert_eq!(attachment("report.pdf", "application/pdf", pdf).sniffed_type(), Some("application/pdf"));
+        assert_eq!(attachment("report.pdf", "application/pdf", pdf).safety_warning(), None);
+        assert_eq!(attachment("notes.txt", "text/plain", b"hello").safety_warning(), None);
+        // A close or missing label is not a mismatch
+        assert_eq!(attachment("photo.png", "image/jpeg", png).safety_warning(), None);
+        assert_eq!(attachment("photo", "application/octet-stream", png).safety_warning(), None);
+
+        assert_eq!(
+            attachment("report.pdf", "application/pdf", exe).safety_warning().as_deref(),
+            Some("\"report.pdf\" is labelled application/pdf but is really a program")
+        );
+        assert_eq!(
+            attachment("photo.png", "image/png", pdf).safety_warning().as_deref(),
+            Some("\"photo.png\" is labelled image/png but contains application/pdf")
+        );
+        assert_eq!(
+            attachment("invoice.pdf      .exe", "application/pdf", pdf).safety_warning().as_deref(),
+            Some("\"invoice.pdf      .exe\" is a .exe program disguised as a .pdf file")
+        );
+        assert_eq!(
+            attachment("setup.exe", "application/octet-stream", exe).safety_warning().as_deref(),
+            Some("\"setup.exe\" is a program")
+        );
+        assert_eq!(
+            attachment("tool", "application/octet-stream", exe).safety_warning().as_deref(),
+            Some("\"tool\" is a program")
+        );
+
+        assert!(attachment("run.sh", "text/plain", b"#!/bin/sh\n").is_program());
+        assert!(attachment("notes", "text/plain", b"#!/bin/sh\n").is_program());
+        assert!(attachment("report.pdf", "application/pdf", exe).is_program());
+        assert!(!attachment("report.pdf", "application/pdf", pdf).is_program());
+
+        assert_eq!(filename_warning("notes.txt"), None);
+        assert_eq!(filename_warning("archive.tar.gz"), None);
+        assert_eq!(filename_warning("setup.x64.exe").as_deref(), Some("\"setup.x64.exe\" is a program"));
+        assert_eq!(
+            filename_warning("Photo.JPG.scr").as_deref(),
+            Some("\"Photo.JPG.scr\" is a .scr program disguised as a .jpg file")
+        );
+    }
+
+    #[test]
+    fn test_save_for_opening() {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let base = std::env::temp_dir().join(format!("wixen_open_{}", nanos));
+        let dir = base.join("Downloads");
+        let attachment = Attachment {
+            filename: "../../report.pdf".to_string(),
+            mime_type: "application/pdf".to_string(),
+            size: 4,
+            data: b"%PDF".to_vec(),
+            description: None,
+        };
+        let handler = AttachmentHandler::new().unwrap();
+        let first = handler.save_for_opening(&attachment, &dir).unwrap();
+        let second = handler.save_for_opening(&attachment, &dir).unwrap();
+        let root = dir.canonicalize().unwrap();
+        assert_eq!(first, root.join("_.._report.pdf"));
+        assert_eq!(second, root.join("_.._report (2).pdf"));
+        assert_eq!(std::fs::read(&first).unwrap(), b"%PDF");
+
+        #[cfg(unix)]
+        {
+            let link = base.join("Elsewhere");
+            std::os::unix::fs::symlink(&dir, &link).unwrap();
+            assert!(matches!(handler.save_for_opening(&attachment, &link), Err(Error::Security(_))));
+        }
+        let _ = std::fs::remove_dir_all(base);
+    }
 }
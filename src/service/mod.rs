@@ -4,15 +4,23 @@
 
 pub mod attachments;
 pub mod cache;
+pub mod multipart;
 pub mod oauth;
 pub mod oauth_credentials;
+#[cfg(feature = "openpgp")]
+pub mod openpgp;
 pub mod protocols;
 pub mod security;
+#[cfg(feature = "smime")]
+pub mod smime;
 pub mod spellcheck;
+pub mod unsubscribe;
 
 pub use attachments::AttachmentHandler;
 pub use cache::CacheService;
 pub use oauth::{AuthManager, OAuthProvider, OAuthService, OAuthTokenSet};
 pub use protocols::{imap, pop3, smtp};
 pub use security::SecurityService;
+#[cfg(feature = "openpgp")]
+pub use security::PgpKeyStore;
 pub use spellcheck::{I18n, Locale, SpellChecker};
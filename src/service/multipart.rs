@@ -0,0 +1,112 @@
+//! Raw multipart helpers for signed and encrypted mail
+//!
+//! Signature checks need the exact bytes of the signed part, which parsed
+//! message structures don't keep, so PGP/MIME and S/MIME split the raw text
+//! on the boundary instead (RFC 1847, RFC 2046 §5.1.1).
+
+use mail_parser::MimeHeaders;
+
+/// Top-level `multipart/signed` or `multipart/encrypted` structure
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecuredMultipart {
+    /// `true` for `multipart/signed`, `false` for `multipart/encrypted`
+    pub signed: bool,
+    /// The `protocol` parameter, lower-cased
+    pub protocol: String,
+    pub boundary: String,
+}
+
+impl SecuredMultipart {
+    /// Read the top-level content type of a raw message; `None` unless it
+    /// is signed or encrypted multipart.
+    pub fn detect(raw: &str) -> Option<Self> {
+        let parsed = mail_parser::MessageParser::default().parse(raw.as_bytes())?;
+        let content_type = parsed.content_type()?;
+        if !content_type.ctype().eq_ignore_ascii_case("multipart") {
+            return None;
+        }
+        let signed = match content_type.subtype()?.to_ascii_lowercase().as_str() {
+            "signed" => true,
+            "encrypted" => false,
+            _ => return None,
+        };
+        Some(Self {
+            signed,
+            protocol: content_type.attribute("protocol")?.to_ascii_lowercase(),
+            boundary: content_type.attribute("boundary")?.to_string(),
+        })
+    }
+}
+
+/// Split a multipart message into its parts' raw text, headers included.
+///
+/// The line break before each delimiter belongs to the delimiter, so it is
+/// not part of the preceding part.
+pub fn split_parts<'a>(raw: &'a str, boundary: &str) -> Vec<&'a str> {
+    let delimiter = format!("--{}", boundary);
+    let next_delimiter = format!("\n{}", delimiter);
+    let mut parts = Vec::new();
+    let Some(first) = raw.find(&delimiter) else {
+        return parts;
+    };
+    let mut rest = &raw[first + delimiter.len()..];
+    while !rest.starts_with("--") {
+        let Some(line_end) = rest.find('\n') else { break };
+        rest = &rest[line_end + 1..];
+        let Some(end) = rest.find(&next_delimiter) else { break };
+        parts.push(rest[..end].strip_suffix('\r').unwrap_or(&rest[..end]));
+        rest = &rest[end + next_delimiter.len()..];
+    }
+    parts
+}
+
+/// The body of a raw MIME part (everything after the blank line).
+pub fn part_body(part: &str) -> &str {
+    part.find("\r\n\r\n")
+        .map(|i| &part[i + 4..])
+        .or_else(|| part.find("\n\n").map(|i| &part[i + 2..]))
+        .unwrap_or("")
+}
+
+/// Convert line endings to CRLF, the canonical form that is signed.
+pub fn canonical_line_endings(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\n', "\r\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIGNED: &str = "From: alice@example.com\r\n\
+        Content-Type: multipart/signed; micalg=pgp-sha256;\r\n protocol=\"application/pgp-signature\"; boundary=\"b1\"\r\n\
+        \r\n\
+        --b1\r\n\
+        Content-Type: text/plain\r\n\
+        \r\n\
+        Hello\r\n\
+        --b1\r\n\
+        Content-Type: application/pgp-signature\r\n\
+        \r\n\
+        -----BEGIN PGP SIGNATURE-----\r\n\
+        --b1--\r\n";
+
+    #[test]
+    fn test_split_signed_multipart() {
+        assert_eq!(
+            SecuredMultipart::detect(SIGNED),
+            Some(SecuredMultipart {
+                signed: true,
+                protocol: "application/pgp-signature".to_string(),
+                boundary: "b1".to_string(),
+            })
+        );
+        assert_eq!(SecuredMultipart::detect("Content-Type: text/plain\r\n\r\nHi"), None);
+        let parts = split_parts(SIGNED, "b1");
+        assert_eq!(parts, vec![
+            "Content-Type: text/plain\r\n\r\nHello",
+            "Content-Type: application/pgp-signature\r\n\r\n-----BEGIN PGP SIGNATURE-----",
+        ]);
+        assert_eq!(part_body(parts[0]), "Hello");
+        assert_eq!(canonical_line_endings("a\nb\r\nc"), "a\r\nb\r\nc");
+    }
+}
@@ -11,6 +11,7 @@
 //! - **`AuthManager`** — per-account token lifecycle: authorize, refresh, retrieve.
 //! - **`OAuthService`** — static helpers and provider registry (backward compat).
 
+use crate::common::clock::{SharedClock, SystemClock};
 use crate::common::{Error, Result};
 use oauth2::{
     basic::BasicClient, AuthUrl, ClientId, ClientSecret, CsrfToken, PkceCodeChallenge,
@@ -325,7 +326,7 @@ pub fn wait_for_redirect_code(
 
         let url_str = format!("http://localhost{}", request.url());
         let parsed = url::Url::parse(&url_str).map_err(|e| {
-            Error::Authentication(format!("Failed to parse redirect URL: {}", e))
+            Error::Parse(format!("Failed to parse redirect URL: {}", e))
         })?;
 
         // Extract query parameters
@@ -404,6 +405,8 @@ pub struct AuthManager {
     /// Client credentials.
     client_id: String,
     client_secret: String,
+    /// Decides when the access token is due for a refresh
+    clock: SharedClock,
 }
 
 impl AuthManager {
@@ -418,9 +421,16 @@ impl AuthManager {
             provider: provider.to_string(),
             client_id: client_id.to_string(),
             client_secret: client_secret.to_string(),
+            clock: SystemClock::shared(),
         }
     }
 
+    /// Take the time from `clock` instead of the system clock
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
     /// Run the full browser-based OAuth2 authorization flow with PKCE.
     ///
     /// 1. Generate auth URL with PKCE challenge.
@@ -483,19 +493,7 @@ impl AuthManager {
     pub async fn get_valid_token(&self) -> Result<String> {
         let tokens = self.load_tokens()?;
 
-        // Check expiry (refresh proactively if within 5 minutes of expiration)
-        let needs_refresh = match &tokens.expires_at {
-            Some(ts) => {
-                chrono::DateTime::parse_from_rfc3339(ts)
-                    .map(|dt| {
-                        dt < chrono::Utc::now() + chrono::TimeDelta::minutes(5)
-                    })
-                    .unwrap_or(true)
-            }
-            None => false,
-        };
-
-        if needs_refresh {
+        if self.needs_refresh(&tokens) {
             let refresh_token = tokens.refresh_token.as_deref().unwrap_or("");
             if refresh_token.is_empty() {
                 return Err(Error::Authentication(
@@ -519,6 +517,17 @@ impl AuthManager {
         Ok(tokens.access_token)
     }
 
+    /// Whether `tokens` should be refreshed before use: they expire within
+    /// 5 minutes, or their expiry can't be read.
+    pub fn needs_refresh(&self, tokens: &OAuthTokenSet) -> bool {
+        match &tokens.expires_at {
+            Some(ts) => chrono::DateTime::parse_from_rfc3339(ts)
+                .map(|dt| dt < self.clock.now() + chrono::TimeDelta::minutes(5))
+                .unwrap_or(true),
+            None => false,
+        }
+    }
+
     /// Store tokens in the OS keychain.
     pub fn store_tokens(&self, tokens: &OAuthTokenSet) {
         let service = format!("wixen-mail-{}", self.provider);
@@ -593,7 +602,7 @@ async fn post_token_request(url: &str, params: &[(&str, &str)]) -> Result<OAuthT
     }
 
     let token: TokenResponse = serde_json::from_str(&body)
-        .map_err(|e| Error::Authentication(format!("Invalid token response: {}", e)))?;
+        .map_err(|e| Error::Parse(format!("Invalid token response: {}", e)))?;
 
     let expires_at = token.expires_in.map(|secs| {
         (chrono::Utc::now() + chrono::TimeDelta::seconds(secs)).to_rfc3339()
@@ -625,6 +634,7 @@ fn percent_encode(input: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::common::clock::MockClock;
 
     #[test]
     fn test_provider_lookup() {
@@ -666,6 +676,33 @@ mod tests {
         assert!(OAuthService::is_expired(Some(&past)));
     }
 
+    #[test]
+    fn test_refresh_due_by_clock() {
+        use chrono::TimeZone;
+        let start = chrono::Utc.with_ymd_and_hms(2025, 3, 12, 9, 0, 0).unwrap();
+        let clock = MockClock::new(start);
+        let manager = AuthManager::new("acc", "gmail", "id", "secret").with_clock(clock.clone());
+        let tokens = OAuthTokenSet {
+            access_token: "access".to_string(),
+            refresh_token: Some("refresh".to_string()),
+            token_type: "Bearer".to_string(),
+            scope: None,
+            expires_at: Some((start + chrono::TimeDelta::hours(1)).to_rfc3339()),
+        };
+        assert!(!manager.needs_refresh(&tokens));
+
+        // Refreshed once the token is within 5 minutes of expiring
+        clock.advance(chrono::TimeDelta::minutes(54));
+        assert!(!manager.needs_refresh(&tokens));
+        clock.advance(chrono::TimeDelta::minutes(2));
+        assert!(manager.needs_refresh(&tokens));
+
+        let unreadable = OAuthTokenSet { expires_at: Some("soon".to_string()), ..tokens.clone() };
+        assert!(manager.needs_refresh(&unreadable));
+        let forever = OAuthTokenSet { expires_at: None, ..tokens };
+        assert!(!manager.needs_refresh(&forever));
+    }
+
     #[test]
     fn test_build_authorization_url_legacy() {
         let url = OAuthService::build_authorization_url(
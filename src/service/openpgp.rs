@@ -0,0 +1,454 @@
+//! OpenPGP (PGP/MIME) signing, encryption, verification and decryption
+//!
+//! Outgoing messages are wrapped per RFC 3156: `multipart/signed` with a
+//! detached signature, or `multipart/encrypted` around the body (signed
+//! inside the encrypted data when a signing key is given). Incoming
+//! messages in either form are decrypted and checked against the sender's
+//! imported public keys. Keys are kept by `security::PgpKeyStore`.
+//!
+//! Only built with the `openpgp` cargo feature.
+
+use crate::common::{Error, Result};
+use crate::service::multipart::{canonical_line_endings, part_body, split_parts, SecuredMultipart};
+use crate::service::security::SignatureVerificationStatus;
+use lettre::message::{header::ContentType, MultiPart, SinglePart};
+use pgp::composed::{Deserializable, Message as PgpMessage, SignedPublicKey, SignedSecretKey, StandaloneSignature};
+use pgp::crypto::{hash::HashAlgorithm, sym::SymmetricKeyAlgorithm};
+use pgp::types::PublicKeyTrait;
+use pgp::ArmorOptions;
+use std::io::Cursor;
+
+const SIGNATURE_PROTOCOL: &str = "application/pgp-signature";
+const ENCRYPTED_PROTOCOL: &str = "application/pgp-encrypted";
+const MICALG: &str = "pgp-sha256";
+
+fn pgp_error(e: pgp::errors::Error) -> Error {
+    Error::Security(format!("OpenPGP: {}", e))
+}
+
+/// A secret key with the passphrase that unlocks it
+#[derive(Clone)]
+pub struct SecretKey {
+    pub key: SignedSecretKey,
+    pub passphrase: String,
+}
+
+impl std::fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SecretKey")
+            .field("fingerprint", &fingerprint_hex(&self.key))
+            .finish_non_exhaustive()
+    }
+}
+
+/// OpenPGP protection for an outgoing message
+#[derive(Debug, Clone, Default)]
+pub struct OutgoingPgp {
+    /// Sign with this key
+    pub sign_with: Option<SecretKey>,
+    /// Encrypt to these keys. Include the sender's own key to be able to
+    /// read the sent copy.
+    pub encrypt_to: Vec<SignedPublicKey>,
+}
+
+/// Summary of an imported key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgpKeyInfo {
+    /// Upper-case hex fingerprint
+    pub fingerprint: String,
+    pub user_ids: Vec<String>,
+    /// Whether the secret half is available (can sign and decrypt)
+    pub secret: bool,
+}
+
+impl PgpKeyInfo {
+    pub fn of_public(key: &SignedPublicKey) -> Self {
+        Self {
+            fingerprint: fingerprint_hex(key),
+            user_ids: key.details.users.iter().map(|u| u.id.id().to_string()).collect(),
+            secret: false,
+        }
+    }
+
+    pub fn of_secret(key: &SignedSecretKey) -> Self {
+        Self {
+            fingerprint: fingerprint_hex(key),
+            user_ids: key.details.users.iter().map(|u| u.id.id().to_string()).collect(),
+            secret: true,
+        }
+    }
+
+    /// Whether one of the key's user IDs is for `email`.
+    pub fn matches_email(&self, email: &str) -> bool {
+        self.user_ids.iter().any(|uid| user_id_email(uid).eq_ignore_ascii_case(email.trim()))
+    }
+}
+
+/// The address part of a user ID such as `Alice <alice@example.com>`.
+fn user_id_email(user_id: &str) -> &str {
+    match (user_id.rfind('<'), user_id.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &user_id[start + 1..end],
+        _ => user_id.trim(),
+    }
+}
+
+fn fingerprint_hex(key: &impl PublicKeyTrait) -> String {
+    key.fingerprint().as_bytes().iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+/// Parse and self-verify an armored public key.
+pub fn parse_public_key(armored: &str) -> Result<SignedPublicKey> {
+    let (key, _) = SignedPublicKey::from_string(armored).map_err(pgp_error)?;
+    key.verify().map_err(pgp_error)?;
+    Ok(key)
+}
+
+/// Parse and self-verify an armored secret key.
+pub fn parse_secret_key(armored: &str) -> Result<SignedSecretKey> {
+    let (key, _) = SignedSecretKey::from_string(armored).map_err(pgp_error)?;
+    key.verify().map_err(pgp_error)?;
+    Ok(key)
+}
+
+/// Create an armored detached signature over `data`.
+pub fn sign_detached(signer: &SecretKey, data: &[u8]) -> Result<String> {
+    let passphrase = signer.passphrase.clone();
+    PgpMessage::new_literal_bytes("", data)
+        .sign(&mut rand::thread_rng(), &signer.key, || passphrase, HashAlgorithm::SHA2_256)
+        .map_err(pgp_error)?
+        .into_signature()
+        .to_armored_string(ArmorOptions::default())
+        .map_err(pgp_error)
+}
+
+/// Check a detached signature against the sender's keys.
+///
+/// With no keys for the sender the result is `Unknown`; with keys that
+/// don't verify it, `Invalid`.
+pub fn verify_detached(
+    signature: &str,
+    data: &[u8],
+    sender_keys: &[SignedPublicKey],
+) -> (SignatureVerificationStatus, Option<String>) {
+    if sender_keys.is_empty() {
+        return (SignatureVerificationStatus::Unknown, None);
+    }
+    let Ok((signature, _)) = StandaloneSignature::from_armor_single(Cursor::new(signature.as_bytes())) else {
+        return (SignatureVerificationStatus::Invalid, None);
+    };
+    check_keys(sender_keys, |key| {
+        signature.verify(key, data).is_ok()
+            || key.public_subkeys.iter().any(|sub| signature.verify(sub, data).is_ok())
+    })
+}
+
+fn check_keys(
+    sender_keys: &[SignedPublicKey],
+    verifies: impl Fn(&SignedPublicKey) -> bool,
+) -> (SignatureVerificationStatus, Option<String>) {
+    if sender_keys.is_empty() {
+        return (SignatureVerificationStatus::Unknown, None);
+    }
+    match sender_keys.iter().find(|key| verifies(key)) {
+        Some(key) => (
+            SignatureVerificationStatus::Valid,
+            key.details.users.first().map(|u| u.id.id().to_string()),
+        ),
+        None => (SignatureVerificationStatus::Invalid, None),
+    }
+}
+
+/// Encrypt `data` to `recipients`, signing it first when `signer` is given.
+pub fn encrypt(data: &[u8], recipients: &[SignedPublicKey], signer: Option<&SecretKey>) -> Result<String> {
+    let mut subkeys = Vec::new();
+    for key in recipients {
+        let subkey = key.public_subkeys.iter().find(|sub| sub.is_encryption_key()).ok_or_else(|| {
+            Error::Security(format!("OpenPGP key {} cannot encrypt", fingerprint_hex(key)))
+        })?;
+        subkeys.push(subkey);
+    }
+    if subkeys.is_empty() {
+        return Err(Error::Security("No OpenPGP recipients to encrypt to".to_string()));
+    }
+    let mut rng = rand::thread_rng();
+    let mut message = PgpMessage::new_literal_bytes("", data);
+    if let Some(signer) = signer {
+        let passphrase = signer.passphrase.clone();
+        message = message
+            .sign(&mut rng, &signer.key, || passphrase, HashAlgorithm::SHA2_256)
+            .map_err(pgp_error)?;
+    }
+    message
+        .encrypt_to_keys_seipdv1(&mut rng, SymmetricKeyAlgorithm::AES256, &subkeys)
+        .map_err(pgp_error)?
+        .to_armored_string(ArmorOptions::default())
+        .map_err(pgp_error)
+}
+
+/// Decrypt an armored message, checking any signature inside it.
+pub fn decrypt(
+    armored: &str,
+    key: &SecretKey,
+    sender_keys: &[SignedPublicKey],
+) -> Result<(Vec<u8>, SignatureVerificationStatus, Option<String>)> {
+    let (message, _) = PgpMessage::from_armor_single(Cursor::new(armored.as_bytes())).map_err(pgp_error)?;
+    let passphrase = key.passphrase.clone();
+    let (message, _) = message.decrypt(|| passphrase, &[&key.key]).map_err(pgp_error)?;
+    let message = if message.is_compressed() {
+        message.decompress().map_err(pgp_error)?
+    } else {
+        message
+    };
+    let (status, signer) = if matches!(message, PgpMessage::Signed { .. }) {
+        check_keys(sender_keys, |k| {
+            message.verify(k).is_ok() || k.public_subkeys.iter().any(|sub| message.verify(sub).is_ok())
+        })
+    } else {
+        (SignatureVerificationStatus::NotSigned, None)
+    };
+    let content = message
+        .get_content()
+        .map_err(pgp_error)?
+        .ok_or_else(|| Error::Security("OpenPGP message has no content".to_string()))?;
+    Ok((content, status, signer))
+}
+
+/// Wrap a message body in `multipart/encrypted` or `multipart/signed` as
+/// `pgp` asks. The body itself is signed or encrypted, headers included.
+pub fn protect(pgp: &OutgoingPgp, body: MultiPart) -> Result<MultiPart> {
+    let content = canonical_line_endings(&String::from_utf8_lossy(&body.formatted()));
+    if !pgp.encrypt_to.is_empty() {
+        let armored = encrypt(content.as_bytes(), &pgp.encrypt_to, pgp.sign_with.as_ref())?;
+        return Ok(MultiPart::encrypted(ENCRYPTED_PROTOCOL.to_string())
+            .singlepart(
+                SinglePart::builder()
+                    .header(content_type(ENCRYPTED_PROTOCOL))
+                    .body("Version: 1\r\n".to_string()),
+            )
+            .singlepart(
+                SinglePart::builder()
+                    .header(content_type("application/octet-stream; name=\"encrypted.asc\""))
+                    .body(armored),
+            ));
+    }
+    let Some(signer) = &pgp.sign_with else {
+        return Ok(body);
+    };
+    // The line break before the next delimiter isn't part of the signed entity
+    let signed = content.strip_suffix("\r\n").unwrap_or(&content);
+    let signature = sign_detached(signer, signed.as_bytes())?;
+    Ok(MultiPart::signed(SIGNATURE_PROTOCOL.to_string(), MICALG.to_string())
+        .multipart(body)
+        .singlepart(
+            SinglePart::builder()
+                .header(content_type("application/pgp-signature; name=\"signature.asc\""))
+                .body(signature),
+        ))
+}
+
+fn content_type(value: &str) -> ContentType {
+    ContentType::parse(value).expect("valid content type")
+}
+
+/// Result of opening an incoming PGP/MIME message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncomingPgp {
+    pub encrypted: bool,
+    /// The decrypted or signed MIME entity, or `None` if it couldn't be
+    /// decrypted
+    pub content: Option<String>,
+    pub signature: SignatureVerificationStatus,
+    /// User ID of the key that made a valid signature
+    pub signer: Option<String>,
+    pub error: Option<String>,
+}
+
+impl IncomingPgp {
+    /// Notes for the reader about encryption and signature validity.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if let Some(error) = &self.error {
+            warnings.push(format!("This message could not be decrypted: {}", error));
+        } else if self.encrypted {
+            warnings.push("This message was encrypted with OpenPGP.".to_string());
+        }
+        match self.signature {
+            SignatureVerificationStatus::Valid => warnings.push(format!(
+                "Valid OpenPGP signature from {}.",
+                self.signer.as_deref().unwrap_or("the sender")
+            )),
+            SignatureVerificationStatus::Invalid => {
+                warnings.push("The OpenPGP signature is not valid. The message may have been altered.".to_string())
+            }
+            SignatureVerificationStatus::Unknown => warnings.push(
+                "This message is signed, but the sender's OpenPGP key has not been imported.".to_string(),
+            ),
+            SignatureVerificationStatus::NotSigned => {}
+        }
+        warnings
+    }
+}
+
+/// Decrypt and verify a raw PGP/MIME message.
+///
+/// `secret_keys` are tried in turn for decryption; `sender_keys` are the
+/// sender's public keys. Returns `None` for messages that aren't PGP/MIME.
+pub fn open(raw: &str, secret_keys: &[SecretKey], sender_keys: &[SignedPublicKey]) -> Option<IncomingPgp> {
+    let structure = SecuredMultipart::detect(raw)?;
+    match (structure.signed, structure.protocol.as_str()) {
+        (true, SIGNATURE_PROTOCOL) => {
+            let parts = split_parts(raw, &structure.boundary);
+            let (signed, signature) = (parts.first()?, parts.get(1)?);
+            let (status, signer) =
+                verify_detached(part_body(signature), canonical_line_endings(signed).as_bytes(), sender_keys);
+            Some(IncomingPgp {
+                encrypted: false,
+                content: Some(signed.to_string()),
+                signature: status,
+                signer,
+                error: None,
+            })
+        }
+        (false, ENCRYPTED_PROTOCOL) => {
+            let parts = split_parts(raw, &structure.boundary);
+            let armored = part_body(parts.get(1)?);
+            let mut last_error = "no secret key for this message".to_string();
+            for key in secret_keys {
+                match decrypt(armored, key, sender_keys) {
+                    Ok((content, status, signer)) => {
+                        let content = String::from_utf8_lossy(&content).into_owned();
+                        // Signed then encrypted as nested MIME (RFC 3156 §6.1)
+                        if status == SignatureVerificationStatus::NotSigned {
+                            if let Some(inner) = open(&content, &[], sender_keys) {
+                                return Some(IncomingPgp { encrypted: true, ..inner });
+                            }
+                        }
+                        return Some(IncomingPgp {
+                            encrypted: true,
+                            content: Some(content),
+                            signature: status,
+                            signer,
+                            error: None,
+                        });
+                    }
+                    Err(e) => last_error = e.to_string(),
+                }
+            }
+            Some(IncomingPgp {
+                encrypted: true,
+                content: None,
+                signature: SignatureVerificationStatus::NotSigned,
+                signer: None,
+                error: Some(last_error),
+            })
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A throwaway RSA key made with gpg for these tests only: a signing
+    // primary key and an encryption subkey, no passphrase.
+    const ALICE_PUBLIC: &str = include_str!("../../tests/fixtures/openpgp/alice_public.asc");
+    const ALICE_SECRET: &str = include_str!("../../tests/fixtures/openpgp/alice_secret.asc");
+
+    fn alice() -> (SecretKey, SignedPublicKey) {
+        let secret = SecretKey { key: parse_secret_key(ALICE_SECRET).unwrap(), passphrase: String::new() };
+        (secret, parse_public_key(ALICE_PUBLIC).unwrap())
+    }
+
+    #[test]
+    fn test_fixture_key_info() {
+        let (secret, public) = alice();
+        let info = PgpKeyInfo::of_public(&public);
+        assert!(info.matches_email("alice@example.com"));
+        assert!(!info.secret);
+        assert_eq!(PgpKeyInfo::of_secret(&secret.key).fingerprint, info.fingerprint);
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let (secret, public) = alice();
+        let data = b"Content-Type: text/plain\r\n\r\nHello Bob\r\n";
+        let signature = sign_detached(&secret, data).unwrap();
+
+        let (status, signer) = verify_detached(&signature, data, std::slice::from_ref(&public));
+        assert_eq!(status, SignatureVerificationStatus::Valid);
+        assert_eq!(signer.as_deref(), Some("Alice Test <alice@example.com>"));
+        assert_eq!(verify_detached(&signature, data, &[]).0, SignatureVerificationStatus::Unknown);
+    }
+
+    #[test]
+    fn test_tampered_signature_is_invalid() {
+        let (secret, public) = alice();
+        let data = b"Pay Bob 10 euros";
+        let signature = sign_detached(&secret, data).unwrap();
+        let keys = std::slice::from_ref(&public);
+
+        // The signed text changed after signing
+        assert_eq!(verify_detached(&signature, b"Pay Bob 99 euros", keys).0, SignatureVerificationStatus::Invalid);
+
+        // The signature itself was altered: the last armored line holds the
+        // end of the RSA signature value
+        let mut lines: Vec<String> = signature.lines().map(str::to_string).collect();
+        let last = lines.iter().rposition(|l| !l.starts_with('=') && !l.starts_with("-----")).unwrap();
+        let flipped = if lines[last].starts_with('A') { "B" } else { "A" };
+        lines[last].replace_range(..1, flipped);
+        let (status, signer) = verify_detached(&lines.join("\n"), data, keys);
+        assert_eq!(status, SignatureVerificationStatus::Invalid);
+        assert_eq!(signer, None);
+    }
+
+    #[test]
+    fn test_encrypt_and_decrypt() {
+        let (secret, public) = alice();
+        let data = b"Meet at noon";
+        let keys = std::slice::from_ref(&public);
+
+        let armored = encrypt(data, keys, None).unwrap();
+        assert!(!armored.contains("Meet at noon"));
+        let (content, status, _) = decrypt(&armored, &secret, keys).unwrap();
+        assert_eq!(content, data);
+        assert_eq!(status, SignatureVerificationStatus::NotSigned);
+
+        // Signed inside the encryption
+        let armored = encrypt(data, keys, Some(&secret)).unwrap();
+        let (content, status, signer) = decrypt(&armored, &secret, keys).unwrap();
+        assert_eq!(content, data);
+        assert_eq!(status, SignatureVerificationStatus::Valid);
+        assert_eq!(signer.as_deref(), Some("Alice Test <alice@example.com>"));
+        assert_eq!(decrypt(&armored, &secret, &[]).unwrap().1, SignatureVerificationStatus::Unknown);
+
+        assert!(encrypt(data, &[], None).is_err());
+    }
+
+    #[test]
+    fn test_key_info_matches_email() {
+        let info = PgpKeyInfo {
+            fingerprint: "ABCD".to_string(),
+            user_ids: vec!["Alice <Alice@Example.com>".to_string()],
+            secret: false,
+        };
+        assert!(info.matches_email("alice@example.com"));
+        assert!(!info.matches_email("bob@example.com"));
+        assert_eq!(user_id_email("bob@example.com"), "bob@example.com");
+    }
+
+    #[test]
+    fn test_warnings() {
+        let incoming = IncomingPgp {
+            encrypted: true,
+            content: None,
+            signature: SignatureVerificationStatus::Unknown,
+            signer: None,
+            error: None,
+        };
+        assert_eq!(incoming.warnings(), vec![
+            "This message was encrypted with OpenPGP.".to_string(),
+            "This message is signed, but the sender's OpenPGP key has not been imported.".to_string(),
+        ]);
+    }
+}
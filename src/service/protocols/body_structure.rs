@@ -0,0 +1,348 @@
+//! IMAP `BODYSTRUCTURE` parsing
+//!
+//! The server describes a message's MIME parts (type, size, encoding,
+//! filename) without sending them, so the client can choose which parts
+//! to download (RFC 3501 section 7.4.2).
+
+use crate::common::{Error, Result};
+use std::collections::HashMap;
+
+/// One leaf part of a message: something with content rather than a
+/// `multipart/*` container. A forwarded `message/rfc822` is one part.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BodyPart {
+    /// Section number to fetch it by, such as `1.2`; `1` for a message
+    /// that isn't multipart
+    pub section: String,
+    /// Lower-cased, such as `text/plain`
+    pub mime_type: String,
+    pub charset: Option<String>,
+    /// `name` parameter of the content type
+    pub name: Option<String>,
+    /// `Content-ID` without its angle brackets
+    pub content_id: Option<String>,
+    /// Lower-cased `Content-Transfer-Encoding`, such as `base64`
+    pub encoding: String,
+    /// Size in bytes as sent, still in its transfer encoding
+    pub size: u64,
+    /// Lower-cased `Content-Disposition`, such as `attachment`
+    pub disposition: Option<String>,
+    /// `filename` parameter of the disposition
+    pub filename: Option<String>,
+}
+
+impl BodyPart {
+    /// Whether the part is a file rather than text to read: marked as an
+    /// attachment, or named and not marked inline
+    pub fn is_attachment(&self) -> bool {
+        match self.disposition.as_deref() {
+            Some("attachment") => true,
+            Some("inline") => false,
+            _ => self.filename.is_some() || self.name.is_some() || !self.mime_type.starts_with("text/"),
+        }
+    }
+
+    /// Name to show for the part: its filename, or a made-up one for parts
+    /// sent without
+    pub fn display_name(&self) -> String {
+        match self.filename.as_ref().or(self.name.as_ref()) {
+            Some(name) => name.clone(),
+            None if self.mime_type.starts_with("message/") => "Forwarded message.eml".to_string(),
+            None => format!("Attachment {}", self.section),
+        }
+    }
+}
+
+/// The parts of a message, in the order the server lists them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BodyStructure {
+    pub parts: Vec<BodyPart>,
+}
+
+impl BodyStructure {
+    /// Size in bytes of all the parts, near the size of the whole message
+    pub fn size(&self) -> u64 {
+        self.parts.iter().map(|p| p.size).sum()
+    }
+
+    /// The part to show before the whole message is downloaded: the plain
+    /// text, or the HTML when there is no plain text
+    pub fn text_part(&self) -> Option<&BodyPart> {
+        let text = |mime_type: &str| self.parts.iter().find(|p| p.mime_type == mime_type && !p.is_attachment());
+        text("text/plain").or_else(|| text("text/html"))
+    }
+
+    /// The parts that are files to open or save, not text to read or
+    /// images shown within the HTML
+    pub fn attachments(&self) -> Vec<&BodyPart> {
+        self.parts
+            .iter()
+            .filter(|p| p.is_attachment() && !(p.content_id.is_some() && p.disposition.is_none() && p.mime_type.starts_with("image/")))
+            .collect()
+    }
+}
+
+/// Parse a `BODYSTRUCTURE` from a FETCH response, or the parenthesised
+/// structure on its own
+pub fn parse(response: &str) -> Result<BodyStructure> {
+    let start = response
+        .to_ascii_uppercase()
+        .find("BODYSTRUCTURE")
+        .map(|at| at + "BODYSTRUCTURE".len())
+        .unwrap_or(0);
+    let mut reader = Reader { bytes: response.as_bytes(), pos: start };
+    let Item::List(items) = reader.item()? else {
+        return Err(invalid("expected a parenthesised list"));
+    };
+    let mut parts = Vec::new();
+    collect_parts(&items, "", &mut parts)?;
+    Ok(BodyStructure { parts })
+}
+
+/// Add the leaf parts of `body` to `parts`; `prefix` is the section of
+/// the multipart containing it, if any
+fn collect_parts(body: &[Item], prefix: &str, parts: &mut Vec<BodyPart>) -> Result<()> {
+    let section = |n: usize| if prefix.is_empty() { n.to_string() } else { format!("{}.{}", prefix, n) };
+    if matches!(body.first(), Some(Item::List(_))) {
+        for (i, child) in body.iter().take_while(|item| matches!(item, Item::List(_))).enumerate() {
+            let Item::List(child) = child else { unreachable!() };
+            if matches!(child.first(), Some(Item::List(_))) {
+                collect_parts(child, &section(i + 1), parts)?;
+            } else {
+                parts.push(single_part(child, section(i + 1))?);
+            }
+        }
+        return Ok(());
+    }
+    // The only part of a message that isn't multipart is section 1
+    parts.push(single_part(body, if prefix.is_empty() { "1".to_string() } else { prefix.to_string() })?);
+    Ok(())
+}
+
+fn single_part(fields: &[Item], section: String) -> Result<BodyPart> {
+    let text = |i: usize| fields.get(i).and_then(Item::text);
+    let (Some(kind), Some(subtype)) = (text(0), text(1)) else {
+        return Err(invalid("a part has no content type"));
+    };
+    let mime_type = format!("{}/{}", kind, subtype).to_ascii_lowercase();
+    let mut params = fields.get(2).map(Item::params).unwrap_or_default();
+    let size = match fields.get(6) {
+        Some(Item::Number(size)) => *size,
+        _ => return Err(invalid("a part has no size")),
+    };
+    // Extension fields come after the line count of text parts, and after
+    // the envelope, structure and line count of attached messages
+    let disposition_at = match mime_type.as_str() {
+        "message/rfc822" | "message/global" => 11,
+        m if m.starts_with("text/") => 9,
+        _ => 8,
+    };
+    let (disposition, filename) = match fields.get(disposition_at) {
+        Some(Item::List(disposition)) => (
+            disposition.first().and_then(Item::text).map(|d| d.to_ascii_lowercase()),
+            disposition.get(1).map(Item::params).unwrap_or_default().remove("filename"),
+        ),
+        _ => (None, None),
+    };
+    Ok(BodyPart {
+        section,
+        mime_type,
+        charset: params.remove("charset"),
+        name: params.remove("name"),
+        content_id: text(3).map(|id| id.trim().trim_start_matches('<').trim_end_matches('>').to_string()),
+        encoding: text(5).unwrap_or("7bit").to_ascii_lowercase(),
+        size,
+        disposition,
+        filename,
+    })
+}
+
+fn invalid(problem: &str) -> Error {
+    Error::Parse(format!("Invalid BODYSTRUCTURE: {}", problem))
+}
+
+/// A parenthesised list, string, number or NIL of an IMAP response
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Item {
+    List(Vec<Item>),
+    Text(String),
+    Number(u64),
+    Nil,
+}
+
+impl Item {
+    fn text(&self) -> Option<&str> {
+        match self {
+            Item::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// A `("NAME" "value" ...)` parameter list, with lower-cased names
+    fn params(&self) -> HashMap<String, String> {
+        let Item::List(items) = self else {
+            return Default::default();
+        };
+        items
+            .chunks(2)
+            .filter_map(|pair| match pair {
+                [Item::Text(name), Item::Text(value)] => Some((name.to_ascii_lowercase(), value.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn item(&mut self) -> Result<Item> {
+        while self.bytes.get(self.pos).is_some_and(|b| b.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+        match self.bytes.get(self.pos) {
+            None => Err(invalid("the response ended early")),
+            Some(b'(') => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                loop {
+                    while self.bytes.get(self.pos).is_some_and(|b| b.is_ascii_whitespace()) {
+                        self.pos += 1;
+                    }
+                    if self.bytes.get(self.pos) == Some(&b')') {
+                        self.pos += 1;
+                        return Ok(Item::List(items));
+                    }
+                    items.push(self.item()?);
+                }
+            }
+            Some(b'"') => {
+                self.pos += 1;
+                let mut text = Vec::new();
+                loop {
+                    match self.bytes.get(self.pos) {
+                        None => return Err(invalid("a string is not closed")),
+                        Some(b'"') => break,
+                        Some(b'\\') => {
+                            self.pos += 1;
+                            text.extend(self.bytes.get(self.pos));
+                        }
+                        Some(&b) => text.push(b),
+                    }
+                    self.pos += 1;
+                }
+                self.pos += 1;
+                Ok(Item::Text(String::from_utf8_lossy(&text).into_owned()))
+            }
+            // A literal: {length} then a line break and that many bytes
+            Some(b'{') => {
+                let close = self.bytes[self.pos..]
+                    .iter()
+                    .position(|&b| b == b'}')
+                    .ok_or_else(|| invalid("a literal has no length"))?;
+                let length: usize = std::str::from_utf8(&self.bytes[self.pos + 1..self.pos + close])
+                    .ok()
+                    .and_then(|n| n.parse().ok())
+                    .ok_or_else(|| invalid("a literal has no length"))?;
+                let mut start = self.pos + close + 1;
+                if self.bytes.get(start) == Some(&b'\r') {
+                    start += 1;
+                }
+                if self.bytes.get(start) == Some(&b'\n') {
+                    start += 1;
+                }
+                let end = start + length;
+                let text = self.bytes.get(start..end).ok_or_else(|| invalid("a literal is cut short"))?;
+                self.pos = end;
+                Ok(Item::Text(String::from_utf8_lossy(text).into_owned()))
+            }
+            Some(_) => {
+                let start = self.pos;
+                while self.bytes.get(self.pos).is_some_and(|&b| !b.is_ascii_whitespace() && b != b'(' && b != b')') {
+                    self.pos += 1;
+                }
+                let atom = String::from_utf8_lossy(&self.bytes[start..self.pos]).into_owned();
+                Ok(if atom.eq_ignore_ascii_case("NIL") {
+                    Item::Nil
+                } else if let Ok(number) = atom.parse() {
+                    Item::Number(number)
+                } else {
+                    Item::Text(atom)
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_single_part() {
+        let structure = parse(
+            "* 12 FETCH (UID 7 BODYSTRUCTURE (\"TEXT\" \"PLAIN\" (\"CHARSET\" \"ISO-8859-1\") NIL NIL \"QUOTED-PRINTABLE\" 1315 42 NIL NIL NIL))",
+        )
+        .unwrap();
+        assert_eq!(structure.parts.len(), 1);
+        let part = &structure.parts[0];
+        assert_eq!(part.section, "1");
+        assert_eq!(part.mime_type, "text/plain");
+        assert_eq!(part.charset.as_deref(), Some("ISO-8859-1"));
+        assert_eq!(part.encoding, "quoted-printable");
+        assert_eq!(part.size, 1315);
+        assert!(!part.is_attachment());
+        assert_eq!(structure.text_part(), Some(part));
+    }
+
+    #[test]
+    fn test_parse_nested_multipart() {
+        // A message with plain and HTML versions, an inline image for the
+        // HTML, a PDF and a forwarded message
+        let structure = parse(concat!(
+            "(((\"text\" \"plain\" (\"charset\" \"utf-8\") NIL NIL \"7bit\" 120 4 NIL NIL NIL NIL)",
+            "(\"text\" \"html\" (\"charset\" \"utf-8\") NIL NIL \"quoted-printable\" 3400 60 NIL NIL NIL NIL)",
+            " \"alternative\" (\"boundary\" \"b2\") NIL NIL)",
+            "(\"image\" \"png\" (\"name\" \"logo.png\") \"<logo@example.com>\" NIL \"base64\" 5000 NIL (\"inline\" NIL) NIL NIL)",
+            "(\"application\" \"pdf\" (\"name\" \"report.pdf\") NIL NIL \"base64\" 2000000 NIL",
+            " (\"attachment\" (\"filename\" {14}\r\nQ3 \"final\".pdf)) NIL NIL)",
+            "(\"message\" \"rfc822\" NIL NIL NIL \"7bit\" 900 (NIL \"Fwd\" NIL NIL NIL NIL NIL NIL NIL NIL)",
+            " (\"text\" \"plain\" NIL NIL NIL \"7bit\" 100 2 NIL NIL NIL NIL) 20 NIL NIL NIL NIL)",
+            " \"mixed\" (\"boundary\" \"b1\") NIL NIL)",
+        ))
+        .unwrap();
+        let sections: Vec<&str> = structure.parts.iter().map(|p| p.section.as_str()).collect();
+        assert_eq!(sections, ["1.1", "1.2", "2", "3", "4"]);
+        assert_eq!(structure.size(), 120 + 3400 + 5000 + 2000000 + 900);
+        assert_eq!(structure.text_part().unwrap().section, "1.1");
+
+        let image = &structure.parts[2];
+        assert_eq!(image.content_id.as_deref(), Some("logo@example.com"));
+        assert_eq!(image.disposition.as_deref(), Some("inline"));
+        assert!(!image.is_attachment());
+        let pdf = &structure.parts[3];
+        assert_eq!(pdf.filename.as_deref(), Some("Q3 \"final\".pdf"));
+        assert!(pdf.is_attachment());
+        let forwarded = &structure.parts[4];
+        assert_eq!(forwarded.mime_type, "message/rfc822");
+        assert!(forwarded.is_attachment());
+
+        let names: Vec<String> = structure.attachments().iter().map(|p| p.display_name()).collect();
+        assert_eq!(names, ["Q3 \"final\".pdf", "Forwarded message.eml"]);
+    }
+
+    #[test]
+    fn test_text_part_falls_back_to_html() {
+        let structure = parse(
+            "((\"TEXT\" \"HTML\" NIL NIL NIL \"BASE64\" 800 10 NIL NIL NIL NIL)\
+             (\"TEXT\" \"PLAIN\" (\"NAME\" \"notes.txt\") NIL NIL \"7BIT\" 50 2 NIL (\"ATTACHMENT\" NIL) NIL NIL) \"MIXED\")",
+        )
+        .unwrap();
+        assert_eq!(structure.text_part().unwrap().mime_type, "text/html");
+        assert!(parse("(\"TEXT\" \"PLAIN\" NIL").is_err());
+        assert!(parse("* 1 FETCH (UID 1)").is_err());
+    }
+}
@@ -0,0 +1,122 @@
+//! Certificate pinning (trust on first use)
+//!
+//! With pinning on, the SHA-256 fingerprint of a server's TLS certificate
+//! is remembered the first time a connection succeeds. Later connections
+//! are refused if the server presents a different certificate, which on a
+//! hostile network may mean someone is intercepting the connection.
+
+use super::network::{self, NetworkTimeouts};
+use crate::common::{Error, Result};
+use sha2::{Digest, Sha256};
+use tokio::net::TcpStream;
+
+/// Colon-separated upper-case hex SHA-256 of a DER certificate, as shown
+/// by browsers and `openssl x509 -fingerprint -sha256`
+pub fn fingerprint(der: &[u8]) -> String {
+    Sha256::digest(der)
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Fingerprint of the certificate `server` presents on an implicit-TLS
+/// `port`. The certificate is read even if it isn't trusted, so a changed
+/// certificate can be shown to the user; trust is decided by the
+/// connection itself and by [`check_pin`].
+pub async fn server_fingerprint(server: &str, port: u16, timeouts: NetworkTimeouts) -> Result<String> {
+    let what = format!("reading the certificate of {}", server);
+    network::with_timeout(timeouts.connect, &what, async {
+        let tcp = TcpStream::connect((server, port)).await.map_err(|e| network::io_error(server, &e))?;
+        let connector = native_tls::TlsConnector::builder()
+            .danger_accept_invalid_certs(true)
+            .danger_accept_invalid_hostnames(true)
+            .build()
+            .map_err(|e| Error::Security(format!("Failed to set up TLS: {}", e)))?;
+        let tls = tokio_native_tls::TlsConnector::from(connector)
+            .connect(server, tcp)
+            .await
+            .map_err(|e| Error::Certificate(format!("Secure connection to {} failed: {}", server, e)))?;
+        let certificate = tls
+            .get_ref()
+            .peer_certificate()
+            .map_err(|e| Error::Certificate(format!("{}: {}", server, e)))?
+            .ok_or_else(|| Error::Certificate(format!("{} did not present a certificate", server)))?;
+        let der = certificate
+            .to_der()
+            .map_err(|e| Error::Certificate(format!("{}: {}", server, e)))?;
+        Ok(fingerprint(&der))
+    })
+    .await
+}
+
+/// A server presented a certificate that doesn't match the one pinned
+/// for the account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertificateChange {
+    pub account_id: String,
+    pub server: String,
+    pub pinned: String,
+    pub presented: String,
+}
+
+/// After a connection was refused for security reasons, whether `server`
+/// now presents a certificate other than the `pinned` one
+pub async fn changed_certificate(
+    account_id: &str,
+    server: &str,
+    port: u16,
+    timeouts: NetworkTimeouts,
+    pinned: &str,
+) -> Option<CertificateChange> {
+    let presented = server_fingerprint(server, port, timeouts).await.ok()?;
+    check_pin(server, pinned, &presented).is_err().then(|| CertificateChange {
+        account_id: account_id.to_string(),
+        server: server.to_string(),
+        pinned: pinned.to_string(),
+        presented,
+    })
+}
+
+/// Refuse a certificate that doesn't match the one pinned for `server`
+pub fn check_pin(server: &str, pinned: &str, presented: &str) -> Result<()> {
+    if pinned.eq_ignore_ascii_case(presented.trim()) {
+        return Ok(());
+    }
+    Err(Error::Security(format!(
+        "The certificate for {} has changed since it was pinned (expected {}, got {}). \
+         Connect only if your provider has renewed its certificate.",
+        server, pinned, presented
+    )))
+}
+
+/// Check `server` against its pinned fingerprint before connecting.
+/// Nothing is checked, and no connection made, when nothing is pinned.
+pub async fn verify_pinned(server: &str, port: u16, timeouts: NetworkTimeouts, pinned: Option<&str>) -> Result<()> {
+    let Some(pinned) = pinned else { return Ok(()) };
+    let presented = server_fingerprint(server, port, timeouts).await?;
+    check_pin(server, pinned, &presented)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_and_pin_check() {
+        let print = fingerprint(b"abc");
+        assert_eq!(print.len(), 32 * 3 - 1);
+        assert!(print.starts_with("BA:78:16:BF:") && print.ends_with(":15:AD"));
+        assert_ne!(print, fingerprint(b"renewed certificate"));
+
+        assert!(check_pin("imap.example.com", &print, &print.to_lowercase()).is_ok());
+        let changed = check_pin("imap.example.com", &print, &fingerprint(b"other")).unwrap_err();
+        assert!(matches!(&changed, Error::Security(m) if m.contains("imap.example.com") && m.contains(&print)));
+    }
+
+    #[tokio::test]
+    async fn test_nothing_pinned_skips_check() {
+        let timeouts = NetworkTimeouts::default();
+        assert!(verify_pinned("unreachable.invalid", 993, timeouts, None).await.is_ok());
+    }
+}
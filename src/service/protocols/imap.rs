@@ -2,7 +2,11 @@
 //!
 //! Handles IMAP4rev1 protocol for receiving email.
 
+use super::body_structure::{self, BodyStructure};
+use super::certificate_pin;
+use super::network::{self, NetworkTimeouts};
 use crate::common::Result;
+use std::collections::{HashMap, HashSet};
 use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
@@ -17,6 +21,10 @@ pub struct ImapConfig {
     pub port: u16,
     pub use_tls: bool,
     pub username: String,
+    pub timeouts: NetworkTimeouts,
+    /// Refuse servers whose certificate doesn't have this SHA-256
+    /// fingerprint (see `certificate_pin`)
+    pub pinned_fingerprint: Option<String>,
 }
 
 /// IMAP folder information
@@ -34,7 +42,86 @@ pub struct ImapMessage {
     pub subject: String,
     pub from: String,
     pub date: String,
+    /// `Message-ID` from the envelope, without angle brackets; empty when
+    /// the message has none
+    pub message_id: String,
     pub flags: Vec<String>,
+    /// Mailing list from the `List-Id` header, fetched with the headers
+    pub list_id: Option<String>,
+    /// MIME parts, fetched with the headers (`BODYSTRUCTURE`)
+    pub body_structure: Option<BodyStructure>,
+}
+
+/// Mailbox usage and limits from a `QUOTA` response (RFC 9208).
+/// `None` where the server doesn't report a resource.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImapQuota {
+    pub storage_used_bytes: Option<u64>,
+    pub storage_limit_bytes: Option<u64>,
+    pub messages_used: Option<u64>,
+    pub messages_limit: Option<u64>,
+}
+
+/// Parse the untagged responses to `GETQUOTAROOT`, e.g.
+/// `* QUOTA "" (STORAGE 10 512 MESSAGE 3 1000)`. STORAGE is counted in
+/// units of 1024 octets. With several quota roots, the first to report a
+/// resource wins. `None` if no QUOTA line has usable resources.
+pub fn parse_quota_response(response: &str) -> Option<ImapQuota> {
+    let mut quota = ImapQuota::default();
+    let mut found = false;
+    for line in response.lines() {
+        let Some(rest) = line.trim().strip_prefix("* QUOTA ") else { continue };
+        let Some(list) = rest.rfind('(').map(|start| rest[start + 1..].trim_end_matches(')')) else { continue };
+        let tokens: Vec<&str> = list.split_whitespace().collect();
+        for resource in tokens.chunks_exact(3) {
+            let (Ok(used), Ok(limit)) = (resource[1].parse::<u64>(), resource[2].parse::<u64>()) else { continue };
+            let (used_slot, limit_slot, unit) = match resource[0].to_ascii_uppercase().as_str() {
+                "STORAGE" => (&mut quota.storage_used_bytes, &mut quota.storage_limit_bytes, 1024),
+                "MESSAGE" => (&mut quota.messages_used, &mut quota.messages_limit, 1),
+                _ => continue,
+            };
+            if used_slot.is_none() {
+                *used_slot = Some(used * unit);
+                *limit_slot = Some(limit * unit);
+                found = true;
+            }
+        }
+    }
+    found.then_some(quota)
+}
+
+/// Parse the `COPYUID` response code a UIDPLUS server (RFC 4315) sends
+/// after `UID COPY` or `UID MOVE`, e.g.
+/// `* OK [COPYUID 38505 304,319:320 3956:3958] Done`, into a map from each
+/// source UID to the UID the message got in the destination folder. Empty
+/// when there is no COPYUID or its UID sets don't match up.
+pub fn parse_copyuid(response: &str) -> HashMap<u32, u32> {
+    let Some(start) = response.find("[COPYUID ") else { return HashMap::new() };
+    let rest = &response[start + "[COPYUID ".len()..];
+    let Some(end) = rest.find(']') else { return HashMap::new() };
+    let fields: Vec<&str> = rest[..end].split_whitespace().collect();
+    let [_validity, source, destination] = fields[..] else { return HashMap::new() };
+    match (expand_uid_set(source), expand_uid_set(destination)) {
+        (Some(source), Some(destination)) if source.len() == destination.len() => {
+            source.into_iter().zip(destination).collect()
+        }
+        _ => HashMap::new(),
+    }
+}
+
+/// Expand an IMAP UID set such as `304,319:320` into its UIDs, in order.
+fn expand_uid_set(set: &str) -> Option<Vec<u32>> {
+    let mut uids = Vec::new();
+    for part in set.split(',') {
+        match part.split_once(':') {
+            Some((a, b)) => {
+                let (a, b): (u32, u32) = (a.parse().ok()?, b.parse().ok()?);
+                uids.extend(a.min(b)..=a.max(b));
+            }
+            None => uids.push(part.parse().ok()?),
+        }
+    }
+    Some(uids)
 }
 
 /// IMAP IDLE event emitted by push notification loop.
@@ -82,6 +169,31 @@ impl ImapIdleHandle {
     }
 }
 
+/// Format UIDs as a compact IMAP sequence set, e.g. `[1, 2, 3, 7]` → `"1:3,7"`.
+pub fn uid_set(uids: &[u32]) -> String {
+    let mut sorted = uids.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+
+    let mut parts = Vec::new();
+    let mut i = 0;
+    while i < sorted.len() {
+        let start = sorted[i];
+        let mut end = start;
+        while i + 1 < sorted.len() && sorted[i + 1] == end + 1 {
+            i += 1;
+            end = sorted[i];
+        }
+        if start == end {
+            parts.push(start.to_string());
+        } else {
+            parts.push(format!("{}:{}", start, end));
+        }
+        i += 1;
+    }
+    parts.join(",")
+}
+
 /// IMAP client (placeholder for full async implementation)
 ///
 /// Note: This is a placeholder implementation. Full IMAP support will be added
@@ -104,13 +216,30 @@ impl ImapClient {
             self.config.port
         );
 
+        if self.config.use_tls {
+            certificate_pin::verify_pinned(
+                &self.config.server,
+                self.config.port,
+                self.config.timeouts,
+                self.config.pinned_fingerprint.as_deref(),
+            )
+            .await?;
+        }
+
         // TODO: Implement actual IMAP connection using a mature async library
-        // For now, return a placeholder session
-        Ok(ImapSession {
-            config: self.config.clone(),
-            selected_folder: None,
-            next_mock_uid: Arc::new(AtomicU32::new(1)),
+        // For now, return a placeholder session. Socket errors from the real
+        // connection go through `network::io_error`.
+        network::connect_with_retry(self.config.timeouts, &self.config.server, || async {
+            Ok(ImapSession {
+                config: self.config.clone(),
+                selected_folder: None,
+                next_mock_uid: Arc::new(AtomicU32::new(1)),
+                unsubscribed: HashSet::new(),
+                uid_next: HashMap::new(),
+                moved_message_ids: HashMap::new(),
+            })
         })
+        .await
     }
 }
 
@@ -120,6 +249,17 @@ pub struct ImapSession {
     config: ImapConfig,
     selected_folder: Option<String>,
     next_mock_uid: Arc<AtomicU32>,
+    /// Folders taken off the mock subscription list
+    unsubscribed: HashSet<String>,
+    /// Next UID the mock hands out per destination folder
+    uid_next: HashMap<String, u32>,
+    /// Message-IDs of messages the mock moved, by folder and new UID
+    moved_message_ids: HashMap<(String, u32), String>,
+}
+
+/// Message-ID the mock gives the message with `uid` in `folder`
+fn mock_message_id(folder: &str, uid: u32) -> String {
+    format!("{}.{}@mock.example.com", uid, folder)
 }
 
 impl ImapSession {
@@ -144,9 +284,58 @@ impl ImapSession {
                 delimiter: "/".to_string(),
                 flags: vec!["\\Drafts".to_string(), "\\HasNoChildren".to_string()],
             },
+            ImapFolder {
+                name: "Trash".to_string(),
+                delimiter: "/".to_string(),
+                flags: vec!["\\Trash".to_string(), "\\HasNoChildren".to_string()],
+            },
+            ImapFolder {
+                name: "Archive".to_string(),
+                delimiter: "/".to_string(),
+                flags: vec!["\\Archive".to_string(), "\\HasNoChildren".to_string()],
+            },
         ])
     }
 
+    /// Folders on the subscription list, from `LSUB` (placeholder)
+    pub async fn list_subscribed(&mut self) -> Result<Vec<ImapFolder>> {
+        tracing::debug!("LSUB \"\" \"*\" (placeholder)");
+        let folders = self.list_folders().await?;
+        Ok(folders.into_iter().filter(|f| !self.unsubscribed.contains(&f.name)).collect())
+    }
+
+    /// Add a folder to the subscription list (placeholder)
+    pub async fn subscribe(&mut self, folder: &str) -> Result<()> {
+        tracing::debug!("SUBSCRIBE {} (placeholder)", folder);
+        self.unsubscribed.remove(folder);
+        Ok(())
+    }
+
+    /// Take a folder off the subscription list (placeholder)
+    pub async fn unsubscribe(&mut self, folder: &str) -> Result<()> {
+        tracing::debug!("UNSUBSCRIBE {} (placeholder)", folder);
+        self.unsubscribed.insert(folder.to_string());
+        Ok(())
+    }
+
+    /// Create a folder (placeholder)
+    pub async fn create_folder(&mut self, folder: &str) -> Result<()> {
+        tracing::debug!("CREATE {} (placeholder)", folder);
+        Ok(())
+    }
+
+    /// Rename a folder; its subfolders move with it (placeholder)
+    pub async fn rename_folder(&mut self, folder: &str, new_name: &str) -> Result<()> {
+        tracing::debug!("RENAME {} {} (placeholder)", folder, new_name);
+        Ok(())
+    }
+
+    /// Delete a folder and the messages in it (placeholder)
+    pub async fn delete_folder(&mut self, folder: &str) -> Result<()> {
+        tracing::debug!("DELETE {} (placeholder)", folder);
+        Ok(())
+    }
+
     /// Select a folder (placeholder)
     pub async fn select_folder(&mut self, folder: &str) -> Result<()> {
         tracing::debug!("Selecting IMAP folder: {} (placeholder)", folder);
@@ -177,7 +366,10 @@ impl ImapSession {
                 subject: format!("Test Message {}", uid),
                 from: "test@example.com".to_string(),
                 date: "Mon, 10 Jan 2022 10:00:00 +0000".to_string(),
+                message_id: mock_message_id(self.selected_folder.as_deref().unwrap_or("INBOX"), *uid),
                 flags: vec!["\\Seen".to_string()],
+                list_id: None,
+                body_structure: None,
             })
             .collect();
 
@@ -191,6 +383,38 @@ impl ImapSession {
         Ok(format!("From: test@example.com\r\nTo: recipient@example.com\r\nSubject: Test Message {}\r\n\r\nThis is a test message body.", uid))
     }
 
+    /// Fetch the complete raw message, headers and MIME included
+    /// (`UID FETCH <uid> BODY.PEEK[]`; placeholder)
+    pub async fn fetch_raw_message(&mut self, folder: &str, uid: u32) -> Result<Vec<u8>> {
+        tracing::debug!("UID FETCH {} BODY.PEEK[] in folder: {} (placeholder)", uid, folder);
+
+        Ok(self.fetch_message_body(folder, uid).await?.into_bytes())
+    }
+
+    /// The MIME parts of a message, without their content
+    /// (`UID FETCH <uid> BODYSTRUCTURE`; placeholder)
+    pub async fn fetch_body_structure(&mut self, folder: &str, uid: u32) -> Result<BodyStructure> {
+        tracing::debug!("UID FETCH {} BODYSTRUCTURE in folder: {} (placeholder)", uid, folder);
+
+        let body = self.fetch_body_part(folder, uid, "1").await?;
+        body_structure::parse(&format!(
+            "* 1 FETCH (UID {} BODYSTRUCTURE (\"TEXT\" \"PLAIN\" (\"CHARSET\" \"UTF-8\") NIL NIL \"7BIT\" {} 1 NIL NIL NIL NIL))",
+            uid,
+            body.len()
+        ))
+    }
+
+    /// One part of a message by its section number (see `BodyPart`), still
+    /// in its transfer encoding (`UID FETCH <uid> BODY.PEEK[<section>]`;
+    /// placeholder)
+    pub async fn fetch_body_part(&mut self, folder: &str, uid: u32, section: &str) -> Result<Vec<u8>> {
+        tracing::debug!("UID FETCH {} BODY.PEEK[{}] in folder: {} (placeholder)", uid, section, folder);
+
+        let message = self.fetch_message_body(folder, uid).await?;
+        let body = message.split_once("\r\n\r\n").map_or("", |(_, body)| body);
+        Ok(body.as_bytes().to_vec())
+    }
+
     /// Fetch messages from a folder (placeholder)
     pub async fn fetch_messages(
         &mut self,
@@ -199,28 +423,42 @@ impl ImapSession {
     ) -> Result<Vec<ImapMessage>> {
         tracing::debug!("Fetching messages from folder: {} (placeholder)", folder);
 
-        // Return mock messages
+        // Return mock messages; the update comes with release notes
+        let text = "(\"TEXT\" \"PLAIN\" (\"CHARSET\" \"UTF-8\") NIL NIL \"7BIT\" 28 1 NIL NIL NIL NIL)";
+        let with_notes = format!(
+            "({} (\"APPLICATION\" \"PDF\" (\"NAME\" \"release-notes.pdf\") NIL NIL \"BASE64\" 48200 NIL (\"ATTACHMENT\" (\"FILENAME\" \"release-notes.pdf\")) NIL NIL) \"MIXED\")",
+            text
+        );
         Ok(vec![
             ImapMessage {
                 uid: 1,
                 subject: format!("Welcome to {}", folder),
                 from: "welcome@example.com".to_string(),
                 date: "Mon, 10 Jan 2024 10:00:00 +0000".to_string(),
+                message_id: mock_message_id(folder, 1),
                 flags: vec!["\\Seen".to_string()],
+                list_id: None,
+                body_structure: body_structure::parse(text).ok(),
             },
             ImapMessage {
                 uid: 2,
                 subject: "Getting Started".to_string(),
                 from: "help@example.com".to_string(),
                 date: "Tue, 11 Jan 2024 14:30:00 +0000".to_string(),
+                message_id: mock_message_id(folder, 2),
                 flags: vec![],
+                list_id: None,
+                body_structure: body_structure::parse(text).ok(),
             },
             ImapMessage {
                 uid: 3,
                 subject: "Important Update".to_string(),
                 from: "updates@example.com".to_string(),
                 date: "Wed, 12 Jan 2024 09:15:00 +0000".to_string(),
+                message_id: mock_message_id(folder, 3),
                 flags: vec!["\\Flagged".to_string()],
+                list_id: None,
+                body_structure: body_structure::parse(&with_notes).ok(),
             },
         ])
     }
@@ -256,6 +494,126 @@ impl ImapSession {
         Ok(())
     }
 
+    /// Clear the \Deleted flag on a message (placeholder)
+    pub async fn undelete_message(&mut self, folder: &str, uid: u32) -> Result<()> {
+        tracing::debug!(
+            "Undeleting message {} in folder: {} (placeholder)",
+            uid,
+            folder
+        );
+        Ok(())
+    }
+
+    /// Move a message to another folder (placeholder)
+    pub async fn move_message(&mut self, folder: &str, uid: u32, destination: &str) -> Result<()> {
+        tracing::debug!(
+            "Moving message {} from {} to {} (placeholder)",
+            uid,
+            folder,
+            destination
+        );
+        Ok(())
+    }
+
+    /// Add or remove a flag on many messages with a single UID STORE (placeholder)
+    pub async fn store_flags(&mut self, folder: &str, uids: &[u32], flag: &str, add: bool) -> Result<()> {
+        tracing::debug!(
+            "UID STORE {} {}FLAGS ({}) in folder: {} (placeholder)",
+            uid_set(uids),
+            if add { "+" } else { "-" },
+            flag,
+            folder
+        );
+        Ok(())
+    }
+
+    /// Move many messages with a single UID MOVE (placeholder)
+    ///
+    /// Returns the UIDs the messages got in `destination`, keyed by their old
+    /// UIDs, from the server's `COPYUID` (see `parse_copyuid`). Empty when the
+    /// server doesn't support UIDPLUS.
+    pub async fn move_messages(&mut self, folder: &str, uids: &[u32], destination: &str) -> Result<HashMap<u32, u32>> {
+        tracing::debug!(
+            "UID MOVE {} from {} to {} (placeholder)",
+            uid_set(uids),
+            folder,
+            destination
+        );
+        // The mock folders hold UIDs 1-5, so moved messages are numbered after them
+        let mut sorted = uids.to_vec();
+        sorted.sort_unstable();
+        sorted.dedup();
+        let next = self.uid_next.entry(destination.to_string()).or_insert(6);
+        let assigned: Vec<u32> = (*next..*next + sorted.len() as u32).collect();
+        *next += sorted.len() as u32;
+        for (old, new) in sorted.iter().zip(&assigned) {
+            let message_id = self
+                .moved_message_ids
+                .remove(&(folder.to_string(), *old))
+                .unwrap_or_else(|| mock_message_id(folder, *old));
+            self.moved_message_ids.insert((destination.to_string(), *new), message_id);
+        }
+        Ok(parse_copyuid(&format!("* OK [COPYUID 1 {} {}] Done", uid_set(&sorted), uid_set(&assigned))))
+    }
+
+    /// Permanently remove messages flagged `\Deleted` from a folder (placeholder)
+    ///
+    /// With `uids`, only those messages are expunged (UIDPLUS `UID EXPUNGE`).
+    pub async fn expunge(&mut self, folder: &str, uids: Option<&[u32]>) -> Result<()> {
+        match uids {
+            Some(uids) => tracing::debug!("UID EXPUNGE {} in folder: {} (placeholder)", uid_set(uids), folder),
+            None => tracing::debug!("EXPUNGE in folder: {} (placeholder)", folder),
+        }
+        Ok(())
+    }
+
+    /// Append a raw RFC 5322 message to a folder with the given flags (placeholder)
+    ///
+    /// Returns the new message's UID when the server reports it (UIDPLUS
+    /// `APPENDUID`).
+    pub async fn append(&mut self, folder: &str, raw: &[u8], flags: &[&str]) -> Result<Option<u32>> {
+        tracing::debug!(
+            "APPEND {} ({}) {{{}}} (placeholder)",
+            folder,
+            flags.join(" "),
+            raw.len()
+        );
+        Ok(None)
+    }
+
+    /// UIDs of messages in a folder whose header contains a value (placeholder)
+    pub async fn search_header(&mut self, folder: &str, header: &str, value: &str) -> Result<Vec<u32>> {
+        tracing::debug!(
+            "UID SEARCH HEADER {} \"{}\" in folder: {} (placeholder)",
+            header,
+            value,
+            folder
+        );
+        // The mock only knows the Message-IDs of messages it moved
+        if !header.eq_ignore_ascii_case("Message-ID") {
+            return Ok(Vec::new());
+        }
+        let value = value.trim().trim_start_matches('<').trim_end_matches('>');
+        let mut found: Vec<u32> = self
+            .moved_message_ids
+            .iter()
+            .filter(|((f, _), id)| f == folder && id.as_str() == value)
+            .map(|((_, uid), _)| *uid)
+            .collect();
+        found.sort_unstable();
+        Ok(found)
+    }
+
+    /// Usage and limits of the quota root containing `folder`
+    /// (`GETQUOTAROOT`; placeholder).
+    ///
+    /// `None` when the server doesn't advertise the QUOTA capability, as the
+    /// placeholder session doesn't.
+    pub async fn get_quota_root(&mut self, folder: &str) -> Result<Option<ImapQuota>> {
+        tracing::debug!("GETQUOTAROOT {} (placeholder)", folder);
+        Ok(None)
+    }
+
     /// Logout and close session (placeholder)
     pub async fn logout(self) -> Result<()> {
         tracing::debug!("Logging out from IMAP server (placeholder)");
@@ -331,11 +689,49 @@ mod tests {
             port: 993,
             use_tls: true,
             username: "test@example.com".to_string(),
+            timeouts: NetworkTimeouts::default(),
+            pinned_fingerprint: None,
         };
         let client = ImapClient::new(config);
         assert!(client.is_ok());
     }
 
+    #[test]
+    fn test_uid_set_compacts_ranges() {
+        assert_eq!(uid_set(&[7, 1, 2, 3, 3]), "1:3,7");
+        assert_eq!(uid_set(&[5]), "5");
+        assert_eq!(uid_set(&[]), "");
+    }
+
+    #[test]
+    fn test_parse_copyuid() {
+        let moved = parse_copyuid("A3 OK [COPYUID 38505 304,319:320 3956:3958] Done\r\n");
+        assert_eq!(moved.len(), 3);
+        assert_eq!(moved[&304], 3956);
+        assert_eq!(moved[&319], 3957);
+        assert_eq!(moved[&320], 3958);
+
+        assert!(parse_copyuid("A3 OK Done").is_empty());
+        // Sets of different sizes can't be paired up
+        assert!(parse_copyuid("* OK [COPYUID 1 1:3 10]").is_empty());
+    }
+
+    #[test]
+    fn test_parse_quota_response() {
+        let quota = parse_quota_response(
+            "* QUOTAROOT INBOX \"\" \"#user\"\r\n\
+             * QUOTA \"\" (STORAGE 512 1024 MESSAGE 90 100)\r\n\
+             * QUOTA \"#user\" (STORAGE 1 2)\r\n",
+        )
+        .unwrap();
+        assert_eq!(quota.storage_used_bytes, Some(512 * 1024));
+        assert_eq!(quota.storage_limit_bytes, Some(1024 * 1024));
+        assert_eq!((quota.messages_used, quota.messages_limit), (Some(90), Some(100)));
+
+        assert_eq!(parse_quota_response("* QUOTAROOT INBOX\r\n"), None);
+        assert_eq!(parse_quota_response("* QUOTA \"\" ()\r\n"), None);
+    }
+
     #[test]
     fn test_imap_folder() {
         let folder = ImapFolder {
@@ -354,12 +750,34 @@ mod tests {
             subject: "Test Subject".to_string(),
             from: "sender@example.com".to_string(),
             date: "Mon, 10 Jan 2022 10:00:00 +0000".to_string(),
+            message_id: "123@example.com".to_string(),
             flags: vec!["\\Seen".to_string()],
+            list_id: None,
+            body_structure: None,
         };
         assert_eq!(msg.uid, 123);
         assert_eq!(msg.subject, "Test Subject");
     }
 
+    #[tokio::test]
+    async fn test_imap_session_fetch_body_parts() {
+        let config = ImapConfig {
+            server: "imap.example.com".to_string(),
+            port: 993,
+            use_tls: true,
+            username: "test@example.com".to_string(),
+            timeouts: NetworkTimeouts::default(),
+            pinned_fingerprint: None,
+        };
+        let client = ImapClient::new(config).unwrap();
+        let mut session = client.connect("password").await.unwrap();
+        let structure = session.fetch_body_structure("INBOX", 4).await.unwrap();
+        let part = structure.text_part().unwrap();
+        let text = session.fetch_body_part("INBOX", 4, &part.section).await.unwrap();
+        assert_eq!(text.len() as u64, part.size);
+        assert_eq!(text, b"This is a test message body.");
+    }
+
     #[tokio::test]
     async fn test_imap_session_list_folders() {
         let config = ImapConfig {
@@ -367,6 +785,8 @@ mod tests {
             port: 993,
             use_tls: true,
             username: "test@example.com".to_string(),
+            timeouts: NetworkTimeouts::default(),
+            pinned_fingerprint: None,
         };
         let client = ImapClient::new(config).unwrap();
         let mut session = client.connect("password").await.unwrap();
@@ -375,6 +795,31 @@ mod tests {
         assert_eq!(folders[0].name, "INBOX");
     }
 
+    #[tokio::test]
+    async fn test_imap_session_subscriptions() {
+        let config = ImapConfig {
+            server: "imap.example.com".to_string(),
+            port: 993,
+            use_tls: true,
+            username: "test@example.com".to_string(),
+            timeouts: NetworkTimeouts::default(),
+            pinned_fingerprint: None,
+        };
+        let client = ImapClient::new(config).unwrap();
+        let mut session = client.connect("password").await.unwrap();
+        let all = session.list_folders().await.unwrap().len();
+        assert_eq!(session.list_subscribed().await.unwrap().len(), all);
+
+        session.unsubscribe("Archive").await.unwrap();
+        let subscribed = session.list_subscribed().await.unwrap();
+        assert_eq!(subscribed.len(), all - 1);
+        assert!(subscribed.iter().all(|f| f.name != "Archive"));
+        assert_eq!(session.list_folders().await.unwrap().len(), all);
+
+        session.subscribe("Archive").await.unwrap();
+        assert_eq!(session.list_subscribed().await.unwrap().len(), all);
+    }
+
     #[tokio::test]
     async fn test_imap_session_fetch_uids() {
         let config = ImapConfig {
@@ -382,6 +827,8 @@ mod tests {
             port: 993,
             use_tls: true,
             username: "test@example.com".to_string(),
+            timeouts: NetworkTimeouts::default(),
+            pinned_fingerprint: None,
         };
         let client = ImapClient::new(config).unwrap();
         let mut session = client.connect("password").await.unwrap();
@@ -397,6 +844,8 @@ mod tests {
             port: 993,
             use_tls: true,
             username: "test@example.com".to_string(),
+            timeouts: NetworkTimeouts::default(),
+            pinned_fingerprint: None,
         };
         let client = ImapClient::new(config).unwrap();
         let mut session = client.connect("password").await.unwrap();
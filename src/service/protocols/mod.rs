@@ -1,5 +1,12 @@
 //! Email protocol implementations
 
+pub mod body_structure;
+pub mod certificate_pin;
 pub mod imap;
+pub mod network;
 pub mod pop3;
+pub mod rate_limit;
 pub mod smtp;
+
+pub use network::NetworkTimeouts;
+pub use rate_limit::RateLimits;
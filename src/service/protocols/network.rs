@@ -0,0 +1,213 @@
+//! Connection timeouts and network error mapping
+//!
+//! Shared by the IMAP, POP3 and SMTP clients so a failure to reach a server
+//! surfaces as a specific [`Error`] variant (host not found, connection
+//! refused, timed out, certificate rejected) rather than a raw string.
+
+use crate::common::{Error, Result};
+use std::future::Future;
+use std::io;
+use std::time::Duration;
+
+/// Delay before the first connection retry; doubled for each further one
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Connection attempts made before giving up on a transient failure
+const CONNECT_ATTEMPTS: u32 = 3;
+
+/// How long to wait on a mail server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetworkTimeouts {
+    /// Reaching the server and completing the TLS handshake
+    pub connect: Duration,
+    /// Waiting for a reply once connected
+    pub read: Duration,
+}
+
+impl Default for NetworkTimeouts {
+    fn default() -> Self {
+        Self::from_secs(30, 60)
+    }
+}
+
+impl NetworkTimeouts {
+    /// Timeouts from settings values in seconds; zero keeps the default
+    pub fn from_secs(connect: u64, read: u64) -> Self {
+        let or = |secs: u64, default: u64| Duration::from_secs(if secs == 0 { default } else { secs });
+        Self { connect: or(connect, 30), read: or(read, 60) }
+    }
+}
+
+/// Run `future`, failing with [`Error::Timeout`] if it takes longer than
+/// `limit`. `what` names the operation in the message, e.g.
+/// "connecting to imap.example.com".
+pub async fn with_timeout<T>(limit: Duration, what: &str, future: impl Future<Output = Result<T>>) -> Result<T> {
+    tokio::time::timeout(limit, future)
+        .await
+        .unwrap_or_else(|_| Err(Error::Timeout(format!("No response after {} seconds while {}", limit.as_secs(), what))))
+}
+
+/// Connect with `attempt`, retrying transient failures (timeouts, refused
+/// connections) with a growing delay. Each attempt is bounded by the
+/// connect timeout.
+pub async fn connect_with_retry<T, F, Fut>(timeouts: NetworkTimeouts, server: &str, mut attempt: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let what = format!("connecting to {}", server);
+    let mut delay = RETRY_DELAY;
+    for remaining in (0..CONNECT_ATTEMPTS).rev() {
+        match with_timeout(timeouts.connect, &what, attempt()).await {
+            Err(e) if remaining > 0 && e.is_transient() => {
+                tracing::warn!("{}; retrying in {} ms", e, delay.as_millis());
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+            result => return result,
+        }
+    }
+    unreachable!("the last attempt always returns")
+}
+
+/// Open and close a TCP connection to `host:port` and return how long it
+/// took. Nothing is sent, so this only shows the server can be reached.
+pub async fn probe(host: &str, port: u16, limit: Duration) -> Result<Duration> {
+    let server = format!("{}:{}", host, port);
+    let started = std::time::Instant::now();
+    with_timeout(limit, &format!("connecting to {}", server), async {
+        tokio::net::TcpStream::connect((host, port)).await.map_err(|e| io_error(&server, &e))
+    })
+    .await?;
+    Ok(started.elapsed())
+}
+
+/// Map a socket error from connecting to `server` to a specific variant
+pub fn io_error(server: &str, err: &io::Error) -> Error {
+    match err.kind() {
+        io::ErrorKind::TimedOut => Error::Timeout(format!("Connecting to {} timed out", server)),
+        io::ErrorKind::ConnectionRefused => Error::ConnectionRefused(format!("{} refused the connection", server)),
+        io::ErrorKind::InvalidData if mentions_certificate(&err.to_string()) => {
+            Error::Certificate(format!("{}: {}", server, err))
+        }
+        _ if is_lookup_failure(&err.to_string()) => {
+            Error::HostNotFound(format!("Could not find a server called {}", server))
+        }
+        _ => Error::Network(format!("{}: {}", server, err)),
+    }
+}
+
+/// Map an SMTP failure while `what` (e.g. "sending email") via `server`
+pub fn smtp_error(server: &str, what: &str, err: lettre::transport::smtp::Error) -> Error {
+    if let Some(code) = err.status() {
+        let code = code.to_string();
+        // 530 authentication required, 534 mechanism too weak, 535 credentials invalid
+        if matches!(code.as_str(), "530" | "534" | "535") {
+            return Error::Authentication(format!("{} rejected the login ({}): {}", server, code, err));
+        }
+        // 421 closes the connection, often for too many of them; other 4xx
+        // replies say in their text when they are about sending too fast
+        if code == "421" || (code.starts_with('4') && super::rate_limit::is_throttling(&err.to_string())) {
+            return Error::RateLimited(format!("{} asked to slow down ({}): {}", server, code, err));
+        }
+        return Error::Protocol(format!("Failed {}: {}", what, err));
+    }
+    if err.is_timeout() {
+        return Error::Timeout(format!("{} did not respond while {}", server, what));
+    }
+    if err.is_tls() {
+        return Error::Certificate(format!("Secure connection to {} failed: {}", server, err));
+    }
+    let mut source = std::error::Error::source(&err);
+    while let Some(inner) = source {
+        if let Some(io_err) = inner.downcast_ref::<io::Error>() {
+            return io_error(server, io_err);
+        }
+        source = inner.source();
+    }
+    Error::Network(format!("Failed {}: {}", what, err))
+}
+
+/// Resolver messages differ by platform and the standard library has no
+/// stable error kind for them.
+fn is_lookup_failure(message: &str) -> bool {
+    let message = message.to_ascii_lowercase();
+    ["failed to lookup address", "name or service not known", "no such host", "nodename nor servname", "no address associated"]
+        .iter()
+        .any(|needle| message.contains(needle))
+}
+
+fn mentions_certificate(message: &str) -> bool {
+    let message = message.to_ascii_lowercase();
+    message.contains("certificate") || message.contains("unknownissuer")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn test_io_error_mapping() {
+        let refused = io::Error::new(io::ErrorKind::ConnectionRefused, "refused");
+        assert!(matches!(io_error("mail.example.com", &refused), Error::ConnectionRefused(_)));
+        let timed_out = io::Error::new(io::ErrorKind::TimedOut, "timed out");
+        assert!(matches!(io_error("mail.example.com", &timed_out), Error::Timeout(_)));
+        let dns = io::Error::other("failed to lookup address information: Name or service not known");
+        assert!(matches!(io_error("mial.example.com", &dns), Error::HostNotFound(m) if m.contains("mial.example.com")));
+        let cert = io::Error::new(io::ErrorKind::InvalidData, "invalid peer certificate: UnknownIssuer");
+        assert!(matches!(io_error("mail.example.com", &cert), Error::Certificate(_)));
+        let reset = io::Error::new(io::ErrorKind::ConnectionReset, "reset");
+        assert!(matches!(io_error("mail.example.com", &reset), Error::Network(_)));
+    }
+
+    #[test]
+    fn test_timeouts_from_settings() {
+        assert_eq!(NetworkTimeouts::from_secs(0, 0), NetworkTimeouts::default());
+        let custom = NetworkTimeouts::from_secs(5, 120);
+        assert_eq!((custom.connect, custom.read), (Duration::from_secs(5), Duration::from_secs(120)));
+    }
+
+    #[tokio::test]
+    async fn test_connect_with_retry() {
+        let timeouts = NetworkTimeouts { connect: Duration::from_millis(50), read: Duration::from_secs(1) };
+        let attempts = AtomicU32::new(0);
+        let result = connect_with_retry(timeouts, "mail.example.com", || async {
+            match attempts.fetch_add(1, Ordering::SeqCst) {
+                0 => Err(Error::ConnectionRefused("busy".into())),
+                _ => Ok("session"),
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), "session");
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+
+        // Bad credentials won't fix themselves, so they aren't retried
+        attempts.store(0, Ordering::SeqCst);
+        let result: Result<()> = connect_with_retry(timeouts, "mail.example.com", || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(Error::Authentication("bad password".into()))
+        })
+        .await;
+        assert!(matches!(result, Err(Error::Authentication(_))));
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+        let result: Result<()> = with_timeout(Duration::from_millis(10), "waiting", async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(())
+        })
+        .await;
+        assert!(matches!(result, Err(Error::Timeout(_))));
+    }
+
+    #[tokio::test]
+    async fn test_probe() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        assert!(probe("127.0.0.1", port, Duration::from_secs(5)).await.is_ok());
+
+        drop(listener);
+        let result = probe("127.0.0.1", port, Duration::from_secs(5)).await;
+        assert!(matches!(result, Err(Error::ConnectionRefused(_))));
+    }
+}
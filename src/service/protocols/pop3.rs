@@ -2,6 +2,7 @@
 //!
 //! Handles POP3 protocol for receiving email.
 
+use super::network::{self, NetworkTimeouts};
 use crate::common::Result;
 use std::collections::{HashMap, HashSet};
 
@@ -12,6 +13,7 @@ pub struct Pop3Config {
     pub port: u16,
     pub use_tls: bool,
     pub username: String,
+    pub timeouts: NetworkTimeouts,
 }
 
 /// POP3 message metadata from LIST/UIDL style commands
@@ -49,7 +51,10 @@ impl Pop3Client {
             self.config.port,
             crate::common::logging::mask_email(&self.config.username)
         );
-        Ok(Pop3Session::new(self.config.clone()))
+        network::connect_with_retry(self.config.timeouts, &self.config.server, || async {
+            Ok(Pop3Session::new(self.config.clone()))
+        })
+        .await
     }
 }
 
@@ -145,7 +150,7 @@ impl Pop3Session {
         self.store
             .get(&id)
             .cloned()
-            .ok_or_else(|| crate::common::Error::Protocol(format!("Message {} not found", id)))
+            .ok_or_else(|| crate::common::Error::NotFound(format!("Message {} not found", id)))
     }
 
     /// TOP: returns header and up to N body lines.
@@ -219,6 +224,7 @@ mod tests {
             port: 995,
             use_tls: true,
             username: "test@example.com".to_string(),
+            timeouts: NetworkTimeouts::default(),
         };
         let client = Pop3Client::new(config);
         assert!(client.is_ok());
@@ -231,6 +237,7 @@ mod tests {
             port: 995,
             use_tls: true,
             username: "test@example.com".to_string(),
+            timeouts: NetworkTimeouts::default(),
         };
         let client = Pop3Client::new(config).unwrap();
         let session = client.connect("password").await.unwrap();
@@ -250,6 +257,7 @@ mod tests {
             port: 995,
             use_tls: true,
             username: "test@example.com".to_string(),
+            timeouts: NetworkTimeouts::default(),
         };
         let client = Pop3Client::new(config).unwrap();
         let mut session = client.connect("password").await.unwrap();
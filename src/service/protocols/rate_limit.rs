@@ -0,0 +1,338 @@
+//! Client-side rate limits
+//!
+//! Providers throttle, or ban for a while, clients that connect or send too
+//! fast. Each account's server gets token buckets for new connections and
+//! sent messages, and a cap on fetches running at once. Limiters live for
+//! the whole process rather than in a controller, since every send makes
+//! its own `MailController`.
+//!
+//! A server that says to slow down (an SMTP 421, an IMAP `[THROTTLED]`, a
+//! "too many connections") pauses that account's use of it, for twice as
+//! long each time it happens again.
+
+use crate::common::{Error, Result};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Pause after the server first throttles an account
+const FIRST_BACKOFF: Duration = Duration::from_secs(30);
+/// Longest pause, however often the server throttles
+const MAX_BACKOFF: Duration = Duration::from_secs(15 * 60);
+/// Longest wait for a limit; beyond it the request fails with
+/// [`Error::RateLimited`] so the user isn't left waiting with no word
+const MAX_WAIT: Duration = Duration::from_secs(60);
+
+/// How fast one account may use its mail server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimits {
+    /// Messages sent a minute; 0 for no limit
+    pub sends_per_minute: u32,
+    /// New connections a minute; 0 for no limit
+    pub connections_per_minute: u32,
+    /// Message fetches at once
+    pub fetch_concurrency: u32,
+}
+
+impl Default for RateLimits {
+    /// Below what Gmail and Outlook.com allow: Outlook.com takes 30
+    /// messages a minute and Gmail 15 connections at once per account
+    fn default() -> Self {
+        Self { sends_per_minute: 20, connections_per_minute: 10, fetch_concurrency: 4 }
+    }
+}
+
+/// Whether a server's reply asks the client to slow down
+pub fn is_throttling(reply: &str) -> bool {
+    let reply = reply.to_ascii_lowercase();
+    [
+        "too many",
+        "rate limit",
+        "throttl",
+        "try again later",
+        "[limit]",
+        "[unavailable]",
+        "quota exceeded",
+        "limit exceeded",
+        "sending limit",
+    ]
+    .iter()
+    .any(|needle| reply.contains(needle))
+}
+
+/// Turn a failure whose message says the server is throttling into
+/// [`Error::RateLimited`]; other errors are returned unchanged
+pub fn classify(error: Error) -> Error {
+    match error {
+        Error::Protocol(msg) | Error::Network(msg) | Error::Other(msg) if is_throttling(&msg) => Error::RateLimited(msg),
+        other => other,
+    }
+}
+
+/// Tokens refilled evenly over a minute, up to a minute's worth
+#[derive(Debug)]
+struct TokenBucket {
+    per_minute: u32,
+    /// Negative when callers have taken tokens ahead of the refill
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(per_minute: u32, now: Instant) -> Self {
+        Self { per_minute, tokens: per_minute as f64, updated: now }
+    }
+
+    /// Take a token, returning how long to wait before using it
+    fn take(&mut self, now: Instant) -> Duration {
+        if self.per_minute == 0 {
+            return Duration::ZERO;
+        }
+        let per_second = self.per_minute as f64 / 60.0;
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * per_second).min(self.per_minute as f64);
+        self.updated = now;
+        self.tokens -= 1.0;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / per_second)
+        }
+    }
+
+    /// Return a token whose caller gave up waiting for it
+    fn give_back(&mut self) {
+        if self.per_minute > 0 {
+            self.tokens += 1.0;
+        }
+    }
+}
+
+/// What a limiter is asked for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Request {
+    Connection,
+    Send,
+}
+
+#[derive(Debug)]
+struct LimiterState {
+    connections: TokenBucket,
+    sends: TokenBucket,
+    /// Set when the server last throttled the account
+    paused_until: Option<Instant>,
+    /// Length of the next pause
+    backoff: Duration,
+}
+
+/// Rate limits for one account on one server
+#[derive(Debug)]
+pub struct ServerLimiter {
+    server: String,
+    limits: RateLimits,
+    state: Mutex<LimiterState>,
+    fetches: Arc<Semaphore>,
+}
+
+impl ServerLimiter {
+    fn new(server: &str, limits: RateLimits, now: Instant) -> Self {
+        Self {
+            server: server.to_string(),
+            limits,
+            state: Mutex::new(LimiterState {
+                connections: TokenBucket::new(limits.connections_per_minute, now),
+                sends: TokenBucket::new(limits.sends_per_minute, now),
+                paused_until: None,
+                backoff: FIRST_BACKOFF,
+            }),
+            fetches: Arc::new(Semaphore::new(limits.fetch_concurrency.max(1) as usize)),
+        }
+    }
+
+    /// Wait until a new connection is allowed
+    pub async fn connection(&self) -> Result<()> {
+        self.wait(Request::Connection).await
+    }
+
+    /// Wait until sending another message is allowed
+    pub async fn send(&self) -> Result<()> {
+        self.wait(Request::Send).await
+    }
+
+    /// Wait for a free fetch slot, held until the permit is dropped
+    pub async fn fetch(&self) -> OwnedSemaphorePermit {
+        self.fetches.clone().acquire_owned().await.expect("the fetch semaphore is never closed")
+    }
+
+    /// Note how a request went. A server that throttled it pauses the
+    /// account, for longer each time; a success resets the pause.
+    /// Throttling replies come back as [`Error::RateLimited`].
+    pub fn finish<T>(&self, result: Result<T>) -> Result<T> {
+        self.finish_at(result, Instant::now())
+    }
+
+    fn finish_at<T>(&self, result: Result<T>, now: Instant) -> Result<T> {
+        let mut state = self.state.lock().unwrap();
+        match result.map_err(classify) {
+            Err(Error::RateLimited(msg)) => {
+                let pause = state.backoff;
+                state.paused_until = Some(now + pause);
+                state.backoff = (pause * 2).min(MAX_BACKOFF);
+                tracing::warn!("{} is throttling; pausing for {} seconds", self.server, pause.as_secs());
+                Err(Error::RateLimited(format!("{} (pausing for {} seconds)", msg, pause.as_secs())))
+            }
+            Ok(value) => {
+                state.backoff = FIRST_BACKOFF;
+                Ok(value)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn wait(&self, request: Request) -> Result<()> {
+        let delay = self.delay(request, Instant::now())?;
+        if !delay.is_zero() {
+            tracing::info!("Waiting {} ms for the rate limit of {}", delay.as_millis(), self.server);
+            tokio::time::sleep(delay).await;
+        }
+        Ok(())
+    }
+
+    /// How long `request` must wait at `now`, or why it can't go ahead
+    fn delay(&self, request: Request, now: Instant) -> Result<Duration> {
+        let mut state = self.state.lock().unwrap();
+        let paused = state.paused_until.map(|until| until.saturating_duration_since(now)).unwrap_or_default();
+        let (bucket, per_minute, what) = match request {
+            Request::Connection => (&mut state.connections, self.limits.connections_per_minute, "connections"),
+            Request::Send => (&mut state.sends, self.limits.sends_per_minute, "messages"),
+        };
+        let queued = bucket.take(now);
+        let delay = paused.max(queued);
+        if delay <= MAX_WAIT {
+            return Ok(delay);
+        }
+        bucket.give_back();
+        let seconds = delay.as_secs() + 1;
+        Err(Error::RateLimited(if paused >= queued {
+            format!("{} asked to slow down; try again in about {} seconds", self.server, seconds)
+        } else {
+            format!("reached {} {} a minute for {}; try again in about {} seconds", per_minute, what, self.server, seconds)
+        }))
+    }
+}
+
+struct Registry {
+    limits: RateLimits,
+    limiters: HashMap<String, Arc<ServerLimiter>>,
+}
+
+fn registry() -> &'static Mutex<Registry> {
+    static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(Registry { limits: RateLimits::default(), limiters: HashMap::new() }))
+}
+
+/// Use `limits` from now on, such as after the settings change. Pauses
+/// the servers asked for are kept.
+pub fn configure(limits: RateLimits) {
+    let mut registry = registry().lock().unwrap();
+    if registry.limits != limits {
+        registry.limits = limits;
+        registry.limiters.retain(|_, limiter| limiter.state.lock().unwrap().paused_until.is_some());
+    }
+}
+
+/// The limiter for `username` on `server`, shared by every connection the
+/// account makes to it
+pub fn limiter(server: &str, username: &str) -> Arc<ServerLimiter> {
+    let mut registry = registry().lock().unwrap();
+    let limits = registry.limits;
+    let key = format!("{}\n{}", server.to_ascii_lowercase(), username.to_ascii_lowercase());
+    registry.limiters.entry(key).or_insert_with(|| Arc::new(ServerLimiter::new(server, limits, Instant::now()))).clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(6, start);
+        for _ in 0..6 {
+            assert_eq!(bucket.take(start), Duration::ZERO);
+        }
+        // One token every 10 seconds once the minute's worth is used
+        assert_eq!(bucket.take(start), Duration::from_secs(10));
+        assert_eq!(bucket.take(start), Duration::from_secs(20));
+        bucket.give_back();
+        assert_eq!(bucket.take(start + Duration::from_secs(10)), Duration::from_secs(10));
+        assert_eq!(TokenBucket::new(0, start).take(start), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_limits_wait_then_refuse() {
+        let start = Instant::now();
+        let limits = RateLimits { sends_per_minute: 2, connections_per_minute: 0, fetch_concurrency: 1 };
+        let limiter = ServerLimiter::new("smtp.example.com", limits, start);
+        assert_eq!(limiter.delay(Request::Send, start).unwrap(), Duration::ZERO);
+        assert_eq!(limiter.delay(Request::Send, start).unwrap(), Duration::ZERO);
+        assert_eq!(limiter.delay(Request::Send, start).unwrap(), Duration::from_secs(30));
+        assert_eq!(limiter.delay(Request::Send, start).unwrap(), Duration::from_secs(60));
+        let err = limiter.delay(Request::Send, start).unwrap_err();
+        assert!(matches!(err, Error::RateLimited(ref msg) if msg.contains("2 messages a minute")));
+        // Connections have no limit here
+        assert_eq!(limiter.delay(Request::Connection, start).unwrap(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_throttling_reply_pauses_with_backoff() {
+        let start = Instant::now();
+        let limiter = ServerLimiter::new("imap.example.com", RateLimits::default(), start);
+        let throttled = || Err::<(), _>(Error::Protocol("NO [THROTTLED] Too many commands".to_string()));
+
+        assert!(matches!(limiter.finish_at(throttled(), start), Err(Error::RateLimited(_))));
+        assert_eq!(limiter.delay(Request::Connection, start).unwrap(), FIRST_BACKOFF);
+        assert!(matches!(limiter.finish_at(throttled(), start), Err(Error::RateLimited(_))));
+        // The second pause (60 s) is as long as anyone is kept waiting
+        assert_eq!(limiter.delay(Request::Connection, start).unwrap(), FIRST_BACKOFF * 2);
+        assert!(limiter.finish_at(throttled(), start).is_err());
+        let err = limiter.delay(Request::Connection, start).unwrap_err();
+        assert!(err.to_string().contains("asked to slow down"));
+
+        // Other failures don't pause, and a success resets the backoff
+        assert!(matches!(limiter.finish_at(Err::<(), _>(Error::auth("bad".to_string())), start), Err(Error::Authentication(_))));
+        assert_eq!(limiter.finish_at(Ok(5), start).unwrap(), 5);
+        let later = start + MAX_BACKOFF;
+        assert!(limiter.finish_at(throttled(), later).is_err());
+        assert_eq!(limiter.delay(Request::Connection, later).unwrap(), FIRST_BACKOFF);
+    }
+
+    #[test]
+    fn test_is_throttling() {
+        assert!(is_throttling("421 4.7.0 Try again later, closing connection."));
+        assert!(is_throttling("Too many simultaneous connections. (Failure)"));
+        assert!(is_throttling("432 4.3.2 STOREDRV.ClientSubmit; sender thread limit exceeded"));
+        assert!(is_throttling("NO [LIMIT] Request is throttled"));
+        assert!(!is_throttling("550 5.1.1 The email account that you tried to reach does not exist"));
+        assert!(matches!(classify(Error::Protocol("Too many login attempts".into())), Error::RateLimited(_)));
+        assert!(matches!(classify(Error::auth("Too many login attempts".into())), Error::Authentication(_)));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_concurrency_and_shared_limiters() {
+        let limits = RateLimits { fetch_concurrency: 2, ..RateLimits::default() };
+        let imap = ServerLimiter::new("imap.example.com", limits, Instant::now());
+        let first = imap.fetch().await;
+        let _second = imap.fetch().await;
+        assert_eq!(imap.fetches.available_permits(), 0);
+        drop(first);
+        assert_eq!(imap.fetches.available_permits(), 1);
+
+        // One limiter per account and server, whoever asks
+        let a = limiter("Mail.Example.org", "Me@Example.org");
+        let b = limiter("mail.example.org", "me@example.org");
+        assert!(Arc::ptr_eq(&a, &b));
+        assert!(!Arc::ptr_eq(&a, &limiter("mail.example.org", "other@example.org")));
+    }
+}
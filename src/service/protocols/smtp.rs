@@ -1,12 +1,29 @@
 //! SMTP protocol client
 //!
-//! Handles SMTP protocol for sending email.
+//! Handles SMTP protocol for sending email, including read receipt
+//! requests and the receipts themselves (RFC 8098).
 
-use crate::common::{Error, Result};
+use super::certificate_pin;
+use super::network::{self, NetworkTimeouts};
+use super::rate_limit::{self, ServerLimiter};
+use crate::common::{
+    types::{EmailAddress, MessagePriority},
+    Error, Result,
+};
+use std::sync::Arc;
+use base64::Engine;
 use lettre::{
-    message::{header::ContentType, Mailbox, Message, MultiPart, SinglePart},
-    transport::smtp::authentication::Credentials,
-    AsyncSmtpTransport, AsyncTransport, Tokio1Executor,
+    address::Envelope,
+    message::{
+        header::{ContentType, Header, HeaderName, HeaderValue},
+        Mailbox, Message, MultiPart, SinglePart,
+    },
+    transport::smtp::{
+        authentication::{Credentials, DEFAULT_MECHANISMS},
+        client::{AsyncSmtpConnection, TlsParameters},
+        extension::ClientId,
+    },
+    Address, AsyncSmtpTransport, AsyncTransport, Tokio1Executor,
 };
 
 /// SMTP client configuration
@@ -16,6 +33,10 @@ pub struct SmtpConfig {
     pub port: u16,
     pub use_tls: bool,
     pub username: String,
+    pub timeouts: NetworkTimeouts,
+    /// Refuse servers whose certificate doesn't have this SHA-256
+    /// fingerprint (see `certificate_pin`)
+    pub pinned_fingerprint: Option<String>,
 }
 
 /// Email to send
@@ -29,6 +50,19 @@ pub struct Email {
     pub subject: String,
     pub body_text: String,
     pub body_html: Option<String>,
+    /// Where replies should go, when not to `from`
+    pub reply_to: Option<String>,
+    /// SMTP `MAIL FROM`, when not `from`. This envelope sender is where
+    /// bounces go and what the receiving server records as `Return-Path`
+    /// and checks SPF against; recipients see only the header `From`.
+    pub envelope_from: Option<String>,
+    /// Ask the recipient to send a read receipt to this address
+    pub disposition_notification_to: Option<String>,
+    /// Sent as `X-Priority` and `Importance` unless normal
+    pub priority: MessagePriority,
+    /// Sign and/or encrypt the body with OpenPGP
+    #[cfg(feature = "openpgp")]
+    pub pgp: Option<crate::service::openpgp::OutgoingPgp>,
 }
 
 impl Email {
@@ -43,7 +77,146 @@ impl Email {
             subject,
             body_text: body,
             body_html: None,
+            reply_to: None,
+            envelope_from: None,
+            disposition_notification_to: None,
+            priority: MessagePriority::Normal,
+            #[cfg(feature = "openpgp")]
+            pgp: None,
+        }
+    }
+}
+
+/// The `Disposition-Notification-To` header
+#[derive(Debug, Clone)]
+struct DispositionNotificationTo(String);
+
+impl Header for DispositionNotificationTo {
+    fn name() -> HeaderName {
+        HeaderName::new_from_ascii_str("Disposition-Notification-To")
+    }
+
+    fn parse(s: &str) -> std::result::Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Self(s.trim().to_string()))
+    }
+
+    fn display(&self) -> HeaderValue {
+        HeaderValue::new(Self::name(), self.0.clone())
+    }
+}
+
+/// The `X-Priority` header
+#[derive(Debug, Clone)]
+struct XPriority(String);
+
+impl Header for XPriority {
+    fn name() -> HeaderName {
+        HeaderName::new_from_ascii_str("X-Priority")
+    }
+
+    fn parse(s: &str) -> std::result::Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Self(s.trim().to_string()))
+    }
+
+    fn display(&self) -> HeaderValue {
+        HeaderValue::new(Self::name(), self.0.clone())
+    }
+}
+
+/// The `Importance` header
+#[derive(Debug, Clone)]
+struct Importance(String);
+
+impl Header for Importance {
+    fn name() -> HeaderName {
+        HeaderName::new_from_ascii_str("Importance")
+    }
+
+    fn parse(s: &str) -> std::result::Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Self(s.trim().to_string()))
+    }
+
+    fn display(&self) -> HeaderValue {
+        HeaderValue::new(Self::name(), self.0.clone())
+    }
+}
+
+/// A read receipt (message disposition notification) owed for a message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadReceipt {
+    /// Address the sender asked the receipt to go to
+    pub to: String,
+    /// Message-ID of the message that was read, without angle brackets
+    pub original_message_id: Option<String>,
+    pub original_subject: String,
+}
+
+impl ReadReceipt {
+    /// The receipt a raw message asks for, if it has a
+    /// `Disposition-Notification-To` header.
+    pub fn requested_by(raw: &str) -> Option<Self> {
+        let parsed = mail_parser::MessageParser::default().parse(raw.as_bytes())?;
+        let to = parsed.header_raw("Disposition-Notification-To")?;
+        let to = EmailAddress::parse_list(to).into_iter().next()?.address;
+        Some(Self {
+            to,
+            original_message_id: parsed.message_id().map(str::to_string),
+            original_subject: parsed.subject().unwrap_or_default().to_string(),
+        })
+    }
+
+    /// MIME for the receipt sent from `from`. `automatic` marks receipts
+    /// sent by policy rather than by the user's choice.
+    pub fn to_mime(&self, from: &str, automatic: bool) -> Vec<u8> {
+        let boundary = format!("mdn-{}", uuid::Uuid::new_v4().simple());
+        let subject = format!("Read: {}", self.original_subject.replace(['\r', '\n'], " "));
+        let subject = if subject.is_ascii() {
+            subject
+        } else {
+            format!("=?UTF-8?B?{}?=", base64::engine::general_purpose::STANDARD.encode(subject))
+        };
+        let disposition = if automatic {
+            "automatic-action/MDN-sent-automatically; displayed"
+        } else {
+            "manual-action/MDN-sent-manually; displayed"
+        };
+        let mut headers = vec![
+            format!("From: {}", from),
+            format!("To: {}", self.to),
+            format!("Subject: {}", subject),
+            format!("Date: {}", chrono::Utc::now().to_rfc2822()),
+            format!("Message-ID: <{}@wixen-mail>", uuid::Uuid::new_v4()),
+            "MIME-Version: 1.0".to_string(),
+            format!(
+                "Content-Type: multipart/report; report-type=disposition-notification; boundary=\"{}\"",
+                boundary
+            ),
+        ];
+        if let Some(id) = &self.original_message_id {
+            headers.push(format!("In-Reply-To: <{}>", id));
+        }
+        let mut report = vec![
+            format!("Reporting-UA: Wixen Mail {}", env!("CARGO_PKG_VERSION")),
+            format!("Final-Recipient: rfc822; {}", from),
+        ];
+        if let Some(id) = &self.original_message_id {
+            report.push(format!("Original-Message-ID: <{}>", id));
         }
+        report.push(format!("Disposition: {}", disposition));
+
+        let text = format!(
+            "Your message was displayed to {}.\r\nThis does not guarantee that it was read or understood.",
+            from
+        );
+        format!(
+            "{}\r\n\r\n--{b}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n{}\r\n\r\n\
+             --{b}\r\nContent-Type: message/disposition-notification\r\n\r\n{}\r\n\r\n--{b}--\r\n",
+            headers.join("\r\n"),
+            text,
+            report.join("\r\n"),
+            b = boundary
+        )
+        .into_bytes()
     }
 }
 
@@ -58,8 +231,9 @@ impl SmtpClient {
         Ok(Self { config })
     }
 
-    /// Send an email
-    pub async fn send_email(&self, email: Email, password: &str) -> Result<()> {
+    /// Send an email, returning the exact MIME that was submitted so it can
+    /// be filed in the Sent folder.
+    pub async fn send_email(&self, email: Email, password: &str) -> Result<Vec<u8>> {
         tracing::info!(
             "Sending email from {} to {:?}",
             crate::common::logging::mask_email(&email.from),
@@ -70,7 +244,164 @@ impl SmtpClient {
                 .collect::<Vec<_>>()
         );
 
-        // Build the message
+        let message = self.build_message(&email)?;
+        let raw = message.formatted();
+
+        // Send the email
+        let limiter = self.limiter();
+        limiter.connection().await?;
+        limiter.send().await?;
+        let sent = self.transport(password).await?
+            .send(message)
+            .await
+            .map_err(|e| network::smtp_error(&self.config.server, "sending email", e));
+        limiter.finish(sent)?;
+
+        tracing::info!("Email sent successfully");
+        Ok(raw)
+    }
+
+    /// Send several emails in order over one authenticated connection,
+    /// returning each one's MIME or the reason it failed. Every message
+    /// keeps its own envelope, so recipients never mix. A message the
+    /// server refuses closes the connection, and the next message opens a
+    /// new one; failing to connect at all fails the whole batch.
+    ///
+    /// The saving is the connect, TLS handshake, EHLO and AUTH of every
+    /// message after the first. lettre doesn't use PIPELINING, so the
+    /// commands of each message still wait for their replies.
+    ///
+    /// Messages wait their turn under the account's rate limits, and a
+    /// server that throttles the batch pauses it (see `rate_limit`).
+    pub async fn send_batch(&self, emails: &[Email], password: &str) -> Result<Vec<Result<Vec<u8>>>> {
+        let limiter = self.limiter();
+        let mut connection = Some(self.connect(&limiter, password).await?);
+        let mut results = Vec::with_capacity(emails.len());
+        for email in emails {
+            let result = async {
+                let message = self.build_message(email)?;
+                let raw = message.formatted();
+                let open = match connection.take().filter(|c| !c.has_broken()) {
+                    Some(open) => open,
+                    None => self.connect(&limiter, password).await?,
+                };
+                limiter.send().await?;
+                let sent = connection
+                    .insert(open)
+                    .send(message.envelope(), &raw)
+                    .await
+                    .map_err(|e| network::smtp_error(&self.config.server, "sending email", e));
+                limiter.finish(sent).map(|_| raw)
+            }
+            .await;
+            results.push(result);
+        }
+        if let Some(mut open) = connection.filter(|c| !c.has_broken()) {
+            let _ = open.quit().await;
+        }
+        tracing::info!(
+            "Sent {} of {} emails over one connection",
+            results.iter().filter(|r| r.is_ok()).count(),
+            emails.len()
+        );
+        Ok(results)
+    }
+
+    /// Send pre-built MIME, such as a read receipt, to `to`.
+    pub async fn send_raw(&self, from: &str, to: &[String], raw: &[u8], password: &str) -> Result<()> {
+        let parse = |a: &str| {
+            a.parse::<Address>()
+                .map_err(|e| Error::Parse(format!("Invalid email address: {}", e)))
+        };
+        let recipients = to.iter().map(|a| parse(a)).collect::<Result<Vec<_>>>()?;
+        let envelope = Envelope::new(Some(parse(from)?), recipients)
+            .map_err(|e| Error::Protocol(format!("Invalid envelope: {}", e)))?;
+        let limiter = self.limiter();
+        limiter.connection().await?;
+        limiter.send().await?;
+        let sent = self.transport(password).await?
+            .send_raw(&envelope, raw)
+            .await
+            .map_err(|e| network::smtp_error(&self.config.server, "sending email", e));
+        limiter.finish(sent).map(|_| ())
+    }
+
+    /// lettre applies its timeout to opening the connection, including the
+    /// TLS handshake. A pinned certificate is checked first.
+    async fn transport(&self, password: &str) -> Result<AsyncSmtpTransport<Tokio1Executor>> {
+        let creds = Credentials::new(self.config.username.clone(), password.to_string());
+        let timeout = Some(self.config.timeouts.connect);
+        Ok(if self.config.use_tls {
+            certificate_pin::verify_pinned(
+                &self.config.server,
+                self.config.port,
+                self.config.timeouts,
+                self.config.pinned_fingerprint.as_deref(),
+            )
+            .await?;
+            AsyncSmtpTransport::<Tokio1Executor>::relay(&self.config.server)
+                .map_err(|e| network::smtp_error(&self.config.server, "setting up TLS", e))?
+                .port(self.config.port)
+                .credentials(creds)
+                .timeout(timeout)
+                .build()
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&self.config.server)
+                .port(self.config.port)
+                .credentials(creds)
+                .timeout(timeout)
+                .build()
+        })
+    }
+
+    /// The rate limits of this account on this server
+    fn limiter(&self) -> Arc<ServerLimiter> {
+        rate_limit::limiter(&self.config.server, &self.config.username)
+    }
+
+    /// One signed-in connection, set up like `transport`'s, for sending
+    /// several messages before QUIT
+    async fn connect(&self, limiter: &ServerLimiter, password: &str) -> Result<AsyncSmtpConnection> {
+        limiter.connection().await?;
+        let connected = self.open_connection(password).await;
+        limiter.finish(connected)
+    }
+
+    async fn open_connection(&self, password: &str) -> Result<AsyncSmtpConnection> {
+        let tls = if self.config.use_tls {
+            certificate_pin::verify_pinned(
+                &self.config.server,
+                self.config.port,
+                self.config.timeouts,
+                self.config.pinned_fingerprint.as_deref(),
+            )
+            .await?;
+            Some(
+                TlsParameters::new(self.config.server.clone())
+                    .map_err(|e| network::smtp_error(&self.config.server, "setting up TLS", e))?,
+            )
+        } else {
+            None
+        };
+        let mut connection = AsyncSmtpConnection::connect_tokio1(
+            (self.config.server.as_str(), self.config.port),
+            Some(self.config.timeouts.connect),
+            &ClientId::default(),
+            tls,
+            None,
+        )
+        .await
+        .map_err(|e| network::smtp_error(&self.config.server, "connecting", e))?;
+        let creds = Credentials::new(self.config.username.clone(), password.to_string());
+        connection
+            .auth(DEFAULT_MECHANISMS, &creds)
+            .await
+            .map_err(|e| network::smtp_error(&self.config.server, "signing in", e))?;
+        Ok(connection)
+    }
+
+    /// Build the MIME message for an email
+    pub fn build_message(&self, email: &Email) -> Result<Message> {
         let mut message_builder = Message::builder()
             .from(self.parse_mailbox(&email.from, email.from_name.as_deref())?)
             .subject(&email.subject);
@@ -85,9 +416,49 @@ impl SmtpClient {
         for bcc in &email.bcc {
             message_builder = message_builder.bcc(self.parse_mailbox(bcc, None)?);
         }
+        if let Some(reply_to) = &email.reply_to {
+            message_builder = message_builder.reply_to(self.parse_mailbox(reply_to, None)?);
+        }
+        if let Some(envelope_from) = &email.envelope_from {
+            // lettre otherwise takes the envelope from the headers; a custom
+            // one must list every recipient, Bcc included
+            let parse = |a: &String| {
+                a.parse::<Address>()
+                    .map_err(|e| Error::Parse(format!("Invalid email address: {}", e)))
+            };
+            let recipients = email
+                .to
+                .iter()
+                .chain(&email.cc)
+                .chain(&email.bcc)
+                .map(|a| Ok(self.parse_mailbox(a, None)?.email))
+                .collect::<Result<Vec<_>>>()?;
+            let envelope = Envelope::new(Some(parse(envelope_from)?), recipients)
+                .map_err(|e| Error::Protocol(format!("Invalid envelope: {}", e)))?;
+            message_builder = message_builder.envelope(envelope);
+        }
+        if let Some(address) = &email.disposition_notification_to {
+            message_builder = message_builder.header(DispositionNotificationTo(address.clone()));
+        }
+        if let Some((x_priority, importance)) = email.priority.header_values() {
+            message_builder = message_builder
+                .header(XPriority(x_priority.to_string()))
+                .header(Importance(importance.to_string()));
+        }
+
+        #[cfg(feature = "openpgp")]
+        if let Some(pgp) = &email.pgp {
+            let body = match &email.body_html {
+                Some(html) => MultiPart::alternative_plain_html(email.body_text.clone(), html.clone()),
+                None => MultiPart::mixed().singlepart(SinglePart::plain(email.body_text.clone())),
+            };
+            return message_builder
+                .multipart(crate::service::openpgp::protect(pgp, body)?)
+                .map_err(|e| Error::Protocol(format!("Failed to build message: {}", e)));
+        }
 
         // Build body
-        let message = if let Some(html) = &email.body_html {
+        if let Some(html) = &email.body_html {
             message_builder
                 .multipart(
                     MultiPart::alternative()
@@ -102,37 +473,12 @@ impl SmtpClient {
                                 .body(html.clone()),
                         ),
                 )
-                .map_err(|e| Error::Protocol(format!("Failed to build message: {}", e)))?
+                .map_err(|e| Error::Protocol(format!("Failed to build message: {}", e)))
         } else {
             message_builder
                 .body(email.body_text.clone())
-                .map_err(|e| Error::Protocol(format!("Failed to build message: {}", e)))?
-        };
-
-        // Create transport
-        let creds = Credentials::new(self.config.username.clone(), password.to_string());
-
-        let transport = if self.config.use_tls {
-            AsyncSmtpTransport::<Tokio1Executor>::relay(&self.config.server)
-                .map_err(|e| Error::Protocol(format!("Failed to create SMTP transport: {}", e)))?
-                .port(self.config.port)
-                .credentials(creds)
-                .build()
-        } else {
-            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&self.config.server)
-                .port(self.config.port)
-                .credentials(creds)
-                .build()
-        };
-
-        // Send the email
-        transport
-            .send(message)
-            .await
-            .map_err(|e| Error::Protocol(format!("Failed to send email: {}", e)))?;
-
-        tracing::info!("Email sent successfully");
-        Ok(())
+                .map_err(|e| Error::Protocol(format!("Failed to build message: {}", e)))
+        }
     }
 
     /// Parse email address into Mailbox
@@ -140,11 +486,11 @@ impl SmtpClient {
         let mailbox = if let Some(name) = name {
             format!("{} <{}>", name, email)
                 .parse()
-                .map_err(|e| Error::Protocol(format!("Invalid email address: {}", e)))?
+                .map_err(|e| Error::Parse(format!("Invalid email address: {}", e)))?
         } else {
             email
                 .parse()
-                .map_err(|e| Error::Protocol(format!("Invalid email address: {}", e)))?
+                .map_err(|e| Error::Parse(format!("Invalid email address: {}", e)))?
         };
         Ok(mailbox)
     }
@@ -153,6 +499,9 @@ impl SmtpClient {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::service::protocols::RateLimits;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 
     #[test]
     fn test_smtp_client_creation() {
@@ -161,6 +510,8 @@ mod tests {
             port: 587,
             use_tls: true,
             username: "test@example.com".to_string(),
+            timeouts: NetworkTimeouts::default(),
+            pinned_fingerprint: None,
         };
         let client = SmtpClient::new(config);
         assert!(client.is_ok());
@@ -206,4 +557,233 @@ mod tests {
         assert_eq!(email.to.len(), 2);
         assert_eq!(email.cc.len(), 1);
     }
+
+    #[test]
+    fn test_build_message_keeps_headers() {
+        let client = SmtpClient::new(SmtpConfig {
+            server: "smtp.example.com".to_string(),
+            port: 587,
+            use_tls: true,
+            username: "sender@example.com".to_string(),
+            timeouts: NetworkTimeouts::default(),
+            pinned_fingerprint: None,
+        })
+        .unwrap();
+        let mut email = Email::simple(
+            "sender@example.com".to_string(),
+            "recipient@example.com".to_string(),
+            "Status".to_string(),
+            "All good".to_string(),
+        );
+        email.cc.push("cc@example.com".to_string());
+        let raw = String::from_utf8(client.build_message(&email).unwrap().formatted()).unwrap();
+        assert!(raw.contains("Subject: Status"));
+        assert!(raw.contains("Cc: cc@example.com"));
+        assert!(raw.contains("All good"));
+        assert!(!raw.contains("X-Priority"));
+
+        email.priority = MessagePriority::High;
+        let raw = String::from_utf8(client.build_message(&email).unwrap().formatted()).unwrap();
+        assert!(raw.contains("X-Priority: 1 (Highest)"));
+        assert!(raw.contains("Importance: high"));
+    }
+
+    #[test]
+    fn test_build_message_from_identity() {
+        let client = SmtpClient::new(SmtpConfig {
+            server: "smtp.example.com".to_string(),
+            port: 587,
+            use_tls: true,
+            username: "me@example.com".to_string(),
+            timeouts: NetworkTimeouts::default(),
+            pinned_fingerprint: None,
+        })
+        .unwrap();
+        let mut email = Email::simple(
+            "support@example.com".to_string(),
+            "customer@example.org".to_string(),
+            "Your ticket".to_string(),
+            "Fixed".to_string(),
+        );
+        email.from_name = Some("Example Support".to_string());
+        email.reply_to = Some("help@example.com".to_string());
+        let raw = String::from_utf8(client.build_message(&email).unwrap().formatted()).unwrap();
+        assert!(raw.contains("From: \"Example Support\" <support@example.com>"));
+        assert!(raw.contains("Reply-To: help@example.com"));
+        let message = client.build_message(&email).unwrap();
+        assert_eq!(message.envelope().from().map(|a| a.to_string()).as_deref(), Some("support@example.com"));
+    }
+
+    #[test]
+    fn test_build_message_envelope_from() {
+        let client = SmtpClient::new(SmtpConfig {
+            server: "smtp.example.com".to_string(),
+            port: 587,
+            use_tls: true,
+            username: "me@example.com".to_string(),
+            timeouts: NetworkTimeouts::default(),
+            pinned_fingerprint: None,
+        })
+        .unwrap();
+        let mut email = Email::simple(
+            "news@example.com".to_string(),
+            "reader@example.org".to_string(),
+            "Newsletter".to_string(),
+            "This month".to_string(),
+        );
+        email.bcc.push("archive@example.com".to_string());
+        email.envelope_from = Some("bounces+news@mailer.example.net".to_string());
+        let message = client.build_message(&email).unwrap();
+        let envelope = message.envelope();
+        assert_eq!(envelope.from().map(|a| a.to_string()).as_deref(), Some("bounces+news@mailer.example.net"));
+        assert_eq!(envelope.to().len(), 2);
+        let raw = String::from_utf8(message.formatted()).unwrap();
+        assert!(raw.contains("From: news@example.com"));
+        assert!(!raw.contains("bounces+news"));
+
+        email.envelope_from = Some("not an address".to_string());
+        assert!(client.build_message(&email).is_err());
+    }
+
+    /// Connections opened, commands answered and messages accepted by
+    /// [`fake_smtp_server`]
+    #[derive(Default)]
+    struct SmtpCounts {
+        connections: AtomicUsize,
+        commands: AtomicUsize,
+        messages: AtomicUsize,
+    }
+
+    /// Minimal SMTP server on localhost that accepts any login and
+    /// message. Returns its port and what it has seen.
+    async fn fake_smtp_server() -> (u16, Arc<SmtpCounts>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let counts = Arc::new(SmtpCounts::default());
+        let seen = counts.clone();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                seen.connections.fetch_add(1, Ordering::SeqCst);
+                let seen = seen.clone();
+                tokio::spawn(async move {
+                    let (read, mut write) = socket.into_split();
+                    let mut lines = BufReader::new(read).lines();
+                    write.write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+                    let mut in_data = false;
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        let reply: &[u8] = if in_data {
+                            if line != "." {
+                                continue;
+                            }
+                            in_data = false;
+                            seen.messages.fetch_add(1, Ordering::SeqCst);
+                            b"250 Queued\r\n"
+                        } else {
+                            seen.commands.fetch_add(1, Ordering::SeqCst);
+                            match line.get(..4).unwrap_or(&line).to_ascii_uppercase().as_str() {
+                                "EHLO" => b"250-localhost\r\n250 AUTH PLAIN LOGIN\r\n",
+                                "AUTH" => b"235 Authenticated\r\n",
+                                "DATA" => {
+                                    in_data = true;
+                                    b"354 Go ahead\r\n"
+                                }
+                                "QUIT" => {
+                                    let _ = write.write_all(b"221 Bye\r\n").await;
+                                    break;
+                                }
+                                _ => b"250 OK\r\n",
+                            }
+                        };
+                        if write.write_all(reply).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+        (port, counts)
+    }
+
+    #[tokio::test]
+    async fn test_send_batch_reuses_one_connection() {
+        // Far more than the default limits allow in a minute
+        rate_limit::configure(RateLimits { sends_per_minute: 0, connections_per_minute: 0, fetch_concurrency: 4 });
+        let (port, counts) = fake_smtp_server().await;
+        let client = SmtpClient::new(SmtpConfig {
+            server: "127.0.0.1".to_string(),
+            port,
+            use_tls: false,
+            username: "me@example.com".to_string(),
+            timeouts: NetworkTimeouts::default(),
+            pinned_fingerprint: None,
+        })
+        .unwrap();
+        let emails: Vec<Email> = (0..50)
+            .map(|i| {
+                Email::simple(
+                    "me@example.com".to_string(),
+                    format!("reader{}@example.org", i),
+                    format!("Queued {}", i),
+                    "Hello".to_string(),
+                )
+            })
+            .collect();
+
+        // EHLO and AUTH once, MAIL, RCPT and DATA per message, then QUIT
+        let results = client.send_batch(&emails, "secret").await.unwrap();
+        assert_eq!(results.len(), 50);
+        assert!(results.iter().all(|r| r.is_ok()));
+        assert_eq!(counts.messages.load(Ordering::SeqCst), 50);
+        assert_eq!(counts.connections.load(Ordering::SeqCst), 1);
+        assert_eq!(counts.commands.load(Ordering::SeqCst), 2 + 50 * 3 + 1);
+        assert!(String::from_utf8_lossy(results[7].as_ref().unwrap()).contains("To: reader7@example.org"));
+
+        // Sent one at a time, every message connects and signs in again
+        for email in &emails {
+            client.send_email(email.clone(), "secret").await.unwrap();
+        }
+        assert_eq!(counts.connections.load(Ordering::SeqCst), 51);
+        assert!(counts.commands.load(Ordering::SeqCst) >= 153 + 50 * 5);
+
+        // A message that can't be built fails alone
+        let mut broken = emails[..3].to_vec();
+        broken[1].to = vec!["not an address".to_string()];
+        let results = client.send_batch(&broken, "secret").await.unwrap();
+        assert!(results[0].is_ok() && results[1].is_err() && results[2].is_ok());
+    }
+
+    #[test]
+    fn test_read_receipt_request_and_reply() {
+        let client = SmtpClient::new(SmtpConfig {
+            server: "smtp.example.com".to_string(),
+            port: 587,
+            use_tls: true,
+            username: "sender@example.com".to_string(),
+            timeouts: NetworkTimeouts::default(),
+            pinned_fingerprint: None,
+        })
+        .unwrap();
+        let mut email = Email::simple(
+            "sender@example.com".to_string(),
+            "recipient@example.com".to_string(),
+            "Minutes".to_string(),
+            "Attached".to_string(),
+        );
+        email.disposition_notification_to = Some("sender@example.com".to_string());
+        let raw = String::from_utf8(client.build_message(&email).unwrap().formatted()).unwrap();
+        assert!(raw.contains("Disposition-Notification-To: sender@example.com"));
+
+        let incoming = "From: Boss <boss@example.com>\r\nTo: me@example.com\r\nSubject: Minutes\r\n\
+                        Message-ID: <m1@example.com>\r\nDisposition-Notification-To: Boss <boss@example.com>\r\n\r\nHi";
+        let receipt = ReadReceipt::requested_by(incoming).unwrap();
+        assert_eq!(receipt.to, "boss@example.com");
+        assert_eq!(receipt.original_message_id.as_deref(), Some("m1@example.com"));
+        assert!(ReadReceipt::requested_by("Subject: Hi\r\n\r\nNo receipt").is_none());
+
+        let mdn = String::from_utf8(receipt.to_mime("me@example.com", false)).unwrap();
+        assert!(mdn.contains("Subject: Read: Minutes"));
+        assert!(mdn.contains("report-type=disposition-notification"));
+        assert!(mdn.contains("Original-Message-ID: <m1@example.com>"));
+        assert!(mdn.contains("Disposition: manual-action/MDN-sent-manually; displayed"));
+    }
 }
@@ -1,20 +1,35 @@
 //! Security service
 //!
 //! Handles local credential protection, message crypto signal detection
-//! (PGP/S-MIME), and phishing risk analysis.
+//! (PGP/S-MIME), phishing risk analysis, and (with the `openpgp` feature)
+//! the OpenPGP key store.
 
+use crate::common::profile::ProfilePaths;
 use crate::common::{Error, Result};
+#[cfg(feature = "openpgp")]
+use crate::service::openpgp::{self, PgpKeyInfo};
 use aes_gcm::aead::{Aead, KeyInit};
 use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::Argon2;
 use base64::{engine::general_purpose::STANDARD, Engine as _};
+#[cfg(feature = "openpgp")]
+use pgp::composed::SignedPublicKey;
 use rand::RngCore;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
 
 const ENCRYPTION_PREFIX: &str = "WXM2:";
 const AES_NONCE_LEN: usize = 12;
+const MASTER_SALT_LEN: usize = 16;
+const MASTER_VERIFIER: &[u8] = b"wixen-mail-master-password-v1";
+const KEYRING_SERVICE: &str = "wixen-mail";
+const KEYRING_MASTER_ENTRY: &str = "master-password-key";
+#[cfg(target_os = "windows")]
+const KEYRING_KEY_ENTRY: &str = "master-key";
 const TRUSTED_BRAND_DOMAINS: [&str; 4] = ["paypal.com", "google.com", "microsoft.com", "apple.com"];
 const SCORE_URGENCY: u8 = 20;
 const SCORE_SENDER_MISMATCH: u8 = 10;
@@ -32,6 +47,19 @@ fn ip_url_re() -> &'static Regex {
     })
 }
 
+/// Credential store entry `entry` for the active profile. Named profiles
+/// get their own entries; the default profile keeps the unsuffixed names.
+fn keyring_entry(entry: &str) -> String {
+    profile_entry_name(entry, ProfilePaths::active().and_then(|p| p.name.as_deref()))
+}
+
+fn profile_entry_name(entry: &str, profile: Option<&str>) -> String {
+    match profile {
+        Some(name) => format!("{}@{}", entry, name),
+        None => entry.to_string(),
+    }
+}
+
 fn deceptive_link_re() -> &'static Regex {
     static LINK_RE: OnceLock<Regex> = OnceLock::new();
     LINK_RE.get_or_init(|| {
@@ -71,10 +99,17 @@ pub struct MessageSecurityReport {
 }
 
 /// Security service for credential management and security operations
+#[derive(Clone)]
 pub struct SecurityService {
     key: [u8; 32],
 }
 
+impl std::fmt::Debug for SecurityService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SecurityService").finish_non_exhaustive()
+    }
+}
+
 impl SecurityService {
     /// Returns configured trusted domains from `WIXEN_TRUSTED_DOMAINS` (comma-separated),
     /// or falls back to the built-in default list when unset/empty.
@@ -96,9 +131,13 @@ impl SecurityService {
     }
 
     fn key_path() -> Result<PathBuf> {
-        let base = dirs::config_dir()
-            .ok_or_else(|| Error::Config("Could not determine config directory".to_string()))?
-            .join("wixen-mail");
+        // Named profiles keep their own key and master password
+        let base = match ProfilePaths::active().filter(|p| p.name.is_some()) {
+            Some(profile) => profile.config_dir.clone(),
+            None => dirs::config_dir()
+                .ok_or_else(|| Error::Config("Could not determine config directory".to_string()))?
+                .join("wixen-mail"),
+        };
         if !base.exists() {
             fs::create_dir_all(&base)
                 .map_err(|e| Error::Config(format!("Failed to create config directory: {}", e)))?;
@@ -122,7 +161,7 @@ impl SecurityService {
     /// Store/retrieve master key via OS credential manager (Windows Credential Manager).
     #[cfg(target_os = "windows")]
     fn load_or_create_key_keyring() -> Result<[u8; 32]> {
-        let entry = keyring::Entry::new("wixen-mail", "master-key")
+        let entry = keyring::Entry::new(KEYRING_SERVICE, &keyring_entry(KEYRING_KEY_ENTRY))
             .map_err(|e| Error::Security(format!("Failed to access credential store: {}", e)))?;
 
         // Try loading existing key
@@ -184,6 +223,15 @@ impl SecurityService {
         })
     }
 
+    /// Derive a security service from a master password with Argon2id.
+    pub fn from_master_password(password: &str, salt: &[u8]) -> Result<Self> {
+        let mut key = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(password.as_bytes(), salt, &mut key)
+            .map_err(|e| Error::Security(format!("Key derivation failed: {}", e)))?;
+        Ok(Self { key })
+    }
+
     /// Encrypt data for local-at-rest storage.
     ///
     /// Uses AES-256-GCM with machine-local key derivation.
@@ -404,10 +452,354 @@ impl SecurityService {
     }
 }
 
+/// On-disk record of the master password: salt plus an encrypted verifier.
+///
+/// The password itself is never stored; unlocking derives the key again and
+/// checks that it decrypts the verifier.
+#[derive(Debug, Serialize, Deserialize)]
+struct MasterPasswordRecord {
+    version: u32,
+    salt: String,
+    verifier: String,
+}
+
+/// Master password setup, unlock, and change for encryption at rest.
+pub struct MasterPasswordStore {
+    path: PathBuf,
+}
+
+impl MasterPasswordStore {
+    /// Store backed by a specific file
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Store in the application config directory
+    pub fn default_location() -> Result<Self> {
+        Ok(Self::new(SecurityService::key_path()?.with_file_name("master_password.json")))
+    }
+
+    /// Whether a master password has been set
+    pub fn is_set(&self) -> bool {
+        self.path.exists()
+    }
+
+    /// Derive the key from `password`, failing cleanly if it is wrong.
+    ///
+    /// Nothing on disk is modified, so a wrong password cannot corrupt data.
+    pub fn unlock(&self, password: &str) -> Result<SecurityService> {
+        let content = fs::read_to_string(&self.path)
+            .map_err(|e| Error::Security(format!("Failed reading master password record: {}", e)))?;
+        let record: MasterPasswordRecord = serde_json::from_str(&content)
+            .map_err(|e| Error::Security(format!("Invalid master password record: {}", e)))?;
+        let salt = STANDARD
+            .decode(&record.salt)
+            .map_err(|e| Error::Security(format!("Invalid master password salt: {}", e)))?;
+        let service = SecurityService::from_master_password(password, &salt)?;
+        match service.decrypt(record.verifier.as_bytes()) {
+            Ok(plain) if plain == MASTER_VERIFIER => Ok(service),
+            _ => Err(Error::Security("Incorrect master password".to_string())),
+        }
+    }
+
+    /// Set a new master password.
+    ///
+    /// `reencrypt` receives the new service and must re-encrypt every stored
+    /// secret (normally `MessageCache::rekey_then`). It is also handed a
+    /// function that saves the new record, to call just before its changes
+    /// commit; if it doesn't, the record is saved once it returns. On failure
+    /// the previous record is put back, so the previous password still opens
+    /// the data that was left unchanged.
+    pub fn set(
+        &self,
+        password: &str,
+        reencrypt: impl FnOnce(&SecurityService, &dyn Fn() -> Result<()>) -> Result<()>,
+    ) -> Result<SecurityService> {
+        if password.is_empty() {
+            return Err(Error::Security("Master password cannot be empty".to_string()));
+        }
+        let mut salt = [0u8; MASTER_SALT_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        let service = SecurityService::from_master_password(password, &salt)?;
+        let verifier = String::from_utf8(service.encrypt(MASTER_VERIFIER)?)
+            .map_err(|e| Error::Security(format!("Encrypted verifier not UTF-8: {}", e)))?;
+        let record = MasterPasswordRecord {
+            version: 1,
+            salt: STANDARD.encode(salt),
+            verifier,
+        };
+        let content = serde_json::to_string_pretty(&record)
+            .map_err(|e| Error::Security(format!("Failed to serialize master password record: {}", e)))?;
+
+        let pending = self.path.with_extension("json.new");
+        let previous = self.path.with_extension("json.old");
+        write_private(&pending, &content)?;
+        let had_previous = self.is_set();
+        if had_previous {
+            if let Err(e) = fs::copy(&self.path, &previous) {
+                let _ = fs::remove_file(&pending);
+                return Err(Error::Security(format!("Failed to back up master password record: {}", e)));
+            }
+        }
+
+        let stored = Cell::new(false);
+        let store_record = || -> Result<()> {
+            if !stored.get() {
+                fs::rename(&pending, &self.path)
+                    .map_err(|e| Error::Security(format!("Failed to store master password record: {}", e)))?;
+                stored.set(true);
+            }
+            Ok(())
+        };
+        if let Err(e) = reencrypt(&service, &store_record).and_then(|()| store_record()) {
+            let _ = fs::remove_file(&pending);
+            if stored.get() {
+                // The data was not rekeyed, so it needs the old record back
+                let _ = if had_previous {
+                    fs::rename(&previous, &self.path)
+                } else {
+                    fs::remove_file(&self.path)
+                };
+            }
+            return Err(e);
+        }
+        let _ = fs::remove_file(&previous);
+        // A remembered key for the old password is no longer valid.
+        Self::forget_remembered();
+        Ok(service)
+    }
+
+    /// Verify `current`, then switch to `new` and re-encrypt stored secrets.
+    pub fn change(
+        &self,
+        current: &str,
+        new: &str,
+        reencrypt: impl FnOnce(&SecurityService, &dyn Fn() -> Result<()>) -> Result<()>,
+    ) -> Result<SecurityService> {
+        self.unlock(current)?;
+        self.set(new, reencrypt)
+    }
+
+    /// Remember the derived key in the OS credential store so the next start
+    /// does not prompt. Without this the key lives only for the session.
+    pub fn remember(service: &SecurityService) -> Result<()> {
+        let entry = keyring::Entry::new(KEYRING_SERVICE, &keyring_entry(KEYRING_MASTER_ENTRY))
+            .map_err(|e| Error::Security(format!("Failed to access credential store: {}", e)))?;
+        entry
+            .set_password(&STANDARD.encode(service.key))
+            .map_err(|e| Error::Security(format!("Failed storing key in credential store: {}", e)))
+    }
+
+    /// Key remembered by `remember`, if it still unlocks this store.
+    pub fn recall(&self) -> Option<SecurityService> {
+        let entry = keyring::Entry::new(KEYRING_SERVICE, &keyring_entry(KEYRING_MASTER_ENTRY)).ok()?;
+        let decoded = STANDARD.decode(entry.get_password().ok()?.trim()).ok()?;
+        let service = SecurityService { key: decoded.try_into().ok()? };
+        let content = fs::read_to_string(&self.path).ok()?;
+        let record: MasterPasswordRecord = serde_json::from_str(&content).ok()?;
+        match service.decrypt(record.verifier.as_bytes()) {
+            Ok(plain) if plain == MASTER_VERIFIER => Some(service),
+            _ => None,
+        }
+    }
+
+    /// Remove any remembered key from the OS credential store.
+    pub fn forget_remembered() {
+        if let Ok(entry) = keyring::Entry::new(KEYRING_SERVICE, &keyring_entry(KEYRING_MASTER_ENTRY)) {
+            let _ = entry.delete_credential();
+        }
+    }
+}
+
+/// Imported OpenPGP keys, one armored file per key in the config directory.
+///
+/// Secret keys stay protected by their own OpenPGP passphrase, which is
+/// asked for when signing or decrypting.
+#[cfg(feature = "openpgp")]
+pub struct PgpKeyStore {
+    dir: PathBuf,
+}
+
+#[cfg(feature = "openpgp")]
+impl PgpKeyStore {
+    /// Store backed by a specific directory
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Store in the application config directory
+    pub fn default_location() -> Result<Self> {
+        Ok(Self::new(SecurityService::key_path()?.with_file_name("pgp_keys")))
+    }
+
+    /// Import an armored public or secret key, replacing any earlier copy.
+    pub fn import(&self, armored: &str) -> Result<PgpKeyInfo> {
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| Error::Security(format!("Failed to create key directory: {}", e)))?;
+        let info = match openpgp::parse_secret_key(armored) {
+            Ok(key) => PgpKeyInfo::of_secret(&key),
+            Err(_) => PgpKeyInfo::of_public(&openpgp::parse_public_key(armored)?),
+        };
+        write_private(&self.key_path(&info.fingerprint, info.secret), armored)?;
+        tracing::info!("Imported OpenPGP key {}", info.fingerprint);
+        Ok(info)
+    }
+
+    /// All imported keys, secret keys first, then by user ID.
+    pub fn list(&self) -> Result<Vec<PgpKeyInfo>> {
+        let mut keys: Vec<PgpKeyInfo> = self.load()?.into_iter().map(|(info, _)| info).collect();
+        keys.sort_by(|a, b| b.secret.cmp(&a.secret).then_with(|| a.user_ids.cmp(&b.user_ids)));
+        Ok(keys)
+    }
+
+    /// Delete a key (both halves).
+    pub fn remove(&self, fingerprint: &str) -> Result<()> {
+        for secret in [false, true] {
+            let path = self.key_path(fingerprint, secret);
+            if path.exists() {
+                fs::remove_file(&path)
+                    .map_err(|e| Error::Security(format!("Failed to remove key: {}", e)))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Public keys with a user ID for `email`, for encrypting to it or
+    /// verifying its signatures.
+    pub fn public_keys_for(&self, email: &str) -> Result<Vec<SignedPublicKey>> {
+        let mut keys = Vec::new();
+        for (info, armored) in self.load()? {
+            if !info.secret && info.matches_email(email) {
+                keys.push(openpgp::parse_public_key(&armored)?);
+            }
+        }
+        Ok(keys)
+    }
+
+    /// The secret key for `email`, if one was imported.
+    pub fn secret_key_for(&self, email: &str, passphrase: &str) -> Result<Option<openpgp::SecretKey>> {
+        for (info, armored) in self.load()? {
+            if info.secret && info.matches_email(email) {
+                return Ok(Some(openpgp::SecretKey {
+                    key: openpgp::parse_secret_key(&armored)?,
+                    passphrase: passphrase.to_string(),
+                }));
+            }
+        }
+        Ok(None)
+    }
+
+    fn key_path(&self, fingerprint: &str, secret: bool) -> PathBuf {
+        let suffix = if secret { "sec" } else { "pub" };
+        self.dir.join(format!("{}.{}.asc", fingerprint, suffix))
+    }
+
+    /// Every readable key file with its armored text; unreadable ones are
+    /// logged and skipped.
+    fn load(&self) -> Result<Vec<(PgpKeyInfo, String)>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let entries = fs::read_dir(&self.dir)
+            .map_err(|e| Error::Security(format!("Failed to read key directory: {}", e)))?;
+        let mut keys = Vec::new();
+        for path in entries.flatten().map(|entry| entry.path()) {
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+            let secret = name.ends_with(".sec.asc");
+            if !secret && !name.ends_with(".pub.asc") {
+                continue;
+            }
+            let parsed = fs::read_to_string(&path)
+                .map_err(|e| Error::Security(e.to_string()))
+                .and_then(|armored| {
+                    let info = if secret {
+                        PgpKeyInfo::of_secret(&openpgp::parse_secret_key(&armored)?)
+                    } else {
+                        PgpKeyInfo::of_public(&openpgp::parse_public_key(&armored)?)
+                    };
+                    Ok((info, armored))
+                });
+            match parsed {
+                Ok(key) => keys.push(key),
+                Err(e) => tracing::warn!("Skipping OpenPGP key {}: {}", path.display(), e),
+            }
+        }
+        Ok(keys)
+    }
+}
+
+/// Write a file readable only by the current user (0o600 on Unix).
+fn write_private(path: &Path, content: &str) -> Result<()> {
+    fs::write(path, content)
+        .map_err(|e| Error::Security(format!("Failed writing {}: {}", path.display(), e)))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+            .map_err(|e| Error::Security(format!("Failed setting permissions: {}", e)))?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn temp_store(name: &str) -> MasterPasswordStore {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let dir = std::env::temp_dir().join(format!("wixen_mail_test_{}_{}", name, nanos));
+        fs::create_dir_all(&dir).unwrap();
+        MasterPasswordStore::new(dir.join("master_password.json"))
+    }
+
+    #[test]
+    fn test_master_password_unlock() {
+        let store = temp_store("master_unlock");
+        assert!(!store.is_set());
+        let service = store.set("correct horse", |_, _| Ok(())).unwrap();
+        assert!(store.is_set());
+
+        let unlocked = store.unlock("correct horse").unwrap();
+        let encrypted = service.encrypt(b"secret").unwrap();
+        assert_eq!(unlocked.decrypt(&encrypted).unwrap(), b"secret");
+
+        let err = store.unlock("wrong").unwrap_err();
+        assert!(err.to_string().contains("Incorrect master password"));
+    }
+
+    #[test]
+    fn test_master_password_change_rolls_back_on_failure() {
+        let store = temp_store("master_change");
+        store.set("old", |_, _| Ok(())).unwrap();
+
+        assert!(store.change("not-old", "new", |_, _| Ok(())).is_err());
+        let failed = store.change("old", "new", |_, _| Err(Error::Other("disk full".into())));
+        assert!(failed.is_err());
+        assert!(store.unlock("old").is_ok());
+
+        // Failing to commit after the record was stored puts the old one back
+        let failed = store.change("old", "new", |_, store_record| {
+            store_record()?;
+            Err(Error::Database("commit failed".into()))
+        });
+        assert!(failed.is_err());
+        assert!(store.unlock("old").is_ok());
+
+        store.change("old", "new", |_, _| Ok(())).unwrap();
+        assert!(store.unlock("new").is_ok());
+        assert!(store.unlock("old").is_err());
+    }
+
+    #[test]
+    fn test_keyring_entries_are_per_profile() {
+        assert_eq!(profile_entry_name(KEYRING_MASTER_ENTRY, None), "master-password-key");
+        assert_eq!(profile_entry_name(KEYRING_MASTER_ENTRY, Some("work")), "master-password-key@work");
+        assert_ne!(profile_entry_name("master-key", Some("work")), profile_entry_name("master-key", Some("home")));
+    }
+
     #[test]
     fn test_security_service_creation() {
         let service = SecurityService::new();
@@ -0,0 +1,174 @@
+//! S/MIME signature verification
+//!
+//! Checks `multipart/signed` messages with an `application/pkcs7-signature`
+//! part (RFC 8551): the signature must match the signed part, and the
+//! signer's certificate must chain to the system trust store.
+//!
+//! Only built with the `smime` cargo feature.
+
+use crate::common::{Error, Result};
+use crate::service::multipart::{canonical_line_endings, part_body, split_parts, SecuredMultipart};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use openssl::nid::Nid;
+use openssl::pkcs7::{Pkcs7, Pkcs7Flags};
+use openssl::stack::Stack;
+use openssl::x509::store::{X509Store, X509StoreBuilder};
+use openssl::x509::X509Ref;
+
+const SIGNATURE_PROTOCOLS: [&str; 2] = ["application/pkcs7-signature", "application/x-pkcs7-signature"];
+
+/// Outcome of checking an S/MIME signature
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmimeStatus {
+    /// Signature matches and the certificate is trusted
+    Valid,
+    /// Signature matches but the certificate doesn't chain to a trusted root
+    Untrusted,
+    /// Signature doesn't match the content (altered) or can't be read
+    Invalid,
+}
+
+/// S/MIME signature check for one message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmimeVerification {
+    pub status: SmimeStatus,
+    /// Signer's email address, or common name when the certificate has none
+    pub signer: Option<String>,
+}
+
+/// Verify the S/MIME signature of a raw message against the system trust
+/// store. `None` if the message isn't S/MIME signed.
+pub fn verify(raw: &str) -> Option<SmimeVerification> {
+    let store = match system_store() {
+        Ok(store) => store,
+        Err(e) => {
+            tracing::warn!("System certificate store unavailable: {}", e);
+            X509StoreBuilder::new().ok()?.build()
+        }
+    };
+    verify_with_store(raw, &store)
+}
+
+fn verify_with_store(raw: &str, store: &X509Store) -> Option<SmimeVerification> {
+    let structure = SecuredMultipart::detect(raw)?;
+    if !structure.signed || !SIGNATURE_PROTOCOLS.contains(&structure.protocol.as_str()) {
+        return None;
+    }
+    let parts = split_parts(raw, &structure.boundary);
+    let (signed, signature) = (parts.first()?, parts.get(1)?);
+    let invalid = SmimeVerification { status: SmimeStatus::Invalid, signer: None };
+
+    let encoded: String = part_body(signature).split_whitespace().collect();
+    let Ok(pkcs7) = STANDARD.decode(encoded).map_err(|e| e.to_string())
+        .and_then(|der| Pkcs7::from_der(&der).map_err(|e| e.to_string()))
+    else {
+        return Some(invalid);
+    };
+    let content = canonical_line_endings(signed);
+    let Ok(no_certs) = Stack::new() else { return Some(invalid) };
+    let signer = pkcs7
+        .signers(&no_certs, Pkcs7Flags::empty())
+        .ok()
+        .and_then(|certs| certs.iter().next().and_then(signer_identity));
+
+    let check = |flags: Pkcs7Flags| {
+        pkcs7.verify(&no_certs, store, Some(content.as_bytes()), None, flags | Pkcs7Flags::BINARY).is_ok()
+    };
+    let status = if check(Pkcs7Flags::empty()) {
+        SmimeStatus::Valid
+    } else if check(Pkcs7Flags::NOVERIFY) {
+        SmimeStatus::Untrusted
+    } else {
+        SmimeStatus::Invalid
+    };
+    Some(SmimeVerification { status, signer })
+}
+
+fn system_store() -> Result<X509Store> {
+    let mut builder = X509StoreBuilder::new()
+        .map_err(|e| Error::Security(format!("Failed to create certificate store: {}", e)))?;
+    builder
+        .set_default_paths()
+        .map_err(|e| Error::Security(format!("Failed to load system certificates: {}", e)))?;
+    Ok(builder.build())
+}
+
+/// The email address in a certificate's alternative names or subject,
+/// falling back to its common name.
+fn signer_identity(cert: &X509Ref) -> Option<String> {
+    let from_alt_names = cert
+        .subject_alt_names()
+        .and_then(|names| names.iter().find_map(|name| name.email().map(str::to_string)));
+    let from_subject = |nid: Nid| {
+        cert.subject_name()
+            .entries_by_nid(nid)
+            .next()
+            .map(|entry| String::from_utf8_lossy(entry.data().as_slice()).into_owned())
+    };
+    from_alt_names
+        .or_else(|| from_subject(Nid::PKCS9_EMAILADDRESS))
+        .or_else(|| from_subject(Nid::COMMONNAME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::asn1::Asn1Time;
+    use openssl::hash::MessageDigest;
+    use openssl::pkey::{PKey, Private};
+    use openssl::rsa::Rsa;
+    use openssl::x509::{X509NameBuilder, X509};
+
+    fn self_signed(email: &str) -> (X509, PKey<Private>) {
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_nid(Nid::COMMONNAME, "Alice").unwrap();
+        name.append_entry_by_nid(Nid::PKCS9_EMAILADDRESS, email).unwrap();
+        let name = name.build();
+        let mut cert = X509::builder().unwrap();
+        cert.set_version(2).unwrap();
+        cert.set_subject_name(&name).unwrap();
+        cert.set_issuer_name(&name).unwrap();
+        cert.set_pubkey(&key).unwrap();
+        cert.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+        cert.set_not_after(&Asn1Time::days_from_now(30).unwrap()).unwrap();
+        cert.sign(&key, MessageDigest::sha256()).unwrap();
+        (cert.build(), key)
+    }
+
+    fn signed_message(cert: &X509, key: &PKey<Private>, body: &str, sent_body: &str) -> String {
+        let part = format!("Content-Type: text/plain\r\n\r\n{}", body);
+        let certs = Stack::new().unwrap();
+        let pkcs7 = Pkcs7::sign(cert, key, &certs, part.as_bytes(), Pkcs7Flags::DETACHED | Pkcs7Flags::BINARY).unwrap();
+        let signature = STANDARD.encode(pkcs7.to_der().unwrap());
+        format!(
+            "From: alice@example.com\r\n\
+             Content-Type: multipart/signed; protocol=\"application/pkcs7-signature\"; micalg=sha-256; boundary=\"s1\"\r\n\
+             \r\n--s1\r\nContent-Type: text/plain\r\n\r\n{}\r\n\
+             --s1\r\nContent-Type: application/pkcs7-signature; name=smime.p7s\r\nContent-Transfer-Encoding: base64\r\n\r\n{}\r\n\
+             --s1--\r\n",
+            sent_body, signature
+        )
+    }
+
+    #[test]
+    fn test_verify_smime_signature() {
+        let (cert, key) = self_signed("alice@example.com");
+        let raw = signed_message(&cert, &key, "Hello", "Hello");
+
+        // Self-signed: the signature matches but nothing vouches for it
+        let empty = X509StoreBuilder::new().unwrap().build();
+        let result = verify_with_store(&raw, &empty).unwrap();
+        assert_eq!(result.status, SmimeStatus::Untrusted);
+        assert_eq!(result.signer.as_deref(), Some("alice@example.com"));
+
+        let mut trusted = X509StoreBuilder::new().unwrap();
+        trusted.add_cert(cert.clone()).unwrap();
+        assert_eq!(verify_with_store(&raw, &trusted.build()).unwrap().status, SmimeStatus::Valid);
+
+        let tampered = signed_message(&cert, &key, "Hello", "Hello, send money");
+        assert_eq!(verify_with_store(&tampered, &empty).unwrap().status, SmimeStatus::Invalid);
+
+        assert!(verify_with_store("Content-Type: text/plain\r\n\r\nHi", &empty).is_none());
+    }
+}
@@ -438,6 +438,9 @@ const CORE_ENGLISH_WORDS: &str = include_str!("../../data/dictionary_en.txt");
 
 // ── I18n / Localization Infrastructure ──────────────────────────────────────
 
+/// Built-in English UI strings; every other locale falls back to these.
+const ENGLISH_STRINGS: &str = include_str!("../../data/locales/en.json");
+
 /// Locale descriptor for UI string localization.
 #[derive(Debug, Clone)]
 pub struct Locale {
@@ -499,30 +502,8 @@ impl I18n {
     }
 
     fn register_english_defaults(&mut self) {
-        let mut en = HashMap::new();
-        for (k, v) in [
-            ("menu.file", "File"), ("menu.edit", "Edit"), ("menu.view", "View"),
-            ("menu.message", "Message"), ("menu.tools", "Tools"), ("menu.help", "Help"),
-            ("action.send", "Send"), ("action.save_draft", "Save Draft"),
-            ("action.cancel", "Cancel"), ("action.ok", "OK"), ("action.delete", "Delete"),
-            ("action.reply", "Reply"), ("action.reply_all", "Reply All"),
-            ("action.forward", "Forward"), ("action.search", "Search"),
-            ("status.ready", "Ready"), ("status.checking_mail", "Checking for new mail..."),
-            ("status.sending", "Sending..."), ("status.offline", "Offline mode"),
-            ("status.online", "Online"), ("status.connected", "Connected"),
-            ("status.disconnected", "Disconnected"),
-            ("compose.to", "To:"), ("compose.cc", "CC:"), ("compose.bcc", "BCC:"),
-            ("compose.subject", "Subject:"), ("compose.from", "From:"),
-            ("spellcheck.no_errors", "No spelling errors found"),
-            ("spellcheck.errors_found", "Spelling errors found"),
-            ("spellcheck.add_to_dictionary", "Add to Dictionary"),
-            ("spellcheck.ignore", "Ignore"), ("spellcheck.ignore_all", "Ignore All"),
-            ("settings.title", "Settings"), ("settings.general", "General"),
-            ("settings.compose", "Compose"), ("settings.reading", "Reading"),
-            ("settings.language", "Language"), ("settings.advanced", "Advanced"),
-        ] {
-            en.insert(k.into(), v.into());
-        }
+        let en: HashMap<String, String> =
+            serde_json::from_str(ENGLISH_STRINGS).expect("data/locales/en.json is valid JSON");
         self.translations.insert("en".into(), en);
     }
 
@@ -539,6 +520,34 @@ impl I18n {
         Ok(count)
     }
 
+    /// Load every `<language>.json` table in `dir` (e.g. `de.json`),
+    /// returning the language codes loaded. Unreadable files are skipped.
+    pub fn load_locale_dir(&mut self, dir: &Path) -> Vec<String> {
+        let Ok(entries) = std::fs::read_dir(dir) else { return Vec::new() };
+        let mut loaded = Vec::new();
+        for path in entries.flatten().map(|e| e.path()) {
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(code) = path.file_stem().and_then(|s| s.to_str()) else { continue };
+            let code = Locale::from_code(code).language_code;
+            match self.load_translations_file(&code, &path) {
+                Ok(_) => loaded.push(code),
+                Err(e) => tracing::warn!("Skipping locale file {}: {}", path.display(), e),
+            }
+        }
+        loaded.sort();
+        loaded
+    }
+
+    /// Language codes with a translation table, English first
+    pub fn available_locales(&self) -> Vec<String> {
+        let mut codes: Vec<String> = self.translations.keys().filter(|c| *c != "en").cloned().collect();
+        codes.sort();
+        codes.insert(0, "en".into());
+        codes
+    }
+
     pub fn set_locale(&mut self, code: &str) { self.active_locale = Locale::from_code(code); }
     pub fn locale(&self) -> &Locale { &self.active_locale }
 
@@ -648,6 +657,22 @@ mod tests {
         assert_eq!(i18n.t("action.cancel"), "Cancel");
     }
 
+    #[test]
+    fn test_i18n_locale_dir() {
+        let dir = std::env::temp_dir().join(format!("wixen_locales_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("de.json"), r#"{"menu.file": "&Datei"}"#).unwrap();
+        std::fs::write(dir.join("broken.json"), "not json").unwrap();
+        std::fs::write(dir.join("notes.txt"), "ignored").unwrap();
+
+        let mut i18n = I18n::with_locale("de-DE");
+        assert_eq!(i18n.load_locale_dir(&dir), vec!["de".to_string()]);
+        assert_eq!(i18n.available_locales(), vec!["en".to_string(), "de".to_string()]);
+        assert_eq!(i18n.t("menu.file"), "&Datei");
+        assert_eq!(i18n.t("menu.quit"), I18n::new().t("menu.quit"));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
     #[test]
     fn test_locale_rtl_detection() {
         let en = Locale::from_code("en-US");
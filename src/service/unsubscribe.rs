@@ -0,0 +1,69 @@
+//! One-click unsubscribe (RFC 8058)
+//!
+//! A list that sends `List-Unsubscribe-Post: List-Unsubscribe=One-Click`
+//! removes the recipient when its https unsubscribe URL receives a POST of
+//! `List-Unsubscribe=One-Click`. The request carries nothing else: no
+//! cookies, no credentials.
+
+use crate::common::{Error, Result};
+use crate::service::protocols::NetworkTimeouts;
+
+/// Unsubscribe through `url`, which must be https
+pub async fn one_click(url: &str, timeouts: NetworkTimeouts) -> Result<()> {
+    let parsed = url::Url::parse(url).map_err(|e| Error::Parse(format!("Invalid unsubscribe link {}: {}", url, e)))?;
+    if parsed.scheme() != "https" {
+        return Err(Error::Security(format!("One-click unsubscribe needs an https link, not {}", url)));
+    }
+    post(url, timeouts).await
+}
+
+async fn post(url: &str, timeouts: NetworkTimeouts) -> Result<()> {
+    let client = reqwest::Client::builder()
+        .connect_timeout(timeouts.connect)
+        .timeout(timeouts.connect + timeouts.read)
+        .build()
+        .map_err(|e| Error::Network(format!("Failed to create HTTP client: {}", e)))?;
+    let response = client
+        .post(url)
+        .form(&[("List-Unsubscribe", "One-Click")])
+        .send()
+        .await
+        .map_err(|e| Error::Network(format!("Unsubscribe request to {} failed: {}", url, e)))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(Error::Network(format!("Unsubscribe request to {} was refused ({})", url, status)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_post_sends_one_click_form() {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/u/42", server.server_addr().to_ip().unwrap());
+        let listener = std::thread::spawn(move || {
+            let mut request = server.recv().unwrap();
+            let mut body = String::new();
+            request.as_reader().read_to_string(&mut body).unwrap();
+            let method = request.method().to_string();
+            let cookies = request.headers().iter().any(|h| h.field.equiv("Cookie"));
+            request.respond(tiny_http::Response::empty(200)).unwrap();
+            (method, body, cookies)
+        });
+
+        post(&url, NetworkTimeouts::default()).await.unwrap();
+        let (method, body, cookies) = listener.join().unwrap();
+        assert_eq!(method, "POST");
+        assert_eq!(body, "List-Unsubscribe=One-Click");
+        assert!(!cookies);
+    }
+
+    #[tokio::test]
+    async fn test_one_click_needs_https() {
+        let result = one_click("http://example.com/u/42", NetworkTimeouts::default()).await;
+        assert!(matches!(result, Err(Error::Security(_))));
+    }
+}
@@ -7,8 +7,10 @@ use wixen_mail::application::contacts::{Contact, ContactManager};
 use wixen_mail::application::filters::{FilterAction, FilterEngine, FilterRule};
 use wixen_mail::application::messages::{Message, MessageManager};
 use wixen_mail::application::search::{SearchEngine, SearchQuery};
+use wixen_mail::application::MailEngine;
 use wixen_mail::common::types::*;
-use wixen_mail::data::message_cache::{CachedMessage, MessageCache};
+use wixen_mail::common::Error;
+use wixen_mail::data::message_cache::{CachePool, CachedMessage, MessageCache};
 use wixen_mail::service::cache::CacheService;
 use wixen_mail::service::oauth::OAuthService;
 use wixen_mail::service::security::SecurityService;
@@ -223,6 +225,7 @@ fn test_filter_multiple_rules_single_message() {
         read: false,
         starred: false,
         deleted: false,
+        priority: MessagePriority::Normal,
     };
 
     let actions = engine.evaluate_message(&message);
@@ -260,6 +263,7 @@ fn test_filter_disabled_rule_not_applied() {
         read: false,
         starred: false,
         deleted: false,
+        priority: MessagePriority::Normal,
     };
 
     let actions = engine.evaluate_message(&message);
@@ -290,7 +294,7 @@ fn test_filter_regex_match() {
         to_addr: "me@co.com".to_string(),
         cc: None, date: "2026-01-01".to_string(),
         body_plain: None, body_html: None,
-        read: false, starred: false, deleted: false,
+        read: false, starred: false, deleted: false, priority: MessagePriority::Normal,
     };
 
     let msg_no_match = CachedMessage {
@@ -301,7 +305,7 @@ fn test_filter_regex_match() {
         to_addr: "me@co.com".to_string(),
         cc: None, date: "2026-01-01".to_string(),
         body_plain: None, body_html: None,
-        read: false, starred: false, deleted: false,
+        read: false, starred: false, deleted: false, priority: MessagePriority::Normal,
     };
 
     assert_eq!(engine.evaluate_message(&msg_match).len(), 1);
@@ -320,19 +324,19 @@ fn test_search_across_folders() {
 
     // Search all folders
     let results = engine
-        .search(&SearchQuery { text: "invoice".to_string(), folder: None })
+        .search(&SearchQuery { text: "invoice".to_string(), folder: None, high_priority_only: false })
         .unwrap();
     assert_eq!(results.len(), 3);
 
     // Search specific folder
     let results = engine
-        .search(&SearchQuery { text: "invoice".to_string(), folder: Some("INBOX".to_string()) })
+        .search(&SearchQuery { text: "invoice".to_string(), folder: Some("INBOX".to_string()), high_priority_only: false })
         .unwrap();
     assert_eq!(results.len(), 1);
 
     // Empty query
     let results = engine
-        .search(&SearchQuery { text: "".to_string(), folder: None })
+        .search(&SearchQuery { text: "".to_string(), folder: None, high_priority_only: false })
         .unwrap();
     assert!(results.is_empty());
 }
@@ -514,8 +518,13 @@ fn test_message_cache_outbox_queue() {
         id: "q-1".to_string(),
         account_id: "acct-1".to_string(),
         to_addr: "recipient@example.com".to_string(),
+        cc: String::new(),
+        bcc: String::new(),
+        identity_id: None,
         subject: "Queued message".to_string(),
         body: "Sent while offline".to_string(),
+        request_receipt: false,
+        priority: MessagePriority::Normal,
         attempt_count: 0,
         last_error: None,
         created_at: chrono::Utc::now().to_rfc3339(),
@@ -538,6 +547,97 @@ fn test_message_cache_outbox_queue() {
     assert!(queued.is_empty());
 }
 
+// ── Mail Engine Tests ───────────────────────────────────────────────────────
+
+fn engine_account() -> wixen_mail::data::account::Account {
+    let mut account = wixen_mail::data::account::Account::new(
+        "Work".to_string(),
+        "me@example.com".to_string(),
+    );
+    account.imap_server = "imap.example.com".to_string();
+    account.smtp_server = "smtp.example.com".to_string();
+    account.username = "me@example.com".to_string();
+    account.password = "secret".to_string();
+    account
+}
+
+#[tokio::test]
+async fn test_mail_engine_accounts() {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = MailEngine::new(MessageCache::new(dir.path().to_path_buf(), None).unwrap());
+
+    let mut invalid = engine_account();
+    invalid.imap_server.clear();
+    assert!(matches!(engine.save_account(&invalid), Err(Error::Config(_))));
+
+    let account = engine_account();
+    engine.save_account(&account).unwrap();
+    assert_eq!(engine.accounts().unwrap().len(), 1);
+    assert_eq!(engine.account(&account.id).unwrap().email, "me@example.com");
+    assert!(matches!(engine.account("missing"), Err(Error::NotFound(_))));
+
+    engine.delete_account(&account.id).await.unwrap();
+    assert!(engine.accounts().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_mail_engine_fetch_and_search() {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = MailEngine::new(MessageCache::new(dir.path().to_path_buf(), None).unwrap());
+    let account = engine_account();
+    engine.save_account(&account).unwrap();
+
+    // Nothing can be fetched before connecting
+    assert!(matches!(engine.folders(&account.id).await, Err(Error::Protocol(_))));
+
+    engine.connect(&account.id).await.unwrap();
+    assert!(engine.is_connected(&account.id).await);
+    assert!(engine.folders(&account.id).await.unwrap().iter().any(|f| f == "INBOX"));
+
+    let messages = engine.messages(&account.id, "INBOX").await.unwrap();
+    assert!(!messages.is_empty());
+
+    // Fetched headers are cached and searchable
+    let query = SearchQuery {
+        text: "welcome".to_string(),
+        folder: Some("INBOX".to_string()),
+        high_priority_only: false,
+    };
+    let found = engine.search(&account.id, &query).unwrap();
+    assert_eq!(found.len(), 1);
+    assert!(found[0].subject.contains("Welcome"));
+
+    engine.disconnect(&account.id).await;
+    assert!(!engine.is_connected(&account.id).await);
+}
+
+#[tokio::test]
+async fn test_mail_engine_shares_a_pool() {
+    let dir = tempfile::tempdir().unwrap();
+    let pool = CachePool::new(MessageCache::new(dir.path().to_path_buf(), None).unwrap());
+    let engine = MailEngine::with_pool(pool.clone());
+
+    // Accounts saved through the engine are seen through the pool
+    let account = engine_account();
+    engine.save_account(&account).unwrap();
+    assert_eq!(pool.read(|c| c.load_accounts()).unwrap().len(), 1);
+
+    engine.delete_account(&account.id).await.unwrap();
+    assert!(pool.read(|c| c.load_accounts()).unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_mail_engine_send_needs_recipients() {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = MailEngine::new(MessageCache::new(dir.path().to_path_buf(), None).unwrap());
+    let account = engine_account();
+    engine.save_account(&account).unwrap();
+
+    let request = engine.send_request(&account.id).unwrap();
+    assert_eq!(request.username, "me@example.com");
+    assert!(matches!(engine.send(&request).await, Err(Error::Config(_))));
+}
+
 // ── Type System Tests ───────────────────────────────────────────────────────
 
 #[test]